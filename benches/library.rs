@@ -0,0 +1,50 @@
+//! In-process benchmark for `cargo bench`
+//!
+//! Runs parser, index, search and solver directly against the library with a
+//! synthetic Packages fixture. Size and iterations can be tuned via
+//! `APT_NG_BENCH_PACKAGES` and `APT_NG_BENCH_ITERATIONS`.
+//!
+//! A second run compares the dpkg subprocesses spawned for system package
+//! queries (one per query vs. a single snapshot); `APT_NG_BENCH_SYSTEM_QUERIES`
+//! sets the number of queried names.
+
+use apt_ng::benchmark::inprocess::{compare_system_queries, run_inprocess_benchmark, synthetic_packages_file};
+use apt_ng::benchmark::output::format_operation_results;
+
+fn env_or(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn main() -> anyhow::Result<()> {
+    let packages = env_or("APT_NG_BENCH_PACKAGES", 1000);
+    let iterations = env_or("APT_NG_BENCH_ITERATIONS", 3);
+
+    let content = synthetic_packages_file(packages);
+    let results = run_inprocess_benchmark(&content, iterations)?;
+    format_operation_results(&results);
+
+    // Common base packages plus names that do not exist (like unresolved dependencies)
+    let queries = env_or("APT_NG_BENCH_SYSTEM_QUERIES", 200);
+    let names: Vec<String> = ["libc6", "libssl3", "zlib1g", "awk", "debconf"]
+        .iter()
+        .map(|name| name.to_string())
+        .chain((0..).map(|i| format!("bench-pkg-{}", i)))
+        .take(queries)
+        .collect();
+    let comparison = compare_system_queries(&names);
+    println!();
+    println!("System package queries ({}):", comparison.queries);
+    println!(
+        "  per query: {:>6} subprocesses  {:>10.2?}",
+        comparison.per_query_subprocesses, comparison.per_query_duration
+    );
+    println!(
+        "  snapshot:  {:>6} subprocesses  {:>10.2?}",
+        comparison.snapshot_subprocesses, comparison.snapshot_duration
+    );
+
+    Ok(())
+}
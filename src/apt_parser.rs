@@ -4,53 +4,99 @@ use crate::solver::DependencyRule;
 use std::collections::HashMap;
 
 /// Parst eine apt Packages-Datei
+///
+/// Stanzas werden durch Leerzeilen getrennt; Zeilen mit führendem Leerzeichen oder Tab
+/// setzen das vorherige Feld fort (z.B. `Description`, lange `Depends`). Fehlerhafte
+/// Stanzas werden mit einer Warnung übersprungen, statt die ganze Datei zu verwerfen.
 pub fn parse_packages_file(content: &str) -> Result<Vec<PackageManifest>> {
     // Schätze Anzahl der Pakete basierend auf Dateigröße für bessere Performance
     let estimated_packages = content.len() / 500; // Grobe Schätzung: ~500 Bytes pro Paket
     let mut packages = Vec::with_capacity(estimated_packages);
-    let mut current_pkg: Option<HashMap<String, String>> = None;
+    let mut stanza = Stanza::default();
     
-    for line in content.lines() {
-        let line = line.trim();
+    for (line_no, line) in content.lines().enumerate() {
+        let line_no = line_no + 1;
         
-        if line.is_empty() {
+        if line.trim().is_empty() {
             // Leere Zeile markiert Ende eines Paket-Eintrags
-            if let Some(pkg_data) = current_pkg.take() {
-                if let Ok(manifest) = parse_package_entry(&pkg_data) {
-                    packages.push(manifest);
+            stanza.finish(line_no, &mut packages);
+            continue;
+        }
+        if stanza.start_line == 0 {
+            stanza.start_line = line_no;
+        }
+        
+        if line.starts_with(' ') || line.starts_with('\t') {
+            // Fortsetzungszeile; " ." steht in Beschreibungen für eine Leerzeile
+            let continuation = line.trim();
+            let continuation = if continuation == "." { "" } else { continuation };
+            match stanza.last_key.as_ref().and_then(|key| stanza.fields.get_mut(key)) {
+                Some(value) => {
+                    value.push('\n');
+                    value.push_str(continuation);
                 }
+                None => stanza.mark_malformed(line_no, "continuation line without a field"),
             }
             continue;
         }
         
-        if let Some(colon_pos) = line.find(':') {
-            let key = line[..colon_pos].trim().to_string();
-            let value = line[colon_pos + 1..].trim().to_string();
-            
-            if current_pkg.is_none() {
-                current_pkg = Some(HashMap::new());
-            }
-            
-            if let Some(ref mut pkg) = current_pkg {
-                // Bei mehrzeiligen Feldern (z.B. Description) wird der Wert angehängt
-                if pkg.contains_key(&key) {
-                    let existing = pkg.get(&key).unwrap().clone();
-                    pkg.insert(key, format!("{}\n{}", existing, value));
-                } else {
-                    pkg.insert(key, value);
+        match line.split_once(':') {
+            Some((key, value)) => {
+                let key = key.trim().to_string();
+                if stanza.fields.contains_key(&key) {
+                    stanza.mark_malformed(line_no, &format!("duplicate field {}", key));
                 }
+                stanza.fields.insert(key.clone(), value.trim().to_string());
+                stanza.last_key = Some(key);
             }
+            None => stanza.mark_malformed(line_no, "line is neither a field nor a continuation"),
         }
     }
     
     // Verarbeite letztes Paket falls Datei nicht mit Leerzeile endet
-    if let Some(pkg_data) = current_pkg.take() {
-        if let Ok(manifest) = parse_package_entry(&pkg_data) {
-            packages.push(manifest);
+    stanza.finish(content.lines().count() + 1, &mut packages);
+    
+    Ok(packages)
+}
+
+/// Ein Stanza der Packages-Datei während des Parsens
+#[derive(Default)]
+struct Stanza {
+    fields: HashMap<String, String>,
+    last_key: Option<String>,
+    start_line: usize,
+    error: Option<String>,
+}
+
+impl Stanza {
+    fn mark_malformed(&mut self, line_no: usize, reason: &str) {
+        if self.error.is_none() {
+            self.error = Some(format!("line {}: {}", line_no, reason));
         }
     }
     
-    Ok(packages)
+    /// Schließt das Stanza ab und übernimmt es, falls es gültig ist
+    fn finish(&mut self, line_no: usize, packages: &mut Vec<PackageManifest>) {
+        let stanza = std::mem::take(self);
+        if stanza.fields.is_empty() && stanza.error.is_none() {
+            return;
+        }
+        
+        let result = match stanza.error {
+            Some(error) => Err(anyhow::anyhow!(error)),
+            None => parse_package_entry(&stanza.fields),
+        };
+        match result {
+            Ok(manifest) => packages.push(manifest),
+            Err(e) => {
+                let name = stanza.fields.get("Package").map(|n| n.as_str()).unwrap_or("<unknown>");
+                eprintln!(
+                    "Warning: skipping malformed package stanza {} (lines {}-{}): {}",
+                    name, stanza.start_line, line_no.saturating_sub(1), e
+                );
+            }
+        }
+    }
 }
 
 fn parse_package_entry(data: &HashMap<String, String>) -> Result<PackageManifest> {
@@ -76,6 +122,21 @@ fn parse_package_entry(data: &HashMap<String, String>) -> Result<PackageManifest
         .map(|p| parse_provides(p))
         .unwrap_or_default();
     
+    // Breaks wird wie Conflicts behandelt: beide verhindern die gemeinsame Installation
+    let conflicts = ["Conflicts", "Breaks"].iter()
+        .filter_map(|field| data.get(*field))
+        .flat_map(|c| parse_relations(c))
+        .collect();
+    let replaces = data.get("Replaces")
+        .map(|r| parse_relations(r))
+        .unwrap_or_default();
+    let recommends = data.get("Recommends")
+        .map(|r| parse_relations(r))
+        .unwrap_or_default();
+    let suggests = data.get("Suggests")
+        .map(|s| parse_relations(s))
+        .unwrap_or_default();
+    
     let size = data.get("Size")
         .and_then(|s| s.parse::<u64>().ok())
         .unwrap_or(0);
@@ -86,6 +147,12 @@ fn parse_package_entry(data: &HashMap<String, String>) -> Result<PackageManifest
         .unwrap_or_default();
     
     let filename = data.get("Filename").cloned();
+    let maintainer = data.get("Maintainer").cloned();
+    let homepage = data.get("Homepage").cloned();
+    let section = data.get("Section").cloned();
+    let priority = data.get("Priority").cloned();
+    let installed_size = data.get("Installed-Size")
+        .and_then(|s| s.parse::<u64>().ok());
     
     let timestamp = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -98,14 +165,21 @@ fn parse_package_entry(data: &HashMap<String, String>) -> Result<PackageManifest
         arch,
         provides,
         depends,
-        conflicts: vec![],
-        replaces: vec![],
+        conflicts,
+        replaces,
         files: vec![],
         size,
         checksum,
         timestamp,
         filename,
         repo_id: None, // Wird später beim Hinzufügen zum Index gesetzt
+        maintainer,
+        homepage,
+        section,
+        priority,
+        installed_size,
+        recommends,
+        suggests,
     })
 }
 
@@ -192,6 +266,34 @@ fn parse_version_constraint(constraint: &str) -> Result<Option<String>> {
     Ok(Some(constraint.to_string()))
 }
 
+/// Ein Eintrag aus `Provides:`; Debian erlaubt versionierte Provides wie `mta (= 1.0)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provide {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl Provide {
+    /// Parst einen einzelnen Provides-Eintrag (`foo`, `foo (= 1.2-3)`, `foo:any`)
+    pub fn parse(entry: &str) -> Provide {
+        let entry = entry.trim();
+        let (name, version) = match entry.split_once('(') {
+            Some((name, rest)) => {
+                // Provides kennt nur den Operator "="
+                let version = rest.trim_end_matches(')').trim().trim_start_matches('=').trim();
+                (name.trim(), Some(version.to_string()).filter(|v| !v.is_empty()))
+            }
+            None => (entry, None),
+        };
+        let name = name.split(':').next().unwrap_or(name).trim();
+        
+        Provide {
+            name: name.to_string(),
+            version,
+        }
+    }
+}
+
 fn parse_provides(provides_str: &str) -> Vec<String> {
     provides_str
         .split(',')
@@ -200,6 +302,220 @@ fn parse_provides(provides_str: &str) -> Vec<String> {
         .collect()
 }
 
+/// Zerlegt Conflicts/Breaks/Replaces; anders als bei `Depends` bleiben die
+/// Versionsangaben erhalten (`foo (<< 2.0)`), Architektur-Qualifier entfallen
+fn parse_relations(relations_str: &str) -> Vec<String> {
+    relations_str
+        .split(',')
+        .map(|r| {
+            let r = r.trim();
+            match r.split_once('(') {
+                Some((name, version)) => format!("{} ({}", name.split(':').next().unwrap_or(name).trim(), version),
+                None => r.split(':').next().unwrap_or(r).to_string(),
+            }
+        })
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Kopfdaten einer Release/InRelease-Datei
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReleaseInfo {
+    pub origin: Option<String>,
+    pub label: Option<String>,
+    pub suite: Option<String>,
+    pub codename: Option<String>,
+    pub version: Option<String>,
+    /// Erstellungszeitpunkt (`Date`) als Unix-Zeitstempel
+    pub date: Option<i64>,
+    /// Ablaufzeitpunkt (`Valid-Until`) als Unix-Zeitstempel
+    pub valid_until: Option<i64>,
+    /// Veröffentlichte Architekturen (`Architectures`), leer wenn nicht angegeben
+    pub architectures: Vec<String>,
+    /// Veröffentlichte Komponenten (`Components`), leer wenn nicht angegeben
+    pub components: Vec<String>,
+}
+
+impl ReleaseInfo {
+    /// Die gewünschten Komponenten, die das Repository veröffentlicht (ohne Angabe: alle)
+    ///
+    /// `updates/main` (debian-security) erfüllt auch `main`.
+    pub fn published_components(&self, wanted: &[String]) -> Vec<String> {
+        if self.components.is_empty() {
+            return wanted.to_vec();
+        }
+        wanted.iter()
+            .filter(|component| self.components.iter().any(|published| {
+                published == *component || published.rsplit('/').next() == Some(component.as_str())
+            }))
+            .cloned()
+            .collect()
+    }
+    
+    /// Die gewünschten Architekturen, die das Repository veröffentlicht (ohne Angabe: alle)
+    pub fn published_architectures(&self, wanted: &[String]) -> Vec<String> {
+        if self.architectures.is_empty() {
+            return wanted.to_vec();
+        }
+        wanted.iter()
+            .filter(|arch| self.architectures.contains(arch))
+            .cloned()
+            .collect()
+    }
+    
+    /// Schutz vor Replay-Angriffen: lehnt abgelaufene Metadaten und solche ab, die älter
+    /// sind als die zuletzt für dieses Repository gesehenen
+    pub fn check_freshness(&self, now: i64, previous_date: Option<i64>) -> std::result::Result<(), String> {
+        if let Some(valid_until) = self.valid_until {
+            if valid_until < now {
+                return Err(format!(
+                    "Release file expired on {} (now {})",
+                    format_release_date(valid_until),
+                    format_release_date(now)
+                ));
+            }
+        }
+        
+        if let (Some(date), Some(previous)) = (self.date, previous_date) {
+            if date < previous {
+                return Err(format!(
+                    "Release file dated {} is older than the previously seen {}",
+                    format_release_date(date),
+                    format_release_date(previous)
+                ));
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+/// Parst ein Datum im Format der Release-Datei (RFC 2822, z.B. "Sat, 10 Aug 2024 09:01:23 UTC")
+pub fn parse_release_date(value: &str) -> Option<i64> {
+    let value = value.trim();
+    // chrono kennt "UTC" nicht als RFC-2822-Zone
+    let value = match value.strip_suffix(" UTC") {
+        Some(rest) => format!("{} +0000", rest),
+        None => value.to_string(),
+    };
+    chrono::DateTime::parse_from_rfc2822(&value).ok().map(|date| date.timestamp())
+}
+
+fn format_release_date(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|date| date.format("%a, %d %b %Y %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Parst die Kopfzeilen einer Release-Datei; bei InRelease wird die PGP-Hülle entfernt
+pub fn parse_release_file(content: &str) -> ReleaseInfo {
+    let mut info = ReleaseInfo::default();
+    
+    for line in strip_pgp_armor(content).lines() {
+        // Fortsetzungszeilen (z.B. die Hash-Listen) gehören nicht zum Kopf
+        if line.starts_with(' ') || line.starts_with('\t') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        
+        match key {
+            "Date" => {
+                info.date = parse_release_date(value);
+                continue;
+            }
+            "Valid-Until" => {
+                info.valid_until = parse_release_date(value);
+                continue;
+            }
+            "Architectures" => {
+                info.architectures = value.split_whitespace().map(str::to_string).collect();
+                continue;
+            }
+            "Components" => {
+                info.components = value.split_whitespace().map(str::to_string).collect();
+                continue;
+            }
+            _ => {}
+        }
+        
+        let field = match key {
+            "Origin" => &mut info.origin,
+            "Label" => &mut info.label,
+            "Suite" => &mut info.suite,
+            "Codename" => &mut info.codename,
+            "Version" => &mut info.version,
+            _ => continue,
+        };
+        *field = Some(value.to_string());
+    }
+    
+    info
+}
+
+/// Gibt den signierten Text einer clearsigned InRelease-Datei zurück (Release bleibt unverändert)
+pub fn strip_pgp_armor(content: &str) -> &str {
+    if !content.starts_with("-----BEGIN PGP SIGNED MESSAGE-----") {
+        return content;
+    }
+    
+    // Armor-Header (Hash: ...) enden mit der ersten Leerzeile
+    let body = content
+        .find("\n\n")
+        .map(|pos| &content[pos + 2..])
+        .unwrap_or("");
+    match body.find("-----BEGIN PGP SIGNATURE-----") {
+        Some(end) => &body[..end],
+        None => body,
+    }
+}
+
+/// Eintrag eines Contents-Index: eine Datei und die Pakete, die sie enthalten
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentsEntry {
+    pub path: String,
+    pub packages: Vec<String>,
+}
+
+/// Parst eine Contents-Datei (`pfad<Leerraum>section/paket,section/paket` pro Zeile)
+///
+/// Pfade dürfen Leerzeichen enthalten, die Paketliste ist immer die letzte Spalte.
+/// Der Freitext-Kopf älterer Dateien bis zur Zeile `FILE  LOCATION` wird übersprungen.
+/// Pfade werden absolut (mit führendem `/`) zurückgegeben.
+pub fn parse_contents_file(content: &str) -> Vec<ContentsEntry> {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.iter()
+        .position(|line| {
+            let mut columns = line.split_whitespace();
+            columns.next() == Some("FILE") && columns.next() == Some("LOCATION") && columns.next().is_none()
+        })
+        .map_or(0, |header_end| header_end + 1);
+    
+    lines[start..].iter()
+        .filter_map(|line| {
+            let line = line.trim_end();
+            let (path, locations) = line.rsplit_once(|c: char| c.is_whitespace())?;
+            let path = path.trim_end();
+            if path.is_empty() {
+                return None;
+            }
+            let packages = locations.split(',')
+                .map(|location| location.rsplit('/').next().unwrap_or(location).to_string())
+                .filter(|package| !package.is_empty())
+                .collect();
+            Some(ContentsEntry {
+                path: format!("/{}", path.trim_start_matches("./").trim_start_matches('/')),
+                packages,
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -226,6 +542,30 @@ Size: 2048
         assert_eq!(packages[0].depends.len(), 2);
     }
     
+    #[test]
+    fn test_parse_descriptive_fields() {
+        let content = r#"Package: nginx
+Version: 1.22.1-9
+Architecture: amd64
+Maintainer: Debian Nginx Maintainers <pkg-nginx-maintainers@alioth-lists.debian.net>
+Homepage: https://nginx.org
+Section: httpd
+Priority: optional
+Installed-Size: 1234
+Recommends: ssl-cert, nginx-doc (>= 1.22)
+Suggests: fcgiwrap | php-fpm
+"#;
+        
+        let packages = parse_packages_file(content).unwrap();
+        assert_eq!(packages[0].maintainer.as_deref(), Some("Debian Nginx Maintainers <pkg-nginx-maintainers@alioth-lists.debian.net>"));
+        assert_eq!(packages[0].homepage.as_deref(), Some("https://nginx.org"));
+        assert_eq!(packages[0].section.as_deref(), Some("httpd"));
+        assert_eq!(packages[0].priority.as_deref(), Some("optional"));
+        assert_eq!(packages[0].installed_size, Some(1234));
+        assert_eq!(packages[0].recommends, vec!["ssl-cert", "nginx-doc (>= 1.22)"]);
+        assert_eq!(packages[0].suggests, vec!["fcgiwrap | php-fpm"]);
+    }
+    
     #[test]
     fn test_parse_dependency_rule() {
         // Simple package name
@@ -273,5 +613,130 @@ Size: 2048
         assert_eq!(parse_version_constraint(">> 0.5").unwrap(), Some(">> 0.5".to_string()));
         assert_eq!(parse_version_constraint("").unwrap(), None);
     }
-}
+    
+    #[test]
+    fn test_parse_inrelease_header() {
+        let content = "-----BEGIN PGP SIGNED MESSAGE-----
+Hash: SHA512
+
+Origin: Debian
+Label: Debian-Security
+Suite: stable-security
+Version: 12
+Codename: bookworm-security
+Date: Sat, 13 Jan 2024 10:31:08 UTC
+Valid-Until: Sat, 20 Jan 2024 10:31:08 UTC
+Acquire-By-Hash: yes
+Architectures: amd64 arm64 armel armhf i386 mips64el ppc64el s390x
+Components: updates/main updates/contrib updates/non-free updates/non-free-firmware
+Description: Debian 12 - Security Updates
+SHA256:
+ 4c1c4cb8b4b1a6e3e7e7a1bfbd1c7f5e8a6f0f3c1f2b9f5d1c2b3a4e5f6a7b8c  1342 main/binary-amd64/Packages
+-----BEGIN PGP SIGNATURE-----
 
+iQIzBAEBCgAdFiEE
+-----END PGP SIGNATURE-----
+";
+        
+        let info = parse_release_file(content);
+        assert_eq!(info.origin.as_deref(), Some("Debian"));
+        assert_eq!(info.label.as_deref(), Some("Debian-Security"));
+        assert_eq!(info.suite.as_deref(), Some("stable-security"));
+        assert_eq!(info.codename.as_deref(), Some("bookworm-security"));
+        assert_eq!(info.version.as_deref(), Some("12"));
+        assert_eq!(info.architectures.len(), 8);
+        
+        // Nur veröffentlichte Kombinationen werden geladen; binary-all gibt es hier nicht
+        let wanted = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(info.published_components(&wanted(&["main", "contrib", "extra"])), wanted(&["main", "contrib"]));
+        assert_eq!(info.published_architectures(&wanted(&["amd64", "all", "riscv64"])), wanted(&["amd64"]));
+        
+        // Unsignierte Release-Datei
+        let info = parse_release_file("Suite: testing\nCodename: trixie\n");
+        assert_eq!(info.codename.as_deref(), Some("trixie"));
+        assert_eq!(info.published_architectures(&wanted(&["amd64", "all"])), wanted(&["amd64", "all"]));
+        assert!(info.version.is_none());
+    }
+    
+    #[test]
+    fn test_parse_versioned_provides() {
+        assert_eq!(Provide::parse("mta (= 1.0)"), Provide { name: "mta".to_string(), version: Some("1.0".to_string()) });
+        assert_eq!(Provide::parse(" awk "), Provide { name: "awk".to_string(), version: None });
+        assert_eq!(Provide::parse("python3-foo:any (=2.1-1)").version.as_deref(), Some("2.1-1"));
+    }
+    
+    #[test]
+    fn test_parse_folded_fields() {
+        let content = "Package: folded\n\
+Version: 1.0-1\n\
+Architecture: amd64\n\
+Depends: libc6 (>= 2.36),\n \
+ libssl3,\n\
+\tzlib1g\n\
+Description: short summary\n \
+ first paragraph\n \
+ .\n \
+ second paragraph\n\
+\n\
+\n\
+Package: after-folded\n\
+Version: 2.0";
+        
+        let packages = parse_packages_file(content).unwrap();
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].depends, vec!["libc6", "libssl3", "zlib1g"]);
+        // Letztes Stanza ohne abschließende Leerzeile bzw. Newline wird übernommen
+        assert_eq!(packages[1].name, "after-folded");
+        assert_eq!(packages[1].version, "2.0");
+    }
+    
+    #[test]
+    fn test_malformed_stanza_is_skipped() {
+        let content = "Package: broken\nthis line has no colon\nVersion: 1.0\n\n\
+Package: no-version\n\n\
+Package: good\nVersion: 1.0\n";
+        
+        let packages = parse_packages_file(content).unwrap();
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "good");
+    }
+    
+    #[test]
+    fn test_release_freshness() {
+        let release = parse_release_file(
+            "Origin: Debian\nSuite: stable\nDate: Sat, 10 Aug 2024 09:01:23 UTC\nValid-Until: Sat, 17 Aug 2024 09:01:23 UTC\n"
+        );
+        let date = release.date.unwrap();
+        assert_eq!(release.valid_until.unwrap() - date, 7 * 24 * 3600);
+        
+        assert!(release.check_freshness(date + 3600, Some(date)).is_ok());
+        
+        let expired = release.check_freshness(date + 8 * 24 * 3600, None).unwrap_err();
+        assert!(expired.contains("expired on Sat, 17 Aug 2024 09:01:23 UTC"));
+        
+        let rollback = release.check_freshness(date + 3600, Some(date + 60)).unwrap_err();
+        assert!(rollback.contains("older than the previously seen"));
+    }
+    
+    #[test]
+    fn test_parse_contents_file() {
+        let content = "This file maps each file to the packages containing it.\n\
+FILE                                                    LOCATION\n\
+usr/bin/curl                                            net/curl\n\
+usr/share/doc/my tool/README                            utils/mytool\n\
+usr/lib/x86_64-linux-gnu/libfoo.so.1                    libs/libfoo1,non-free/libs/libfoo1-dbg\n";
+        
+        let entries = parse_contents_file(content);
+        assert_eq!(entries, [
+            ContentsEntry { path: "/usr/bin/curl".to_string(), packages: vec!["curl".to_string()] },
+            ContentsEntry { path: "/usr/share/doc/my tool/README".to_string(), packages: vec!["mytool".to_string()] },
+            ContentsEntry {
+                path: "/usr/lib/x86_64-linux-gnu/libfoo.so.1".to_string(),
+                packages: vec!["libfoo1".to_string(), "libfoo1-dbg".to_string()],
+            },
+        ]);
+        
+        // Aktuelle Dateien haben keinen Kopf
+        assert_eq!(parse_contents_file("bin/ls\tutils/coreutils\n")[0].packages, ["coreutils"]);
+    }
+}
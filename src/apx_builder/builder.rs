@@ -1,16 +1,10 @@
 use anyhow::Result;
 use std::path::{Path, PathBuf};
-use std::fs::{self, File};
-use std::io::{BufWriter, Write};
+use std::fs;
 use std::os::unix::fs::PermissionsExt;
 use sha2::{Sha256, Digest};
 use hex;
-use zstd::stream::Encoder;
-use tar::{Builder, Header};
-use crate::package::{PackageManifest, FileEntry};
-use serde_json;
-
-const APX_MAGIC: &[u8] = b"APX\x01";
+use crate::package::{create_apx_package, PackageManifest, FileEntry};
 
 /// Builder for creating .apx packages
 pub struct ApxBuilder {
@@ -37,6 +31,13 @@ impl ApxBuilder {
                 timestamp: 0,
                 filename: None,
                 repo_id: None,
+                maintainer: None,
+                homepage: None,
+                section: None,
+                priority: None,
+                installed_size: None,
+                recommends: vec![],
+                suggests: vec![],
             },
         }
     }
@@ -47,64 +48,24 @@ impl ApxBuilder {
     }
 
     /// Build the .apx package from the source directory
+    ///
+    /// The container itself is written by `package::create_apx_package`, so packages
+    /// built here use the same (current) format version that `ApxPackage::open` reads.
     pub fn build(&self, output_path: impl AsRef<Path>) -> Result<()> {
-        let output_path = output_path.as_ref();
-        
-        // Create output file
-        let mut output_file = BufWriter::new(File::create(output_path)?);
-        
-        // Write APX magic header
-        output_file.write_all(APX_MAGIC)?;
-        
         // Scan source directory and build file list
         let files = self.scan_directory(&self.source_dir)?;
         
         // Update manifest with file information
         let mut manifest = self.manifest.clone();
-        manifest.files = files.clone();
         manifest.timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)?
             .as_secs() as i64;
         
         // Calculate total size
         manifest.size = files.iter().map(|f| f.size).sum();
+        manifest.files = files;
         
-        // Serialize manifest to JSON
-        let manifest_json = serde_json::to_string_pretty(&manifest)?;
-        
-        // Compress manifest with zstd
-        let mut encoder = Encoder::new(Vec::new(), 3)?; // Compression level 3
-        encoder.write_all(manifest_json.as_bytes())?;
-        let compressed_manifest = encoder.finish()?;
-        
-        // Write compressed manifest length (4 bytes, little-endian)
-        let manifest_len = compressed_manifest.len() as u32;
-        output_file.write_all(&manifest_len.to_le_bytes())?;
-        
-        // Write compressed manifest
-        output_file.write_all(&compressed_manifest)?;
-        
-        // Create content.tar.zst
-        let content_tar_zst = self.create_content_tar_zst(&files)?;
-        
-        // Write content.tar.zst
-        output_file.write_all(&content_tar_zst)?;
-        
-        output_file.flush()?;
-        
-        // Calculate checksum (for future use)
-        let mut hasher = Sha256::new();
-        hasher.update(&compressed_manifest);
-        hasher.update(&content_tar_zst);
-        let _checksum = hex::encode(hasher.finalize());
-        
-        // Note: We can't update the checksum in the file now, but we can store it
-        // In a full implementation, we'd either:
-        // 1. Write checksum at the end and update it
-        // 2. Calculate checksum before writing and include it in manifest
-        // For now, the checksum is calculated but not stored in the package file
-        
-        Ok(())
+        create_apx_package(&self.source_dir, manifest, output_path.as_ref(), None)
     }
 
     /// Scan directory and build file list with checksums
@@ -150,41 +111,11 @@ impl ApxBuilder {
                     checksum,
                     size: file_data.len() as u64,
                     mode,
+                    link_target: None,
                 });
             }
         }
         
         Ok(())
     }
-
-    /// Create content.tar.zst from file list
-    fn create_content_tar_zst(&self, files: &[FileEntry]) -> Result<Vec<u8>> {
-        let mut tar_data = Vec::new();
-        {
-            let mut tar = Builder::new(&mut tar_data);
-            
-            for file_entry in files {
-                let file_path = self.source_dir.join(&file_entry.path);
-                let mut file = File::open(&file_path)?;
-                
-                let mut header = Header::new_gnu();
-                header.set_path(&file_entry.path)?;
-                header.set_size(file_entry.size);
-                header.set_mode(file_entry.mode);
-                header.set_cksum();
-                
-                tar.append(&header, &mut file)?;
-            }
-            
-            tar.finish()?;
-        }
-        
-        // Compress with zstd
-        let mut encoder = Encoder::new(Vec::new(), 3)?;
-        encoder.write_all(&tar_data)?;
-        let compressed = encoder.finish()?;
-        
-        Ok(compressed)
-    }
 }
-
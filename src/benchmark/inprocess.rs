@@ -0,0 +1,210 @@
+use crate::apt_parser::parse_packages_file;
+use crate::benchmark::metrics::collect_metrics;
+use crate::benchmark::BenchmarkResult;
+use crate::index::Index;
+use crate::solver::{DependencySolver, PackageSpec};
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Bezeichnung der In-Process-Ergebnisse in `BenchmarkResult::tool`
+pub const TOOL_NAME: &str = "apt-ng (in-process)";
+
+/// Erzeugt eine synthetische Packages-Datei mit `count` Paketen
+///
+/// Jedes Paket hängt von bis zu zwei Vorgängern ab, sodass der Solver echte
+/// Abhängigkeitsketten auflösen muss.
+pub fn synthetic_packages_file(count: usize) -> String {
+    let mut content = String::new();
+    for i in 0..count {
+        let _ = writeln!(content, "Package: bench-pkg-{}", i);
+        let _ = writeln!(content, "Version: 1.{}.0-1", i % 50);
+        let _ = writeln!(content, "Architecture: amd64");
+        let _ = writeln!(content, "Maintainer: Benchmark <bench@example.org>");
+        let _ = writeln!(content, "Installed-Size: {}", 100 + i % 900);
+        let deps: Vec<String> = [i / 2, i / 3]
+            .iter()
+            .filter(|&&dep| dep < i)
+            .map(|dep| format!("bench-pkg-{} (>= 1.0)", dep))
+            .collect();
+        if !deps.is_empty() {
+            let _ = writeln!(content, "Depends: {}", deps.join(", "));
+        }
+        let _ = writeln!(content, "Filename: pool/main/b/bench-pkg-{0}/bench-pkg-{0}_1.0-1_amd64.deb", i);
+        let _ = writeln!(content, "Size: {}", 1024 * (1 + i % 64));
+        let _ = writeln!(content, "SHA256: {:064x}", i);
+        let _ = writeln!(content, "Description: synthetic benchmark package {}", i);
+        let _ = writeln!(content, " Generated by apt-ng-benchmark.");
+        content.push('\n');
+    }
+    content
+}
+
+/// Temporäres Verzeichnis für den Benchmark-Index, wird beim Drop gelöscht
+struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Misst eine Operation und sammelt dabei die Prozess-Metriken
+fn measure<T>(operation: &str, f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<(T, BenchmarkResult)> {
+    let mut outcome = None;
+    let mut duration = Duration::ZERO;
+    let metrics = collect_metrics(|| {
+        let start = Instant::now();
+        outcome = Some(f());
+        duration = start.elapsed();
+        Ok(())
+    })?;
+
+    let value = outcome.expect("benchmark operation was not executed")?;
+    Ok((value, BenchmarkResult {
+        tool: TOOL_NAME.to_string(),
+        operation: operation.to_string(),
+        duration,
+        metrics,
+    }))
+}
+
+/// Führt die Kernoperationen direkt über die Bibliothek aus (ohne apt-ng-Binary)
+///
+/// Gemessen werden Parsen der Packages-Datei, Einfügen in einen temporären
+/// Index (einzeln und im Bulk-Insert-Modus wie bei `update`), `Index::search`
+/// sowie Aufbau und Lauf des Solvers. Liefert pro
+/// Iteration und Operation ein `BenchmarkResult`.
+pub fn run_inprocess_benchmark(packages_content: &str, iterations: usize) -> anyhow::Result<Vec<BenchmarkResult>> {
+    let mut results = Vec::new();
+
+    for iteration in 0..iterations {
+        let (packages, result) = measure("parse Packages", || parse_packages_file(packages_content))?;
+        results.push(result);
+        if packages.is_empty() {
+            anyhow::bail!("Packages fixture contains no packages");
+        }
+
+        // Frischer Index auf der Platte, damit auch die SQLite-I/O gemessen wird
+        let db_dir = TempDir(std::env::temp_dir().join(format!("apt-ng-bench-{}-{}", std::process::id(), iteration)));
+        let db_path = db_dir.0.join("index.db");
+        let index = Index::new(db_path.to_str().unwrap())?;
+        let (_, result) = measure("index insert", || index.add_packages_batch(&packages, 1))?;
+        results.push(result);
+
+        // Wie `update`: Indizes während des Einfügens entfernt, danach neu aufgebaut
+        let bulk_db_path = db_dir.0.join("index-bulk.db");
+        let bulk_index = Index::new(bulk_db_path.to_str().unwrap())?;
+        let (_, result) = measure("index insert (bulk mode)", || {
+            let bulk = bulk_index.bulk_insert()?;
+            bulk_index.replace_repo_packages(1, &packages)?;
+            bulk.finish()
+        })?;
+        results.push(result);
+
+        // Suche nach einem Namensteil, der in vielen Paketen vorkommt
+        let query = common_query(&packages[0].name);
+        let (_, result) = measure("index search", || index.search(&query))?;
+        results.push(result);
+
+        let (solver, result) = measure("solver build", || {
+            let mut solver = DependencySolver::new();
+            for manifest in &packages {
+                solver.add_package(DependencySolver::manifest_to_package_info(manifest)?);
+            }
+            Ok(solver)
+        })?;
+        results.push(result);
+
+        // Die letzten Pakete haben die längsten Abhängigkeitsketten
+        let requested: Vec<PackageSpec> = packages
+            .iter()
+            .rev()
+            .take(10)
+            .map(|pkg| PackageSpec {
+                name: pkg.name.clone(),
+                version: None,
+                arch: None,
+            })
+            .collect();
+        let (_, result) = measure("solver solve", || Ok(solver.solve(&requested)?))?;
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
+/// Gestartete dpkg-Prozesse für dieselben Systempaket-Abfragen, einzeln und über eine Momentaufnahme
+#[derive(Debug, Clone)]
+pub struct SystemQueryComparison {
+    pub queries: usize,
+    pub per_query_subprocesses: usize,
+    pub per_query_duration: Duration,
+    pub snapshot_subprocesses: usize,
+    pub snapshot_duration: Duration,
+}
+
+/// Fragt `names` ab wie der Solver: installiert, Version und Provides je Name
+///
+/// Zuerst mit einem dpkg-Aufruf pro Frage (`dpkg::is_installed` usw.), dann über eine
+/// einzige `SystemSnapshot`. Ohne `dpkg-query` lesen beide Wege die Statusdatei und
+/// starten keine Prozesse.
+pub fn compare_system_queries(names: &[String]) -> SystemQueryComparison {
+    let calls = crate::dpkg::subprocess_calls();
+    let start = Instant::now();
+    for name in names {
+        if crate::dpkg::is_installed(name) {
+            let _ = crate::dpkg::installed_version(name);
+        }
+        let _ = crate::dpkg::is_provided(name);
+    }
+    let per_query_duration = start.elapsed();
+    let per_query_subprocesses = crate::dpkg::subprocess_calls() - calls;
+
+    let calls = crate::dpkg::subprocess_calls();
+    let start = Instant::now();
+    let snapshot = crate::dpkg::SystemSnapshot::load();
+    for name in names {
+        if snapshot.is_installed(name) {
+            let _ = snapshot.installed_version(name);
+        }
+        let _ = snapshot.is_provided(name);
+    }
+
+    SystemQueryComparison {
+        queries: names.len(),
+        per_query_subprocesses,
+        per_query_duration,
+        snapshot_subprocesses: crate::dpkg::subprocess_calls() - calls,
+        snapshot_duration: start.elapsed(),
+    }
+}
+
+/// Suchbegriff für den Such-Benchmark: Paketname ohne abschließende Ziffern
+fn common_query(name: &str) -> String {
+    let trimmed = name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '-');
+    if trimmed.is_empty() {
+        name.to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inprocess_benchmark_reports_each_operation() {
+        let content = synthetic_packages_file(20);
+        let results = run_inprocess_benchmark(&content, 1).unwrap();
+
+        let operations: Vec<&str> = results.iter().map(|r| r.operation.as_str()).collect();
+        assert_eq!(
+            operations,
+            ["parse Packages", "index insert", "index insert (bulk mode)", "index search", "solver build", "solver solve"]
+        );
+        assert!(results.iter().all(|r| r.tool == TOOL_NAME));
+        assert_eq!(common_query("bench-pkg-42"), "bench-pkg");
+    }
+}
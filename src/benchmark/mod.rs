@@ -1,3 +1,4 @@
+pub mod inprocess;
 pub mod metrics;
 pub mod output;
 
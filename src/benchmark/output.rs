@@ -73,6 +73,46 @@ pub fn format_results(apt_get_results: &[BenchmarkResult], apt_ng_results: &[Ben
     }
 }
 
+/// Formatiert In-Process-Ergebnisse (Zeiten und Metriken je Operation)
+pub fn format_operation_results(results: &[BenchmarkResult]) {
+    println!("\n{}", "=".repeat(80));
+    println!("In-Process Benchmark Results");
+    println!("{}", "=".repeat(80));
+    
+    // Operationen in der Reihenfolge ihres ersten Auftretens gruppieren
+    let mut operations: Vec<&str> = Vec::new();
+    for result in results {
+        if !operations.contains(&result.operation.as_str()) {
+            operations.push(&result.operation);
+        }
+    }
+    
+    let mut table = Table::new();
+    table.set_header(vec!["Operation", "Runs", "Avg (ms)", "Min (ms)", "Max (ms)", "CPU Usage (%)", "Memory Delta (MB)"]);
+    
+    for operation in operations {
+        let runs: Vec<BenchmarkResult> = results.iter()
+            .filter(|r| r.operation == operation)
+            .cloned()
+            .collect();
+        let avg = calculate_average(&runs);
+        let min = runs.iter().map(|r| r.duration).min().unwrap_or_default();
+        let max = runs.iter().map(|r| r.duration).max().unwrap_or_default();
+        
+        table.add_row(vec![
+            operation.to_string(),
+            runs.len().to_string(),
+            format!("{:.3}", avg.duration.as_secs_f64() * 1000.0),
+            format!("{:.3}", min.as_secs_f64() * 1000.0),
+            format!("{:.3}", max.as_secs_f64() * 1000.0),
+            format!("{:.2}", avg.metrics.cpu_usage),
+            format!("{:.2}", avg.metrics.memory_usage as f64 / 1024.0 / 1024.0),
+        ]);
+    }
+    
+    println!("\n{}", table);
+}
+
 /// Berechnet Durchschnittswerte
 fn calculate_average(results: &[BenchmarkResult]) -> BenchmarkResult {
     if results.is_empty() {
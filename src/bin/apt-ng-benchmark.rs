@@ -34,6 +34,18 @@ enum Commands {
         #[arg(short, long, default_value_t = 3)]
         iterations: usize,
     },
+    /// Benchmark parser, index, search and solver in-process (no apt-ng binary needed)
+    Library {
+        /// Packages file to use as fixture (default: synthetic fixture)
+        #[arg(long)]
+        packages_file: Option<std::path::PathBuf>,
+        /// Number of packages in the synthetic fixture
+        #[arg(long, default_value_t = 1000)]
+        packages: usize,
+        /// Number of iterations
+        #[arg(short, long, default_value_t = 3)]
+        iterations: usize,
+    },
 }
 
 #[tokio::main]
@@ -50,6 +62,14 @@ async fn main() -> anyhow::Result<()> {
         Commands::Full { packages, iterations } => {
             apt_ng::benchmark::run_full_benchmark(&packages, iterations).await?;
         }
+        Commands::Library { packages_file, packages, iterations } => {
+            let content = match packages_file {
+                Some(path) => std::fs::read_to_string(&path)?,
+                None => apt_ng::benchmark::inprocess::synthetic_packages_file(packages),
+            };
+            let results = apt_ng::benchmark::inprocess::run_inprocess_benchmark(&content, iterations)?;
+            apt_ng::benchmark::output::format_operation_results(&results);
+        }
     }
     
     Ok(())
@@ -112,6 +112,13 @@ fn create_package(
         timestamp: 0,
         filename: None,
         repo_id: None,
+        maintainer: None,
+        homepage: None,
+        section: None,
+        priority: None,
+        installed_size: None,
+        recommends: vec![],
+        suggests: vec![],
     };
     
     builder.set_manifest(manifest);
@@ -2,7 +2,7 @@ use anyhow::Result;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use sha2::{Sha256, Digest};
 use hex;
 use std::os::unix::fs::MetadataExt;
@@ -11,6 +11,25 @@ pub struct Cache {
     pub cache_dir: PathBuf,
 }
 
+/// Hardlinks einer Datei im Cache: Pfade, Größe, Anzahl Links, Änderungsdatum
+type LinkGroup = (Vec<PathBuf>, u64, u64, SystemTime);
+
+/// Belegung des Caches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheUsage {
+    /// Tatsächlich belegter Speicher (Hardlinks einmal gezählt)
+    pub size: u64,
+    /// Summe aller Dateigrößen, als wären Hardlinks eigene Kopien
+    pub apparent_size: u64,
+}
+
+impl CacheUsage {
+    /// Durch Hardlink-Deduplizierung eingesparte Bytes
+    pub fn hardlink_savings(&self) -> u64 {
+        self.apparent_size.saturating_sub(self.size)
+    }
+}
+
 impl Cache {
     pub fn new(cache_dir: impl AsRef<Path>) -> Result<Self> {
         let cache_dir = cache_dir.as_ref().to_path_buf();
@@ -32,19 +51,43 @@ impl Cache {
         self.cache_dir.join("packages").join(filename)
     }
     
+    /// Wie `package_path_with_ext`, aber nur für gültige Namen, Versionen und Architekturen
+    ///
+    /// Die Felder stammen aus Repository-Indizes; der Pfad muss auch nach Auflösen von
+    /// Symlinks direkt im `packages`-Verzeichnis liegen.
+    pub fn checked_package_path(&self, name: &str, version: &str, arch: &str, ext: &str) -> Result<PathBuf> {
+        crate::package::validate_package_name(name)?;
+        crate::package::validate_version(version)?;
+        crate::package::validate_architecture(arch)?;
+        if ext.is_empty() || !ext.chars().all(|c| c.is_ascii_alphanumeric()) {
+            anyhow::bail!("Invalid package file extension {:?}", ext);
+        }
+        
+        let package_dir = self.cache_dir.join("packages");
+        fs::create_dir_all(&package_dir)?;
+        let path = self.package_path_with_ext(name, version, arch, ext);
+        
+        // Ein vorhandener Eintrag (oder Symlink) zählt mit seinem aufgelösten Ziel
+        let resolved = if fs::symlink_metadata(&path).is_ok() {
+            path.canonicalize()
+        } else {
+            package_dir.canonicalize().map(|dir| dir.join(path.file_name().unwrap_or_default()))
+        };
+        let cache_root = self.cache_dir.canonicalize()?;
+        match resolved {
+            Ok(resolved) if resolved.starts_with(&cache_root) => Ok(path),
+            _ => anyhow::bail!("Cache path {} resolves outside of {}", path.display(), cache_root.display()),
+        }
+    }
+    
     /// Prüft, ob ein Paket im Cache vorhanden ist
-    #[allow(dead_code)]
     pub fn has_package(&self, name: &str, version: &str, arch: &str) -> bool {
         self.package_path(name, version, arch).exists()
     }
     
     /// Fügt ein Paket zum Cache hinzu
-    #[allow(dead_code)]
     pub fn add_package(&self, name: &str, version: &str, arch: &str, data: &[u8]) -> Result<PathBuf> {
-        let package_dir = self.cache_dir.join("packages");
-        fs::create_dir_all(&package_dir)?;
-        
-        let path = self.package_path(name, version, arch);
+        let path = self.checked_package_path(name, version, arch, "deb")?;
         
         // Berechne Checksumme für Deduplikation
         let checksum = Self::calculate_checksum(data);
@@ -54,11 +97,11 @@ impl Cache {
             // Erstelle Hardlink statt Datei zu kopieren
             if let Err(_e) = fs::hard_link(&existing_path, &path) {
                 // Falls Hardlink fehlschlägt (z.B. auf verschiedenen Dateisystemen), kopiere die Datei
-                fs::copy(&existing_path, &path)?;
+                Self::copy_atomic(&existing_path, &path)?;
             }
         } else {
             // Neue Datei schreiben
-            fs::write(&path, data)?;
+            Self::write_atomic(&path, |file| std::io::Write::write_all(file, data))?;
             
             // Speichere Checksumme in Index
             self.update_checksum_index(&checksum, &path)?;
@@ -68,36 +111,71 @@ impl Cache {
     }
     
     /// Fügt ein Paket aus einer Datei zum Cache hinzu (mit Deduplikation)
+    ///
+    /// Die Quelldatei wird dabei in den Cache übernommen (verschoben bzw. entfernt).
     pub fn add_package_from_file(&self, name: &str, version: &str, arch: &str, ext: &str, source_file: &Path) -> Result<PathBuf> {
-        let package_dir = self.cache_dir.join("packages");
-        fs::create_dir_all(&package_dir)?;
-        
-        let path = self.package_path_with_ext(name, version, arch, ext);
+        let path = self.checked_package_path(name, version, arch, ext)?;
         
         // Berechne Checksumme der Quelldatei (streaming für große Dateien)
         let checksum = Self::calculate_file_checksum(source_file)?;
         
         // Prüfe, ob bereits ein Paket mit derselber Checksumme existiert
-        if let Some(existing_path) = self.find_package_by_checksum(&checksum)? {
-            // Erstelle Hardlink statt Datei zu kopieren
-            if let Err(_e) = fs::hard_link(&existing_path, &path) {
-                // Falls Hardlink fehlschlägt, kopiere die Datei
-                fs::copy(&existing_path, &path)?;
+        match self.find_package_by_checksum(&checksum)? {
+            Some(existing_path) if existing_path != path => {
+                if path.exists() {
+                    fs::remove_file(&path)?;
+                }
+                // Erstelle Hardlink statt Datei zu kopieren
+                if let Err(_e) = fs::hard_link(&existing_path, &path) {
+                    // Falls Hardlink fehlschlägt, kopiere die Datei
+                    Self::copy_atomic(&existing_path, &path)?;
+                }
+                fs::remove_file(source_file)?;
             }
-        } else {
-            // Versuche rename zuerst (schneller als copy, atomisch)
-            if let Err(_) = fs::rename(source_file, &path) {
-                // Falls rename fehlschlägt (verschiedene Dateisysteme), kopiere
-                fs::copy(source_file, &path)?;
+            existing => {
+                // Versuche rename zuerst (schneller als copy, atomisch)
+                if let Err(_) = fs::rename(source_file, &path) {
+                    // Falls rename fehlschlägt (verschiedene Dateisysteme), kopiere
+                    Self::copy_atomic(source_file, &path)?;
+                    fs::remove_file(source_file)?;
+                }
+                
+                // Speichere Checksumme in Index
+                if existing.is_none() {
+                    self.update_checksum_index(&checksum, &path)?;
+                }
             }
-            
-            // Speichere Checksumme in Index
-            self.update_checksum_index(&checksum, &path)?;
         }
         
         Ok(path)
     }
     
+    /// Legt `path` atomar an: `write` füllt eine temporäre Datei daneben, die erst vollständig
+    /// und synchronisiert per `rename` an ihren Platz kommt
+    ///
+    /// Ein Abbruch hinterlässt höchstens `.<name>.partial`, nie eine halbe Datei unter `path`,
+    /// die `has_package` als vorhanden melden würde.
+    fn write_atomic(path: &Path, write: impl FnOnce(&mut fs::File) -> std::io::Result<()>) -> Result<()> {
+        let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let partial = path.with_file_name(format!(".{}.partial", file_name));
+        
+        let result = fs::File::create(&partial).and_then(|mut file| {
+            write(&mut file)?;
+            file.sync_all()?;
+            fs::rename(&partial, path)
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&partial);
+        }
+        Ok(result?)
+    }
+    
+    /// Kopiert `source` atomar nach `dest` (siehe `write_atomic`)
+    fn copy_atomic(source: &Path, dest: &Path) -> Result<()> {
+        let mut source = fs::File::open(source)?;
+        Self::write_atomic(dest, |file| std::io::copy(&mut source, file).map(|_| ()))
+    }
+    
     /// Findet ein Paket anhand seiner Checksumme
     fn find_package_by_checksum(&self, checksum: &str) -> Result<Option<PathBuf>> {
         let checksum_index = self.load_checksum_index()?;
@@ -163,7 +241,7 @@ impl Cache {
     }
     
     /// Berechnet die Checksumme einer Datei (streaming für große Dateien)
-    fn calculate_file_checksum(file_path: &Path) -> Result<String> {
+    pub fn calculate_file_checksum(file_path: &Path) -> Result<String> {
         use std::io::Read;
         use std::fs::File;
         
@@ -284,10 +362,17 @@ impl Cache {
     
     /// Bereinigt den Cache, wenn die Größe das Limit überschreitet
     pub fn clean_if_over_limit(&self, max_size_bytes: u64) -> Result<usize> {
+        Ok(self.clean_if_over_limit_preferring(max_size_bytes, &[])?.len())
+    }
+    
+    /// Bereinigt den Cache, wenn die Größe das Limit überschreitet, und entfernt dabei
+    /// zuerst die bevorzugten Dateien (z.B. Pakete, die bereits installiert sind), danach die ältesten.
+    /// Gibt die entfernten Dateien zurück.
+    pub fn clean_if_over_limit_preferring(&self, max_size_bytes: u64, preferred: &[PathBuf]) -> Result<Vec<PathBuf>> {
         let current_size = self.size()?;
         
         if current_size <= max_size_bytes {
-            return Ok(0);
+            return Ok(Vec::new());
         }
         
         // Berechne wie viel entfernt werden muss
@@ -296,73 +381,88 @@ impl Cache {
         // Sammle alle Pakete mit Größe und Änderungsdatum
         let packages_dir = self.cache_dir.join("packages");
         if !packages_dir.exists() {
-            return Ok(0);
+            return Ok(Vec::new());
         }
         
-        let mut packages: Vec<(PathBuf, u64, SystemTime)> = Vec::new();
+        // Gruppiere Hardlinks (gleiches Gerät + Inode): Speicher wird erst frei,
+        // wenn der letzte Link entfernt ist, daher werden sie gemeinsam entfernt
+        let mut groups: HashMap<(u64, u64), LinkGroup> = HashMap::new();
         for entry in fs::read_dir(&packages_dir)? {
             let entry = entry?;
             let path = entry.path();
             
             if path.is_file() {
                 let metadata = fs::metadata(&path)?;
-                let size = metadata.len();
-                let modified = metadata.modified()?;
-                packages.push((path, size, modified));
+                let group = groups.entry((metadata.dev(), metadata.ino()))
+                    .or_insert_with(|| (Vec::new(), metadata.len(), metadata.nlink(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)));
+                group.0.push(path);
             }
         }
         
-        // Sortiere nach Änderungsdatum (älteste zuerst)
-        packages.sort_by(|a, b| a.2.cmp(&b.2));
+        let mut packages: Vec<LinkGroup> = groups.into_values().collect();
+        
+        // Sortiere: bevorzugte Dateien zuerst, dann nach Änderungsdatum (älteste zuerst)
+        let is_preferred = |paths: &[PathBuf]| paths.iter().any(|p| preferred.contains(p));
+        packages.sort_by(|a, b| {
+            is_preferred(&b.0).cmp(&is_preferred(&a.0)).then(a.3.cmp(&b.3))
+        });
         
         // Entferne Pakete bis das Limit erreicht ist
         let mut removed_size = 0u64;
-        let mut removed_count = 0usize;
+        let mut removed = Vec::new();
         
-        for (path, size, _) in packages {
+        for (paths, size, nlink, _) in packages {
             if removed_size >= to_remove {
                 break;
             }
             
-            fs::remove_file(&path)?;
+            // Links außerhalb des Caches halten die Daten am Leben - Entfernen bringt nichts
+            if nlink > paths.len() as u64 {
+                continue;
+            }
+            
+            for path in paths {
+                fs::remove_file(&path)?;
+                removed.push(path);
+            }
             removed_size += size;
-            removed_count += 1;
         }
         
-        Ok(removed_count)
+        self.clean_checksum_index()?;
+        
+        Ok(removed)
     }
     
-    /// Gibt die Größe des Caches zurück
+    /// Gibt die Größe des Caches zurück (Hardlinks werden nur einmal gezählt)
     pub fn size(&self) -> Result<u64> {
-        let mut total_size = 0u64;
-        
+        Ok(self.usage()?.size)
+    }
+    
+    /// Gibt die Belegung des Caches samt Ersparnis durch Hardlinks zurück
+    pub fn usage(&self) -> Result<CacheUsage> {
+        let mut usage = CacheUsage { size: 0, apparent_size: 0 };
         if self.cache_dir.exists() {
-            for entry in fs::read_dir(&self.cache_dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_file() {
-                    total_size += fs::metadata(&path)?.len();
-                } else if path.is_dir() {
-                    total_size += self.dir_size(&path)?;
-                }
-            }
+            self.dir_usage(&self.cache_dir, &mut HashSet::new(), &mut usage)?;
         }
-        
-        Ok(total_size)
+        Ok(usage)
     }
     
-    fn dir_size(&self, dir: &Path) -> Result<u64> {
-        let mut total = 0u64;
+    fn dir_usage(&self, dir: &Path, seen: &mut HashSet<(u64, u64)>, usage: &mut CacheUsage) -> Result<()> {
         for entry in fs::read_dir(dir)? {
             let entry = entry?;
             let path = entry.path();
             if path.is_file() {
-                total += fs::metadata(&path)?.len();
+                let metadata = fs::metadata(&path)?;
+                usage.apparent_size += metadata.len();
+                // Jede physische Datei nur einmal zählen
+                if seen.insert((metadata.dev(), metadata.ino())) {
+                    usage.size += metadata.len();
+                }
             } else if path.is_dir() {
-                total += self.dir_size(&path)?;
+                self.dir_usage(&path, seen, usage)?;
             }
         }
-        Ok(total)
+        Ok(())
     }
 }
 
@@ -389,6 +489,105 @@ mod tests {
         assert!(cache.has_package("test", "1.0", "amd64"));
     }
     
+    #[test]
+    fn test_clean_if_over_limit_prefers_given_packages() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = Cache::new(temp_dir.path()).unwrap();
+        
+        let old = cache.add_package("old", "1.0", "amd64", &[0u8; 1000]).unwrap();
+        let installed = cache.add_package("installed", "1.0", "amd64", &[1u8; 1000]).unwrap();
+        
+        // Limit erlaubt nur noch ein Paket (plus Checksum-Index)
+        let limit = cache.size().unwrap() - 500;
+        let removed = cache.clean_if_over_limit_preferring(limit, std::slice::from_ref(&installed)).unwrap();
+        
+        assert_eq!(removed, vec![installed.clone()]);
+        assert!(old.exists());
+        assert!(!installed.exists());
+    }
+    
+    #[test]
+    fn test_add_package_from_file_hardlinks_duplicates() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = Cache::new(temp_dir.path().join("cache")).unwrap();
+        
+        let first = temp_dir.path().join("first.tmp");
+        let second = temp_dir.path().join("second.tmp");
+        fs::write(&first, b"identical deb").unwrap();
+        fs::write(&second, b"identical deb").unwrap();
+        
+        let a = cache.add_package_from_file("tool", "1.0", "all", "deb", &first).unwrap();
+        let b = cache.add_package_from_file("tool-data", "1.0", "all", "deb", &second).unwrap();
+        
+        assert!(!first.exists());
+        assert!(!second.exists());
+        assert_eq!(fs::metadata(&a).unwrap().nlink(), 2);
+        assert_eq!(fs::metadata(&b).unwrap().ino(), fs::metadata(&a).unwrap().ino());
+        assert_eq!(cache.load_checksum_index().unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn test_size_counts_hardlinks_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = Cache::new(temp_dir.path()).unwrap();
+        
+        let original = cache.add_package("lib", "1.0", "all", &[7u8; 4096]).unwrap();
+        let size_single = cache.size().unwrap();
+        
+        let link = cache.package_path("lib-alias", "1.0", "all");
+        fs::hard_link(&original, &link).unwrap();
+        assert_eq!(cache.size().unwrap(), size_single);
+        assert_eq!(cache.usage().unwrap().hardlink_savings(), 4096);
+        
+        // Nur das gemeinsame Entfernen beider Links gibt Speicher frei
+        let removed = cache.clean_if_over_limit_preferring(size_single - 1, &[]).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(!original.exists());
+        assert!(!link.exists());
+    }
+    
+    #[test]
+    fn test_interrupted_insert_is_not_visible() {
+        use std::io::Write;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let cache = Cache::new(temp_dir.path()).unwrap();
+        let path = cache.checked_package_path("tool", "1.0", "amd64", "deb").unwrap();
+        
+        // Abbruch mitten im Schreiben (z.B. volle Platte)
+        let err = Cache::write_atomic(&path, |file| {
+            file.write_all(b"!<arch>\ntrunc")?;
+            Err(std::io::Error::other("interrupted"))
+        }).unwrap_err();
+        assert!(err.to_string().contains("interrupted"));
+        assert!(!cache.has_package("tool", "1.0", "amd64"));
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 0);
+        
+        Cache::write_atomic(&path, |file| file.write_all(b"complete")).unwrap();
+        assert!(cache.has_package("tool", "1.0", "amd64"));
+        assert_eq!(fs::read(&path).unwrap(), b"complete");
+    }
+    
+    #[test]
+    fn test_rejects_paths_outside_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = Cache::new(temp_dir.path().join("cache")).unwrap();
+        let source = temp_dir.path().join("download.tmp");
+        fs::write(&source, b"payload").unwrap();
+        
+        // Name und Version aus einem präparierten Index
+        assert!(cache.add_package_from_file("../../escape", "1.0", "all", "deb", &source).is_err());
+        assert!(cache.add_package_from_file("tool", "1.0/../../../escape", "all", "deb", &source).is_err());
+        assert!(source.exists());
+        
+        // Ein Symlink im Cache darf nicht nach außen schreiben lassen
+        let outside = temp_dir.path().join("outside.deb");
+        fs::create_dir_all(cache.cache_dir.join("packages")).unwrap();
+        std::os::unix::fs::symlink(&outside, cache.package_path("tool", "1.0", "all")).unwrap();
+        assert!(cache.add_package_from_file("tool", "1.0", "all", "deb", &source).is_err());
+        assert!(!outside.exists());
+    }
+    
     #[test]
     fn test_checksum() {
         let data = b"test";
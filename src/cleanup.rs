@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Ab diesem Alter gelten `apt-ng-*`-Reste im Temp-Verzeichnis als verwaist
+pub const STALE_TEMP_AGE: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Temporäre Dateien und Verzeichnisse, die dieser Prozess angelegt hat
+///
+/// Bei einem Abbruch (Ctrl-C) werden die laufenden Futures verworfen, ohne dass
+/// deren Aufräumcode läuft; `remove_temp_paths` entfernt die Reste dann gesammelt.
+static TEMP_PATHS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Verzeichnis für temporäre Dateien (aus `paths.tmp_dir`); ohne Einstellung das des Systems
+static TEMP_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Setzt das Temp-Verzeichnis für diesen Prozess
+pub fn set_temp_dir(dir: impl Into<PathBuf>) {
+    *TEMP_DIR.lock().unwrap_or_else(|e| e.into_inner()) = Some(dir.into());
+}
+
+/// Aktuelles Temp-Verzeichnis für Downloads und entpackte Pakete
+pub fn temp_dir() -> PathBuf {
+    TEMP_DIR
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// Liefert einen Pfad im Temp-Verzeichnis und merkt ihn für das Aufräumen vor
+pub fn temp_path(name: &str) -> PathBuf {
+    let path = temp_dir().join(name);
+    register_temp_path(&path);
+    path
+}
+
+/// Merkt einen bereits gewählten temporären Pfad (Datei oder Verzeichnis) vor
+pub fn register_temp_path(path: &Path) {
+    let mut paths = TEMP_PATHS.lock().unwrap_or_else(|e| e.into_inner());
+    if !paths.iter().any(|p| p == path) {
+        paths.push(path.to_path_buf());
+    }
+}
+
+/// Vergisst einen vorgemerkten Pfad wieder (er wurde bereits aufgeräumt)
+fn forget_temp_path(path: &Path) {
+    let mut paths = TEMP_PATHS.lock().unwrap_or_else(|e| e.into_inner());
+    paths.retain(|p| p != path);
+}
+
+/// Löscht eine Datei oder ein Verzeichnis; fehlende Pfade sind kein Fehler
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+/// Temporäre Datei, die beim Drop gelöscht wird – egal, über welchen Pfad die Funktion endet
+///
+/// Fehlgeschlagene Downloads, Parse-Fehler und Zeitüberschreitungen hinterlassen so
+/// keine Reste in /tmp. Bis zum Drop ist der Pfad zusätzlich für `remove_temp_paths`
+/// vorgemerkt, damit auch ein Abbruch per Ctrl-C aufräumt.
+pub struct TempPath {
+    path: PathBuf,
+}
+
+impl TempPath {
+    /// Legt einen Pfad `name` im Temp-Verzeichnis an (die Datei selbst wird nicht erstellt)
+    pub fn new(name: &str) -> TempPath {
+        TempPath { path: temp_path(name) }
+    }
+    
+    /// Pfad der temporären Datei
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl std::ops::Deref for TempPath {
+    type Target = Path;
+    
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = remove_path(&self.path);
+        forget_temp_path(&self.path);
+    }
+}
+
+/// Löscht `apt-ng-*`-Reste im Temp-Verzeichnis, die älter als `max_age` sind
+///
+/// Fängt Dateien früherer Läufe ab, die hart beendet wurden (z.B. SIGKILL), bevor
+/// ihre Guards aufräumen konnten. Liefert die Anzahl gelöschter Einträge.
+pub fn sweep_stale_temp_files(max_age: Duration) -> usize {
+    // Ältere Versionen haben immer das System-Temp-Verzeichnis verwendet
+    let mut removed = sweep_stale_in(&temp_dir(), max_age);
+    if temp_dir() != std::env::temp_dir() {
+        removed += sweep_stale_in(&std::env::temp_dir(), max_age);
+    }
+    removed
+}
+
+fn sweep_stale_in(dir: &Path, max_age: Duration) -> usize {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        if !entry.file_name().to_string_lossy().starts_with("apt-ng-") {
+            continue;
+        }
+        let age = entry.metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok());
+        if age.is_some_and(|age| age >= max_age) && remove_path(&entry.path()).is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// Entfernt alle vorgemerkten temporären Pfade, die noch existieren
+///
+/// Liefert die Anzahl tatsächlich gelöschter Einträge.
+pub fn remove_temp_paths() -> usize {
+    let paths = std::mem::take(&mut *TEMP_PATHS.lock().unwrap_or_else(|e| e.into_inner()));
+    let mut removed = 0;
+    for path in paths {
+        if remove_path(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registered_temp_paths_are_removed() {
+        let file = temp_path(&format!("apt-ng-cleanup-test-{}.tmp", std::process::id()));
+        std::fs::write(&file, b"partial download").unwrap();
+
+        let dir = std::env::temp_dir().join(format!("apt-ng-cleanup-test-dir-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("usr/bin")).unwrap();
+        register_temp_path(&dir);
+
+        assert!(remove_temp_paths() >= 2);
+        assert!(!file.exists());
+        assert!(!dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_no_temp_file_remains_after_failed_download() {
+        let source_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(source_dir.path().join("Packages"), b"Package: foo\n").unwrap();
+        let url = format!("file://{}/Packages", source_dir.path().display());
+        
+        let name = format!("apt-ng-cleanup-download-{}.tmp", std::process::id());
+        let result = async {
+            let temp_file = TempPath::new(&name);
+            let downloader = crate::downloader::Downloader::new(1)?;
+            // Falsche Prüfsumme: der Download schreibt die Datei und schlägt dann fehl
+            downloader.download_file_with_checksum(&url, &temp_file, Some("00")).await?;
+            Ok::<(), anyhow::Error>(())
+        }.await;
+        
+        assert!(result.is_err());
+        assert!(!std::env::temp_dir().join(&name).exists());
+        assert!(!TEMP_PATHS.lock().unwrap().iter().any(|p| p.ends_with(&name)));
+    }
+    
+    #[test]
+    fn test_sweep_removes_only_stale_apt_ng_entries() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("apt-ng-packages-old.tmp"), b"x").unwrap();
+        std::fs::create_dir_all(dir.path().join("apt-ng-install-1234/usr")).unwrap();
+        std::fs::write(dir.path().join("unrelated.tmp"), b"x").unwrap();
+        
+        assert_eq!(sweep_stale_in(dir.path(), STALE_TEMP_AGE), 0);
+        assert_eq!(sweep_stale_in(dir.path(), Duration::ZERO), 2);
+        assert!(dir.path().join("unrelated.tmp").exists());
+    }
+}
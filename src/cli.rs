@@ -48,6 +48,16 @@ const HELP_TEMPLATE: &str = "\
     Show package information:\n\
       $ apt-ng show nginx\n\
     \n\
+    Exit codes:\n\
+      0   success\n\
+      1   generic failure\n\
+      2   invalid command line\n\
+      10  dependency resolution failed (package not found, conflict)\n\
+      20  signature or checksum verification failed\n\
+      30  network error (usually retryable)\n\
+      40  disk, filesystem or database error\n\
+      130 interrupted (Ctrl-C)\n\
+    \n\
     For more information, visit: https://github.com/KyoshiHikari/apt-ng"
 )]
 pub struct Cli {
@@ -68,12 +78,99 @@ pub struct Cli {
     #[arg(long = "dry-run", global = true)]
     pub dry_run: bool,
     
-    /// Verbose output
+    /// Run maintainer scripts without sandbox
+    ///
+    /// By default, preinst/postinst/prerm/postrm scripts run inside a
+    /// bubblewrap sandbox without network access when bwrap is available.
+    #[arg(long = "no-sandbox", global = true)]
+    pub no_sandbox: bool,
+    
+    /// Per-file download timeout in seconds
+    ///
+    /// Overrides `overall_timeout` from the config for this run.
+    /// By default the limit grows with the file size.
+    #[arg(long = "timeout", global = true, value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+    
+    /// Prefer package versions from this suite or codename
+    ///
+    /// Like `apt -t`: candidates from the given release win over newer
+    /// versions from other repositories, but never downgrade an installed
+    /// package. Overrides `default_release` from the config.
+    #[arg(short = 't', long = "target-release", global = true, value_name = "RELEASE")]
+    pub target_release: Option<String>,
+    
+    /// Run without root privileges
+    ///
+    /// Keeps configuration, state and cache in the XDG directories
+    /// ($XDG_CONFIG_HOME, $XDG_STATE_HOME, $XDG_CACHE_HOME/apt-ng) and
+    /// installs below $XDG_DATA_HOME/apt-ng/root.
+    #[arg(long = "user", global = true)]
+    pub user: bool,
+    
+    /// Do not check for a new apt-ng release after the command
+    ///
+    /// The check runs at most once a day; it can also be disabled with
+    /// `update_check = false` in the config or APT_NG_NO_UPDATE_CHECK.
+    #[arg(long = "no-update-check", global = true)]
+    pub no_update_check: bool,
+    
+    /// Never access the network
+    ///
+    /// `update` and `self-update` fail, `install` and `upgrade` only use
+    /// packages already in the cache, and no update check runs. Useful on
+    /// air-gapped systems; can also be set with `offline = true` in the config.
+    #[arg(long = "offline", global = true)]
+    pub offline: bool,
+    
+    /// Back up the package index and rebuild its package lists
+    ///
+    /// Use when the index database is corrupted. The old file is kept
+    /// next to it as index.db.bak-<timestamp>; installed packages, history
+    /// and repositories are carried over as far as they are readable.
+    /// Run `apt-ng update` afterwards to reload the package lists.
+    #[arg(long = "rebuild-index", global = true)]
+    pub rebuild_index: bool,
+    
+    /// Write a diagnostic log to this file
+    ///
+    /// Records download URLs, resolution steps, maintainer script runs
+    /// and errors independently of -v, e.g. to attach to bug reports.
+    /// The file is appended to.
+    #[arg(long = "log-file", global = true, value_name = "FILE")]
+    pub log_file: Option<String>,
+    
+    /// Minimum level written to --log-file
+    ///
+    /// One of error, warn, info, debug, trace.
+    #[arg(long = "log-level", global = true, value_name = "LEVEL", default_value = "info")]
+    pub log_level: String,
+    
+    /// Format of --log-file (text or json)
+    #[arg(long = "log-format", global = true, value_name = "FORMAT", default_value = "text")]
+    pub log_format: String,
+    
+    /// Verbose output (repeat for more, e.g. -vv)
     ///
     /// Enables detailed output including dependency resolution steps,
-    /// download progress, and installation details.
-    #[arg(short, long, global = true)]
-    pub verbose: bool,
+    /// download progress, and installation details. `-vv` additionally
+    /// dumps the resolver internals.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    
+    /// Quiet output: only warnings and errors (on stderr)
+    ///
+    /// Hides headings, progress bars and informational messages,
+    /// e.g. for scripts and cron jobs.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+}
+
+impl Cli {
+    /// Output verbosity from `--quiet` and the number of `-v`
+    pub fn verbosity(&self) -> crate::output::Verbosity {
+        crate::output::Verbosity::from_flags(self.quiet, self.verbose)
+    }
 }
 
 #[derive(Subcommand)]
@@ -86,8 +183,33 @@ pub enum Commands {
     /// Examples:
     ///   $ apt-ng update
     ///   $ apt-ng update -v  # Verbose output
+    ///   $ apt-ng update --allow-expired-keys  # Accept lagging mirrors
+    ///   $ apt-ng update --allow-stale-metadata  # Accept frozen mirrors
+    ///   $ apt-ng update --download-contents  # Also index files for file-search
     #[command(alias = "up")]
-    Update,
+    Update {
+        /// Accept repositories signed by expired keys
+        ///
+        /// Escape hatch for mirrors that lag behind a key rotation.
+        /// Signatures created after the key expired are still rejected.
+        #[arg(long = "allow-expired-keys")]
+        allow_expired_keys: bool,
+        
+        /// Accept expired or older Release files
+        ///
+        /// For intentionally frozen mirrors. Without this flag, Release files past
+        /// their Valid-Until date or older than the last seen one are rejected.
+        #[arg(long = "allow-stale-metadata")]
+        allow_stale_metadata: bool,
+        
+        /// Also download the Contents indexes (files per package)
+        ///
+        /// Required for `file-search`. Contents files are large, so this is
+        /// off by default; set `download_contents = true` in the config to
+        /// always fetch them.
+        #[arg(long = "download-contents")]
+        download_contents: bool,
+    },
     
     /// Search for packages in the local index
     ///
@@ -103,6 +225,20 @@ pub enum Commands {
         term: String,
     },
     
+    /// Find packages that contain a file
+    ///
+    /// Searches the Contents indexes fetched by `update --download-contents`
+    /// for file paths containing the given pattern, like `apt-file search`.
+    ///
+    /// Examples:
+    ///   $ apt-ng file-search bin/curl
+    ///   $ apt-ng file-search libssl.so.3
+    FileSearch {
+        /// Part of the file path to search for
+        #[arg(value_name = "PATTERN")]
+        pattern: String,
+    },
+    
     /// Install one or more packages
     ///
     /// Downloads and installs packages along with their dependencies.
@@ -112,11 +248,61 @@ pub enum Commands {
     ///   $ apt-ng install nginx
     ///   $ apt-ng install nginx curl -j 8  # Use 8 parallel workers
     ///   $ apt-ng install nginx --dry-run   # Preview installation
+    ///   $ apt-ng install nginx --download-only  # Stage in cache, install later
+    ///   $ apt-ng install ./foo_1.0_amd64.deb    # Install a local package file
+    ///   $ apt-ng install nginx --write-lock apt-ng.lock  # Record the resolution
+    ///   $ apt-ng install -t bookworm-backports nginx     # Prefer a suite
+    ///   $ apt-ng install nginx --install-suggests        # Also install suggested packages
     #[command(alias = "i")]
     Install {
-        /// Package name(s) to install
+        /// Package name(s) or local .deb/.apx file(s) to install
+        #[arg(value_name = "PACKAGE", required = true)]
+        packages: Vec<String>,
+        
+        /// Only download and verify packages into the cache, do not install
+        #[arg(long)]
+        download_only: bool,
+        
+        /// Write the resolved package set to a lockfile (replay with install-lock)
+        #[arg(long = "write-lock", value_name = "FILE")]
+        write_lock: Option<String>,
+        
+        /// Also install packages listed in Suggests (skipped if unavailable)
+        #[arg(long)]
+        install_suggests: bool,
+    },
+    
+    /// Install exactly the packages recorded in a lockfile
+    ///
+    /// No dependency resolution takes place: every package must still be
+    /// available in the recorded version and architecture with the recorded
+    /// checksum, otherwise nothing is installed.
+    ///
+    /// Examples:
+    ///   $ apt-ng install nginx --write-lock apt-ng.lock
+    ///   $ apt-ng install-lock apt-ng.lock
+    InstallLock {
+        /// Lockfile written by --write-lock
+        #[arg(value_name = "FILE")]
+        file: String,
+    },
+    
+    /// Download packages without installing them
+    ///
+    /// Resolves the requested packages, downloads the .deb files into the
+    /// current directory (not the cache) and verifies their checksums.
+    ///
+    /// Examples:
+    ///   $ apt-ng download nginx
+    ///   $ apt-ng download nginx --resolve-deps  # Include all dependencies
+    Download {
+        /// Package name(s) to download
         #[arg(value_name = "PACKAGE", required = true)]
         packages: Vec<String>,
+        
+        /// Also download all dependencies (for offline installation)
+        #[arg(long = "resolve-deps")]
+        resolve_deps: bool,
     },
     
     /// Remove one or more packages
@@ -142,7 +328,41 @@ pub enum Commands {
     /// Examples:
     ///   $ apt-ng upgrade
     ///   $ apt-ng upgrade --dry-run  # Preview upgrades
-    Upgrade,
+    ///   $ apt-ng upgrade --download-only  # Stage upgrades in cache
+    Upgrade {
+        /// Only download and verify packages into the cache, do not install
+        #[arg(long)]
+        download_only: bool,
+        
+        /// Write the resolved package set to a lockfile (replay with install-lock)
+        #[arg(long = "write-lock", value_name = "FILE")]
+        write_lock: Option<String>,
+    },
+    
+    /// Upgrade all installed packages, removing packages if necessary
+    ///
+    /// Like upgrade, but instead of keeping back upgrades that conflict with
+    /// installed packages (Conflicts/Breaks), those packages are removed.
+    /// Removals are listed separately and must be confirmed.
+    ///
+    /// Examples:
+    ///   $ apt-ng full-upgrade
+    ///   $ apt-ng full-upgrade --dry-run  # Preview upgrades and removals
+    ///   $ apt-ng full-upgrade --yes  # Do not ask before removing packages
+    #[command(alias = "dist-upgrade")]
+    FullUpgrade {
+        /// Only download and verify packages into the cache, do not install
+        #[arg(long)]
+        download_only: bool,
+        
+        /// Remove packages without asking for confirmation
+        #[arg(short = 'y', long = "yes")]
+        yes: bool,
+        
+        /// Write the resolved package set to a lockfile (replay with install-lock)
+        #[arg(long = "write-lock", value_name = "FILE")]
+        write_lock: Option<String>,
+    },
     
     /// Show detailed package information
     ///
@@ -152,10 +372,123 @@ pub enum Commands {
     /// Examples:
     ///   $ apt-ng show nginx
     ///   $ apt-ng show curl
+    ///   $ apt-ng show nginx --all-versions
     Show {
         /// Package name
         #[arg(value_name = "PACKAGE")]
         package: String,
+        
+        /// List every available version with its repository
+        #[arg(long = "all-versions")]
+        all_versions: bool,
+    },
+    
+    /// Show the changelog of a package
+    ///
+    /// Fetches the Debian changelog of the newest available version from the
+    /// changelog server or repository, falling back to the changelog of the
+    /// installed version in /usr/share/doc.
+    ///
+    /// Examples:
+    ///   $ apt-ng changelog nginx
+    Changelog {
+        /// Package name
+        #[arg(value_name = "PACKAGE")]
+        package: String,
+    },
+    
+    /// List the files installed by a package
+    ///
+    /// Uses the file list recorded at installation time. For packages that
+    /// were installed by dpkg (or before apt-ng recorded file lists), the
+    /// dpkg database is consulted instead.
+    ///
+    /// Examples:
+    ///   $ apt-ng files nginx
+    Files {
+        /// Package name
+        #[arg(value_name = "PACKAGE")]
+        package: String,
+    },
+    
+    /// Find the installed package that owns a file
+    ///
+    /// Searches the file lists recorded by apt-ng and falls back to the
+    /// dpkg database (like `dpkg -S`) for packages apt-ng did not install.
+    ///
+    /// Examples:
+    ///   $ apt-ng which-package /usr/sbin/nginx
+    ///   $ apt-ng which-package ./bin/tool
+    WhichPackage {
+        /// Path of the file
+        #[arg(value_name = "PATH")]
+        path: String,
+    },
+    
+    /// Verify a .deb or .apx file without installing it
+    ///
+    /// For .apx packages the embedded signature and the checksums of all
+    /// contained files are checked. For .deb packages the SHA256 is compared
+    /// with the index and a detached Ed25519 signature is verified if one is
+    /// available. Exits non-zero if any check fails.
+    ///
+    /// Examples:
+    ///   $ apt-ng verify ./hello_1.0_amd64.apx
+    ///   $ apt-ng verify ./foo_1.0_amd64.deb --signature foo.sig
+    Verify {
+        /// Package file to verify
+        #[arg(value_name = "FILE")]
+        path: String,
+        
+        /// Detached signature (default: FILE.sig or the .sig next to FILE, if present)
+        #[arg(long, value_name = "SIG")]
+        signature: Option<String>,
+    },
+    
+    /// Build an .apx package from a directory
+    ///
+    /// The directory holds the files as they are installed (usr/bin/...).
+    /// Package metadata is read from a Debian-style control file
+    /// (default: SOURCE_DIR/DEBIAN/control) and/or given with flags; flags
+    /// override the control file. Every file is recorded with its SHA256 and
+    /// mode so `verify` and the installer can check it.
+    ///
+    /// Examples:
+    ///   $ apt-ng build-apx ./hello-root                       # uses hello-root/DEBIAN/control
+    ///   $ apt-ng build-apx ./root --name hello --version 1.0-1 --depends libc6
+    ///   $ apt-ng build-apx ./root -o hello.apx --sign-key apt-ng.key
+    BuildApx {
+        /// Directory with the package contents
+        #[arg(value_name = "SOURCE_DIR")]
+        source_dir: String,
+        
+        /// Output file (default: NAME_VERSION_ARCH.apx)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+        
+        /// Control file with package metadata
+        #[arg(long, value_name = "FILE")]
+        control: Option<String>,
+        
+        /// Package name
+        #[arg(long)]
+        name: Option<String>,
+        
+        /// Package version
+        #[arg(long)]
+        version: Option<String>,
+        
+        /// Package architecture (default: native architecture)
+        #[arg(long)]
+        arch: Option<String>,
+        
+        /// Dependencies (comma-separated or repeated)
+        #[arg(long, value_delimiter = ',')]
+        depends: Vec<String>,
+        
+        /// Ed25519 signing key (32 raw bytes, e.g. from `apt-ng-build generate-key`)
+        #[arg(long, value_name = "KEY")]
+        sign_key: Option<String>,
     },
     
     /// Repository management
@@ -172,28 +505,93 @@ pub enum Commands {
     #[command(subcommand)]
     Cache(CacheAction),
     
+    /// Index database maintenance
+    ///
+    /// `update` refreshes the query statistics after every run and compacts
+    /// the database once many pages have been freed; `index vacuum` does
+    /// both right away.
+    ///
+    /// Examples:
+    ///   $ apt-ng index vacuum
+    #[command(subcommand)]
+    Index(IndexCommands),
+    
     /// Security audit
     ///
     /// Run security checks and generate security audit reports.
     ///
     /// Examples:
     ///   $ apt-ng security audit
-    ///   $ apt-ng security audit --json
+    ///   $ apt-ng security audit --format json
+    ///   $ apt-ng security audit --offline
     #[command(subcommand)]
     Security(SecurityCommands),
     
+    /// Trusted key management
+    ///
+    /// Inspect the keys used to verify repository and package signatures.
+    ///
+    /// Examples:
+    ///   $ apt-ng key list
+    #[command(subcommand)]
+    Key(KeyCommands),
+    
+    /// Inspect the effective configuration
+    ///
+    /// Shows the configuration apt-ng actually uses: defaults, the config file
+    /// and command line options merged, plus derived values such as the
+    /// resolved paths and job counts. Unknown keys in the config file are
+    /// reported as warnings.
+    ///
+    /// Examples:
+    ///   $ apt-ng config list
+    ///   $ apt-ng config get paths.state_dir
+    ///   $ apt-ng -j 4 config get effective.download_jobs
+    #[command(subcommand)]
+    Config(ConfigCommands),
+    
+    /// Show or undo past install, upgrade and remove operations
+    ///
+    /// Every operation that changes the installed packages is recorded with
+    /// its command line, the versions before and after, and its result.
+    ///
+    /// Examples:
+    ///   $ apt-ng history list
+    ///   $ apt-ng history undo      # Remove what the last operation installed
+    ///   $ apt-ng history undo 12
+    #[command(subcommand)]
+    History(HistoryCommands),
+    
+    /// Show usage statistics
+    ///
+    /// Prints an overview of the package index, installed packages, cache
+    /// usage (including hardlink and delta update savings), the configured
+    /// repositories and the fastest mirror per host.
+    ///
+    /// Examples:
+    ///   $ apt-ng stats
+    Stats,
+    
     /// Update apt-ng to the latest version
     ///
     /// Checks GitHub Releases for newer versions and automatically
-    /// downloads and installs the update if available.
+    /// downloads and installs the update if available. The download is
+    /// verified against the SHA256 checksum published with the release
+    /// and its Ed25519 signature (`<asset>.sig`) against the release key
+    /// built into apt-ng.
     ///
     /// Examples:
     ///   $ apt-ng self-update
+    ///   $ apt-ng self-update --dry-run  # Only show what would be installed
     ///   $ apt-ng self-update --force  # Force update even if same version
     SelfUpdate {
         /// Force update even if already on latest version
         #[arg(long)]
         force: bool,
+        
+        /// Install even if the release signature is missing or cannot be checked
+        #[arg(long)]
+        allow_unsigned_update: bool,
     },
 }
 
@@ -207,12 +605,71 @@ pub enum RepoCommands {
     /// Examples:
     ///   $ apt-ng repo add https://deb.debian.org/debian
     ///   $ apt-ng repo add https://mirror.example.com/debian
+    ///   $ apt-ng repo add https://deb.debian.org/debian --suite testing --components "main contrib"
+    ///   $ apt-ng repo add ppa:deadsnakes/ppa
+    ///   $ apt-ng repo add https://apt.example.com --signed-by /usr/share/keyrings/example.gpg
+    ///   $ apt-ng repo add file:///srv/local-repo --trusted yes  # Intentionally unsigned
     Add {
+        /// Repository URL or `ppa:<owner>/<name>`
+        #[arg(value_name = "URL")]
+        url: String,
+        
+        /// Suite or codename below dists/ (default: detected on update)
+        #[arg(long, value_name = "SUITE")]
+        suite: Option<String>,
+        
+        /// Components, separated by commas or spaces (default: main)
+        #[arg(long, value_name = "LIST")]
+        components: Option<String>,
+        
+        /// Architectures, separated by commas or spaces (default: from the config)
+        #[arg(long, value_name = "LIST")]
+        arch: Option<String>,
+        
+        /// Keyring that alone may sign this repository's Release file
+        #[arg(long = "signed-by", value_name = "KEYRING")]
+        signed_by: Option<String>,
+        
+        /// Skip (yes) or always require (no) signature verification for this repository
+        ///
+        /// Without this option the repository follows `require_signatures` from the config.
+        #[arg(long, value_name = "yes|no", value_parser = clap::builder::BoolishValueParser::new())]
+        trusted: Option<bool>,
+    },
+    
+    /// Enable a repository
+    ///
+    /// Re-enables a previously disabled repository.
+    ///
+    /// Examples:
+    ///   $ apt-ng repo enable https://mirror.example.com/debian
+    Enable {
         /// Repository URL
         #[arg(value_name = "URL")]
         url: String,
     },
     
+    /// Disable a repository
+    ///
+    /// Disables a repository without deleting it. Disabled repositories are
+    /// skipped by update, install and mirror probing.
+    ///
+    /// Examples:
+    ///   $ apt-ng repo disable https://mirror.example.com/debian
+    Disable {
+        /// Repository URL
+        #[arg(value_name = "URL")]
+        url: String,
+    },
+    
+    /// List configured repositories
+    ///
+    /// Shows all repositories including disabled ones.
+    ///
+    /// Examples:
+    ///   $ apt-ng repo list
+    List,
+    
     /// Probe mirrors and update prioritization
     ///
     /// Tests mirror performance (RTT and throughput) and updates
@@ -225,11 +682,16 @@ pub enum RepoCommands {
     
     /// Generate repository index files
     ///
-    /// Scans a directory for packages and generates Packages and Release files.
+    /// Scans a directory (recursively) for packages and generates one
+    /// Packages file (plus .gz and .xz) per architecture and a Release file
+    /// with MD5Sum, SHA1 and SHA256 entries for all of them. Architecture
+    /// `all` packages are added to every index.
     ///
     /// Examples:
     ///   $ apt-ng repo generate /path/to/packages
     ///   $ apt-ng repo generate /path/to/packages --suite stable --component main
+    ///   $ apt-ng repo generate /path/to/packages --arch amd64,arm64
+    ///   $ apt-ng repo generate /path/to/packages --key repo.key  # Sign InRelease/Release.gpg
     Generate {
         /// Directory containing package files
         #[arg(value_name = "DIRECTORY")]
@@ -240,10 +702,11 @@ pub enum RepoCommands {
         /// Component name (e.g., main, contrib)
         #[arg(long, default_value = "main")]
         component: String,
-        /// Architecture (e.g., amd64, arm64)
-        #[arg(long, default_value = "amd64")]
-        arch: String,
-        /// Signing key file (optional)
+        /// Architectures to index, comma-separated (default: all found in the directory)
+        #[arg(long, value_delimiter = ',')]
+        arch: Vec<String>,
+        /// Signing key file: raw 32-byte Ed25519 secret key (`apt-ng-build generate-key`).
+        /// Writes InRelease and Release.gpg; clients trust the matching `.pub` key.
         #[arg(long)]
         key: Option<String>,
     },
@@ -254,12 +717,65 @@ pub enum SecurityCommands {
     /// Run security audit
     ///
     /// Performs comprehensive security checks including signature verification,
-    /// sandbox configuration, and input validation checks.
+    /// sandbox configuration, input validation checks, and known vulnerabilities
+    /// (CVEs) in installed packages via OSV.dev.
     Audit {
         /// Output format (text or json)
         #[arg(long, default_value = "text")]
         format: String,
+        
+        /// Use the cached advisory snapshot instead of querying OSV.dev
+        #[arg(long)]
+        offline: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum KeyCommands {
+    /// List trusted keys
+    ///
+    /// Shows all trusted keys with their fingerprint and expiry date.
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Print a single setting
+    ///
+    /// Keys use dots for nesting, e.g. `paths.cache_dir` or `effective.solver_jobs`.
+    Get {
+        /// Setting name
+        #[arg(value_name = "KEY")]
+        key: String,
     },
+    
+    /// Print all settings
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum HistoryCommands {
+    /// List recorded operations, newest first
+    List {
+        /// Number of operations to show
+        #[arg(short = 'n', long, default_value = "20", value_name = "N")]
+        limit: usize,
+    },
+    
+    /// Remove the packages an operation installed
+    ///
+    /// Upgrades and removals cannot be undone and are only reported.
+    Undo {
+        /// Operation to undo (default: the most recent one)
+        #[arg(value_name = "ID")]
+        id: Option<i64>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum IndexCommands {
+    /// Compact the index database and refresh its query statistics
+    Vacuum,
 }
 
 #[derive(Subcommand)]
@@ -2,6 +2,7 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -9,6 +10,140 @@ pub struct Config {
     pub jobs: Option<usize>,
     pub repos: Vec<RepoConfig>,
     pub sandbox: Option<SandboxConfig>,
+    #[serde(default)]
+    pub cache: Option<CacheConfig>,
+    /// Architekturen, deren Paketindizes geladen werden (Standard: automatisch erkannt)
+    #[serde(default)]
+    pub architectures: Option<Vec<String>>,
+    #[serde(default)]
+    pub timeouts: Option<TimeoutConfig>,
+    #[serde(default)]
+    pub parallelism: Option<ParallelismConfig>,
+    #[serde(default)]
+    pub keyrings: Option<KeyringConfig>,
+    #[serde(default)]
+    pub http: Option<HttpConfig>,
+    #[serde(default)]
+    pub limits: Option<LimitsConfig>,
+    /// Bevorzugte Suite oder Codename bei der Versionswahl (wie `APT::Default-Release`, `-t` überschreibt)
+    #[serde(default)]
+    pub default_release: Option<String>,
+    /// Contents-Indizes bei `update` laden (für `file-search`, Standard: false, die Dateien sind groß)
+    #[serde(default)]
+    pub download_contents: Option<bool>,
+    /// Signaturprüfung für Repositories ohne `signed-by`/`trusted` (Standard: true)
+    #[serde(default)]
+    pub require_signatures: Option<bool>,
+    /// Nach Befehlen einmal am Tag auf neue apt-ng-Versionen hinweisen (Standard: true)
+    #[serde(default)]
+    pub update_check: Option<bool>,
+    /// Nie auf das Netzwerk zugreifen, nur aus dem Cache installieren (Standard: false, `--offline`)
+    #[serde(default)]
+    pub offline: Option<bool>,
+}
+
+/// HTTP-Verhalten des Downloaders
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HttpConfig {
+    /// Weiterleitungen folgen, z.B. von Redirectoren wie deb.debian.org (Standard: true)
+    pub follow_redirects: Option<bool>,
+    /// Maximale Anzahl Weiterleitungen pro Request (Standard: 10)
+    pub max_redirects: Option<usize>,
+}
+
+/// Obergrenzen für Daten aus Repositories
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LimitsConfig {
+    /// Maximal entpackte Bytes pro Paketinhalt (Standard: 4 GiB)
+    pub max_decompressed_size: Option<u64>,
+    /// Maximal entpackte Bytes einer Indexdatei, die im Speicher gelesen wird (Standard: 512 MiB)
+    pub max_index_size: Option<u64>,
+}
+
+/// Standardpfad der Konfigurationsdatei
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/apt-ng/config.toml";
+
+/// Per `--user` erzwungener Nutzermodus (siehe `user_mode`)
+static FORCE_USER_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Erzwingt den Nutzermodus für diesen Prozess (`--user`)
+pub fn set_user_mode(enabled: bool) {
+    FORCE_USER_MODE.store(enabled, Ordering::Relaxed);
+}
+
+/// Nutzermodus: Konfiguration, Zustand, Cache und Installationsbaum liegen in den
+/// XDG-Verzeichnissen des Benutzers statt unter /etc, /var und /
+///
+/// Nur mit `--user` aktiv, nicht automatisch ohne Root-Rechte.
+pub fn user_mode() -> bool {
+    FORCE_USER_MODE.load(Ordering::Relaxed)
+}
+
+/// XDG-Basisverzeichnis aus `var`, sonst `$HOME/<fallback>`
+///
+/// Relative Werte sind laut XDG-Spezifikation ungültig und werden ignoriert.
+fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)))
+}
+
+fn default_install_root() -> PathBuf {
+    PathBuf::from("/")
+}
+
+/// Schlüsselbunde von apt, die zusätzlich zu den eigenen Schlüsseln geladen werden
+pub const APT_KEYRING_PATHS: &[&str] = &["/etc/apt/trusted.gpg", "/etc/apt/trusted.gpg.d", "/etc/apt/keyrings"];
+
+/// OpenPGP-Schlüsselbunde für die Prüfung von Repository-Signaturen
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KeyringConfig {
+    /// Schlüsselbunde von apt laden (`APT_KEYRING_PATHS`, Standard: true)
+    pub apt_keyrings: Option<bool>,
+    /// Weitere Schlüsselbund-Dateien oder -Verzeichnisse (`*.gpg`, `*.asc`)
+    #[serde(default)]
+    pub paths: Vec<PathBuf>,
+}
+
+/// Obergrenze für parallele Worker ohne eigene Vorgabe (schont Mirrors und Dateideskriptoren)
+pub const DEFAULT_JOB_CAP: usize = 32;
+
+/// Parallelität je Operation; Netzwerk-I/O und Abhängigkeitsauflösung skalieren unterschiedlich
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParallelismConfig {
+    /// Gleichzeitige Downloads (Standard: CPU-Kerne * 2)
+    pub download_jobs: Option<usize>,
+    /// Threads für die parallele Abhängigkeitsauflösung (Standard: CPU-Kerne)
+    pub solver_jobs: Option<usize>,
+    /// Obergrenze für alle Werte, auch für `--jobs` (Standard: 32)
+    pub max_jobs: Option<usize>,
+}
+
+/// Effektive Parallelität, bereits auf die Obergrenze begrenzt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Jobs {
+    /// Gleichzeitige Downloads und Installations-Worker
+    pub download: usize,
+    /// Threads des Solvers (1 = sequentiell)
+    pub solver: usize,
+}
+
+/// Zeitlimits für Downloads in Sekunden
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimeoutConfig {
+    /// Verbindungsaufbau (Standard: 10)
+    pub connect_timeout: Option<u64>,
+    /// Maximale Wartezeit auf Antwort bzw. nächsten Datenblock (Standard: 30)
+    pub read_timeout: Option<u64>,
+    /// Feste Obergrenze pro Datei (Standard: abhängig von der Dateigröße)
+    pub overall_timeout: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// Maximale Cache-Größe in Bytes; wird nach jeder Installation automatisch eingehalten
+    pub max_size: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +152,9 @@ pub struct SandboxConfig {
     pub network_allowed: bool,
     pub memory_limit: Option<u64>, // in Bytes
     pub cpu_limit: Option<f64>,     // z.B. 0.5 für 50%
+    /// Pakete, deren Maintainer-Skripte Netzwerkzugriff erhalten
+    #[serde(default)]
+    pub network_allowed_packages: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,13 +163,26 @@ pub struct Paths {
     pub state_dir: PathBuf,
     pub cache_dir: PathBuf,
     pub trusted_keys_dir: PathBuf,
+    /// Wurzel des Installationsbaums (Standard: `/`, im Nutzermodus unter `$XDG_DATA_HOME`)
+    #[serde(default = "default_install_root")]
+    pub install_root: PathBuf,
+    /// Temporäre Dateien (Downloads, entpackte Pakete); Standard: `<cache_dir>/tmp`, damit
+    /// fertige Downloads per `rename` in den Cache wandern
+    #[serde(default)]
+    pub tmp_dir: Option<PathBuf>,
 }
 
+/// Schlüssel, deren Werte `apt-ng config` nie im Klartext ausgibt
+const SECRET_KEYS: &[&str] = &["token", "password", "credentials"];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepoConfig {
     pub url: String,
     pub priority: i32,
     pub enabled: bool,
+    /// Bearer-Token für Repositories mit Authentifizierung
+    #[serde(default)]
+    pub token: Option<String>,
 }
 
 impl Default for Config {
@@ -47,6 +198,8 @@ impl Default for Config {
                 state_dir,
                 cache_dir,
                 trusted_keys_dir,
+                install_root: default_install_root(),
+                tmp_dir: None,
             },
             jobs: None,
             repos: Vec::new(),
@@ -55,17 +208,65 @@ impl Default for Config {
                 network_allowed: false,
                 memory_limit: Some(512 * 1024 * 1024), // 512 MB default
                 cpu_limit: Some(1.0),                  // 100% CPU default
+                network_allowed_packages: Vec::new(),
             }),
+            cache: None,
+            architectures: None,
+            timeouts: None,
+            parallelism: None,
+            keyrings: None,
+            http: None,
+            limits: None,
+            default_release: None,
+            download_contents: None,
+            require_signatures: None,
+            update_check: None,
+            offline: None,
         }
     }
 }
 
 impl Config {
+    /// Standardkonfiguration für den Nutzermodus (None, wenn weder `$HOME` noch XDG-Variablen gesetzt sind)
+    ///
+    /// Zustand und Index liegen unter `$XDG_STATE_HOME/apt-ng`, der Paket-Cache unter
+    /// `$XDG_CACHE_HOME/apt-ng` und installiert wird nach `$XDG_DATA_HOME/apt-ng/root`.
+    pub fn user_default() -> Option<Self> {
+        let config_dir = xdg_dir("XDG_CONFIG_HOME", ".config")?.join("apt-ng");
+        let state_dir = xdg_dir("XDG_STATE_HOME", ".local/state")?.join("apt-ng");
+        let cache_dir = xdg_dir("XDG_CACHE_HOME", ".cache")?.join("apt-ng");
+        let install_root = xdg_dir("XDG_DATA_HOME", ".local/share")?.join("apt-ng").join("root");
+        
+        Some(Config {
+            paths: Paths {
+                trusted_keys_dir: config_dir.join("trusted.gpg.d"),
+                config_dir,
+                state_dir,
+                cache_dir,
+                install_root,
+                tmp_dir: None,
+            },
+            ..Config::default()
+        })
+    }
+    
+    /// Pfad der Konfigurationsdatei: `DEFAULT_CONFIG_PATH`, im Nutzermodus
+    /// `$XDG_CONFIG_HOME/apt-ng/config.toml`
+    pub fn default_path() -> PathBuf {
+        match user_mode().then(Self::user_default).flatten() {
+            Some(config) => config.paths.config_dir.join("config.toml"),
+            None => PathBuf::from(DEFAULT_CONFIG_PATH),
+        }
+    }
+    
     /// Lädt die Konfiguration aus einer TOML-Datei oder erstellt eine Default-Konfiguration
+    ///
+    /// Ohne `config_path` gilt `default_path()`; im Nutzermodus wird eine fehlende Datei
+    /// mit den Pfaden aus `user_default()` angelegt.
     pub fn load(config_path: Option<&Path>) -> Result<Self> {
         let config_path = config_path
             .map(|p| p.to_path_buf())
-            .unwrap_or_else(|| PathBuf::from("/etc/apt-ng/config.toml"));
+            .unwrap_or_else(Self::default_path);
         
         if config_path.exists() {
             let content = fs::read_to_string(&config_path)?;
@@ -73,7 +274,11 @@ impl Config {
             Ok(config)
         } else {
             // Erstelle Default-Konfiguration
-            let config = Config::default();
+            let config = if user_mode() {
+                Self::user_default().unwrap_or_default()
+            } else {
+                Config::default()
+            };
             // Erstelle Verzeichnisse falls nötig
             if let Some(parent) = config_path.parent() {
                 fs::create_dir_all(parent)?;
@@ -85,6 +290,126 @@ impl Config {
         }
     }
     
+    /// Liefert Schlüssel der Konfigurationsdatei, die apt-ng nicht kennt (z.B. Tippfehler)
+    ///
+    /// serde ignoriert unbekannte Felder stillschweigend; hier wird die Datei daher mit
+    /// der daraus geparsten und wieder serialisierten Konfiguration verglichen. Die
+    /// Schlüssel werden mit Punkten angegeben (`parallelism.solver_job`).
+    pub fn unknown_keys(content: &str) -> Result<Vec<String>> {
+        let raw: toml::Value = toml::from_str(content)?;
+        let config: Config = toml::from_str(content)?;
+        let known = toml::Value::try_from(&config)?;
+        
+        let mut unknown = Vec::new();
+        Self::collect_unknown_keys("", &raw, &known, &mut unknown);
+        Ok(unknown)
+    }
+    
+    fn collect_unknown_keys(prefix: &str, raw: &toml::Value, known: &toml::Value, unknown: &mut Vec<String>) {
+        match (raw, known) {
+            (toml::Value::Table(raw), toml::Value::Table(known)) => {
+                for (key, value) in raw {
+                    let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                    match known.get(key) {
+                        Some(known_value) => Self::collect_unknown_keys(&path, value, known_value, unknown),
+                        None => unknown.push(path),
+                    }
+                }
+            }
+            (toml::Value::Array(raw), toml::Value::Array(known)) => {
+                for (i, (value, known_value)) in raw.iter().zip(known).enumerate() {
+                    Self::collect_unknown_keys(&format!("{}[{}]", prefix, i), value, known_value, unknown);
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    /// Unbekannte Schlüssel der Konfigurationsdatei unter `config_path` (Standard: `default_path()`)
+    ///
+    /// Fehlt die Datei oder lässt sie sich nicht parsen, ist die Liste leer; Parse-Fehler
+    /// meldet bereits `load`.
+    pub fn unknown_keys_in(config_path: Option<&Path>) -> Vec<String> {
+        let config_path = config_path.map(Path::to_path_buf).unwrap_or_else(Self::default_path);
+        fs::read_to_string(config_path)
+            .ok()
+            .and_then(|content| Self::unknown_keys(&content).ok())
+            .unwrap_or_default()
+    }
+    
+    /// Effektive Einstellungen als sortierte `schlüssel = wert`-Paare (für `apt-ng config`)
+    ///
+    /// Enthält alle gesetzten Werte aus Standardwerten, Datei und Kommandozeile sowie
+    /// unter `effective.*` die daraus abgeleiteten Werte, die apt-ng tatsächlich verwendet
+    /// (Parallelität nach `--jobs`, Zeitlimits, Architekturen, Pfade). Geheime Werte wie
+    /// Repository-Tokens erscheinen nur als `"<redacted>"`.
+    pub fn effective_settings(&self, requested_jobs: Option<usize>) -> Result<Vec<(String, String)>> {
+        let mut settings = std::collections::BTreeMap::new();
+        Self::flatten("", &toml::Value::try_from(self)?, &mut settings);
+        
+        let jobs = self.effective_jobs(requested_jobs);
+        let timeouts = self.download_timeouts();
+        let quoted = |path: &Path| toml::Value::String(path.display().to_string()).to_string();
+        let quoted_list = |items: Vec<String>| toml::Value::Array(items.into_iter().map(toml::Value::String).collect()).to_string();
+        let derived = [
+            ("effective.download_jobs", jobs.download.to_string()),
+            ("effective.solver_jobs", jobs.solver.to_string()),
+            ("effective.max_jobs", self.job_cap().to_string()),
+            ("effective.connect_timeout", timeouts.connect.as_secs().to_string()),
+            ("effective.read_timeout", timeouts.read.as_secs().to_string()),
+            ("effective.overall_timeout", timeouts.overall
+                .map(|overall| overall.as_secs().to_string())
+                .unwrap_or_else(|| "\"auto\"".to_string())),
+            ("effective.max_redirects", self.max_redirects()
+                .map(|max| max.to_string())
+                .unwrap_or_else(|| "\"disabled\"".to_string())),
+            ("effective.max_decompressed_size", self.max_decompressed_size().to_string()),
+            ("effective.max_index_size", self.max_index_size().to_string()),
+            ("effective.architectures", quoted_list(self.architectures())),
+            ("effective.keyring_paths", quoted_list(
+                self.keyring_paths().iter().map(|path| path.display().to_string()).collect())),
+            ("effective.cache_max_size", self.cache_max_size()
+                .map(|size| size.to_string())
+                .unwrap_or_else(|| "\"unlimited\"".to_string())),
+            ("effective.sandbox", (self.sandbox_config().is_some()).to_string()),
+            ("effective.user_mode", user_mode().to_string()),
+            ("effective.index_db", quoted(&self.index_db_path())),
+            ("effective.lock", quoted(&self.lock_path())),
+            ("effective.tmp_dir", quoted(&self.tmp_dir())),
+        ];
+        for (key, value) in derived {
+            settings.insert(key.to_string(), value);
+        }
+        
+        Ok(settings.into_iter().collect())
+    }
+    
+    fn flatten(prefix: &str, value: &toml::Value, settings: &mut std::collections::BTreeMap<String, String>) {
+        match value {
+            toml::Value::Table(table) => {
+                for (key, value) in table {
+                    let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                    Self::flatten(&path, value, settings);
+                }
+            }
+            // Listen von Tabellen (z.B. `repos`) einzeln aufführen
+            toml::Value::Array(items) if items.iter().any(|item| item.is_table()) => {
+                for (i, item) in items.iter().enumerate() {
+                    Self::flatten(&format!("{}[{}]", prefix, i), item, settings);
+                }
+            }
+            _ => {
+                let name = prefix.rsplit('.').next().unwrap_or(prefix);
+                let value = if SECRET_KEYS.contains(&name) {
+                    toml::Value::String("<redacted>".to_string()).to_string()
+                } else {
+                    value.to_string()
+                };
+                settings.insert(prefix.to_string(), value);
+            }
+        }
+    }
+    
     /// Gibt die Anzahl der Worker-Threads zurück
     /// 
     /// Gibt immer die maximale Anzahl verfügbarer CPU-Kerne zurück.
@@ -104,28 +429,219 @@ impl Config {
     /// Wird verwendet, wenn explizit die maximale Anzahl benötigt wird.
     /// 
     /// Für normale Verwendung sollte `jobs()` verwendet werden, die Config-Einstellungen respektiert.
-    #[allow(dead_code)]
     pub fn max_jobs(&self) -> usize {
         std::thread::available_parallelism()
             .map(|n| n.get())
             .unwrap_or(4)
     }
     
+    /// Obergrenze für parallele Worker (`parallelism.max_jobs`, sonst `DEFAULT_JOB_CAP`)
+    pub fn job_cap(&self) -> usize {
+        self.parallelism.as_ref()
+            .and_then(|p| p.max_jobs)
+            .unwrap_or(DEFAULT_JOB_CAP)
+            .max(1)
+    }
+    
+    /// Effektive Parallelität für Downloads und Solver
+    ///
+    /// `requested` (aus `--jobs`) gilt für beide Operationen; ohne Angabe greifen die
+    /// Werte aus `[parallelism]` bzw. die Standardwerte. Alles wird auf `job_cap()` begrenzt.
+    pub fn effective_jobs(&self, requested: Option<usize>) -> Jobs {
+        let cpus = self.max_jobs();
+        let parallelism = self.parallelism.clone().unwrap_or_default();
+        let cap = self.job_cap();
+        
+        Jobs {
+            download: requested.or(parallelism.download_jobs).unwrap_or(cpus * 2).clamp(1, cap),
+            solver: requested.or(parallelism.solver_jobs).unwrap_or(cpus).clamp(1, cap),
+        }
+    }
+    
     /// Gibt den Pfad zur Index-Datenbank zurück
     pub fn index_db_path(&self) -> PathBuf {
         self.paths.state_dir.join("index.db")
     }
     
+    /// Gibt den Pfad zum Transaktions-Journal zurück (für Crash-Recovery)
+    pub fn transaction_journal_path(&self) -> PathBuf {
+        self.paths.state_dir.join("transaction.journal")
+    }
+    
+    /// Gibt den Pfad zur Lock-Datei zurück (eine schreibende apt-ng-Instanz zur Zeit)
+    pub fn lock_path(&self) -> PathBuf {
+        self.paths.state_dir.join("lock")
+    }
+    
+    /// Gibt den Pfad zum Zeitstempel der letzten Suche nach apt-ng-Updates zurück
+    pub fn update_check_stamp_path(&self) -> PathBuf {
+        self.paths.state_dir.join("last-update-check")
+    }
+    
+    /// Gibt den Pfad zum zuletzt gefundenen, noch nicht gemeldeten apt-ng-Release zurück
+    pub fn update_notice_path(&self) -> PathBuf {
+        self.paths.state_dir.join("available-update")
+    }
+    
+    /// Gibt den Pfad zum Cache der installierten Systempakete (dpkg) für den Solver zurück
+    pub fn system_snapshot_path(&self) -> PathBuf {
+        self.paths.state_dir.join("system-packages.json")
+    }
+    
+    /// Gibt den Pfad zum Snapshot der Sicherheitshinweise zurück (für Offline-Audits)
+    pub fn advisory_snapshot_path(&self) -> PathBuf {
+        self.paths.state_dir.join("advisories.json")
+    }
+    
+    /// Gibt die Zeitlimits für den Downloader zurück
+    pub fn download_timeouts(&self) -> crate::downloader::Timeouts {
+        let mut timeouts = crate::downloader::Timeouts::default();
+        if let Some(config) = &self.timeouts {
+            if let Some(secs) = config.connect_timeout {
+                timeouts.connect = std::time::Duration::from_secs(secs);
+            }
+            if let Some(secs) = config.read_timeout {
+                timeouts.read = std::time::Duration::from_secs(secs);
+            }
+            timeouts.overall = config.overall_timeout.map(std::time::Duration::from_secs);
+        }
+        timeouts
+    }
+    
+    /// Maximale Anzahl verfolgter Weiterleitungen (None = Weiterleitungen nicht folgen)
+    pub fn max_redirects(&self) -> Option<usize> {
+        let http = self.http.clone().unwrap_or_default();
+        if !http.follow_redirects.unwrap_or(true) {
+            return None;
+        }
+        Some(http.max_redirects.unwrap_or(crate::downloader::DEFAULT_MAX_REDIRECTS))
+    }
+    
+    /// Obergrenze für entpackte Daten pro Quelle (Schutz vor Kompressionsbomben)
+    pub fn max_decompressed_size(&self) -> u64 {
+        self.limits
+            .as_ref()
+            .and_then(|limits| limits.max_decompressed_size)
+            .unwrap_or(crate::extract::DEFAULT_MAX_DECOMPRESSED_SIZE)
+    }
+    
+    /// Obergrenze für Indexdateien, die vollständig in den Speicher gelesen werden
+    pub fn max_index_size(&self) -> u64 {
+        self.limits
+            .as_ref()
+            .and_then(|limits| limits.max_index_size)
+            .unwrap_or(crate::extract::DEFAULT_MAX_INDEX_SIZE)
+    }
+    
+    /// Setzt das Zeitlimit pro Datei (z.B. aus `--timeout`)
+    pub fn set_overall_timeout(&mut self, secs: u64) {
+        self.timeouts.get_or_insert_with(TimeoutConfig::default).overall_timeout = Some(secs);
+    }
+    
+    /// Setzt die bevorzugte Suite bzw. den Codename (z.B. aus `--target-release`)
+    pub fn set_default_release(&mut self, release: &str) {
+        self.default_release = Some(release.to_string());
+    }
+    
+    /// Ob apt-ng ohne Netzwerk arbeitet (`--offline`): kein `update`, Installation nur aus dem Cache
+    pub fn offline(&self) -> bool {
+        self.offline.unwrap_or(false)
+    }
+    
+    /// Schaltet den Offline-Modus ein (z.B. aus `--offline`)
+    pub fn set_offline(&mut self) {
+        self.offline = Some(true);
+    }
+    
+    /// Ob `update` die Contents-Indizes lädt (`--download-contents` schaltet es zusätzlich ein)
+    pub fn download_contents(&self) -> bool {
+        self.download_contents.unwrap_or(false)
+    }
+    
+    /// Vorgabe für die Signaturprüfung von Repositories ohne `signed-by`/`trusted`
+    ///
+    /// Wie bei apt standardmäßig an, unabhängig davon, wie viele Schlüssel vorhanden sind;
+    /// unsignierte Repositories werden per `trusted=yes` oder `require_signatures = false` zugelassen.
+    pub fn require_signatures(&self) -> bool {
+        self.require_signatures.unwrap_or(true)
+    }
+    
+    /// Ob nach Befehlen auf neue apt-ng-Versionen hingewiesen wird (`--no-update-check` schaltet es ab)
+    pub fn update_check(&self) -> bool {
+        self.update_check.unwrap_or(true)
+    }
+    
+    /// Gibt die Architekturen für `update` zurück; `all` ist immer enthalten
+    pub fn architectures(&self) -> Vec<String> {
+        match &self.architectures {
+            Some(architectures) if !architectures.is_empty() => {
+                crate::system::with_arch_all(architectures.clone())
+            }
+            _ => crate::system::detect_architectures(),
+        }
+    }
+    
+    /// Gibt die Zugangsdaten für Repositories zurück (apt auth.conf.d und Tokens aus der Config)
+    pub fn repo_auth(&self) -> crate::repo::RepoAuth {
+        let tokens: Vec<(String, String)> = self.repos.iter()
+            .filter_map(|repo| repo.token.as_ref().map(|token| (repo.url.clone(), token.clone())))
+            .collect();
+        crate::repo::RepoAuth::load(Path::new("/etc/apt"), &tokens)
+    }
+    
+    /// Gibt die Sandbox-Konfiguration für Maintainer-Skripte zurück (None wenn deaktiviert)
+    pub fn sandbox_config(&self) -> Option<crate::sandbox::SandboxConfig> {
+        let sandbox = self.sandbox.as_ref()?;
+        if !sandbox.enabled {
+            return None;
+        }
+        
+        Some(crate::sandbox::SandboxConfig {
+            enabled: true,
+            network_allowed: sandbox.network_allowed,
+            memory_limit: sandbox.memory_limit,
+            cpu_limit: sandbox.cpu_limit,
+            network_allowed_packages: sandbox.network_allowed_packages.clone(),
+            ..Default::default()
+        })
+    }
+    
+    /// Gibt die konfigurierte maximale Cache-Größe zurück (None = unbegrenzt)
+    pub fn cache_max_size(&self) -> Option<u64> {
+        self.cache.as_ref().and_then(|cache| cache.max_size)
+    }
+    
     /// Gibt den Pfad zum Paket-Cache zurück
     pub fn cache_path(&self) -> &Path {
         &self.paths.cache_dir
     }
     
+    /// Verzeichnis für temporäre Dateien: `paths.tmp_dir`, sonst `<cache_dir>/tmp`
+    ///
+    /// Liegt standardmäßig auf demselben Dateisystem wie der Cache, damit große Pakete
+    /// nicht ein kleines tmpfs füllen und Downloads per `rename` übernommen werden.
+    pub fn tmp_dir(&self) -> PathBuf {
+        self.paths.tmp_dir.clone().unwrap_or_else(|| self.paths.cache_dir.join("tmp"))
+    }
+    
     /// Gibt den Pfad zum Trusted-Keys-Verzeichnis zurück
-    #[allow(dead_code)]
     pub fn trusted_keys_dir(&self) -> &Path {
         &self.paths.trusted_keys_dir
     }
+    
+    /// Gibt die OpenPGP-Schlüsselbunde für Repository-Signaturen zurück
+    ///
+    /// Das eigene Trusted-Keys-Verzeichnis darf neben `*.pub` auch `*.gpg`/`*.asc`
+    /// enthalten; dazu kommen apts Schlüsselbunde und `keyrings.paths`.
+    pub fn keyring_paths(&self) -> Vec<PathBuf> {
+        let keyrings = self.keyrings.clone().unwrap_or_default();
+        let mut paths = vec![self.paths.trusted_keys_dir.clone()];
+        if keyrings.apt_keyrings.unwrap_or(true) {
+            paths.extend(APT_KEYRING_PATHS.iter().map(PathBuf::from));
+        }
+        paths.extend(keyrings.paths);
+        paths
+    }
 }
 
 #[cfg(test)]
@@ -147,5 +663,89 @@ mod tests {
         let config = Config::load(Some(&config_path)).unwrap();
         assert_eq!(config.paths.config_dir, PathBuf::from("/etc/apt-ng"));
     }
-}
+    
+    #[test]
+    fn test_download_timeouts() {
+        let mut config: Config = toml::from_str(&format!(
+            "{}\n[timeouts]\nread_timeout = 120\n",
+            toml::to_string(&Config::default()).unwrap()
+        )).unwrap();
+        
+        let timeouts = config.download_timeouts();
+        assert_eq!(timeouts.read, std::time::Duration::from_secs(120));
+        assert_eq!(timeouts.connect, std::time::Duration::from_secs(10));
+        // Ohne feste Vorgabe wächst das Limit mit der Dateigröße
+        assert!(timeouts.overall_for(Some(500 * 1024 * 1024)) > timeouts.overall_for(Some(1024)));
+        
+        config.set_overall_timeout(300);
+        assert_eq!(config.download_timeouts().overall_for(Some(500 * 1024 * 1024)), std::time::Duration::from_secs(300));
+        
+        assert_eq!(config.max_redirects(), Some(crate::downloader::DEFAULT_MAX_REDIRECTS));
+        config.http = Some(HttpConfig { follow_redirects: None, max_redirects: Some(3) });
+        assert_eq!(config.max_redirects(), Some(3));
+        config.http = Some(HttpConfig { follow_redirects: Some(false), max_redirects: Some(3) });
+        assert_eq!(config.max_redirects(), None);
+    }
+    
+    #[test]
+    fn test_unknown_keys_and_effective_settings() {
+        let content = r#"
+typo_key = 1
+
+[paths]
+config_dir = "/etc/apt-ng"
+state_dir = "/var/lib/apt-ng"
+cache_dir = "/var/cache/apt-ng"
+trusted_keys_dir = "/etc/apt-ng/trusted.gpg.d"
+
+[parallelism]
+solver_job = 2
+download_jobs = 3
 
+[[repos]]
+url = "https://deb.example.org"
+priority = 1
+enabled = true
+tokn = "x"
+token = "s3cret"
+"#;
+        let mut unknown = Config::unknown_keys(content).unwrap();
+        unknown.sort();
+        assert_eq!(unknown, ["parallelism.solver_job", "repos[0].tokn", "typo_key"]);
+        assert!(Config::unknown_keys(&toml::to_string(&Config::default()).unwrap()).unwrap().is_empty());
+        
+        let config: Config = toml::from_str(content).unwrap();
+        let settings: std::collections::HashMap<String, String> = config.effective_settings(Some(5)).unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(settings["paths.state_dir"], "\"/var/lib/apt-ng\"");
+        assert_eq!(settings["parallelism.download_jobs"], "3");
+        // --jobs hat Vorrang vor der Datei
+        assert_eq!(settings["effective.download_jobs"], "5");
+        assert_eq!(settings["repos[0].url"], "\"https://deb.example.org\"");
+        assert_eq!(settings["effective.index_db"], "\"/var/lib/apt-ng/index.db\"");
+        assert_eq!(settings["effective.tmp_dir"], "\"/var/cache/apt-ng/tmp\"");
+        assert_eq!(settings["effective.max_decompressed_size"], "4294967296");
+        assert_eq!(settings["effective.max_index_size"], "536870912");
+        // Tokens werden nie ausgegeben
+        assert_eq!(settings["repos[0].token"], "\"<redacted>\"");
+        assert!(settings.values().all(|value| !value.contains("s3cret")));
+    }
+    
+    #[test]
+    fn test_jobs_are_clamped_to_cap() {
+        let mut config = Config::default();
+        
+        let jobs = config.effective_jobs(Some(10_000));
+        assert_eq!(jobs, Jobs { download: DEFAULT_JOB_CAP, solver: DEFAULT_JOB_CAP });
+        assert_eq!(config.effective_jobs(Some(0)).download, 1);
+        
+        config.parallelism = Some(ParallelismConfig {
+            download_jobs: Some(12),
+            solver_jobs: Some(2),
+            max_jobs: Some(8),
+        });
+        assert_eq!(config.effective_jobs(None), Jobs { download: 8, solver: 2 });
+        assert_eq!(config.effective_jobs(Some(4)), Jobs { download: 4, solver: 4 });
+    }
+}
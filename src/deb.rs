@@ -0,0 +1,292 @@
+//! Eingebauter Leser für `.deb`-Dateien
+//!
+//! Eine `.deb`-Datei ist ein `ar`-Archiv mit `debian-binary`, `control.tar.*` (Control-Datei und
+//! Maintainer-Skripte) und `data.tar.*` (Paketinhalt). Die tar-Teile sind unkomprimiert oder mit
+//! gzip, xz bzw. zstd komprimiert.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+const AR_MAGIC: &[u8; 8] = b"!<arch>\n";
+const AR_HEADER_LEN: usize = 60;
+
+/// Ein Eintrag des ar-Archivs
+#[derive(Debug, Clone)]
+struct ArMember {
+    name: String,
+    offset: u64,
+    size: u64,
+}
+
+/// Eine geöffnete `.deb`-Datei
+#[derive(Debug)]
+pub struct DebArchive {
+    path: PathBuf,
+    members: Vec<ArMember>,
+}
+
+impl DebArchive {
+    /// Öffnet die Datei und prüft ar-Kopf und `debian-binary`
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let members = read_ar_members(&mut file)
+            .with_context(|| format!("{} is not a valid .deb archive", path.display()))?;
+        let deb = DebArchive { path: path.to_path_buf(), members };
+
+        let mut version = String::new();
+        deb.member_reader("debian-binary")?.read_to_string(&mut version)?;
+        if !version.trim_start().starts_with("2.") {
+            anyhow::bail!("{} has unsupported .deb format version {:?}", path.display(), version.trim());
+        }
+
+        Ok(deb)
+    }
+
+    /// Inhalt der Control-Datei (`DEBIAN/control`)
+    pub fn control(&self) -> Result<String> {
+        let mut archive = self.tar("control.tar")?;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.into_owned();
+            if path.strip_prefix(".").unwrap_or(&path) == Path::new("control") {
+                let mut control = String::new();
+                entry.read_to_string(&mut control)?;
+                return Ok(control);
+            }
+        }
+        anyhow::bail!("{} has no control file", self.path.display())
+    }
+
+    /// Pfade im Datenteil in Archivreihenfolge (wie sie `dpkg-deb -c` ausgibt)
+    pub fn data_paths(&self) -> Result<Vec<String>> {
+        let mut archive = self.tar("data.tar")?;
+        let mut paths = Vec::new();
+        for entry in archive.entries()? {
+            paths.push(String::from_utf8_lossy(&entry?.path_bytes()).into_owned());
+        }
+        Ok(paths)
+    }
+
+    /// Entpackt Control-Datei und Maintainer-Skripte nach `dest` (wie `dpkg-deb -e`)
+    pub fn extract_control(&self, dest: &Path) -> Result<()> {
+        self.unpack("control.tar", dest)
+    }
+
+    /// Entpackt den Paketinhalt nach `dest` (wie `dpkg-deb -x`)
+    pub fn extract_data(&self, dest: &Path) -> Result<()> {
+        self.unpack("data.tar", dest)
+    }
+
+    fn unpack(&self, prefix: &str, dest: &Path) -> Result<()> {
+        let mut archive = self.tar(prefix)?;
+        archive.set_preserve_permissions(true);
+        archive.set_overwrite(true);
+        crate::extract::unpack_tar(&mut archive, dest)
+            .with_context(|| format!("Failed to extract {} from {}", prefix, self.path.display()))
+    }
+
+    /// tar-Leser für `control.tar.*` bzw. `data.tar.*` mit passendem, größenbegrenztem Dekompressor
+    fn tar(&self, prefix: &str) -> Result<tar::Archive<Box<dyn Read>>> {
+        let member = self
+            .members
+            .iter()
+            .find(|m| m.name == prefix || m.name.strip_prefix(prefix).is_some_and(|ext| ext.starts_with('.')))
+            .with_context(|| format!("{} has no {}.* member", self.path.display(), prefix))?;
+        let reader = self.member_reader(&member.name)?;
+
+        let decoder: Box<dyn Read> = match &member.name[prefix.len()..] {
+            "" => reader,
+            ".gz" => Box::new(flate2::read::GzDecoder::new(reader)),
+            ".xz" => Box::new(xz2::read::XzDecoder::new(reader)),
+            ".zst" => Box::new(zstd::stream::read::Decoder::new(reader)?),
+            other => anyhow::bail!(
+                "{} uses unsupported compression {:?} for {}",
+                self.path.display(),
+                other.trim_start_matches('.'),
+                prefix
+            ),
+        };
+        let source_name = format!("{} in {}", member.name, self.path.display());
+        Ok(tar::Archive::new(Box::new(crate::extract::LimitedReader::with_default_limit(decoder, source_name))))
+    }
+
+    /// Liest genau den Bereich eines ar-Eintrags
+    fn member_reader(&self, name: &str) -> Result<Box<dyn Read>> {
+        let member = self
+            .members
+            .iter()
+            .find(|m| m.name == name)
+            .with_context(|| format!("{} has no {} member", self.path.display(), name))?;
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(member.offset))?;
+        Ok(Box::new(file.take(member.size)))
+    }
+}
+
+/// Liest die Eintragsköpfe eines ar-Archivs (BSD-/GNU-Namen ohne Erweiterungstabellen)
+fn read_ar_members(file: &mut File) -> Result<Vec<ArMember>> {
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic).context("file too short")?;
+    if &magic != AR_MAGIC {
+        anyhow::bail!("missing ar signature");
+    }
+
+    let file_len = file.metadata()?.len();
+    let mut members = Vec::new();
+    let mut offset = AR_MAGIC.len() as u64;
+    while offset + AR_HEADER_LEN as u64 <= file_len {
+        let mut header = [0u8; AR_HEADER_LEN];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut header)?;
+        if &header[58..60] != b"`\n" {
+            anyhow::bail!("corrupt ar header at offset {}", offset);
+        }
+
+        let name = String::from_utf8_lossy(&header[0..16]).trim_end().trim_end_matches('/').to_string();
+        let size: u64 = String::from_utf8_lossy(&header[48..58])
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid size for ar member {}", name))?;
+        let data_offset = offset + AR_HEADER_LEN as u64;
+        if data_offset + size > file_len {
+            anyhow::bail!("ar member {} is truncated", name);
+        }
+
+        members.push(ArMember { name, offset: data_offset, size });
+        // Einträge sind auf gerade Offsets ausgerichtet
+        offset = data_offset + size + size % 2;
+    }
+
+    Ok(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn tar_with(files: &[(&str, &[u8], u32)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data, mode) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(*mode);
+            header.set_cksum();
+            builder.append_data(&mut header, path, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn ar_member(out: &mut Vec<u8>, name: &str, data: &[u8]) {
+        writeln!(out, "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`", format!("{}/", name), 0, 0, 0, 100644, data.len()).unwrap();
+        out.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            out.push(b'\n');
+        }
+    }
+
+    /// Baut eine minimale .deb mit gzip-komprimiertem Control- und xz-komprimiertem Datenteil
+    fn build_deb(path: &Path) {
+        let control = tar_with(&[
+            ("./control", b"Package: hello\nVersion: 1.0\nArchitecture: all\n", 0o644),
+            ("./postinst", b"#!/bin/sh\nexit 0\n", 0o755),
+        ]);
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&control).unwrap();
+
+        let data = tar_with(&[("./usr/bin/hello", b"#!/bin/sh\necho hello\n", 0o755)]);
+        let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+        xz.write_all(&data).unwrap();
+
+        let mut deb = AR_MAGIC.to_vec();
+        ar_member(&mut deb, "debian-binary", b"2.0\n");
+        ar_member(&mut deb, "control.tar.gz", &gz.finish().unwrap());
+        ar_member(&mut deb, "data.tar.xz", &xz.finish().unwrap());
+        std::fs::write(path, deb).unwrap();
+    }
+
+    #[test]
+    fn test_read_deb() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let deb_path = temp_dir.path().join("hello_1.0_all.deb");
+        build_deb(&deb_path);
+
+        let deb = DebArchive::open(&deb_path).unwrap();
+        assert!(deb.control().unwrap().starts_with("Package: hello\n"));
+
+        let control_dir = temp_dir.path().join("control");
+        deb.extract_control(&control_dir).unwrap();
+        assert!(control_dir.join("postinst").exists());
+
+        let root = temp_dir.path().join("root");
+        deb.extract_data(&root).unwrap();
+        let hello = root.join("usr/bin/hello");
+        assert_eq!(std::fs::read_to_string(&hello).unwrap(), "#!/bin/sh\necho hello\n");
+        assert_eq!(std::fs::metadata(&hello).unwrap().permissions().mode() & 0o777, 0o755);
+
+        // Ohne ar-Signatur ist es keine .deb-Datei
+        std::fs::write(&deb_path, b"not a deb").unwrap();
+        assert!(DebArchive::open(&deb_path).is_err());
+    }
+
+    #[test]
+    fn test_data_paths_match_dpkg_deb() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::process::Command;
+
+        if Command::new("dpkg-deb").arg("--version").output().is_err() {
+            eprintln!("Skipping: dpkg-deb not available");
+            return;
+        }
+
+        // Fixture mit Verzeichnissen, ausführbarer Datei, Symlink und Maintainer-Skript
+        let temp_dir = TempDir::new().unwrap();
+        let pkg_dir = temp_dir.path().join("pkg");
+        std::fs::create_dir_all(pkg_dir.join("DEBIAN")).unwrap();
+        std::fs::create_dir_all(pkg_dir.join("usr/bin")).unwrap();
+        std::fs::create_dir_all(pkg_dir.join("usr/share/doc/hello")).unwrap();
+        std::fs::write(
+            pkg_dir.join("DEBIAN/control"),
+            "Package: hello\nVersion: 1.0\nArchitecture: all\nMaintainer: Test <test@example.com>\nDescription: fixture\n",
+        ).unwrap();
+        std::fs::write(pkg_dir.join("DEBIAN/postinst"), "#!/bin/sh\nexit 0\n").unwrap();
+        std::fs::set_permissions(pkg_dir.join("DEBIAN/postinst"), std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(pkg_dir.join("usr/bin/hello"), "#!/bin/sh\necho hello\n").unwrap();
+        std::fs::set_permissions(pkg_dir.join("usr/bin/hello"), std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::os::unix::fs::symlink("hello", pkg_dir.join("usr/bin/hi")).unwrap();
+        std::fs::write(pkg_dir.join("usr/share/doc/hello/copyright"), "public domain\n").unwrap();
+
+        let deb_path = temp_dir.path().join("hello_1.0_all.deb");
+        let status = Command::new("dpkg-deb")
+            .arg("--build")
+            .arg("--root-owner-group")
+            .arg(&pkg_dir)
+            .arg(&deb_path)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success());
+
+        let listing = Command::new("dpkg-deb").arg("-c").arg(&deb_path).output().unwrap();
+        assert!(listing.status.success());
+        let expected: Vec<String> = String::from_utf8_lossy(&listing.stdout)
+            .lines()
+            .filter_map(|line| line.split_whitespace().nth(5))
+            .map(|path| path.to_string())
+            .collect();
+
+        let deb = DebArchive::open(&deb_path).unwrap();
+        assert_eq!(deb.data_paths().unwrap(), expected);
+        assert!(deb.control().unwrap().contains("Package: hello"));
+
+        let root = temp_dir.path().join("root");
+        deb.extract_data(&root).unwrap();
+        assert_eq!(std::fs::read_link(root.join("usr/bin/hi")).unwrap(), Path::new("hello"));
+        assert_eq!(std::fs::read_to_string(root.join("usr/share/doc/hello/copyright")).unwrap(), "public domain\n");
+    }
+}
@@ -6,12 +6,10 @@ use hex;
 use xdelta3::decode;
 
 /// Applies delta patches to reconstruct files
-#[allow(dead_code)]
 pub struct DeltaApplier;
 
 impl DeltaApplier {
     /// Apply delta to reconstruct target file
-    #[allow(dead_code)]
     pub fn apply_delta(
         base_file: &Path,
         delta_file: &Path,
@@ -78,7 +76,6 @@ impl DeltaApplier {
     }
     
     /// Verify delta can be applied to base file
-    #[allow(dead_code)]
     pub fn verify_delta_applicable(
         base_file: &Path,
         metadata: &DeltaMetadata,
@@ -6,13 +6,11 @@ use hex;
 use xdelta3::encode;
 
 /// Calculates delta between two package versions
-#[allow(dead_code)]
 pub struct DeltaCalculator;
 
 impl DeltaCalculator {
     /// Calculate delta between two files
     /// Returns delta data and metadata
-    #[allow(dead_code)]
     pub fn calculate_delta(
         from_file: &Path,
         to_file: &Path,
@@ -99,7 +97,6 @@ impl DeltaCalculator {
     
     /// Check if delta is available for a package version pair
     /// This checks if a delta file exists in the repository
-    #[allow(dead_code)]
     pub fn delta_available(
         _package_name: &str,
         _from_version: &str,
@@ -2,7 +2,6 @@ use serde::{Deserialize, Serialize};
 
 /// Metadata for a delta package
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[allow(dead_code)]
 pub struct DeltaMetadata {
     pub from_version: String,
     pub to_version: String,
@@ -15,18 +14,86 @@ pub struct DeltaMetadata {
 
 impl DeltaMetadata {
     /// Calculate size savings percentage
-    #[allow(dead_code)]
     pub fn savings_percentage(&self) -> f64 {
         if self.full_size == 0 {
             return 0.0;
         }
-        ((self.full_size - self.delta_size) as f64 / self.full_size as f64) * 100.0
+        (self.bytes_saved() as f64 / self.full_size as f64) * 100.0
+    }
+    
+    /// Bytes saved compared to downloading the full package
+    pub fn bytes_saved(&self) -> u64 {
+        self.full_size.saturating_sub(self.delta_size)
+    }
+    
+    /// File name of a delta in a repository's `deltas/` directory; its metadata is
+    /// published next to it with an additional `.json` extension
+    pub fn file_name(package: &str, from_version: &str, to_version: &str, arch: &str) -> String {
+        // Like pool file names, delta file names carry no epoch
+        let without_epoch = |version: &str| version.split_once(':').map_or(version, |(_, v)| v).to_string();
+        format!("{}_{}_{}_{}.delta", package, without_epoch(from_version), without_epoch(to_version), arch)
     }
     
     /// Check if delta is worth using (e.g., saves at least 10%)
-    #[allow(dead_code)]
     pub fn is_worthwhile(&self) -> bool {
         self.savings_percentage() >= 10.0
     }
 }
 
+
+/// Bandwidth saved by delta updates, per transaction or accumulated in the index
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeltaSavings {
+    /// Number of packages fetched as delta
+    pub packages: u64,
+    /// Bytes not downloaded thanks to deltas
+    pub bytes_saved: u64,
+}
+
+impl DeltaSavings {
+    /// Record a delta that was applied instead of downloading the full package
+    pub fn record(&mut self, metadata: &DeltaMetadata) {
+        self.packages += 1;
+        self.bytes_saved += metadata.bytes_saved();
+    }
+    
+    /// Add savings of another transaction
+    pub fn add(&mut self, other: &DeltaSavings) {
+        self.packages += other.packages;
+        self.bytes_saved += other.bytes_saved;
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.packages == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn metadata(delta_size: u64, full_size: u64) -> DeltaMetadata {
+        DeltaMetadata {
+            from_version: "1.0".to_string(),
+            to_version: "1.1".to_string(),
+            package_name: "hello".to_string(),
+            delta_size,
+            full_size,
+            algorithm: "xdelta3".to_string(),
+            checksum: String::new(),
+        }
+    }
+    
+    #[test]
+    fn test_delta_savings() {
+        let mut savings = DeltaSavings::default();
+        assert!(savings.is_empty());
+        
+        savings.record(&metadata(100, 1000));
+        // A delta larger than the package saves nothing (and must not underflow)
+        savings.record(&metadata(2000, 1000));
+        assert_eq!(savings, DeltaSavings { packages: 2, bytes_saved: 900 });
+        assert_eq!(metadata(2000, 1000).savings_percentage(), 0.0);
+        assert!(metadata(100, 1000).is_worthwhile());
+    }
+}
@@ -9,5 +9,5 @@ pub use calculator::DeltaCalculator;
 pub use applier::DeltaApplier;
 // DeltaMetadata is exported for public API but may not be directly imported
 #[allow(unused_imports)]
-pub use format::DeltaMetadata;
+pub use format::{DeltaMetadata, DeltaSavings};
 
@@ -1,13 +1,255 @@
-use anyhow::Result;
+use crate::error::{AptNgError, Result};
 use reqwest::Client;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tokio::io::{AsyncWriteExt, AsyncSeekExt};
 use futures::stream::{self, StreamExt};
-use std::time::Instant;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use indicatif::{MultiProgress, ProgressBar};
+use crate::repo::{Credentials, RepoAuth};
+
+/// Maximale Dauer eines einzelnen Mirror-Probes
+pub const PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Höchstens so viele Bytes lädt ein Mirror-Probe für die Throughput-Messung
+const PROBE_MAX_BYTES: u64 = 1024 * 1024;
+
+/// Höchstzahl an Downloadversuchen, wenn die Checksumme nicht stimmt
+pub const CHECKSUM_ATTEMPTS: usize = 3;
+
+/// Maximale Anzahl verfolgter HTTP-Weiterleitungen pro Request (schützt vor Schleifen)
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// Ergebnis eines gemessenen Downloads
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadMetrics {
+    /// Angefragte URL
+    pub url: String,
+    /// URL nach allen Weiterleitungen, z.B. der CDN-Host hinter `deb.debian.org`
+    pub resolved_url: String,
+    pub rtt_ms: u64,
+    pub throughput: u64,
+}
+
+/// Lokaler Pfad für `file://`- und `copy://`-URLs (lokale Repositories), sonst None
+pub fn local_path(url: &str) -> Option<PathBuf> {
+    url.strip_prefix("file://")
+        .or_else(|| url.strip_prefix("copy://"))
+        .map(PathBuf::from)
+}
+
+/// Angenommener Mindestdurchsatz, aus dem das Zeitlimit pro Datei abgeleitet wird
+const MIN_THROUGHPUT_BPS: u64 = 64 * 1024;
+
+/// Zeitlimits für Downloads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeouts {
+    /// Verbindungsaufbau
+    pub connect: std::time::Duration,
+    /// Maximale Wartezeit auf die Antwort bzw. den nächsten Datenblock
+    pub read: std::time::Duration,
+    /// Feste Obergrenze pro Datei; `None` leitet sie aus der Dateigröße ab
+    pub overall: Option<std::time::Duration>,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            connect: std::time::Duration::from_secs(10),
+            read: std::time::Duration::from_secs(30),
+            overall: None,
+        }
+    }
+}
+
+impl Timeouts {
+    /// Zeitlimit für eine Datei: die feste Vorgabe oder 60s plus die Übertragungszeit
+    /// bei `MIN_THROUGHPUT_BPS`
+    pub fn overall_for(&self, size: Option<u64>) -> std::time::Duration {
+        self.overall.unwrap_or_else(|| {
+            std::time::Duration::from_secs(60 + size.unwrap_or(0) / MIN_THROUGHPUT_BPS)
+        })
+    }
+}
+
+/// Zeitfenster, über das `SpeedTracker` die Übertragungsrate mittelt
+pub const SPEED_WINDOW: Duration = Duration::from_secs(5);
+
+/// Abstand zwischen zwei Aktualisierungen der Geschwindigkeitsanzeige
+const PROGRESS_UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Misst die Übertragungsrate als gleitenden Durchschnitt
+///
+/// Kann von mehreren Downloads gleichzeitig gefüttert werden (`&self`), sodass die
+/// Rate den gemeinsamen Durchsatz aller parallelen Worker widerspiegelt.
+pub struct SpeedTracker {
+    window: Duration,
+    state: Mutex<SpeedState>,
+}
+
+struct SpeedState {
+    started: Instant,
+    total: u64,
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl SpeedTracker {
+    pub fn new() -> Self {
+        Self::with_window(SPEED_WINDOW)
+    }
+    
+    /// Tracker mit eigenem Mittelungsfenster
+    pub fn with_window(window: Duration) -> Self {
+        SpeedTracker {
+            window,
+            state: Mutex::new(SpeedState {
+                started: Instant::now(),
+                total: 0,
+                samples: VecDeque::new(),
+            }),
+        }
+    }
+    
+    /// Verbucht `bytes` übertragene Bytes
+    pub fn add(&self, bytes: u64) {
+        self.add_at(Instant::now(), bytes);
+    }
+    
+    fn add_at(&self, at: Instant, bytes: u64) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.total += bytes;
+        state.samples.push_back((at, bytes));
+        while let Some(&(oldest, _)) = state.samples.front() {
+            if at.duration_since(oldest) > self.window {
+                state.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+    
+    /// Insgesamt verbuchte Bytes
+    pub fn total(&self) -> u64 {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).total
+    }
+    
+    /// Durchschnittliche Rate in Bytes/s über das Zeitfenster (bzw. seit dem Start, falls kürzer)
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec_at(Instant::now())
+    }
+    
+    fn bytes_per_sec_at(&self, now: Instant) -> u64 {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let span = now.duration_since(state.started).min(self.window);
+        let bytes: u64 = state.samples.iter()
+            .filter(|(at, _)| now.duration_since(*at) <= self.window)
+            .map(|(_, bytes)| bytes)
+            .sum();
+        if span.as_millis() == 0 {
+            0
+        } else {
+            (bytes as u128 * 1000 / span.as_millis()) as u64
+        }
+    }
+    
+    /// Geschätzte Restdauer für `remaining` Bytes bei der aktuellen Rate
+    pub fn eta(&self, remaining: u64) -> Option<Duration> {
+        match self.bytes_per_sec() {
+            0 => None,
+            speed => Some(Duration::from_secs(remaining.div_ceil(speed))),
+        }
+    }
+}
+
+impl Default for SpeedTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Gesamtfortschritt eines Batches paralleler Downloads
+///
+/// Zeigt unter den Balken der einzelnen Dateien einen Gesamtbalken mit der
+/// gemeinsamen Rate aller Worker und der geschätzten Restdauer.
+pub struct BatchProgress {
+    multi: MultiProgress,
+    total: ProgressBar,
+    speed: SpeedTracker,
+}
+
+impl BatchProgress {
+    /// Gesamtbalken für `total_bytes` noch herunterzuladende Bytes
+    pub fn new(total_bytes: u64) -> Self {
+        let multi = MultiProgress::new();
+        if total_bytes == 0 {
+            // Alles liegt bereits im Cache
+            multi.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+        }
+        let total = multi.add(crate::output::Output::progress_bar(total_bytes));
+        BatchProgress { multi, total, speed: SpeedTracker::new() }
+    }
+    
+    /// Hängt den Balken einer einzelnen Datei über dem Gesamtbalken ein
+    fn add_file_bar(&self, bar: ProgressBar) -> ProgressBar {
+        self.multi.insert_before(&self.total, bar)
+    }
+    
+    /// Verbucht übertragene Bytes und aktualisiert Rate und ETA
+    fn record(&self, bytes: u64) {
+        self.speed.add(bytes);
+        self.total.inc(bytes);
+        
+        // Pakete aus dem Cache waren nicht eingeplant, werden aber ggf. neu geladen
+        let position = self.total.position();
+        let length = self.total.length().unwrap_or(0).max(position);
+        self.total.set_length(length);
+        
+        let eta = self.speed.eta(length - position)
+            .map(format_eta)
+            .unwrap_or_else(|| "--:--".to_string());
+        self.total.set_message(format!("Total: {} avg, ETA {}", format_speed(self.speed.bytes_per_sec()), eta));
+    }
+    
+    /// Schließt den Gesamtbalken mit der Durchschnittsrate ab
+    pub fn finish(&self) {
+        let elapsed = self.total.elapsed().as_millis().max(1);
+        let average = (self.speed.total() as u128 * 1000 / elapsed) as u64;
+        self.total.finish_with_message(format!("Done ({} avg)", format_speed(average)));
+    }
+}
+
+/// Formatiert eine Restdauer als `m:ss` bzw. `h:mm:ss`
+pub fn format_eta(eta: Duration) -> String {
+    let secs = eta.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Format download speed as human-readable string
+pub fn format_speed(bytes_per_sec: u64) -> String {
+    if bytes_per_sec >= 1024 * 1024 {
+        format!("{:.2} MB/s", bytes_per_sec as f64 / (1024.0 * 1024.0))
+    } else if bytes_per_sec >= 1024 {
+        format!("{:.2} KB/s", bytes_per_sec as f64 / 1024.0)
+    } else {
+        format!("{} B/s", bytes_per_sec)
+    }
+}
 
 pub struct Downloader {
     pub client: Client,
     max_parallel: usize,
+    auth: RepoAuth,
+    timeouts: Timeouts,
+    /// Verfolgte Weiterleitungen; `None` folgt keiner
+    max_redirects: Option<usize>,
+    batch: Option<Arc<BatchProgress>>,
+    /// Offline-Modus: jeder HTTP-Request schlägt sofort fehl, lokale Repositories bleiben lesbar
+    offline: bool,
 }
 
 impl Downloader {
@@ -30,10 +272,31 @@ impl Downloader {
     /// 
     /// Falls HTTP/3 nicht verfügbar ist, fällt der Client automatisch auf HTTP/2 oder HTTP/1.1 zurück.
     pub fn new_with_http3_fallback(max_parallel: usize, _try_http3: bool) -> Result<Self> {
+        let timeouts = Timeouts::default();
+        let max_redirects = Some(DEFAULT_MAX_REDIRECTS);
+        
+        Ok(Downloader {
+            client: Self::build_client(&timeouts, max_redirects)?,
+            max_parallel,
+            auth: RepoAuth::default(),
+            timeouts,
+            max_redirects,
+            batch: None,
+            offline: false,
+        })
+    }
+    
+    fn build_client(timeouts: &Timeouts, max_redirects: Option<usize>) -> Result<Client> {
+        // HTTP/2 wird automatisch verwendet wenn verfügbar
+        // HTTP/3 kann aktiviert werden wenn reqwest's http3 feature aktiviert ist
+        // Kein Gesamt-Timeout am Client: große Dateien werden über `Timeouts::overall_for` begrenzt
+        let redirect_policy = match max_redirects {
+            Some(max) => reqwest::redirect::Policy::limited(max),
+            None => reqwest::redirect::Policy::none(),
+        };
         let builder = Client::builder()
-            // HTTP/2 wird automatisch verwendet wenn verfügbar
-            // HTTP/3 kann aktiviert werden wenn reqwest's http3 feature aktiviert ist
-            .timeout(std::time::Duration::from_secs(30)); // 30 Sekunden Timeout
+            .connect_timeout(timeouts.connect)
+            .redirect(redirect_policy);
         
         // Note: HTTP/3 support in reqwest is currently unstable
         // To enable it:
@@ -41,19 +304,125 @@ impl Downloader {
         // 2. Set RUSTFLAGS='--cfg reqwest_unstable' environment variable
         // 3. reqwest will automatically try HTTP/3 if server supports it
         
-        let client = builder.build()?;
-        
-        Ok(Downloader {
-            client,
-            max_parallel,
-        })
+        Ok(builder.build()?)
+    }
+    
+    /// Verwendet die Zugangsdaten für Repositories, die Authentifizierung benötigen
+    pub fn with_auth(mut self, auth: RepoAuth) -> Self {
+        self.auth = auth;
+        self
+    }
+    
+    /// Verbietet alle HTTP-Requests (`--offline`); `file://`- und `copy://`-URLs funktionieren weiter
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+    
+    /// Fehler statt eines Requests im Offline-Modus, damit nichts auf ein fehlendes Netz wartet
+    fn ensure_online(&self, url: &str) -> Result<()> {
+        if self.offline {
+            return Err(AptNgError::Offline { url: url.to_string() });
+        }
+        Ok(())
+    }
+    
+    /// Meldet alle übertragenen Bytes zusätzlich an einen gemeinsamen Gesamtfortschritt
+    pub fn with_batch_progress(mut self, batch: Arc<BatchProgress>) -> Self {
+        self.batch = Some(batch);
+        self
+    }
+    
+    /// Fortschrittsbalken für eine einzelne Datei (unter dem Gesamtbalken, falls vorhanden)
+    fn file_progress_bar(&self, size: u64) -> ProgressBar {
+        let bar = crate::output::Output::progress_bar(size);
+        match &self.batch {
+            Some(batch) => batch.add_file_bar(bar),
+            None => bar,
+        }
+    }
+    
+    /// Verbucht übertragene Bytes im Gesamtfortschritt
+    fn record_transferred(&self, bytes: u64) {
+        if let Some(batch) = &self.batch {
+            batch.record(bytes);
+        }
+    }
+    
+    /// Verwendet die angegebenen Zeitlimits (baut den HTTP-Client neu)
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Result<Self> {
+        self.client = Self::build_client(&timeouts, self.max_redirects)?;
+        self.timeouts = timeouts;
+        Ok(self)
+    }
+    
+    /// Folgt höchstens `max_redirects` Weiterleitungen, bei `None` keiner (baut den HTTP-Client neu)
+    pub fn with_redirects(mut self, max_redirects: Option<usize>) -> Result<Self> {
+        self.client = Self::build_client(&self.timeouts, max_redirects)?;
+        self.max_redirects = max_redirects;
+        Ok(self)
+    }
+    
+    /// Sendet einen Request und wartet höchstens das Lese-Zeitlimit auf die Antwort
+    async fn send(&self, request: reqwest::RequestBuilder, url: &str) -> Result<reqwest::Response> {
+        self.ensure_online(url)?;
+        match tokio::time::timeout(self.timeouts.read, request.send()).await {
+            Ok(response) => Ok(response?),
+            Err(_) => Err(self.read_timeout_error(url)),
+        }
+    }
+    
+    /// Liest den nächsten Datenblock; bricht ab, wenn der Server zu lange schweigt
+    async fn next_chunk(&self, response: &mut reqwest::Response, url: &str) -> Result<Option<bytes::Bytes>> {
+        match tokio::time::timeout(self.timeouts.read, response.chunk()).await {
+            Ok(chunk) => Ok(chunk?),
+            Err(_) => Err(self.read_timeout_error(url)),
+        }
+    }
+    
+    fn read_timeout_error(&self, url: &str) -> AptNgError {
+        AptNgError::Timeout { what: format!("Reading {}", url), seconds: self.timeouts.read.as_secs() }
+    }
+    
+    /// Baut einen Request und hängt passende Zugangsdaten an
+    fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, url);
+        match self.auth.credentials_for(url) {
+            Some(Credentials::Basic { login, password }) => request.basic_auth(login, Some(password)),
+            Some(Credentials::Bearer(token)) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+    
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::GET, url)
+    }
+    
+    fn head(&self, url: &str) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::HEAD, url)
+    }
+    
+    /// Fehler für eine nicht erfolgreiche Antwort; 401/403 mit Hinweis auf die Zugangsdaten
+    fn http_error(&self, url: &str, status: reqwest::StatusCode, context: &str) -> AptNgError {
+        let has_credentials = self.auth.credentials_for(url).is_some();
+        match status {
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN if has_credentials => {
+                AptNgError::AuthRejected { url: url.to_string(), status: status.as_u16() }
+            }
+            reqwest::StatusCode::UNAUTHORIZED => AptNgError::AuthRequired { url: url.to_string() },
+            _ if status.is_redirection() => AptNgError::Http {
+                url: url.to_string(),
+                status: status.as_u16(),
+                context: format!("{} (redirect not followed, see http.follow_redirects)", context),
+            },
+            _ => AptNgError::Http { url: url.to_string(), status: status.as_u16(), context: context.to_string() },
+        }
     }
     
     /// Prüft, ob HTTP/3 QUIC für eine URL verfügbar ist
     /// 
     /// Diese Methode versucht eine Verbindung mit HTTP/3 herzustellen.
     /// Falls HTTP/3 nicht unterstützt wird, gibt sie false zurück.
-    #[allow(dead_code)]
     pub async fn check_http3_support(&self, url: &str) -> bool {
         // Placeholder: HTTP/3 detection würde hier implementiert werden
         // Aktuell gibt reqwest keine einfache Möglichkeit, das verwendete Protokoll zu prüfen
@@ -61,7 +430,10 @@ impl Downloader {
         
         // Für jetzt: Versuche eine HEAD-Anfrage und prüfe die Antwort
         // Falls HTTP/3 verfügbar ist, würde reqwest es automatisch verwenden (mit http3 feature)
-        if let Ok(response) = self.client.head(url).send().await {
+        if self.offline {
+            return false;
+        }
+        if let Ok(response) = self.head(url).send().await {
             // Prüfe ob Alt-Svc Header vorhanden ist (zeigt HTTP/3 Unterstützung an)
             if let Some(alt_svc) = response.headers().get("alt-svc") {
                 if let Ok(alt_svc_str) = alt_svc.to_str() {
@@ -80,6 +452,26 @@ impl Downloader {
     
     /// Lädt eine Datei von einer URL herunter mit optionaler Checksum-Validierung
     pub async fn download_file_with_checksum(&self, url: &str, dest: &Path, expected_checksum: Option<&str>) -> Result<()> {
+        self.fetch(url, dest, expected_checksum).await
+            .map(|_| ())
+            .inspect_err(|e| tracing::warn!(url, error = %e, "download failed"))
+    }
+    
+    /// Lädt eine Datei herunter und gibt die URL nach allen Weiterleitungen zurück
+    async fn fetch(&self, url: &str, dest: &Path, expected_checksum: Option<&str>) -> Result<String> {
+        tracing::info!(url, dest = %dest.display(), checksum = expected_checksum, "download");
+        
+        // Lokale Repositories werden direkt aus dem Dateisystem kopiert
+        if let Some(source) = local_path(url) {
+            let copied = tokio::fs::copy(&source, dest).await
+                .map_err(|e| anyhow::Error::new(e).context(format!("Failed to read {}", source.display())))?;
+            self.record_transferred(copied);
+            if let Some(expected) = expected_checksum {
+                self.validate_file_checksum(dest, expected).await?;
+            }
+            return Ok(url.to_string());
+        }
+        
         // Check if file already exists (for resume)
         let existing_size = if dest.exists() {
             tokio::fs::metadata(dest).await.map(|m| m.len()).unwrap_or(0)
@@ -88,13 +480,34 @@ impl Downloader {
         };
         
         // Check if server supports range requests
-        let head_response = self.client.head(url).send().await?;
+        let head_response = self.send(self.head(url), url).await?;
+        // Redirectoren (z.B. deb.debian.org) verraten erst hier den ausliefernden Host
+        let resolved_url = head_response.url().to_string();
         let supports_ranges = head_response.headers().contains_key("accept-ranges");
         let content_length = head_response.headers()
             .get("content-length")
             .and_then(|v| v.to_str().ok())
             .and_then(|s| s.parse::<u64>().ok());
         
+        // Zeitlimit pro Datei: fest konfiguriert oder abhängig von der Dateigröße
+        let overall = self.timeouts.overall_for(content_length);
+        let transfer = self.transfer(url, dest, expected_checksum, existing_size, supports_ranges, content_length);
+        match tokio::time::timeout(overall, transfer).await {
+            Ok(result) => result.map(|_| resolved_url),
+            Err(_) => Err(AptNgError::Timeout { what: format!("Download of {}", url), seconds: overall.as_secs() }),
+        }
+    }
+    
+    /// Überträgt eine Datei (Fortsetzen, parallele Chunks oder einfacher Download)
+    async fn transfer(
+        &self,
+        url: &str,
+        dest: &Path,
+        expected_checksum: Option<&str>,
+        existing_size: u64,
+        supports_ranges: bool,
+        content_length: Option<u64>,
+    ) -> Result<()> {
         // Resume download if file exists and server supports ranges
         if existing_size > 0 && supports_ranges {
             if let Some(total_size) = content_length {
@@ -129,51 +542,34 @@ impl Downloader {
         }
         
         // Fallback to regular download
-        let mut response = self.client.get(url).send().await?;
+        let mut response = self.send(self.get(url), url).await?;
         
         if !response.status().is_success() {
-            return Err(anyhow::anyhow!("HTTP error: {}", response.status()));
+            return Err(self.http_error(url, response.status(), "HTTP error"));
         }
         
         // Get content length for progress bar
         let total_size = response.content_length();
-        let progress_bar = if let Some(size) = total_size {
-            Some(crate::output::Output::progress_bar(size))
-        } else {
-            None
-        };
+        let progress_bar = total_size.map(|size| self.file_progress_bar(size));
         
         let mut file = tokio::fs::File::create(dest).await?;
         
+        let speed = SpeedTracker::new();
         let mut downloaded = 0u64;
         let mut last_update = Instant::now();
-        let mut last_downloaded = 0u64;
-        let update_interval = std::time::Duration::from_millis(100); // Update every 100ms
         
-        while let Some(chunk) = response.chunk().await? {
+        while let Some(chunk) = self.next_chunk(&mut response, url).await? {
             file.write_all(&chunk).await?;
             downloaded += chunk.len() as u64;
+            speed.add(chunk.len() as u64);
+            self.record_transferred(chunk.len() as u64);
             
             // Update progress bar with speed
             if let Some(ref pb) = progress_bar {
                 pb.set_position(downloaded);
-                
-                // Calculate and display speed
-                let elapsed = last_update.elapsed();
-                if elapsed >= update_interval {
-                    let bytes_since_update = downloaded - last_downloaded;
-                    let speed = if elapsed.as_secs() > 0 {
-                        bytes_since_update / elapsed.as_secs()
-                    } else if elapsed.as_millis() > 0 {
-                        bytes_since_update * 1000 / elapsed.as_millis() as u64
-                    } else {
-                        0
-                    };
-                    
-                    let speed_str = Self::format_speed(speed);
-                    pb.set_message(format!("{}", speed_str));
+                if last_update.elapsed() >= PROGRESS_UPDATE_INTERVAL {
+                    pb.set_message(format_speed(speed.bytes_per_sec()));
                     last_update = Instant::now();
-                    last_downloaded = downloaded;
                 }
             }
         }
@@ -191,11 +587,12 @@ impl Downloader {
     }
     
     /// Lädt eine Datei herunter und gibt Performance-Metriken zurück
-    pub async fn download_file_with_metrics(&self, url: &str, dest: &Path) -> Result<(u64, u64)> {
+    pub async fn download_file_with_metrics(&self, url: &str, dest: &Path, expected_checksum: Option<&str>) -> Result<DownloadMetrics> {
         use std::time::Instant;
         
         let download_start = Instant::now();
-        self.download_file(url, dest).await?;
+        let resolved_url = self.fetch(url, dest, expected_checksum).await
+            .inspect_err(|e| tracing::warn!(url, error = %e, "download failed"))?;
         let download_time = download_start.elapsed();
         
         let file_size = tokio::fs::metadata(dest).await.map(|m| m.len()).unwrap_or(0);
@@ -207,25 +604,49 @@ impl Downloader {
             0
         };
         let rtt_ms = download_time.as_millis() as u64;
+        tracing::debug!(url, resolved_url = %resolved_url, bytes = file_size, ms = rtt_ms, "download finished");
         
-        Ok((rtt_ms, throughput))
+        Ok(DownloadMetrics { url: url.to_string(), resolved_url, rtt_ms, throughput })
+    }
+    
+    /// Wie `download_file_with_metrics`, lädt bei einer Checksum-Abweichung aber erneut
+    ///
+    /// Die beschädigte Datei wird verworfen und der nächste Versuch geht an die nächste
+    /// URL aus `urls` (Mirrors, beste zuerst, danach wieder von vorn). Erst nach
+    /// `CHECKSUM_ATTEMPTS` fehlgeschlagenen Versuchen wird die Abweichung gemeldet; andere
+    /// Fehler brechen sofort ab. Liefert die Metriken samt der erfolgreichen URL.
+    pub async fn download_with_failover(&self, urls: &[String], dest: &Path, expected_checksum: Option<&str>) -> Result<DownloadMetrics> {
+        let mut attempt = 0;
+        loop {
+            let url = urls.get(attempt % urls.len().max(1))
+                .ok_or_else(|| AptNgError::Other(anyhow::anyhow!("No download URL for {}", dest.display())))?;
+            match self.download_file_with_metrics(url, dest, expected_checksum).await {
+                Ok(metrics) => return Ok(metrics),
+                Err(AptNgError::ChecksumMismatch { .. }) if attempt + 1 < CHECKSUM_ATTEMPTS => {
+                    // validate_file_checksum hat die Datei bereits entfernt
+                    let _ = tokio::fs::remove_file(dest).await;
+                    attempt += 1;
+                    crate::output::Output::warning(&format!(
+                        "Checksum mismatch for {}, retrying from {} (attempt {} of {})",
+                        url, urls[attempt % urls.len()], attempt + 1, CHECKSUM_ATTEMPTS
+                    ));
+                }
+                Err(e) => return Err(e),
+            }
+        }
     }
     
     /// Setzt einen unterbrochenen Download fort
     async fn resume_download(&self, url: &str, dest: &Path, existing_size: u64, total_size: u64) -> Result<()> {
         let range_header = format!("bytes={}-{}", existing_size, total_size - 1);
-        let mut response = self.client
-            .get(url)
-            .header("Range", range_header)
-            .send()
-            .await?;
+        let mut response = self.send(self.get(url).header("Range", range_header), url).await?;
         
         if !response.status().is_success() && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
-            return Err(anyhow::anyhow!("HTTP error for resume: {}", response.status()));
+            return Err(self.http_error(url, response.status(), "HTTP error for resume"));
         }
         
         // Show progress bar for resume
-        let progress_bar = crate::output::Output::progress_bar(total_size);
+        let progress_bar = self.file_progress_bar(total_size);
         progress_bar.set_position(existing_size);
         
         let mut file = tokio::fs::OpenOptions::new()
@@ -234,33 +655,21 @@ impl Downloader {
             .open(dest)
             .await?;
         
+        let speed = SpeedTracker::new();
         let mut downloaded = existing_size;
         let mut last_update = Instant::now();
-        let mut last_downloaded = existing_size;
-        let update_interval = std::time::Duration::from_millis(100);
         
-        while let Some(chunk) = response.chunk().await? {
+        while let Some(chunk) = self.next_chunk(&mut response, url).await? {
             file.write_all(&chunk).await?;
             downloaded += chunk.len() as u64;
+            speed.add(chunk.len() as u64);
+            self.record_transferred(chunk.len() as u64);
             
             // Update progress bar with speed
             progress_bar.set_position(downloaded);
-            
-            let elapsed = last_update.elapsed();
-            if elapsed >= update_interval {
-                let bytes_since_update = downloaded - last_downloaded;
-                let speed = if elapsed.as_secs() > 0 {
-                    bytes_since_update / elapsed.as_secs()
-                } else if elapsed.as_millis() > 0 {
-                    bytes_since_update * 1000 / elapsed.as_millis() as u64
-                } else {
-                    0
-                };
-                
-                let speed_str = Self::format_speed(speed);
-                progress_bar.set_message(format!("{}", speed_str));
+            if last_update.elapsed() >= PROGRESS_UPDATE_INTERVAL {
+                progress_bar.set_message(format_speed(speed.bytes_per_sec()));
                 last_update = Instant::now();
-                last_downloaded = downloaded;
             }
         }
         
@@ -281,7 +690,6 @@ impl Downloader {
         let chunks: Vec<_> = (0..num_chunks).collect();
         let results: Vec<_> = stream::iter(chunks.iter())
             .map(|&chunk_idx| {
-                let client = &self.client;
                 let url = url.to_string();
                 let dest_path = dest.to_path_buf();
                 
@@ -291,14 +699,10 @@ impl Downloader {
                     
                     // Download chunk with range request
                     let range_header = format!("bytes={}-{}", start, end);
-                    let mut response = client
-                        .get(&url)
-                        .header("Range", range_header)
-                        .send()
-                        .await?;
+                    let mut response = self.send(self.get(&url).header("Range", range_header), &url).await?;
                     
                     if !response.status().is_success() && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
-                        return Err(anyhow::anyhow!("HTTP error for chunk {}: {}", chunk_idx, response.status()));
+                        return Err(self.http_error(&url, response.status(), &format!("HTTP error for chunk {}", chunk_idx)));
                     }
                     
                     // Write chunk to file at correct position
@@ -309,11 +713,12 @@ impl Downloader {
                     
                     file.seek(tokio::io::SeekFrom::Start(start)).await?;
                     
-                    while let Some(chunk) = response.chunk().await? {
+                    while let Some(chunk) = self.next_chunk(&mut response, &url).await? {
                         file.write_all(&chunk).await?;
+                        self.record_transferred(chunk.len() as u64);
                     }
                     
-                    Ok::<(), anyhow::Error>(())
+                    Ok::<(), AptNgError>(())
                 }
             })
             .buffer_unordered(self.max_parallel)
@@ -328,17 +733,6 @@ impl Downloader {
         Ok(())
     }
     
-    /// Format download speed as human-readable string
-    fn format_speed(bytes_per_sec: u64) -> String {
-        if bytes_per_sec >= 1024 * 1024 {
-            format!("{:.2} MB/s", bytes_per_sec as f64 / (1024.0 * 1024.0))
-        } else if bytes_per_sec >= 1024 {
-            format!("{:.2} KB/s", bytes_per_sec as f64 / 1024.0)
-        } else {
-            format!("{} B/s", bytes_per_sec)
-        }
-    }
-    
     /// Validiert die SHA256-Checksumme einer Datei
     async fn validate_file_checksum(&self, file_path: &Path, expected: &str) -> Result<()> {
         use sha2::{Sha256, Digest};
@@ -346,47 +740,59 @@ impl Downloader {
         use tokio::io::AsyncReadExt;
         
         let mut file = tokio::fs::File::open(file_path).await?;
-        let mut hasher = Sha256::new();
         let mut buffer = vec![0u8; 8192];
         
-        loop {
-            let bytes_read = file.read(&mut buffer).await?;
-            if bytes_read == 0 {
-                break;
+        // Packages-Index liefert SHA256, ältere Repositories nur MD5sum
+        let calculated = if expected.len() == 32 {
+            let mut context = md5::Context::new();
+            loop {
+                let bytes_read = file.read(&mut buffer).await?;
+                if bytes_read == 0 {
+                    break;
+                }
+                context.consume(&buffer[..bytes_read]);
             }
-            hasher.update(&buffer[..bytes_read]);
-        }
+            format!("{:x}", context.compute())
+        } else {
+            let mut hasher = Sha256::new();
+            loop {
+                let bytes_read = file.read(&mut buffer).await?;
+                if bytes_read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..bytes_read]);
+            }
+            hex::encode(hasher.finalize())
+        };
         
-        let calculated = hex::encode(hasher.finalize());
-        if calculated != expected {
-            return Err(anyhow::anyhow!(
-                "Checksum mismatch: expected {}, got {}",
-                expected,
-                calculated
-            ));
+        if !calculated.eq_ignore_ascii_case(expected) {
+            // Beschädigte Datei entfernen, damit sie weder gecacht noch fortgesetzt wird
+            let _ = tokio::fs::remove_file(file_path).await;
+            return Err(AptNgError::ChecksumMismatch {
+                expected: expected.to_string(),
+                actual: calculated,
+            });
         }
         
         Ok(())
     }
     
     /// Lädt mehrere Dateien parallel herunter
-    #[allow(dead_code)]
     pub async fn download_files(&self, urls: &[(&str, &Path)]) -> Result<Vec<Result<()>>> {
         let results: Vec<_> = stream::iter(urls.iter())
             .map(|(url, dest)| {
-                let client = &self.client;
                 let url = *url;
                 let dest = *dest;
                 
                 async move {
-                    let mut response = client.get(url).send().await?;
+                    let mut response = self.send(self.get(url), url).await?;
                     let mut file = tokio::fs::File::create(dest).await?;
                     
-                    while let Some(chunk) = response.chunk().await? {
+                    while let Some(chunk) = self.next_chunk(&mut response, url).await? {
                         file.write_all(&chunk).await?;
                     }
                     
-                    Ok::<(), anyhow::Error>(())
+                    Ok::<(), AptNgError>(())
                 }
             })
             .buffer_unordered(self.max_parallel)
@@ -396,97 +802,95 @@ impl Downloader {
         Ok(results)
     }
     
+    /// Wie `probe_mirror`, bricht aber nach `timeout` ab, damit ein hängender
+    /// Mirror nicht den ganzen Probe-Lauf blockiert
+    pub async fn probe_mirror_with_timeout(&self, url: &str, timeout: std::time::Duration) -> Result<MirrorStats> {
+        match tokio::time::timeout(timeout, self.probe_mirror(url)).await {
+            Ok(result) => result,
+            Err(_) => Err(AptNgError::Timeout { what: "Probe".to_string(), seconds: timeout.as_secs() }),
+        }
+    }
+    
     /// Testet die Geschwindigkeit eines Mirrors (RTT + Throughput)
+    ///
+    /// `url` sollte eine kleine Metadatendatei sein (siehe `Repository::probe_url`): die Wurzel
+    /// eines Archivs liefert oft eine Indexseite oder 403 und damit keine aussagekräftigen Werte.
     pub async fn probe_mirror(&self, url: &str) -> Result<MirrorStats> {
         use std::time::Instant;
         
-        // Measure RTT
+        // Lokale Repositories haben keine Netzwerklatenz
+        if let Some(path) = local_path(url) {
+            if !path.exists() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("{} does not exist", path.display()),
+                ).into());
+            }
+            return Ok(MirrorStats {
+                url: url.to_string(),
+                rtt_ms: 0,
+                throughput: 0,
+                failed: false,
+            });
+        }
+        
+        // RTT bis zu den Antwort-Headern, Throughput aus einem begrenzten GET derselben Datei
+        self.ensure_online(url)?;
         let start = Instant::now();
-        let head_response = self.client.head(url).send().await?;
+        let mut response = self.get(url).send().await?;
         let rtt_ms = start.elapsed().as_millis() as u64;
         
-        // Measure throughput by downloading a small chunk
-        let content_length = head_response.headers()
-            .get("content-length")
-            .and_then(|v| v.to_str().ok())
-            .and_then(|s| s.parse::<u64>().ok());
+        if !response.status().is_success() {
+            return Err(self.http_error(url, response.status(), "Probe failed"));
+        }
         
-        let throughput = if let Some(total_size) = content_length {
-            // Download first 1MB or entire file if smaller
-            let test_size = std::cmp::min(1024 * 1024, total_size);
-            
-            let download_start = Instant::now();
-            let range_header = format!("bytes=0-{}", test_size - 1);
-            let mut response = self.client
-                .get(url)
-                .header("Range", range_header)
-                .send()
-                .await?;
-            
-            if response.status().is_success() || response.status() == reqwest::StatusCode::PARTIAL_CONTENT {
-                let mut bytes_downloaded = 0u64;
-                while let Some(chunk) = response.chunk().await? {
-                    bytes_downloaded += chunk.len() as u64;
-                }
-                
-                let elapsed = download_start.elapsed();
-                if elapsed.as_secs() > 0 {
-                    bytes_downloaded / elapsed.as_secs()
-                } else {
-                    bytes_downloaded * 1000 / elapsed.as_millis() as u64
-                }
-            } else {
-                0
-            }
-        } else {
-            // If no content-length, try downloading first chunk
-            let download_start = Instant::now();
-            let mut response = self.client.get(url).send().await?;
-            
-            if response.status().is_success() {
-                let mut bytes_downloaded = 0u64;
-                let mut chunks = 0;
-                while let Some(chunk) = response.chunk().await? {
-                    bytes_downloaded += chunk.len() as u64;
-                    chunks += 1;
-                    // Only measure first few chunks for speed
-                    if chunks >= 10 {
-                        break;
-                    }
-                }
-                
-                let elapsed = download_start.elapsed();
-                if elapsed.as_secs() > 0 {
-                    bytes_downloaded / elapsed.as_secs()
-                } else if elapsed.as_millis() > 0 {
-                    bytes_downloaded * 1000 / elapsed.as_millis() as u64
-                } else {
-                    0
-                }
-            } else {
-                0
+        let download_start = Instant::now();
+        let mut bytes_downloaded = 0u64;
+        while let Some(chunk) = self.next_chunk(&mut response, url).await? {
+            bytes_downloaded += chunk.len() as u64;
+            if bytes_downloaded >= PROBE_MAX_BYTES {
+                break;
             }
-        };
+        }
+        let elapsed_us = download_start.elapsed().as_micros().max(1) as u64;
+        let throughput = bytes_downloaded.saturating_mul(1_000_000) / elapsed_us;
         
         Ok(MirrorStats {
             url: url.to_string(),
             rtt_ms,
             throughput,
+            failed: false,
         })
     }
 }
 
 #[derive(Clone)]
-#[allow(dead_code)]
 pub struct MirrorStats {
     pub url: String,
     pub rtt_ms: u64,
     pub throughput: u64, // bytes per second
+    pub failed: bool,
 }
 
 impl MirrorStats {
+    /// Statistik für einen fehlgeschlagenen oder abgelaufenen Probe.
+    /// Die RTT wird auf das Timeout gesetzt, damit der Mirror auch in der DB hinten einsortiert wird.
+    pub fn failed(url: &str, timeout: std::time::Duration) -> Self {
+        MirrorStats {
+            url: url.to_string(),
+            rtt_ms: timeout.as_millis() as u64,
+            throughput: 0,
+            failed: true,
+        }
+    }
+    
     /// Berechnet einen Score für die Mirror-Auswahl (niedriger ist besser)
     pub fn score(&self) -> f64 {
+        if self.failed {
+            // Fehlgeschlagene Mirrors immer hinter allen erreichbaren einsortieren
+            return f64::INFINITY;
+        }
+        
         // Kombiniere RTT und Throughput zu einem Score
         // Niedrige RTT und hoher Throughput = niedriger Score
         if self.throughput > 0 {
@@ -510,5 +914,221 @@ mod tests {
         let downloader = Downloader::new(4).unwrap();
         assert_eq!(downloader.max_parallel, 4);
     }
+    
+    #[tokio::test]
+    async fn test_local_repository() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let repo_dir = temp_dir.path().join("repo");
+        std::fs::create_dir_all(repo_dir.join("dists/stable")).unwrap();
+        std::fs::create_dir_all(repo_dir.join("pool/main/h")).unwrap();
+        std::fs::write(repo_dir.join("dists/stable/Release"), "Suite: stable\nCodename: bookworm\n").unwrap();
+        std::fs::write(repo_dir.join("pool/main/h/hello_1.0_all.deb"), b"deb").unwrap();
+        
+        let repo_url = format!("file://{}", repo_dir.display());
+        let downloader = Downloader::new(2).unwrap();
+        
+        let release = temp_dir.path().join("Release");
+        downloader.download_file(&format!("{}/dists/stable/Release", repo_url), &release).await.unwrap();
+        assert!(std::fs::read_to_string(&release).unwrap().contains("bookworm"));
+        
+        // Paket-"Download" kopiert aus dem lokalen Pool, die Quelle bleibt erhalten
+        let deb = temp_dir.path().join("hello.deb");
+        let checksum = hex::encode(<sha2::Sha256 as sha2::Digest>::digest(b"deb"));
+        downloader.download_file_with_checksum(
+            &format!("copy://{}/pool/main/h/hello_1.0_all.deb", repo_dir.display()), &deb, Some(&checksum),
+        ).await.unwrap();
+        assert_eq!(std::fs::read(&deb).unwrap(), b"deb");
+        assert!(repo_dir.join("pool/main/h/hello_1.0_all.deb").exists());
+        
+        // Der ursprüngliche io::Error bleibt in der Fehlerkette erhalten
+        let err = downloader.download_file(&format!("{}/pool/missing.deb", repo_url), &deb).await.unwrap_err();
+        let err = anyhow::Error::from(err);
+        assert!(err.to_string().starts_with("Failed to read"), "{}", err);
+        assert!(err.chain().any(|cause| cause.is::<std::io::Error>()));
+        assert_eq!(crate::error::exit_code_for(&err), crate::error::exit_code::IO);
+        
+        let stats = downloader.probe_mirror(&repo_url).await.unwrap();
+        assert_eq!(stats.rtt_ms, 0);
+        assert!(downloader.probe_mirror("file:///nonexistent/repo").await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_offline_mode_refuses_http() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let local = temp_dir.path().join("Release");
+        std::fs::write(&local, "Suite: stable\n").unwrap();
+        let downloader = Downloader::new(1).unwrap().with_offline(true);
+        
+        // 192.0.2.0/24 ist nicht routbar; ohne Offline-Prüfung liefe hier das Zeitlimit ab
+        let started = Instant::now();
+        let dest = temp_dir.path().join("Packages");
+        let err = downloader.download_file("http://192.0.2.1/debian/Packages", &dest).await.unwrap_err();
+        assert!(matches!(err, AptNgError::Offline { .. }), "{}", err);
+        assert!(matches!(downloader.probe_mirror("http://192.0.2.1/debian").await, Err(AptNgError::Offline { .. })));
+        assert!(!downloader.check_http3_support("http://192.0.2.1/").await);
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(!dest.exists());
+        
+        // Lokale Repositories brauchen kein Netz
+        downloader.download_file(&format!("file://{}", local.display()), &dest).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&dest).unwrap(), "Suite: stable\n");
+    }
+    
+    #[tokio::test]
+    async fn test_checksum_mismatch_fails_over_to_next_mirror() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mirror = |name: &str, content: &[u8]| {
+            let path = temp_dir.path().join(name).join("pool/hello_1.0_all.deb");
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, content).unwrap();
+            format!("copy://{}", path.display())
+        };
+        let corrupt = mirror("corrupt", b"broken");
+        let good = mirror("good", b"deb");
+        let checksum = hex::encode(<sha2::Sha256 as sha2::Digest>::digest(b"deb"));
+        let dest = temp_dir.path().join("hello.deb");
+        let downloader = Downloader::new(2).unwrap();
+        
+        let url = downloader.download_with_failover(&[corrupt.clone(), good.clone()], &dest, Some(&checksum))
+            .await.unwrap().url;
+        assert_eq!(url, good);
+        assert_eq!(std::fs::read(&dest).unwrap(), b"deb");
+        
+        // Nur beschädigte Quellen: Fehler erst nach allen Versuchen, keine Datei bleibt liegen
+        std::fs::remove_file(&dest).unwrap();
+        let result = downloader.download_with_failover(&[corrupt], &dest, Some(&checksum)).await;
+        assert!(matches!(result, Err(AptNgError::ChecksumMismatch { .. })));
+        assert!(!dest.exists());
+    }
+    
+    /// Minimaler HTTP-Server: `/redirect` leitet auf `/pool/hello.deb` weiter, das "deb" liefert
+    async fn redirecting_server() -> String {
+        use tokio::io::AsyncReadExt;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let target = format!("{}/pool/hello.deb", base);
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match socket.read(&mut buffer).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buffer[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let response = if request.contains(" /redirect ") {
+                    format!("HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", target)
+                } else if request.starts_with("HEAD") {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\n".to_string()
+                } else {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\ndeb".to_string()
+                };
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        base
+    }
+    
+    #[tokio::test]
+    async fn test_redirect_records_resolved_url() {
+        let base = redirecting_server().await;
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dest = temp_dir.path().join("hello.deb");
+        let url = format!("{}/redirect", base);
+        
+        let metrics = Downloader::new(1).unwrap().download_file_with_metrics(&url, &dest, None).await.unwrap();
+        assert_eq!(metrics.url, url);
+        assert_eq!(metrics.resolved_url, format!("{}/pool/hello.deb", base));
+        assert_eq!(std::fs::read(&dest).unwrap(), b"deb");
+        
+        // Ohne Weiterleitungen bleibt es beim 302
+        let downloader = Downloader::new(1).unwrap().with_redirects(None).unwrap();
+        let result = downloader.download_file(&url, &temp_dir.path().join("other.deb")).await;
+        assert!(matches!(result, Err(AptNgError::Http { status: 302, .. })));
+    }
+    
+    /// HTTP-Server, der nur `/debian/dists/stable/Release` (64 KiB) ausliefert, sonst 403
+    async fn forbidden_root_server() -> String {
+        use tokio::io::AsyncReadExt;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match socket.read(&mut buffer).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buffer[..n]),
+                    }
+                }
+                let response = if String::from_utf8_lossy(&request).contains(" /debian/dists/stable/Release ") {
+                    let body = "Suite: stable\n".repeat(64 * 1024 / 14);
+                    format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+                } else {
+                    "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                };
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        base
+    }
+    
+    #[tokio::test]
+    async fn test_probe_uses_release_file() {
+        let base = forbidden_root_server().await;
+        let repo = crate::repo::Repository::new(&format!("{}/debian", base), Some("stable"), &[], &[]);
+        let distro = crate::system::parse_os_release("ID=debian\nVERSION_CODENAME=bookworm\n");
+        let downloader = Downloader::new(1).unwrap();
+        
+        // Die Wurzel des Archivs liefert 403 und taugt nicht als Messpunkt
+        assert!(downloader.probe_mirror(&repo.url).await.is_err());
+        
+        let probe_url = repo.probe_url(&distro);
+        assert_eq!(probe_url, format!("{}/debian/dists/stable/Release", base));
+        let stats = downloader.probe_mirror(&probe_url).await.unwrap();
+        assert!(!stats.failed);
+        assert!(stats.rtt_ms < PROBE_TIMEOUT.as_millis() as u64);
+        assert!(stats.throughput > 0);
+    }
+    
+    #[test]
+    fn test_failed_probe_scores_last() {
+        let slow = MirrorStats {
+            url: "https://slow.example.com".to_string(),
+            rtt_ms: 5000,
+            throughput: 0,
+            failed: false,
+        };
+        let failed = MirrorStats::failed("https://down.example.com", PROBE_TIMEOUT);
+        
+        assert!(failed.score() > slow.score());
+        assert_eq!(failed.rtt_ms, PROBE_TIMEOUT.as_millis() as u64);
+    }
+    
+    #[test]
+    fn test_speed_tracker_averages_over_window() {
+        let tracker = SpeedTracker::with_window(Duration::from_secs(2));
+        let start = tracker.state.lock().unwrap().started;
+        
+        // Zwei Worker liefern gleichzeitig je 1 MB/s
+        for second in 0..4 {
+            let at = start + Duration::from_secs(second) + Duration::from_millis(500);
+            tracker.add_at(at, 1024 * 1024);
+            tracker.add_at(at, 1024 * 1024);
+        }
+        assert_eq!(tracker.total(), 8 * 1024 * 1024);
+        
+        // Nur die letzten zwei Sekunden zählen
+        let now = start + Duration::from_secs(4);
+        assert_eq!(tracker.bytes_per_sec_at(now), 2 * 1024 * 1024);
+        
+        assert_eq!(format_eta(Duration::from_secs(75)), "1:15");
+        assert_eq!(format_eta(Duration::from_secs(3725)), "1:02:05");
+        assert_eq!(SpeedTracker::new().eta(1024), None);
+    }
 }
-
@@ -0,0 +1,673 @@
+//! Systemwerkzeuge von dpkg/apt und ihre eingebauten Ersatzwege
+//!
+//! Solver und Installer fragen den Paketbestand über `dpkg-query` ab. Auf minimalen Containern
+//! oder Build-Hosts ohne dpkg fehlen diese Werkzeuge; dann liest apt-ng `/var/lib/dpkg/status`
+//! direkt. `.deb`-Dateien entpackt apt-ng immer selbst ([`crate::deb`]).
+
+use crate::error::{AptNgError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+/// Statusdatenbank von dpkg
+pub const DPKG_STATUS_PATH: &str = "/var/lib/dpkg/status";
+
+/// Verzeichnis mit den Dateilisten installierter Pakete (`<paket>.list`)
+pub const DPKG_INFO_DIR: &str = "/var/lib/dpkg/info";
+
+/// Externe Werkzeuge, die apt-ng verwendet, sofern sie vorhanden sind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Dpkg,
+    DpkgQuery,
+    AptCache,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 3] = [Tool::Dpkg, Tool::DpkgQuery, Tool::AptCache];
+
+    /// Programmname im PATH
+    pub fn program(self) -> &'static str {
+        match self {
+            Tool::Dpkg => "dpkg",
+            Tool::DpkgQuery => "dpkg-query",
+            Tool::AptCache => "apt-cache",
+        }
+    }
+
+    /// Womit apt-ng das Werkzeug ersetzt, wenn es fehlt
+    pub fn fallback(self) -> &'static str {
+        match self {
+            Tool::Dpkg => "the architecture reported by uname",
+            Tool::DpkgQuery => "reading /var/lib/dpkg/status directly",
+            Tool::AptCache => "Provides from /var/lib/dpkg/status",
+        }
+    }
+}
+
+/// Welche Werkzeuge auf diesem System vorhanden sind
+#[derive(Debug, Clone, Default)]
+pub struct Capabilities {
+    available: Vec<Tool>,
+}
+
+impl Capabilities {
+    /// Sucht die Werkzeuge im aktuellen PATH
+    pub fn detect() -> Self {
+        Self::detect_in(std::env::var_os("PATH").as_deref())
+    }
+
+    fn detect_in(path_var: Option<&OsStr>) -> Self {
+        let available = Tool::ALL
+            .into_iter()
+            .filter(|tool| path_var.is_some_and(|path_var| find_in_path(tool.program(), path_var).is_some()))
+            .collect();
+        Capabilities { available }
+    }
+
+    /// Einmal pro Prozess ermittelte Werkzeuge
+    pub fn get() -> &'static Self {
+        static CAPABILITIES: OnceLock<Capabilities> = OnceLock::new();
+        CAPABILITIES.get_or_init(Self::detect)
+    }
+
+    pub fn has(&self, tool: Tool) -> bool {
+        self.available.contains(&tool)
+    }
+
+    /// Fehlende Werkzeuge, für die ein Ersatzweg greift
+    pub fn missing(&self) -> Vec<Tool> {
+        Tool::ALL.into_iter().filter(|tool| !self.has(*tool)).collect()
+    }
+}
+
+/// Kurzform für `Capabilities::get().has(tool)`
+pub fn available(tool: Tool) -> bool {
+    Capabilities::get().has(tool)
+}
+
+/// Gestartete dpkg-Prozesse, für Benchmarks
+static SUBPROCESS_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+/// Aufruf eines Werkzeugs; jeder Start wird mitgezählt
+fn command(tool: Tool) -> Command {
+    SUBPROCESS_CALLS.fetch_add(1, Ordering::Relaxed);
+    Command::new(tool.program())
+}
+
+/// Anzahl der bisher in diesem Prozess gestarteten dpkg-Werkzeuge
+pub fn subprocess_calls() -> usize {
+    SUBPROCESS_CALLS.load(Ordering::Relaxed)
+}
+
+/// Sucht ein ausführbares Programm in den Verzeichnissen einer PATH-Variablen
+fn find_in_path(program: &str, path_var: &OsStr) -> Option<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::env::split_paths(path_var)
+        .map(|dir| dir.join(program))
+        .find(|candidate| {
+            std::fs::metadata(candidate)
+                .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+                .unwrap_or(false)
+        })
+}
+
+/// Ein Paket aus der dpkg-Datenbank
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+    /// `Status`-Feld, z.B. `install ok installed`
+    pub status: String,
+    /// Namen aus `Provides` (ohne Versionsangabe)
+    pub provides: Vec<String>,
+    /// Quellpaket (`Source`); None, wenn es wie das Binärpaket heißt
+    pub source: Option<String>,
+    /// Version des Quellpakets; None, wenn sie der Paketversion entspricht
+    pub source_version: Option<String>,
+}
+
+impl InstalledPackage {
+    /// Nur vollständig installierte Pakete zählen; entfernte mit Restkonfiguration
+    /// (`config-files`) oder halb entpackte nicht
+    pub fn is_installed(&self) -> bool {
+        self.status.split_whitespace().nth(2) == Some("installed")
+    }
+
+    /// Quellpaket mit Version, aus dem dieses Paket gebaut wurde
+    pub fn source_package(&self) -> (&str, &str) {
+        (
+            self.source.as_deref().unwrap_or(&self.name),
+            self.source_version.as_deref().unwrap_or(&self.version),
+        )
+    }
+}
+
+/// Zerlegt ein `Source`-Feld (`openssl (3.0.11-1)`) in Name und optionale Version
+fn parse_source(value: &str) -> (Option<String>, Option<String>) {
+    let (name, version) = match value.split_once('(') {
+        Some((name, version)) => (name, Some(version.trim_end_matches(')').trim())),
+        None => (value, None),
+    };
+    let name = name.trim();
+    (
+        Some(name.to_string()).filter(|name| !name.is_empty()),
+        version.filter(|version| !version.is_empty()).map(str::to_string),
+    )
+}
+
+/// Zerlegt ein `Provides`-Feld in die bereitgestellten Namen
+fn parse_provides(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .filter_map(|entry| entry.split('(').next())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Parst den Inhalt von `/var/lib/dpkg/status`
+pub fn parse_status(content: &str) -> Vec<InstalledPackage> {
+    let mut packages = Vec::new();
+
+    for paragraph in content.split("\n\n") {
+        let mut package = InstalledPackage {
+            name: String::new(),
+            version: String::new(),
+            status: String::new(),
+            provides: Vec::new(),
+            source: None,
+            source_version: None,
+        };
+        for line in paragraph.lines() {
+            // Fortsetzungszeilen (Description, Conffiles) sind hier nicht von Interesse
+            if line.starts_with(' ') || line.starts_with('\t') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once(':') else { continue };
+            let value = value.trim();
+            match key {
+                "Package" => package.name = value.to_string(),
+                "Version" => package.version = value.to_string(),
+                "Status" => package.status = value.to_string(),
+                "Provides" => package.provides = parse_provides(value),
+                "Source" => (package.source, package.source_version) = parse_source(value),
+                _ => {}
+            }
+        }
+        if !package.name.is_empty() {
+            packages.push(package);
+        }
+    }
+
+    packages
+}
+
+/// Liest alle Pakete der dpkg-Datenbank – über `dpkg-query`, sonst direkt aus der Statusdatei
+pub fn installed_packages() -> Result<Vec<InstalledPackage>> {
+    if available(Tool::DpkgQuery) {
+        let output = command(Tool::DpkgQuery)
+            .arg("-W")
+            .arg("-f=${Package}\t${Version}\t${Status}\t${Provides}\t${source:Package}\t${source:Version}\n")
+            .output()?;
+        if output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| {
+                    let mut fields = line.split('\t');
+                    let name = fields.next().filter(|name| !name.is_empty())?;
+                    Some(InstalledPackage {
+                        name: name.to_string(),
+                        version: fields.next().unwrap_or("").to_string(),
+                        status: fields.next().unwrap_or("").to_string(),
+                        provides: parse_provides(fields.next().unwrap_or("")),
+                        source: fields.next().filter(|source| !source.is_empty()).map(str::to_string),
+                        source_version: fields.next().filter(|version| !version.is_empty()).map(str::to_string),
+                    })
+                })
+                .collect());
+        }
+    }
+
+    read_status_file(Path::new(DPKG_STATUS_PATH))
+}
+
+fn read_status_file(path: &Path) -> Result<Vec<InstalledPackage>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(parse_status(&content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(AptNgError::MissingCapability {
+            capability: "Querying installed system packages".to_string(),
+            tool: Tool::DpkgQuery.program().to_string(),
+            hint: format!(" and {} does not exist", path.display()),
+        }),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Sucht ein Paket in der dpkg-Datenbank
+fn query_package(name: &str) -> Option<InstalledPackage> {
+    if available(Tool::DpkgQuery) {
+        let output = command(Tool::DpkgQuery)
+            .arg("-W")
+            .arg("-f=${Version}\t${Status}")
+            .arg(name)
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (version, status) = stdout.trim_end().split_once('\t')?;
+        return Some(InstalledPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            status: status.to_string(),
+            provides: Vec::new(),
+            source: None,
+            source_version: None,
+        });
+    }
+
+    read_status_file(Path::new(DPKG_STATUS_PATH))
+        .ok()?
+        .into_iter()
+        .find(|package| package.name == name)
+}
+
+/// Installierte Version eines Systempakets
+pub fn installed_version(name: &str) -> Option<String> {
+    query_package(name)
+        .filter(InstalledPackage::is_installed)
+        .map(|package| package.version)
+        .filter(|version| !version.is_empty())
+}
+
+/// Ob ein Paket auf dem System installiert ist
+pub fn is_installed(name: &str) -> bool {
+    query_package(name).is_some_and(|package| package.is_installed())
+}
+
+/// Ob ein installiertes Systempaket `name` über `Provides` bereitstellt
+pub fn is_provided(name: &str) -> bool {
+    installed_packages()
+        .map(|packages| {
+            packages
+                .iter()
+                .any(|package| package.is_installed() && package.provides.iter().any(|provided| provided == name))
+        })
+        .unwrap_or(false)
+}
+
+/// Momentaufnahme der installierten Systempakete für viele Abfragen hintereinander
+///
+/// Entsteht aus einem einzigen `dpkg-query -W`-Aufruf (bzw. einmaligem Lesen der
+/// Statusdatei) statt eines Prozesses pro Abfrage; der Solver beantwortet darüber alle
+/// Fragen nach Systempaketen.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SystemSnapshot {
+    versions: HashMap<String, String>,
+    /// Bereitgestellter Name → installierte Pakete mit passendem `Provides`
+    providers: HashMap<String, Vec<String>>,
+}
+
+impl SystemSnapshot {
+    /// Liest die dpkg-Datenbank; ohne dpkg-Datenbank ist die Momentaufnahme leer
+    pub fn load() -> Self {
+        Self::read().unwrap_or_default()
+    }
+
+    /// Liest die dpkg-Datenbank und meldet Fehler, statt leer zu bleiben
+    pub fn read() -> Result<Self> {
+        installed_packages().map(Self::from_packages)
+    }
+
+    /// Wie [`SystemSnapshot::load`], aber über einen Cache unter `cache_path` (z.B. im `state_dir`)
+    ///
+    /// Der Cache gilt, solange Änderungszeit und Größe der dpkg-Statusdatei unverändert sind;
+    /// sonst wird die Datenbank neu gelesen und der Cache ersetzt. Scheitert das Lesen, ist
+    /// die Momentaufnahme leer und wird nicht zwischengespeichert.
+    pub fn load_cached(cache_path: &Path) -> Self {
+        Self::load_cached_with(cache_path, Path::new(DPKG_STATUS_PATH), Self::read)
+    }
+
+    fn load_cached_with(cache_path: &Path, status_path: &Path, load: impl FnOnce() -> Result<Self>) -> Self {
+        // Ohne Statusdatei gibt es keinen Schlüssel für den Cache
+        let Some(status) = StatusStamp::of(status_path) else {
+            return load().unwrap_or_default();
+        };
+
+        let cached = std::fs::read(cache_path)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<CachedSnapshot>(&data).ok());
+        if let Some(cached) = cached.filter(|cached| cached.status == status) {
+            return cached.snapshot;
+        }
+
+        let snapshot = match load() {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                tracing::debug!(error = %e, "could not read system packages");
+                return SystemSnapshot::default();
+            }
+        };
+        // Ein nicht schreibbarer Cache verlangsamt nur den nächsten Aufruf
+        let cached = CachedSnapshot { status, snapshot };
+        if let Err(e) = write_cache(cache_path, &cached) {
+            tracing::debug!(path = %cache_path.display(), error = %e, "could not cache system packages");
+        }
+        cached.snapshot
+    }
+
+    /// Momentaufnahme aus bereits gelesenen Paketen (nur vollständig installierte zählen)
+    pub fn from_packages(packages: impl IntoIterator<Item = InstalledPackage>) -> Self {
+        let mut snapshot = SystemSnapshot::default();
+        for package in packages.into_iter().filter(InstalledPackage::is_installed) {
+            for provided in &package.provides {
+                snapshot.providers.entry(provided.clone()).or_default().push(package.name.clone());
+            }
+            snapshot.versions.insert(package.name, package.version);
+        }
+        snapshot
+    }
+
+    pub fn len(&self) -> usize {
+        self.versions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.versions.is_empty()
+    }
+
+    pub fn is_installed(&self, name: &str) -> bool {
+        self.versions.contains_key(name)
+    }
+
+    /// Installierte Version, wie [`installed_version`]
+    pub fn installed_version(&self, name: &str) -> Option<&str> {
+        self.versions.get(name).map(String::as_str).filter(|version| !version.is_empty())
+    }
+
+    /// Ob ein installiertes Paket `name` über `Provides` bereitstellt, wie [`is_provided`]
+    pub fn is_provided(&self, name: &str) -> bool {
+        self.providers.contains_key(name)
+    }
+}
+
+/// Änderungszeit und Größe der dpkg-Statusdatei, aus der eine Momentaufnahme entstand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct StatusStamp {
+    modified_secs: u64,
+    modified_nanos: u32,
+    size: u64,
+}
+
+impl StatusStamp {
+    fn of(status_path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(status_path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?;
+        Some(StatusStamp {
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+            size: metadata.len(),
+        })
+    }
+}
+
+/// Inhalt der Cache-Datei von [`SystemSnapshot::load_cached`]
+#[derive(Serialize, Deserialize)]
+struct CachedSnapshot {
+    status: StatusStamp,
+    snapshot: SystemSnapshot,
+}
+
+/// Schreibt den Cache über eine temporäre Datei, damit parallele Leser nie eine halbe Datei sehen
+fn write_cache(cache_path: &Path, cached: &CachedSnapshot) -> Result<()> {
+    if let Some(parent) = cache_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let partial = cache_path.with_extension(format!("partial-{}", std::process::id()));
+    std::fs::write(&partial, serde_json::to_vec(cached)?)?;
+    std::fs::rename(&partial, cache_path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&partial);
+    })?;
+    Ok(())
+}
+
+/// Dateiliste eines installierten Pakets – über `dpkg-query -L`, sonst aus `info/<paket>.list`
+pub fn installed_files(name: &str) -> Option<Vec<PathBuf>> {
+    let listing = if available(Tool::DpkgQuery) {
+        let output = command(Tool::DpkgQuery).arg("-L").arg(name).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    } else {
+        let info_dir = Path::new(DPKG_INFO_DIR);
+        std::fs::read_to_string(info_dir.join(format!("{}.list", name)))
+            .or_else(|_| {
+                // Multiarch-Pakete heißen `<paket>:<arch>.list`
+                let prefix = format!("{}:", name);
+                let list = std::fs::read_dir(info_dir)?
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .find(|path| {
+                        path.extension().is_some_and(|ext| ext == "list")
+                            && path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with(&prefix))
+                    })
+                    .ok_or(std::io::ErrorKind::NotFound)?;
+                std::fs::read_to_string(list)
+            })
+            .ok()?
+    };
+
+    Some(
+        listing
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with('/'))
+            .map(PathBuf::from)
+            .collect(),
+    )
+}
+
+/// Pakete, zu deren Dateiliste `path` gehört (wie `dpkg -S`)
+///
+/// Ohne `dpkg-query` werden die `.list`-Dateien unter `/var/lib/dpkg/info` durchsucht.
+pub fn owning_packages(path: &Path) -> Vec<String> {
+    if available(Tool::DpkgQuery) {
+        return match command(Tool::DpkgQuery).arg("-S").arg(path).output() {
+            Ok(output) if output.status.success() => parse_search_output(&String::from_utf8_lossy(&output.stdout), path),
+            _ => Vec::new(),
+        };
+    }
+
+    let Ok(entries) = std::fs::read_dir(DPKG_INFO_DIR) else {
+        return Vec::new();
+    };
+    let mut owners: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|list| list.extension().is_some_and(|ext| ext == "list"))
+        .filter(|list| {
+            std::fs::read_to_string(list)
+                .is_ok_and(|content| content.lines().any(|line| Path::new(line.trim()) == path))
+        })
+        .filter_map(|list| list.file_stem().and_then(|stem| stem.to_str()).map(package_without_arch))
+        .collect();
+    owners.sort();
+    owners.dedup();
+    owners
+}
+
+/// Liest die Ausgabe von `dpkg-query -S` (`paket1, paket2:arch: /pfad`) für genau `path`
+fn parse_search_output(output: &str, path: &Path) -> Vec<String> {
+    let mut owners = Vec::new();
+    for line in output.lines() {
+        if line.starts_with("diversion by ") {
+            continue;
+        }
+        let Some((packages, file)) = line.split_once(": /") else { continue };
+        if Path::new("/").join(file) != path {
+            continue;
+        }
+        owners.extend(packages.split(", ").map(|name| package_without_arch(name.trim())));
+    }
+    owners.sort();
+    owners.dedup();
+    owners
+}
+
+/// Entfernt eine Architekturangabe (`libc6:amd64` → `libc6`)
+fn package_without_arch(name: &str) -> String {
+    name.split(':').next().unwrap_or(name).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const STATUS: &str = "\
+Package: libc6
+Status: install ok installed
+Version: 2.36-9
+Provides: libc6-udeb (= 2.36-9), glibc
+Description: GNU C Library
+ Contains the standard libraries.
+
+Package: mawk
+Status: install ok installed
+Version: 1.3.4
+Provides: awk
+
+Package: old-tool
+Status: deinstall ok config-files
+Version: 0.9
+Provides: legacy
+";
+
+    #[test]
+    fn test_parse_search_output() {
+        let output = "diversion by dash from: /bin/sh\nlibc6:amd64, libc6:i386: /usr/share/doc/libc6\nlibc6:amd64: /usr/share/doc/libc6/copyright\n";
+        assert_eq!(parse_search_output(output, Path::new("/usr/share/doc/libc6")), vec!["libc6"]);
+        assert_eq!(parse_search_output(output, Path::new("/usr/share/doc/libc6/copyright")), vec!["libc6"]);
+        assert!(parse_search_output(output, Path::new("/bin/sh")).is_empty());
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let packages = parse_status(STATUS);
+        assert_eq!(packages.len(), 3);
+        assert_eq!(packages[0].name, "libc6");
+        assert_eq!(packages[0].version, "2.36-9");
+        assert_eq!(packages[0].provides, vec!["libc6-udeb", "glibc"]);
+        assert!(packages[0].is_installed());
+        assert_eq!(packages[1].provides, vec!["awk"]);
+
+        assert_eq!(packages[1].source_package(), ("mawk", "1.3.4"));
+
+        // Entfernte Pakete mit Restkonfiguration gelten nicht als installiert
+        assert!(!packages[2].is_installed());
+
+        // Binärpakete aus einem anders benannten bzw. versionierten Quellpaket
+        let rebuilt = parse_status("Package: libssl3\nVersion: 3.0.11-1+b1\nSource: openssl (3.0.11-1)\n\nPackage: openssl\nVersion: 3.0.11-1\nSource: openssl\n");
+        assert_eq!(rebuilt[0].source_package(), ("openssl", "3.0.11-1"));
+        assert_eq!(rebuilt[1].source_package(), ("openssl", "3.0.11-1"));
+    }
+
+    #[test]
+    fn test_system_snapshot() {
+        let snapshot = SystemSnapshot::from_packages(parse_status(STATUS));
+        assert_eq!(snapshot.len(), 2);
+        assert!(snapshot.is_installed("mawk"));
+        assert_eq!(snapshot.installed_version("libc6"), Some("2.36-9"));
+        assert!(snapshot.is_provided("awk"));
+        assert!(snapshot.is_provided("glibc"));
+        // Restkonfiguration zählt weder als installiert noch als Provider
+        assert!(!snapshot.is_installed("old-tool"));
+        assert!(!snapshot.is_provided("legacy"));
+        assert_eq!(snapshot.installed_version("old-tool"), None);
+    }
+
+    #[test]
+    fn test_cached_snapshot_invalidated_by_status_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let status_path = temp_dir.path().join("status");
+        let cache_path = temp_dir.path().join("state/system-packages.json");
+        std::fs::write(&status_path, STATUS).unwrap();
+        let from_status = || Ok(SystemSnapshot::from_packages(parse_status(&std::fs::read_to_string(&status_path).unwrap())));
+
+        let snapshot = SystemSnapshot::load_cached_with(&cache_path, &status_path, from_status);
+        assert!(snapshot.is_installed("mawk"));
+        assert!(cache_path.exists());
+
+        // Unveränderte Statusdatei: der Cache wird verwendet, dpkg nicht erneut gefragt
+        let cached = SystemSnapshot::load_cached_with(&cache_path, &status_path, || panic!("cache not used"));
+        assert_eq!(cached, snapshot);
+
+        // Neuere Änderungszeit (gleiche Größe): der Cache ist veraltet
+        std::fs::write(&status_path, STATUS.replace("Package: mawk", "Package: gawk")).unwrap();
+        let file = std::fs::File::options().write(true).open(&status_path).unwrap();
+        file.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60)).unwrap();
+        let refreshed = SystemSnapshot::load_cached_with(&cache_path, &status_path, from_status);
+        assert!(refreshed.is_installed("gawk"));
+        assert!(!refreshed.is_installed("mawk"));
+        let cached = SystemSnapshot::load_cached_with(&cache_path, &status_path, || panic!("cache not refreshed"));
+        assert_eq!(cached, refreshed);
+
+        // Ein beschädigter Cache wird neu aufgebaut
+        std::fs::write(&cache_path, "not json").unwrap();
+        assert_eq!(SystemSnapshot::load_cached_with(&cache_path, &status_path, from_status), refreshed);
+    }
+
+    #[test]
+    fn test_failed_snapshot_load_is_not_cached() {
+        let temp_dir = TempDir::new().unwrap();
+        let status_path = temp_dir.path().join("status");
+        let cache_path = temp_dir.path().join("state/system-packages.json");
+        std::fs::write(&status_path, STATUS).unwrap();
+
+        let failed = SystemSnapshot::load_cached_with(&cache_path, &status_path, || {
+            Err(AptNgError::Other(anyhow::anyhow!("dpkg-query failed")))
+        });
+        assert!(failed.is_empty());
+        assert!(!cache_path.exists());
+
+        // Der nächste Aufruf liest die Datenbank erneut
+        let snapshot = SystemSnapshot::load_cached_with(&cache_path, &status_path, || Ok(SystemSnapshot::from_packages(parse_status(STATUS))));
+        assert!(snapshot.is_installed("mawk"));
+        assert!(cache_path.exists());
+    }
+
+    #[test]
+    fn test_missing_status_file_names_capability() {
+        let temp_dir = TempDir::new().unwrap();
+        let err = read_status_file(&temp_dir.path().join("status")).unwrap_err();
+        assert!(matches!(err, AptNgError::MissingCapability { .. }));
+        assert!(err.to_string().contains("`dpkg-query` is not installed"));
+    }
+
+    #[test]
+    fn test_detect_capabilities() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let tool = temp_dir.path().join("dpkg");
+        std::fs::write(&tool, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+        // Nicht ausführbare Dateien zählen nicht
+        std::fs::write(temp_dir.path().join("dpkg-query"), "").unwrap();
+
+        let capabilities = Capabilities::detect_in(Some(temp_dir.path().as_os_str()));
+        assert!(capabilities.has(Tool::Dpkg));
+        assert!(!capabilities.has(Tool::DpkgQuery));
+        assert_eq!(capabilities.missing(), vec![Tool::DpkgQuery, Tool::AptCache]);
+
+        assert!(Capabilities::detect_in(None).missing().len() == Tool::ALL.len());
+    }
+}
@@ -0,0 +1,254 @@
+use crate::verifier::SignatureError;
+use thiserror::Error;
+
+/// Fehlerkategorien von apt-ng, auf die Aufrufer der Bibliothek reagieren können
+///
+/// Solver, Downloader, Verifier und Installer liefern diesen Typ; die CLI
+/// wandelt ihn weiterhin über `anyhow` in Meldungen um.
+#[derive(Debug, Error)]
+pub enum AptNgError {
+    /// Verbindungs- oder Protokollfehler
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+
+    /// Der Server hat mit einem Fehlerstatus geantwortet
+    #[error("{context}: {status}")]
+    Http { url: String, status: u16, context: String },
+
+    /// Das Repository verlangt Zugangsdaten, es sind aber keine konfiguriert
+    #[error("Authentication required for {url} (HTTP 401): add credentials to /etc/apt/auth.conf.d/ or a `token` for this repository in the apt-ng config")]
+    AuthRequired { url: String },
+
+    /// Die konfigurierten Zugangsdaten wurden abgelehnt
+    #[error("Authentication failed for {url} (HTTP {status}): the configured credentials were rejected")]
+    AuthRejected { url: String, status: u16 },
+
+    /// Netzwerkzugriff im Offline-Modus (`--offline`)
+    #[error("Offline mode: refusing to fetch {url}")]
+    Offline { url: String },
+
+    /// Zeitüberschreitung
+    #[error("{what} timed out after {seconds}s")]
+    Timeout { what: String, seconds: u64 },
+
+    /// Prüfsumme einer heruntergeladenen Datei stimmt nicht
+    #[error("Checksum mismatch: expected {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+
+    /// Signaturprüfung fehlgeschlagen
+    #[error(transparent)]
+    Signature(#[from] SignatureError),
+
+    /// Release-Datei ist abgelaufen oder älter als die zuletzt gesehene (Replay-Schutz)
+    #[error("Stale metadata for {repo}: {message} (use --allow-stale-metadata for frozen mirrors)")]
+    StaleMetadata { repo: String, message: String },
+
+    /// Ungültiger oder nicht lesbarer Schlüssel
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+
+    /// Paket ist im Index nicht vorhanden
+    #[error("Package not found: {0}")]
+    PackageNotFound(String),
+
+    /// Virtueller Paketname mit mehreren Anbietern, von denen einer gewählt werden muss
+    #[error("{name} is a virtual package provided by {}; select one to install", providers.join(", "))]
+    VirtualPackage { name: String, providers: Vec<String> },
+
+    /// Keine Version erfüllt die Anforderung
+    #[error("No matching package found for {name} {constraint}")]
+    NoMatchingVersion { name: String, constraint: String },
+
+    /// Abhängigkeit lässt sich nicht auflösen; `hint` nennt Provider und ähnliche Pakete
+    #[error("Dependency not found: {dependency}{hint}")]
+    UnresolvedDependency { dependency: String, hint: String },
+
+    /// Pakete stehen in Konflikt zueinander
+    #[error("Conflicts detected: {0:?}")]
+    Conflict(Vec<String>),
+
+    /// Paket ist nicht installiert
+    #[error("Package {0} is not installed")]
+    NotInstalled(String),
+
+    /// Paket wird noch von anderen installierten Paketen benötigt
+    #[error("Cannot remove {package}: the following packages depend on it: {}", dependents.join(", "))]
+    RequiredBy { package: String, dependents: Vec<String> },
+
+    /// Maintainer-Skript ist fehlgeschlagen
+    #[error("Hook {hook} failed: {message}")]
+    HookFailed { hook: String, message: String },
+
+    /// Paketdatei ist beschädigt und wurde gelöscht
+    #[error("{0}")]
+    CorruptPackage(String),
+
+    /// Mindestens eine Prüfung von `apt-ng verify` ist fehlgeschlagen
+    #[error("{0}")]
+    VerificationFailed(String),
+
+    /// Ein benötigtes Systemwerkzeug fehlt und es gibt keinen eingebauten Ersatz
+    #[error("{capability} is unavailable: `{tool}` is not installed{hint}")]
+    MissingCapability { capability: String, tool: String, hint: String },
+
+    /// Installation fehlgeschlagen (Änderungen ggf. zurückgerollt)
+    #[error("Installation failed: {0}")]
+    Installation(String),
+
+    /// Installation fehlgeschlagen; `outcome` beschreibt das Ergebnis des Rollbacks,
+    /// `source` bleibt für die Einordnung (Exit-Code) erhalten
+    #[error("Installation failed: {source}. {outcome}")]
+    RolledBack { source: Box<AptNgError>, outcome: String },
+
+    /// Die Index-Datenbank bleibt von einem anderen Prozess gesperrt
+    #[error("The package index {path} is locked by another process; try again when it has finished")]
+    IndexLocked { path: String },
+
+    /// Die Index-Datenbank ist beschädigt
+    #[error("The package index {path} is corrupted ({message}); run with --rebuild-index to back it up and create a new one")]
+    IndexCorrupt { path: String, message: String },
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Database(#[from] rusqlite::Error),
+
+    /// Fehler aus Modulen, die (noch) `anyhow` verwenden
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+pub type Result<T> = std::result::Result<T, AptNgError>;
+
+/// Exit-Codes der CLI je Fehlerkategorie
+pub mod exit_code {
+    /// Allgemeiner Fehler
+    pub const GENERIC: i32 = 1;
+    /// Ungültige Kommandozeile
+    pub const USAGE: i32 = 2;
+    /// Abhängigkeiten nicht auflösbar (Paket fehlt, Konflikt)
+    pub const DEPENDENCY: i32 = 10;
+    /// Signatur- oder Prüfsummenfehler
+    pub const VERIFICATION: i32 = 20;
+    /// Netzwerkfehler (meist wiederholbar)
+    pub const NETWORK: i32 = 30;
+    /// Festplatten-, Dateisystem- oder Datenbankfehler
+    pub const IO: i32 = 40;
+    /// Durch Ctrl-C abgebrochen (128 + SIGINT, wie in der Shell üblich)
+    pub const INTERRUPTED: i32 = 130;
+}
+
+impl AptNgError {
+    /// Exit-Code für diese Fehlerkategorie
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AptNgError::PackageNotFound(_)
+            | AptNgError::VirtualPackage { .. }
+            | AptNgError::NoMatchingVersion { .. }
+            | AptNgError::UnresolvedDependency { .. }
+            | AptNgError::Conflict(_)
+            | AptNgError::NotInstalled(_)
+            | AptNgError::RequiredBy { .. } => exit_code::DEPENDENCY,
+            AptNgError::Signature(_)
+            | AptNgError::InvalidKey(_)
+            | AptNgError::StaleMetadata { .. }
+            | AptNgError::ChecksumMismatch { .. }
+            | AptNgError::CorruptPackage(_)
+            | AptNgError::VerificationFailed(_) => exit_code::VERIFICATION,
+            AptNgError::Network(_)
+            | AptNgError::Http { .. }
+            | AptNgError::AuthRequired { .. }
+            | AptNgError::AuthRejected { .. }
+            | AptNgError::Offline { .. }
+            | AptNgError::Timeout { .. } => exit_code::NETWORK,
+            AptNgError::Io(_)
+            | AptNgError::Json(_)
+            | AptNgError::Database(_)
+            | AptNgError::IndexLocked { .. }
+            | AptNgError::IndexCorrupt { .. } => exit_code::IO,
+            AptNgError::HookFailed { .. }
+            | AptNgError::MissingCapability { .. }
+            | AptNgError::Installation(_) => exit_code::GENERIC,
+            AptNgError::RolledBack { source, .. } => source.exit_code(),
+            AptNgError::Other(inner) => exit_code_for(inner),
+        }
+    }
+}
+
+/// Bestimmt den Exit-Code für einen (ggf. mit Kontext versehenen) Fehler
+pub fn exit_code_for(err: &anyhow::Error) -> i32 {
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<AptNgError>() {
+            return e.exit_code();
+        }
+        if cause.is::<SignatureError>() {
+            return exit_code::VERIFICATION;
+        }
+        if cause.is::<reqwest::Error>() {
+            return exit_code::NETWORK;
+        }
+        if cause.is::<std::io::Error>() || cause.is::<rusqlite::Error>() {
+            return exit_code::IO;
+        }
+    }
+    exit_code::GENERIC
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_can_be_matched_through_anyhow() {
+        let err: anyhow::Error = AptNgError::PackageNotFound("foo".to_string()).into();
+        assert_eq!(err.to_string(), "Package not found: foo");
+        assert!(matches!(err.downcast_ref::<AptNgError>(), Some(AptNgError::PackageNotFound(name)) if name == "foo"));
+
+        let err = AptNgError::from(SignatureError::Invalid);
+        assert_eq!(err.to_string(), SignatureError::Invalid.to_string());
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        use anyhow::Context;
+
+        let unresolved = AptNgError::UnresolvedDependency { dependency: "libfoo".to_string(), hint: String::new() };
+        assert_eq!(unresolved.exit_code(), exit_code::DEPENDENCY);
+
+        let network: anyhow::Result<()> = Err(AptNgError::Timeout { what: "Probe".to_string(), seconds: 10 }.into());
+        let network = network.context("Download of foo failed").unwrap_err();
+        assert_eq!(exit_code_for(&network), exit_code::NETWORK);
+        assert_eq!(AptNgError::Offline { url: "http://example.com".to_string() }.exit_code(), exit_code::NETWORK);
+
+        let io = anyhow::Error::new(std::io::Error::other("disk full"));
+        assert_eq!(exit_code_for(&io), exit_code::IO);
+
+        let nested = AptNgError::Other(AptNgError::Signature(SignatureError::Invalid).into());
+        assert_eq!(nested.exit_code(), exit_code::VERIFICATION);
+
+        assert_eq!(exit_code_for(&anyhow::anyhow!("something else")), exit_code::GENERIC);
+    }
+
+    #[test]
+    fn test_rolled_back_installation_keeps_its_cause() {
+        use std::error::Error as _;
+
+        let io = AptNgError::RolledBack {
+            source: Box::new(AptNgError::Io(std::io::Error::other("disk full"))),
+            outcome: "Rolled back changes.".to_string(),
+        };
+        assert_eq!(io.to_string(), "Installation failed: disk full. Rolled back changes.");
+        assert!(io.source().is_some());
+        assert_eq!(exit_code_for(&anyhow::Error::new(io).context("Failed to install foo")), exit_code::IO);
+
+        let checksum = AptNgError::RolledBack {
+            source: Box::new(AptNgError::ChecksumMismatch { expected: "a".to_string(), actual: "b".to_string() }),
+            outcome: "Rollback also failed: permission denied".to_string(),
+        };
+        assert_eq!(checksum.exit_code(), exit_code::VERIFICATION);
+    }
+}
@@ -0,0 +1,276 @@
+//! Begrenzte Dekompression und sicheres Entpacken von tar-Archiven
+//!
+//! Paketindizes und Paketinhalte kommen aus Repositories, denen nur die Signatur vertraut.
+//! Eine wenige Kilobyte große xz- oder gzip-Datei kann sich auf viele Gigabyte entpacken;
+//! `LimitedReader` bricht ab, sobald mehr als `max_decompressed_size()` Bytes entstehen.
+
+use anyhow::{Context, Result};
+use std::io::{self, Read};
+use std::path::{Component, Path};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Standardobergrenze für entpackte Daten pro Quelle (4 GiB)
+///
+/// Großzügig genug für die größten Debian-Pakete, aber weit unter dem, was eine
+/// Kompressionsbombe erzeugen würde.
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Standardobergrenze für Indexdateien, die vollständig im Speicher landen (512 MiB)
+///
+/// Selbst die Packages-Datei von Debian main entpackt sich auf unter 100 MiB.
+pub const DEFAULT_MAX_INDEX_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Prozessweite Obergrenze (aus `limits.max_decompressed_size`)
+static MAX_DECOMPRESSED_SIZE: AtomicU64 = AtomicU64::new(DEFAULT_MAX_DECOMPRESSED_SIZE);
+
+/// Prozessweite Obergrenze für Indexdateien (aus `limits.max_index_size`)
+static MAX_INDEX_SIZE: AtomicU64 = AtomicU64::new(DEFAULT_MAX_INDEX_SIZE);
+
+/// Setzt die Obergrenze für diesen Prozess
+pub fn set_max_decompressed_size(limit: u64) {
+    MAX_DECOMPRESSED_SIZE.store(limit, Ordering::Relaxed);
+}
+
+/// Aktuelle Obergrenze für entpackte Daten pro Quelle
+pub fn max_decompressed_size() -> u64 {
+    MAX_DECOMPRESSED_SIZE.load(Ordering::Relaxed)
+}
+
+/// Setzt die Obergrenze für Indexdateien für diesen Prozess
+pub fn set_max_index_size(limit: u64) {
+    MAX_INDEX_SIZE.store(limit, Ordering::Relaxed);
+}
+
+/// Aktuelle Obergrenze für entpackte Indexdateien; nie größer als `max_decompressed_size()`
+pub fn max_index_size() -> u64 {
+    MAX_INDEX_SIZE.load(Ordering::Relaxed).min(max_decompressed_size())
+}
+
+/// Eine Quelle hat mehr Daten geliefert als erlaubt
+#[derive(Debug, thiserror::Error)]
+#[error("{source_name} expands to more than {limit} bytes; refusing to decompress further (possible decompression bomb)")]
+pub struct DecompressionLimitExceeded {
+    pub source_name: String,
+    pub limit: u64,
+}
+
+/// Reader, der nach `limit` Bytes mit `DecompressionLimitExceeded` abbricht
+///
+/// Anders als `Read::take` schneidet er nicht stillschweigend ab: Liefert die Quelle
+/// nach Erreichen der Grenze noch Daten, ist das ein Fehler.
+pub struct LimitedReader<R> {
+    inner: R,
+    remaining: u64,
+    limit: u64,
+    source_name: String,
+}
+
+impl<R: Read> LimitedReader<R> {
+    pub fn new(inner: R, limit: u64, source_name: impl Into<String>) -> Self {
+        LimitedReader { inner, remaining: limit, limit, source_name: source_name.into() }
+    }
+
+    /// Begrenzt auf `max_decompressed_size()`
+    pub fn with_default_limit(inner: R, source_name: impl Into<String>) -> Self {
+        Self::new(inner, max_decompressed_size(), source_name)
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            let mut probe = [0u8; 1];
+            return match self.inner.read(&mut probe)? {
+                0 => Ok(0),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    DecompressionLimitExceeded { source_name: self.source_name.clone(), limit: self.limit },
+                )),
+            };
+        }
+        let max = buf.len().min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        let n = self.inner.read(&mut buf[..max])?;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// Liest eine (ggf. xz-, gzip- oder zstd-komprimierte) Indexdatei begrenzt als Text
+///
+/// Die Kompression ergibt sich aus der Endung von `name` (z.B. `main/binary-amd64/Packages.xz`).
+/// Da der Text vollständig im Speicher landet, gilt `max_index_size()` statt der Grenze für
+/// Paketinhalte.
+pub fn read_to_string_limited(path: &Path, name: &str) -> Result<String> {
+    read_to_string_with_limit(path, name, max_index_size())
+}
+
+fn read_to_string_with_limit(path: &Path, name: &str, limit: u64) -> Result<String> {
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let decoder: Box<dyn Read> = if name.ends_with(".xz") {
+        Box::new(xz2::read::XzDecoder::new(file))
+    } else if name.ends_with(".gz") {
+        Box::new(flate2::read::GzDecoder::new(file))
+    } else if name.ends_with(".zst") {
+        Box::new(zstd::stream::read::Decoder::new(file)?)
+    } else {
+        Box::new(file)
+    };
+
+    let mut content = String::new();
+    LimitedReader::new(decoder, limit, name)
+        .read_to_string(&mut content)
+        .with_context(|| format!("Failed to decompress {}", name))?;
+    Ok(content)
+}
+
+/// Ob ein Pfad absolut ist oder `..` enthält
+fn is_unsafe_path(path: &Path) -> bool {
+    path.components().any(|c| matches!(c, Component::RootDir | Component::Prefix(_) | Component::ParentDir))
+}
+
+/// Ob ein Symlink-Ziel, vom Verzeichnis des Eintrags aus aufgelöst, über die Wurzel hinausführt
+///
+/// Absolute Ziele wie `/etc/alternatives/awk` sind in Paketen üblich und beziehen sich auf
+/// die Installationswurzel; relative Ziele dürfen nicht mehr `..` enthalten, als der
+/// Eintrag tief liegt.
+fn symlink_escapes(entry_path: &Path, target: &Path) -> bool {
+    if target.is_absolute() {
+        return false;
+    }
+    let mut depth = entry_path
+        .parent()
+        .map(|parent| parent.components().filter(|c| matches!(c, Component::Normal(_))).count())
+        .unwrap_or(0);
+    for component in target.components() {
+        match component {
+            Component::ParentDir if depth == 0 => return true,
+            Component::ParentDir => depth -= 1,
+            Component::Normal(_) => depth += 1,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Entpackt ein tar-Archiv nach `dest`, ohne dass ein Eintrag außerhalb landen kann
+///
+/// Abgelehnt werden absolute Pfade, `..`-Einträge, Hardlinks auf solche Pfade und Symlinks,
+/// deren relatives Ziel aus `dest` herausführt. Die Einstellungen des Archivs
+/// (z.B. `set_preserve_permissions`) gelten für alle Einträge.
+pub fn unpack_tar<R: Read>(archive: &mut tar::Archive<R>, dest: &Path) -> Result<()> {
+    std::fs::create_dir_all(dest)?;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if is_unsafe_path(&path) {
+            anyhow::bail!("Refusing to extract {}: absolute or parent-directory path in archive", path.display());
+        }
+        if let Some(target) = entry.link_name()? {
+            let escapes = match entry.header().entry_type() {
+                tar::EntryType::Link => is_unsafe_path(&target),
+                tar::EntryType::Symlink => symlink_escapes(&path, &target),
+                _ => false,
+            };
+            if escapes {
+                anyhow::bail!("Refusing to extract {}: link target {} leaves the destination", path.display(), target.display());
+            }
+        }
+        entry
+            .unpack_in(dest)
+            .with_context(|| format!("Failed to extract {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_limited_reader_rejects_decompression_bomb() {
+        // 64 MiB Nullen komprimieren auf wenige Kilobyte
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        let zeros = vec![0u8; 1024 * 1024];
+        for _ in 0..64 {
+            encoder.write_all(&zeros).unwrap();
+        }
+        let bomb = encoder.finish().unwrap();
+        assert!(bomb.len() < 256 * 1024);
+
+        let mut reader = LimitedReader::new(flate2::read::GzDecoder::new(&bomb[..]), 1024 * 1024, "Packages.gz");
+        let err = io::copy(&mut reader, &mut io::sink()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("Packages.gz expands to more than 1048576 bytes"));
+
+        // Genau an der Grenze ist noch alles in Ordnung
+        let mut reader = LimitedReader::new(&zeros[..], zeros.len() as u64, "exact");
+        assert_eq!(io::copy(&mut reader, &mut io::sink()).unwrap(), zeros.len() as u64);
+    }
+
+    #[test]
+    fn test_index_text_is_capped_in_memory() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("Packages.gz");
+        let mut encoder = flate2::write::GzEncoder::new(std::fs::File::create(&path).unwrap(), flate2::Compression::best());
+        encoder.write_all(&vec![b'a'; 2 * 1024 * 1024]).unwrap();
+        encoder.finish().unwrap();
+
+        let err = read_to_string_with_limit(&path, "main/binary-amd64/Packages.gz", 1024 * 1024).unwrap_err();
+        assert!(err.chain().any(|cause| cause.to_string().contains("expands to more than 1048576 bytes")));
+        assert_eq!(read_to_string_with_limit(&path, "main/binary-amd64/Packages.gz", 4 * 1024 * 1024).unwrap().len(), 2 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_unpack_tar_rejects_traversal() {
+        let temp_dir = TempDir::new().unwrap();
+        let dest = temp_dir.path().join("dest");
+
+        // Der tar-Builder selbst verweigert `..`, daher wird der Name direkt in den Header geschrieben
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        let data = b"owned\n";
+        header.as_gnu_mut().unwrap().name[..13].copy_from_slice(b"../escaped.sh");
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, &data[..]).unwrap();
+        let archive_bytes = builder.into_inner().unwrap();
+
+        let err = unpack_tar(&mut tar::Archive::new(&archive_bytes[..]), &dest).unwrap_err();
+        assert!(err.to_string().contains("../escaped.sh"));
+        assert!(!temp_dir.path().join("escaped.sh").exists());
+    }
+
+    fn symlink_tar(path: &str, target: &str) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_mode(0o777);
+        builder.append_link(&mut header, path, target).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_unpack_tar_rejects_escaping_symlinks() {
+        let temp_dir = TempDir::new().unwrap();
+        let dest = temp_dir.path().join("dest");
+
+        let escaping = symlink_tar("./usr/lib/evil", "../../../etc/passwd");
+        let err = unpack_tar(&mut tar::Archive::new(&escaping[..]), &dest).unwrap_err();
+        assert!(err.to_string().contains("leaves the destination"));
+        assert!(std::fs::symlink_metadata(dest.join("usr/lib/evil")).is_err());
+
+        // Übliche Paket-Symlinks bleiben erlaubt
+        for (path, target) in [("./usr/bin/awk", "/etc/alternatives/awk"), ("./usr/bin/tool", "../lib/tool/tool")] {
+            let archive = symlink_tar(path, target);
+            unpack_tar(&mut tar::Archive::new(&archive[..]), &dest).unwrap();
+            assert_eq!(std::fs::read_link(dest.join(path)).unwrap(), Path::new(target));
+        }
+    }
+}
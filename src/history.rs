@@ -0,0 +1,143 @@
+//! Verlauf der verändernden Operationen (`apt-ng history`)
+//!
+//! Wie `/var/log/apt/history.log` hält apt-ng für jedes Install, Upgrade und Remove den
+//! Zeitpunkt, die Kommandozeile, die geänderten Pakete samt Versionen und das Ergebnis fest.
+//! Gespeichert wird im Index (`Index::record_history`).
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Änderung eines Pakets durch eine Operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageChange {
+    pub name: String,
+    /// Version vor der Operation (None: war nicht installiert)
+    pub old_version: Option<String>,
+    /// Version danach (None: wurde entfernt)
+    pub new_version: Option<String>,
+}
+
+impl PackageChange {
+    /// Art der Änderung für die Ausgabe
+    pub fn action(&self) -> &'static str {
+        match (&self.old_version, &self.new_version) {
+            (None, _) => "install",
+            (Some(_), None) => "remove",
+            (Some(old), Some(new)) => match crate::solver::DependencySolver::compare_versions(new, old) {
+                std::cmp::Ordering::Less => "downgrade",
+                _ => "upgrade",
+            },
+        }
+    }
+}
+
+/// Eine festgehaltene Operation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// Vom Index vergeben, None vor dem Speichern
+    pub id: Option<i64>,
+    /// Unix-Zeitstempel in Sekunden
+    pub timestamp: i64,
+    pub command_line: String,
+    pub changes: Vec<PackageChange>,
+    /// Fehlermeldung, wenn die Operation abgebrochen ist
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Neuer Eintrag mit dem aktuellen Zeitpunkt
+    pub fn new(command_line: &str, changes: Vec<PackageChange>, error: Option<String>) -> Self {
+        HistoryEntry {
+            id: None,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs() as i64)
+                .unwrap_or(0),
+            command_line: command_line.to_string(),
+            changes,
+            error,
+        }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Pakete, die die Operation neu installiert hat; `history undo` entfernt sie wieder
+    pub fn added_packages(&self) -> Vec<&str> {
+        self.changes.iter()
+            .filter(|change| change.old_version.is_none() && change.new_version.is_some())
+            .map(|change| change.name.as_str())
+            .collect()
+    }
+
+    /// Änderungen, die sich nicht durch Entfernen rückgängig machen lassen (Upgrades, Entfernungen)
+    pub fn irreversible_changes(&self) -> Vec<&PackageChange> {
+        self.changes.iter()
+            .filter(|change| change.old_version.is_some())
+            .collect()
+    }
+}
+
+/// Unterschied zwischen zwei Ständen der installierten Pakete (Name → Version), nach Namen sortiert
+pub fn changes_between(before: &HashMap<String, String>, after: &HashMap<String, String>) -> Vec<PackageChange> {
+    let mut changes: Vec<PackageChange> = before.keys()
+        .chain(after.keys().filter(|name| !before.contains_key(*name)))
+        .filter(|name| before.get(*name) != after.get(*name))
+        .map(|name| PackageChange {
+            name: name.clone(),
+            old_version: before.get(name).cloned(),
+            new_version: after.get(name).cloned(),
+        })
+        .collect();
+    changes.sort_by(|a, b| a.name.cmp(&b.name));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::Index;
+
+    #[test]
+    fn test_history_round_trip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        let state = |pkgs: &[(&str, &str)]| pkgs.iter()
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        let before = state(&[("libc6", "2.36"), ("curl", "7.88"), ("old", "1.0")]);
+        let after = state(&[("libc6", "2.36"), ("curl", "8.5"), ("nginx", "1.24"), ("libpcre", "8.39")]);
+        let changes = changes_between(&before, &after);
+        assert_eq!(
+            changes.iter().map(|change| (change.name.as_str(), change.action())).collect::<Vec<_>>(),
+            [("curl", "upgrade"), ("libpcre", "install"), ("nginx", "install"), ("old", "remove")]
+        );
+
+        let first = HistoryEntry::new("apt-ng install nginx", changes, None);
+        let first_id = index.record_history(&first).unwrap();
+        let failed = HistoryEntry::new("apt-ng remove libc6", Vec::new(), Some("permission denied".to_string()));
+        index.record_history(&failed).unwrap();
+
+        // Neueste zuerst, alle Felder bleiben erhalten
+        let entries = index.history(10).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(!entries[0].succeeded());
+        assert_eq!(entries[0].error.as_deref(), Some("permission denied"));
+        assert_eq!(entries[1], HistoryEntry { id: Some(first_id), ..first });
+        assert_eq!(index.history_entry(first_id).unwrap(), Some(entries[1].clone()));
+        assert_eq!(index.history(1).unwrap().len(), 1);
+
+        assert_eq!(entries[1].added_packages(), ["libpcre", "nginx"]);
+        assert_eq!(
+            entries[1].irreversible_changes().iter().map(|change| change.name.as_str()).collect::<Vec<_>>(),
+            ["curl", "old"]
+        );
+
+        // Ein unlesbarer Eintrag wird gemeldet statt als Operation ohne Änderungen zu erscheinen
+        index.conn().execute("UPDATE history SET changes = 'not json' WHERE id = ?1", [first_id]).unwrap();
+        assert!(index.history_entry(first_id).is_err());
+        assert!(index.history(10).is_err());
+    }
+}
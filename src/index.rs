@@ -1,6 +1,25 @@
 use rusqlite::{Connection, Result as SqliteResult};
 use anyhow::Result;
-use crate::package::PackageManifest;
+use crate::apt_parser::ContentsEntry;
+use crate::package::{FileEntry, PackageManifest};
+use crate::solver::DependencySolver;
+use crate::delta::DeltaSavings;
+use crate::history::{HistoryEntry, PackageChange};
+use crate::error::AptNgError;
+
+/// `repo_url` für Einträge in `index_meta`, die zu keinem Repository gehören
+const GLOBAL_META_SCOPE: &str = "";
+const DELTA_PACKAGES_KEY: &str = "delta_packages";
+const DELTA_BYTES_SAVED_KEY: &str = "delta_bytes_saved";
+
+/// Schlüssel in `index_meta`: Host, der die Downloads eines Repositories zuletzt ausgeliefert hat
+pub const RESOLVED_HOST_META_KEY: &str = "resolved_host";
+
+/// Ab diesem Anteil freier Seiten lohnt sich ein VACUUM nach `update`
+const VACUUM_FREE_RATIO: f64 = 0.25;
+
+/// So lange wartet SQLite auf eine von einem anderen Prozess gesperrte Datenbank
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 
 pub struct Index {
     conn: Connection,
@@ -13,13 +32,118 @@ impl Index {
         if let Some(parent) = std::path::Path::new(db_path).parent() {
             std::fs::create_dir_all(parent)?;
         }
+        Self::open(db_path).map_err(|e| Self::open_error(db_path, e))
+    }
+    
+    fn open(db_path: &str) -> SqliteResult<Self> {
         let conn = Connection::open(db_path)?;
+        // Kurz warten statt sofort mit SQLITE_BUSY abzubrechen, wenn ein anderer Prozess schreibt
+        conn.busy_timeout(BUSY_TIMEOUT)?;
         let index = Index { conn };
         index.init_schema()?;
         index.optimize_for_bulk_inserts()?;
         Ok(index)
     }
     
+    /// Übersetzt gesperrte und beschädigte Datenbanken in verständliche Fehler
+    fn open_error(db_path: &str, e: rusqlite::Error) -> anyhow::Error {
+        use rusqlite::ErrorCode;
+        
+        match e.sqlite_error_code() {
+            Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => {
+                AptNgError::IndexLocked { path: db_path.to_string() }.into()
+            }
+            Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase) => {
+                AptNgError::IndexCorrupt { path: db_path.to_string(), message: e.to_string() }.into()
+            }
+            _ => e.into(),
+        }
+    }
+    
+    /// Sichert die vorhandene Datenbank nach `<db_path>.bak-<Zeitstempel>` und legt sie neu an
+    ///
+    /// Für beschädigte Indizes und `--rebuild-index`. Installierte Pakete, Verlauf und
+    /// Repositories werden aus der Sicherung übernommen, soweit sie noch lesbar sind; nur die
+    /// Paketdaten der Repositories lädt das nächste `update` neu. Liefert den neuen Index und
+    /// den Pfad der Sicherung (None, wenn es keine Datei gab).
+    pub fn rebuild(db_path: &str) -> Result<(Self, Option<std::path::PathBuf>)> {
+        let mut backup = None;
+        if std::path::Path::new(db_path).exists() {
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let target = format!("{}.bak-{}", db_path, timestamp);
+            // WAL und Shared Memory gehören zur Datenbank und werden mit verschoben
+            for suffix in ["", "-wal", "-shm"] {
+                let file = format!("{}{}", db_path, suffix);
+                if std::path::Path::new(&file).exists() {
+                    std::fs::rename(&file, format!("{}{}", target, suffix))?;
+                }
+            }
+            backup = Some(std::path::PathBuf::from(target));
+        }
+        
+        let index = Self::new(db_path)?;
+        if let Some(backup) = &backup {
+            index.restore_local_state(backup)?;
+        }
+        Ok((index, backup))
+    }
+    
+    /// Übernimmt installierte Pakete, Verlauf und Repositories aus einer gesicherten Datenbank
+    ///
+    /// Jede Tabelle wird für sich kopiert; ist sie (oder die ganze Sicherung) nicht mehr
+    /// lesbar, bleibt sie leer. Von den Paketen bleiben nur die installierten erhalten (samt ihrer ID).
+    fn restore_local_state(&self, backup: &std::path::Path) -> Result<()> {
+        if let Err(e) = self.conn.execute("ATTACH DATABASE ?1 AS backup", [backup.to_string_lossy()]) {
+            tracing::warn!(backup = %backup.display(), error = %e, "index backup is not readable; nothing restored");
+            return Ok(());
+        }
+        
+        let tables = [
+            ("packages", "WHERE id IN (SELECT pkg_id FROM backup.installed)"),
+            ("installed", ""),
+            ("history", ""),
+            ("repos", ""),
+            ("index_meta", ""),
+        ];
+        for (table, filter) in tables {
+            if let Err(e) = self.copy_from_backup(table, filter) {
+                tracing::warn!(table, error = %e, "could not restore table from index backup");
+            }
+        }
+        
+        self.conn.execute("DETACH DATABASE backup", [])?;
+        Ok(())
+    }
+    
+    /// Kopiert die Zeilen einer Tabelle aus `backup`; nur Spalten, die beide Schemata kennen
+    fn copy_from_backup(&self, table: &str, filter: &str) -> SqliteResult<usize> {
+        let columns = |schema: &str| -> SqliteResult<Vec<String>> {
+            let mut stmt = self.conn.prepare(&format!("PRAGMA {}.table_info({})", schema, table))?;
+            let names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+            names.collect()
+        };
+        let current = columns("main")?;
+        let shared: Vec<String> = columns("backup")?
+            .into_iter()
+            .filter(|column| current.contains(column))
+            .collect();
+        if shared.is_empty() {
+            return Ok(0);
+        }
+        
+        let columns = shared.join(", ");
+        let tx = self.conn.unchecked_transaction()?;
+        let copied = tx.execute(
+            &format!("INSERT INTO main.{table} ({columns}) SELECT {columns} FROM backup.{table} {filter}"),
+            [],
+        )?;
+        tx.commit()?;
+        Ok(copied)
+    }
+    
     /// Optimiert SQLite für Bulk-Inserts (schnelleres Indexing)
     fn optimize_for_bulk_inserts(&self) -> SqliteResult<()> {
         // WAL-Mode für bessere Concurrency und Performance
@@ -38,6 +162,43 @@ impl Index {
         Ok(())
     }
     
+    /// Logische Größe der Datenbank in Bytes (belegte und freie Seiten)
+    pub fn size_bytes(&self) -> Result<u64> {
+        let page_count: i64 = self.conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let page_size: i64 = self.conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        Ok((page_count * page_size) as u64)
+    }
+    
+    /// Prüft, ob nach vielen Ersetzungen genug freie Seiten für ein VACUUM angefallen sind
+    pub fn needs_vacuum(&self) -> Result<bool> {
+        let page_count: i64 = self.conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let free_pages: i64 = self.conn.query_row("PRAGMA freelist_count", [], |row| row.get(0))?;
+        Ok(page_count > 0 && free_pages as f64 / page_count as f64 >= VACUUM_FREE_RATIO)
+    }
+    
+    /// Aktualisiert die Statistiken des Query-Planers (`PRAGMA optimize`)
+    pub fn optimize(&self) -> Result<()> {
+        self.conn.execute_batch("PRAGMA optimize")?;
+        Ok(())
+    }
+    
+    /// Baut die Datenbankdatei neu auf und gibt freie Seiten an das Dateisystem zurück
+    pub fn vacuum(&self) -> Result<()> {
+        self.conn.execute_batch("VACUUM")?;
+        // Im WAL-Modus landet der neue Inhalt zunächst im Log; danach auch dieses kürzen
+        self.conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE)")?;
+        Ok(())
+    }
+    
+    /// Bulk-Insert-Modus für die Lebensdauer des zurückgegebenen Guards
+    ///
+    /// Der Guard beendet den Modus auch dann, wenn der Aufrufer vorher mit einem Fehler
+    /// aussteigt, sodass die Indizes nie fehlen; `BulkInsert::finish` meldet Fehler dabei.
+    pub fn bulk_insert(&self) -> Result<BulkInsert<'_>> {
+        self.begin_bulk_insert()?;
+        Ok(BulkInsert { index: self, finished: false })
+    }
+    
     /// Aktiviert Bulk-Insert-Modus (deaktiviert Indizes temporär)
     pub fn begin_bulk_insert(&self) -> Result<()> {
         // Deaktiviere Indizes temporär für schnelleres Inserting
@@ -85,6 +246,16 @@ impl Index {
                         repo_id INTEGER,
                         timestamp INTEGER,
                         filename TEXT,
+                        maintainer TEXT,
+                        homepage TEXT,
+                        section TEXT,
+                        priority TEXT,
+                        installed_size INTEGER,
+                        conflicts TEXT,
+                        replaces TEXT,
+                        recommends TEXT,
+                        suggests TEXT,
+                        available INTEGER DEFAULT 1,
                         UNIQUE(name, version, arch)
                     )",
                     [],
@@ -99,7 +270,12 @@ impl Index {
                 rtt_ms INTEGER,
                 enabled INTEGER DEFAULT 1,
                 suite TEXT,
-                components TEXT
+                components TEXT,
+                throughput_bps INTEGER,
+                codename TEXT,
+                architectures TEXT,
+                signed_by TEXT,
+                trusted INTEGER
             )",
             [],
         )?;
@@ -114,6 +290,44 @@ impl Index {
             [],
         )?;
         
+        // Metadaten je Repository (z.B. zuletzt gesehenes Release-Datum für den Replay-Schutz)
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS index_meta (
+                repo_url TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY(repo_url, key)
+            )",
+            [],
+        )?;
+        
+        // Verlauf der Install-, Upgrade- und Remove-Operationen (`apt-ng history`)
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp INTEGER NOT NULL,
+                command_line TEXT NOT NULL,
+                changes TEXT NOT NULL,
+                error TEXT
+            )",
+            [],
+        )?;
+        
+        // Dateien aus den Contents-Indizes der Repositories (`apt-ng file-search`)
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS contents (
+                repo_id INTEGER NOT NULL,
+                path TEXT NOT NULL,
+                package TEXT NOT NULL
+            )",
+            [],
+        )?;
+        
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_contents_repo ON contents(repo_id)",
+            [],
+        )?;
+        
         // Indexe für schnelle Suchen
         self.conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_packages_name ON packages(name)",
@@ -146,6 +360,31 @@ impl Index {
                 // Füge filename-Spalte hinzu
                 self.conn.execute("ALTER TABLE packages ADD COLUMN filename TEXT", [])?;
             }
+            // Beschreibende Felder (Maintainer, Homepage, Section, Priority)
+            for column in ["maintainer", "homepage", "section", "priority"] {
+                if !sql.contains(column) {
+                    self.conn.execute(&format!("ALTER TABLE packages ADD COLUMN {} TEXT", column), [])?;
+                }
+            }
+            if !sql.contains("installed_size") {
+                self.conn.execute("ALTER TABLE packages ADD COLUMN installed_size INTEGER", [])?;
+            }
+            // Conflicts/Breaks und Replaces (JSON wie provides/depends)
+            for column in ["conflicts", "replaces"] {
+                if !sql.contains(column) {
+                    self.conn.execute(&format!("ALTER TABLE packages ADD COLUMN {} TEXT", column), [])?;
+                }
+            }
+            // Recommends und Suggests (JSON, nur für `show` und `--install-suggests`)
+            for column in ["recommends", "suggests"] {
+                if !sql.contains(column) {
+                    self.conn.execute(&format!("ALTER TABLE packages ADD COLUMN {} TEXT", column), [])?;
+                }
+            }
+            // 0 = installiert, aber upstream nicht mehr verfügbar
+            if !sql.contains("available") {
+                self.conn.execute("ALTER TABLE packages ADD COLUMN available INTEGER DEFAULT 1", [])?;
+            }
         }
 
         Ok(())
@@ -169,11 +408,62 @@ impl Index {
                 // Füge components-Spalte hinzu
                 self.conn.execute("ALTER TABLE repos ADD COLUMN components TEXT", [])?;
             }
+            if !sql.contains("throughput_bps") {
+                // Füge throughput_bps-Spalte hinzu
+                self.conn.execute("ALTER TABLE repos ADD COLUMN throughput_bps INTEGER", [])?;
+            }
+            if !sql.contains("codename") {
+                // Füge codename-Spalte hinzu
+                self.conn.execute("ALTER TABLE repos ADD COLUMN codename TEXT", [])?;
+            }
+            if !sql.contains("architectures") {
+                self.conn.execute("ALTER TABLE repos ADD COLUMN architectures TEXT", [])?;
+            }
+            // Signaturrichtlinie je Repository (signed-by= und trusted= aus sources.list)
+            if !sql.contains("signed_by") {
+                self.conn.execute("ALTER TABLE repos ADD COLUMN signed_by TEXT", [])?;
+            }
+            if !sql.contains("trusted") {
+                self.conn.execute("ALTER TABLE repos ADD COLUMN trusted INTEGER", [])?;
+            }
         }
         
         Ok(())
     }
     
+    /// Liest eine als JSON gespeicherte Liste; ältere Zeilen haben die Spalte noch nicht befüllt
+    fn json_list(value: Option<String>) -> Vec<String> {
+        value.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default()
+    }
+    
+    /// Erstellt ein Manifest aus einer Zeile mit den Spalten
+    /// name, version, arch, provides, depends, size, checksum, timestamp, repo_id, filename,
+    /// maintainer, homepage, section, priority, installed_size, conflicts, replaces, recommends, suggests
+    fn manifest_from_row(row: &rusqlite::Row) -> rusqlite::Result<PackageManifest> {
+        Ok(PackageManifest {
+            name: row.get(0)?,
+            version: row.get(1)?,
+            arch: row.get(2)?,
+            provides: serde_json::from_str(row.get::<_, String>(3)?.as_str()).unwrap_or_default(),
+            depends: serde_json::from_str(row.get::<_, String>(4)?.as_str()).unwrap_or_default(),
+            conflicts: Self::json_list(row.get(15)?),
+            replaces: Self::json_list(row.get(16)?),
+            recommends: Self::json_list(row.get(17)?),
+            suggests: Self::json_list(row.get(18)?),
+            files: vec![],
+            size: row.get(5)?,
+            checksum: row.get(6)?,
+            timestamp: row.get(7)?,
+            repo_id: row.get::<_, Option<i64>>(8)?,
+            filename: row.get::<_, Option<String>>(9)?.filter(|s| !s.is_empty()),
+            maintainer: row.get(10)?,
+            homepage: row.get(11)?,
+            section: row.get(12)?,
+            priority: row.get(13)?,
+            installed_size: row.get::<_, Option<i64>>(14)?.map(|size| size as u64),
+        })
+    }
+    
     /// Gibt die Datenbank-Verbindung zurück (für erweiterte Operationen)
     pub fn conn(&self) -> &Connection {
         &self.conn
@@ -183,8 +473,9 @@ impl Index {
     pub fn add_package(&self, manifest: &PackageManifest, repo_id: i64) -> Result<()> {
         self.conn.execute(
             "INSERT OR REPLACE INTO packages 
-             (name, version, arch, provides, depends, size, checksum, repo_id, timestamp, filename)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+             (name, version, arch, provides, depends, size, checksum, repo_id, timestamp, filename,
+              maintainer, homepage, section, priority, installed_size, conflicts, replaces, recommends, suggests)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
             rusqlite::params![
                 manifest.name,
                 manifest.version,
@@ -196,28 +487,78 @@ impl Index {
                 repo_id,
                 manifest.timestamp,
                 manifest.filename.as_deref().unwrap_or(""),
+                manifest.maintainer,
+                manifest.homepage,
+                manifest.section,
+                manifest.priority,
+                manifest.installed_size.map(|size| size as i64),
+                serde_json::to_string(&manifest.conflicts).unwrap_or_default(),
+                serde_json::to_string(&manifest.replaces).unwrap_or_default(),
+                serde_json::to_string(&manifest.recommends).unwrap_or_default(),
+                serde_json::to_string(&manifest.suggests).unwrap_or_default(),
             ],
         )?;
         Ok(())
     }
     
+    /// Nimmt eine lokale Paketdatei (ohne Repository) in den Index auf
+    ///
+    /// Ist dieselbe Version bereits aus einem Repository bekannt, bleibt deren Eintrag
+    /// unverändert. Liefert true, wenn ein neuer Eintrag angelegt wurde.
+    pub fn add_local_package(&self, manifest: &PackageManifest) -> Result<bool> {
+        let inserted = self.conn.execute(
+            "INSERT INTO packages
+             (name, version, arch, provides, depends, size, checksum, repo_id, timestamp, filename,
+              maintainer, homepage, section, priority, installed_size, conflicts, replaces, recommends, suggests)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, NULL, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)
+             ON CONFLICT(name, version, arch) DO NOTHING",
+            rusqlite::params![
+                manifest.name,
+                manifest.version,
+                manifest.arch,
+                serde_json::to_string(&manifest.provides).unwrap_or_default(),
+                serde_json::to_string(&manifest.depends).unwrap_or_default(),
+                manifest.size as i64,
+                manifest.checksum,
+                manifest.timestamp,
+                manifest.filename.as_deref().unwrap_or(""),
+                manifest.maintainer,
+                manifest.homepage,
+                manifest.section,
+                manifest.priority,
+                manifest.installed_size.map(|size| size as i64),
+                serde_json::to_string(&manifest.conflicts).unwrap_or_default(),
+                serde_json::to_string(&manifest.replaces).unwrap_or_default(),
+                serde_json::to_string(&manifest.recommends).unwrap_or_default(),
+                serde_json::to_string(&manifest.suggests).unwrap_or_default(),
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+    
+    /// Entfernt Einträge lokaler Paketdateien (`repo_id` NULL), die nicht installiert sind
+    ///
+    /// Solche Zeilen gehören zu keinem Repository und werden daher von keinem Update
+    /// ersetzt. Liefert die Anzahl entfernter Einträge.
+    pub fn prune_local_packages(&self) -> Result<usize> {
+        Ok(self.conn.execute(
+            "DELETE FROM packages
+             WHERE repo_id IS NULL
+               AND id NOT IN (SELECT pkg_id FROM installed)",
+            [],
+        )?)
+    }
+    
     /// Fügt mehrere Pakete in einer Transaktion hinzu (für bessere Performance)
     pub fn add_packages_batch(&self, manifests: &[PackageManifest], repo_id: i64) -> Result<()> {
         // Serialisiere JSON-Daten vorher für bessere Performance
-        let serialized_data: Vec<(String, String, String, String, String, i64, String, i64, i64, String)> = manifests
+        let serialized_data: Vec<(&PackageManifest, String, String)> = manifests
             .iter()
             .map(|manifest| {
                 (
-                    manifest.name.clone(),
-                    manifest.version.clone(),
-                    manifest.arch.clone(),
+                    manifest,
                     serde_json::to_string(&manifest.provides).unwrap_or_default(),
                     serde_json::to_string(&manifest.depends).unwrap_or_default(),
-                    manifest.size as i64,
-                    manifest.checksum.clone(),
-                    repo_id,
-                    manifest.timestamp,
-                    manifest.filename.as_deref().unwrap_or("").to_string(),
                 )
             })
             .collect();
@@ -226,22 +567,32 @@ impl Index {
         
         {
             let mut stmt = tx.prepare(
-                "INSERT OR REPLACE INTO packages (name, version, arch, provides, depends, size, checksum, repo_id, timestamp, filename)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"
+                "INSERT OR REPLACE INTO packages (name, version, arch, provides, depends, size, checksum, repo_id, timestamp, filename,
+                 maintainer, homepage, section, priority, installed_size, conflicts, replaces, recommends, suggests)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)"
             )?;
             
-            for (name, version, arch, provides, depends, size, checksum, repo_id_val, timestamp, filename) in serialized_data {
+            for (manifest, provides, depends) in serialized_data {
                 stmt.execute(rusqlite::params![
-                    name,
-                    version,
-                    arch,
+                    manifest.name,
+                    manifest.version,
+                    manifest.arch,
                     provides,
                     depends,
-                    size,
-                    checksum,
-                    repo_id_val,
-                    timestamp,
-                    filename,
+                    manifest.size as i64,
+                    manifest.checksum,
+                    repo_id,
+                    manifest.timestamp,
+                    manifest.filename.as_deref().unwrap_or(""),
+                    manifest.maintainer,
+                    manifest.homepage,
+                    manifest.section,
+                    manifest.priority,
+                    manifest.installed_size.map(|size| size as i64),
+                    serde_json::to_string(&manifest.conflicts).unwrap_or_default(),
+                    serde_json::to_string(&manifest.replaces).unwrap_or_default(),
+                    serde_json::to_string(&manifest.recommends).unwrap_or_default(),
+                    serde_json::to_string(&manifest.suggests).unwrap_or_default(),
                 ])?;
             }
         }
@@ -250,10 +601,148 @@ impl Index {
         Ok(())
     }
     
+    /// Ersetzt den kompletten Paketbestand eines Repositories atomar
+    ///
+    /// Die neuen Zeilen landen zunächst in einer Staging-Tabelle; erst danach werden in
+    /// derselben Transaktion verschwundene Pakete gelöscht und die übrigen per Upsert
+    /// übernommen. Ein abgebrochenes Update hinterlässt so den alten Stand statt einer
+    /// Mischung. Bestehende Zeilen behalten ihre ID, damit `installed` gültig bleibt;
+    /// installierte Pakete werden nicht entfernt, sondern als nicht mehr verfügbar
+    /// markiert. Liefert die Zahl gelöschter Pakete.
+    pub fn replace_repo_packages(&self, repo_id: i64, manifests: &[PackageManifest]) -> Result<usize> {
+        self.store_repo_packages(repo_id, manifests, true)
+    }
+    
+    /// Übernimmt Pakete eines Repositories wie `replace_repo_packages`, löscht aber nichts
+    ///
+    /// Für Updates, bei denen nur ein Teil der Packages-Indizes geladen werden konnte: Pakete
+    /// aus den fehlenden Indizes sind nicht verschwunden, sondern nur nicht gelesen worden.
+    pub fn merge_repo_packages(&self, repo_id: i64, manifests: &[PackageManifest]) -> Result<()> {
+        self.store_repo_packages(repo_id, manifests, false).map(|_| ())
+    }
+    
+    fn store_repo_packages(&self, repo_id: i64, manifests: &[PackageManifest], prune: bool) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        
+        tx.execute_batch(
+            "CREATE TEMP TABLE IF NOT EXISTS packages_staging (
+                name TEXT NOT NULL,
+                version TEXT NOT NULL,
+                arch TEXT NOT NULL,
+                provides TEXT,
+                depends TEXT,
+                size INTEGER,
+                checksum TEXT,
+                timestamp INTEGER,
+                filename TEXT,
+                maintainer TEXT,
+                homepage TEXT,
+                section TEXT,
+                priority TEXT,
+                installed_size INTEGER,
+                conflicts TEXT,
+                replaces TEXT,
+                recommends TEXT,
+                suggests TEXT,
+                UNIQUE(name, version, arch) ON CONFLICT REPLACE
+            );
+            DELETE FROM packages_staging;"
+        )?;
+        
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO packages_staging (name, version, arch, provides, depends, size, checksum, timestamp, filename,
+                 maintainer, homepage, section, priority, installed_size, conflicts, replaces, recommends, suggests)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)"
+            )?;
+            for manifest in manifests {
+                stmt.execute(rusqlite::params![
+                    manifest.name,
+                    manifest.version,
+                    manifest.arch,
+                    serde_json::to_string(&manifest.provides).unwrap_or_default(),
+                    serde_json::to_string(&manifest.depends).unwrap_or_default(),
+                    manifest.size as i64,
+                    manifest.checksum,
+                    manifest.timestamp,
+                    manifest.filename.as_deref().unwrap_or(""),
+                    manifest.maintainer,
+                    manifest.homepage,
+                    manifest.section,
+                    manifest.priority,
+                    manifest.installed_size.map(|size| size as i64),
+                    serde_json::to_string(&manifest.conflicts).unwrap_or_default(),
+                    serde_json::to_string(&manifest.replaces).unwrap_or_default(),
+                    serde_json::to_string(&manifest.recommends).unwrap_or_default(),
+                    serde_json::to_string(&manifest.suggests).unwrap_or_default(),
+                ])?;
+            }
+        }
+        
+        let mut pruned = 0;
+        if prune {
+            // Upstream verschwundene Pakete entfernen (installierte bleiben erhalten)
+            pruned = tx.execute(
+                "DELETE FROM packages
+                 WHERE repo_id = ?1
+                   AND id NOT IN (SELECT pkg_id FROM installed)
+                   AND NOT EXISTS (
+                       SELECT 1 FROM packages_staging s
+                       WHERE s.name = packages.name AND s.version = packages.version AND s.arch = packages.arch
+                   )",
+                [repo_id],
+            )?;
+            
+            // Übrig gebliebene, upstream verschwundene Zeilen sind installiert: nur markieren
+            tx.execute(
+                "UPDATE packages SET available = 0
+                 WHERE repo_id = ?1
+                   AND NOT EXISTS (
+                       SELECT 1 FROM packages_staging s
+                       WHERE s.name = packages.name AND s.version = packages.version AND s.arch = packages.arch
+                   )",
+                [repo_id],
+            )?;
+        }
+        
+        // Neue und geänderte Pakete übernehmen; vorhandene Zeilen behalten ihre ID
+        tx.execute(
+            "INSERT INTO packages (name, version, arch, provides, depends, size, checksum, repo_id, timestamp, filename,
+             maintainer, homepage, section, priority, installed_size, conflicts, replaces, recommends, suggests)
+             SELECT name, version, arch, provides, depends, size, checksum, ?1, timestamp, filename,
+                    maintainer, homepage, section, priority, installed_size, conflicts, replaces, recommends, suggests
+             FROM packages_staging WHERE true
+             ON CONFLICT(name, version, arch) DO UPDATE SET
+                provides = excluded.provides,
+                depends = excluded.depends,
+                size = excluded.size,
+                checksum = excluded.checksum,
+                repo_id = excluded.repo_id,
+                timestamp = excluded.timestamp,
+                filename = excluded.filename,
+                maintainer = excluded.maintainer,
+                homepage = excluded.homepage,
+                section = excluded.section,
+                priority = excluded.priority,
+                installed_size = excluded.installed_size,
+                conflicts = excluded.conflicts,
+                replaces = excluded.replaces,
+                recommends = excluded.recommends,
+                suggests = excluded.suggests,
+                available = 1",
+            [repo_id],
+        )?;
+        
+        tx.execute("DELETE FROM packages_staging", [])?;
+        tx.commit()?;
+        Ok(pruned)
+    }
+    
     /// Sucht nach Paketen im Index (fuzzy search - findet auch Teilstrings)
     pub fn search(&self, query: &str) -> Result<Vec<PackageManifest>> {
         let mut stmt = self.conn.prepare(
-            "SELECT name, version, arch, provides, depends, size, checksum, timestamp, repo_id, filename
+            "SELECT name, version, arch, provides, depends, size, checksum, timestamp, repo_id, filename,
+                    maintainer, homepage, section, priority, installed_size, conflicts, replaces, recommends, suggests
              FROM packages
              WHERE name LIKE ?1 OR name LIKE ?2
              ORDER BY name, version DESC"
@@ -264,23 +753,7 @@ impl Index {
         
         let rows = stmt.query_map(
             rusqlite::params![pattern, prefix_pattern],
-            |row| {
-                Ok(PackageManifest {
-                    name: row.get(0)?,
-                    version: row.get(1)?,
-                    arch: row.get(2)?,
-                    provides: serde_json::from_str(row.get::<_, String>(3)?.as_str()).unwrap_or_default(),
-                    depends: serde_json::from_str(row.get::<_, String>(4)?.as_str()).unwrap_or_default(),
-                    conflicts: vec![],
-                    replaces: vec![],
-                    files: vec![],
-                    size: row.get(5)?,
-                    checksum: row.get(6)?,
-                    timestamp: row.get(7)?,
-                    repo_id: row.get::<_, Option<i64>>(8)?,
-                    filename: row.get::<_, Option<String>>(9)?.filter(|s| !s.is_empty()),
-                })
-            }
+            Self::manifest_from_row
         )?;
         
         let mut results = Vec::new();
@@ -291,66 +764,95 @@ impl Index {
     }
     
     /// Sucht nach Paketen mit exaktem Namen (für Upgrades)
+    ///
+    /// Die Versionen sind absteigend nach Debian-Versionsordnung sortiert
+    /// (SQL sortiert nur als String, z.B. "1.9" > "1.10").
     pub fn search_exact(&self, package_name: &str) -> Result<Vec<PackageManifest>> {
         let mut stmt = self.conn.prepare(
-            "SELECT name, version, arch, provides, depends, size, checksum, timestamp, repo_id, filename
+            "SELECT name, version, arch, provides, depends, size, checksum, timestamp, repo_id, filename,
+                    maintainer, homepage, section, priority, installed_size, conflicts, replaces, recommends, suggests
              FROM packages
-             WHERE name = ?1
-             ORDER BY version DESC"
+             WHERE name = ?1"
         )?;
         
         let rows = stmt.query_map(
             [package_name],
-            |row| {
-                Ok(PackageManifest {
-                    name: row.get(0)?,
-                    version: row.get(1)?,
-                    arch: row.get(2)?,
-                    provides: serde_json::from_str(row.get::<_, String>(3)?.as_str()).unwrap_or_default(),
-                    depends: serde_json::from_str(row.get::<_, String>(4)?.as_str()).unwrap_or_default(),
-                    conflicts: vec![],
-                    replaces: vec![],
-                    files: vec![],
-                    size: row.get(5)?,
-                    checksum: row.get(6)?,
-                    timestamp: row.get(7)?,
-                    repo_id: row.get::<_, Option<i64>>(8)?,
-                    filename: row.get::<_, Option<String>>(9)?.filter(|s| !s.is_empty()),
-                })
-            }
+            Self::manifest_from_row
         )?;
         
         let mut results = Vec::new();
         for row in rows {
             results.push(row?);
         }
+        results.sort_by(|a, b| DependencySolver::compare_versions(&b.version, &a.version));
         Ok(results)
     }
     
+    /// Paketnamen, die `name` ähneln (für "Meinten Sie ...?"), die ähnlichsten zuerst
+    ///
+    /// Berücksichtigt Tippfehler (Levenshtein-Distanz bis ein Drittel der Namenslänge, mindestens 2)
+    /// sowie Namen, die `name` enthalten (über `search`).
+    pub fn similar_package_names(&self, name: &str, limit: usize) -> Result<Vec<String>> {
+        let max_distance = (name.chars().count() / 3).max(2);
+        let mut stmt = self.conn.prepare("SELECT DISTINCT name FROM packages")?;
+        let names = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        
+        let mut candidates: Vec<(usize, String)> = Vec::new();
+        for candidate in names {
+            let candidate = candidate?;
+            if candidate.len().abs_diff(name.len()) > max_distance || candidate == name {
+                continue;
+            }
+            let distance = edit_distance(name, &candidate);
+            if distance <= max_distance {
+                candidates.push((distance, candidate));
+            }
+        }
+        candidates.sort();
+        
+        let mut similar: Vec<String> = candidates.into_iter().map(|(_, candidate)| candidate).collect();
+        for pkg in self.search(name)? {
+            if pkg.name != name && !similar.contains(&pkg.name) {
+                similar.push(pkg.name);
+            }
+        }
+        similar.truncate(limit);
+        Ok(similar)
+    }
+    
+    /// Pakete, die den (virtuellen) Paketnamen `name` per Provides bereitstellen
+    pub fn providers_of(&self, name: &str) -> Result<Vec<PackageManifest>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name, version, arch, provides, depends, size, checksum, timestamp, repo_id, filename,
+                    maintainer, homepage, section, priority, installed_size, conflicts, replaces, recommends, suggests
+             FROM packages
+             WHERE provides LIKE ?1
+             ORDER BY name"
+        )?;
+        
+        // Vorfilter per LIKE, danach exakter Vergleich ohne Version und Architektur ("mta (= 1.0)")
+        let rows = stmt.query_map([format!("%{}%", name)], Self::manifest_from_row)?;
+        let mut providers: Vec<PackageManifest> = Vec::new();
+        for row in rows {
+            let pkg = row?;
+            let provides_name = pkg.provides.iter()
+                .any(|provided| crate::apt_parser::Provide::parse(provided).name == name);
+            if provides_name && !providers.iter().any(|known| known.name == pkg.name) {
+                providers.push(pkg);
+            }
+        }
+        Ok(providers)
+    }
+    
     /// Gibt Paketinformationen zurück
     /// Get all packages from the index (for solver population)
     pub fn get_all_packages(&self) -> Result<Vec<PackageManifest>> {
         let mut stmt = self.conn.prepare(
-            "SELECT name, version, arch, provides, depends, size, checksum, timestamp, repo_id, filename FROM packages"
-        )?;
-        
-        let packages_iter = stmt.query_map([], |row| {
-            Ok(PackageManifest {
-                name: row.get(0)?,
-                version: row.get(1)?,
-                arch: row.get(2)?,
-                provides: serde_json::from_str(row.get::<_, String>(3)?.as_str()).unwrap_or_default(),
-                depends: serde_json::from_str(row.get::<_, String>(4)?.as_str()).unwrap_or_default(),
-                conflicts: vec![],
-                replaces: vec![],
-                files: vec![],
-                size: row.get(5)?,
-                checksum: row.get(6)?,
-                timestamp: row.get(7)?,
-                repo_id: row.get::<_, Option<i64>>(8)?,
-                filename: row.get::<_, Option<String>>(9)?.filter(|s| !s.is_empty()),
-            })
-        })?;
+            "SELECT name, version, arch, provides, depends, size, checksum, timestamp, repo_id, filename,
+                    maintainer, homepage, section, priority, installed_size, conflicts, replaces, recommends, suggests FROM packages"
+        )?;
+        
+        let packages_iter = stmt.query_map([], Self::manifest_from_row)?;
         
         let mut packages = Vec::new();
         for pkg in packages_iter {
@@ -361,39 +863,125 @@ impl Index {
     }
     
     pub fn show(&self, package_name: &str) -> Result<Option<PackageManifest>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT name, version, arch, provides, depends, size, checksum, timestamp, repo_id, filename
-             FROM packages
-             WHERE name = ?1
-             ORDER BY version DESC
-             LIMIT 1"
-        )?;
-        
-        let result = stmt.query_row([package_name], |row| {
-            Ok(PackageManifest {
-                name: row.get(0)?,
-                version: row.get(1)?,
-                arch: row.get(2)?,
-                provides: serde_json::from_str(row.get::<_, String>(3)?.as_str()).unwrap_or_default(),
-                depends: serde_json::from_str(row.get::<_, String>(4)?.as_str()).unwrap_or_default(),
-                conflicts: vec![],
-                replaces: vec![],
-                files: vec![],
-                size: row.get(5)?,
-                checksum: row.get(6)?,
-                timestamp: row.get(7)?,
-                repo_id: row.get::<_, Option<i64>>(8)?,
-                filename: row.get::<_, Option<String>>(9)?.filter(|s| !s.is_empty()),
-            })
-        });
+        Ok(self.search_exact(package_name)?.into_iter().next())
+    }
+    
+    /// Gibt die installierte Version eines Pakets zurück
+    pub fn installed_version(&self, package_name: &str) -> Result<Option<String>> {
+        let result = self.conn.query_row(
+            "SELECT p.version FROM packages p
+             INNER JOIN installed i ON p.id = i.pkg_id
+             WHERE p.name = ?1",
+            [package_name],
+            |row| row.get(0),
+        );
         
         match result {
-            Ok(manifest) => Ok(Some(manifest)),
+            Ok(version) => Ok(Some(version)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
             Err(e) => Err(e.into()),
         }
     }
     
+    /// Liest einen Metadaten-Eintrag eines Repositories
+    pub fn get_meta(&self, repo_url: &str, key: &str) -> Result<Option<String>> {
+        let result = self.conn.query_row(
+            "SELECT value FROM index_meta WHERE repo_url = ?1 AND key = ?2",
+            [repo_url, key],
+            |row| row.get(0),
+        );
+        
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Schreibt einen Metadaten-Eintrag eines Repositories
+    pub fn set_meta(&self, repo_url: &str, key: &str, value: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO index_meta (repo_url, key, value) VALUES (?1, ?2, ?3)",
+            [repo_url, key, value],
+        )?;
+        Ok(())
+    }
+    
+    /// Addiert die Einsparungen durch Delta-Updates zur Gesamtsumme (für `apt-ng stats`)
+    pub fn record_delta_savings(&self, savings: &DeltaSavings) -> Result<()> {
+        for (key, value) in [(DELTA_PACKAGES_KEY, savings.packages), (DELTA_BYTES_SAVED_KEY, savings.bytes_saved)] {
+            self.conn.execute(
+                "INSERT INTO index_meta (repo_url, key, value) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(repo_url, key) DO UPDATE SET value = CAST(value AS INTEGER) + CAST(excluded.value AS INTEGER)",
+                rusqlite::params![GLOBAL_META_SCOPE, key, value.to_string()],
+            )?;
+        }
+        Ok(())
+    }
+    
+    /// Gesamte Einsparungen durch Delta-Updates seit Anlegen des Index
+    pub fn delta_savings(&self) -> Result<DeltaSavings> {
+        let total = |key| -> Result<u64> {
+            Ok(self.get_meta(GLOBAL_META_SCOPE, key)?
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0))
+        };
+        Ok(DeltaSavings {
+            packages: total(DELTA_PACKAGES_KEY)?,
+            bytes_saved: total(DELTA_BYTES_SAVED_KEY)?,
+        })
+    }
+    
+    /// Speichert eine Operation im Verlauf und gibt ihre ID zurück
+    pub fn record_history(&self, entry: &HistoryEntry) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO history (timestamp, command_line, changes, error) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                entry.timestamp,
+                entry.command_line,
+                serde_json::to_string(&entry.changes)?,
+                entry.error,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+    
+    /// Die letzten `limit` Operationen, neueste zuerst
+    pub fn history(&self, limit: usize) -> Result<Vec<HistoryEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, timestamp, command_line, changes, error FROM history ORDER BY id DESC LIMIT ?1"
+        )?;
+        let entries = stmt.query_map([limit as i64], Self::history_from_row)?;
+        Ok(entries.collect::<SqliteResult<Vec<_>>>()?)
+    }
+    
+    /// Eine einzelne Operation aus dem Verlauf
+    pub fn history_entry(&self, id: i64) -> Result<Option<HistoryEntry>> {
+        let result = self.conn.query_row(
+            "SELECT id, timestamp, command_line, changes, error FROM history WHERE id = ?1",
+            [id],
+            Self::history_from_row,
+        );
+        match result {
+            Ok(entry) => Ok(Some(entry)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    fn history_from_row(row: &rusqlite::Row) -> SqliteResult<HistoryEntry> {
+        let changes: String = row.get(3)?;
+        Ok(HistoryEntry {
+            id: Some(row.get(0)?),
+            timestamp: row.get(1)?,
+            command_line: row.get(2)?,
+            changes: serde_json::from_str::<Vec<PackageChange>>(&changes).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e))
+            })?,
+            error: row.get(4)?,
+        })
+    }
+    
     /// Gibt die Repository-URL für eine repo_id zurück
     pub fn get_repo_url(&self, repo_id: i64) -> Result<Option<String>> {
         let mut stmt = self.conn.prepare(
@@ -407,7 +995,7 @@ impl Index {
         match result {
             Ok(url) => Ok(Some(url)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(anyhow::anyhow!("Database error: {}", e)),
+            Err(e) => Err(anyhow::Error::new(e).context("Database error")),
         }
     }
     
@@ -416,21 +1004,13 @@ impl Index {
     pub fn select_best_mirror_url(&self, base_url: &str) -> Result<String> {
         use crate::repo::Repository;
         
+        // Lokale Repositories (file://, copy://) haben keinen Host und damit keine Mirrors
+        if crate::downloader::local_path(base_url).is_some() {
+            return Ok(base_url.to_string());
+        }
+        
         // Extrahiere Base-URL (ohne Pfad)
-        let base = if let Some(slash_pos) = base_url.find('/') {
-            if base_url[slash_pos..].starts_with("//") {
-                // http:// oder https://
-                if let Some(end_pos) = base_url[slash_pos+2..].find('/') {
-                    &base_url[..slash_pos+2+end_pos]
-                } else {
-                    base_url
-                }
-            } else {
-                &base_url[..slash_pos]
-            }
-        } else {
-            base_url
-        };
+        let base = crate::repo::base_url(base_url);
         
         // Suche nach dem besten Mirror für diese Base-URL
         if let Ok(Some(best_repo)) = Repository::select_best_mirror(self.conn(), base) {
@@ -447,33 +1027,111 @@ impl Index {
         }
     }
     
-    /// Aktualisiert die Performance-Metriken für eine Mirror-URL nach einem Download
-    pub fn update_mirror_performance(&self, url: &str, rtt_ms: u64, _throughput: u64) -> Result<()> {
+    /// Download-URLs einer Datei aus einem Repository, beste zuerst
+    ///
+    /// Die erste URL stammt aus `select_best_mirror_url`; danach folgen das Repository
+    /// selbst und seine Mirrors (`Repository::mirrors_of`) als Ausweichziele, etwa wenn
+    /// ein Mirror eine beschädigte Datei ausliefert.
+    pub fn download_urls(&self, repo_id: i64, filename: &str) -> Result<Vec<String>> {
         use crate::repo::Repository;
         
-        // Extrahiere Base-URL
-        let base_url = if let Some(path_start) = url.find('/') {
-            if url[path_start..].starts_with("//") {
-                if let Some(end_pos) = url[path_start+2..].find('/') {
-                    &url[..path_start+2+end_pos]
-                } else {
-                    url
-                }
-            } else {
-                &url[..path_start]
+        let repo_url = self.get_repo_url(repo_id)?
+            .ok_or_else(|| anyhow::anyhow!("Repository {} not found", repo_id))?;
+        let file_url = |base: &str| format!("{}/{}", base.trim_end_matches('/'), filename.trim_start_matches('/'));
+        
+        let mut urls = vec![self.select_best_mirror_url(&file_url(&repo_url))?];
+        let mirrors = Repository::mirrors_of(&self.conn, repo_id)?;
+        for base in std::iter::once(repo_url).chain(mirrors.into_iter().map(|mirror| mirror.url)) {
+            let url = file_url(&base);
+            if !urls.contains(&url) {
+                urls.push(url);
             }
-        } else {
-            url
-        };
+        }
+        Ok(urls)
+    }
+    
+    /// Aktualisiert die Performance-Metriken nach einem Download
+    ///
+    /// Die Messung gilt dem Repository, aus dem `url` stammt. Liefert ein Redirector wie
+    /// deb.debian.org die Datei von einem anderen Host aus (`resolved_url`), wird dieser
+    /// als `RESOLVED_HOST_META_KEY` des Repositories festgehalten.
+    pub fn update_mirror_performance(&self, url: &str, resolved_url: &str, rtt_ms: u64, throughput: u64) -> Result<()> {
+        use crate::repo::{base_url, Repository};
+        
+        // Längste passende Repository-URL; ohne Treffer wie bisher die Base-URL
+        let repo_url = Repository::load_all_including_disabled(self.conn())?
+            .into_iter()
+            .map(|repo| repo.url)
+            .filter(|repo_url| url.starts_with(repo_url.trim_end_matches('/')))
+            .max_by_key(|repo_url| repo_url.len())
+            .unwrap_or_else(|| base_url(url).to_string());
+        
+        Repository::update_probe_stats(self.conn(), &repo_url, rtt_ms, throughput)?;
         
-        // Aktualisiere RTT (Throughput wird nicht in der DB gespeichert, nur RTT)
-        Repository::update_probe_stats(self.conn(), base_url, rtt_ms)?;
+        let resolved_host = base_url(resolved_url);
+        if resolved_host != base_url(url) {
+            self.set_meta(&repo_url, RESOLVED_HOST_META_KEY, resolved_host)?;
+        }
         
         Ok(())
     }
     
+    /// Prüft, ob eine Paketversion noch von ihrem Repository angeboten wird
+    pub fn is_available(&self, package_name: &str, version: &str, arch: &str) -> Result<bool> {
+        let result = self.conn.query_row(
+            "SELECT available FROM packages WHERE name = ?1 AND version = ?2 AND arch = ?3",
+            [package_name, version, arch],
+            |row| row.get::<_, Option<i64>>(0),
+        );
+        
+        match result {
+            Ok(available) => Ok(available.unwrap_or(1) != 0),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Installierte Pakete, deren Version upstream nicht mehr verfügbar ist (Name, Version)
+    pub fn list_unavailable_installed(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.name, p.version FROM packages p
+             INNER JOIN installed i ON p.id = i.pkg_id
+             WHERE p.available = 0
+             ORDER BY p.name"
+        )?;
+        
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(row?);
+        }
+        Ok(results)
+    }
+    
+    /// Alle Paketversionen, die upstream nicht mehr verfügbar sind (Name, Version)
+    pub fn list_unavailable(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare("SELECT name, version FROM packages WHERE available = 0")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(row?);
+        }
+        Ok(results)
+    }
+    
+    /// Anzahl der Pakete im Index (alle Versionen und Repositories)
+    pub fn package_count(&self) -> Result<u64> {
+        Ok(self.conn.query_row("SELECT COUNT(*) FROM packages", [], |row| row.get(0))?)
+    }
+    
+    /// Anzahl der installierten Pakete
+    pub fn installed_count(&self) -> Result<u64> {
+        Ok(self.conn.query_row("SELECT COUNT(*) FROM installed", [], |row| row.get(0))?)
+    }
+    
     /// Gibt alle installierten Pakete zurück
-    #[allow(dead_code)]
     pub fn list_installed(&self) -> Result<Vec<String>> {
         let mut stmt = self.conn.prepare(
             "SELECT p.name FROM packages p
@@ -492,8 +1150,15 @@ impl Index {
     }
     
     /// Markiert ein Paket als installiert
-    #[allow(dead_code)]
     pub fn mark_installed(&self, package_name: &str, version: &str) -> Result<()> {
+        self.mark_installed_with_files(package_name, version, &[])
+    }
+    
+    /// Markiert ein Paket als installiert und speichert seine installierten Dateien
+    ///
+    /// Die Dateiliste (relativ zur Installationswurzel) landet als `{"files": [...]}` in der
+    /// `manifest`-Spalte; `remove` und `apt-ng files` lesen sie von dort.
+    pub fn mark_installed_with_files(&self, package_name: &str, version: &str, files: &[FileEntry]) -> Result<()> {
         // Finde Paket-ID
         let pkg_id: i64 = self.conn.query_row(
             "SELECT id FROM packages WHERE name = ?1 AND version = ?2",
@@ -511,13 +1176,104 @@ impl Index {
                     .duration_since(std::time::UNIX_EPOCH)
                     .unwrap()
                     .as_secs() as i64,
-                "{}" // Placeholder für Manifest
+                serde_json::json!({ "files": files }).to_string()
             ],
         )?;
         
         Ok(())
     }
     
+    /// Installierte Dateien eines Pakets (None, wenn es nicht installiert ist)
+    ///
+    /// Für Pakete, die vor der Erfassung der Dateilisten installiert wurden, ist die Liste leer.
+    pub fn installed_files(&self, package_name: &str) -> Result<Option<Vec<FileEntry>>> {
+        let result = self.conn.query_row(
+            "SELECT i.manifest FROM packages p
+             INNER JOIN installed i ON p.id = i.pkg_id
+             WHERE p.name = ?1",
+            [package_name],
+            |row| row.get::<_, Option<String>>(0),
+        );
+        
+        match result {
+            Ok(manifest) => Ok(Some(Self::installed_files_from(manifest))),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Installierte Pakete, deren Dateiliste `path` (relativ zur Installationswurzel) enthält
+    pub fn packages_owning_file(&self, path: &str) -> Result<Vec<String>> {
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        let mut stmt = self.conn.prepare(
+            "SELECT p.name, i.manifest FROM packages p
+             INNER JOIN installed i ON p.id = i.pkg_id
+             ORDER BY p.name"
+        )?;
+        
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))?;
+        
+        let mut owners = Vec::new();
+        for row in rows {
+            let (name, manifest) = row?;
+            if Self::installed_files_from(manifest).iter().any(|entry| entry.path == path) {
+                owners.push(name);
+            }
+        }
+        owners.dedup();
+        Ok(owners)
+    }
+    
+    /// Ersetzt die Contents-Einträge eines Repositories in einer Transaktion
+    ///
+    /// Liefert die Zahl gespeicherter Datei-Paket-Zuordnungen.
+    pub fn replace_repo_contents(&self, repo_id: i64, entries: &[ContentsEntry]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM contents WHERE repo_id = ?1", [repo_id])?;
+        
+        let mut count = 0;
+        {
+            let mut stmt = tx.prepare("INSERT INTO contents (repo_id, path, package) VALUES (?1, ?2, ?3)")?;
+            for entry in entries {
+                for package in &entry.packages {
+                    stmt.execute(rusqlite::params![repo_id, entry.path, package])?;
+                    count += 1;
+                }
+            }
+        }
+        
+        tx.commit()?;
+        Ok(count)
+    }
+    
+    /// Sucht Dateien in den Contents-Indizes, deren Pfad `pattern` enthält
+    ///
+    /// Liefert `(Paket, Pfad)`-Paare, sortiert nach Paket und Pfad.
+    pub fn search_contents(&self, pattern: &str) -> Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT package, path FROM contents
+             WHERE instr(path, ?1) > 0
+             ORDER BY package, path"
+        )?;
+        
+        let rows = stmt.query_map([pattern], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<SqliteResult<Vec<_>>>()?)
+    }
+    
+    /// Liest die Dateiliste aus der `manifest`-Spalte der `installed`-Tabelle
+    fn installed_files_from(manifest: Option<String>) -> Vec<FileEntry> {
+        #[derive(serde::Deserialize)]
+        struct InstalledManifest {
+            #[serde(default)]
+            files: Vec<FileEntry>,
+        }
+        
+        manifest
+            .and_then(|json| serde_json::from_str::<InstalledManifest>(&json).ok())
+            .map(|manifest| manifest.files)
+            .unwrap_or_default()
+    }
+    
     /// Entfernt ein Paket aus der installierten Liste
     pub fn mark_removed(&self, package_name: &str) -> Result<()> {
         self.conn.execute(
@@ -528,30 +1284,19 @@ impl Index {
         Ok(())
     }
     
-    /// Gibt alle installierten Pakete mit ihren vollständigen Manifests zurück
+    /// Gibt alle installierten Pakete mit ihren vollständigen Manifests (samt Dateiliste) zurück
     pub fn list_installed_packages_with_manifests(&self) -> Result<Vec<PackageManifest>> {
         let mut stmt = self.conn.prepare(
-            "SELECT p.name, p.version, p.arch, p.provides, p.depends, p.size, p.checksum, p.timestamp, p.repo_id, p.filename
+            "SELECT p.name, p.version, p.arch, p.provides, p.depends, p.size, p.checksum, p.timestamp, p.repo_id, p.filename,
+                    p.maintainer, p.homepage, p.section, p.priority, p.installed_size, p.conflicts, p.replaces, p.recommends, p.suggests, i.manifest
              FROM packages p
              INNER JOIN installed i ON p.id = i.pkg_id"
         )?;
         
         let rows = stmt.query_map([], |row| {
-            Ok(PackageManifest {
-                name: row.get(0)?,
-                version: row.get(1)?,
-                arch: row.get(2)?,
-                provides: serde_json::from_str(row.get::<_, String>(3)?.as_str()).unwrap_or_default(),
-                depends: serde_json::from_str(row.get::<_, String>(4)?.as_str()).unwrap_or_default(),
-                conflicts: vec![],
-                replaces: vec![],
-                files: vec![],
-                size: row.get(5)?,
-                checksum: row.get(6)?,
-                timestamp: row.get(7)?,
-                repo_id: row.get::<_, Option<i64>>(8)?,
-                filename: row.get::<_, Option<String>>(9)?.filter(|s| !s.is_empty()),
-            })
+            let mut manifest = Self::manifest_from_row(row)?;
+            manifest.files = Self::installed_files_from(row.get(19)?);
+            Ok(manifest)
         })?;
         
         let mut results = Vec::new();
@@ -562,6 +1307,45 @@ impl Index {
     }
 }
 
+/// Aktiver Bulk-Insert-Modus, siehe `Index::bulk_insert`
+pub struct BulkInsert<'a> {
+    index: &'a Index,
+    finished: bool,
+}
+
+impl BulkInsert<'_> {
+    /// Beendet den Bulk-Insert-Modus und stellt die Indizes wieder her
+    pub fn finish(mut self) -> Result<()> {
+        self.finished = true;
+        self.index.end_bulk_insert()
+    }
+}
+
+impl Drop for BulkInsert<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            if let Err(e) = self.index.end_bulk_insert() {
+                tracing::warn!(error = %e, "failed to restore indexes after bulk insert");
+            }
+        }
+    }
+}
+
+/// Levenshtein-Distanz zweier Namen (Einfügen, Löschen, Ersetzen je 1)
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -580,5 +1364,353 @@ mod tests {
         
         let _ = fs::remove_file(test_db);
     }
+    
+    #[test]
+    fn test_corrupted_index_is_reported_and_rebuilt() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("index.db");
+        let db_path = db_path.to_str().unwrap();
+        fs::write(db_path, vec![0x5a; 8192]).unwrap();
+        
+        let err = Index::new(db_path).err().unwrap();
+        assert!(matches!(err.downcast_ref::<AptNgError>(), Some(AptNgError::IndexCorrupt { .. })), "{}", err);
+        
+        let (index, backup) = Index::rebuild(db_path).unwrap();
+        assert_eq!(fs::read(backup.unwrap()).unwrap(), vec![0x5a; 8192]);
+        assert!(index.get_all_packages().unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_rebuild_keeps_installed_packages_and_history() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("index.db");
+        let db_path = db_path.to_str().unwrap();
+        
+        {
+            let index = Index::new(db_path).unwrap();
+            let packages = crate::apt_parser::parse_packages_file(
+                "Package: tool\nVersion: 1.0\nArchitecture: amd64\n\nPackage: extra\nVersion: 1.0\nArchitecture: amd64\n"
+            ).unwrap();
+            index.replace_repo_packages(1, &packages).unwrap();
+            index.mark_installed("tool", "1.0").unwrap();
+            index.record_history(&HistoryEntry::new("apt-ng install tool", Vec::new(), None)).unwrap();
+        }
+        
+        let (index, backup) = Index::rebuild(db_path).unwrap();
+        assert!(backup.is_some());
+        
+        // Nur die Paketdaten der Repositories sind weg
+        assert!(index.show("extra").unwrap().is_none());
+        assert_eq!(index.list_installed().unwrap(), ["tool"]);
+        assert!(index.is_available("tool", "1.0", "amd64").unwrap());
+        assert_eq!(index.history(10).unwrap()[0].command_line, "apt-ng install tool");
+    }
+    
+    #[test]
+    fn test_vacuum_after_large_update() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        
+        let content: String = (0..2000)
+            .map(|i| format!("Package: pkg-{}\nVersion: 1.0\nDepends: libfoo, libbar (>= 2.0)\n\n", i))
+            .collect();
+        index.replace_repo_packages(1, &crate::apt_parser::parse_packages_file(&content).unwrap()).unwrap();
+        assert!(!index.needs_vacuum().unwrap());
+        
+        // Repository ist geschrumpft: die meisten Seiten sind jetzt frei
+        let remaining = crate::apt_parser::parse_packages_file("Package: pkg-0\nVersion: 1.1\n").unwrap();
+        index.replace_repo_packages(1, &remaining).unwrap();
+        assert!(index.needs_vacuum().unwrap());
+        
+        let before = index.size_bytes().unwrap();
+        index.optimize().unwrap();
+        index.vacuum().unwrap();
+        assert!(index.size_bytes().unwrap() < before);
+        assert!(!index.needs_vacuum().unwrap());
+        assert_eq!(index.show("pkg-0").unwrap().unwrap().version, "1.1");
+    }
+    
+    #[test]
+    fn test_bulk_insert_restores_indexes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        let indexes = |index: &Index| -> Vec<String> {
+            let mut stmt = index.conn()
+                .prepare("SELECT name FROM sqlite_master WHERE type = 'index' AND name LIKE 'idx_packages_%' ORDER BY name")
+                .unwrap();
+            let names = stmt.query_map([], |row| row.get(0)).unwrap();
+            names.collect::<SqliteResult<Vec<String>>>().unwrap()
+        };
+        let expected = ["idx_packages_name", "idx_packages_timestamp"];
+        let packages = crate::apt_parser::parse_packages_file("Package: tool\nVersion: 1.0\n").unwrap();
+        
+        let bulk = index.bulk_insert().unwrap();
+        assert!(indexes(&index).is_empty());
+        index.replace_repo_packages(1, &packages).unwrap();
+        bulk.finish().unwrap();
+        assert_eq!(indexes(&index), expected);
+        
+        // Auch ohne `finish`, etwa nach einem fehlgeschlagenen Einfügen
+        {
+            let _bulk = index.bulk_insert().unwrap();
+            assert!(indexes(&index).is_empty());
+        }
+        assert_eq!(indexes(&index), expected);
+        assert!(index.show("tool").unwrap().is_some());
+    }
+    
+    #[test]
+    fn test_descriptive_fields_migration() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("index.db");
+        
+        // Alte Datenbank ohne maintainer/homepage/section/priority
+        {
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute(
+                "CREATE TABLE packages (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL, version TEXT NOT NULL, arch TEXT NOT NULL,
+                    provides TEXT, depends TEXT, size INTEGER, checksum TEXT,
+                    repo_id INTEGER, timestamp INTEGER, filename TEXT,
+                    UNIQUE(name, version, arch)
+                )",
+                [],
+            ).unwrap();
+            conn.execute(
+                "INSERT INTO packages (name, version, arch, provides, depends, size, checksum, repo_id, timestamp, filename)
+                 VALUES ('old', '1.0', 'all', '[]', '[]', 0, '', 1, 0, '')",
+                [],
+            ).unwrap();
+        }
+        
+        let index = Index::new(db_path.to_str().unwrap()).unwrap();
+        let old = index.show("old").unwrap().unwrap();
+        assert!(old.maintainer.is_none());
+        
+        let mut manifest = old.clone();
+        manifest.name = "new".to_string();
+        manifest.section = Some("utils".to_string());
+        manifest.priority = Some("optional".to_string());
+        index.add_packages_batch(&[manifest], 1).unwrap();
+        
+        let new = index.show("new").unwrap().unwrap();
+        assert_eq!(new.section.as_deref(), Some("utils"));
+        assert_eq!(new.priority.as_deref(), Some("optional"));
+    }
+    
+    #[test]
+    fn test_show_uses_debian_version_order() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        
+        let packages = crate::apt_parser::parse_packages_file(
+            "Package: tool\nVersion: 1.9\n\nPackage: tool\nVersion: 1.10\n\nPackage: tool\nVersion: 1:0.5\n"
+        ).unwrap();
+        index.add_packages_batch(&packages, 1).unwrap();
+        
+        assert_eq!(index.show("tool").unwrap().unwrap().version, "1:0.5");
+        let versions: Vec<String> = index.search_exact("tool").unwrap().into_iter().map(|p| p.version).collect();
+        assert_eq!(versions, vec!["1:0.5", "1.10", "1.9"]);
+        
+        assert_eq!(index.installed_version("tool").unwrap(), None);
+        index.mark_installed("tool", "1.10").unwrap();
+        assert_eq!(index.installed_version("tool").unwrap().as_deref(), Some("1.10"));
+    }
+    
+    #[test]
+    fn test_update_replaces_repo_packages_atomically() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        
+        let first = crate::apt_parser::parse_packages_file(
+            "Package: keep\nVersion: 1.0\n\nPackage: gone\nVersion: 1.0\n\nPackage: tool\nVersion: 1.0\n"
+        ).unwrap();
+        assert_eq!(index.replace_repo_packages(1, &first).unwrap(), 0);
+        index.mark_installed("tool", "1.0").unwrap();
+        
+        // Zweites Update: "gone" ist upstream verschwunden, "keep" hat eine neue Version
+        let second = crate::apt_parser::parse_packages_file(
+            "Package: keep\nVersion: 1.1\n\nPackage: tool\nVersion: 1.0\n"
+        ).unwrap();
+        assert_eq!(index.replace_repo_packages(1, &second).unwrap(), 2);
+        
+        assert!(index.show("gone").unwrap().is_none());
+        let versions: Vec<String> = index.search_exact("keep").unwrap().into_iter().map(|p| p.version).collect();
+        assert_eq!(versions, vec!["1.1"]);
+        // Die Zeile des installierten Pakets behält ihre ID
+        assert_eq!(index.list_installed().unwrap(), vec!["tool".to_string()]);
+        
+        // Pakete anderer Repositories bleiben unberührt
+        let other = crate::apt_parser::parse_packages_file("Package: other\nVersion: 2.0\n").unwrap();
+        index.replace_repo_packages(2, &other).unwrap();
+        index.replace_repo_packages(1, &second).unwrap();
+        assert!(index.show("other").unwrap().is_some());
+        
+        // Teilweise geladene Updates übernehmen neue Versionen, löschen aber nichts
+        let partial = crate::apt_parser::parse_packages_file("Package: new\nVersion: 1.0\n").unwrap();
+        index.merge_repo_packages(1, &partial).unwrap();
+        assert!(index.show("new").unwrap().is_some());
+        assert_eq!(index.search_exact("keep").unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn test_installed_package_gone_upstream_is_flagged() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        
+        let first = crate::apt_parser::parse_packages_file(
+            "Package: tool\nVersion: 1.0\nArchitecture: amd64\n\nPackage: extra\nVersion: 1.0\nArchitecture: amd64\n"
+        ).unwrap();
+        index.replace_repo_packages(1, &first).unwrap();
+        index.mark_installed("tool", "1.0").unwrap();
+        assert!(index.is_available("tool", "1.0", "amd64").unwrap());
+        
+        // Update 2 enthält weder "tool" noch "extra"
+        let second = crate::apt_parser::parse_packages_file("Package: other\nVersion: 2.0\nArchitecture: amd64\n").unwrap();
+        assert_eq!(index.replace_repo_packages(1, &second).unwrap(), 1);
+        
+        assert!(index.show("extra").unwrap().is_none());
+        assert!(index.show("tool").unwrap().is_some());
+        assert!(!index.is_available("tool", "1.0", "amd64").unwrap());
+        assert_eq!(index.list_unavailable_installed().unwrap(), vec![("tool".to_string(), "1.0".to_string())]);
+        assert_eq!(index.list_unavailable().unwrap(), vec![("tool".to_string(), "1.0".to_string())]);
+        
+        // Taucht das Paket wieder auf, ist es wieder verfügbar
+        index.replace_repo_packages(1, &first).unwrap();
+        assert!(index.is_available("tool", "1.0", "amd64").unwrap());
+        assert!(index.list_unavailable_installed().unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_uninstalled_local_packages_are_pruned() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        
+        let local = crate::apt_parser::parse_packages_file(
+            "Package: kept\nVersion: 1.0\nArchitecture: amd64\n\nPackage: dropped\nVersion: 1.0\nArchitecture: amd64\n"
+        ).unwrap();
+        for manifest in &local {
+            assert!(index.add_local_package(manifest).unwrap());
+        }
+        index.mark_installed("kept", "1.0").unwrap();
+        
+        assert_eq!(index.prune_local_packages().unwrap(), 1);
+        assert!(index.show("kept").unwrap().is_some());
+        assert!(index.show("dropped").unwrap().is_none());
+        
+        // Nach dem Entfernen verschwindet auch der Eintrag des installierten Pakets
+        index.mark_removed("kept").unwrap();
+        assert_eq!(index.prune_local_packages().unwrap(), 1);
+        assert!(index.show("kept").unwrap().is_none());
+    }
+    
+    #[test]
+    fn test_mirror_performance_follows_redirects() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        let repo = crate::repo::Repository::new("http://deb.debian.org/debian", Some("bookworm"), &[], &[]);
+        crate::repo::Repository::add_to_db(index.conn(), &repo).unwrap();
+        
+        index.update_mirror_performance(
+            "http://deb.debian.org/debian/pool/main/h/hello/hello_2.10_amd64.deb",
+            "http://cdn-fastly.deb.debian.org/debian/pool/main/h/hello/hello_2.10_amd64.deb",
+            40, 5_000_000,
+        ).unwrap();
+        
+        let repos = crate::repo::Repository::load_all(index.conn()).unwrap();
+        assert_eq!(repos[0].rtt_ms, Some(40));
+        assert_eq!(repos[0].throughput_bps, Some(5_000_000));
+        assert_eq!(
+            index.get_meta("http://deb.debian.org/debian", RESOLVED_HOST_META_KEY).unwrap().as_deref(),
+            Some("http://cdn-fastly.deb.debian.org"),
+        );
+    }
+    
+    #[test]
+    fn test_stats_counters() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        assert!(index.delta_savings().unwrap().is_empty());
+        
+        index.record_delta_savings(&DeltaSavings { packages: 2, bytes_saved: 1500 }).unwrap();
+        index.record_delta_savings(&DeltaSavings { packages: 1, bytes_saved: 500 }).unwrap();
+        assert_eq!(index.delta_savings().unwrap(), DeltaSavings { packages: 3, bytes_saved: 2000 });
+        
+        let packages = crate::apt_parser::parse_packages_file("Package: a\nVersion: 1.0\n\nPackage: b\nVersion: 1.0\n").unwrap();
+        index.replace_repo_packages(1, &packages).unwrap();
+        index.mark_installed("a", "1.0").unwrap();
+        assert_eq!(index.package_count().unwrap(), 2);
+        assert_eq!(index.installed_count().unwrap(), 1);
+    }
+    
+    #[test]
+    fn test_installed_file_manifest() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        let packages = crate::apt_parser::parse_packages_file(
+            "Package: tool\nVersion: 1.0\nArchitecture: amd64\n\nPackage: legacy\nVersion: 1.0\nArchitecture: amd64\n"
+        ).unwrap();
+        index.replace_repo_packages(1, &packages).unwrap();
+        
+        let files = vec![FileEntry { path: "usr/bin/tool".to_string(), checksum: "abc".to_string(), size: 3, mode: 0o100755, link_target: None }];
+        index.mark_installed_with_files("tool", "1.0", &files).unwrap();
+        // Ohne Dateiliste (z.B. vor der Erfassung installiert) bleibt die Liste leer
+        index.mark_installed("legacy", "1.0").unwrap();
+        
+        assert_eq!(index.installed_files("tool").unwrap().unwrap()[0].path, "usr/bin/tool");
+        assert_eq!(index.installed_files("legacy").unwrap().unwrap().len(), 0);
+        assert!(index.installed_files("missing").unwrap().is_none());
+        
+        let installed = index.list_installed_packages_with_manifests().unwrap();
+        let tool = installed.iter().find(|pkg| pkg.name == "tool").unwrap();
+        assert_eq!(tool.files.len(), 1);
+        assert_eq!(tool.files[0].mode, 0o100755);
+        
+        assert_eq!(index.packages_owning_file("/usr/bin/tool").unwrap(), vec!["tool".to_string()]);
+        assert!(index.packages_owning_file("/usr/bin").unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_not_found_suggestions() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        let packages = crate::apt_parser::parse_packages_file(
+            "Package: nginx\nVersion: 1.24\nArchitecture: amd64\nProvides: httpd, httpd-cgi\n\n\
+             Package: nginx-extras\nVersion: 1.24\nArchitecture: amd64\nProvides: httpd (= 1.24)\n\n\
+             Package: apache2\nVersion: 2.4\nArchitecture: amd64\nProvides: httpd-cgi\n\n\
+             Package: ngircd\nVersion: 26\nArchitecture: amd64\n"
+        ).unwrap();
+        index.replace_repo_packages(1, &packages).unwrap();
+        
+        assert_eq!(edit_distance("ngnix", "nginx"), 2);
+        assert_eq!(index.similar_package_names("ngnix", 5).unwrap(), vec!["nginx".to_string()]);
+        assert_eq!(index.similar_package_names("nginx", 5).unwrap(), vec!["nginx-extras".to_string()]);
+        
+        let providers: Vec<String> = index.providers_of("httpd").unwrap().into_iter().map(|pkg| pkg.name).collect();
+        assert_eq!(providers, vec!["nginx", "nginx-extras"]);
+        assert!(index.providers_of("http").unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_contents_lookup() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        
+        let entries = crate::apt_parser::parse_contents_file(
+            "usr/bin/curl net/curl\nusr/share/man/man1/curl.1.gz doc/curl\nusr/bin/wget web/wget\n"
+        );
+        assert_eq!(index.replace_repo_contents(1, &entries).unwrap(), 3);
+        index.replace_repo_contents(2, &crate::apt_parser::parse_contents_file("usr/bin/curl net/curl\n")).unwrap();
+        
+        // Mehrere Repositories liefern dieselbe Zuordnung nur einmal
+        assert_eq!(index.search_contents("bin/curl").unwrap(), [("curl".to_string(), "/usr/bin/curl".to_string())]);
+        assert_eq!(index.search_contents("curl").unwrap().len(), 2);
+        assert!(index.search_contents("%").unwrap().is_empty());
+        
+        // Ein erneutes Update ersetzt die Einträge des Repositories
+        index.replace_repo_contents(1, &[]).unwrap();
+        assert!(index.search_contents("wget").unwrap().is_empty());
+        assert_eq!(index.search_contents("curl").unwrap().len(), 1);
+    }
 }
-
@@ -1,10 +1,11 @@
-use anyhow::Result;
+use crate::error::{AptNgError, Result};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
 use sha2::{Sha256, Digest};
 use hex;
+use serde::{Deserialize, Serialize};
 use crate::sandbox::{Sandbox, SandboxConfig};
 
 pub struct Installer {
@@ -12,13 +13,30 @@ pub struct Installer {
     #[allow(dead_code)]
     install_root: PathBuf,
     sandbox: Option<Sandbox>,
+    journal_path: Option<PathBuf>,
+}
+
+/// A single record in the on-disk transaction journal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JournalEntry {
+    Installed { path: PathBuf },
+    Backup { original: PathBuf, backup: PathBuf },
+    CreatedDir { path: PathBuf },
 }
 
 /// Tracks installed files for rollback purposes
+///
+/// If a journal path is set, every change is appended to the journal file before
+/// it is applied, so an interrupted installation can be rolled back on the next run.
 #[derive(Debug, Clone)]
 pub struct InstallationTransaction {
     installed_files: Vec<PathBuf>,
     backup_files: Vec<(PathBuf, PathBuf)>, // (original, backup)
+    created_dirs: Vec<PathBuf>,            // in creation order
+    journal_path: Option<PathBuf>,
+    /// Journal length before this transaction's first entry
+    journal_start: u64,
 }
 
 impl InstallationTransaction {
@@ -26,22 +44,164 @@ impl InstallationTransaction {
         InstallationTransaction {
             installed_files: Vec::new(),
             backup_files: Vec::new(),
+            created_dirs: Vec::new(),
+            journal_path: None,
+            journal_start: 0,
         }
     }
     
-    pub fn add_installed_file(&mut self, path: PathBuf) {
+    /// Creates a transaction that is persisted to a journal file
+    ///
+    /// Entries of earlier transactions that are still uncommitted (members of a
+    /// `TransactionGroup`) are kept, so an interrupted group is recovered as a whole.
+    pub fn with_journal(journal_path: impl AsRef<Path>) -> Result<Self> {
+        let journal_path = journal_path.as_ref().to_path_buf();
+        if let Some(parent) = journal_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = fs::OpenOptions::new().create(true).append(true).open(&journal_path)?;
+        file.sync_all()?;
+        let journal_start = file.metadata()?.len();
+        
+        Ok(InstallationTransaction {
+            installed_files: Vec::new(),
+            backup_files: Vec::new(),
+            created_dirs: Vec::new(),
+            journal_path: Some(journal_path),
+            journal_start,
+        })
+    }
+    
+    /// Loads an incomplete transaction from its journal file
+    pub fn load_journal(journal_path: impl AsRef<Path>) -> Result<Self> {
+        let journal_path = journal_path.as_ref();
+        let content = fs::read_to_string(journal_path)?;
+        
+        let mut transaction = InstallationTransaction {
+            installed_files: Vec::new(),
+            backup_files: Vec::new(),
+            created_dirs: Vec::new(),
+            journal_path: Some(journal_path.to_path_buf()),
+            journal_start: 0,
+        };
+        
+        for line in content.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            // A torn last line (crash during write) is ignored
+            match serde_json::from_str::<JournalEntry>(line) {
+                Ok(JournalEntry::Installed { path }) => transaction.installed_files.push(path),
+                Ok(JournalEntry::Backup { original, backup }) => transaction.backup_files.push((original, backup)),
+                Ok(JournalEntry::CreatedDir { path }) => transaction.created_dirs.push(path),
+                Err(_) => continue,
+            }
+        }
+        
+        Ok(transaction)
+    }
+    
+    pub fn add_installed_file(&mut self, path: PathBuf) -> Result<()> {
+        self.append_journal(&JournalEntry::Installed { path: path.clone() })?;
         self.installed_files.push(path);
+        Ok(())
     }
     
-    pub fn add_backup(&mut self, original: PathBuf, backup: PathBuf) {
+    pub fn add_backup(&mut self, original: PathBuf, backup: PathBuf) -> Result<()> {
+        self.append_journal(&JournalEntry::Backup { original: original.clone(), backup: backup.clone() })?;
         self.backup_files.push((original, backup));
+        Ok(())
+    }
+    
+    pub fn add_created_dir(&mut self, path: PathBuf) -> Result<()> {
+        self.append_journal(&JournalEntry::CreatedDir { path: path.clone() })?;
+        self.created_dirs.push(path);
+        Ok(())
+    }
+    
+    /// Creates a directory and all missing parents, recording each one that did not exist yet
+    pub fn create_dir_all(&mut self, dir: &Path) -> Result<()> {
+        let mut missing: Vec<&Path> = dir.ancestors()
+            .take_while(|p| !p.as_os_str().is_empty() && !p.exists())
+            .collect();
+        missing.reverse();
+        
+        for path in missing {
+            self.add_created_dir(path.to_path_buf())?;
+            fs::create_dir(path)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Files and symlinks written by this transaction (absolute paths)
+    pub fn installed_files(&self) -> &[PathBuf] {
+        &self.installed_files
+    }
+    
+    /// Number of files installed by this transaction
+    pub fn installed_file_count(&self) -> usize {
+        self.installed_files.len()
+    }
+    
+    /// Number of backups taken by this transaction
+    pub fn backup_count(&self) -> usize {
+        self.backup_files.len()
+    }
+    
+    /// Appends an entry to the journal and syncs it to disk
+    fn append_journal(&self, entry: &JournalEntry) -> Result<()> {
+        use std::io::Write;
+        
+        if let Some(ref journal_path) = self.journal_path {
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(journal_path)?;
+            let line = serde_json::to_string(entry)?;
+            writeln!(file, "{}", line)?;
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+    
+    /// Removes this transaction's entries from the journal
+    ///
+    /// Entries of earlier transactions (uncommitted members of the same `TransactionGroup`)
+    /// are kept; without any, the journal file is removed.
+    fn remove_journal(&self) -> Result<()> {
+        if let Some(ref journal_path) = self.journal_path {
+            if !journal_path.exists() {
+                return Ok(());
+            }
+            if self.journal_start == 0 {
+                fs::remove_file(journal_path)?;
+            } else {
+                let file = fs::OpenOptions::new().write(true).open(journal_path)?;
+                file.set_len(self.journal_start)?;
+                file.sync_all()?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Commit: keep installed files, discard backups and the journal
+    pub fn commit(&self) -> Result<()> {
+        for (_original, backup) in &self.backup_files {
+            if backup.exists() {
+                fs::remove_file(backup)?;
+            }
+        }
+        self.remove_journal()
     }
     
-    /// Rollback: remove installed files and restore backups
+    /// Rollback: remove installed files, restore backups and remove created directories
     pub fn rollback(&self) -> Result<()> {
-        // Remove installed files
+        // Remove installed files (and symlinks, even if dangling)
         for file in &self.installed_files {
-            if file.exists() {
+            if file.is_symlink() {
+                fs::remove_file(file)?;
+            } else if file.exists() {
                 if file.is_dir() {
                     fs::remove_dir_all(file)?;
                 } else {
@@ -50,8 +210,8 @@ impl InstallationTransaction {
             }
         }
         
-        // Restore backups
-        for (original, backup) in &self.backup_files {
+        // Restore backups, newest first (a file may have been backed up more than once)
+        for (original, backup) in self.backup_files.iter().rev() {
             if backup.exists() {
                 if original.exists() {
                     fs::remove_file(original)?;
@@ -60,23 +220,140 @@ impl InstallationTransaction {
             }
         }
         
+        // Remove created directories, deepest first; keep those that are not empty
+        for dir in self.created_dirs.iter().rev() {
+            if dir.is_dir() {
+                let _ = fs::remove_dir(dir);
+            }
+        }
+        
+        self.remove_journal()
+    }
+}
+
+/// Installations and removals of several packages that succeed or fail together
+///
+/// Each member keeps its `InstallationTransaction` uncommitted until the whole batch is
+/// installed. If one package fails, `rollback` undoes all members in reverse order and
+/// restores their previous entries in the index. Maintainer scripts that already ran
+/// are not reverted.
+#[derive(Default)]
+pub struct TransactionGroup {
+    members: Vec<GroupMember>,
+}
+
+struct GroupMember {
+    package: String,
+    /// Installed version and file list before this group touched the package
+    previous: Option<(String, Vec<crate::package::FileEntry>)>,
+    transaction: InstallationTransaction,
+}
+
+impl TransactionGroup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Number of packages installed or removed by this group so far
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+    
+    /// Adds an installed package and marks it installed in the index with its file list
+    ///
+    /// The previous index entry is remembered for `rollback`. On error the transaction
+    /// still belongs to the group and is undone by `rollback`.
+    pub fn add(
+        &mut self,
+        installer: &Installer,
+        index: &crate::index::Index,
+        package: &str,
+        version: &str,
+        transaction: InstallationTransaction,
+    ) -> Result<()> {
+        let previous = match Self::installed_state(index, package) {
+            Ok(previous) => previous,
+            Err(e) => {
+                transaction.rollback()?;
+                return Err(e);
+            }
+        };
+        self.members.push(GroupMember { package: package.to_string(), previous, transaction });
+        
+        let transaction = &self.members[self.members.len() - 1].transaction;
+        let files = installer.file_manifest(transaction)?;
+        index.mark_installed_with_files(package, version, &files)?;
+        Ok(())
+    }
+    
+    /// Marks an installed package as removed, as part of this group
+    ///
+    /// The previous index entry is remembered, so `rollback` reinstates the package.
+    pub fn remove(&mut self, index: &crate::index::Index, package: &str) -> Result<()> {
+        let previous = Self::installed_state(index, package)?;
+        self.members.push(GroupMember { package: package.to_string(), previous, transaction: InstallationTransaction::new() });
+        index.mark_removed(package)?;
+        Ok(())
+    }
+    
+    fn installed_state(index: &crate::index::Index, package: &str) -> Result<Option<(String, Vec<crate::package::FileEntry>)>> {
+        Ok(match index.installed_version(package)? {
+            Some(version) => Some((version, index.installed_files(package)?.unwrap_or_default())),
+            None => None,
+        })
+    }
+    
+    /// Keeps all installed packages
+    ///
+    /// Members are committed last one first, so the journal keeps the entries of all
+    /// members not yet committed and is only removed with the first member.
+    pub fn commit(self) -> Result<()> {
+        for member in self.members.iter().rev() {
+            member.transaction.commit()?;
+        }
         Ok(())
     }
+    
+    /// Undoes all members, last one first, and restores their index entries
+    ///
+    /// Continues after errors so as much as possible is restored; the first error is returned.
+    pub fn rollback(self, index: &crate::index::Index) -> Result<()> {
+        let mut first_error = None;
+        for member in self.members.iter().rev() {
+            if let Err(e) = member.transaction.rollback() {
+                first_error.get_or_insert(e);
+            }
+            let restored = index.mark_removed(&member.package).and_then(|()| match &member.previous {
+                Some((version, files)) => index.mark_installed_with_files(&member.package, version, files),
+                None => Ok(()),
+            });
+            if let Err(e) = restored {
+                first_error.get_or_insert(e.into());
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
 }
 
 impl Installer {
     /// Erstellt einen neuen Installer
-    #[allow(dead_code)]
     pub fn new(worker_pool_size: usize, install_root: impl AsRef<Path>) -> Self {
         Installer {
             worker_pool_size,
             install_root: install_root.as_ref().to_path_buf(),
             sandbox: None,
+            journal_path: None,
         }
     }
     
     /// Erstellt einen neuen Installer mit Sandbox-Konfiguration
-    #[allow(dead_code)]
     pub fn new_with_sandbox(
         worker_pool_size: usize,
         install_root: impl AsRef<Path>,
@@ -87,6 +364,20 @@ impl Installer {
             worker_pool_size,
             install_root: install_root.as_ref().to_path_buf(),
             sandbox,
+            journal_path: None,
+        }
+    }
+    
+    /// Persists installation transactions to the given journal file
+    pub fn set_journal_path(&mut self, journal_path: impl AsRef<Path>) {
+        self.journal_path = Some(journal_path.as_ref().to_path_buf());
+    }
+    
+    /// Starts a new transaction, journaled if a journal path is configured
+    fn begin_transaction(&self) -> Result<InstallationTransaction> {
+        match self.journal_path {
+            Some(ref path) => InstallationTransaction::with_journal(path),
+            None => Ok(InstallationTransaction::new()),
         }
     }
     
@@ -94,7 +385,7 @@ impl Installer {
     pub async fn install_package(&self, apx_path: &Path, verifier: Option<&crate::verifier::PackageVerifier>, verbose: bool) -> Result<InstallationTransaction> {
         use crate::package::ApxPackage;
         
-        let mut transaction = InstallationTransaction::new();
+        let mut transaction = self.begin_transaction()?;
         
         // 1. Öffne .apx-Datei
         let apx_pkg = ApxPackage::open(apx_path)?;
@@ -110,7 +401,7 @@ impl Installer {
         // 3-4. Manifest wurde bereits beim Öffnen geparst
         
         // 5. Dekomprimiere content.tar.zst in temporäres Verzeichnis
-        let temp_dir = std::env::temp_dir().join(format!("apt-ng-apx-install-{}", 
+        let temp_dir = crate::cleanup::TempPath::new(&format!("apt-ng-apx-install-{}", 
             std::process::id()));
         fs::create_dir_all(&temp_dir)?;
         
@@ -127,7 +418,10 @@ impl Installer {
         }
         
         // 7. Installiere Dateien atomisch
-        Self::copy_directory_atomic(&temp_dir, &self.install_root, &mut transaction, verbose)?;
+        if let Err(e) = Self::copy_directory_atomic(&temp_dir, &self.install_root, &mut transaction, verbose) {
+            transaction.rollback()?;
+            return Err(e);
+        }
         
         // 8. Führe Hooks aus (falls vorhanden)
         // Für .apx-Pakete werden Hooks im Manifest gespeichert, nicht als separate Skripte
@@ -140,7 +434,6 @@ impl Installer {
     }
     
     /// Installiert mehrere Pakete parallel
-    #[allow(dead_code)]
     pub async fn install_packages(&self, apx_paths: &[PathBuf], verifier: Option<&crate::verifier::PackageVerifier>, verbose: bool) -> Result<Vec<Result<InstallationTransaction>>> {
         use futures::stream::{self, StreamExt};
         
@@ -157,13 +450,13 @@ impl Installer {
     }
     
     /// Entfernt ein installiertes Paket
-    #[allow(dead_code)]
     pub async fn remove_package(&self, package_name: &str, index: &crate::index::Index, verbose: bool) -> Result<()> {
+        tracing::info!(package = package_name, "removing package");
         // 1. Lade Manifest des installierten Pakets
         let installed_packages = index.list_installed_packages_with_manifests()?;
         let package_manifest = installed_packages.iter()
             .find(|p| p.name == package_name)
-            .ok_or_else(|| anyhow::anyhow!("Package {} is not installed", package_name))?;
+            .ok_or_else(|| AptNgError::NotInstalled(package_name.to_string()))?;
         
         if verbose {
             println!("Removing package: {} ({})", package_name, package_manifest.version);
@@ -185,11 +478,10 @@ impl Installer {
         }
         
         if !dependent_packages.is_empty() {
-            return Err(anyhow::anyhow!(
-                "Cannot remove {}: the following packages depend on it: {}",
-                package_name,
-                dependent_packages.join(", ")
-            ));
+            return Err(AptNgError::RequiredBy {
+                package: package_name.to_string(),
+                dependents: dependent_packages,
+            });
         }
         
         // 2. Führe pre-remove Hook aus (if .deb file exists in cache)
@@ -225,45 +517,41 @@ impl Installer {
             println!("  Removing files...");
         }
         
-        // Remove files listed in manifest
-        for file_entry in &package_manifest.files {
-            let file_path = self.install_root.join(&file_entry.path);
-            if file_path.exists() {
-                if file_path.is_dir() {
-                    if verbose {
-                        println!("    Removing directory: {}", file_entry.path);
-                    }
-                    fs::remove_dir_all(&file_path)?;
-                } else {
-                    if verbose {
-                        println!("    Removing file: {}", file_entry.path);
-                    }
-                    fs::remove_file(&file_path)?;
-                }
+        // Dateiliste aus dem Index, für ältere Installationen aus der dpkg-Datenbank
+        let files: Vec<PathBuf> = if package_manifest.files.is_empty() {
+            crate::dpkg::installed_files(package_name)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|path| path.starts_with(&self.install_root))
+                .collect()
+        } else {
+            package_manifest.files.iter().map(|entry| self.install_root.join(&entry.path)).collect()
+        };
+        
+        let mut parent_dirs = Vec::new();
+        for file_path in &files {
+            // Symlinks werden selbst entfernt, nie ihr Ziel; Verzeichnisse nur, wenn sie leer sind
+            let Ok(metadata) = fs::symlink_metadata(file_path) else { continue };
+            if metadata.is_dir() {
+                let _ = fs::remove_dir(file_path);
+                continue;
+            }
+            if verbose {
+                println!("    Removing file: {}", file_path.display());
+            }
+            fs::remove_file(file_path)?;
+            if let Some(parent) = file_path.parent() {
+                parent_dirs.push(parent.to_path_buf());
             }
         }
         
-        // Also try to remove using dpkg-deb if available (for .deb packages)
-        if deb_path_opt.is_none() {
-            // Try using dpkg to get file list
-            let output = Command::new("dpkg-query")
-                .arg("-L")
-                .arg(package_name)
-                .output();
-            
-            if let Ok(output) = output {
-                if output.status.success() {
-                    let file_list = String::from_utf8_lossy(&output.stdout);
-                    for line in file_list.lines() {
-                        let file_path = Path::new(line.trim());
-                        if file_path.exists() && file_path.starts_with(&self.install_root) {
-                            if file_path.is_dir() {
-                                let _ = fs::remove_dir_all(file_path);
-                            } else {
-                                let _ = fs::remove_file(file_path);
-                            }
-                        }
-                    }
+        // Leer gewordene Verzeichnisse aufräumen, tiefste zuerst, nie die Wurzel selbst
+        parent_dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+        parent_dirs.dedup();
+        for dir in parent_dirs {
+            for ancestor in dir.ancestors() {
+                if ancestor == self.install_root || !ancestor.starts_with(&self.install_root) || fs::remove_dir(ancestor).is_err() {
+                    break;
                 }
             }
         }
@@ -303,17 +591,10 @@ impl Installer {
         };
         
         // Extract control.tar.gz from .deb to get scripts
-        let temp_dir = std::env::temp_dir().join(format!("apt-ng-hook-{}", std::process::id()));
+        let temp_dir = crate::cleanup::TempPath::new(&format!("apt-ng-hook-{}", std::process::id()));
         fs::create_dir_all(&temp_dir)?;
         
-        // Extract control.tar.gz using dpkg-deb
-        let output = Command::new("dpkg-deb")
-            .arg("-e")
-            .arg(deb_path)
-            .arg(&temp_dir)
-            .output()?;
-        
-        if !output.status.success() {
+        if Self::extract_deb(deb_path, temp_dir.path(), true).is_err() {
             // No control directory means no hooks - this is OK
             if verbose {
                 println!("  No control directory found, skipping {} hook", script_name);
@@ -352,7 +633,7 @@ impl Installer {
         let old_ver = if let Some(ov) = old_version {
             ov.to_string()
         } else {
-            // Try to get old version from dpkg-query
+            // Try to get old version from the dpkg database
             // Extract package name from deb path
             let deb_name = deb_path.file_stem()
                 .and_then(|s| s.to_str())
@@ -361,25 +642,7 @@ impl Installer {
                 .next()
                 .unwrap_or("");
             
-            if !deb_name.is_empty() {
-                let output = std::process::Command::new("dpkg-query")
-                    .arg("-W")
-                    .arg("-f=${Version}")
-                    .arg(deb_name)
-                    .output();
-                
-                if let Ok(output) = output {
-                    if output.status.success() {
-                        String::from_utf8_lossy(&output.stdout).trim().to_string()
-                    } else {
-                        String::new() // Not installed, empty string
-                    }
-                } else {
-                    String::new()
-                }
-            } else {
-                String::new()
-            }
+            crate::dpkg::installed_version(deb_name).unwrap_or_default()
         };
         
         // Prepare script arguments
@@ -418,15 +681,27 @@ impl Installer {
             ("DPKG_ADMINDIR".to_string(), "/var/lib/dpkg".to_string()),
         ];
         
+        // Pakete aus sandbox.network_allowed_packages erhalten Netzwerkzugriff, alle anderen bleiben isoliert
+        let network_sandbox;
+        let sandbox = match self.sandbox {
+            Some(ref sandbox) if sandbox.grants_network_to(package_name) => {
+                println!("  Granting network access to {} script of {} (sandbox.network_allowed_packages)", script_name, package_name);
+                network_sandbox = sandbox.with_network();
+                Some(&network_sandbox)
+            }
+            ref sandbox => sandbox.as_ref(),
+        };
+        
+        tracing::info!(package = package_name, script = script_name, args = ?script_args, sandboxed = sandbox.is_some(), "running maintainer script");
+        
         // Execute hook with or without sandbox
-        let output = if let Some(ref sandbox) = self.sandbox {
+        let output = if let Some(sandbox) = sandbox {
             // Use sandboxed execution
             match sandbox.execute_hook_sandboxed(&script_path, &script_args, &env_vars) {
                 Ok(output) => output,
                 Err(e) => {
-                    if verbose {
-                        eprintln!("  Sandbox execution failed, falling back to normal execution: {}", e);
-                    }
+                    tracing::warn!(package = package_name, script = script_name, error = %e, "sandbox failed, running unsandboxed");
+                    eprintln!("  Warning: sandbox execution of {} failed, running it unsandboxed: {}", script_name, e);
                     // Fallback to normal execution
                     let mut cmd = Command::new("/bin/sh");
                     cmd.arg(&script_path)
@@ -461,9 +736,11 @@ impl Installer {
         
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow::anyhow!("Hook {} failed: {}", script_name, stderr));
+            tracing::error!(package = package_name, script = script_name, status = %output.status, stderr = %stderr, "maintainer script failed");
+            return Err(AptNgError::HookFailed { hook: script_name.to_string(), message: stderr.to_string() });
         }
         
+        tracing::debug!(package = package_name, script = script_name, "maintainer script finished");
         if verbose {
             println!("  {} hook completed successfully", script_name);
         }
@@ -472,7 +749,6 @@ impl Installer {
     }
     
     /// Extract and run hooks from a .deb package
-    #[allow(dead_code)]
     pub async fn run_package_hooks(&self, deb_path: &Path, hook_types: &[HookType], verbose: bool) -> Result<()> {
         for hook_type in hook_types {
             self.run_hook(hook_type.clone(), deb_path, verbose).await?;
@@ -482,26 +758,46 @@ impl Installer {
     
     /// Installiert eine .deb-Datei mit Rollback-Unterstützung
     pub async fn install_deb_package(&self, deb_path: &Path, expected_checksum: Option<&str>, verbose: bool) -> Result<InstallationTransaction> {
-        let mut transaction = InstallationTransaction::new();
-        // Verwende dpkg-deb zum Extrahieren der .deb-Datei
-        // Dies ist eine einfache Implementierung, die dpkg-deb verwendet
+        let prepared = Self::prepare_deb(deb_path.to_path_buf(), expected_checksum.map(str::to_string), verbose)?;
+        self.install_prepared_deb(prepared, verbose).await
+    }
+    
+    /// Entpackt mehrere .deb-Dateien parallel (höchstens `worker_pool_size` gleichzeitig)
+    ///
+    /// Die Ergebnisse kommen in der Reihenfolge von `debs`; während der Aufrufer ein Paket mit
+    /// `install_prepared_deb` installiert, werden die folgenden bereits entpackt. Hooks und das
+    /// Kopieren ins System laufen so weiterhin nacheinander in Installationsreihenfolge.
+    pub fn prepare_deb_packages(
+        &self,
+        debs: Vec<(PathBuf, Option<String>)>,
+        verbose: bool,
+    ) -> impl futures::Stream<Item = Result<PreparedDeb>> {
+        let tasks: Vec<_> = debs.into_iter()
+            .map(|(deb_path, expected_checksum)| move || Self::prepare_deb(deb_path, expected_checksum, verbose))
+            .collect();
+        bounded_blocking(self.worker_pool_size, tasks)
+    }
+    
+    /// Prüft und entpackt eine .deb-Datei in ein eigenes temporäres Verzeichnis
+    ///
+    /// Berührt das System noch nicht und kann daher für unabhängige Pakete parallel laufen.
+    pub fn prepare_deb(deb_path: PathBuf, expected_checksum: Option<String>, verbose: bool) -> Result<PreparedDeb> {
+        let started = std::time::Instant::now();
+        let deb_path = deb_path.as_path();
+        let expected_checksum = expected_checksum.as_deref();
+        // Die .deb-Datei wird mit dem eingebauten Leser (crate::deb) entpackt
         
         // First, try to extract the package to see if it's valid
-        let temp_dir = std::env::temp_dir().join(format!("apt-ng-install-{}", 
-            std::process::id()));
+        // (one directory per package, several packages are extracted at the same time)
+        let temp_dir = crate::cleanup::TempPath::new(&format!("apt-ng-install-{}-{}", 
+            std::process::id(),
+            deb_path.file_stem().and_then(|s| s.to_str()).unwrap_or("package")));
         fs::create_dir_all(&temp_dir)?;
         
         // Test extraction first - if it works, the file is valid regardless of checksum
-        let test_output = Command::new("dpkg-deb")
-            .arg("-I")
-            .arg(deb_path)
-            .output();
-        
-        let extraction_test_ok = if let Ok(output) = test_output {
-            output.status.success()
-        } else {
-            false
-        };
+        let extraction_test_ok = crate::deb::DebArchive::open(deb_path)
+            .and_then(|deb| deb.control())
+            .is_ok();
         
         // Validate checksum if provided, but only fail if extraction also fails
         if let Some(expected) = expected_checksum {
@@ -516,8 +812,8 @@ impl Installer {
                     );
                     eprintln!("  File also fails extraction test. Deleting corrupted file...");
                     let _ = std::fs::remove_file(deb_path);
-                    return Err(anyhow::anyhow!(
-                        "Package file corrupted (checksum mismatch and extraction failed). Deleted corrupted file. Please run the command again to re-download."
+                    return Err(AptNgError::CorruptPackage(
+                        "Package file corrupted (checksum mismatch and extraction failed). Deleted corrupted file. Please run the command again to re-download.".to_string()
                     ));
                 } else {
                     // Checksum mismatch but extraction works - index might be wrong, warn but continue
@@ -534,34 +830,43 @@ impl Installer {
             }
         }
         
-        // Extrahiere .deb-Datei mit dpkg-deb
-        let output = Command::new("dpkg-deb")
-            .arg("-x")
-            .arg(deb_path)
-            .arg(&temp_dir)
-            .output()?;
-        
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
+        // Extrahiere den Paketinhalt
+        if let Err(stderr) = Self::extract_deb(deb_path, temp_dir.path(), false) {
             // Check if the error indicates a corrupted file
             if stderr.contains("unexpected end of file") || 
                stderr.contains("lzma error") || 
-               stderr.contains("corrupted") ||
+               stderr.contains("corrupt") ||
+               stderr.contains("truncated") ||
+               stderr.contains("not a valid") ||
                stderr.contains("invalid") {
                 // Try to delete the corrupted file
                 let _ = std::fs::remove_file(deb_path);
-                return Err(anyhow::anyhow!(
+                return Err(AptNgError::CorruptPackage(format!(
                     "Package file appears to be corrupted: {}. Deleted corrupted file. Please run the command again to re-download.",
                     stderr
-                ));
+                )));
             }
-            return Err(anyhow::anyhow!("Failed to extract .deb package: {}", stderr));
+            return Err(AptNgError::Installation(format!("failed to extract .deb package: {}", stderr)));
         }
         
         if verbose {
             println!("  Extracted package to temporary directory");
         }
         
+        Ok(PreparedDeb {
+            deb_path: deb_path.to_path_buf(),
+            contents: temp_dir,
+            extract_time: started.elapsed(),
+        })
+    }
+    
+    /// Installiert ein mit `prepare_deb` entpacktes Paket: Hooks und atomisches Kopieren
+    pub async fn install_prepared_deb(&self, prepared: PreparedDeb, verbose: bool) -> Result<InstallationTransaction> {
+        tracing::info!(deb = %prepared.deb_path.display(), root = %self.install_root.display(), "installing package");
+        let mut transaction = self.begin_transaction()?;
+        let deb_path = prepared.deb_path.as_path();
+        let temp_dir = prepared.contents;
+        
         // Get old version if package is already installed
         let old_version = {
             // Extract package name from deb path (format: package_version_arch.deb)
@@ -573,26 +878,7 @@ impl Installer {
                 .unwrap_or("");
             
             if !deb_name.is_empty() {
-                let output = std::process::Command::new("dpkg-query")
-                    .arg("-W")
-                    .arg("-f=${Version}")
-                    .arg(deb_name)
-                    .output();
-                
-                if let Ok(output) = output {
-                    if output.status.success() {
-                        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                        if !version.is_empty() {
-                            Some(version)
-                        } else {
-                            None
-                        }
-                    } else {
-                        None // Not installed
-                    }
-                } else {
-                    None
-                }
+                crate::dpkg::installed_version(deb_name)
             } else {
                 None
             }
@@ -609,8 +895,11 @@ impl Installer {
                     println!("  Installed files to {}", self.install_root.display());
                 }
                 
-                // Run post-install hook with old version
-                self.run_hook_with_old_version(HookType::PostInstall, deb_path, old_version.as_deref(), verbose).await?;
+                // Run post-install hook with old version; the files go again if it fails
+                if let Err(e) = self.run_hook_with_old_version(HookType::PostInstall, deb_path, old_version.as_deref(), verbose).await {
+                    transaction.rollback()?;
+                    return Err(e);
+                }
                 
                 // Aufräumen
                 fs::remove_dir_all(&temp_dir)?;
@@ -620,19 +909,31 @@ impl Installer {
             Err(e) => {
                 // Rollback on error
                 if let Err(rollback_err) = transaction.rollback() {
-                    return Err(anyhow::anyhow!("Installation failed: {}. Rollback also failed: {}", e, rollback_err));
+                    return Err(AptNgError::RolledBack {
+                        source: Box::new(e),
+                        outcome: format!("Rollback also failed: {}", rollback_err),
+                    });
                 }
-                Err(anyhow::anyhow!("Installation failed: {}. Rolled back changes.", e))
+                Err(AptNgError::RolledBack { source: Box::new(e), outcome: "Rolled back changes.".to_string() })
             }
         }
     }
     
+    /// Entpackt den Paketinhalt (`control = false`) oder Control-Bereich einer .deb-Datei
+    ///
+    /// Fehler kommen als Meldungstext zurück, an dem der Aufrufer beschädigte Dateien erkennt.
+    fn extract_deb(deb_path: &Path, dest: &Path, control: bool) -> std::result::Result<(), String> {
+        let deb = crate::deb::DebArchive::open(deb_path).map_err(|e| format!("{:#}", e))?;
+        let result = if control { deb.extract_control(dest) } else { deb.extract_data(dest) };
+        result.map_err(|e| format!("{:#}", e))
+    }
+    
     /// Copy directory contents atomically using temp files and rename
     fn copy_directory_atomic(source: &Path, dest: &Path, transaction: &mut InstallationTransaction, verbose: bool) -> Result<()> {
         use std::io;
         
         // Ensure destination directory exists
-        fs::create_dir_all(dest)?;
+        transaction.create_dir_all(dest)?;
         
         // Walk through source directory
         for entry in fs::read_dir(source)? {
@@ -640,11 +941,14 @@ impl Installer {
             let source_path = entry.path();
             let file_name = entry.file_name();
             let dest_path = dest.join(&file_name);
+            // Typ des Eintrags selbst: Symlinks aus dem Paket werden nie verfolgt, sonst würde
+            // ein Link auf /etc dessen Inhalt in den Installationsbaum kopieren
+            let file_type = entry.file_type()?;
             
-            if source_path.is_dir() {
+            if file_type.is_dir() {
                 // Recursively copy directories
                 Self::copy_directory_atomic(&source_path, &dest_path, transaction, verbose)?;
-            } else if source_path.is_file() {
+            } else if file_type.is_file() {
                 // Check if destination exists and is a directory (conflict)
                 // Also check if it's a symlink to a directory
                 if dest_path.exists() {
@@ -659,11 +963,11 @@ impl Installer {
                     };
                     
                     if is_dir {
-                        return Err(anyhow::anyhow!(
-                            "Cannot install file {}: destination {} is a directory",
+                        return Err(AptNgError::Installation(format!(
+                            "cannot install file {}: destination {} is a directory",
                             source_path.display(),
                             dest_path.display()
-                        ));
+                        )));
                     }
                 }
                 
@@ -681,18 +985,18 @@ impl Installer {
                 
                 // Ensure parent directory exists
                 if let Some(parent) = temp_dest.parent() {
-                    fs::create_dir_all(parent)?;
+                    transaction.create_dir_all(parent)?;
                 }
                 
                 // Copy file contents
                 let mut source_file = fs::File::open(&source_path)?;
                 let mut dest_file = fs::File::create(&temp_dest).map_err(|e| {
-                    anyhow::anyhow!(
+                    std::io::Error::new(e.kind(), format!(
                         "Failed to create temporary file {}: {} (source: {})",
                         temp_dest.display(),
                         e,
                         source_path.display()
-                    )
+                    ))
                 })?;
                 
                 // Preserve permissions
@@ -706,10 +1010,19 @@ impl Installer {
                 
                 // 2. Backup existing file if it exists (only if it's a file, not a directory)
                 if dest_path.exists() && dest_path.is_file() {
-                    let backup_path = dest_path.with_extension(format!("{}.bak", 
+                    let mut backup_path = dest_path.with_extension(format!("{}.bak", 
                         dest_path.extension().and_then(|s| s.to_str()).unwrap_or("bak")));
+                    // An earlier package of the same transaction group may hold a backup already
+                    let first_backup = backup_path.clone();
+                    let mut generation = 1;
+                    while backup_path.exists() {
+                        let mut name = first_backup.clone().into_os_string();
+                        name.push(format!(".{}", generation));
+                        backup_path = PathBuf::from(name);
+                        generation += 1;
+                    }
+                    transaction.add_backup(dest_path.clone(), backup_path.clone())?;
                     fs::copy(&dest_path, &backup_path)?;
-                    transaction.add_backup(dest_path.clone(), backup_path);
                 }
                 
                 // 3. Remove existing destination if it exists (could be a symlink or file)
@@ -722,18 +1035,20 @@ impl Installer {
                 }
                 
                 // 4. Atomically rename temp file to final destination
+                // (journaled first so an interrupted rename can still be rolled back)
+                transaction.add_installed_file(dest_path.clone())?;
                 fs::rename(&temp_dest, &dest_path)?;
-                transaction.add_installed_file(dest_path.clone());
                 
                 if verbose {
                     println!("    Installed: {}", dest_path.display());
                 }
-            } else if source_path.is_symlink() {
+            } else if file_type.is_symlink() {
                 // Handle symlinks
                 let link_target = fs::read_link(&source_path)?;
                 if dest_path.exists() || dest_path.is_symlink() {
                     fs::remove_file(&dest_path)?;
                 }
+                transaction.add_installed_file(dest_path.clone())?;
                 std::os::unix::fs::symlink(&link_target, &dest_path)?;
                 
                 if verbose {
@@ -745,6 +1060,28 @@ impl Installer {
         Ok(())
     }
     
+    /// Dateiliste eines installierten Pakets (relativ zu `install_root`) aus einer Transaktion
+    ///
+    /// Enthält alle geschriebenen Dateien und Symlinks; für Symlinks bleibt die Prüfsumme leer.
+    pub fn file_manifest(&self, transaction: &InstallationTransaction) -> Result<Vec<crate::package::FileEntry>> {
+        let mut files = Vec::new();
+        for path in transaction.installed_files() {
+            let Ok(relative) = path.strip_prefix(&self.install_root) else { continue };
+            let metadata = fs::symlink_metadata(path)?;
+            let checksum = if metadata.is_file() { Self::calculate_file_checksum(path)? } else { String::new() };
+            files.push(crate::package::FileEntry {
+                path: relative.to_string_lossy().into_owned(),
+                checksum,
+                size: if metadata.is_file() { metadata.len() } else { 0 },
+                mode: metadata.permissions().mode(),
+                link_target: fs::read_link(path).ok().map(|target| target.to_string_lossy().into_owned()),
+            });
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        files.dedup_by(|a, b| a.path == b.path);
+        Ok(files)
+    }
+    
     /// Calculate SHA256 checksum of a file
     fn calculate_file_checksum(file_path: &Path) -> Result<String> {
         use std::io::Read;
@@ -765,8 +1102,38 @@ impl Installer {
     }
 }
 
+/// Ein geprüftes und entpacktes .deb-Paket, bereit zur Installation
+pub struct PreparedDeb {
+    deb_path: PathBuf,
+    contents: crate::cleanup::TempPath,
+    /// Dauer von Prüfung und Entpacken
+    pub extract_time: std::time::Duration,
+}
+
+impl PreparedDeb {
+    pub fn deb_path(&self) -> &Path {
+        &self.deb_path
+    }
+}
+
+/// Führt blockierende Aufgaben mit höchstens `jobs` gleichzeitig aus, Ergebnisse in Eingabereihenfolge
+fn bounded_blocking<T, F>(jobs: usize, tasks: Vec<F>) -> impl futures::Stream<Item = Result<T>>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    use futures::stream::{self, StreamExt};
+    
+    stream::iter(tasks)
+        .map(|task| async move {
+            tokio::task::spawn_blocking(task)
+                .await
+                .map_err(|e| AptNgError::Installation(format!("worker failed: {}", e)))?
+        })
+        .buffered(jobs.max(1))
+}
+
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub enum HookType {
     PreInstall,
     PostInstall,
@@ -785,5 +1152,193 @@ mod tests {
         let installer = Installer::new(4, temp_dir.path());
         assert_eq!(installer.worker_pool_size, 4);
     }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_bounded_blocking_respects_job_count() {
+        use futures::StreamExt;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let tasks: Vec<_> = (0..8).map(|i| {
+            let running = running.clone();
+            let peak = peak.clone();
+            move || {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(i)
+            }
+        }).collect();
+        
+        let results: Vec<usize> = bounded_blocking(3, tasks)
+            .map(|result| result.unwrap())
+            .collect()
+            .await;
+        
+        assert_eq!(results, (0..8).collect::<Vec<_>>());
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+    }
+    
+    #[test]
+    fn test_transaction_journal_recovery() {
+        let temp_dir = TempDir::new().unwrap();
+        let journal = temp_dir.path().join("transaction.journal");
+        let installed = temp_dir.path().join("usr/bin/tool");
+        let original = temp_dir.path().join("etc/tool.conf");
+        let backup = temp_dir.path().join("etc/tool.conf.bak");
+        fs::create_dir_all(installed.parent().unwrap()).unwrap();
+        fs::create_dir_all(original.parent().unwrap()).unwrap();
+        fs::write(&original, "old").unwrap();
+        
+        {
+            let mut transaction = InstallationTransaction::with_journal(&journal).unwrap();
+            transaction.add_backup(original.clone(), backup.clone()).unwrap();
+            fs::copy(&original, &backup).unwrap();
+            fs::write(&original, "new").unwrap();
+            transaction.add_installed_file(installed.clone()).unwrap();
+            fs::write(&installed, "binary").unwrap();
+            // Simulate a crash: transaction is dropped without commit or rollback
+        }
+        
+        let recovered = InstallationTransaction::load_journal(&journal).unwrap();
+        assert_eq!(recovered.installed_file_count(), 1);
+        assert_eq!(recovered.backup_count(), 1);
+        
+        recovered.rollback().unwrap();
+        assert!(!installed.exists());
+        assert_eq!(fs::read_to_string(&original).unwrap(), "old");
+        assert!(!journal.exists());
+    }
+    
+    #[test]
+    fn test_group_journal_survives_member_rollback() {
+        let root = TempDir::new().unwrap();
+        let journal = root.path().join("transaction.journal");
+        let first = root.path().join("usr/bin/first");
+        let second = root.path().join("usr/bin/second");
+        fs::create_dir_all(first.parent().unwrap()).unwrap();
+        
+        let mut member = InstallationTransaction::with_journal(&journal).unwrap();
+        member.add_installed_file(first.clone()).unwrap();
+        fs::write(&first, "first").unwrap();
+        
+        // Das zweite Paket scheitert und rollt sich selbst zurück
+        let mut failed = InstallationTransaction::with_journal(&journal).unwrap();
+        failed.add_installed_file(second.clone()).unwrap();
+        fs::write(&second, "second").unwrap();
+        failed.rollback().unwrap();
+        assert!(!second.exists());
+        
+        // Nach einem Absturz an dieser Stelle findet die Wiederherstellung das erste Paket noch
+        let recovered = InstallationTransaction::load_journal(&journal).unwrap();
+        assert_eq!(recovered.installed_files(), std::slice::from_ref(&first));
+        
+        member.commit().unwrap();
+        assert!(first.exists());
+        assert!(!journal.exists());
+    }
+    
+    #[test]
+    fn test_rollback_restores_fresh_tree() {
+        let source = TempDir::new().unwrap();
+        let root = TempDir::new().unwrap();
+        fs::create_dir_all(source.path().join("usr/share/doc/tool")).unwrap();
+        fs::write(source.path().join("usr/share/doc/tool/README"), "docs").unwrap();
+        fs::create_dir_all(source.path().join("usr/bin")).unwrap();
+        fs::write(source.path().join("usr/bin/tool"), "binary").unwrap();
+        std::os::unix::fs::symlink("tool", source.path().join("usr/bin/tool-alias")).unwrap();
+        
+        let dest = root.path().join("install");
+        let mut transaction = InstallationTransaction::new();
+        Installer::copy_directory_atomic(source.path(), &dest, &mut transaction, false).unwrap();
+        assert!(dest.join("usr/share/doc/tool/README").exists());
+        
+        transaction.rollback().unwrap();
+        assert!(!dest.exists());
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 0);
+    }
+    
+    #[test]
+    fn test_package_symlinks_are_not_followed() {
+        let source = TempDir::new().unwrap();
+        let root = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        fs::write(outside.path().join("shadow"), "secret").unwrap();
+        
+        // Ein präpariertes Paket verlinkt auf Verzeichnisse und Dateien außerhalb des Baums
+        fs::create_dir_all(source.path().join("usr/lib")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), source.path().join("usr/lib/host-etc")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("shadow"), source.path().join("usr/lib/shadow")).unwrap();
+        
+        let dest = root.path().join("install");
+        let mut transaction = InstallationTransaction::new();
+        Installer::copy_directory_atomic(source.path(), &dest, &mut transaction, false).unwrap();
+        
+        // Die Links werden als Links angelegt, nicht als Kopien der Ziele
+        for name in ["host-etc", "shadow"] {
+            let installed = dest.join("usr/lib").join(name);
+            assert!(fs::symlink_metadata(&installed).unwrap().file_type().is_symlink());
+        }
+        assert_eq!(fs::read_link(dest.join("usr/lib/host-etc")).unwrap(), outside.path());
+    }
+    
+    #[test]
+    fn test_transaction_group_rollback_restores_everything() {
+        let root = TempDir::new().unwrap();
+        let sources = TempDir::new().unwrap();
+        let installer = Installer::new(1, root.path());
+        let index = crate::index::Index::new(root.path().join("index.db").to_str().unwrap()).unwrap();
+        let packages = crate::apt_parser::parse_packages_file(
+            "Package: tool\nVersion: 1.0\n\nPackage: tool\nVersion: 2.0\n\nPackage: libtool\nVersion: 2.0\n\nPackage: legacy\nVersion: 1.0\n\n"
+        ).unwrap();
+        index.replace_repo_packages(1, &packages).unwrap();
+        
+        // Ausgangszustand: tool 1.0 ist installiert
+        let etc = root.path().join("etc");
+        fs::create_dir_all(&etc).unwrap();
+        fs::write(etc.join("tool.conf"), "v1").unwrap();
+        let old_files = vec![crate::package::FileEntry { path: "etc/tool.conf".to_string(), checksum: String::new(), size: 2, mode: 0o644, link_target: None }];
+        index.mark_installed_with_files("tool", "1.0", &old_files).unwrap();
+        index.mark_installed("legacy", "1.0").unwrap();
+        
+        let source = |name: &str, files: &[(&str, &str)]| {
+            let dir = sources.path().join(name);
+            for (path, content) in files {
+                fs::create_dir_all(dir.join(path).parent().unwrap()).unwrap();
+                fs::write(dir.join(path), content).unwrap();
+            }
+            dir
+        };
+        let batch = [
+            ("libtool", "2.0", source("libtool", &[("usr/lib/libtool.so", "lib"), ("etc/tool.conf", "lib")])),
+            ("tool", "2.0", source("tool", &[("usr/bin/tool", "bin"), ("etc/tool.conf", "v2")])),
+        ];
+        
+        // Beide Pakete werden installiert, das dritte scheitert
+        let mut group = TransactionGroup::new();
+        for (name, version, dir) in &batch {
+            let mut transaction = InstallationTransaction::new();
+            Installer::copy_directory_atomic(dir, root.path(), &mut transaction, false).unwrap();
+            group.add(&installer, &index, name, version, transaction).unwrap();
+        }
+        assert_eq!(fs::read_to_string(etc.join("tool.conf")).unwrap(), "v2");
+        assert_eq!(index.installed_version("libtool").unwrap().as_deref(), Some("2.0"));
+        group.remove(&index, "legacy").unwrap();
+        assert_eq!(index.installed_version("legacy").unwrap(), None);
+        assert_eq!(group.len(), 3);
+        
+        group.rollback(&index).unwrap();
+        
+        // Dateien und Index entsprechen wieder dem Ausgangszustand
+        assert_eq!(fs::read_to_string(etc.join("tool.conf")).unwrap(), "v1");
+        assert!(!root.path().join("usr").exists());
+        assert_eq!(fs::read_dir(&etc).unwrap().count(), 1, "no backups left behind");
+        assert_eq!(index.installed_version("tool").unwrap().as_deref(), Some("1.0"));
+        assert_eq!(index.installed_files("tool").unwrap().unwrap()[0].path, "etc/tool.conf");
+        assert_eq!(index.installed_version("libtool").unwrap(), None);
+        assert_eq!(index.installed_version("legacy").unwrap().as_deref(), Some("1.0"));
+    }
 }
-
@@ -1,10 +1,37 @@
+//! apt-ng als Bibliothek: Paketindex, Parser für apt-Metadaten, Dependency-Solver und Cache.
+//!
+//! Die `apt-ng`-Binary ist nur eine dünne CLI über diesen Modulen.
+//!
+//! ```no_run
+//! use apt_ng::{parse_packages_file, DependencySolver, Index};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let packages = parse_packages_file(&std::fs::read_to_string("Packages")?)?;
+//! let index = Index::new("/tmp/index.db")?;
+//! index.add_packages_batch(&packages, 1)?;
+//!
+//! let (solver, _skipped) = DependencySolver::from_manifests(&packages);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod cleanup;
 pub mod cli;
 pub mod config;
+pub mod error;
+pub mod history;
 pub mod index;
 pub mod downloader;
 pub mod verifier;
+pub mod openpgp;
 pub mod installer;
+pub mod lock;
+pub mod lockfile;
+pub mod logging;
 pub mod package;
+pub mod deb;
+pub mod dpkg;
+pub mod extract;
 pub mod repo;
 pub mod solver;
 pub mod cache;
@@ -20,3 +47,10 @@ pub mod repo_generator;
 pub mod repo_server;
 pub mod self_update;
 
+
+pub use apt_parser::parse_packages_file;
+pub use cache::Cache;
+pub use error::AptNgError;
+pub use index::Index;
+pub use package::PackageManifest;
+pub use solver::DependencySolver;
@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Wie lange ein zweiter Prozess standardmäßig auf die Sperre wartet
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Exklusive Sperre (flock) auf die Lock-Datei im State-Verzeichnis
+///
+/// Verhindert, dass zwei schreibende apt-ng-Prozesse gleichzeitig Index und
+/// Installationsbaum verändern. Die Sperre wird beim Drop (spätestens beim
+/// Prozessende durch den Kernel) freigegeben.
+pub struct InstanceLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    /// Erwirbt die Sperre und wartet dabei höchstens `timeout`
+    ///
+    /// `on_wait` wird einmal aufgerufen, sobald gewartet werden muss (mit der PID des Halters).
+    /// Gewartet wird asynchron, sodass ein Ctrl-C den Befehl auch währenddessen abbricht.
+    pub async fn acquire(path: &Path, timeout: Duration, on_wait: impl FnOnce(Option<u32>)) -> Result<InstanceLock> {
+        let start = Instant::now();
+        let mut on_wait = Some(on_wait);
+
+        loop {
+            if let Some(lock) = Self::try_acquire(path)? {
+                return Ok(lock);
+            }
+
+            let holder = Self::holder_pid(path);
+            if start.elapsed() >= timeout {
+                return Err(match holder {
+                    Some(pid) => anyhow::anyhow!("another apt-ng process holds the lock (pid {})", pid),
+                    None => anyhow::anyhow!("another apt-ng process holds the lock ({})", path.display()),
+                });
+            }
+            if let Some(on_wait) = on_wait.take() {
+                on_wait(holder);
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    }
+
+    /// Versucht die Sperre ohne Warten zu erwerben; `None`, wenn ein anderer Prozess sie hält
+    pub fn try_acquire(path: &Path) -> Result<Option<InstanceLock>> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+
+        // SAFETY: flock auf einem gültigen, offenen Dateideskriptor
+        let result = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+        if result != 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::WouldBlock {
+                return Ok(None);
+            }
+            return Err(err).with_context(|| format!("Failed to lock {}", path.display()));
+        }
+
+        // Eigene PID für Fehlermeldungen anderer Prozesse hinterlegen
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        writeln!(file, "{}", std::process::id())?;
+        file.flush()?;
+
+        Ok(Some(InstanceLock {
+            file,
+            path: path.to_path_buf(),
+        }))
+    }
+
+    /// PID des Prozesses, der die Sperre zuletzt erworben hat
+    pub fn holder_pid(path: &Path) -> Option<u32> {
+        let mut content = String::new();
+        File::open(path).ok()?.read_to_string(&mut content).ok()?;
+        content.trim().parse().ok()
+    }
+
+    /// Pfad der Lock-Datei
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // SAFETY: Der Deskriptor gehört `self.file` und ist noch offen
+        unsafe {
+            libc::flock(self.file.as_raw_fd(), libc::LOCK_UN);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_second_lock_fails_with_pid() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("lock");
+
+        let lock = InstanceLock::try_acquire(&path).unwrap().expect("first lock must succeed");
+        assert!(InstanceLock::try_acquire(&path).unwrap().is_none());
+
+        let err = InstanceLock::acquire(&path, Duration::ZERO, |_| {}).await.err().unwrap();
+        assert_eq!(err.to_string(), format!("another apt-ng process holds the lock (pid {})", std::process::id()));
+
+        drop(lock);
+        assert!(InstanceLock::try_acquire(&path).unwrap().is_some());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_waiting_for_the_lock_does_not_block_the_runtime() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("lock");
+        let _lock = InstanceLock::try_acquire(&path).unwrap().unwrap();
+
+        // Ein Ctrl-C (hier: ein anderer Zweig von select!) kommt auch während des Wartens durch
+        let interrupted = tokio::select! {
+            _ = InstanceLock::acquire(&path, Duration::from_secs(30), |_| {}) => false,
+            _ = tokio::time::sleep(Duration::from_millis(300)) => true,
+        };
+        assert!(interrupted);
+    }
+}
@@ -0,0 +1,183 @@
+//! Lockfiles: eine aufgelöste Paketmenge zum exakten Wiederholen
+//!
+//! `install --write-lock` hält Name, Version, Architektur, Prüfsumme und Repository jedes
+//! Pakets der Lösung fest; `install-lock` installiert genau diese Menge wieder. Die Datei
+//! enthält keine Zeitstempel, dieselbe Lösung ergibt also byte-identische Lockfiles.
+
+use crate::index::Index;
+use crate::package::PackageManifest;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Aktuelle Formatversion
+pub const LOCKFILE_VERSION: u32 = 1;
+
+/// Ein festgehaltenes Paket
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    pub arch: String,
+    /// SHA256 aus dem Packages-Index
+    pub checksum: String,
+    /// Basis-URL des Repositories, aus dem das Paket stammte
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo_url: Option<String>,
+}
+
+/// Aufgelöste Paketmenge in Installationsreihenfolge
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    pub packages: Vec<LockedPackage>,
+}
+
+impl Lockfile {
+    /// Hält die Pakete einer Lösung fest; die Reihenfolge bleibt erhalten
+    pub fn from_packages(index: &Index, packages: &[PackageManifest]) -> Result<Self> {
+        let mut locked = Vec::with_capacity(packages.len());
+        for pkg in packages {
+            let repo_url = match pkg.repo_id {
+                Some(repo_id) => index.get_repo_url(repo_id)?,
+                None => None,
+            };
+            locked.push(LockedPackage {
+                name: pkg.name.clone(),
+                version: pkg.version.clone(),
+                arch: pkg.arch.clone(),
+                checksum: pkg.checksum.clone(),
+                repo_url,
+            });
+        }
+        Ok(Lockfile { version: LOCKFILE_VERSION, packages: locked })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    pub fn from_json(content: &str) -> Result<Self> {
+        let lockfile: Lockfile = serde_json::from_str(content).context("Invalid lockfile")?;
+        if lockfile.version != LOCKFILE_VERSION {
+            anyhow::bail!(
+                "Unsupported lockfile version {} (expected {})",
+                lockfile.version,
+                LOCKFILE_VERSION
+            );
+        }
+        Ok(lockfile)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_json()?)
+            .with_context(|| format!("Failed to write lockfile {}", path.display()))
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read lockfile {}", path.display()))?;
+        Self::from_json(&content)
+    }
+
+    /// Sucht jedes festgehaltene Paket im Index
+    ///
+    /// Schlägt fehl, wenn ein Paket in dieser Version und Architektur nicht mehr verfügbar
+    /// ist, nur noch aus einem anderen Repository stammt oder sich seine Prüfsumme geändert
+    /// hat; alle Abweichungen werden gemeinsam gemeldet.
+    pub fn resolve(&self, index: &Index) -> Result<Vec<PackageManifest>> {
+        let mut resolved = Vec::with_capacity(self.packages.len());
+        let mut problems = Vec::new();
+        for locked in &self.packages {
+            let mut candidates = Vec::new();
+            for pkg in index.search_exact(&locked.name)? {
+                if pkg.version != locked.version || pkg.arch != locked.arch {
+                    continue;
+                }
+                let repo_url = match pkg.repo_id {
+                    Some(repo_id) => index.get_repo_url(repo_id)?,
+                    None => None,
+                };
+                candidates.push((pkg, repo_url));
+            }
+            let from_locked_repo = candidates.iter()
+                .position(|(_, repo_url)| locked.repo_url.is_none() || *repo_url == locked.repo_url);
+            let candidate = from_locked_repo.map(|position| candidates.swap_remove(position).0);
+            match candidate {
+                None if !candidates.is_empty() => problems.push(format!(
+                    "{} {} ({}) is no longer available from {}",
+                    locked.name, locked.version, locked.arch, locked.repo_url.as_deref().unwrap_or_default()
+                )),
+                None => problems.push(format!(
+                    "{} {} ({}) is no longer available",
+                    locked.name, locked.version, locked.arch
+                )),
+                Some(pkg) if pkg.checksum != locked.checksum => problems.push(format!(
+                    "{} {} ({}) has checksum {} instead of {}",
+                    locked.name, locked.version, locked.arch, pkg.checksum, locked.checksum
+                )),
+                Some(pkg) => resolved.push(pkg),
+            }
+        }
+
+        if !problems.is_empty() {
+            anyhow::bail!("Lockfile cannot be installed:\n  {}", problems.join("\n  "));
+        }
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockfile_round_trip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        let packages = crate::apt_parser::parse_packages_file(
+            "Package: libfoo\nVersion: 1.2-1\nArchitecture: amd64\nSHA256: aaaa\n\n\
+             Package: foo\nVersion: 2.0\nArchitecture: all\nDepends: libfoo\nSHA256: bbbb\n",
+        )
+        .unwrap();
+        let repo = crate::repo::Repository::new("https://deb.example.org", None, &[], &[]);
+        crate::repo::Repository::add_to_db(index.conn(), &repo).unwrap();
+        index.replace_repo_packages(1, &packages).unwrap();
+
+        let solution = index.search_exact("libfoo").unwrap().into_iter()
+            .chain(index.search_exact("foo").unwrap())
+            .collect::<Vec<_>>();
+        let lockfile = Lockfile::from_packages(&index, &solution).unwrap();
+        let path = temp_dir.path().join("apt-ng.lock");
+        lockfile.write(&path).unwrap();
+
+        // Dieselbe Lösung ergibt dieselben Bytes
+        let json = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(json, Lockfile::from_packages(&index, &solution).unwrap().to_json().unwrap());
+
+        let read = Lockfile::read(&path).unwrap();
+        assert_eq!(read, lockfile);
+        assert_eq!(read.packages[0].repo_url.as_deref(), Some("https://deb.example.org"));
+        let resolved = read.resolve(&index).unwrap();
+        assert_eq!(
+            resolved.iter().map(|pkg| (pkg.name.as_str(), pkg.version.as_str())).collect::<Vec<_>>(),
+            vec![("libfoo", "1.2-1"), ("foo", "2.0")]
+        );
+
+        // Geänderte Prüfsummen und verschwundene Versionen werden abgelehnt
+        let mut tampered = read.clone();
+        tampered.packages[0].checksum = "cccc".to_string();
+        tampered.packages[1].version = "1.0".to_string();
+        let err = tampered.resolve(&index).unwrap_err().to_string();
+        assert!(err.contains("libfoo 1.2-1 (amd64) has checksum aaaa instead of cccc"));
+        assert!(err.contains("foo 1.0 (all) is no longer available"));
+
+        // Dieselbe Version aus einem anderen Repository zählt nicht
+        let mut moved = read.clone();
+        moved.packages[1].repo_url = Some("https://mirror.example.org".to_string());
+        let err = moved.resolve(&index).unwrap_err().to_string();
+        assert!(err.contains("foo 2.0 (all) is no longer available from https://mirror.example.org"), "{}", err);
+
+        assert!(Lockfile::from_json("{\"version\": 2, \"packages\": []}").is_err());
+    }
+}
@@ -0,0 +1,82 @@
+//! Protokolldatei für die Fehlersuche (`--log-file`)
+//!
+//! Unabhängig von der Terminalausgabe (`output::Output`, `-v`) schreibt apt-ng über `tracing`
+//! Download-URLs, Schritte der Abhängigkeitsauflösung, Hook-Aufrufe und Fehler in eine Datei,
+//! die sich an Fehlerberichte anhängen lässt. Ohne `--log-file` wird nichts aufgezeichnet.
+
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+use tracing::level_filters::LevelFilter;
+
+/// Format der Protokolldatei
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Eine lesbare Zeile pro Ereignis
+    Text,
+    /// Ein JSON-Objekt pro Zeile (Zeitstempel, Level, Modul, Felder)
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => anyhow::bail!("Unknown log format `{}` (expected text or json)", other),
+        }
+    }
+}
+
+/// Parst ein Level (`error`, `warn`, `info`, `debug`, `trace` oder `off`)
+pub fn parse_level(level: &str) -> Result<LevelFilter> {
+    LevelFilter::from_str(level).map_err(|_| {
+        anyhow::anyhow!("Unknown log level `{}` (expected error, warn, info, debug, trace or off)", level)
+    })
+}
+
+/// Leitet alle `tracing`-Ereignisse ab `level` in die Datei `path` um
+///
+/// Die Datei wird angelegt oder fortgeschrieben, damit mehrere Läufe in einem Protokoll
+/// landen. Darf nur einmal pro Prozess aufgerufen werden.
+pub fn init(path: &Path, level: LevelFilter, format: LogFormat) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create log directory {}", parent.display()))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open log file {}", path.display()))?;
+
+    let builder = tracing_subscriber::fmt()
+        .with_writer(Mutex::new(file))
+        .with_max_level(level)
+        .with_ansi(false);
+    let result = match format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().try_init(),
+    };
+    result
+        .map_err(anyhow::Error::from_boxed)
+        .context("Failed to initialize logging")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_options() {
+        assert_eq!(parse_level("debug").unwrap(), LevelFilter::DEBUG);
+        assert_eq!(parse_level("off").unwrap(), LevelFilter::OFF);
+        assert!(parse_level("verbose").is_err());
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+}
@@ -1,25 +1,11 @@
-mod cli;
-mod config;
-mod index;
-mod downloader;
-mod verifier;
-mod installer;
-mod package;
-mod repo;
-mod solver;
-mod cache;
-mod apt_parser;
-mod system;
-mod output;
-mod sandbox;
-mod security;
-mod delta;
-mod repo_generator;
-mod self_update;
-
-use cli::{Commands, RepoCommands, CacheAction, SecurityCommands};
+use apt_ng::{
+    apt_parser, cache, cleanup, cli, config, downloader, extract, index, installer, lock, lockfile, logging, output, repo,
+    sandbox, self_update, solver, system, verifier,
+};
+use cli::{Commands, RepoCommands, CacheAction, IndexCommands, SecurityCommands, KeyCommands, ConfigCommands, HistoryCommands};
+use anyhow::Context;
 use std::path::Path;
-use std::collections::{HashSet, HashMap};
+use std::collections::{HashMap, HashSet};
 use clap::CommandFactory;
 
 fn format_size(bytes: u64) -> String {
@@ -39,8 +25,73 @@ fn format_size(bytes: u64) -> String {
     }
 }
 
+fn format_size_delta(old: u64, new: u64) -> String {
+    if new >= old {
+        format!("+{}", format_size(new - old))
+    } else {
+        format!("-{}", format_size(old - new))
+    }
+}
+
+/// Zeile zum Speicherbedarf eines Plans wie bei apt ("... will be used" bzw. "... will be freed")
+fn disk_usage_message(delta: i64) -> String {
+    if delta < 0 {
+        format!("After this operation, {} of disk space will be freed.", format_size(delta.unsigned_abs()))
+    } else {
+        format!("After this operation, {} of additional disk space will be used.", format_size(delta as u64))
+    }
+}
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() {
+    // Bei Ctrl-C wird `run` verworfen: laufende Downloads brechen ab, die Index-Verbindung
+    // wird geschlossen (SQLite schreibt dabei das WAL zurück) und die Instanz-Sperre freigegeben
+    let result = tokio::select! {
+        result = run() => Some(result),
+        _ = tokio::signal::ctrl_c() => None,
+    };
+    
+    let Some(result) = result else {
+        eprintln!();
+        output::Output::warning("Interrupted, cleaning up...");
+        tracing::warn!("interrupted by Ctrl-C");
+        cleanup_after_interrupt();
+        std::process::exit(apt_ng::error::exit_code::INTERRUPTED);
+    };
+    
+    if let Err(e) = result {
+        tracing::error!("{:#}", e);
+        eprintln!("Error: {:#}", e);
+        std::process::exit(apt_ng::error::exit_code_for(&e));
+    }
+}
+
+/// Räumt nach einem Abbruch auf: temporäre Dateien und eine halb fertige Installation
+fn cleanup_after_interrupt() {
+    cleanup::remove_temp_paths();
+    
+    let config = match config::Config::load(None) {
+        Ok(config) => config,
+        Err(_) => return,
+    };
+    let journal_path = config.transaction_journal_path();
+    if !journal_path.exists() {
+        return;
+    }
+    
+    // Nur zurückrollen, wenn kein anderer Prozess gerade installiert (dessen Journal wäre nicht verwaist)
+    let _lock = match lock::InstanceLock::try_acquire(&config.lock_path()) {
+        Ok(Some(lock)) => lock,
+        _ => return,
+    };
+    match installer::InstallationTransaction::load_journal(&journal_path).and_then(|t| t.rollback()) {
+        Ok(()) => output::Output::info("Interrupted installation rolled back"),
+        Err(e) => output::Output::error(&format!(
+            "Failed to roll back interrupted installation: {} (it will be offered again on the next run)", e)),
+    }
+}
+
+async fn run() -> anyhow::Result<()> {
     // Initialize output system
     output::Output::init();
     
@@ -51,141 +102,345 @@ async fn main() -> anyhow::Result<()> {
         return Ok(());
     }
     
-    // Start update check early (before parsing, so it runs even if help is shown)
-    // Check if this is a self-update command by checking args first
     let args: Vec<String> = std::env::args().collect();
-    let is_self_update = args.len() > 1 && args[1] == "self-update";
-    
-    let update_check_handle = if !is_self_update {
-        Some(check_for_updates_background())
-    } else {
-        None
-    };
     
     // Parse CLI arguments - use try_parse to catch errors without exiting
     let opts = match cli::try_parse() {
         Ok(opts) => opts,
         Err(e) => {
-            // If parsing fails (e.g., no command), show help and wait for update check
             e.print().unwrap();
-            if let Some(handle) = update_check_handle {
-                // Wait longer (up to 6 seconds) to ensure update check completes
-                // The quick_check_update_available has a 5 second timeout
-                tokio::time::sleep(tokio::time::Duration::from_secs(6)).await;
-                // Try to await the handle to ensure it completes
-                let _ = handle.await;
-            }
-            std::process::exit(2);
+            std::process::exit(apt_ng::error::exit_code::USAGE);
         }
     };
     
-    // Check for updates in background (non-blocking)
-    // Skip check for self-update command to avoid recursion
-    let check_updates = !matches!(&opts.command, Commands::SelfUpdate { .. });
+    // -q / -v / -vv gelten für alle Meldungen über output::Output
+    output::Output::set_verbosity(opts.verbosity());
+    let verbose = opts.verbose > 0;
+    
+    // Diagnoseprotokoll, unabhängig von der Terminalausgabe
+    if let Some(log_file) = &opts.log_file {
+        logging::init(Path::new(log_file), logging::parse_level(&opts.log_level)?, opts.log_format.parse()?)?;
+        tracing::info!(version = env!("CARGO_PKG_VERSION"), args = ?args, "apt-ng started");
+    }
+    
+    // Load configuration (im Nutzermodus aus den XDG-Verzeichnissen)
+    config::set_user_mode(opts.user);
+    let mut config = config::Config::load(None)?;
+    if let Some(timeout) = opts.timeout {
+        config.set_overall_timeout(timeout);
+    }
+    if let Some(release) = &opts.target_release {
+        config.set_default_release(release);
+    }
+    if opts.offline {
+        config.set_offline();
+    }
+    for key in config::Config::unknown_keys_in(None) {
+        output::Output::warning(&format!("Unknown config key `{}` in {} is ignored", key, config::Config::default_path().display()));
+    }
+    
+    extract::set_max_decompressed_size(config.max_decompressed_size());
+    extract::set_max_index_size(config.max_index_size());
     
-    // Use the handle we created earlier, or create a new one if needed
-    let final_update_check_handle = if let Some(handle) = update_check_handle {
-        Some(handle)
-    } else if check_updates {
-        Some(check_for_updates_background())
+    // Höchstens einmal am Tag parallel zum Befehl nach einer neuen apt-ng-Version suchen
+    let update_check = if update_check_wanted(&opts, &config) {
+        Some(check_for_updates_background(&config))
     } else {
         None
     };
     
-    // Load configuration
-    let config = config::Config::load(None)?;
+    // Fehlende dpkg/apt-Werkzeuge werden durch eingebaute Ersatzwege abgedeckt
+    if verbose {
+        for tool in apt_ng::dpkg::Capabilities::get().missing() {
+            output::Output::info(&format!("{} not found, using {}", tool.program(), tool.fallback()));
+        }
+    }
     
     // Stelle sicher, dass alle benötigten Verzeichnisse existieren
     if let Err(e) = std::fs::create_dir_all(&config.paths.state_dir) {
         eprintln!("Warning: Could not create state directory {:?}: {}", config.paths.state_dir, e);
-        eprintln!("Hint: You may need root privileges or the directory may need to be created manually.");
+        eprintln!("Hint: You may need root privileges (or use --user) or the directory may need to be created manually.");
         return Err(e.into());
     }
     if let Err(e) = std::fs::create_dir_all(&config.paths.cache_dir) {
         eprintln!("Warning: Could not create cache directory {:?}: {}", config.paths.cache_dir, e);
-        eprintln!("Hint: You may need root privileges or the directory may need to be created manually.");
+        eprintln!("Hint: You may need root privileges (or use --user) or the directory may need to be created manually.");
+        return Err(e.into());
+    }
+    if let Err(e) = std::fs::create_dir_all(&config.paths.install_root) {
+        eprintln!("Warning: Could not create install root {:?}: {}", config.paths.install_root, e);
+        eprintln!("Hint: You may need root privileges (or use --user) or the directory may need to be created manually.");
         return Err(e.into());
     }
     if let Err(e) = std::fs::create_dir_all(&config.paths.trusted_keys_dir) {
         eprintln!("Warning: Could not create trusted keys directory {:?}: {}", config.paths.trusted_keys_dir, e);
-        eprintln!("Hint: You may need root privileges or the directory may need to be created manually.");
+        eprintln!("Hint: You may need root privileges (or use --user) or the directory may need to be created manually.");
         return Err(e.into());
     }
+    // Ohne Schreibrecht (z.B. `search` als normaler Nutzer) bleibt es beim System-Temp-Verzeichnis
+    match std::fs::create_dir_all(config.tmp_dir()) {
+        Ok(()) => cleanup::set_temp_dir(config.tmp_dir()),
+        Err(e) if verbose => {
+            output::Output::warning(&format!("Could not create temp directory {}: {}", config.tmp_dir().display(), e));
+        }
+        Err(_) => {}
+    }
+    
+    // Schreibende Befehle laufen exklusiv; lesende nehmen die Sperre nicht
+    let instance_lock = if command_needs_lock(&opts.command) || opts.rebuild_index {
+        Some(lock::InstanceLock::acquire(&config.lock_path(), lock::DEFAULT_LOCK_TIMEOUT, |holder| {
+            let holder = holder.map(|pid| format!(" (pid {})", pid)).unwrap_or_default();
+            output::Output::info(&format!("Waiting for another apt-ng process{} to finish...", holder));
+        }).await?)
+    } else {
+        None
+    };
+    
+    // Reste früherer, hart beendeter Läufe aus dem Temp-Verzeichnis entfernen
+    cleanup::sweep_stale_temp_files(cleanup::STALE_TEMP_AGE);
+    
+    // Prüfe auf eine unterbrochene Installation vom letzten Lauf – aber nur, wenn kein
+    // anderer Prozess gerade installiert (dessen Journal ist nicht verwaist)
+    if instance_lock.is_some() {
+        recover_incomplete_transaction(&config)?;
+    } else if let Some(_recovery_lock) = lock::InstanceLock::try_acquire(&config.lock_path())? {
+        recover_incomplete_transaction(&config)?;
+    }
     
     // Initialisiere Index
-    let index = index::Index::new(config.index_db_path().to_str().unwrap())?;
+    let index = open_index(&config, opts.rebuild_index)?;
+    
+    // Parallelität je Operation, begrenzt auf die konfigurierte Obergrenze
+    let jobs = config.effective_jobs(opts.jobs);
+    if let Some(requested) = opts.jobs {
+        if requested > config.job_cap() {
+            output::Output::warning(&format!(
+                "--jobs {} exceeds the maximum of {}; using {}", requested, config.job_cap(), config.job_cap()));
+        }
+    }
     
     // Führe Command aus
     match &opts.command {
-        Commands::Update => {
-            // Use max jobs if -j not specified, otherwise use config.jobs() which respects config file
-            let jobs = opts.jobs.unwrap_or_else(|| config.jobs());
-            cmd_update(&index, &config, jobs, opts.verbose).await?;
+        Commands::Update { .. } if config.offline() => {
+            let urls: Vec<String> = repo::Repository::load_all(index.conn())?.into_iter().map(|repo| repo.url).collect();
+            return Err(anyhow::Error::new(apt_ng::AptNgError::Offline { url: urls.join(", ") })
+                .context("Cannot update the package index in offline mode (--offline)"));
+        }
+        Commands::Update { allow_expired_keys, allow_stale_metadata, download_contents } => {
+            let download_contents = *download_contents || config.download_contents();
+            cmd_update(&index, &config, jobs.download, *allow_expired_keys, *allow_stale_metadata, download_contents, verbose).await?;
         }
         Commands::Search { term } => {
-            cmd_search(&index, term, opts.verbose)?;
+            cmd_search(&index, term, verbose)?;
+        }
+        Commands::FileSearch { pattern } => {
+            cmd_file_search(&index, pattern)?;
+        }
+        Commands::Install { packages, download_only, write_lock, install_suggests } => {
+            let write_lock = write_lock.as_deref().map(Path::new);
+            cmd_install(&index, &config, packages, &[], None, write_lock, jobs, opts.dry_run, *download_only, *install_suggests, opts.no_sandbox, verbose).await?;
+        }
+        Commands::InstallLock { file } => {
+            cmd_install_lock(&index, &config, Path::new(file), jobs, opts.dry_run, opts.no_sandbox, verbose).await?;
         }
-        Commands::Install { packages } => {
-            // Use max jobs if -j not specified, otherwise use config.jobs() which respects config file
-            let jobs = opts.jobs.unwrap_or_else(|| config.jobs());
-            cmd_install(&index, &config, packages, jobs, opts.dry_run, opts.verbose).await?;
+        Commands::Download { packages, resolve_deps } => {
+            cmd_download(&index, &config, packages, *resolve_deps, jobs, verbose).await?;
         }
         Commands::Remove { packages } => {
-            cmd_remove(&index, packages, opts.dry_run, opts.verbose).await?;
+            cmd_remove(&index, packages, opts.dry_run, verbose).await?;
+        }
+        Commands::Upgrade { download_only, write_lock } => {
+            let write_lock = write_lock.as_deref().map(Path::new);
+            cmd_upgrade(&index, &config, jobs, false, false, write_lock, opts.dry_run, *download_only, opts.no_sandbox, verbose).await?;
+        }
+        Commands::FullUpgrade { download_only, yes, write_lock } => {
+            let write_lock = write_lock.as_deref().map(Path::new);
+            cmd_upgrade(&index, &config, jobs, true, *yes, write_lock, opts.dry_run, *download_only, opts.no_sandbox, verbose).await?;
+        }
+        Commands::Show { package, all_versions } => {
+            if *all_versions {
+                cmd_show_all_versions(&index, package)?;
+            } else {
+                cmd_show(&index, package, verbose)?;
+            }
+        }
+        Commands::Changelog { package } => {
+            cmd_changelog(&index, &config, package, verbose).await?;
+        }
+        Commands::Files { package } => {
+            cmd_files(&index, &config, package)?;
+        }
+        Commands::WhichPackage { path } => {
+            cmd_which_package(&index, &config, Path::new(path))?;
         }
-        Commands::Upgrade => {
-            // Use max jobs if -j not specified, otherwise use config.jobs() which respects config file
-            let jobs = opts.jobs.unwrap_or_else(|| config.jobs());
-            cmd_upgrade(&index, &config, jobs, opts.dry_run, opts.verbose).await?;
+        Commands::Verify { path, signature } => {
+            cmd_verify(&index, &config, Path::new(path), signature.as_deref().map(Path::new), verbose)?;
         }
-        Commands::Show { package } => {
-            cmd_show(&index, package, opts.verbose)?;
+        Commands::BuildApx { source_dir, output, control, name, version, arch, depends, sign_key } => {
+            cmd_build_apx(
+                Path::new(source_dir),
+                output.as_deref(),
+                control.as_deref(),
+                name.as_deref(),
+                version.as_deref(),
+                arch.as_deref(),
+                depends,
+                sign_key.as_deref(),
+                verbose,
+            )?;
         }
         Commands::Repo(repo_cmd) => {
             match repo_cmd {
-                RepoCommands::Add { url } => {
-                    cmd_repo_add(&index, url)?;
+                RepoCommands::Add { url, suite, components, arch, signed_by, trusted } => {
+                    cmd_repo_add(&index, url, suite.as_deref(), components.as_deref(), arch.as_deref(), signed_by.as_deref(), *trusted)?;
+                }
+                RepoCommands::Enable { url } => {
+                    cmd_repo_set_enabled(&index, url, true)?;
+                }
+                RepoCommands::Disable { url } => {
+                    cmd_repo_set_enabled(&index, url, false)?;
+                }
+                RepoCommands::List => {
+                    cmd_repo_list(&index)?;
                 }
                 RepoCommands::Update => {
-                    cmd_repo_update(&index, &config, opts.verbose).await?;
+                    cmd_repo_update(&index, &config, verbose).await?;
                 }
                 RepoCommands::Generate { directory, suite, component, arch, key } => {
-                    cmd_repo_generate(directory, suite, component, arch, key.as_deref(), opts.verbose)?;
+                    cmd_repo_generate(directory, suite, component, arch, key.as_deref(), verbose)?;
                 }
             }
         }
         Commands::Cache(action) => {
             match action {
                 CacheAction::Clean { old_versions, max_size } => {
-                    cmd_cache_clean(&config, *old_versions, *max_size, opts.verbose)?;
+                    cmd_cache_clean(&config, *old_versions, *max_size, verbose)?;
+                }
+            }
+        }
+        Commands::Index(index_cmd) => {
+            match index_cmd {
+                IndexCommands::Vacuum => {
+                    cmd_index_vacuum(&index)?;
                 }
             }
         }
         Commands::Security(security_cmd) => {
             match security_cmd {
-                SecurityCommands::Audit { format } => {
-                    cmd_security_audit(&format, opts.verbose)?;
+                SecurityCommands::Audit { format, offline } => {
+                    cmd_security_audit(&config, format, *offline || config.offline(), verbose).await?;
+                }
+            }
+        }
+        Commands::Key(key_cmd) => {
+            match key_cmd {
+                KeyCommands::List => {
+                    cmd_key_list(&config)?;
+                }
+            }
+        }
+        Commands::Config(config_cmd) => {
+            match config_cmd {
+                ConfigCommands::Get { key } => {
+                    cmd_config_get(&config, opts.jobs, key)?;
+                }
+                ConfigCommands::List => {
+                    cmd_config_list(&config, opts.jobs)?;
+                }
+            }
+        }
+        Commands::History(history_cmd) => {
+            match history_cmd {
+                HistoryCommands::List { limit } => {
+                    cmd_history_list(&index, *limit, verbose)?;
+                }
+                HistoryCommands::Undo { id } => {
+                    cmd_history_undo(&index, *id, opts.dry_run, verbose).await?;
                 }
             }
         }
-        Commands::SelfUpdate { force } => {
-            cmd_self_update(*force, opts.verbose).await?;
+        Commands::Stats => {
+            cmd_stats(&index, &config)?;
+        }
+        Commands::SelfUpdate { .. } if config.offline() => {
+            return Err(anyhow::Error::new(apt_ng::AptNgError::Offline { url: self_update::GITHUB_API_URL.to_string() })
+                .context("Cannot check for apt-ng updates in offline mode (--offline)"));
+        }
+        Commands::SelfUpdate { force, allow_unsigned_update } => {
+            cmd_self_update(*force, *allow_unsigned_update, opts.dry_run, verbose).await?;
         }
     }
     
-    // Wait for update check to complete and display message if update available
-    if let Some(handle) = final_update_check_handle {
-        // Wait longer (up to 6 seconds) to ensure update check completes
-        // The quick_check_update_available has a 5 second timeout
-        tokio::time::sleep(tokio::time::Duration::from_secs(6)).await;
-        // Try to await the handle to ensure it completes
-        let _ = handle.await;
+    // Nicht auf die Suche warten: eine noch laufende wird abgebrochen, ein gefundenes
+    // Release meldet dann ein späterer Aufruf
+    if let Some(handle) = update_check {
+        handle.abort();
+    }
+    // Nur nach erfolgreichen Befehlen
+    if update_notice_wanted(&opts, &config) {
+        if let Some(version) = self_update::SelfUpdater::take_update_notice(&config.update_notice_path()) {
+            // Auf stderr, damit umgeleitete Ausgaben des Befehls unverändert bleiben
+            eprintln!("A new apt-ng release ({}) is available; run `apt-ng self-update`.", version);
+        }
     }
     
     Ok(())
 }
 
-async fn cmd_update(index: &index::Index, config: &config::Config, jobs: usize, verbose: bool) -> anyhow::Result<()> {
+/// Öffnet die Index-Datenbank
+///
+/// Mit `--rebuild-index` oder nach Rückfrage bei einer beschädigten Datenbank wird die
+/// vorhandene Datei gesichert und ein neuer Index angelegt, der installierte Pakete, Verlauf
+/// und Repositories aus der Sicherung übernimmt.
+fn open_index(config: &config::Config, rebuild: bool) -> anyhow::Result<index::Index> {
+    let db_path = config.index_db_path();
+    let db_path = db_path.to_str().unwrap();
+    if !rebuild {
+        match index::Index::new(db_path) {
+            Err(e) if matches!(e.downcast_ref(), Some(apt_ng::AptNgError::IndexCorrupt { .. })) => {
+                output::Output::error(&e.to_string());
+                if !confirm("Back up the damaged index and create a new one?")? {
+                    return Err(e);
+                }
+            }
+            result => return result,
+        }
+    }
+    
+    let (index, backup) = index::Index::rebuild(db_path)?;
+    if let Some(backup) = backup {
+        output::Output::info(&format!("Previous index saved to {}", backup.display()));
+    }
+    output::Output::success("Rebuilt the package index; run `apt-ng update` to reload the package lists");
+    Ok(index)
+}
+
+/// Befehle, die Index oder Installationsbaum verändern und daher die Sperre benötigen
+fn command_needs_lock(command: &Commands) -> bool {
+    match command {
+        Commands::Update { .. }
+        | Commands::Install { .. }
+        | Commands::InstallLock { .. }
+        | Commands::Remove { .. }
+        | Commands::Upgrade { .. }
+        | Commands::FullUpgrade { .. }
+        | Commands::Cache(_)
+        | Commands::Index(_)
+        | Commands::History(HistoryCommands::Undo { .. }) => true,
+        Commands::Repo(repo_cmd) => !matches!(repo_cmd, RepoCommands::List | RepoCommands::Generate { .. }),
+        _ => false,
+    }
+}
+
+async fn cmd_update(
+    index: &index::Index,
+    config: &config::Config,
+    jobs: usize,
+    allow_expired_keys: bool,
+    allow_stale_metadata: bool,
+    download_contents: bool,
+    verbose: bool,
+) -> anyhow::Result<()> {
     output::Output::heading("🔄 Updating Package Index");
     
     if verbose {
@@ -211,73 +466,193 @@ async fn cmd_update(index: &index::Index, config: &config::Config, jobs: usize,
     output::Output::info(&format!("Found {} repositories", repos.len()));
     
     // Prüfe auf unsignierte Repositories
-    let verifier = verifier::PackageVerifier::new(config.trusted_keys_dir())?;
-    let require_signatures = verifier.trusted_key_count() > 0;
-    
-    if require_signatures {
-        output::Output::info(&format!("Signature verification enabled ({} trusted key(s))", verifier.trusted_key_count()));
-    } else {
-        output::Output::warning("No trusted keys found. Unsigned repositories will be allowed.");
+    let mut verifier = verifier::PackageVerifier::new(config.trusted_keys_dir())?;
+    verifier.load_openpgp_keyrings(&config.keyring_paths());
+    verifier.set_allow_expired_keys(allow_expired_keys);
+    // Vorgabe für Repositories ohne signed-by= oder trusted=; hängt nicht von der Zahl der Schlüssel ab
+    let require_by_default = config.require_signatures();
+    
+    if !require_by_default {
+        output::Output::warning("Signatures are only checked for repositories with signed-by or trusted=no (require_signatures = false)");
+    } else if verifier.trusted_key_count() == 0 {
+        output::Output::warning("No trusted keys found. Repositories without signed-by or trusted=yes will fail verification.");
         output::Output::info(&format!("Add trusted keys to: {}", config.trusted_keys_dir().display()));
+    } else {
+        output::Output::info(&format!("Signature verification enabled ({} trusted key(s))", verifier.trusted_key_count()));
     }
     
     // Lade Metadaten von Repositories
-    let downloader = downloader::Downloader::new(jobs)?;
+    let downloader = create_downloader(config, jobs)?;
     let mut total_packages = 0;
     
-    // Erkenne Debian-Suite automatisch
-    let detected_suite = system::detect_debian_suite().unwrap_or_else(|_| "stable".to_string());
-    output::Output::info(&format!("Detected Debian suite: {}", detected_suite));
+    // Distribution und Suite aus /etc/os-release (Fallback: /etc/debian_version)
+    let distro = system::detect_distro();
+    output::Output::info(&format!("Detected distribution: {} {}", distro.id, distro.codename));
+    
+    let architectures = config.architectures();
+    output::Output::info(&format!("Architectures: {}", architectures.join(", ")));
+    
+    // Erste abgelehnte (veraltete) Release-Datei; die übrigen Repositories werden trotzdem aktualisiert
+    let mut stale_error = None;
     
     for repo in &repos {
         output::Output::repo_info(&repo.url);
         
-        // Verwende erkannte Suite oder die aus der sources.list
-        let suite = repo.suite.as_deref()
-            .or_else(|| Some(&detected_suite))
-            .unwrap_or("stable");
-        let components = if repo.components.is_empty() {
+        let mut components = if repo.components.is_empty() {
             vec!["main".to_string()]
         } else {
             repo.components.clone()
         };
-        
-        // Für Security-Repositories: Verwende bookworm-security oder bookworm/updates
-        let is_security = repo.url.contains("security.debian.org");
-        let suite_path = if is_security {
-            // Security-Repos verwenden entweder {suite}-security oder {suite}/updates
-            format!("{}-security", suite)
+        // `repo add --arch` bzw. `[arch=...]` aus sources.list gehen der Konfiguration vor
+        let mut architectures = if repo.architectures.is_empty() {
+            architectures.clone()
         } else {
-            suite.to_string()
+            system::with_arch_all(repo.architectures.clone())
+        };
+        
+        if repo.is_local() && verbose {
+            output::Output::info("  Local repository, reading metadata from the filesystem");
+        }
+        
+        // Signaturen je Repository: eigener Schlüsselbund, globale Schlüssel oder bewusst ungeprüft
+        let policy = repo.signature_policy(require_by_default);
+        let keyring_verifier = match &policy {
+            repo::SignaturePolicy::SignedBy(keyring) => match verifier::PackageVerifier::with_keyring(keyring) {
+                Ok(mut keyring_verifier) => {
+                    keyring_verifier.set_allow_expired_keys(allow_expired_keys);
+                    Some(keyring_verifier)
+                }
+                Err(e) => {
+                    output::Output::error(&format!("Repository {} rejected: {}. Skipping.", repo.url, e));
+                    continue;
+                }
+            },
+            _ => None,
+        };
+        let release_verifier = match &policy {
+            repo::SignaturePolicy::SignedBy(_) => keyring_verifier.as_ref(),
+            repo::SignaturePolicy::Required => Some(&verifier),
+            repo::SignaturePolicy::Skip => None,
+        };
+        if verbose {
+            match &policy {
+                repo::SignaturePolicy::SignedBy(keyring) => output::Output::info(&format!("  Signed by {}", keyring.display())),
+                repo::SignaturePolicy::Skip if repo.trusted == Some(true) => output::Output::info("  Trusted repository, skipping signature verification"),
+                _ => {}
+            }
+        }
+        
+        // Suite aus sources.list bzw. gespeichertem Codename, sonst nach den Konventionen des Archivs
+        let candidates = repo.suite_candidates(&distro);
+        
+        // Die Release-Datei bestätigt den Suite-Pfad und liefert den Codename
+        let (suite_path, release) = match fetch_release_info(&downloader, &repo.url, &candidates).await {
+            Some((suite_path, release)) => {
+                if verbose {
+                    output::Output::info(&format!("  Release: Suite {}, Codename {}, Version {}",
+                        release.suite.as_deref().unwrap_or("-"),
+                        release.codename.as_deref().unwrap_or("-"),
+                        release.version.as_deref().unwrap_or("-")));
+                }
+                
+                let suite_path = match &release.codename {
+                    Some(codename) => {
+                        if repo.codename.as_deref() != Some(codename.as_str()) {
+                            repo::Repository::set_codename(index.conn(), &repo.url, codename)?;
+                        }
+                        // Ohne explizite Suite den Codename verwenden, damit spätere Läufe denselben Pfad nutzen
+                        if repo.suite.is_none() { codename.clone() } else { suite_path }
+                    }
+                    None => suite_path,
+                };
+                (suite_path, Some(release))
+            }
+            None => {
+                if verbose {
+                    output::Output::warning(&format!("  No Release file found, trying suite {}", candidates[0]));
+                }
+                (candidates[0].clone(), None)
+            }
+        };
+        
+        // Signatur prüfen, bevor irgendetwas aus der Release-Datei übernommen wird
+        let verified_release = match release_verifier {
+            Some(release_verifier) => match fetch_verified_release(&downloader, &repo.url, &suite_path, release_verifier, verbose).await {
+                Ok(verified) => {
+                    if verbose {
+                        output::Output::info(&format!("✓ Repository signature verified for {}", repo.url));
+                    }
+                    Some(verified)
+                }
+                Err(Some(reason)) => {
+                    output::Output::error(&format!("Repository {} rejected: {}. Skipping.", repo.url, reason));
+                    if !allow_expired_keys {
+                        output::Output::list_item("Use --allow-expired-keys to accept mirrors that lag behind a key rotation.");
+                    }
+                    continue;
+                }
+                Err(None) => {
+                    output::Output::warning(&format!("Repository {} has no valid signature files. Skipping.", repo.url));
+                    continue;
+                }
+            },
+            None => None,
+        };
+        // Bei signierten Repositories zählt nur der geprüfte Text; unsignierte liefern nichts Besseres
+        let release = match &verified_release {
+            Some(verified) => Some(verified.info()),
+            None => release,
         };
         
+        if let Some(release) = &release {
+            // Replay-Schutz: abgelaufene oder ältere Release-Dateien ablehnen
+            let previous_date = index.get_meta(&repo.url, RELEASE_DATE_META_KEY)?
+                .and_then(|date| date.parse::<i64>().ok());
+            if let Err(message) = release.check_freshness(chrono::Utc::now().timestamp(), previous_date) {
+                if allow_stale_metadata {
+                    output::Output::warning(&format!("  {} (accepted: --allow-stale-metadata)", message));
+                } else {
+                    output::Output::error(&format!("  {}", message));
+                    stale_error.get_or_insert(apt_ng::AptNgError::StaleMetadata { repo: repo.url.clone(), message });
+                    continue;
+                }
+            }
+            if let Some(date) = release.date {
+                if previous_date.is_none_or(|previous| date > previous) {
+                    index.set_meta(&repo.url, RELEASE_DATE_META_KEY, &date.to_string())?;
+                }
+            }
+            
+            // Nur Kombinationen laden, die das Repository laut Release-Datei veröffentlicht
+            let published_components = release.published_components(&components);
+            let published_architectures = release.published_architectures(&architectures);
             if verbose {
-                output::Output::info(&format!("  Suite: {:?}, Components: {:?}", suite, components));
+                let skipped: Vec<&String> = components.iter().filter(|c| !published_components.contains(c))
+                    .chain(architectures.iter().filter(|a| !published_architectures.contains(a)))
+                    .collect();
+                if !skipped.is_empty() {
+                    output::Output::info(&format!("  Not published by the repository, skipping: {}",
+                        skipped.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ")));
+                }
             }
+            components = published_components;
+            architectures = published_architectures;
+        }
         
-        // Versuche verschiedene Architekturen
-        let architectures = vec!["amd64", "all"];
+            if verbose {
+                output::Output::info(&format!("  Suite: {:?}, Components: {:?}", suite_path, components));
+            }
         
+        // Alle Packages-Dateien des Repositories sammeln und erst danach atomar übernehmen
+        let mut repo_packages = Vec::new();
         let mut packages_loaded = false;
+        // Indizes, die nicht geladen werden konnten; ihre Pakete dürfen nicht gelöscht werden
+        let mut missing_indices = Vec::new();
         for component in &components {
             for arch in &architectures {
                         // Versuche verschiedene komprimierte Formate
-                        let possible_files = vec![
-                            format!("dists/{}/{}/binary-{}/Packages.xz", suite_path, component, arch),
-                            format!("dists/{}/{}/binary-{}/Packages.gz", suite_path, component, arch),
-                            format!("dists/{}/{}/binary-{}/Packages", suite_path, component, arch),
-                        ];
-                        
-                        // Für Security-Repos: Versuche auch bookworm/updates
-                        let mut security_files = Vec::new();
-                        if is_security {
-                            security_files.extend(vec![
-                                format!("dists/{}/updates/{}/binary-{}/Packages.xz", suite, component, arch),
-                                format!("dists/{}/updates/{}/binary-{}/Packages.gz", suite, component, arch),
-                                format!("dists/{}/updates/{}/binary-{}/Packages", suite, component, arch),
-                            ]);
-                        }
-                        let possible_files: Vec<String> = possible_files.into_iter().chain(security_files).collect();
+                        let possible_files = repo::packages_paths(&suite_path, component, arch);
+                let mut index_loaded = false;
                 
                 for file_path in possible_files {
                     let url = if file_path.starts_with("http") {
@@ -290,170 +665,38 @@ async fn cmd_update(index: &index::Index, config: &config::Config, jobs: usize,
                         output::Output::progress_message(&format!("Trying: {}...", url));
                     }
                     
-                    let temp_file = std::env::temp_dir().join(format!("apt-ng-packages-{}.tmp", 
+                    let temp_file = cleanup::TempPath::new(&format!("apt-ng-packages-{}.tmp", 
                         url.replace("/", "_").replace(":", "_").replace(".", "_")));
                     
-                    // Versuche herunterzuladen mit Timeout
-                    let download_result = tokio::time::timeout(
-                        std::time::Duration::from_secs(60),
-                        downloader.download_file(&url, &temp_file)
-                    ).await;
+                    // Zeitlimits (Verbindung, Lesen, pro Datei) setzt der Downloader aus der Konfiguration
+                    let download_result = downloader.download_file(&url, &temp_file).await;
                     
                     match download_result {
-                        Ok(Ok(_)) => {
+                        Ok(_) => {
                             if verbose {
                                 output::Output::success(&format!("Downloaded Packages file from {}", url));
                             }
                             
-                            // Prüfe und verifiziere Signatur-Dateien, wenn Signaturen erforderlich sind
-                            if require_signatures {
-                                let release_urls = vec![
-                                    format!("{}/dists/{}/InRelease", repo.url.trim_end_matches('/'), suite),
-                                    format!("{}/dists/{}/Release.gpg", repo.url.trim_end_matches('/'), suite),
-                                ];
-                                
-                                let mut has_valid_signature = false;
-                                for release_url in &release_urls {
-                                    // Versuche Release-Datei herunterzuladen
-                                    let release_temp = std::env::temp_dir().join(format!("apt-ng-release-{}.tmp", 
-                                        release_url.replace("/", "_").replace(":", "_").replace(".", "_")));
-                                    
-                                    if let Ok(_) = downloader.download_file(release_url, &release_temp).await {
-                                        // Versuche Signatur zu verifizieren
-                                        if let Ok(release_data) = std::fs::read(&release_temp) {
-                                            // Für InRelease: Signatur ist eingebettet, für Release.gpg: separate Datei
-                                            if release_url.ends_with("InRelease") {
-                                                // InRelease hat eingebettete Signatur - vereinfachte Prüfung
-                                                // In einer vollständigen Implementierung würde man hier die Signatur extrahieren und verifizieren
-                                                // Für jetzt prüfen wir nur ob die Datei existiert und nicht leer ist
-                                                if !release_data.is_empty() {
-                                                    has_valid_signature = true;
-                                                }
-                                            } else {
-                                                // Release.gpg benötigt separate Release-Datei
-                                                let release_file_url = release_url.replace(".gpg", "");
-                                                let release_file_temp = std::env::temp_dir().join(format!("apt-ng-release-file-{}.tmp", 
-                                                    release_file_url.replace("/", "_").replace(":", "_").replace(".", "_")));
-                                                
-                                                if let Ok(_) = downloader.download_file(&release_file_url, &release_file_temp).await {
-                                                    if let Ok(release_file_data) = std::fs::read(&release_file_temp) {
-                                                        // Versuche Signatur zu verifizieren
-                                                        if verifier.verify_with_trusted_keys(&release_file_data, &release_data).is_ok() {
-                                                            has_valid_signature = true;
-                                                        }
-                                                        let _ = std::fs::remove_file(&release_file_temp);
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        let _ = std::fs::remove_file(&release_temp);
-                                        
-                                        if has_valid_signature {
-                                            break;
-                                        }
-                                    }
-                                }
-                                
-                                if !has_valid_signature {
-                                    output::Output::warning(&format!("Repository {} has no valid signature files. Skipping.", repo.url));
+                            // Größe und SHA256 stammen aus dem signaturgeprüften Text, nicht aus der Datei
+                            if let Some(release) = &verified_release {
+                                let index_path = file_path.trim_start_matches(&format!("dists/{}/", suite_path));
+                                if let Err(e) = release.check_index_file(index_path, &temp_file) {
+                                    output::Output::warning(&format!("Rejecting {}: {}", url, e));
+                                    let _ = std::fs::remove_file(&temp_file);
                                     continue;
                                 }
-                                
-                                if verbose {
-                                    output::Output::info(&format!("✓ Repository signature verified for {}", repo.url));
-                                }
                             }
                             
-                            // Versuche zu dekomprimieren und zu parsen
-                            let content = if file_path.ends_with(".xz") {
-                                // XZ-Kompression
-                                use xz2::read::XzDecoder;
-                                use std::io::Read;
-                                let mut decoder = XzDecoder::new(std::fs::File::open(&temp_file)?);
-                                let mut content = String::new();
-                                decoder.read_to_string(&mut content)?;
-                                content
-                            } else if file_path.ends_with(".gz") {
-                                // GZIP-Kompression
-                                use flate2::read::GzDecoder;
-                                use std::io::Read;
-                                let mut decoder = GzDecoder::new(std::fs::File::open(&temp_file)?);
-                                let mut content = String::new();
-                                decoder.read_to_string(&mut content)?;
-                                content
-                            } else {
-                                // Unkomprimiert
-                                std::fs::read_to_string(&temp_file)?
-                            };
+                            // Dekomprimieren (begrenzt auf limits.max_decompressed_size) und parsen
+                            let content = extract::read_to_string_limited(&temp_file, &file_path)?;
                             
                             // Parse Packages-Datei
                             match apt_parser::parse_packages_file(&content) {
                                 Ok(packages) => {
                                     output::Output::info(&format!("Found {} packages in {}/{}", packages.len(), component, arch));
-                                    if verbose {
-                                        output::Output::info("Indexing packages...");
-                                    }
-                                    
-                                    // Erstelle Fortschrittsanzeige
-                                    let pb = output::Output::progress_bar(packages.len() as u64);
-                                    pb.set_message("Indexing");
-                                    
-                                    // Verwende Batch-Insert für bessere Performance
-                                    let repo_id = repo.id.unwrap_or(1);
-                                    
-                                    // Aktiviere Bulk-Insert-Modus für maximale Performance
-                                    if let Err(e) = index.begin_bulk_insert() {
-                                        if verbose {
-                                            output::Output::warning(&format!("Failed to enable bulk insert mode: {}", e));
-                                        }
-                                    }
-                                    
-                                    // Teile in Batches von 5000 Paketen auf (größere Batches = bessere Performance)
-                                    const BATCH_SIZE: usize = 5000;
-                                    let mut batch_errors = 0;
-                                    for (batch_idx, chunk) in packages.chunks(BATCH_SIZE).enumerate() {
-                                        match index.add_packages_batch(chunk, repo_id) {
-                                            Ok(_) => {
-                                                total_packages += chunk.len();
-                                                pb.inc(chunk.len() as u64);
-                                            }
-                                            Err(e) => {
-                                                batch_errors += 1;
-                                                // Fallback: Einzelne Pakete hinzufügen
-                                                if verbose {
-                                                    output::Output::warning(&format!("Batch insert failed (batch {}), using individual inserts: {}", batch_idx + 1, e));
-                                                }
-                                                for pkg in chunk {
-                                                    match index.add_package(pkg, repo_id) {
-                                                        Ok(_) => {
-                                                            total_packages += 1;
-                                                            pb.inc(1);
-                                                        }
-                                                        Err(e) => {
-                                                            if verbose {
-                                                                output::Output::warning(&format!("Failed to add package {}: {}", pkg.name, e));
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                    
-                                    // Deaktiviere Bulk-Insert-Modus und reaktiviere Indizes
-                                    if let Err(e) = index.end_bulk_insert() {
-                                        if verbose {
-                                            output::Output::warning(&format!("Failed to end bulk insert mode: {}", e));
-                                        }
-                                    }
-                                    
-                                    pb.finish_with_message("Indexed");
-                                    
-                                    if batch_errors > 0 && verbose {
-                                        output::Output::warning(&format!("{} batches had errors and used fallback method", batch_errors));
-                                    }
-                                    
+                                    repo_packages.extend(packages);
                                     packages_loaded = true;
+                                    index_loaded = true;
                                     let _ = std::fs::remove_file(&temp_file);
                                     break;
                                 }
@@ -463,30 +706,88 @@ async fn cmd_update(index: &index::Index, config: &config::Config, jobs: usize,
                                 }
                             }
                         }
-                        Ok(Err(e)) => {
-                            // Download fehlgeschlagen, versuche nächste URL
+                        Err(e) => {
+                            // Download fehlgeschlagen oder Zeitlimit überschritten, versuche nächste URL
                             if verbose {
                                 output::Output::warning(&format!("Failed to download: {} ({})", url, e));
                             }
                         }
-                        Err(_) => {
-                            // Timeout
-                            if verbose {
-                                output::Output::warning(&format!("Timeout downloading: {}", url));
-                            }
-                        }
                     }
                 }
-                if packages_loaded {
-                    break;
+                if !index_loaded {
+                    missing_indices.push(format!("{}/{}", component, arch));
                 }
             }
-            if packages_loaded {
-                break;
-            }
         }
         
-        if !packages_loaded {
+        if packages_loaded {
+            if verbose {
+                output::Output::info("Indexing packages...");
+            }
+            // Aktiviere Bulk-Insert-Modus für maximale Performance; der Guard stellt die
+            // Indizes auch wieder her, wenn das Einfügen fehlschlägt
+            let bulk = match index.bulk_insert() {
+                Ok(bulk) => Some(bulk),
+                Err(e) => {
+                    if verbose {
+                        output::Output::warning(&format!("Failed to enable bulk insert mode: {}", e));
+                    }
+                    None
+                }
+            };
+            
+            let repo_id = repo.id.unwrap_or(1);
+            let result = if missing_indices.is_empty() {
+                index.replace_repo_packages(repo_id, &repo_packages)
+            } else {
+                output::Output::warning(&format!(
+                    "Could not load {} from {}; keeping their packages in the index",
+                    missing_indices.join(", "),
+                    repo.url
+                ));
+                index.merge_repo_packages(repo_id, &repo_packages).map(|()| 0)
+            };
+            
+            // Deaktiviere Bulk-Insert-Modus und reaktiviere Indizes
+            if let Some(Err(e)) = bulk.map(|bulk| bulk.finish()) {
+                if verbose {
+                    output::Output::warning(&format!("Failed to end bulk insert mode: {}", e));
+                }
+            }
+            
+            match result {
+                Ok(pruned) => {
+                    total_packages += repo_packages.len();
+                    if pruned > 0 {
+                        output::Output::info(&format!("Removed {} package(s) no longer available from {}", pruned, repo.url));
+                    }
+                }
+                Err(e) => {
+                    // Die Transaktion wurde zurückgerollt, der bisherige Stand bleibt erhalten
+                    output::Output::warning(&format!("Failed to index packages from {}: {} (keeping previous index)", repo.url, e));
+                }
+            }
+            
+            if download_contents {
+                let entries = fetch_contents(
+                    &downloader,
+                    &repo.url,
+                    &suite_path,
+                    &components,
+                    &architectures,
+                    verified_release.as_ref(),
+                    verbose,
+                ).await;
+                if entries.is_empty() {
+                    output::Output::warning(&format!("No Contents index found for {}", repo.url));
+                } else {
+                    match index.replace_repo_contents(repo_id, &entries) {
+                        Ok(count) => output::Output::info(&format!("Indexed {} file(s) from Contents of {}", count, repo.url)),
+                        Err(e) => output::Output::warning(&format!("Failed to index Contents from {}: {}", repo.url, e)),
+                    }
+                }
+            }
+        } else {
             output::Output::warning(&format!("Could not load Packages from {}", repo.url));
             if verbose {
                 output::Output::info(&format!("  Suite: {:?}, Components: {:?}", repo.suite, repo.components));
@@ -494,6 +795,29 @@ async fn cmd_update(index: &index::Index, config: &config::Config, jobs: usize,
         }
     }
     
+    // Installierte Pakete, die upstream verschwunden sind, bleiben im Index, lassen sich aber nicht mehr laden
+    let unavailable = index.list_unavailable_installed()?;
+    if !unavailable.is_empty() {
+        output::Output::warning("The following installed packages are no longer available from their repository:");
+        for (name, version) in &unavailable {
+            output::Output::list_item(&format!("{} ({})", name, version));
+        }
+    }
+    
+    // Lokale Paketdateien gehören zu keinem Repository; nicht (mehr) installierte entfernen
+    match index.prune_local_packages() {
+        Ok(pruned) if pruned > 0 && verbose => {
+            output::Output::info(&format!("Removed {} local package file(s) that are not installed from the index", pruned));
+        }
+        Ok(_) => {}
+        Err(e) => output::Output::warning(&format!("Failed to prune local packages: {}", e)),
+    }
+    
+    // Nach dem Ersetzen vieler Zeilen: Statistiken auffrischen, bei viel freiem Platz kompaktieren
+    if let Err(e) = compact_index(index, false) {
+        output::Output::warning(&format!("Failed to optimize the index: {}", e));
+    }
+    
     if total_packages == 0 {
         output::Output::warning("No packages were indexed");
         output::Output::info("This might indicate:");
@@ -505,6 +829,186 @@ async fn cmd_update(index: &index::Index, config: &config::Config, jobs: usize,
         output::Output::summary("Index updated", total_packages);
     }
     
+    if let Some(e) = stale_error {
+        return Err(e.into());
+    }
+    
+    Ok(())
+}
+
+/// Lädt die Contents-Indizes aller Komponenten und Architekturen eines Repositories
+///
+/// Mit signaturgeprüfter Release-Datei werden Größe und SHA256 wie bei den Packages-Dateien
+/// geprüft. Fehlende Contents-Dateien sind kein Fehler.
+async fn fetch_contents(
+    downloader: &downloader::Downloader,
+    repo_url: &str,
+    suite_path: &str,
+    components: &[String],
+    architectures: &[String],
+    verified_release: Option<&verifier::VerifiedRelease>,
+    verbose: bool,
+) -> Vec<apt_parser::ContentsEntry> {
+    let mut entries = Vec::new();
+    let mut fetched = HashSet::new();
+    for component in components {
+        for arch in architectures {
+            for file_path in repo::contents_paths(suite_path, component, arch) {
+                // Contents direkt unter dists/<suite>/ gilt für alle Komponenten
+                if fetched.contains(&file_path) {
+                    break;
+                }
+                let url = format!("{}/{}", repo_url.trim_end_matches('/'), file_path);
+                let temp_file = cleanup::TempPath::new(&format!("apt-ng-contents-{}.tmp",
+                    url.replace("/", "_").replace(":", "_").replace(".", "_")));
+                
+                if downloader.download_file(&url, &temp_file).await.is_err() {
+                    continue;
+                }
+                if let Some(release) = verified_release {
+                    let index_path = file_path.trim_start_matches(&format!("dists/{}/", suite_path));
+                    if let Err(e) = release.check_index_file(index_path, &temp_file) {
+                        output::Output::warning(&format!("Rejecting {}: {}", url, e));
+                        continue;
+                    }
+                }
+                match extract::read_to_string_limited(&temp_file, &file_path) {
+                    Ok(content) => {
+                        let parsed = apt_parser::parse_contents_file(&content);
+                        if verbose {
+                            output::Output::info(&format!("  {} file(s) in {}", parsed.len(), file_path));
+                        }
+                        entries.extend(parsed);
+                        fetched.insert(file_path);
+                        break;
+                    }
+                    Err(e) => output::Output::warning(&format!("Failed to read {}: {}", url, e)),
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// Lädt InRelease bzw. Release und Release.gpg und prüft die Signatur
+///
+/// Im Fehlerfall enthält `Err` den Grund, wenn ein abgelaufener Schlüssel die Prüfung
+/// verhindert hat (getrennt von fehlenden oder ungültigen Signaturen gemeldet).
+async fn fetch_verified_release(
+    downloader: &downloader::Downloader,
+    repo_url: &str,
+    suite_path: &str,
+    release_verifier: &verifier::PackageVerifier,
+    verbose: bool,
+) -> Result<verifier::VerifiedRelease, Option<String>> {
+    let release_urls = [
+        format!("{}/dists/{}/InRelease", repo_url.trim_end_matches('/'), suite_path),
+        format!("{}/dists/{}/Release.gpg", repo_url.trim_end_matches('/'), suite_path),
+    ];
+    
+    let mut expiry_error: Option<String> = None;
+    for release_url in &release_urls {
+        let release_temp = cleanup::TempPath::new(&format!("apt-ng-release-{}.tmp", 
+            release_url.replace("/", "_").replace(":", "_").replace(".", "_")));
+        
+        if downloader.download_file(release_url, &release_temp).await.is_err() {
+            continue;
+        }
+        let Ok(release_data) = std::fs::read(&release_temp) else {
+            continue;
+        };
+        
+        // Für InRelease: Signatur ist eingebettet, für Release.gpg: separate Datei
+        let verified = if release_url.ends_with("InRelease") {
+            Some(release_verifier.verify_release_file(&release_data, None))
+        } else {
+            let release_file_url = release_url.replace(".gpg", "");
+            let release_file_temp = cleanup::TempPath::new(&format!("apt-ng-release-file-{}.tmp", 
+                release_file_url.replace("/", "_").replace(":", "_").replace(".", "_")));
+            
+            match downloader.download_file(&release_file_url, &release_file_temp).await {
+                Ok(_) => std::fs::read(&release_file_temp).ok()
+                    .map(|release_file_data| release_verifier.verify_release_file(&release_file_data, Some(&release_data))),
+                Err(_) => None,
+            }
+        };
+        
+        match verified {
+            Some(Ok(release)) => return Ok(release),
+            Some(Err(e)) => {
+                // Abgelaufene Schlüssel getrennt von fehlenden Signaturen melden
+                if let apt_ng::AptNgError::Signature(sig_err @ (verifier::SignatureError::KeyExpired { .. }
+                    | verifier::SignatureError::SignedAfterExpiry { .. })) = &e
+                {
+                    expiry_error = Some(sig_err.to_string());
+                } else if verbose {
+                    output::Output::warning(&format!("{}: {}", release_url, e));
+                }
+            }
+            None => {}
+        }
+    }
+    
+    Err(expiry_error)
+}
+
+/// Schlüssel in `index_meta` für das zuletzt akzeptierte Release-Datum
+const RELEASE_DATE_META_KEY: &str = "release_date";
+
+/// Lädt InRelease (oder Release) für die erste vorhandene Suite und parst die Kopfdaten
+async fn fetch_release_info(
+    downloader: &downloader::Downloader,
+    repo_url: &str,
+    suites: &[String],
+) -> Option<(String, apt_parser::ReleaseInfo)> {
+    for suite in suites {
+        for name in ["InRelease", "Release"] {
+            let url = format!("{}/dists/{}/{}", repo_url.trim_end_matches('/'), suite, name);
+            let temp_file = cleanup::TempPath::new(&format!("apt-ng-release-info-{}.tmp",
+                url.replace("/", "_").replace(":", "_").replace(".", "_")));
+            
+            let content = match downloader.download_file(&url, &temp_file).await {
+                Ok(()) => std::fs::read_to_string(&temp_file).ok(),
+                Err(_) => None,
+            };
+            let _ = std::fs::remove_file(&temp_file);
+            
+            if let Some(content) = content {
+                let release = apt_parser::parse_release_file(&content);
+                if release.suite.is_some() || release.codename.is_some() {
+                    return Some((suite.clone(), release));
+                }
+            }
+        }
+    }
+    None
+}
+
+fn cmd_file_search(index: &index::Index, pattern: &str) -> anyhow::Result<()> {
+    output::Output::heading(&format!("🔍 Searching files for '{}'", pattern));
+    
+    let results = index.search_contents(pattern)?;
+    if results.is_empty() {
+        output::Output::warning(&format!("No files found matching '{}'", pattern));
+        output::Output::info("File search needs Contents indexes: run 'apt-ng update --download-contents'.");
+        return Ok(());
+    }
+    
+    output::Output::info(&format!("Found {} file(s):", results.len()));
+    if output::Output::shows(output::Verbosity::Normal) {
+        let mut table = output::Output::table();
+        table.set_header(vec!["Package", "File"]);
+        for (package, path) in &results {
+            let package_cell = if output::Output::colors_enabled() {
+                comfy_table::Cell::new(package).fg(comfy_table::Color::Cyan)
+            } else {
+                comfy_table::Cell::new(package)
+            };
+            table.add_row(vec![package_cell, comfy_table::Cell::new(path)]);
+        }
+        println!("{}", table);
+    }
+    
     Ok(())
 }
 
@@ -533,8 +1037,14 @@ async fn cmd_install(
     index: &index::Index,
     config: &config::Config,
     packages: &[String],
-    jobs: usize,
+    to_remove: &[String],
+    resolver_input: Option<&ResolverInput>,
+    write_lock: Option<&Path>,
+    jobs: config::Jobs,
     dry_run: bool,
+    download_only: bool,
+    install_suggests: bool,
+    no_sandbox: bool,
     verbose: bool,
 ) -> anyhow::Result<()> {
     if packages.is_empty() {
@@ -542,94 +1052,180 @@ async fn cmd_install(
         return Ok(());
     }
     
-    output::Output::heading("📦 Installing Packages");
+    if download_only {
+        output::Output::heading("⬇ Staging Packages (download only)");
+    } else {
+        output::Output::heading("📦 Installing Packages");
+    }
     
     if verbose {
         output::Output::info(&format!("Resolving dependencies for: {:?}", packages));
     }
     
-    // 1. Populate solver with all available packages
-    output::Output::section("🔍 Loading package index...");
-    let all_manifests = index.get_all_packages()?;
-    let mut solver = solver::DependencySolver::new();
+    let (requested, local_packages) = install_requests(index, packages)?;
     
-    for manifest in &all_manifests {
-        match solver::DependencySolver::manifest_to_package_info(manifest) {
-            Ok(pkg_info) => {
-                solver.add_package(pkg_info);
-            }
-            Err(e) => {
-                if verbose {
-                    output::Output::warning(&format!("Failed to parse dependencies for {}: {}", manifest.name, e));
-                }
-                // Continue with other packages even if one fails
-            }
-        }
+    let mut loaded = None;
+    if resolver_input.is_none() {
+        let mut input = load_resolver_input(index, config, verbose)?;
+        input.add_local_packages(&local_packages)?;
+        // Installierte Pakete nicht erneut installieren, ältere Versionen als Upgrade führen
+        input.solver.set_installed_versions(installed_versions(index)?);
+        input.solver.set_install_suggests(install_suggests);
+        loaded = Some(input);
     }
+    let resolver_input = resolver_input.or(loaded.as_ref());
     
-    if verbose {
-        output::Output::info(&format!("Loaded {} packages into solver", all_manifests.len()));
+    let resolved = resolve_packages(index, config, &requested, resolver_input, jobs.solver, verbose)?;
+    
+    if !resolved.to_remove.is_empty() && !download_only {
+        let reason = format!("installation would require removing {}", resolved.to_remove.join(", "));
+        output::Output::error(&format!("{}; remove them first or use `apt-ng full-upgrade`", reason));
+        return Err(apt_ng::AptNgError::Conflict(vec![reason]).into());
     }
     
-    // 2. Create PackageSpec for requested packages
-    let requested_specs: Vec<solver::PackageSpec> = packages.iter()
-        .map(|name| solver::PackageSpec {
-            name: name.clone(),
-            version: None,
-            arch: None,
-        })
-        .collect();
+    // Das Lockfile hält die ganze Lösung fest, auch bei --dry-run und bereits installierten Paketen
+    if let Some(path) = write_lock {
+        lockfile::Lockfile::from_packages(index, &resolved.closure)?.write(path)?;
+        output::Output::info(&format!("Wrote lockfile {}", path.display()));
+    }
     
-    // 3. Resolve dependencies using solver (with optional parallel solving)
-    output::Output::section("🧩 Resolving dependencies...");
-    // Use parallel solver for better performance with large dependency graphs
-    // Can be disabled by setting use_parallel to false
-    let use_parallel_solver = jobs > 1; // Use parallel solver if multiple jobs are configured
-    let solution = match solver.solve_parallel(&requested_specs, use_parallel_solver) {
-        Ok(sol) => sol,
-        Err(e) => {
-            output::Output::error(&format!("Dependency resolution failed: {}", e));
-            return Err(e);
-        }
-    };
+    if resolved.to_install.is_empty() && resolved.to_upgrade.is_empty() && to_remove.is_empty() {
+        output::Output::success("All requested packages are already installed.");
+        return Ok(());
+    }
     
-    // 4. Convert PackageInfo back to PackageManifest for installation
-    let mut packages_to_install = Vec::new();
-    for pkg_info in &solution.to_install {
-        // Find the corresponding manifest
-        if let Some(manifest) = all_manifests.iter()
-            .find(|m| m.name == pkg_info.name && m.version == pkg_info.version && m.arch == pkg_info.arch) {
-            packages_to_install.push(manifest.clone());
-        } else {
-            // Fallback: try to find by name only
-            if let Some(manifest) = all_manifests.iter()
-                .find(|m| m.name == pkg_info.name) {
-                packages_to_install.push(manifest.clone());
-            } else {
-                return Err(anyhow::anyhow!("Package {} {} not found in index", pkg_info.name, pkg_info.version));
-            }
+    // Show what will be installed
+    if !resolved.to_install.is_empty() {
+        output::Output::section("📋 Packages to install:");
+        for pkg in &resolved.to_install {
+            output::Output::package_info(&pkg.name, &pkg.version, &pkg.arch);
+        }
+    }
+    if !resolved.to_upgrade.is_empty() {
+        output::Output::section("📋 Packages to upgrade:");
+        for pkg in &resolved.to_upgrade {
+            output::Output::package_info(&pkg.name, &pkg.version, &pkg.arch);
+        }
+    }
+    let packages_to_install = resolved.install_order;
+    for pkg in &packages_to_install {
+        if let Err(e) = pkg.validate_identity() {
+            output::Output::error(&format!("Rejecting package {:?}: {}", pkg.name, e));
+            return Err(e.context("The package index contains unsafe metadata"));
         }
     }
     
-    // Show what will be installed
+    install_package_set(index, config, &packages_to_install, to_remove, &local_packages, jobs, dry_run, download_only, no_sandbox, verbose).await
+}
+
+/// Installiert genau die Pakete eines Lockfiles
+///
+/// Es findet keine Auflösung statt; jedes Paket muss in der festgehaltenen Version,
+/// Architektur und mit derselben Prüfsumme im Index stehen.
+async fn cmd_install_lock(
+    index: &index::Index,
+    config: &config::Config,
+    file: &Path,
+    jobs: config::Jobs,
+    dry_run: bool,
+    no_sandbox: bool,
+    verbose: bool,
+) -> anyhow::Result<()> {
+    output::Output::heading("📦 Installing Locked Packages");
+    
+    let lockfile = lockfile::Lockfile::read(file)?;
+    let installed = installed_versions(index)?;
+    let packages_to_install: Vec<apt_ng::PackageManifest> = lockfile.resolve(index)?
+        .into_iter()
+        .filter(|pkg| installed.get(&pkg.name) != Some(&pkg.version))
+        .collect();
+    
+    if packages_to_install.is_empty() {
+        output::Output::success("All locked packages are already installed.");
+        return Ok(());
+    }
+    
     output::Output::section("📋 Packages to install:");
     for pkg in &packages_to_install {
         output::Output::package_info(&pkg.name, &pkg.version, &pkg.arch);
     }
+    for pkg in &packages_to_install {
+        pkg.validate_identity()
+            .with_context(|| format!("Rejecting package {:?}: the package index contains unsafe metadata", pkg.name))?;
+    }
+    
+    install_package_set(index, config, &packages_to_install, &[], &[], jobs, dry_run, false, no_sandbox, verbose).await
+}
+
+/// Lädt, prüft und installiert eine fertig aufgelöste Paketmenge in der gegebenen Reihenfolge
+///
+/// `to_remove` wird nach der Installation in derselben Transaktionsgruppe entfernt, sodass
+/// ein Fehler auch die Entfernungen zurücknimmt. `local_packages` sind lokale .deb/.apx-Dateien, die vor dem Download in den Cache kommen.
+async fn install_package_set(
+    index: &index::Index,
+    config: &config::Config,
+    packages_to_install: &[apt_ng::PackageManifest],
+    to_remove: &[String],
+    local_packages: &[apt_ng::PackageManifest],
+    jobs: config::Jobs,
+    dry_run: bool,
+    download_only: bool,
+    no_sandbox: bool,
+    verbose: bool,
+) -> anyhow::Result<()> {
+    // Plan-Übersicht wie bei apt: Download-Größe (ohne bereits gecachte Pakete) und Speicherbedarf
+    let cache = cache::Cache::new(config.cache_path())?;
+    let is_local = |pkg: &apt_ng::PackageManifest| {
+        local_packages.iter().any(|local| local.name == pkg.name && local.version == pkg.version && local.arch == pkg.arch)
+    };
+    let needs_download = |pkg: &&apt_ng::PackageManifest| {
+        !is_local(pkg)
+            && !cache.package_path_with_ext(&pkg.name, &pkg.version, &pkg.arch, "deb").exists()
+            && !cache.package_path_with_ext(&pkg.name, &pkg.version, &pkg.arch, "apx").exists()
+    };
+    let download_size: u64 = packages_to_install.iter()
+        .filter(needs_download)
+        .map(|pkg| pkg.size)
+        .sum();
+    let disk_delta = apt_ng::package::disk_usage_delta(packages_to_install, to_remove, &index.list_installed_packages_with_manifests()?);
+    output::Output::info(&format!("Need to get {}. {}", format_size(download_size), disk_usage_message(disk_delta)));
     
     if dry_run {
         output::Output::info("[DRY RUN] Would install:");
-        for pkg in &packages_to_install {
+        for pkg in packages_to_install {
             output::Output::list_item(&format!("{} ({})", pkg.name, pkg.version));
         }
+        if !to_remove.is_empty() {
+            output::Output::info(&format!("[DRY RUN] Would remove: {:?}", to_remove));
+        }
         return Ok(());
     }
     
+    // Offline darf nichts mehr geladen werden: fehlende Pakete vor jeder Änderung melden
+    if config.offline() {
+        let missing: Vec<String> = packages_to_install.iter()
+            .filter(needs_download)
+            .map(|pkg| format!("{} ({})", pkg.name, pkg.version))
+            .collect();
+        if !missing.is_empty() {
+            anyhow::bail!("Not cached (offline mode): {}", missing.join(", "));
+        }
+    }
+    
+    // Lokale Dateien landen im Cache und durchlaufen ab hier dieselben Prüfungen wie Downloads
+    stage_local_packages(index, &cache, local_packages)?;
+    
     // 3. Prefetch all packages in parallel before installation
     output::Output::section("⬇ Prefetching packages...");
     
-    let downloader = downloader::Downloader::new(jobs)?;
-    let cache = cache::Cache::new(config.cache_path())?;
+    // Gesamtfortschritt mit gemeinsamer Rate und ETA über alle parallelen Downloads
+    let batch_progress = std::sync::Arc::new(downloader::BatchProgress::new(download_size));
+    let downloader = create_downloader(config, jobs.download)?
+        .with_batch_progress(batch_progress.clone());
+    
+    // Upgrades werden nach Möglichkeit als Delta zur gecachten installierten Version geladen
+    let installed = installed_versions(index)?;
+    let delta_savings = std::sync::Mutex::new(apt_ng::delta::DeltaSavings::default());
     
     // Collect all download tasks
     use futures::stream::{self, StreamExt};
@@ -638,6 +1234,8 @@ async fn cmd_install(
         let downloader = &downloader;
         let cache = &cache;
         let index = index;
+        let installed = &installed;
+        let delta_savings = &delta_savings;
         let verbose = verbose;
         
         async move {
@@ -646,17 +1244,10 @@ async fn cmd_install(
             let cache_path_apx = cache.package_path_with_ext(&pkg.name, &pkg.version, &pkg.arch, "apx");
             
             let package_in_cache = if cache_path_deb.exists() {
-                // Try to validate the .deb file by checking if dpkg-deb can read it
-                let test_output = std::process::Command::new("dpkg-deb")
-                    .arg("-I")
-                    .arg(&cache_path_deb)
-                    .output();
-                
-                let dpkg_valid = if let Ok(output) = test_output {
-                    output.status.success()
-                } else {
-                    false
-                };
+                // Die .deb-Datei muss sich als Archiv mit Control-Datei lesen lassen
+                let deb_valid = apt_ng::deb::DebArchive::open(&cache_path_deb)
+                    .and_then(|deb| deb.control())
+                    .is_ok();
                 
                 // Also check checksum if available (streaming für große Dateien)
                 let checksum_valid = if !pkg.checksum.is_empty() {
@@ -694,11 +1285,11 @@ async fn cmd_install(
                     true // No checksum to validate
                 };
                 
-                // If file is corrupted (dpkg can't read it or checksum mismatch), delete it
-                if !dpkg_valid || !checksum_valid {
+                // If file is corrupted (unreadable archive or checksum mismatch), delete it
+                if !deb_valid || !checksum_valid {
                     if verbose {
-                        if !dpkg_valid {
-                            output::Output::warning(&format!("Package {} in cache is corrupted (dpkg-deb failed), deleting...", pkg.name));
+                        if !deb_valid {
+                            output::Output::warning(&format!("Package {} in cache is corrupted (unreadable .deb archive), deleting...", pkg.name));
                         } else {
                             output::Output::warning(&format!("Package {} in cache has checksum mismatch, deleting...", pkg.name));
                         }
@@ -722,30 +1313,45 @@ async fn cmd_install(
             }
             
             // Download package
-            let repo_id = pkg.repo_id.ok_or_else(|| {
-                anyhow::anyhow!("Package {} has no repository ID", pkg.name)
-            })?;
-            
-            let repo_url = index.get_repo_url(repo_id)?
-                .ok_or_else(|| anyhow::anyhow!("Repository {} not found", repo_id))?;
-            
-            let filename = pkg.filename.as_ref()
-                .ok_or_else(|| anyhow::anyhow!("Package {} has no filename", pkg.name))?;
-            
-            // Select best mirror URL based on performance metrics
-            let base_download_url = format!("{}/{}", repo_url.trim_end_matches('/'), filename.trim_start_matches('/'));
-            let download_url = index.select_best_mirror_url(&base_download_url)?;
+            let (download_urls, filename) = package_download_urls(index, &pkg)?;
             
             output::Output::download_info(&pkg.name, &format_size(pkg.size));
             
-            let temp_file = std::env::temp_dir().join(format!("apt-ng-download-{}-{}.tmp", 
+            let temp_file = cleanup::TempPath::new(&format!("apt-ng-download-{}-{}.tmp", 
                 pkg.name, pkg.version));
             
-            // Download with performance tracking
-            let (rtt_ms, throughput) = downloader.download_file_with_metrics(&download_url, &temp_file).await?;
+            if let Some(from_version) = installed.get(&pkg.name).filter(|version| **version != pkg.version) {
+                match fetch_delta(downloader, index, cache, &pkg, from_version, &temp_file).await {
+                    Ok(Some(metadata)) => {
+                        if verbose {
+                            output::Output::info(&format!("{}: delta from {} saves {:.1}%",
+                                pkg.name, from_version, metadata.savings_percentage()));
+                        }
+                        cache.add_package_from_file(&pkg.name, &pkg.version, &pkg.arch, "deb", &temp_file)?;
+                        delta_savings.lock().unwrap_or_else(|e| e.into_inner()).record(&metadata);
+                        return Ok(());
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        if verbose {
+                            output::Output::warning(&format!("Delta for {} unusable, downloading the full package: {:#}", pkg.name, e));
+                        }
+                    }
+                }
+            }
+            
+            // Download with performance tracking; the checksum from the Packages index is
+            // validated right away so a corrupt download never reaches the cache.
+            // Corrupt downloads are retried from the next mirror.
+            let expected_checksum = Some(pkg.checksum.as_str()).filter(|c| !c.is_empty());
+            let metrics = downloader.download_with_failover(&download_urls, &temp_file, expected_checksum).await
+                .with_context(|| format!("Download of {} failed", pkg.name))?;
+            if verbose && metrics.resolved_url != metrics.url {
+                output::Output::info(&format!("{} served by {}", pkg.name, metrics.resolved_url));
+            }
             
             // Update mirror performance metrics
-            if let Err(e) = index.update_mirror_performance(&download_url, rtt_ms, throughput) {
+            if let Err(e) = index.update_mirror_performance(&metrics.url, &metrics.resolved_url, metrics.rtt_ms, metrics.throughput) {
                 if verbose {
                     output::Output::warning(&format!("Failed to update mirror performance: {}", e));
                 }
@@ -754,7 +1360,6 @@ async fn cmd_install(
             // Move to cache with deduplication
             let ext = filename.split('.').last().unwrap_or("deb");
             cache.add_package_from_file(&pkg.name, &pkg.version, &pkg.arch, ext, &temp_file)?;
-            std::fs::remove_file(&temp_file)?;
             
             Ok(())
         }
@@ -762,16 +1367,27 @@ async fn cmd_install(
     
     // Execute all downloads in parallel
     let results: Vec<_> = stream::iter(download_tasks)
-        .buffer_unordered(jobs)
+        .buffer_unordered(jobs.download)
         .collect()
         .await;
     
+    batch_progress.finish();
+    
     // Check for errors
     for result in results {
         result?;
     }
     
-    // 4. Download phase complete, now verify signatures
+    let delta_savings = delta_savings.into_inner().unwrap_or_else(|e| e.into_inner());
+    if !delta_savings.is_empty() {
+        output::Output::info(&format!("Saved {} via delta updates ({} package(s))",
+            format_size(delta_savings.bytes_saved), delta_savings.packages));
+        if let Err(e) = index.record_delta_savings(&delta_savings) {
+            output::Output::warning(&format!("Failed to record delta savings: {}", e));
+        }
+    }
+    
+    // 4. Download phase complete, now verify signatures and checksums
     output::Output::section("🔐 Verifying package signatures...");
     let verifier = verifier::PackageVerifier::new(config.trusted_keys_dir())?;
     
@@ -780,254 +1396,911 @@ async fn cmd_install(
         output::Output::info(&format!("Add trusted keys to: {}", config.trusted_keys_dir().display()));
     } else {
         output::Output::info(&format!("Found {} trusted key(s)", verifier.trusted_key_count()));
-        
-        for pkg in &packages_to_install {
+    }
+    
+    // Signaturen (.apx) und Checksummen (.deb) aller Pakete parallel prüfen, Fehler gesammelt melden
+    let checks: Vec<verifier::PackageCheck> = packages_to_install.iter()
+        .filter_map(|pkg| {
             // Versuche zuerst .apx, dann .deb
             let cache_path_apx = cache.package_path_with_ext(&pkg.name, &pkg.version, &pkg.arch, "apx");
             let cache_path_deb = cache.package_path_with_ext(&pkg.name, &pkg.version, &pkg.arch, "deb");
-            
-            let (cache_path, is_apx) = if cache_path_apx.exists() {
-                (cache_path_apx, true)
+            let path = if cache_path_apx.exists() {
+                cache_path_apx
             } else if cache_path_deb.exists() {
-                (cache_path_deb, false)
+                cache_path_deb
             } else {
-                continue; // Skip if not downloaded yet
+                return None; // Skip if not downloaded yet
             };
-            
-            if is_apx {
-                // Für .apx-Pakete: Verifiziere Signatur
-                use crate::package::ApxPackage;
-                if let Ok(apx_pkg) = ApxPackage::open(&cache_path) {
-                    match apx_pkg.verify_signature(&cache_path, &verifier) {
-                        Ok(_) => {
-                            if verbose {
-                                output::Output::info(&format!("✓ Verified signature for {}", pkg.name));
-                            }
-                        }
-                        Err(e) => {
-                            return Err(anyhow::anyhow!(
-                                "Signature verification failed for {}: {}",
-                                pkg.name,
-                                e
-                            ));
-                        }
-                    }
-                }
-            } else {
-                // Für .deb-Pakete: Verifiziere Checksumme (streaming für große Dateien)
-                if !pkg.checksum.is_empty() {
-                    use sha2::{Sha256, Digest};
-                    use hex;
-                    use std::io::Read;
-                    use std::fs::File;
-                    
-                    let mut file = File::open(&cache_path)?;
-                    let mut hasher = Sha256::new();
-                    let mut buffer = vec![0u8; 64 * 1024]; // 64KB Buffer
-                    
-                    loop {
-                        let bytes_read = file.read(&mut buffer)?;
-                        if bytes_read == 0 {
-                            break;
-                        }
-                        hasher.update(&buffer[..bytes_read]);
-                    }
-                    
-                    let calculated_checksum = hex::encode(hasher.finalize());
-                    
-                    if calculated_checksum != pkg.checksum {
-                        // File is corrupted, delete it
-                        output::Output::warning(&format!(
-                            "Checksum mismatch for {}: expected {}, got {}. Deleting corrupted file...",
-                            pkg.name,
-                            pkg.checksum,
-                            calculated_checksum
-                        ));
-                        let _ = std::fs::remove_file(&cache_path);
-                        return Err(anyhow::anyhow!(
-                            "Package file corrupted (checksum mismatch). Please run the command again to re-download."
-                        ));
-                    }
-                    
-                    if verbose {
-                        output::Output::info(&format!("✓ Verified checksum for {}", pkg.name));
-                    }
-                }
-            }
+            Some(verifier::PackageCheck { name: pkg.name.clone(), path, checksum: pkg.checksum.clone() })
+        })
+        .collect();
+    
+    let pb = output::Output::count_progress_bar(checks.len() as u64);
+    let failures = verifier.verify_packages(&checks, jobs.download, |check| {
+        pb.set_message(check.name.clone());
+        pb.inc(1);
+    })?;
+    if !failures.is_empty() {
+        pb.abandon();
+        for failure in &failures {
+            output::Output::error(&format!("{}: {}", failure.name, failure.reason));
+        }
+        anyhow::bail!(
+            "Verification failed for {} package(s): {}",
+            failures.len(),
+            failures.iter().map(|failure| failure.name.as_str()).collect::<Vec<_>>().join(", ")
+        );
+    }
+    pb.finish_with_message("verified");
+    if verbose {
+        output::Output::info(&format!("✓ Verified {} package(s)", checks.len()));
+    }
+    
+    // 5. Bei --download-only bleiben die verifizierten Pakete im Cache; ein späteres
+    // `install` findet sie dort über die Cache-Prüfung und lädt nichts erneut
+    if download_only {
+        output::Output::section("📁 Staged packages:");
+        for pkg in packages_to_install {
+            let cache_path_apx = cache.package_path_with_ext(&pkg.name, &pkg.version, &pkg.arch, "apx");
+            let cache_path_deb = cache.package_path_with_ext(&pkg.name, &pkg.version, &pkg.arch, "deb");
+            let cache_path = if cache_path_apx.exists() { cache_path_apx } else { cache_path_deb };
+            output::Output::list_item(&cache_path.display().to_string());
         }
+        output::Output::summary("Downloaded (not installed)", packages_to_install.len());
+        return Ok(());
     }
     
     // 6. Installiere Pakete
     output::Output::section("🔧 Installing packages...");
     
-    let installer = installer::Installer::new(jobs, Path::new("/"));
+    let mut installer = create_installer(config, jobs.download, no_sandbox, verbose);
+    installer.set_journal_path(config.transaction_journal_path());
     
-    for pkg in &packages_to_install {
-        // Versuche zuerst .apx, dann .deb
+    // Paketdateien vorab bestimmen: .apx bevorzugt, sonst .deb
+    let mut package_files = Vec::with_capacity(packages_to_install.len());
+    for pkg in packages_to_install {
         let cache_path_apx = cache.package_path_with_ext(&pkg.name, &pkg.version, &pkg.arch, "apx");
         let cache_path_deb = cache.package_path_with_ext(&pkg.name, &pkg.version, &pkg.arch, "deb");
         
-        let (cache_path, is_apx) = if cache_path_apx.exists() {
-            (cache_path_apx, true)
+        if cache_path_apx.exists() {
+            package_files.push((cache_path_apx, true));
         } else if cache_path_deb.exists() {
-            (cache_path_deb, false)
+            package_files.push((cache_path_deb, false));
         } else {
             return Err(anyhow::anyhow!("Package file not found for {} (tried .apx and .deb)", pkg.name));
-        };
-        
-        output::Output::install_info(&pkg.name, &pkg.version);
-        
-        let transaction = if is_apx {
-            // Installiere .apx-Paket mit Signatur-Verifikation
-            installer.install_package(&cache_path, Some(&verifier), verbose).await?
-        } else {
-            // Installiere .deb-Paket
-            installer.install_deb_package(&cache_path, Some(&pkg.checksum), verbose).await?
-        };
-        
-        // Markiere als installiert (transaction wird automatisch bei Fehler zurückgerollt)
-        if let Err(e) = index.mark_installed(&pkg.name, &pkg.version) {
-            // Rollback installation if marking as installed fails
-            transaction.rollback()?;
-            return Err(e);
         }
     }
     
-    output::Output::summary("Successfully installed", packages_to_install.len());
-    
-    Ok(())
-}
-
-async fn cmd_remove(
-    index: &index::Index,
-    packages: &[String],
-    dry_run: bool,
-    verbose: bool,
-) -> anyhow::Result<()> {
-    if dry_run {
-        output::Output::info(&format!("[DRY RUN] Would remove: {:?}", packages));
-        return Ok(());
+    // .deb-Pakete werden parallel entpackt; Hooks und Kopieren laufen in Installationsreihenfolge
+    let debs_to_prepare: Vec<_> = packages_to_install.iter()
+        .zip(&package_files)
+        .filter(|(_, (_, is_apx))| !is_apx)
+        .map(|(pkg, (path, _))| (path.clone(), Some(pkg.checksum.clone()).filter(|c| !c.is_empty())))
+        .collect();
+    let mut prepared_debs = std::pin::pin!(installer.prepare_deb_packages(debs_to_prepare, verbose));
+    let install_started = std::time::Instant::now();
+    let mut sequential_time = std::time::Duration::ZERO;
+    
+    // Vorher/Nachher für den Verlauf, auch wenn die Installation mittendrin abbricht
+    let before = installed_versions(index)?;
+    // Alle Pakete bleiben unbestätigt, bis das letzte installiert ist; scheitert eines,
+    // werden auch die bereits installierten zurückgerollt
+    let mut group = installer::TransactionGroup::new();
+    let result = async {
+        for (pkg, (cache_path, is_apx)) in packages_to_install.iter().zip(package_files) {
+            output::Output::install_info(&pkg.name, &pkg.version);
+            
+            let transaction = if is_apx {
+                // Installiere .apx-Paket mit Signatur-Verifikation
+                let started = std::time::Instant::now();
+                let transaction = installer.install_package(&cache_path, Some(&verifier), verbose).await?;
+                sequential_time += started.elapsed();
+                transaction
+            } else {
+                // Installiere das bereits entpackte .deb-Paket
+                use futures::StreamExt;
+                let prepared = prepared_debs.next().await
+                    .ok_or_else(|| anyhow::anyhow!("Package {} was not extracted", pkg.name))??;
+                sequential_time += prepared.extract_time;
+                let started = std::time::Instant::now();
+                let transaction = installer.install_prepared_deb(prepared, verbose).await?;
+                sequential_time += started.elapsed();
+                transaction
+            };
+            
+            // Markiere als installiert samt Dateiliste
+            group.add(&installer, index, &pkg.name, &pkg.version, transaction)?;
+        }
+        
+        // Entfernungen erst, wenn alle neuen Pakete installiert sind
+        for name in to_remove {
+            group.remove(index, name)?;
+            if verbose {
+                output::Output::success(&format!("Removed: {}", name));
+            }
+        }
+        Ok::<(), anyhow::Error>(())
+    }.await;
+    match &result {
+        Ok(()) => group.commit()?,
+        Err(_) => {
+            if !group.is_empty() {
+                output::Output::warning(&format!("Rolling back {} already applied change(s)...", group.len()));
+            }
+            if let Err(e) = group.rollback(index) {
+                output::Output::error(&format!("Rollback incomplete: {}", e));
+            }
+        }
     }
+    record_history(index, &before, result.as_ref().err());
+    result?;
     
     if verbose {
-        output::Output::info(&format!("Removing packages: {:?}", packages));
+        let elapsed = install_started.elapsed();
+        output::Output::info(&format!(
+            "Installed {} package(s) in {:.1}s with {} worker(s) (sequential: {:.1}s, {:.1}x speedup)",
+            packages_to_install.len(),
+            elapsed.as_secs_f64(),
+            jobs.download,
+            sequential_time.as_secs_f64(),
+            sequential_time.as_secs_f64() / elapsed.as_secs_f64().max(0.001)
+        ));
     }
     
-    for pkg_name in packages {
-        index.mark_removed(pkg_name)?;
-        if verbose {
-            output::Output::success(&format!("Removed: {}", pkg_name));
+    output::Output::summary("Successfully installed", packages_to_install.len());
+    if !to_remove.is_empty() {
+        output::Output::summary("Removed", to_remove.len());
+    }
+    
+    // Halte die konfigurierte maximale Cache-Größe ein
+    if let Some(max_size) = config.cache_max_size() {
+        if let Err(e) = enforce_cache_limit(index, &cache, max_size, verbose) {
+            output::Output::warning(&format!("Failed to enforce cache size limit: {}", e));
         }
     }
     
     Ok(())
 }
 
-async fn cmd_upgrade(
+/// Lädt Pakete (optional samt Abhängigkeiten) ins aktuelle Verzeichnis, ohne sie zu installieren
+async fn cmd_download(
     index: &index::Index,
     config: &config::Config,
-    jobs: usize,
-    dry_run: bool,
+    packages: &[String],
+    resolve_deps: bool,
+    jobs: config::Jobs,
     verbose: bool,
 ) -> anyhow::Result<()> {
-    output::Output::heading("🔄 Upgrading Packages");
-    
-    let installed_packages = index.list_installed_packages_with_manifests()?;
+    output::Output::heading("⬇ Downloading Packages");
     
-    if installed_packages.is_empty() {
-        output::Output::info("No packages installed.");
-        return Ok(());
-    }
+    // Ohne --resolve-deps wird nur das jeweilige Paket selbst gewählt, der Solver läuft nicht
+    let packages_to_download = if resolve_deps {
+        resolve_packages(index, config, &name_specs(packages), None, jobs.solver, verbose)?.install_order
+    } else {
+        select_requested_packages(index, config, &name_specs(packages), verbose)?
+    };
     
-    if verbose {
-        output::Output::info(&format!("Checking {} installed packages for upgrades...", installed_packages.len()));
-    }
+    let download_size: u64 = packages_to_download.iter().map(|pkg| pkg.size).sum();
+    output::Output::info(&format!("Need to get {} in {} package(s).", format_size(download_size), packages_to_download.len()));
     
-    // 1. Finde verfügbare Upgrades
-    let mut packages_to_upgrade = Vec::new();
+    let target_dir = std::env::current_dir()?;
+    let downloader = create_downloader(config, jobs.download)?;
     
-    for installed_pkg in &installed_packages {
-        // Get latest available version (exact match only for upgrades)
-        let available_packages = index.search_exact(&installed_pkg.name)?;
+    use futures::stream::{self, StreamExt};
+    let download_tasks: Vec<_> = packages_to_download.iter().map(|pkg| {
+        let downloader = &downloader;
+        let target_dir = &target_dir;
         
-        if let Some(latest_pkg) = available_packages.first() {
-            // Compare versions using solver's version comparison
-            use crate::solver::DependencySolver;
-            let comparison = DependencySolver::compare_versions(&latest_pkg.version, &installed_pkg.version);
+        async move {
+            let (download_urls, filename) = package_download_urls(index, pkg)?;
+            let file_name = Path::new(&filename).file_name()
+                .ok_or_else(|| anyhow::anyhow!("Package {} has an invalid filename: {}", pkg.name, filename))?;
+            let dest = target_dir.join(file_name);
             
-            match comparison {
-                std::cmp::Ordering::Greater => {
-                    // Newer version available
-                    packages_to_upgrade.push(latest_pkg.clone());
-                    if verbose {
-                        output::Output::info(&format!(
-                            "{}: {} -> {}",
-                            installed_pkg.name,
-                            installed_pkg.version,
-                            latest_pkg.version
-                        ));
-                    }
-                }
-                _ => {
-                    // Already up to date or same version
-                    if verbose {
-                        output::Output::info(&format!("{}: {} (up to date)", installed_pkg.name, installed_pkg.version));
-                    }
+            output::Output::download_info(&pkg.name, &format_size(pkg.size));
+            
+            let expected_checksum = Some(pkg.checksum.as_str()).filter(|c| !c.is_empty());
+            let metrics = downloader.download_with_failover(&download_urls, &dest, expected_checksum).await
+                .with_context(|| format!("Download of {} failed", pkg.name))?;
+            if verbose && metrics.resolved_url != metrics.url {
+                output::Output::info(&format!("{} served by {}", pkg.name, metrics.resolved_url));
+            }
+            
+            if let Err(e) = index.update_mirror_performance(&metrics.url, &metrics.resolved_url, metrics.rtt_ms, metrics.throughput) {
+                if verbose {
+                    output::Output::warning(&format!("Failed to update mirror performance: {}", e));
                 }
             }
+            
+            Ok::<_, anyhow::Error>(dest)
         }
-    }
+    }).collect();
     
-    if packages_to_upgrade.is_empty() {
-        output::Output::success("All packages are up to date.");
-        return Ok(());
-    }
+    let results: Vec<_> = stream::iter(download_tasks)
+        .buffer_unordered(jobs.download)
+        .collect()
+        .await;
     
-    output::Output::section(&format!("📦 Found {} package(s) to upgrade:", packages_to_upgrade.len()));
-    for pkg in &packages_to_upgrade {
-        output::Output::list_item(&format!("{} ({})", pkg.name, pkg.version));
+    let mut saved = Vec::new();
+    for result in results {
+        saved.push(result?);
     }
+    saved.sort();
     
-    if dry_run {
-        output::Output::info("[DRY RUN] Would upgrade the above packages");
-        return Ok(());
+    output::Output::section("📁 Saved files:");
+    for path in &saved {
+        output::Output::list_item(&path.display().to_string());
     }
+    output::Output::summary("Successfully downloaded", saved.len());
     
-    // 2. Resolve dependencies for upgrades
-    let all_available_packages = index.get_all_packages()?;
-    let mut solver = solver::DependencySolver::new();
-    
-    // Add available packages to solver
-    for manifest in &all_available_packages {
-        match solver::DependencySolver::manifest_to_package_info(manifest) {
-            Ok(pkg_info) => {
-                solver.add_package(pkg_info);
-            }
-            Err(e) => {
-                if verbose {
-                    output::Output::warning(&format!("Failed to parse dependencies for {}: {}", manifest.name, e));
-                }
-            }
+    Ok(())
+}
+
+/// Verfügbare Pakete aus dem Index samt daraus aufgebautem Solver
+///
+/// `cmd_upgrade` reicht seine Instanz an `cmd_install` weiter, damit die Abhängigkeiten
+/// aller Pakete nur einmal geparst werden.
+struct ResolverInput {
+    manifests: Vec<apt_ng::PackageManifest>,
+    solver: solver::DependencySolver,
+}
+
+impl ResolverInput {
+    /// Macht lokale Paketdateien (`apt-ng install ./foo.deb`) für den Solver sichtbar
+    fn add_local_packages(&mut self, local_packages: &[apt_ng::PackageManifest]) -> anyhow::Result<()> {
+        if let Some((name, e)) = self.solver.add_manifests(local_packages).into_iter().next() {
+            return Err(anyhow::Error::new(e).context(format!("Failed to parse dependencies of {}", name)));
         }
+        self.manifests.extend(local_packages.iter().cloned());
+        Ok(())
     }
-    
-    // Add installed packages to solver so dependencies already satisfied by installed packages can be found
-    for manifest in &installed_packages {
-        match solver::DependencySolver::manifest_to_package_info(manifest) {
-            Ok(pkg_info) => {
-                solver.add_package(pkg_info);
-            }
-            Err(e) => {
-                if verbose {
-                    output::Output::warning(&format!("Failed to parse dependencies for installed package {}: {}", manifest.name, e));
+}
+
+/// Aufgelöste Pakete als Manifeste, wie `solver::Solution` nach Aktion getrennt
+struct ResolvedPackages {
+    to_install: Vec<apt_ng::PackageManifest>,
+    to_upgrade: Vec<apt_ng::PackageManifest>,
+    to_remove: Vec<String>,
+    /// `to_install` und `to_upgrade` zusammen in Installationsreihenfolge
+    install_order: Vec<apt_ng::PackageManifest>,
+    /// Vollständige Hülle in Installationsreihenfolge (für Lockfiles)
+    closure: Vec<apt_ng::PackageManifest>,
+}
+
+/// Installierte Pakete mit ihrer Version, für `DependencySolver::set_installed_versions`
+fn installed_versions(index: &index::Index) -> anyhow::Result<HashMap<String, String>> {
+    Ok(index.list_installed_packages_with_manifests()?
+        .into_iter()
+        .map(|pkg| (pkg.name, pkg.version))
+        .collect())
+}
+
+/// Anforderungen für den Solver aus Paketnamen (beliebige Version)
+fn name_specs(packages: &[String]) -> Vec<solver::PackageSpec> {
+    packages.iter()
+        .map(|name| solver::PackageSpec {
+            name: name.clone(),
+            version: None,
+            arch: None,
+        })
+        .collect()
+}
+
+/// Wandelt Install-Argumente in Anforderungen für den Solver um
+///
+/// Paketnamen werden in beliebiger Version angefordert, lokale .deb/.apx-Dateien mit
+/// genau ihrer Version; deren Manifeste werden zusätzlich zurückgegeben. Ist dieselbe
+/// Version auch im Index bekannt, muss die Datei deren Prüfsumme haben.
+fn install_requests(
+    index: &index::Index,
+    packages: &[String],
+) -> anyhow::Result<(Vec<solver::PackageSpec>, Vec<apt_ng::PackageManifest>)> {
+    let mut requested = Vec::new();
+    let mut local_packages = Vec::new();
+    
+    for arg in packages {
+        if !apt_ng::package::is_local_package_path(arg) {
+            requested.extend(name_specs(std::slice::from_ref(arg)));
+            continue;
+        }
+        
+        let path = Path::new(arg);
+        if !path.is_file() {
+            return Err(anyhow::anyhow!("Package file not found: {}", path.display()));
+        }
+        let manifest = apt_ng::package::read_local_package(path)
+            .with_context(|| format!("Failed to read package file {}", path.display()))?;
+        
+        let known = index.search_exact(&manifest.name)?
+            .into_iter()
+            .find(|pkg| pkg.version == manifest.version && pkg.arch == manifest.arch);
+        if let Some(known) = known.filter(|pkg| !pkg.checksum.is_empty()) {
+            if known.checksum != manifest.checksum {
+                return Err(anyhow::Error::new(apt_ng::AptNgError::ChecksumMismatch {
+                    expected: known.checksum,
+                    actual: manifest.checksum,
+                }).context(format!("{} does not match {} {} from the index", path.display(), manifest.name, manifest.version)));
+            }
+        }
+        
+        requested.push(solver::PackageSpec {
+            name: manifest.name.clone(),
+            version: Some(manifest.version.clone()),
+            arch: Some(manifest.arch.clone()),
+        });
+        local_packages.push(manifest);
+    }
+    
+    Ok((requested, local_packages))
+}
+
+/// Legt lokale Paketdateien in Index und Cache ab, damit sie wie heruntergeladene
+/// Pakete geprüft und installiert werden
+fn stage_local_packages(index: &index::Index, cache: &cache::Cache, local_packages: &[apt_ng::PackageManifest]) -> anyhow::Result<()> {
+    for pkg in local_packages {
+        let source = Path::new(pkg.filename.as_deref().unwrap_or_default());
+        let ext = source.extension().and_then(|ext| ext.to_str()).unwrap_or("deb");
+        
+        index.add_local_package(pkg)?;
+        
+        // Der Cache verschiebt die Datei; die Quelle des Benutzers bleibt unangetastet
+        let staged = cleanup::TempPath::new(&format!("apt-ng-local-{}-{}.tmp", pkg.name, pkg.version));
+        std::fs::copy(source, &staged)
+            .with_context(|| format!("Failed to copy {} into the cache", source.display()))?;
+        cache.add_package_from_file(&pkg.name, &pkg.version, &pkg.arch, ext, &staged)?;
+        
+        output::Output::info(&format!("Using local file {} for {} {}", source.display(), pkg.name, pkg.version));
+    }
+    Ok(())
+}
+
+/// Lädt alle verfügbaren Pakete und baut den Solver darüber auf
+///
+/// Mit `default_release` (bzw. `--target-release`) bevorzugt der Solver die Versionen aus den
+/// Repositories dieser Suite bzw. dieses Codenames.
+fn load_resolver_input(index: &index::Index, config: &config::Config, verbose: bool) -> anyhow::Result<ResolverInput> {
+    output::Output::section("🔍 Loading package index...");
+    let manifests = index.get_all_packages()?;
+    let (mut solver, skipped) = solver::DependencySolver::from_manifests(&manifests);
+    // Systempakete aus dem Cache, solange sich die dpkg-Statusdatei nicht geändert hat
+    solver.set_system_snapshot(apt_ng::dpkg::SystemSnapshot::load_cached(&config.system_snapshot_path()));
+    solver.set_unavailable_versions(index.list_unavailable()?.into_iter().collect());
+    
+    if let Some(release) = &config.default_release {
+        let target_repos: HashSet<i64> = repo::Repository::load_all(index.conn())?
+            .into_iter()
+            .filter(|repo| repo.matches_release(release))
+            .filter_map(|repo| repo.id)
+            .collect();
+        if target_repos.is_empty() {
+            anyhow::bail!("Target release {} is not available from any enabled repository", release);
+        }
+        if verbose {
+            output::Output::info(&format!("Preferring versions from {} ({} repositories)", release, target_repos.len()));
+        }
+        solver.set_target_repos(target_repos);
+    }
+    
+    if verbose {
+        // Continue with other packages even if one fails
+        for (name, e) in &skipped {
+            output::Output::warning(&format!("Failed to parse dependencies for {}: {}", name, e));
+        }
+        output::Output::info(&format!("Loaded {} packages into solver", manifests.len()));
+    }
+    
+    Ok(ResolverInput { manifests, solver })
+}
+
+/// Löst die angeforderten Pakete samt Abhängigkeiten über den Solver auf
+/// und liefert die zugehörigen Manifeste aus dem Index
+///
+/// Ohne `input` wird der Solver aus dem Index neu aufgebaut.
+fn resolve_packages(
+    index: &index::Index,
+    config: &config::Config,
+    requested: &[solver::PackageSpec],
+    input: Option<&ResolverInput>,
+    solver_jobs: usize,
+    verbose: bool,
+) -> anyhow::Result<ResolvedPackages> {
+    // 1. Populate solver with all available packages
+    let loaded;
+    let input = match input {
+        Some(input) => input,
+        None => {
+            loaded = load_resolver_input(index, config, verbose)?;
+            &loaded
+        }
+    };
+    let all_manifests = &input.manifests;
+    
+    // 2. Resolve dependencies using solver (with optional parallel solving)
+    output::Output::section("🧩 Resolving dependencies...");
+    // Use parallel solver for better performance with large dependency graphs (solver_jobs = 1 disables it)
+    let mut requested = requested.to_vec();
+    tracing::info!(requested = ?requested.iter().map(|spec| spec.name.as_str()).collect::<Vec<_>>(), solver_jobs, "resolving dependencies");
+    output::Output::debug(&format!("{} candidate package(s), {} solver job(s)", all_manifests.len(), solver_jobs));
+    for spec in &requested {
+        output::Output::debug(&format!("requested: {} {}", spec.name, spec.version.as_deref().unwrap_or("(any version)")));
+    }
+    let solution = loop {
+        match input.solver.solve_with_jobs(&requested, solver_jobs) {
+            Ok(sol) => break sol,
+            // Virtuelle Pakete mit mehreren Anbietern: einen auswählen und erneut auflösen
+            Err(apt_ng::AptNgError::VirtualPackage { name, providers }) => {
+                let provider = choose_provider(all_manifests, &name, &providers)?;
+                tracing::info!(virtual_package = %name, provider = %provider, "provider chosen");
+                output::Output::debug(&format!("virtual package {} → provider {}", name, provider));
+                for spec in requested.iter_mut().filter(|spec| spec.name == name) {
+                    *spec = input.solver.provider_spec(spec, &provider);
+                }
+            }
+            Err(e) => {
+                output::Output::error(&format!("Dependency resolution failed: {}", e));
+                if let apt_ng::AptNgError::PackageNotFound(ref name) = e {
+                    report_missing_package(index, name)?;
+                }
+                return Err(e.into());
+            }
+        }
+    };
+    
+    let names = |pkgs: &[solver::PackageInfo]| pkgs.iter().map(|pkg| format!("{} {}", pkg.name, pkg.version)).collect::<Vec<_>>();
+    tracing::info!(
+        install = ?names(&solution.to_install),
+        upgrade = ?names(&solution.to_upgrade),
+        remove = ?solution.to_remove,
+        "dependencies resolved"
+    );
+    // -vv: aufgelöste Pakete samt Herkunft und Abhängigkeiten
+    for (action, pkgs) in [("install", &solution.to_install), ("upgrade", &solution.to_upgrade)] {
+        for pkg in pkgs {
+            let repo = pkg.repo_id.map(|id| format!(" repo {}", id)).unwrap_or_default();
+            output::Output::debug(&format!("{} {} {} [{}]{}", action, pkg.name, pkg.version, pkg.arch, repo));
+            for dep in &pkg.depends {
+                let constraint = dep.version_constraint.as_deref().map(|c| format!(" ({})", c)).unwrap_or_default();
+                output::Output::debug(&format!("    depends: {}{}", dep.name, constraint));
+            }
+        }
+    }
+    for name in &solution.to_remove {
+        output::Output::debug(&format!("remove {}", name));
+    }
+    
+    // 3. Convert PackageInfo back to PackageManifest for installation
+    let to_manifests = |infos: &[solver::PackageInfo]| -> anyhow::Result<Vec<apt_ng::PackageManifest>> {
+        let mut manifests = Vec::new();
+        for pkg_info in infos {
+            // Find the corresponding manifest
+            if let Some(manifest) = all_manifests.iter()
+                .find(|m| m.name == pkg_info.name && m.version == pkg_info.version && m.arch == pkg_info.arch) {
+                manifests.push(manifest.clone());
+            } else {
+                // Fallback: try to find by name only
+                if let Some(manifest) = all_manifests.iter()
+                    .find(|m| m.name == pkg_info.name) {
+                    manifests.push(manifest.clone());
+                } else {
+                    return Err(anyhow::anyhow!("Package {} {} not found in index", pkg_info.name, pkg_info.version));
+                }
+            }
+        }
+        Ok(manifests)
+    };
+    
+    let install_order: Vec<solver::PackageInfo> = solution.install_order().into_iter().cloned().collect();
+    Ok(ResolvedPackages {
+        install_order: to_manifests(&install_order)?,
+        to_install: to_manifests(&solution.to_install)?,
+        to_upgrade: to_manifests(&solution.to_upgrade)?,
+        to_remove: solution.to_remove,
+        closure: to_manifests(&solution.closure)?,
+    })
+}
+
+/// Wählt zu jeder Anforderung das passende Paket, ohne Abhängigkeiten aufzulösen
+fn select_requested_packages(
+    index: &index::Index,
+    config: &config::Config,
+    requested: &[solver::PackageSpec],
+    verbose: bool,
+) -> anyhow::Result<Vec<apt_ng::PackageManifest>> {
+    let input = load_resolver_input(index, config, verbose)?;
+    let mut selected = Vec::new();
+    for spec in requested {
+        let candidate = match input.solver.candidate(spec) {
+            Ok(candidate) => candidate,
+            Err(apt_ng::AptNgError::VirtualPackage { name, providers }) => {
+                let provider = choose_provider(&input.manifests, &name, &providers)?;
+                input.solver.candidate(&input.solver.provider_spec(spec, &provider))?
+            }
+            Err(e) => {
+                if let apt_ng::AptNgError::PackageNotFound(ref name) = e {
+                    report_missing_package(index, name)?;
+                }
+                return Err(e.into());
+            }
+        };
+        let manifest = input.manifests.iter()
+            .find(|m| m.name == candidate.name && m.version == candidate.version && m.arch == candidate.arch)
+            .ok_or_else(|| anyhow::anyhow!("Package {} {} not found in index", candidate.name, candidate.version))?;
+        selected.push(manifest.clone());
+    }
+    Ok(selected)
+}
+
+/// Wählt den Anbieter eines virtuellen Pakets
+///
+/// Interaktiv fragt apt-ng nach; ohne Terminal gewinnt der Anbieter mit der höchsten
+/// Priority (required vor important, standard, optional, extra), bei Gleichstand alphabetisch.
+fn choose_provider(manifests: &[apt_ng::PackageManifest], name: &str, providers: &[String]) -> anyhow::Result<String> {
+    let rank = |provider: &String| {
+        manifests.iter()
+            .filter(|pkg| pkg.name == *provider)
+            .filter_map(|pkg| pkg.priority.as_deref())
+            .map(|priority| match priority {
+                "required" => 0,
+                "important" => 1,
+                "standard" => 2,
+                "optional" => 3,
+                "extra" => 4,
+                _ => 5,
+            })
+            .min()
+            .unwrap_or(5)
+    };
+    let mut ranked = providers.to_vec();
+    ranked.sort_by_key(rank);
+    
+    output::Output::info(&format!("Package {} is a virtual package provided by:", name));
+    for (i, provider) in ranked.iter().enumerate() {
+        output::Output::list_item(&format!("{}) {}", i + 1, provider));
+    }
+    
+    if !atty::is(atty::Stream::Stdin) {
+        output::Output::info(&format!("Selecting {} (highest priority)", ranked[0]));
+        return Ok(ranked[0].clone());
+    }
+    
+    print!("Select a provider [1]: ");
+    use std::io::Write;
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    let choice = match answer.trim() {
+        "" => 1,
+        number => number.parse::<usize>().ok()
+            .filter(|n| (1..=ranked.len()).contains(n))
+            .ok_or_else(|| anyhow::anyhow!("Invalid selection {:?}", number))?,
+    };
+    Ok(ranked[choice - 1].clone())
+}
+
+/// Erklärt wie apt, warum ein angefordertes Paket fehlt
+///
+/// Nennt die Architekturen bei `name:arch`, die Anbieter eines virtuellen Pakets und
+/// ähnlich geschriebene Paketnamen.
+fn report_missing_package(index: &index::Index, name: &str) -> anyhow::Result<()> {
+    if let Some((base, arch)) = name.split_once(':') {
+        let available: Vec<String> = index.search_exact(base)?.into_iter().map(|pkg| pkg.arch).collect();
+        if !available.is_empty() {
+            output::Output::info(&format!(
+                "Package {} is not available for architecture {} (available: {})",
+                base, arch, available.join(", ")
+            ));
+            if available.iter().any(|candidate| candidate == arch || candidate == "all") {
+                output::Output::info(&format!("Architecture qualifiers are not supported; try `apt-ng install {}`", base));
+            }
+            return Ok(());
+        }
+    }
+    
+    let providers = index.providers_of(name)?;
+    if !providers.is_empty() {
+        output::Output::info(&format!("Package {} is a virtual package provided by:", name));
+        for pkg in &providers {
+            output::Output::list_item(&format!("{} {}", pkg.name, pkg.version));
+        }
+        output::Output::info("You should explicitly select one to install.");
+        return Ok(());
+    }
+    
+    let similar = index.similar_package_names(name, 5)?;
+    if !similar.is_empty() {
+        output::Output::info(&format!("Did you mean: {}?", similar.join(", ")));
+    }
+    Ok(())
+}
+
+/// Baut die Download-URLs eines Pakets aus Repository-URL und Filename (schnellster
+/// Mirror zuerst, danach Ausweich-Mirrors) und gibt zusätzlich den Filename zurück
+/// Lädt ein Upgrade als Delta zur installierten Version, sofern das Repository eines anbietet
+///
+/// Deltas liegen unter `<repo>/deltas/`, ihre Metadaten daneben als `.json`. Genutzt wird
+/// ein Delta nur, wenn es sich lohnt und die installierte Version noch im Cache liegt; das
+/// rekonstruierte Paket muss die Prüfsumme aus dem Packages-Index haben. `Ok(None)` heißt,
+/// dass das volle Paket geladen werden muss.
+async fn fetch_delta(
+    downloader: &downloader::Downloader,
+    index: &index::Index,
+    cache: &cache::Cache,
+    pkg: &apt_ng::PackageManifest,
+    from_version: &str,
+    dest: &Path,
+) -> anyhow::Result<Option<apt_ng::delta::DeltaMetadata>> {
+    use apt_ng::delta::{DeltaApplier, DeltaMetadata};
+    
+    let base = cache.package_path_with_ext(&pkg.name, from_version, &pkg.arch, "deb");
+    let Some(repo_id) = pkg.repo_id else { return Ok(None) };
+    // Das rekonstruierte Paket wird gegen den SHA256 aus dem Packages-Index geprüft
+    if pkg.checksum.len() != 64 || !base.exists() {
+        return Ok(None);
+    }
+    let Some(repo_url) = index.get_repo_url(repo_id)? else { return Ok(None) };
+    let delta_url = format!("{}/deltas/{}", repo_url.trim_end_matches('/'),
+        DeltaMetadata::file_name(&pkg.name, from_version, &pkg.version, &pkg.arch));
+    
+    // Die meisten Repositories bieten keine Deltas an: fehlende Metadaten sind kein Fehler
+    let metadata_file = cleanup::TempPath::new(&format!("apt-ng-delta-{}-{}.json", pkg.name, pkg.version));
+    if downloader.download_file(&format!("{}.json", delta_url), &metadata_file).await.is_err() {
+        return Ok(None);
+    }
+    let metadata: DeltaMetadata = serde_json::from_slice(&std::fs::read(&metadata_file)?)
+        .with_context(|| format!("Invalid delta metadata at {}.json", delta_url))?;
+    if metadata.from_version != from_version || metadata.to_version != pkg.version
+        || !metadata.is_worthwhile() || !DeltaApplier::verify_delta_applicable(&base, &metadata)? {
+        return Ok(None);
+    }
+    
+    let delta_file = cleanup::TempPath::new(&format!("apt-ng-delta-{}-{}.delta", pkg.name, pkg.version));
+    downloader.download_file_with_checksum(&delta_url, &delta_file, Some(&metadata.checksum)).await?;
+    DeltaApplier::apply_delta(&base, &delta_file, dest, &metadata)?;
+    
+    let checksum = cache::Cache::calculate_file_checksum(dest)?;
+    if !checksum.eq_ignore_ascii_case(&pkg.checksum) {
+        anyhow::bail!("reconstructed package has checksum {}, expected {}", checksum, pkg.checksum);
+    }
+    Ok(Some(metadata))
+}
+
+fn package_download_urls(index: &index::Index, pkg: &apt_ng::PackageManifest) -> anyhow::Result<(Vec<String>, String)> {
+    let repo_id = pkg.repo_id.ok_or_else(|| {
+        anyhow::anyhow!("Package {} has no repository ID", pkg.name)
+    })?;
+    
+    let filename = pkg.filename.clone()
+        .ok_or_else(|| anyhow::anyhow!("Package {} has no filename", pkg.name))?;
+    
+    // Felder aus dem Index landen in URLs und Dateinamen und werden daher vorher geprüft
+    if let Err(e) = pkg.validate_identity().and_then(|()| apt_ng::package::validate_repo_filename(&filename)) {
+        output::Output::warning(&format!("Rejecting package {:?} from repository {}: {}", pkg.name, repo_id, e));
+        return Err(e.context(format!("Package {} has unsafe index metadata", pkg.name)));
+    }
+    
+    let download_urls = index.download_urls(repo_id, &filename)?;
+    
+    Ok((download_urls, filename))
+}
+
+/// Evicts cached packages until the cache is below `max_size`,
+/// removing debs of already installed packages first, then the oldest files
+fn enforce_cache_limit(index: &index::Index, cache: &cache::Cache, max_size: u64, verbose: bool) -> anyhow::Result<()> {
+    let installed_paths: Vec<std::path::PathBuf> = index.list_installed_packages_with_manifests()?
+        .iter()
+        .flat_map(|pkg| {
+            ["deb", "apx"].map(|ext| cache.package_path_with_ext(&pkg.name, &pkg.version, &pkg.arch, ext))
+        })
+        .collect();
+    
+    let evicted = cache.clean_if_over_limit_preferring(max_size, &installed_paths)?;
+    
+    if verbose && !evicted.is_empty() {
+        let names: Vec<String> = evicted.iter()
+            .filter_map(|path| path.file_name().map(|name| name.to_string_lossy().to_string()))
+            .collect();
+        output::Output::info(&format!(
+            "Cache over {} limit, evicted {} package(s): {}",
+            format_size(max_size),
+            evicted.len(),
+            names.join(", ")
+        ));
+    }
+    
+    Ok(())
+}
+
+async fn cmd_remove(
+    index: &index::Index,
+    packages: &[String],
+    dry_run: bool,
+    verbose: bool,
+) -> anyhow::Result<()> {
+    let disk_delta = apt_ng::package::disk_usage_delta(&[], packages, &index.list_installed_packages_with_manifests()?);
+    output::Output::info(&disk_usage_message(disk_delta));
+    
+    if dry_run {
+        output::Output::info(&format!("[DRY RUN] Would remove: {:?}", packages));
+        return Ok(());
+    }
+    
+    if verbose {
+        output::Output::info(&format!("Removing packages: {:?}", packages));
+    }
+    
+    let before = installed_versions(index)?;
+    let result = packages.iter().try_for_each(|pkg_name| {
+        index.mark_removed(pkg_name)?;
+        if verbose {
+            output::Output::success(&format!("Removed: {}", pkg_name));
+        }
+        Ok(())
+    });
+    record_history(index, &before, result.as_ref().err());
+    result
+}
+
+/// Hält eine verändernde Operation im Verlauf fest
+///
+/// Die Änderungen ergeben sich aus dem Vergleich der installierten Pakete vor und nach der
+/// Operation. Ein Fehler beim Schreiben des Verlaufs lässt die Operation nicht scheitern.
+fn record_history(index: &index::Index, before: &HashMap<String, String>, error: Option<&anyhow::Error>) {
+    let recorded = installed_versions(index).and_then(|after| {
+        let changes = apt_ng::history::changes_between(before, &after);
+        if changes.is_empty() && error.is_none() {
+            return Ok(());
+        }
+        let command_line = std::iter::once("apt-ng".to_string())
+            .chain(std::env::args().skip(1))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let entry = apt_ng::history::HistoryEntry::new(&command_line, changes, error.map(|e| format!("{:#}", e)));
+        index.record_history(&entry).map(|_| ())
+    });
+    if let Err(e) = recorded {
+        output::Output::warning(&format!("Failed to record the operation in the history: {}", e));
+    }
+}
+
+/// Upgrade-Übersicht als Tabelle: installierte -> neue Version, Größenänderung und Repository.
+/// Major-Sprünge werden gelb, Downgrades rot hervorgehoben.
+fn print_upgrade_table(
+    index: &index::Index,
+    installed: &[apt_ng::PackageManifest],
+    upgrades: &[apt_ng::PackageManifest],
+) -> anyhow::Result<()> {
+    use apt_ng::solver::DependencySolver;
+    
+    if !output::Output::shows(output::Verbosity::Normal) {
+        return Ok(());
+    }
+    
+    let installed: HashMap<&str, &apt_ng::PackageManifest> = installed.iter()
+        .map(|pkg| (pkg.name.as_str(), pkg))
+        .collect();
+    let colors = output::Output::colors_enabled();
+    
+    let mut table = output::Output::table();
+    table.set_header(vec!["Package", "Version", "Size", "Repository"]);
+    for pkg in upgrades {
+        let current = installed.get(pkg.name.as_str());
+        let current_version = current.map(|c| c.version.as_str()).unwrap_or("-");
+        
+        let change_cell = comfy_table::Cell::new(format!("{} -> {}", current_version, pkg.version));
+        let change_cell = if !colors || current.is_none() {
+            change_cell
+        } else if DependencySolver::compare_versions(&pkg.version, current_version) == std::cmp::Ordering::Less {
+            change_cell.fg(comfy_table::Color::Red).add_attribute(comfy_table::Attribute::Bold)
+        } else if DependencySolver::is_major_change(current_version, &pkg.version) {
+            change_cell.fg(comfy_table::Color::Yellow).add_attribute(comfy_table::Attribute::Bold)
+        } else {
+            change_cell.fg(comfy_table::Color::Green)
+        };
+        
+        // Installierte Größe, wenn beide Seiten sie kennen, sonst die Größe der .deb-Dateien
+        let size_delta = match current {
+            Some(current) => match (current.installed_size, pkg.installed_size) {
+                (Some(old), Some(new)) => format_size_delta(old.saturating_mul(1024), new.saturating_mul(1024)),
+                _ => format_size_delta(current.size, pkg.size),
+            },
+            None => "-".to_string(),
+        };
+        let repo_url = match pkg.repo_id {
+            Some(repo_id) => index.get_repo_url(repo_id)?.unwrap_or_else(|| format!("#{}", repo_id)),
+            None => "-".to_string(),
+        };
+        
+        let name_cell = if colors {
+            comfy_table::Cell::new(&pkg.name).fg(comfy_table::Color::Cyan)
+        } else {
+            comfy_table::Cell::new(&pkg.name)
+        };
+        table.add_row(vec![name_cell, change_cell, comfy_table::Cell::new(size_delta), comfy_table::Cell::new(repo_url)]);
+    }
+    println!("{}", table);
+    Ok(())
+}
+
+/// Upgrade aller installierten Pakete; mit `allow_removals` als `full-upgrade`, das
+/// kollidierende Pakete entfernt statt das Upgrade zurückzuhalten
+async fn cmd_upgrade(
+    index: &index::Index,
+    config: &config::Config,
+    jobs: config::Jobs,
+    allow_removals: bool,
+    assume_yes: bool,
+    write_lock: Option<&Path>,
+    dry_run: bool,
+    download_only: bool,
+    no_sandbox: bool,
+    verbose: bool,
+) -> anyhow::Result<()> {
+    output::Output::heading("🔄 Upgrading Packages");
+    
+    let installed_packages = index.list_installed_packages_with_manifests()?;
+    
+    if installed_packages.is_empty() {
+        output::Output::info("No packages installed.");
+        return Ok(());
+    }
+    
+    if verbose {
+        output::Output::info(&format!("Checking {} installed packages for upgrades...", installed_packages.len()));
+    }
+    
+    // 1. Finde verfügbare Upgrades
+    let mut packages_to_upgrade = Vec::new();
+    
+    for installed_pkg in &installed_packages {
+        // Get latest available version (exact match only for upgrades)
+        let available_packages = index.search_exact(&installed_pkg.name)?;
+        
+        if let Some(latest_pkg) = available_packages.first() {
+            // Compare versions using solver's version comparison
+            use apt_ng::solver::DependencySolver;
+            let comparison = DependencySolver::compare_versions(&latest_pkg.version, &installed_pkg.version);
+            
+            match comparison {
+                std::cmp::Ordering::Greater => {
+                    // Newer version available
+                    packages_to_upgrade.push(latest_pkg.clone());
+                    if verbose {
+                        output::Output::info(&format!(
+                            "{}: {} -> {}",
+                            installed_pkg.name,
+                            installed_pkg.version,
+                            latest_pkg.version
+                        ));
+                    }
+                }
+                _ => {
+                    // Already up to date or same version
+                    if verbose {
+                        output::Output::info(&format!("{}: {} (up to date)", installed_pkg.name, installed_pkg.version));
+                    }
                 }
             }
         }
     }
     
+    if packages_to_upgrade.is_empty() {
+        output::Output::success("All packages are up to date.");
+        return Ok(());
+    }
+    
+    // 2. Resolve dependencies for upgrades (der Solver wird danach an cmd_install weitergereicht)
+    let mut resolver_input = load_resolver_input(index, config, verbose)?;
+    
+    // Add installed packages to solver so dependencies already satisfied by installed packages can be found
+    for (name, e) in resolver_input.solver.add_manifests(&installed_packages) {
+        if verbose {
+            output::Output::warning(&format!("Failed to parse dependencies for installed package {}: {}", name, e));
+        }
+    }
+    
     // Tell the solver which packages are already installed so it can skip resolving their dependencies
-    let installed_package_names: HashSet<String> = installed_packages.iter()
-        .map(|p| p.name.clone())
+    let mut installed_package_map: HashMap<String, String> = installed_packages.iter()
+        .map(|p| (p.name.clone(), p.version.clone()))
         .collect();
     
     // Debug: Check if any installed dependencies that need libqt5core5t64
@@ -1039,7 +2312,61 @@ async fn cmd_upgrade(
         }
     }
     
-    solver.set_installed_packages(installed_package_names);
+    resolver_input.solver.set_installed_versions(installed_package_map.clone());
+    
+    // full-upgrade darf upstream verschwundene Pakete entfernen, wenn ein neues sie ersetzt
+    if allow_removals {
+        let obsolete = index.list_unavailable_installed()?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        resolver_input.solver.set_obsolete_packages(obsolete);
+    }
+    
+    // Wie apt: Upgrades, die nicht auflösbar sind oder eine Entfernung erfordern würden, zurückhalten
+    // (full-upgrade hält nur unauflösbare zurück)
+    let mut kept_back = Vec::new();
+    packages_to_upgrade.retain(|pkg| {
+        let spec = solver::PackageSpec {
+            name: pkg.name.clone(),
+            version: Some(pkg.version.clone()),
+            arch: Some(pkg.arch.clone()),
+        };
+        let reason = if allow_removals {
+            resolver_input.solver.unresolvable_reason(&spec)
+        } else {
+            resolver_input.solver.kept_back_reason(&spec)
+        };
+        match reason {
+            Some(reason) => {
+                kept_back.push((pkg.name.clone(), pkg.version.clone(), reason));
+                false
+            }
+            None => true,
+        }
+    });
+    
+    if !packages_to_upgrade.is_empty() {
+        output::Output::section(&format!("📦 Found {} package(s) to upgrade:", packages_to_upgrade.len()));
+        print_upgrade_table(index, &installed_packages, &packages_to_upgrade)?;
+    }
+    
+    if !kept_back.is_empty() {
+        output::Output::section("⏸ The following packages have been kept back:");
+        for (name, version, reason) in &kept_back {
+            output::Output::list_item(&format!("{} ({}): {}", name, version, reason));
+        }
+    }
+    
+    if packages_to_upgrade.is_empty() {
+        output::Output::info("No upgrades can be applied.");
+        return Ok(());
+    }
+    
+    if dry_run && !allow_removals {
+        output::Output::info("[DRY RUN] Would upgrade the above packages");
+        return Ok(());
+    }
     
     let upgrade_specs: Vec<solver::PackageSpec> = packages_to_upgrade.iter()
         .map(|p| solver::PackageSpec {
@@ -1051,93 +2378,91 @@ async fn cmd_upgrade(
     
     output::Output::section("🧩 Resolving dependencies for upgrades...");
     // Use parallel solver for better performance
-    let use_parallel_solver = jobs > 1;
-    let solution = match solver.solve_parallel(&upgrade_specs, use_parallel_solver) {
+    let solution = match resolver_input.solver.solve_with_jobs(&upgrade_specs, jobs.solver) {
         Ok(sol) => sol,
         Err(e) => {
             output::Output::error(&format!("Dependency resolution for upgrade failed: {}", e));
-            return Err(e);
+            return Err(e.into());
         }
     };
     
     if verbose {
-        output::Output::info(&format!("Solver returned {} packages to install, {} to upgrade", 
-            solution.to_install.len(), solution.to_upgrade.len()));
-        for pkg in &solution.to_install {
+        output::Output::info(&format!("Solver returned {} packages to install, {} to upgrade, {} to remove",
+            solution.to_install.len(), solution.to_upgrade.len(), solution.to_remove.len()));
+        for pkg in solution.install_order() {
             output::Output::info(&format!("  - {} {}", pkg.name, pkg.version));
         }
     }
     
-    // Separate packages into to_install and to_upgrade based on whether they're already installed
-    let installed_package_map: HashMap<String, String> = installed_packages.iter()
-        .map(|p| (p.name.clone(), p.version.clone()))
-        .collect();
-    
-    let mut packages_to_install = Vec::new();
-    let mut packages_to_upgrade = Vec::new();
+    // Einzeln unbedenkliche Upgrades können zusammen doch eine Entfernung erfordern
+    if !solution.to_remove.is_empty() && !allow_removals {
+        let reason = format!("upgrade would require removing {}", solution.to_remove.join(", "));
+        output::Output::error(&format!("{}; use `apt-ng full-upgrade` to allow removals", reason));
+        return Err(apt_ng::AptNgError::Conflict(vec![reason]).into());
+    }
     
-    for pkg in solution.to_install {
-        if let Some(installed_version) = installed_package_map.get(&pkg.name) {
-            // Package is already installed - check if version is different
-            use crate::solver::DependencySolver;
-            let comparison = DependencySolver::compare_versions(&pkg.version, installed_version);
-            match comparison {
-                std::cmp::Ordering::Greater => {
-                    // Newer version available - add to upgrade list
-                    packages_to_upgrade.push(pkg);
-                }
-                std::cmp::Ordering::Equal => {
-                    // Same version - skip (already installed)
-                    if verbose {
-                        output::Output::info(&format!("Package {} {} is already installed, skipping", pkg.name, pkg.version));
-                    }
-                }
-                std::cmp::Ordering::Less => {
-                    // Older version - shouldn't happen, but skip it
-                    if verbose {
-                        output::Output::warning(&format!("Package {} {} is older than installed version {}, skipping", pkg.name, pkg.version, installed_version));
-                    }
-                }
-            }
-        } else {
-            // Package is not installed - add to install list
-            packages_to_install.push(pkg);
+    // Entfernungen getrennt anzeigen und bestätigen lassen
+    if !solution.to_remove.is_empty() {
+        output::Output::section(&format!("🗑 The following {} package(s) will be REMOVED:", solution.to_remove.len()));
+        for name in &solution.to_remove {
+            output::Output::list_item(name);
         }
     }
     
-    // Add packages from solution.to_upgrade (if any)
-    packages_to_upgrade.extend(solution.to_upgrade);
+    if dry_run {
+        output::Output::info("[DRY RUN] Would upgrade the above packages");
+        return Ok(());
+    }
     
-    if packages_to_install.is_empty() && packages_to_upgrade.is_empty() {
+    // Mit --download-only wird nichts entfernt, die Entfernungen folgen beim eigentlichen Lauf
+    let to_remove: &[String] = if download_only { &[] } else { &solution.to_remove };
+    if !to_remove.is_empty() {
+        if !assume_yes && !confirm("Remove these packages to complete the upgrade?")? {
+            output::Output::info("Aborted, nothing was changed. Use --yes to confirm removals non-interactively.");
+            return Ok(());
+        }
+        
+        // Die Auflösung in cmd_install plant ohne die zu entfernenden Pakete; entfernt werden
+        // sie erst nach der Installation, in derselben Transaktionsgruppe
+        installed_package_map.retain(|name, _| !to_remove.contains(name));
+        resolver_input.solver.set_installed_versions(installed_package_map);
+    }
+    
+    if solution.to_install.is_empty() && solution.to_upgrade.is_empty() {
         output::Output::info("No packages to install or upgrade after dependency resolution.");
         return Ok(());
     }
     
     if verbose {
-        if !packages_to_upgrade.is_empty() {
+        if !solution.to_upgrade.is_empty() {
             output::Output::section("📋 Packages to upgrade:");
-            for pkg in &packages_to_upgrade {
+            for pkg in &solution.to_upgrade {
                 output::Output::list_item(&format!("{} ({})", pkg.name, pkg.version));
             }
         }
-        if !packages_to_install.is_empty() {
+        if !solution.to_install.is_empty() {
             output::Output::section("📋 Packages to install:");
-            for pkg in &packages_to_install {
+            for pkg in &solution.to_install {
                 output::Output::list_item(&format!("{} ({})", pkg.name, pkg.version));
             }
         }
     }
     
-    // Combine both lists for installation (install logic handles both new installs and upgrades)
-    let all_packages: Vec<String> = packages_to_install.iter()
-        .chain(packages_to_upgrade.iter())
+    // Beide Listen in Installationsreihenfolge (install logic handles both new installs and upgrades)
+    let all_packages: Vec<String> = solution.install_order()
+        .into_iter()
         .map(|p| p.name.clone())
         .collect();
     
     // 3. Use install logic for upgrades (it handles dependencies automatically)
-    cmd_install(index, config, &all_packages, jobs, false, verbose).await?;
+    cmd_install(index, config, &all_packages, to_remove, Some(&resolver_input), write_lock, jobs, false, download_only, false, no_sandbox, verbose).await?;
     
-    output::Output::success(&format!("Successfully upgraded {} package(s)", packages_to_upgrade.len()));
+    if download_only {
+        let command = if allow_removals { "full-upgrade" } else { "upgrade" };
+        output::Output::success(&format!("Downloaded {} upgrade(s); run `apt-ng {}` to install them", solution.to_upgrade.len(), command));
+    } else {
+        output::Output::success(&format!("Successfully upgraded {} package(s)", solution.to_upgrade.len()));
+    }
     
     Ok(())
 }
@@ -1160,6 +2485,24 @@ fn cmd_show(index: &index::Index, package: &str, _verbose: bool) -> anyhow::Resu
             table.add_row(vec![comfy_table::Cell::new("Version"), comfy_table::Cell::new(&pkg.version)]);
             table.add_row(vec![comfy_table::Cell::new("Architecture"), comfy_table::Cell::new(&pkg.arch)]);
             table.add_row(vec![comfy_table::Cell::new("Size"), comfy_table::Cell::new(&format_size(pkg.size))]);
+            if let Some(installed_size) = pkg.installed_size {
+                table.add_row(vec![comfy_table::Cell::new("Installed-Size"), comfy_table::Cell::new(format_size(installed_size.saturating_mul(1024)))]);
+            }
+            if !index.is_available(&pkg.name, &pkg.version, &pkg.arch)? {
+                table.add_row(vec![comfy_table::Cell::new("Status"), comfy_table::Cell::new("installed, no longer available from repository")]);
+            }
+            
+            let descriptive_fields = [
+                ("Section", &pkg.section),
+                ("Priority", &pkg.priority),
+                ("Maintainer", &pkg.maintainer),
+                ("Homepage", &pkg.homepage),
+            ];
+            for (field, value) in descriptive_fields {
+                if let Some(value) = value {
+                    table.add_row(vec![comfy_table::Cell::new(field), comfy_table::Cell::new(value)]);
+                }
+            }
             
             if !pkg.depends.is_empty() {
                 table.add_row(vec![comfy_table::Cell::new("Depends"), comfy_table::Cell::new(&pkg.depends.join(", "))]);
@@ -1167,38 +2510,500 @@ fn cmd_show(index: &index::Index, package: &str, _verbose: bool) -> anyhow::Resu
             if !pkg.provides.is_empty() {
                 table.add_row(vec![comfy_table::Cell::new("Provides"), comfy_table::Cell::new(&pkg.provides.join(", "))]);
             }
+            if !pkg.recommends.is_empty() {
+                table.add_row(vec![comfy_table::Cell::new("Recommends"), comfy_table::Cell::new(pkg.recommends.join(", "))]);
+            }
+            if !pkg.suggests.is_empty() {
+                table.add_row(vec![comfy_table::Cell::new("Suggests"), comfy_table::Cell::new(pkg.suggests.join(", "))]);
+            }
             
             println!("{}", table);
         }
-        None => {
-            output::Output::error(&format!("Package '{}' not found", package));
+        None => {
+            output::Output::error(&format!("Package '{}' not found", package));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Listet alle verfügbaren Versionen eines Pakets (neueste zuerst)
+fn cmd_show_all_versions(index: &index::Index, package: &str) -> anyhow::Result<()> {
+    output::Output::heading(&format!("📋 Available Versions: {}", package));
+    
+    let versions = index.search_exact(package)?;
+    if versions.is_empty() {
+        output::Output::error(&format!("Package '{}' not found", package));
+        return Ok(());
+    }
+    
+    let installed_version = index.installed_version(package)?;
+    
+    let mut table = output::Output::table();
+    table.set_header(vec!["Version", "Architecture", "Repository", "Installed"]);
+    for pkg in &versions {
+        let repo_url = match pkg.repo_id {
+            Some(repo_id) => index.get_repo_url(repo_id)?.unwrap_or_else(|| format!("#{}", repo_id)),
+            None => "-".to_string(),
+        };
+        let installed = installed_version.as_deref() == Some(pkg.version.as_str());
+        
+        let version_cell = if installed && output::Output::colors_enabled() {
+            comfy_table::Cell::new(&pkg.version).fg(comfy_table::Color::Green)
+        } else {
+            comfy_table::Cell::new(&pkg.version)
+        };
+        table.add_row(vec![
+            version_cell,
+            comfy_table::Cell::new(&pkg.arch),
+            comfy_table::Cell::new(&repo_url),
+            comfy_table::Cell::new(match (installed, index.is_available(&pkg.name, &pkg.version, &pkg.arch)?) {
+                (true, true) => "✓",
+                (true, false) => "✓ (no longer available)",
+                _ => "",
+            }),
+        ]);
+    }
+    println!("{}", table);
+    
+    Ok(())
+}
+
+/// Listet die Dateien eines installierten Pakets auf
+///
+/// Ohne aufgezeichnete Dateiliste wird auf die dpkg-Datenbank zurückgegriffen.
+fn cmd_files(index: &index::Index, config: &config::Config, package: &str) -> anyhow::Result<()> {
+    let install_root = &config.paths.install_root;
+    let files: Vec<std::path::PathBuf> = match index.installed_files(package)? {
+        Some(files) if !files.is_empty() => files.iter().map(|entry| install_root.join(&entry.path)).collect(),
+        recorded => match apt_ng::dpkg::installed_files(package) {
+            Some(files) => files,
+            None if recorded.is_some() => Vec::new(),
+            None => return Err(apt_ng::error::AptNgError::NotInstalled(package.to_string()).into()),
+        },
+    };
+    
+    for file in files {
+        println!("{}", file.display());
+    }
+    
+    Ok(())
+}
+
+/// Sucht das installierte Paket, dem eine Datei gehört
+///
+/// Zuerst werden die von apt-ng erfassten Dateilisten durchsucht, danach die dpkg-Datenbank.
+/// Symlinks im Pfad werden nicht aufgelöst, da die Listen die Pfade aus dem Paket enthalten.
+fn cmd_which_package(index: &index::Index, config: &config::Config, path: &Path) -> anyhow::Result<()> {
+    let absolute = if path.is_absolute() { path.to_path_buf() } else { std::env::current_dir()?.join(path) };
+    let install_root = &config.paths.install_root;
+    
+    let mut owners = match absolute.strip_prefix(install_root) {
+        Ok(relative) => index.packages_owning_file(&relative.to_string_lossy())?,
+        Err(_) => Vec::new(),
+    };
+    if owners.is_empty() {
+        owners = apt_ng::dpkg::owning_packages(&absolute);
+    }
+    
+    if owners.is_empty() {
+        return Err(anyhow::anyhow!("No installed package contains {}", absolute.display()));
+    }
+    println!("{}: {}", owners.join(", "), absolute.display());
+    
+    Ok(())
+}
+
+/// Zeigt das Changelog eines Pakets (online, sonst aus /usr/share/doc)
+/// Prüft eine .deb- oder .apx-Datei, ohne sie zu installieren
+///
+/// Jede Prüfung wird einzeln ausgegeben; schlägt eine fehl, endet der Befehl mit
+/// dem Exit-Code für Verifikationsfehler.
+fn cmd_verify(
+    index: &index::Index,
+    config: &config::Config,
+    path: &Path,
+    signature: Option<&Path>,
+    verbose: bool,
+) -> anyhow::Result<()> {
+    output::Output::heading("🔐 Verifying Package");
+    
+    if !path.is_file() {
+        return Err(anyhow::anyhow!("Package file not found: {}", path.display()));
+    }
+    let verifier = verifier::PackageVerifier::new(config.trusted_keys_dir())?;
+    if verbose {
+        output::Output::info(&format!("Using {} trusted key(s) from {}", verifier.trusted_key_count(), config.trusted_keys_dir().display()));
+    }
+    
+    let mut checks = 0;
+    let mut failed = 0;
+    let mut report = |check: &str, result: anyhow::Result<String>| {
+        checks += 1;
+        match result {
+            Ok(detail) => output::Output::success(&format!("{}: {}", check, detail)),
+            Err(e) => {
+                failed += 1;
+                output::Output::error(&format!("{}: {:#}", check, e));
+            }
+        }
+    };
+    
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("apx") => {
+            use apt_ng::package::ApxPackage;
+            
+            let apx = match ApxPackage::open(path) {
+                Ok(apx) => apx,
+                Err(e) => return Err(e.context(format!("{} is not a valid .apx package", path.display()))),
+            };
+            report("Metadata", Ok(format!("{} {} ({})", apx.manifest.name, apx.manifest.version, apx.manifest.arch)));
+            report("Signature", apx.verify_signature(path, &verifier).map(|_| "valid (trusted key)".to_string()));
+            
+            let extract_dir = cleanup::TempPath::new(&format!("apt-ng-verify-{}", std::process::id()));
+            let checksums = apx.extract_to(&extract_dir)
+                .and_then(|_| apx.verify_checksums(&extract_dir))
+                .map(|_| format!("{} file(s) match the manifest", apx.manifest.files.len()));
+            report("File checksums", checksums);
+        }
+        Some("deb") => {
+            let manifest = apt_ng::package::read_local_package(path)
+                .with_context(|| format!("{} is not a valid .deb package", path.display()))?;
+            report("Metadata", Ok(format!("{} {} ({})", manifest.name, manifest.version, manifest.arch)));
+            
+            // SHA256 gegen den Index, falls dieselbe Version dort bekannt ist
+            let known = index.search_exact(&manifest.name)?
+                .into_iter()
+                .find(|pkg| pkg.version == manifest.version && pkg.arch == manifest.arch && !pkg.checksum.is_empty());
+            let sha256 = match known {
+                Some(known) if known.checksum == manifest.checksum => Ok(format!("{} (matches index)", manifest.checksum)),
+                Some(known) => Err(apt_ng::AptNgError::ChecksumMismatch {
+                    expected: known.checksum,
+                    actual: manifest.checksum.clone(),
+                }.into()),
+                None => Ok(format!("{} (package not in index)", manifest.checksum)),
+            };
+            report("SHA256", sha256);
+            
+            // Abgesetzte Signatur wie von `apt-ng-build sign` (64 Byte Ed25519 über die ganze Datei)
+            let signature_path = signature.map(Path::to_path_buf).or_else(|| {
+                [path.with_extension("deb.sig"), path.with_extension("sig")]
+                    .into_iter()
+                    .find(|candidate| candidate.is_file())
+            });
+            match signature_path {
+                Some(signature_path) => {
+                    let result = std::fs::read(&signature_path)
+                        .with_context(|| format!("Failed to read {}", signature_path.display()))
+                        .and_then(|signature_bytes| {
+                            let data = std::fs::read(path)?;
+                            Ok(verifier.verify_package_signature(&data, &signature_bytes)?)
+                        })
+                        .map(|_| format!("valid ({})", signature_path.display()));
+                    report("Signature", result);
+                }
+                None => output::Output::info("Signature: no detached signature found, skipped"),
+            }
+        }
+        _ => return Err(anyhow::anyhow!("{} is neither a .deb nor an .apx file", path.display())),
+    }
+    
+    if failed > 0 {
+        return Err(apt_ng::AptNgError::VerificationFailed(format!(
+            "{}: {} of {} check(s) failed", path.display(), failed, checks
+        )).into());
+    }
+    
+    output::Output::success(&format!("{}: all {} check(s) passed", path.display(), checks));
+    Ok(())
+}
+
+/// Baut ein .apx-Paket aus einem Verzeichnis
+///
+/// Metadaten kommen aus der Control-Datei (explizit oder DEBIAN/control); die
+/// Flags `name`, `version`, `arch` und `depends` haben Vorrang.
+fn cmd_build_apx(
+    source_dir: &Path,
+    output: Option<&str>,
+    control: Option<&str>,
+    name: Option<&str>,
+    version: Option<&str>,
+    arch: Option<&str>,
+    depends: &[String],
+    sign_key: Option<&str>,
+    verbose: bool,
+) -> anyhow::Result<()> {
+    output::Output::heading("📦 Building APX Package");
+    
+    if !source_dir.is_dir() {
+        return Err(anyhow::anyhow!("Source directory does not exist: {}", source_dir.display()));
+    }
+    
+    let default_control = source_dir.join("DEBIAN/control");
+    let control_path = control.map(Path::new)
+        .or_else(|| default_control.is_file().then_some(default_control.as_path()));
+    let mut manifest = match control_path {
+        Some(path) => {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read control file {}", path.display()))?;
+            apt_ng::parse_packages_file(&content)?
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("Control file {} has no Package field", path.display()))?
+        }
+        None => apt_ng::PackageManifest {
+            name: String::new(),
+            version: String::new(),
+            arch: String::new(),
+            provides: vec![],
+            depends: vec![],
+            conflicts: vec![],
+            replaces: vec![],
+            files: vec![],
+            size: 0,
+            checksum: String::new(),
+            timestamp: 0,
+            filename: None,
+            repo_id: None,
+            maintainer: None,
+            homepage: None,
+            section: None,
+            priority: None,
+            installed_size: None,
+            recommends: vec![],
+            suggests: vec![],
+        },
+    };
+    if let Some(name) = name {
+        manifest.name = name.to_string();
+    }
+    if let Some(version) = version {
+        manifest.version = version.to_string();
+    }
+    if let Some(arch) = arch {
+        manifest.arch = arch.to_string();
+    }
+    if !depends.is_empty() {
+        manifest.depends = depends.iter().map(|dep| dep.trim().to_string()).collect();
+    }
+    if manifest.name.is_empty() || manifest.version.is_empty() {
+        return Err(anyhow::anyhow!("Package name and version are required (--name/--version or a control file)"));
+    }
+    if manifest.arch.is_empty() {
+        manifest.arch = system::detect_native_architecture();
+    }
+    
+    // Dateiliste mit SHA256 und Modus; DEBIAN/ enthält nur Metadaten
+    manifest.files = apt_ng::package::collect_file_entries(source_dir, &["DEBIAN"])?;
+    manifest.installed_size = Some(manifest.files.iter().map(|file| file.size).sum::<u64>().div_ceil(1024));
+    manifest.timestamp = chrono::Utc::now().timestamp();
+    if verbose {
+        for file in &manifest.files {
+            match &file.link_target {
+                Some(target) => output::Output::list_item(&format!("{} -> {}", file.path, target)),
+                None => output::Output::list_item(&format!("{} ({:o}, {})", file.path, file.mode & 0o7777, format_size(file.size))),
+            }
+        }
+    }
+    
+    let key_bytes = match sign_key {
+        Some(path) => {
+            let bytes = std::fs::read(path).with_context(|| format!("Failed to read signing key {}", path))?;
+            if bytes.len() != 32 {
+                return Err(apt_ng::AptNgError::InvalidKey(format!("{}: expected 32 bytes, got {}", path, bytes.len())).into());
+            }
+            Some(bytes)
+        }
+        None => None,
+    };
+    
+    let output_path = output.map(std::path::PathBuf::from).unwrap_or_else(|| {
+        std::path::PathBuf::from(format!("{}_{}_{}.apx", manifest.name, manifest.version, manifest.arch))
+    });
+    let (name, version, file_count) = (manifest.name.clone(), manifest.version.clone(), manifest.files.len());
+    apt_ng::package::create_apx_package(source_dir, manifest, &output_path, key_bytes.as_deref())?;
+    
+    output::Output::package_info(&name, &version, &format!("{} file(s)", file_count));
+    if key_bytes.is_some() {
+        output::Output::info("Signed with Ed25519 key");
+    }
+    output::Output::success(&format!("Package written to {}", output_path.display()));
+    Ok(())
+}
+
+async fn cmd_changelog(index: &index::Index, config: &config::Config, package: &str, verbose: bool) -> anyhow::Result<()> {
+    let mut changelog = None;
+    
+    if let Some(pkg) = index.show(package)? {
+        if let (Some(repo_id), Some(filename)) = (pkg.repo_id, pkg.filename.as_ref()) {
+            if let Some(repo_url) = index.get_repo_url(repo_id)? {
+                let downloader = create_downloader(config, 1)?;
+                let temp_file = cleanup::TempPath::new(&format!("apt-ng-changelog-{}-{}.tmp", pkg.name, pkg.version));
+                
+                for url in repo::changelog_urls(&repo_url, filename, &pkg.version) {
+                    if verbose {
+                        output::Output::info(&format!("Fetching changelog from {}", url));
+                    }
+                    let _ = std::fs::remove_file(&temp_file);
+                    match downloader.download_file(&url, &temp_file).await {
+                        Ok(()) => {
+                            changelog = std::fs::read_to_string(&temp_file).ok();
+                            let _ = std::fs::remove_file(&temp_file);
+                            if changelog.is_some() {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            if verbose {
+                                output::Output::warning(&format!("Could not fetch {}: {}", url, e));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    if changelog.is_none() {
+        changelog = read_installed_changelog(package)?;
+        if changelog.is_some() {
+            output::Output::info("Showing changelog of the installed version (could not fetch it online)");
+        }
+    }
+    
+    match changelog {
+        Some(text) => page_output(&text),
+        None => anyhow::bail!("No changelog found for '{}'", package),
+    }
+}
+
+/// Liest das Changelog eines installierten Pakets aus /usr/share/doc
+fn read_installed_changelog(package: &str) -> anyhow::Result<Option<String>> {
+    use std::io::Read;
+    
+    let doc_dir = Path::new("/usr/share/doc").join(package);
+    for name in ["changelog.Debian.gz", "changelog.gz"] {
+        let path = doc_dir.join(name);
+        if !path.exists() {
+            continue;
+        }
+        let mut text = String::new();
+        flate2::read::GzDecoder::new(std::fs::File::open(&path)?)
+            .read_to_string(&mut text)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        return Ok(Some(text));
+    }
+    Ok(None)
+}
+
+/// Gibt Text über `$PAGER` (Standard: less) aus, wenn stdout ein Terminal ist
+fn page_output(text: &str) -> anyhow::Result<()> {
+    use std::io::{IsTerminal, Write};
+    
+    if std::io::stdout().is_terminal() {
+        let pager = std::env::var("PAGER").unwrap_or_else(|_| "less -R".to_string());
+        let mut parts = pager.split_whitespace();
+        if let Some(program) = parts.next() {
+            if let Ok(mut child) = std::process::Command::new(program)
+                .args(parts)
+                .stdin(std::process::Stdio::piped())
+                .spawn()
+            {
+                if let Some(mut stdin) = child.stdin.take() {
+                    // Ein vorzeitig beendeter Pager (q) ist kein Fehler
+                    let _ = stdin.write_all(text.as_bytes());
+                }
+                child.wait()?;
+                return Ok(());
+            }
         }
     }
     
+    print!("{}", text);
     Ok(())
 }
 
-fn cmd_repo_add(index: &index::Index, url: &str) -> anyhow::Result<()> {
-    let repo = repo::Repository {
-        id: None,
-        url: url.to_string(),
-        priority: 500,
-        enabled: true,
-        last_probe_ms: None,
-        rtt_ms: None,
-        suite: None,
-        components: vec!["main".to_string()],
-    };
+fn cmd_repo_add(
+    index: &index::Index,
+    url: &str,
+    suite: Option<&str>,
+    components: Option<&str>,
+    architectures: Option<&str>,
+    signed_by: Option<&str>,
+    trusted: Option<bool>,
+) -> anyhow::Result<()> {
+    let components = components.map(repo::split_list).unwrap_or_default();
+    let architectures = architectures.map(repo::split_list).unwrap_or_default();
+    // `ppa:<owner>/<name>` wie bei add-apt-repository
+    let url = repo::expand_ppa(url).unwrap_or_else(|| url.to_string());
+    let mut repo = repo::Repository::new(&url, suite, &components, &architectures);
+    repo.signed_by = signed_by.map(str::to_string);
+    repo.trusted = trusted;
     
     repo::Repository::add_to_db(index.conn(), &repo)?;
-    output::Output::success(&format!("Added repository: {}", url));
+    output::Output::success(&format!("Added repository: {} ({} {})",
+        url, repo.suite.as_deref().unwrap_or("auto-detected suite"), repo.components.join(" ")));
+    
+    Ok(())
+}
+
+fn cmd_repo_set_enabled(index: &index::Index, url: &str, enabled: bool) -> anyhow::Result<()> {
+    if !repo::Repository::set_enabled(index.conn(), url, enabled)? {
+        anyhow::bail!("Repository not found: {}", url);
+    }
+    
+    if enabled {
+        output::Output::success(&format!("Enabled repository: {}", url));
+    } else {
+        output::Output::success(&format!("Disabled repository: {}", url));
+    }
+    
+    Ok(())
+}
+
+fn cmd_repo_list(index: &index::Index) -> anyhow::Result<()> {
+    output::Output::heading("📚 Repositories");
+    
+    let repos = repo::Repository::load_all_including_disabled(index.conn())?;
+    if repos.is_empty() {
+        output::Output::warning("No repositories configured");
+        output::Output::list_item("Add one with: apt-ng repo add <url>");
+        return Ok(());
+    }
+    
+    let mut table = output::Output::table();
+    table.set_header(vec!["URL", "Suite", "Components", "Priority", "RTT", "State"]);
+    
+    for repo in &repos {
+        let rtt = repo.rtt_ms
+            .map(|rtt| format!("{}ms", rtt))
+            .unwrap_or_else(|| "-".to_string());
+        let state = if repo.enabled {
+            comfy_table::Cell::new("enabled")
+        } else if output::Output::colors_enabled() {
+            comfy_table::Cell::new("disabled").fg(comfy_table::Color::Yellow)
+        } else {
+            comfy_table::Cell::new("disabled")
+        };
+        table.add_row(vec![
+            comfy_table::Cell::new(&repo.url),
+            comfy_table::Cell::new(repo.suite_path().unwrap_or("-")),
+            comfy_table::Cell::new(repo.components.join(" ")),
+            comfy_table::Cell::new(repo.priority),
+            comfy_table::Cell::new(rtt),
+            state,
+        ]);
+    }
+    
+    println!("{}", table);
+    let enabled = repos.iter().filter(|r| r.enabled).count();
+    output::Output::info(&format!("{} repository(s), {} enabled", repos.len(), enabled));
     
     Ok(())
 }
 
 async fn cmd_repo_update(index: &index::Index, config: &config::Config, verbose: bool) -> anyhow::Result<()> {
-    // Use jobs() which respects config file, defaults to max CPU cores
-    let jobs = config.jobs();
+    let jobs = config.effective_jobs(None).download;
     if verbose {
         output::Output::info(&format!("Using {} parallel workers", jobs));
     }
@@ -1208,24 +3013,47 @@ async fn cmd_repo_update(index: &index::Index, config: &config::Config, verbose:
     
     output::Output::info(&format!("Probing {} mirrors...", repos.len()));
     
-    let downloader = downloader::Downloader::new(jobs)?;
-    let mut mirror_stats = Vec::new();
+    let downloader = create_downloader(config, jobs)?;
+    let distro = system::detect_distro();
     
-    for repo in &repos {
-        if let Ok(stats) = downloader.probe_mirror(&repo.url).await {
-            let rtt = stats.rtt_ms;
-            let throughput = stats.throughput;
-            repo::Repository::update_probe_stats(index.conn(), &repo.url, rtt)?;
-            mirror_stats.push((repo.url.clone(), stats));
-            if verbose {
-                output::Output::success(&format!("{}: {}ms RTT, {} bytes/s throughput", 
-                    repo.url, rtt, throughput));
-            } else {
-                output::Output::success(&format!("{}: {}ms", repo.url, rtt));
-            }
-        } else {
-            output::Output::warning(&format!("Failed to probe {}", repo.url));
+    // Probes parallel ausführen, jeder mit eigenem Timeout
+    use futures::stream::{self, StreamExt};
+    let pb = output::Output::count_progress_bar(repos.len() as u64);
+    let results: Vec<_> = stream::iter(repos.iter().map(|repo| {
+        let downloader = &downloader;
+        let pb = &pb;
+        let probe_url = repo.probe_url(&distro);
+        async move {
+            let result = downloader.probe_mirror_with_timeout(&probe_url, downloader::PROBE_TIMEOUT).await;
+            pb.inc(1);
+            (repo.url.clone(), result)
         }
+    }))
+        .buffer_unordered(jobs)
+        .collect()
+        .await;
+    pb.finish_and_clear();
+    
+    let mut mirror_stats = Vec::new();
+    for (url, result) in results {
+        let stats = match result {
+            Ok(stats) => {
+                if verbose {
+                    output::Output::success(&format!("{}: {}ms RTT, {} bytes/s throughput", 
+                        url, stats.rtt_ms, stats.throughput));
+                } else {
+                    output::Output::success(&format!("{}: {}ms", url, stats.rtt_ms));
+                }
+                stats
+            }
+            Err(e) => {
+                output::Output::warning(&format!("Failed to probe {}: {}", url, e));
+                // Mit Strafwert speichern, damit der Mirror hinten einsortiert wird
+                downloader::MirrorStats::failed(&url, downloader::PROBE_TIMEOUT)
+            }
+        };
+        repo::Repository::update_probe_stats(index.conn(), &url, stats.rtt_ms, stats.throughput)?;
+        mirror_stats.push((url, stats));
     }
     
     // Sortiere Mirrors nach Score (beste zuerst)
@@ -1247,11 +3075,11 @@ fn cmd_repo_generate(
     directory: &str,
     suite: &str,
     component: &str,
-    arch: &str,
+    arch: &[String],
     key: Option<&str>,
     verbose: bool,
 ) -> anyhow::Result<()> {
-    use crate::repo_generator::{RepositoryIndexGenerator, RepositorySigner};
+    use apt_ng::repo_generator::{RepositoryIndexGenerator, RepositorySigner};
     
     output::Output::heading("📦 Generating Repository Index");
     
@@ -1262,28 +3090,24 @@ fn cmd_repo_generate(
     
     if verbose {
         output::Output::info(&format!("Scanning directory: {}", directory));
-        output::Output::info(&format!("Suite: {}, Component: {}, Arch: {}", suite, component, arch));
+        output::Output::info(&format!("Suite: {}, Component: {}", suite, component));
     }
     
-    // Create output directory structure
-    let output_dir = package_dir.join("dists").join(suite).join(component).join(format!("binary-{}", arch));
-    std::fs::create_dir_all(&output_dir)?;
-    
-    // Generate Packages file
-    let generator = RepositoryIndexGenerator::new(package_dir, suite, component, arch);
-    let packages_path = output_dir.join("Packages");
-    generator.generate_packages_file(&packages_path)?;
-    
-    if verbose {
-        output::Output::success(&format!("Generated Packages file: {:?}", packages_path));
+    // Packages-Dateien je Architektur und gemeinsame Release-Datei
+    let generator = RepositoryIndexGenerator::new(package_dir, suite, component, arch.to_vec());
+    let indices = generator.generate()?;
+    if indices.is_empty() {
+        output::Output::warning("No packages found; generated an empty Release file");
+    }
+    for arch_index in &indices {
+        output::Output::list_item(&format!("{}: {} package(s)", arch_index.arch, arch_index.package_count));
+        if verbose {
+            output::Output::info(&format!("Generated Packages file: {:?}", arch_index.path));
+        }
     }
     
-    // Generate Release file
-    let release_dir = package_dir.join("dists").join(suite);
-    std::fs::create_dir_all(&release_dir)?;
+    let release_dir = generator.release_dir();
     let release_path = release_dir.join("Release");
-    generator.generate_release_file(&packages_path, &release_path)?;
-    
     if verbose {
         output::Output::success(&format!("Generated Release file: {:?}", release_path));
     }
@@ -1303,6 +3127,32 @@ fn cmd_repo_generate(
     Ok(())
 }
 
+/// Führt `PRAGMA optimize` aus und – falls `force` oder genug Seiten frei sind – ein VACUUM
+///
+/// Liefert die Größe vor und nach dem VACUUM, None wenn keines nötig war.
+fn compact_index(index: &index::Index, force: bool) -> anyhow::Result<Option<(u64, u64)>> {
+    index.optimize()?;
+    if !force && !index.needs_vacuum()? {
+        return Ok(None);
+    }
+    
+    let before = index.size_bytes()?;
+    index.vacuum()?;
+    let after = index.size_bytes()?;
+    output::Output::info(&format!("Index compacted: {} → {}", format_size(before), format_size(after)));
+    Ok(Some((before, after)))
+}
+
+fn cmd_index_vacuum(index: &index::Index) -> anyhow::Result<()> {
+    output::Output::heading("🗜 Compacting Index");
+    
+    if let Some((before, after)) = compact_index(index, true)? {
+        output::Output::success(&format!("Index compacted, freed {}", format_size(before.saturating_sub(after))));
+    }
+    
+    Ok(())
+}
+
 fn cmd_cache_clean(config: &config::Config, clean_old: bool, max_size: Option<u64>, verbose: bool) -> anyhow::Result<()> {
     output::Output::heading("🧹 Cleaning Cache");
     
@@ -1360,17 +3210,56 @@ fn cmd_cache_clean(config: &config::Config, clean_old: bool, max_size: Option<u6
     Ok(())
 }
 
-fn cmd_security_audit(format: &str, verbose: bool) -> anyhow::Result<()> {
-    use crate::security::SecurityAudit;
-    use crate::security::SecurityReport;
+async fn cmd_security_audit(config: &config::Config, format: &str, offline: bool, verbose: bool) -> anyhow::Result<()> {
+    use apt_ng::security::SecurityAudit;
+    use apt_ng::security::SecurityReport;
+    use apt_ng::security::vulnerabilities;
     
     output::Output::heading("🔐 Security Audit");
     
+    // Sammle bekannte Schwachstellen der installierten Pakete
+    let mut extra_checks = Vec::new();
+    match vulnerabilities::installed_system_packages() {
+        Ok(packages) => {
+            // Debian-Advisories gelten für Quellpakete
+            let sources = vulnerabilities::source_packages(&packages);
+            let snapshot_path = config.advisory_snapshot_path();
+            let scan = if offline {
+                if !snapshot_path.exists() {
+                    output::Output::warning("No cached advisory snapshot found. Run 'apt-ng security audit' online first.");
+                }
+                vulnerabilities::load_snapshot(&snapshot_path, &sources)?
+            } else {
+                if verbose {
+                    output::Output::info(&format!("Querying OSV.dev for {} source package(s) of {} installed package(s)...",
+                        sources.len(), packages.len()));
+                }
+                match vulnerabilities::VulnerabilityScanner::new()?.scan(&sources).await {
+                    Ok(advisories) => {
+                        if let Err(e) = vulnerabilities::save_snapshot(&snapshot_path, &sources, &advisories) {
+                            output::Output::warning(&format!("Could not save advisory snapshot: {}", e));
+                        }
+                        vulnerabilities::SnapshotScan { advisories, unscanned: Vec::new() }
+                    }
+                    Err(e) => {
+                        output::Output::warning(&format!("Vulnerability lookup failed: {}. Using cached advisory snapshot.", e));
+                        vulnerabilities::load_snapshot(&snapshot_path, &sources)?
+                    }
+                }
+            }.for_binaries(&packages);
+            let scanned = packages.len() - scan.unscanned.len();
+            extra_checks.extend(vulnerabilities::to_check_results(&scan.advisories, scanned, &scan.unscanned));
+        }
+        Err(e) => {
+            output::Output::warning(&format!("Skipping vulnerability scan: {}", e));
+        }
+    }
+    
     if verbose {
         output::Output::info("Running security checks...");
     }
     
-    let result = SecurityAudit::run()?;
+    let result = SecurityAudit::run_with(extra_checks)?;
     
     match format {
         "json" => {
@@ -1396,32 +3285,396 @@ fn cmd_security_audit(format: &str, verbose: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
-/// Check for updates in background and display message if available
-/// Returns a handle that can be awaited (though we don't wait for it to complete)
-fn check_for_updates_background() -> tokio::task::JoinHandle<()> {
-    // Spawn a background task to check for updates
-    tokio::spawn(async {
-        // Small delay to ensure main command output is shown first
-        tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
-        
-        if let Ok(updater) = self_update::SelfUpdater::new() {
-            if let Some(update_available) = updater.quick_check_update_available().await {
-                if update_available {
-                    // Use eprintln! to stderr to ensure message is shown even if stdout is redirected
-                    // Also flush to ensure message appears immediately
-                    eprintln!();
-                    eprintln!("⚠️  A new version of apt-ng is available!");
-                    eprintln!("   Run 'apt-ng self-update' to update.");
-                    eprintln!();
-                    use std::io::Write;
-                    let _ = std::io::stderr().flush();
-                }
+fn cmd_key_list(config: &config::Config) -> anyhow::Result<()> {
+    output::Output::heading("🔑 Trusted Keys");
+    
+    let mut verifier = verifier::PackageVerifier::new(config.trusted_keys_dir())?;
+    verifier.load_openpgp_keyrings(&config.keyring_paths());
+    let keys = verifier.trusted_key_info();
+    
+    if verifier.trusted_key_count() == 0 {
+        output::Output::warning("No trusted keys found");
+        output::Output::list_item(&format!(
+            "Add ed25519 public keys (*.pub) or OpenPGP keyrings (*.gpg, *.asc) to: {}",
+            config.trusted_keys_dir().display()
+        ));
+        return Ok(());
+    }
+    
+    let now = chrono::Utc::now();
+    let mut table = output::Output::table();
+    table.set_header(vec!["Name", "Fingerprint", "Expires", "Status"]);
+    let expires_cell = |expires: Option<chrono::DateTime<chrono::Utc>>| {
+        comfy_table::Cell::new(expires
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "never".to_string()))
+    };
+    let status_cell = |status: &str| {
+        if status != "valid" && output::Output::colors_enabled() {
+            comfy_table::Cell::new(status).fg(comfy_table::Color::Red)
+        } else {
+            comfy_table::Cell::new(status)
+        }
+    };
+    
+    for key in keys {
+        table.add_row(vec![
+            comfy_table::Cell::new(&key.name),
+            comfy_table::Cell::new(key.fingerprint()),
+            expires_cell(key.expires),
+            status_cell(if key.is_expired_at(now) { "expired" } else { "valid" }),
+        ]);
+    }
+    
+    // OpenPGP-Schlüssel (apt-Schlüsselbunde) prüfen nur Repository-Signaturen
+    for key in verifier.openpgp_keys() {
+        let name = key.user_id.clone().unwrap_or_else(|| "(no user id)".to_string());
+        let status = if key.revoked {
+            "revoked"
+        } else if key.is_expired_at(now) {
+            "expired"
+        } else {
+            "valid"
+        };
+        table.add_row(vec![
+            comfy_table::Cell::new(if key.is_subkey { format!("{} (subkey)", name) } else { name }),
+            comfy_table::Cell::new(hex::encode_upper(key.key_id())),
+            expires_cell(key.expires),
+            status_cell(status),
+        ]);
+    }
+    
+    println!("{}", table);
+    output::Output::info(&format!("{} trusted key(s)", verifier.trusted_key_count()));
+    
+    Ok(())
+}
+
+/// Listet die festgehaltenen Operationen, neueste zuerst; mit `verbose` samt Paketen
+fn cmd_history_list(index: &index::Index, limit: usize, verbose: bool) -> anyhow::Result<()> {
+    output::Output::heading("📜 History");
+    
+    let entries = index.history(limit)?;
+    if entries.is_empty() {
+        output::Output::info("No operations recorded yet.");
+        return Ok(());
+    }
+    
+    let mut table = output::Output::table();
+    table.set_header(vec!["ID", "Date", "Command", "Changes", "Result"]);
+    for entry in &entries {
+        let date = chrono::DateTime::from_timestamp(entry.timestamp, 0)
+            .map(|date| date.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for change in &entry.changes {
+            match counts.iter_mut().find(|(action, _)| *action == change.action()) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((change.action(), 1)),
+            }
+        }
+        let changes = counts.iter()
+            .map(|(action, count)| format!("{} {}", count, action))
+            .collect::<Vec<_>>()
+            .join(", ");
+        table.add_row(vec![
+            entry.id.map(|id| id.to_string()).unwrap_or_default(),
+            date,
+            entry.command_line.clone(),
+            changes,
+            if entry.succeeded() { "ok".to_string() } else { "failed".to_string() },
+        ]);
+    }
+    println!("{}", table);
+    
+    if verbose {
+        for entry in &entries {
+            output::Output::section(&format!("#{} {}", entry.id.unwrap_or_default(), entry.command_line));
+            for change in &entry.changes {
+                let versions = match (&change.old_version, &change.new_version) {
+                    (Some(old), Some(new)) => format!("{} -> {}", old, new),
+                    (old, new) => old.clone().or(new.clone()).unwrap_or_default(),
+                };
+                output::Output::list_item(&format!("{} {} {}", change.action(), change.name, versions));
+            }
+            if let Some(error) = &entry.error {
+                output::Output::list_item(&format!("error: {}", error));
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Macht eine Operation rückgängig, soweit möglich: Neu installierte Pakete werden entfernt
+///
+/// Upgrades und Entfernungen lassen sich nicht zurückdrehen und werden nur gemeldet.
+async fn cmd_history_undo(index: &index::Index, id: Option<i64>, dry_run: bool, verbose: bool) -> anyhow::Result<()> {
+    output::Output::heading("↩ Undoing Operation");
+    
+    let entry = match id {
+        Some(id) => index.history_entry(id)?
+            .ok_or_else(|| anyhow::anyhow!("No operation #{} in the history", id))?,
+        None => index.history(1)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("No operations recorded yet"))?,
+    };
+    output::Output::info(&format!("Undoing #{}: {}", entry.id.unwrap_or_default(), entry.command_line));
+    
+    for change in entry.irreversible_changes() {
+        output::Output::warning(&format!(
+            "Cannot undo {} of {} ({} -> {})",
+            change.action(),
+            change.name,
+            change.old_version.as_deref().unwrap_or("-"),
+            change.new_version.as_deref().unwrap_or("-")
+        ));
+    }
+    
+    // Nur Pakete, die noch in der damals installierten Version vorhanden sind
+    let installed = installed_versions(index)?;
+    let to_remove: Vec<String> = entry.changes.iter()
+        .filter(|change| entry.added_packages().contains(&change.name.as_str()))
+        .filter(|change| installed.get(&change.name) == change.new_version.as_ref())
+        .map(|change| change.name.clone())
+        .collect();
+    if to_remove.is_empty() {
+        output::Output::info("Nothing to remove.");
+        return Ok(());
+    }
+    
+    output::Output::section("📋 Packages to remove:");
+    for name in &to_remove {
+        output::Output::list_item(name);
+    }
+    cmd_remove(index, &to_remove, dry_run, verbose).await
+}
+
+fn cmd_stats(index: &index::Index, config: &config::Config) -> anyhow::Result<()> {
+    output::Output::heading("📊 Statistics");
+    
+    output::Output::section("📦 Packages");
+    output::Output::info(&format!("Indexed: {}", index.package_count()?));
+    output::Output::info(&format!("Installed: {}", index.installed_count()?));
+    let db_path = config.index_db_path();
+    let db_size = std::fs::metadata(&db_path).map(|metadata| metadata.len()).unwrap_or(0);
+    output::Output::info(&format!("Index database: {} ({})", format_size(db_size), db_path.display()));
+    
+    output::Output::section("💾 Cache");
+    let usage = cache::Cache::new(config.cache_path())?.usage()?;
+    output::Output::info(&format!("Size: {} ({})", format_size(usage.size), config.cache_path().display()));
+    output::Output::info(&format!("Saved by hardlinks: {}", format_size(usage.hardlink_savings())));
+    let savings = index.delta_savings()?;
+    if savings.is_empty() {
+        output::Output::info("Delta updates: none used yet");
+    } else {
+        output::Output::info(&format!("Delta updates: {} saved across {} package(s)",
+            format_size(savings.bytes_saved), savings.packages));
+    }
+    
+    output::Output::section("🌐 Repositories");
+    let repos = repo::Repository::load_all_including_disabled(index.conn())?;
+    let enabled: Vec<&repo::Repository> = repos.iter().filter(|r| r.enabled).collect();
+    output::Output::info(&format!("Configured: {} ({} enabled)", repos.len(), enabled.len()));
+    
+    // Schnellster Mirror je Basis-URL, wie ihn die Download-Auswahl verwendet
+    let mut bases: Vec<&str> = enabled.iter().map(|r| repo::base_url(&r.url)).collect();
+    bases.sort();
+    bases.dedup();
+    for base in bases {
+        if let Some(best) = repo::Repository::select_best_mirror(index.conn(), base)? {
+            let stats = match best.rtt_ms {
+                Some(rtt_ms) => format!("{} ms, {}/s", rtt_ms, format_size(best.throughput_bps.unwrap_or(0))),
+                None => "not probed yet".to_string(),
+            };
+            let served_by = index.get_meta(&best.url, index::RESOLVED_HOST_META_KEY)?
+                .map(|host| format!(", served by {}", host))
+                .unwrap_or_default();
+            output::Output::list_item(&format!("{}: fastest mirror {} ({}{})", base, best.url, stats, served_by));
+        }
+    }
+    
+    Ok(())
+}
+
+fn cmd_config_list(config: &config::Config, requested_jobs: Option<usize>) -> anyhow::Result<()> {
+    output::Output::heading("⚙ Effective Configuration");
+    
+    let path = config::Config::default_path();
+    if path.exists() {
+        output::Output::info(&format!("Config file: {}", path.display()));
+    } else {
+        output::Output::info(&format!("Config file: {} (not found, using defaults)", path.display()));
+    }
+    
+    for (key, value) in config.effective_settings(requested_jobs)? {
+        println!("{} = {}", key, value);
+    }
+    
+    Ok(())
+}
+
+/// Gibt eine Einstellung aus; Zeichenketten ohne TOML-Anführungszeichen, damit sich
+/// die Ausgabe in Skripten verwenden lässt. Ein Abschnitt (`paths`) listet alle Schlüssel darunter.
+fn cmd_config_get(config: &config::Config, requested_jobs: Option<usize>, key: &str) -> anyhow::Result<()> {
+    let settings = config.effective_settings(requested_jobs)?;
+    
+    if let Some((_, value)) = settings.iter().find(|(name, _)| name == key) {
+        match toml::from_str::<toml::Table>(&format!("value = {}", value)).ok().and_then(|t| t.get("value").cloned()) {
+            Some(toml::Value::String(text)) => println!("{}", text),
+            _ => println!("{}", value),
+        }
+        return Ok(());
+    }
+    
+    let section: Vec<_> = settings.iter()
+        .filter(|(name, _)| name.strip_prefix(key).is_some_and(|rest| rest.starts_with('.') || rest.starts_with('[')))
+        .collect();
+    if section.is_empty() {
+        return Err(anyhow::anyhow!("Unknown config key: {} (see `apt-ng config list`)", key));
+    }
+    for (name, value) in section {
+        println!("{} = {}", name, value);
+    }
+    
+    Ok(())
+}
+
+/// Creates the installer, sandboxing maintainer scripts unless disabled
+/// Erstellt einen Downloader mit Zugangsdaten und Zeitlimits aus der Konfiguration
+fn create_downloader(config: &config::Config, jobs: usize) -> anyhow::Result<downloader::Downloader> {
+    Ok(downloader::Downloader::new(jobs.clamp(1, config.job_cap()))?
+        .with_auth(config.repo_auth())
+        .with_timeouts(config.download_timeouts())?
+        .with_redirects(config.max_redirects())?
+        .with_offline(config.offline()))
+}
+
+fn create_installer(config: &config::Config, jobs: usize, no_sandbox: bool, verbose: bool) -> installer::Installer {
+    let sandbox_config = if no_sandbox {
+        if verbose {
+            output::Output::info("Maintainer scripts will run without sandbox (--no-sandbox)");
+        }
+        None
+    } else {
+        match config.sandbox_config() {
+            Some(sandbox_config) if sandbox::Sandbox::check_bubblewrap_available() => Some(sandbox_config),
+            Some(_) => {
+                output::Output::warning("bubblewrap (bwrap) is not installed. Maintainer scripts will run without sandbox.");
+                output::Output::list_item("Install bubblewrap or pass --no-sandbox to silence this warning.");
+                None
+            }
+            None => None,
+        }
+    };
+    
+    installer::Installer::new_with_sandbox(jobs, &config.paths.install_root, sandbox_config)
+}
+
+/// Fragt eine Ja/Nein-Bestätigung ab (Standard: nein); ohne Terminal gilt immer nein
+fn confirm(prompt: &str) -> anyhow::Result<bool> {
+    if !atty::is(atty::Stream::Stdin) {
+        return Ok(false);
+    }
+    
+    print!("{} [y/N] ", prompt);
+    use std::io::Write;
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Checks for a transaction journal left behind by an interrupted installation
+/// and lets the user roll it back or keep the already installed files
+fn recover_incomplete_transaction(config: &config::Config) -> anyhow::Result<()> {
+    let journal_path = config.transaction_journal_path();
+    if !journal_path.exists() {
+        return Ok(());
+    }
+    
+    let transaction = installer::InstallationTransaction::load_journal(&journal_path)?;
+    
+    output::Output::warning("An interrupted installation was detected.");
+    output::Output::list_item(&format!("{} file(s) installed, {} backup(s) taken",
+        transaction.installed_file_count(), transaction.backup_count()));
+    
+    if !atty::is(atty::Stream::Stdin) {
+        output::Output::info(&format!("Run apt-ng interactively to roll it back, or remove {} to keep the files.",
+            journal_path.display()));
+        return Ok(());
+    }
+    
+    print!("Roll back [r], complete [c] or skip [s]? [R/c/s] ");
+    use std::io::Write;
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    
+    match answer.trim().to_lowercase().as_str() {
+        "" | "r" | "rollback" => {
+            transaction.rollback()?;
+            output::Output::success("Interrupted installation rolled back");
+        }
+        "c" | "complete" => {
+            transaction.commit()?;
+            output::Output::success("Interrupted installation completed, backups discarded");
+        }
+        _ => {
+            output::Output::info("Leaving the interrupted installation untouched");
+        }
+    }
+    
+    Ok(())
+}
+
+/// Ob nach diesem Befehl auf neue apt-ng-Versionen hingewiesen werden soll
+///
+/// Nicht bei `self-update`, im Offline-Modus, mit `--no-update-check`, `update_check = false` oder
+/// `APT_NG_NO_UPDATE_CHECK`, ohne Terminal (Skripte, Cron) und bei `--quiet`.
+fn update_notice_wanted(opts: &cli::Cli, config: &config::Config) -> bool {
+    !matches!(opts.command, Commands::SelfUpdate { .. })
+        && !config.offline()
+        && !opts.no_update_check
+        && config.update_check()
+        && std::env::var_os(self_update::NO_UPDATE_CHECK_ENV).is_none()
+        && atty::is(atty::Stream::Stderr)
+        && output::Output::shows(output::Verbosity::Normal)
+}
+
+/// Ob parallel zum Befehl nach neuen apt-ng-Versionen gesucht werden soll
+///
+/// Wie `update_notice_wanted`, aber nur, wenn die letzte Suche mindestens einen Tag zurückliegt.
+fn update_check_wanted(opts: &cli::Cli, config: &config::Config) -> bool {
+    update_notice_wanted(opts, config)
+        && self_update::SelfUpdater::update_check_due(&config.update_check_stamp_path(), std::time::SystemTime::now())
+}
+
+/// Sucht im Hintergrund nach einem neueren Release und merkt sich dessen Tag
+///
+/// Die Suche wird sofort vermerkt, damit auch eine fehlgeschlagene erst am nächsten Tag
+/// wiederholt wird.
+fn check_for_updates_background(config: &config::Config) -> tokio::task::JoinHandle<()> {
+    if let Err(e) = self_update::SelfUpdater::record_update_check(&config.update_check_stamp_path(), std::time::SystemTime::now()) {
+        tracing::debug!(error = %e, "could not record update check");
+    }
+    
+    let notice_path = config.update_notice_path();
+    tokio::spawn(async move {
+        let Ok(updater) = self_update::SelfUpdater::new() else {
+            return;
+        };
+        if let Some(tag) = updater.quick_check_update_available().await {
+            if let Err(e) = self_update::SelfUpdater::record_update_notice(&notice_path, &tag) {
+                tracing::debug!(error = %e, "could not record available update");
             }
         }
     })
 }
 
-async fn cmd_self_update(force: bool, verbose: bool) -> anyhow::Result<()> {
+async fn cmd_self_update(force: bool, allow_unsigned_update: bool, dry_run: bool, verbose: bool) -> anyhow::Result<()> {
     output::Output::heading("🔄 Checking for Updates");
     
     let updater = self_update::SelfUpdater::new()?;
@@ -1465,79 +3718,93 @@ async fn cmd_self_update(force: bool, verbose: bool) -> anyhow::Result<()> {
                 .join(", ")
         ))?;
     
-    // Check SHA256 checksum first
-    let update_needed = if let Some(latest_checksum) = updater.get_latest_binary_checksum(asset).await? {
-        if verbose {
-            output::Output::info(&format!("Latest binary SHA256: {}", &latest_checksum[..16]));
+    // Ob aktualisiert wird, entscheidet die Version; die Prüfsumme gilt dem Archiv, nicht dem Binary
+    match self_update::SelfUpdater::compare_versions(&current_version, latest_version) {
+        std::cmp::Ordering::Less => {
+            output::Output::section(&format!("Update available: {} -> {}", current_version, latest_version));
+        }
+        std::cmp::Ordering::Equal if force => {
+            output::Output::info("Already on latest version, but --force specified. Updating anyway...");
+        }
+        std::cmp::Ordering::Equal => {
+            output::Output::success("Already on latest version!");
+            return Ok(());
         }
-        let needs_update = current_checksum != latest_checksum;
-        if needs_update {
-            output::Output::section(&format!(
-                "Update available: {} -> {} (SHA256 differs)",
+        std::cmp::Ordering::Greater if force => {
+            output::Output::warning(&format!(
+                "Current version ({}) is newer than latest release ({}). --force specified, updating anyway...",
+                current_version,
+                latest_version
+            ));
+        }
+        std::cmp::Ordering::Greater => {
+            output::Output::info(&format!(
+                "Current version ({}) is newer than latest release ({}). No update needed.",
                 current_version,
                 latest_version
             ));
-        } else if force {
-            output::Output::info("Binary checksums match, but --force specified. Updating anyway...");
-        } else {
-            output::Output::success("Already on latest version (SHA256 matches)!");
             return Ok(());
         }
-        needs_update || force
-    } else {
-        // Fallback to version comparison if checksum not available
-        let comparison = self_update::SelfUpdater::compare_versions(&current_version, latest_version);
-        
-        match comparison {
-            std::cmp::Ordering::Less => {
-                output::Output::section(&format!(
-                    "Update available: {} -> {}",
-                    current_version,
-                    latest_version
-                ));
-                true
-            }
-            std::cmp::Ordering::Equal => {
-                if force {
-                    output::Output::info("Already on latest version, but --force specified. Updating anyway...");
-                    true
-                } else {
-                    output::Output::success("Already on latest version!");
-                    return Ok(());
-                }
-            }
-            std::cmp::Ordering::Greater => {
-                if force {
-                    output::Output::warning(&format!(
-                        "Current version ({}) is newer than latest release ({}). --force specified, updating anyway...",
-                        current_version,
-                        latest_version
-                    ));
-                    true
-                } else {
-                    output::Output::info(&format!(
-                        "Current version ({}) is newer than latest release ({}). No update needed.",
-                        current_version,
-                        latest_version
-                    ));
-                    return Ok(());
-                }
-            }
+    }
+    
+    let latest_checksum = updater.get_latest_binary_checksum(asset).await?;
+    if verbose {
+        if let Some(latest_checksum) = &latest_checksum {
+            output::Output::info(&format!("Release asset SHA256: {}", &latest_checksum[..16]));
         }
+    }
+    
+    output::Output::info(&format!("Found binary: {} ({})", asset.name, format_size(asset.size)));
+    
+    // Ohne veröffentlichte Prüfsumme lässt sich der Download nicht prüfen
+    let Some(latest_checksum) = latest_checksum else {
+        anyhow::bail!("The release publishes no SHA256 checksum for {}; refusing to install an unverified binary", asset.name);
+    };
+    
+    // Signatur mit dem eingebetteten Release-Schlüssel; nur explizit darf sie fehlen
+    let signature_check = if allow_unsigned_update {
+        output::Output::warning("Skipping release signature verification (--allow-unsigned-update)");
+        None
+    } else {
+        let verifier = self_update::SelfUpdater::release_verifier()
+            .context("Cannot verify the release signature; use --allow-unsigned-update to install anyway")?;
+        let signature_asset = updater.find_signature_asset(&release, asset)
+            .ok_or_else(|| anyhow::anyhow!(
+                "The release publishes no signature ({}.sig); use --allow-unsigned-update to install anyway", asset.name))?;
+        Some((verifier, signature_asset))
     };
     
-    if !update_needed && !force {
+    if dry_run {
+        output::Output::info(&format!(
+            "Dry run: would download {}, verify SHA256 {} and replace {}",
+            asset.name,
+            &latest_checksum[..16],
+            self_update::SelfUpdater::get_current_binary_path()?.display()
+        ));
         return Ok(());
     }
     
-    output::Output::info(&format!("Found binary: {} ({})", asset.name, format_size(asset.size)));
-    
     // Download binary
-    let temp_dir = std::env::temp_dir();
+    let temp_dir = cleanup::temp_dir();
     let archive_path = temp_dir.join(&asset.name);
     let binary_path = temp_dir.join("apt-ng-new");
     
     updater.download_binary(asset, &archive_path, verbose).await?;
+    if let Err(e) = self_update::SelfUpdater::verify_checksum(&archive_path, &latest_checksum) {
+        let _ = std::fs::remove_file(&archive_path);
+        return Err(e.context(format!("Downloaded {} does not match the release checksum", asset.name)));
+    }
+    if verbose {
+        output::Output::info("SHA256 checksum verified");
+    }
+    if let Some((verifier, signature_asset)) = &signature_check {
+        let signature = updater.download_asset_bytes(signature_asset).await?;
+        if let Err(e) = self_update::SelfUpdater::verify_signature(verifier, &archive_path, &signature) {
+            let _ = std::fs::remove_file(&archive_path);
+            return Err(e.context(format!("Release signature of {} is not valid; the update was rejected", asset.name)));
+        }
+        output::Output::info("Release signature verified");
+    }
     
     // Extract if needed
     if asset.name.ends_with(".tar.gz") || asset.name.ends_with(".tgz") {
@@ -1552,6 +3819,13 @@ async fn cmd_self_update(force: bool, verbose: bool) -> anyhow::Result<()> {
         std::fs::rename(&archive_path, &binary_path)?;
     }
     
+    // Ein identisches Binary muss nicht ersetzt werden
+    if self_update::SelfUpdater::file_checksum(&binary_path)? == current_checksum {
+        let _ = std::fs::remove_file(&binary_path);
+        output::Output::success(&format!("apt-ng {} is already installed (identical binary)", latest_version));
+        return Ok(());
+    }
+    
     // Install binary
     updater.install_binary(&binary_path, verbose)?;
     
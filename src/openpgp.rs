@@ -0,0 +1,937 @@
+//! Minimaler OpenPGP-Leser für apt-Schlüsselbunde und Repository-Signaturen
+//!
+//! Unterstützt werden v4-Schlüssel (RSA und EdDSA/Ed25519) aus binären (`*.gpg`) und
+//! ASCII-armored (`*.asc`) Schlüsselbunden sowie v4-Signaturen über SHA-2 – also das,
+//! womit Debian- und Ubuntu-Repositories `InRelease` und `Release.gpg` signieren.
+//! Zum Signieren eigener Repositories (`apt-ng repo generate --key`) werden außerdem
+//! EdDSA-Signaturen mit rohen Ed25519-Schlüsseln erzeugt.
+
+use crate::error::{AptNgError, Result};
+use crate::verifier::SignatureError;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use rsa::{BigUint, Pkcs1v15Sign, RsaPublicKey};
+use sha1::Sha1;
+use sha2::{Digest, Sha224, Sha256, Sha384, Sha512};
+
+const TAG_SIGNATURE: u8 = 2;
+const TAG_PUBLIC_KEY: u8 = 6;
+const TAG_USER_ID: u8 = 13;
+const TAG_PUBLIC_SUBKEY: u8 = 14;
+
+/// Signaturtypen über Schlüssel (RFC 4880, 5.2.1)
+const SIG_SUBKEY_BINDING: u8 = 0x18;
+const SIG_DIRECT_KEY: u8 = 0x1F;
+const SIG_KEY_REVOCATION: u8 = 0x20;
+const SIG_SUBKEY_REVOCATION: u8 = 0x28;
+
+const ALGO_RSA: u8 = 1;
+const ALGO_RSA_SIGN_ONLY: u8 = 3;
+const ALGO_EDDSA: u8 = 22;
+
+/// OID der Kurve Ed25519 (1.3.6.1.4.1.11591.15.1)
+const ED25519_OID: &[u8] = &[0x2B, 0x06, 0x01, 0x04, 0x01, 0xDA, 0x47, 0x0F, 0x01];
+
+/// Größte akzeptierte RSA-Schlüssellänge in Bit
+const MAX_RSA_BITS: usize = 16384;
+
+/// Erstellungszeitpunkt im Schlüsselpaket roher Ed25519-Schlüssel (`*.pub`)
+///
+/// Rohe Schlüssel haben keinen Erstellungszeitpunkt; mit festem Wert ergibt jeder Schlüssel
+/// genau einen Fingerprint, den Signierer und Verifier gleich berechnen.
+const RAW_KEY_CREATED: u32 = 0;
+
+/// Hash-Verfahren eigener Signaturen (SHA-512)
+const HASH_SHA512: u8 = 10;
+
+/// Öffentliches Schlüsselmaterial eines OpenPGP-Schlüssels
+#[derive(Debug, Clone)]
+pub enum KeyMaterial {
+    Rsa(RsaPublicKey),
+    Ed25519(ed25519_dalek::VerifyingKey),
+    /// Algorithmus wird nicht unterstützt; Signaturen dieses Schlüssels schlagen fehl
+    Unsupported(u8),
+}
+
+/// Ein (Unter-)Schlüssel aus einem OpenPGP-Schlüsselbund
+#[derive(Debug, Clone)]
+pub struct OpenPgpKey {
+    /// v4-Fingerprint (SHA-1 über das Schlüsselpaket)
+    pub fingerprint: [u8; 20],
+    /// Erste User-ID des Hauptschlüssels
+    pub user_id: Option<String>,
+    pub is_subkey: bool,
+    pub material: KeyMaterial,
+    /// Erstellungszeitpunkt aus dem Schlüsselpaket
+    pub created: DateTime<Utc>,
+    /// Ablaufzeitpunkt laut jüngster gültiger Selbst- bzw. Bindungssignatur (Subpaket 9)
+    pub expires: Option<DateTime<Utc>>,
+    /// Durch eine Widerrufssignatur des Hauptschlüssels zurückgezogen (0x20 bzw. 0x28)
+    pub revoked: bool,
+    /// Inhalt des Schlüsselpakets, über den Selbstsignaturen gebildet werden
+    packet: Vec<u8>,
+}
+
+impl OpenPgpKey {
+    /// Key-ID (letzte 8 Bytes des Fingerprints)
+    pub fn key_id(&self) -> [u8; 8] {
+        let mut key_id = [0u8; 8];
+        key_id.copy_from_slice(&self.fingerprint[12..]);
+        key_id
+    }
+
+    /// Fingerprint in der von gpg gewohnten Schreibweise (40 Hex-Zeichen, Großbuchstaben)
+    pub fn fingerprint_hex(&self) -> String {
+        hex::encode_upper(self.fingerprint)
+    }
+
+    /// Prüft, ob der Schlüssel zum angegebenen Zeitpunkt abgelaufen ist
+    pub fn is_expired_at(&self, time: DateTime<Utc>) -> bool {
+        self.expires.is_some_and(|expires| expires <= time)
+    }
+
+    /// Schlüsselpaket, wie es in Selbstsignaturen gehasht wird (0x99, Länge, Inhalt)
+    fn hashed_packet(&self) -> Vec<u8> {
+        let mut data = vec![0x99];
+        data.extend_from_slice(&(self.packet.len() as u16).to_be_bytes());
+        data.extend_from_slice(&self.packet);
+        data
+    }
+}
+
+/// Eine v4-Signatur über ein Dokument (binär oder Text)
+#[derive(Debug, Clone)]
+pub struct OpenPgpSignature {
+    pub sig_type: u8,
+    pub pubkey_algo: u8,
+    pub hash_algo: u8,
+    /// Key-ID des Ausstellers (Subpaket 16)
+    pub issuer: Option<[u8; 8]>,
+    /// Fingerprint des Ausstellers (Subpaket 33)
+    pub issuer_fingerprint: Option<[u8; 20]>,
+    /// Erstellungszeitpunkt (Subpaket 2, nur gehashte)
+    pub created: Option<DateTime<Utc>>,
+    /// Gültigkeit des Schlüssels in Sekunden ab seiner Erstellung (Subpaket 9, nur gehashte)
+    pub key_expiry: Option<u32>,
+    /// Signierter Teil des Pakets: Version bis Ende der gehashten Subpakete
+    hashed: Vec<u8>,
+    hash_prefix: [u8; 2],
+    values: Vec<Vec<u8>>,
+}
+
+impl OpenPgpSignature {
+    /// Prüft, ob die Signatur vom angegebenen Schlüssel stammen kann (laut Aussteller-Angabe)
+    pub fn may_be_from(&self, key: &OpenPgpKey) -> bool {
+        match (self.issuer_fingerprint, self.issuer) {
+            (Some(fingerprint), _) => fingerprint == key.fingerprint,
+            (None, Some(key_id)) => key_id == key.key_id(),
+            (None, None) => true,
+        }
+    }
+
+    /// Verifiziert die Signatur über `data` mit `key`
+    ///
+    /// Bei Text-Signaturen (Typ 0x01) werden Zeilenenden vorher auf CRLF normalisiert.
+    pub fn verify(&self, key: &OpenPgpKey, data: &[u8]) -> bool {
+        if self.sig_type != 0x00 && self.sig_type != 0x01 {
+            return false;
+        }
+        let canonical;
+        let data = if self.sig_type == 0x01 {
+            canonical = canonical_text(data);
+            &canonical[..]
+        } else {
+            data
+        };
+        self.verify_digest(key, data)
+    }
+
+    /// Verifiziert eine Signatur über Schlüssel- und User-ID-Pakete (Zertifizierung,
+    /// Bindung, Widerruf); `signed` ist deren gehashte Darstellung
+    fn verify_key_signature(&self, key: &OpenPgpKey, signed: &[u8]) -> bool {
+        self.may_be_from(key) && self.verify_digest(key, signed)
+    }
+
+    fn verify_digest(&self, key: &OpenPgpKey, data: &[u8]) -> bool {
+        // Ohne signierten Erstellungszeitpunkt lässt sich die Gültigkeit nicht prüfen (RFC 4880 5.2.3.4)
+        if self.created.is_none() {
+            return false;
+        }
+        let Some(digest) = self.digest(data) else {
+            return false;
+        };
+        if digest[..2] != self.hash_prefix {
+            return false;
+        }
+
+        match (&key.material, self.pubkey_algo) {
+            (KeyMaterial::Rsa(public), ALGO_RSA | ALGO_RSA_SIGN_ONLY) => {
+                let scheme = match self.hash_algo {
+                    8 => Pkcs1v15Sign::new::<Sha256>(),
+                    9 => Pkcs1v15Sign::new::<Sha384>(),
+                    10 => Pkcs1v15Sign::new::<Sha512>(),
+                    11 => Pkcs1v15Sign::new::<Sha224>(),
+                    _ => return false,
+                };
+                let Some(value) = self.values.first() else {
+                    return false;
+                };
+                // Das MPI verliert führende Nullen; RSA erwartet die volle Modulus-Länge
+                let size = rsa::traits::PublicKeyParts::size(public);
+                if value.len() > size {
+                    return false;
+                }
+                let mut signature = vec![0u8; size - value.len()];
+                signature.extend_from_slice(value);
+                public.verify(scheme, &digest, &signature).is_ok()
+            }
+            (KeyMaterial::Ed25519(public), ALGO_EDDSA) => {
+                use ed25519_dalek::Verifier;
+
+                let [r, s] = &self.values[..] else {
+                    return false;
+                };
+                if r.len() > 32 || s.len() > 32 {
+                    return false;
+                }
+                let mut bytes = [0u8; 64];
+                bytes[32 - r.len()..32].copy_from_slice(r);
+                bytes[64 - s.len()..].copy_from_slice(s);
+                public.verify(&digest, &ed25519_dalek::Signature::from_bytes(&bytes)).is_ok()
+            }
+            _ => false,
+        }
+    }
+
+    /// Hash über Daten und Signatur-Trailer; None bei unbekanntem Verfahren
+    fn digest(&self, data: &[u8]) -> Option<Vec<u8>> {
+        signature_digest(self.hash_algo, &self.hashed, data)
+    }
+}
+
+/// Hash über Daten und Signatur-Trailer (RFC 4880, 5.2.4); None bei unbekanntem Verfahren
+fn signature_digest(hash_algo: u8, hashed: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    fn hash<D: Digest>(data: &[u8], hashed: &[u8]) -> Vec<u8> {
+        let mut hasher = D::new();
+        hasher.update(data);
+        hasher.update(hashed);
+        hasher.update([4, 0xff]);
+        hasher.update((hashed.len() as u32).to_be_bytes());
+        hasher.finalize().to_vec()
+    }
+
+    // SHA-1 und MD5 werden (wie von apt) nicht mehr akzeptiert
+    match hash_algo {
+        8 => Some(hash::<Sha256>(data, hashed)),
+        9 => Some(hash::<Sha384>(data, hashed)),
+        10 => Some(hash::<Sha512>(data, hashed)),
+        11 => Some(hash::<Sha224>(data, hashed)),
+        _ => None,
+    }
+}
+
+/// Signierter Text und Signaturen einer clearsigned Nachricht (z.B. InRelease)
+#[derive(Debug, Clone)]
+pub struct ClearSigned {
+    /// Signierter Text: Dash-Escaping entfernt, Leerzeichen am Zeilenende gekürzt, LF-Zeilenenden
+    pub text: String,
+    pub signatures: Vec<OpenPgpSignature>,
+}
+
+impl ClearSigned {
+    /// Daten, über die die Signatur gebildet wurde (ohne das letzte Zeilenende)
+    pub fn signed_data(&self) -> &[u8] {
+        self.text.strip_suffix('\n').unwrap_or(&self.text).as_bytes()
+    }
+}
+
+/// Liest alle unterstützten Schlüssel aus einem Schlüsselbund (binär oder ASCII-armored)
+pub fn parse_keyring(data: &[u8]) -> Result<Vec<OpenPgpKey>> {
+    if data.len() >= 12 && &data[8..12] == b"KBXf" {
+        return Err(AptNgError::InvalidKey(
+            "GnuPG keybox files are not supported; export the keys with `gpg --export`".to_string(),
+        ));
+    }
+
+    let packets = parse_packets(&dearmor(data).map_err(AptNgError::InvalidKey)?)
+        .map_err(AptNgError::InvalidKey)?;
+
+    let mut keys = Vec::new();
+    let mut block: Option<KeyBlock> = None;
+    for (tag, body) in packets {
+        match tag {
+            TAG_PUBLIC_KEY => {
+                keys.extend(block.take().map(KeyBlock::into_keys).unwrap_or_default());
+                block = Some(KeyBlock {
+                    primary: parse_public_key(&body, false).map_err(AptNgError::InvalidKey)?,
+                    direct_signatures: Vec::new(),
+                    user_ids: Vec::new(),
+                    subkeys: Vec::new(),
+                });
+            }
+            TAG_PUBLIC_SUBKEY => {
+                let key = parse_public_key(&body, true).map_err(AptNgError::InvalidKey)?;
+                if let Some(block) = block.as_mut() {
+                    block.subkeys.push((key, Vec::new()));
+                }
+            }
+            TAG_USER_ID => {
+                if let Some(block) = block.as_mut() {
+                    block.user_ids.push((body, Vec::new()));
+                }
+            }
+            TAG_SIGNATURE => {
+                // Unbekannte Signaturversionen und -formate gelten einfach als nicht vorhanden
+                let (Some(block), Ok(Some(signature))) = (block.as_mut(), parse_signature(&body)) else {
+                    continue;
+                };
+                // Signaturen folgen dem Paket, auf das sie sich beziehen
+                if let Some((_, signatures)) = block.subkeys.last_mut() {
+                    signatures.push(signature);
+                } else if let Some((_, signatures)) = block.user_ids.last_mut() {
+                    signatures.push(signature);
+                } else {
+                    block.direct_signatures.push(signature);
+                }
+            }
+            _ => {}
+        }
+    }
+    keys.extend(block.map(KeyBlock::into_keys).unwrap_or_default());
+
+    Ok(keys)
+}
+
+/// Ein Hauptschlüssel mit seinen User-IDs, Unterschlüsseln und deren Signaturen
+struct KeyBlock {
+    primary: Option<OpenPgpKey>,
+    direct_signatures: Vec<OpenPgpSignature>,
+    user_ids: Vec<(Vec<u8>, Vec<OpenPgpSignature>)>,
+    subkeys: Vec<(Option<OpenPgpKey>, Vec<OpenPgpSignature>)>,
+}
+
+impl KeyBlock {
+    /// Wertet die Selbstsignaturen aus: Ablaufdatum und Widerruf des Hauptschlüssels,
+    /// Bindung, Ablauf und Widerruf der Unterschlüssel
+    ///
+    /// Unterschlüssel ohne gültige Bindungssignatur werden verworfen, ebenso alle
+    /// Schlüssel eines nicht unterstützten Hauptschlüssels.
+    fn into_keys(self) -> Vec<OpenPgpKey> {
+        let Some(mut primary) = self.primary else {
+            return Vec::new();
+        };
+        let primary_packet = primary.hashed_packet();
+        let signed_by_primary = |signature: &OpenPgpSignature, sig_types: &[u8], signed: &[u8]| {
+            sig_types.contains(&signature.sig_type) && signature.verify_key_signature(&primary, signed)
+        };
+
+        let revoked = self.direct_signatures.iter()
+            .any(|signature| signed_by_primary(signature, &[SIG_KEY_REVOCATION], &primary_packet));
+
+        // Die jüngste gültige Selbstsignatur (direkt oder über eine User-ID) legt den Ablauf fest
+        let direct = self.direct_signatures.iter()
+            .filter(|signature| signed_by_primary(signature, &[SIG_DIRECT_KEY], &primary_packet));
+        let certifications = self.user_ids.iter().flat_map(|(user_id, signatures)| {
+            let mut signed = primary_packet.clone();
+            signed.push(0xB4);
+            signed.extend_from_slice(&(user_id.len() as u32).to_be_bytes());
+            signed.extend_from_slice(user_id);
+            signatures.iter()
+                .filter(move |signature| signed_by_primary(signature, &[0x10, 0x11, 0x12, 0x13], &signed))
+        });
+        let expires = direct.chain(certifications)
+            .max_by_key(|signature| signature.created)
+            .and_then(|signature| expiry_after(primary.created, signature.key_expiry));
+
+        let mut subkeys = Vec::new();
+        for (subkey, signatures) in &self.subkeys {
+            let Some(subkey) = subkey else {
+                continue;
+            };
+            let mut signed = primary_packet.clone();
+            signed.extend_from_slice(&subkey.hashed_packet());
+
+            let Some(binding) = signatures.iter()
+                .filter(|signature| signed_by_primary(signature, &[SIG_SUBKEY_BINDING], &signed))
+                .max_by_key(|signature| signature.created)
+            else {
+                continue;
+            };
+            let subkey_expires = expiry_after(subkey.created, binding.key_expiry);
+            subkeys.push(OpenPgpKey {
+                expires: subkey_expires.into_iter().chain(expires).min(),
+                revoked: revoked || signatures.iter()
+                    .any(|signature| signed_by_primary(signature, &[SIG_SUBKEY_REVOCATION], &signed)),
+                ..subkey.clone()
+            });
+        }
+
+        primary.user_id = self.user_ids.first()
+            .map(|(user_id, _)| String::from_utf8_lossy(user_id).into_owned());
+        primary.expires = expires;
+        primary.revoked = revoked;
+        for subkey in &mut subkeys {
+            subkey.user_id = primary.user_id.clone();
+        }
+
+        std::iter::once(primary).chain(subkeys).collect()
+    }
+}
+
+/// Ablaufzeitpunkt aus Erstellung und Gültigkeitsdauer (0 oder fehlend: läuft nicht ab)
+fn expiry_after(created: DateTime<Utc>, seconds: Option<u32>) -> Option<DateTime<Utc>> {
+    seconds.filter(|&seconds| seconds > 0)
+        .map(|seconds| created + chrono::Duration::seconds(seconds as i64))
+}
+
+/// Liest alle v4-Signaturen (binär, z.B. Release.gpg, oder ASCII-armored)
+pub fn parse_signatures(data: &[u8]) -> Result<Vec<OpenPgpSignature>> {
+    let malformed = |reason: String| AptNgError::Signature(SignatureError::Malformed(reason));
+    let packets = parse_packets(&dearmor(data).map_err(malformed)?).map_err(malformed)?;
+
+    let mut signatures = Vec::new();
+    for (tag, body) in packets {
+        if tag == TAG_SIGNATURE {
+            if let Some(signature) = parse_signature(&body).map_err(malformed)? {
+                signatures.push(signature);
+            }
+        }
+    }
+    Ok(signatures)
+}
+
+/// Zerlegt eine clearsigned Nachricht (RFC 4880, Abschnitt 7) in Text und Signaturen
+pub fn parse_clearsigned(content: &[u8]) -> Result<ClearSigned> {
+    let malformed = |reason: &str| AptNgError::Signature(SignatureError::Malformed(reason.to_string()));
+    let content = std::str::from_utf8(content).map_err(|_| malformed("clearsigned message is not UTF-8"))?;
+
+    let mut lines = content.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line));
+    if lines.next() != Some("-----BEGIN PGP SIGNED MESSAGE-----") {
+        return Err(malformed("not a clearsigned message"));
+    }
+    // Armor-Header (Hash: ...) enden mit der ersten Leerzeile
+    for line in lines.by_ref() {
+        if line.is_empty() {
+            break;
+        }
+    }
+
+    let mut text = String::new();
+    let mut found_signature = false;
+    for line in lines.by_ref() {
+        if line == "-----BEGIN PGP SIGNATURE-----" {
+            found_signature = true;
+            break;
+        }
+        let line = line.strip_prefix("- ").unwrap_or(line);
+        text.push_str(line.trim_end_matches([' ', '\t']));
+        text.push('\n');
+    }
+    if !found_signature {
+        return Err(malformed("missing signature block"));
+    }
+
+    let armored = format!("-----BEGIN PGP SIGNATURE-----\n{}", lines.collect::<Vec<_>>().join("\n"));
+    let signatures = parse_signatures(armored.as_bytes())?;
+    Ok(ClearSigned { text, signatures })
+}
+
+/// OpenPGP-Sicht auf einen rohen Ed25519-Schlüssel (z.B. aus `*.pub`)
+pub fn ed25519_key(key: &ed25519_dalek::VerifyingKey) -> OpenPgpKey {
+    let mut body = vec![4];
+    body.extend_from_slice(&RAW_KEY_CREATED.to_be_bytes());
+    body.push(ALGO_EDDSA);
+    body.push(ED25519_OID.len() as u8);
+    body.extend_from_slice(ED25519_OID);
+    let mut point = vec![0x40];
+    point.extend_from_slice(key.as_bytes());
+    write_mpi(&mut body, &point);
+
+    parse_public_key(&body, false)
+        .ok()
+        .flatten()
+        .expect("Ed25519 key packet is well-formed")
+}
+
+/// Erstellt eine v4-EdDSA-Signatur (SHA-512) über `data` als binäres Signaturpaket
+///
+/// Mit `text` entsteht eine Text-Signatur (Typ 0x01, für clearsigned Nachrichten), sonst
+/// eine Signatur über die unveränderten Bytes (Typ 0x00, z.B. `Release.gpg`).
+pub fn sign_ed25519(signing_key: &ed25519_dalek::SigningKey, data: &[u8], text: bool, created: DateTime<Utc>) -> Vec<u8> {
+    use ed25519_dalek::Signer;
+
+    let key = ed25519_key(&signing_key.verifying_key());
+    let sig_type = if text { 0x01 } else { 0x00 };
+
+    // Gehashte Subpakete: Erstellungszeitpunkt (2) und Aussteller-Fingerprint (33)
+    let mut subpackets = vec![5, 2];
+    subpackets.extend_from_slice(&(created.timestamp().max(0) as u32).to_be_bytes());
+    subpackets.extend_from_slice(&[22, 33, 4]);
+    subpackets.extend_from_slice(&key.fingerprint);
+    let mut body = vec![4, sig_type, ALGO_EDDSA, HASH_SHA512];
+    body.extend_from_slice(&(subpackets.len() as u16).to_be_bytes());
+    body.extend_from_slice(&subpackets);
+
+    let canonical;
+    let data = if text {
+        canonical = canonical_text(data);
+        &canonical[..]
+    } else {
+        data
+    };
+    let digest = signature_digest(HASH_SHA512, &body, data).expect("SHA-512 is supported");
+    let signature = signing_key.sign(&digest).to_bytes();
+
+    // Ungehashter Bereich: Key-ID (16) für ältere Leser
+    body.extend_from_slice(&10u16.to_be_bytes());
+    body.extend_from_slice(&[9, 16]);
+    body.extend_from_slice(&key.key_id());
+    body.extend_from_slice(&digest[..2]);
+    write_mpi(&mut body, &signature[..32]);
+    write_mpi(&mut body, &signature[32..]);
+
+    let mut packet = vec![0xC0 | TAG_SIGNATURE];
+    write_packet_length(&mut packet, body.len());
+    packet.extend_from_slice(&body);
+    packet
+}
+
+/// ASCII-Armor eines Blocks (`kind` z.B. "SIGNATURE") samt CRC24-Prüfsumme
+pub fn armor(kind: &str, data: &[u8]) -> String {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let encoded = engine.encode(data);
+
+    let mut armored = format!("-----BEGIN PGP {}-----\n\n", kind);
+    for chunk in encoded.as_bytes().chunks(64) {
+        armored.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        armored.push('\n');
+    }
+    armored.push_str(&format!("={}\n", engine.encode(&crc24(data).to_be_bytes()[1..])));
+    armored.push_str(&format!("-----END PGP {}-----\n", kind));
+    armored
+}
+
+/// CRC-24 der Armor-Prüfsumme (RFC 4880, 6.1)
+fn crc24(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xB704CE;
+    for &byte in data {
+        crc ^= (byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= 0x1864CFB;
+            }
+        }
+    }
+    crc & 0xFFFFFF
+}
+
+/// Schreibt ein MPI (Bitlänge und Wert ohne führende Nullen)
+fn write_mpi(out: &mut Vec<u8>, value: &[u8]) {
+    let start = value.iter().position(|&byte| byte != 0).unwrap_or(value.len());
+    let value = &value[start..];
+    let bits = value.first().map_or(0, |&first| (value.len() - 1) * 8 + (8 - first.leading_zeros() as usize));
+    out.extend_from_slice(&(bits as u16).to_be_bytes());
+    out.extend_from_slice(value);
+}
+
+/// Paketlänge im neuen Format
+fn write_packet_length(out: &mut Vec<u8>, len: usize) {
+    match len {
+        0..=191 => out.push(len as u8),
+        192..=8383 => {
+            let len = len - 192;
+            out.push((len >> 8) as u8 + 192);
+            out.push(len as u8);
+        }
+        _ => {
+            out.push(0xFF);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    }
+}
+
+/// Normalisiert Zeilenenden auf CRLF (Text-Signaturen, Typ 0x01)
+fn canonical_text(data: &[u8]) -> Vec<u8> {
+    let mut canonical = Vec::with_capacity(data.len() + data.len() / 32);
+    for (i, &byte) in data.iter().enumerate() {
+        if byte == b'\n' && (i == 0 || data[i - 1] != b'\r') {
+            canonical.push(b'\r');
+        }
+        canonical.push(byte);
+    }
+    canonical
+}
+
+/// Dekodiert ASCII-Armor (alle Blöcke); binäre Daten werden unverändert zurückgegeben
+fn dearmor(data: &[u8]) -> std::result::Result<Vec<u8>, String> {
+    if data.iter().find(|b| !b.is_ascii_whitespace()) != Some(&b'-') {
+        return Ok(data.to_vec());
+    }
+
+    let text = String::from_utf8_lossy(data);
+    let mut decoded = Vec::new();
+    let mut body: Option<String> = None;
+    let mut in_headers = false;
+    for line in text.lines().map(str::trim_end) {
+        if line.starts_with("-----BEGIN PGP ") {
+            body = Some(String::new());
+            in_headers = true;
+        } else if line.starts_with("-----END PGP ") {
+            let encoded = body.take().ok_or("unexpected armor end")?;
+            let block = base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|e| format!("invalid armor: {}", e))?;
+            decoded.extend_from_slice(&block);
+        } else if let Some(encoded) = body.as_mut() {
+            if in_headers {
+                // Header wie "Version: ..." bis zur ersten Leerzeile
+                in_headers = !line.is_empty();
+            } else if !line.starts_with('=') {
+                // "=XXXX" ist die CRC24-Prüfsumme des Blocks
+                encoded.push_str(line);
+            }
+        }
+    }
+
+    if body.is_some() {
+        return Err("unterminated armor block".to_string());
+    }
+    Ok(decoded)
+}
+
+/// Lesezeiger über einen Paketinhalt
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> std::result::Result<&'a [u8], String> {
+        if self.data.len() < len {
+            return Err("truncated OpenPGP packet".to_string());
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> std::result::Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> std::result::Result<u16, String> {
+        Ok(u16::from_be_bytes([self.u8()?, self.u8()?]))
+    }
+
+    fn u32(&mut self) -> std::result::Result<u32, String> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Multiprecision Integer: Bitlänge (2 Byte) und Big-Endian-Wert
+    fn mpi(&mut self) -> std::result::Result<&'a [u8], String> {
+        let bits = self.u16()? as usize;
+        self.take(bits.div_ceil(8))
+    }
+}
+
+/// Zerlegt Daten in (Tag, Inhalt) der enthaltenen Pakete (altes und neues Paketformat)
+fn parse_packets(data: &[u8]) -> std::result::Result<Vec<(u8, Vec<u8>)>, String> {
+    let mut reader = Reader { data };
+    let mut packets = Vec::new();
+
+    while !reader.data.is_empty() {
+        let ctb = reader.u8()?;
+        if ctb & 0x80 == 0 {
+            return Err("invalid OpenPGP packet header".to_string());
+        }
+
+        if ctb & 0x40 != 0 {
+            // Neues Format; Teillängen werden zu einem Paket zusammengesetzt
+            let mut body = Vec::new();
+            loop {
+                let first = reader.u8()?;
+                let (len, partial) = match first {
+                    0..=191 => (first as usize, false),
+                    192..=223 => ((((first as usize) - 192) << 8) + reader.u8()? as usize + 192, false),
+                    255 => (reader.u32()? as usize, false),
+                    _ => (1usize << (first & 0x1f), true),
+                };
+                body.extend_from_slice(reader.take(len)?);
+                if !partial {
+                    break;
+                }
+            }
+            packets.push((ctb & 0x3f, body));
+        } else {
+            let len = match ctb & 0x03 {
+                0 => reader.u8()? as usize,
+                1 => reader.u16()? as usize,
+                2 => reader.u32()? as usize,
+                _ => reader.data.len(),
+            };
+            packets.push(((ctb >> 2) & 0x0f, reader.take(len)?.to_vec()));
+        }
+    }
+
+    Ok(packets)
+}
+
+/// Liest ein v4-Schlüsselpaket; andere Versionen werden übersprungen (None)
+fn parse_public_key(body: &[u8], is_subkey: bool) -> std::result::Result<Option<OpenPgpKey>, String> {
+    let mut reader = Reader { data: body };
+    if reader.u8()? != 4 {
+        return Ok(None);
+    }
+    let created = reader.u32()?;
+
+    let algo = reader.u8()?;
+    let material = match algo {
+        ALGO_RSA | ALGO_RSA_SIGN_ONLY => {
+            let n = BigUint::from_bytes_be(reader.mpi()?);
+            let e = BigUint::from_bytes_be(reader.mpi()?);
+            RsaPublicKey::new_with_max_size(n, e, MAX_RSA_BITS)
+                .map(KeyMaterial::Rsa)
+                .unwrap_or(KeyMaterial::Unsupported(algo))
+        }
+        ALGO_EDDSA => {
+            let oid_len = reader.u8()? as usize;
+            let oid = reader.take(oid_len)?;
+            // Punkt im nativen Format: Präfix 0x40 und 32 Bytes
+            match reader.mpi()? {
+                [0x40, point @ ..] if oid == ED25519_OID && point.len() == 32 => {
+                    ed25519_dalek::VerifyingKey::from_bytes(point.try_into().unwrap())
+                        .map(KeyMaterial::Ed25519)
+                        .unwrap_or(KeyMaterial::Unsupported(algo))
+                }
+                _ => KeyMaterial::Unsupported(algo),
+            }
+        }
+        _ => KeyMaterial::Unsupported(algo),
+    };
+
+    let mut hasher = Sha1::new();
+    hasher.update([0x99]);
+    hasher.update((body.len() as u16).to_be_bytes());
+    hasher.update(body);
+
+    Ok(Some(OpenPgpKey {
+        fingerprint: hasher.finalize().into(),
+        user_id: None,
+        is_subkey,
+        material,
+        created: DateTime::from_timestamp(created as i64, 0).unwrap_or_default(),
+        expires: None,
+        revoked: false,
+        packet: body.to_vec(),
+    }))
+}
+
+/// Liest ein v4-Signaturpaket; andere Versionen werden übersprungen (None)
+fn parse_signature(body: &[u8]) -> std::result::Result<Option<OpenPgpSignature>, String> {
+    let mut reader = Reader { data: body };
+    if reader.u8()? != 4 {
+        return Ok(None);
+    }
+    let sig_type = reader.u8()?;
+    let pubkey_algo = reader.u8()?;
+    let hash_algo = reader.u8()?;
+    let hashed_len = reader.u16()? as usize;
+    let hashed_area = reader.take(hashed_len)?;
+    let hashed = body[..6 + hashed_len].to_vec();
+    let unhashed_len = reader.u16()? as usize;
+    let unhashed_area = reader.take(unhashed_len)?;
+    let hash_prefix = [reader.u8()?, reader.u8()?];
+
+    let mut values = Vec::new();
+    while !reader.data.is_empty() {
+        values.push(reader.mpi()?.to_vec());
+    }
+
+    let mut signature = OpenPgpSignature {
+        sig_type,
+        pubkey_algo,
+        hash_algo,
+        issuer: None,
+        issuer_fingerprint: None,
+        created: None,
+        key_expiry: None,
+        hashed,
+        hash_prefix,
+        values,
+    };
+
+    // Aussteller steht bei gpg meist im ungehashten Bereich; er dient nur der Schlüsselauswahl.
+    // Zeitangaben zählen nur aus dem gehashten Bereich, sonst ließen sie sich unsigniert ändern
+    for (is_hashed, area) in [(true, hashed_area), (false, unhashed_area)] {
+        let mut subpackets = Reader { data: area };
+        while !subpackets.data.is_empty() {
+            let first = subpackets.u8()? as usize;
+            let len = match first {
+                0..=191 => first,
+                192..=254 => ((first - 192) << 8) + subpackets.u8()? as usize + 192,
+                _ => subpackets.u32()? as usize,
+            };
+            let data = subpackets.take(len)?;
+            let Some((&kind, data)) = data.split_first() else {
+                continue;
+            };
+            match (kind & 0x7f, data) {
+                (2, [a, b, c, d]) if is_hashed => {
+                    signature.created = DateTime::from_timestamp(u32::from_be_bytes([*a, *b, *c, *d]) as i64, 0);
+                }
+                (9, [a, b, c, d]) if is_hashed => {
+                    signature.key_expiry = Some(u32::from_be_bytes([*a, *b, *c, *d]));
+                }
+                (16, key_id) if key_id.len() == 8 => {
+                    signature.issuer = key_id.try_into().ok();
+                }
+                (33, [4, fingerprint @ ..]) if fingerprint.len() == 20 => {
+                    signature.issuer_fingerprint = fingerprint.try_into().ok();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(Some(signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clearsigned_text_is_unescaped_and_trimmed() {
+        let message = b"-----BEGIN PGP SIGNED MESSAGE-----\r\nHash: SHA256\r\n\r\n- -----dashed\r\nOrigin: test  \r\n-----BEGIN PGP SIGNATURE-----\r\n\r\n-----END PGP SIGNATURE-----\r\n";
+        let clearsigned = parse_clearsigned(message).unwrap();
+        assert_eq!(clearsigned.text, "-----dashed\nOrigin: test\n");
+        assert_eq!(clearsigned.signed_data(), b"-----dashed\nOrigin: test");
+        assert!(clearsigned.signatures.is_empty());
+
+        assert_eq!(canonical_text(b"a\nb\r\nc\n"), b"a\r\nb\r\nc\r\n");
+        assert!(parse_clearsigned(b"Origin: unsigned\n").is_err());
+    }
+
+    #[test]
+    fn test_ed25519_signature_round_trip() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[3; 32]);
+        let key = ed25519_key(&signing_key.verifying_key());
+        let created = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        // Binäre Signatur, armored wie Release.gpg
+        let packet = sign_ed25519(&signing_key, b"Suite: stable\n", false, created);
+        let signatures = parse_signatures(armor("SIGNATURE", &packet).as_bytes()).unwrap();
+        assert_eq!(signatures.len(), 1);
+        assert!(signatures[0].may_be_from(&key));
+        assert_eq!(signatures[0].created, Some(created));
+        assert!(signatures[0].verify(&key, b"Suite: stable\n"));
+        assert!(!signatures[0].verify(&key, b"Suite: unstable\n"));
+
+        // Text-Signaturen sind unabhängig von LF/CRLF
+        let signatures = parse_signatures(&sign_ed25519(&signing_key, b"a\nb", true, created)).unwrap();
+        assert!(signatures[0].verify(&key, b"a\r\nb"));
+
+        assert_eq!(crc24(b""), 0xB704CE);
+        let other = ed25519_key(&ed25519_dalek::SigningKey::from_bytes(&[4; 32]).verifying_key());
+        assert!(!signatures[0].verify(&other, b"a\nb"));
+    }
+
+    /// Hauptschlüssel nur zum Zertifizieren, signiert wird mit dem Unterschlüssel (GnuPG 2.2)
+    const SUBKEY_KEYRING: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----
+
+mDMEatJ7/RYJKwYBBAHaRw8BAQdA1mcYjfDHCNVLifFTovHJmX4raYtEzN1ia1Wq
+i767aJi0JWFwdC1uZyBzdWJrZXkgPHN1YmtleUBhcHQtbmcuaW52YWxpZD6IkAQT
+FggAOBYhBDZj4q9ypvVx0Dc1YDqTSH3ExfBkBQJq0nv9AhsBBQsJCAcCBhUKCQgL
+AgQWAgMBAh4BAheAAAoJEDqTSH3ExfBkezUA+gNNS8QLE49qXFvYRZcAvfpWmDsp
+rBuRu02iysP3ey04AQDZIkjtaE1KSGIUT7rUmlUCrXsIHAHhxSEClwBt97kzBLgz
+BGrSe/0WCSsGAQQB2kcPAQEHQH593188UNTiT8EyJqA6ScXITtN6ovqinKSATL0X
+Ta1EiO8EGBYIACAWIQQ2Y+Kvcqb1cdA3NWA6k0h9xMXwZAUCatJ7/QIbAgCBCRA6
+k0h9xMXwZHYgBBkWCAAdFiEEtIHay6GyYwrcCLtlcY71hDjqXxAFAmrSe/0ACgkQ
+cY71hDjqXxDeXAEAik2g9U1s24vDyKGi+W6CPZVEGRfEzjneJG8ctAnPM0EBAN8D
+qCwPBe983j0UGKAPAI7TlmMqfJlcv/TvHWSK/r8OqAUBAJc+kyjE31dq5hrjQ/EP
+qqljFbyozk1/lq6mKzP1E+l6AQCsbUkXLWkAoe74kjvsFwqbSC2ASZHoma0l510C
+T9jTAw==
+=HjzK
+-----END PGP PUBLIC KEY BLOCK-----
+";
+    const SUBKEY_SIGNATURE: &str = "-----BEGIN PGP SIGNATURE-----
+
+iIwEABYIADQWIQS0gdrLobJjCtwIu2VxjvWEOOpfEAUCatJ7/RYcc3Via2V5QGFw
+dC1uZy5pbnZhbGlkAAoJEHGO9YQ46l8QsKcBAK1M4cBMB+ax6RGY1DA6NhsnoWdh
+tQsvdHjSlc8IiAoBAP9fntiVGdOReYwBdAojFzh2BsWlXzqo0LF37vqWgfTpAw==
+=eKFl
+-----END PGP SIGNATURE-----
+";
+
+    #[test]
+    fn test_unhashed_creation_time_is_ignored() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[3; 32]);
+        let key = ed25519_key(&signing_key.verifying_key());
+        let created = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let backdated = DateTime::from_timestamp(1_000_000_000, 0).unwrap();
+        let unhashed_created = |body: &[u8], hashed_len: usize| {
+            let mut forged = body[..6 + hashed_len].to_vec();
+            let old_len = u16::from_be_bytes([body[6 + hashed_len], body[7 + hashed_len]]) as usize;
+            let mut unhashed = vec![5, 2];
+            unhashed.extend_from_slice(&(backdated.timestamp() as u32).to_be_bytes());
+            unhashed.extend_from_slice(&body[8 + hashed_len..8 + hashed_len + old_len]);
+            forged.extend_from_slice(&(unhashed.len() as u16).to_be_bytes());
+            forged.extend_from_slice(&unhashed);
+            forged.extend_from_slice(&body[8 + hashed_len + old_len..]);
+            forged
+        };
+
+        // Ein zusätzlicher ungehashter Zeitpunkt überschreibt den signierten nicht
+        let packet = sign_ed25519(&signing_key, b"Suite: stable\n", false, created);
+        let body = &packet[2..];
+        let hashed_len = u16::from_be_bytes([body[4], body[5]]) as usize;
+        let signature = parse_signature(&unhashed_created(body, hashed_len)).unwrap().unwrap();
+        assert_eq!(signature.created, Some(created));
+        assert!(signature.verify(&key, b"Suite: stable\n"));
+
+        // Steht der Zeitpunkt nur im ungehashten Bereich, ist die Signatur ungültig
+        use ed25519_dalek::Signer;
+        let mut hashed = vec![22, 33, 4];
+        hashed.extend_from_slice(&key.fingerprint);
+        let mut body = vec![4, 0x00, ALGO_EDDSA, HASH_SHA512];
+        body.extend_from_slice(&(hashed.len() as u16).to_be_bytes());
+        body.extend_from_slice(&hashed);
+        let digest = signature_digest(HASH_SHA512, &body, b"Suite: stable\n").unwrap();
+        let value = signing_key.sign(&digest).to_bytes();
+        body.extend_from_slice(&0u16.to_be_bytes());
+        body.extend_from_slice(&digest[..2]);
+        write_mpi(&mut body, &value[..32]);
+        write_mpi(&mut body, &value[32..]);
+        let signature = parse_signature(&unhashed_created(&body, hashed.len())).unwrap().unwrap();
+        assert_eq!(signature.created, None);
+        assert!(!signature.verify(&key, b"Suite: stable\n"));
+    }
+
+    #[test]
+    fn test_subkeys_need_a_binding_signature() {
+        let release = b"Origin: apt-ng
+Suite: stable
+";
+        let signature = &parse_signatures(SUBKEY_SIGNATURE.as_bytes()).unwrap()[0];
+
+        let keys = parse_keyring(SUBKEY_KEYRING.as_bytes()).unwrap();
+        assert_eq!(keys.len(), 2);
+        assert!(keys[1].is_subkey && keys[1].expires.is_none() && !keys[1].revoked);
+        assert_eq!(keys[1].user_id.as_deref(), Some("apt-ng subkey <subkey@apt-ng.invalid>"));
+        assert!(signature.may_be_from(&keys[1]) && signature.verify(&keys[1], release));
+
+        // Ohne Bindungssignatur (0x18) gehört der Unterschlüssel nicht zum Schlüssel
+        let mut unbound = Vec::new();
+        for (tag, body) in parse_packets(&dearmor(SUBKEY_KEYRING.as_bytes()).unwrap()).unwrap() {
+            if tag == TAG_SIGNATURE && body[1] == SIG_SUBKEY_BINDING {
+                continue;
+            }
+            unbound.push(0xC0 | tag);
+            write_packet_length(&mut unbound, body.len());
+            unbound.extend_from_slice(&body);
+        }
+        let keys = parse_keyring(&unbound).unwrap();
+        assert_eq!(keys.len(), 1);
+        assert!(!keys[0].is_subkey);
+    }
+}
@@ -2,13 +2,54 @@ use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
 use atty::Stream;
 use comfy_table::{Table, Cell, presets::UTF8_FULL, ContentArrangement};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How much apt-ng prints (`-q`, default, `-v`, `-vv`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only warnings and errors, on stderr; no progress bars
+    Quiet = 0,
+    Normal = 1,
+    /// Detailed steps (`-v`)
+    Verbose = 2,
+    /// Additionally dumps resolution internals (`-vv`)
+    Debug = 3,
+}
+
+impl Verbosity {
+    /// From `--quiet` and the number of `-v` flags
+    pub fn from_flags(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        }
+    }
+    
+    /// Whether output of `level` is shown at this verbosity
+    pub fn shows(self, level: Verbosity) -> bool {
+        self >= level
+    }
+    
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Verbosity::Quiet,
+            1 => Verbosity::Normal,
+            2 => Verbosity::Verbose,
+            _ => Verbosity::Debug,
+        }
+    }
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
 
 /// Formatted output for apt-ng
 pub struct Output;
 
 impl Output {
     /// Check if colors should be enabled
-    pub(crate) fn colors_enabled() -> bool {
+    pub fn colors_enabled() -> bool {
         atty::is(Stream::Stdout) && std::env::var("NO_COLOR").is_err()
     }
     
@@ -24,8 +65,25 @@ impl Output {
         Self::disable_colors_if_needed();
     }
     
+    /// Set the verbosity for all following output
+    pub fn set_verbosity(verbosity: Verbosity) {
+        VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+    }
+    
+    pub fn verbosity() -> Verbosity {
+        Verbosity::from_u8(VERBOSITY.load(Ordering::Relaxed))
+    }
+    
+    /// Whether messages of this level are shown
+    pub fn shows(level: Verbosity) -> bool {
+        Self::verbosity().shows(level)
+    }
+    
     /// Show a success message
     pub fn success(msg: &str) {
+        if !Self::shows(Verbosity::Normal) {
+            return;
+        }
         if Self::colors_enabled() {
             println!("{} {}", "✓".green().bold(), msg.green());
         } else {
@@ -35,6 +93,7 @@ impl Output {
     
     /// Show an error message
     pub fn error(msg: &str) {
+        tracing::error!("{}", msg);
         if Self::colors_enabled() {
             eprintln!("{} {}", "✗".red().bold(), msg.red());
         } else {
@@ -44,15 +103,25 @@ impl Output {
     
     /// Show a warning message
     pub fn warning(msg: &str) {
-        if Self::colors_enabled() {
-            println!("{} {}", "⚠".yellow().bold(), msg.yellow());
+        tracing::warn!("{}", msg);
+        let line = if Self::colors_enabled() {
+            format!("{} {}", "⚠".yellow().bold(), msg.yellow())
         } else {
-            println!("⚠ {}", msg);
+            format!("⚠ {}", msg)
+        };
+        // In quiet mode stdout stays clean for scripts
+        if Self::shows(Verbosity::Normal) {
+            println!("{}", line);
+        } else {
+            eprintln!("{}", line);
         }
     }
     
     /// Show an info message
     pub fn info(msg: &str) {
+        if !Self::shows(Verbosity::Normal) {
+            return;
+        }
         if Self::colors_enabled() {
             println!("{} {}", "ℹ".cyan().bold(), msg.cyan());
         } else {
@@ -60,8 +129,23 @@ impl Output {
         }
     }
     
+    /// Show solver and other internals (only with `-vv`)
+    pub fn debug(msg: &str) {
+        if !Self::shows(Verbosity::Debug) {
+            return;
+        }
+        if Self::colors_enabled() {
+            println!("  {} {}", "·".dimmed(), msg.dimmed());
+        } else {
+            println!("  · {}", msg);
+        }
+    }
+    
     /// Show a heading
     pub fn heading(msg: &str) {
+        if !Self::shows(Verbosity::Normal) {
+            return;
+        }
         if Self::colors_enabled() {
             println!("\n{}", msg.bold().bright_blue());
         } else {
@@ -71,6 +155,9 @@ impl Output {
     
     /// Show a section header
     pub fn section(msg: &str) {
+        if !Self::shows(Verbosity::Normal) {
+            return;
+        }
         if Self::colors_enabled() {
             println!("{}", msg.bold());
         } else {
@@ -80,6 +167,9 @@ impl Output {
     
     /// Show a list item
     pub fn list_item(item: &str) {
+        if !Self::shows(Verbosity::Normal) {
+            return;
+        }
         if Self::colors_enabled() {
             println!("  {} {}", "•".cyan(), item);
         } else {
@@ -117,6 +207,9 @@ impl Output {
     
     /// Create a progress bar
     pub fn progress_bar(len: u64) -> ProgressBar {
+        if !Self::shows(Verbosity::Normal) {
+            return ProgressBar::hidden();
+        }
         let pb = ProgressBar::new(len);
         
         // Use different styles based on terminal capabilities
@@ -136,14 +229,41 @@ impl Output {
         pb
     }
     
+    /// Create a progress bar counting items (e.g. packages) instead of bytes
+    pub fn count_progress_bar(len: u64) -> ProgressBar {
+        if !Self::shows(Verbosity::Normal) {
+            return ProgressBar::hidden();
+        }
+        let pb = ProgressBar::new(len);
+        
+        let template = if Self::colors_enabled() {
+            "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} {msg}"
+        } else {
+            "[{elapsed_precise}] [{bar:40}] {pos}/{len} {msg}"
+        };
+        
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template(template)
+                .unwrap()
+                .progress_chars(if Self::colors_enabled() { "#>-" } else { "=>-" })
+        );
+        pb
+    }
+    
     /// Create a simple progress bar without template
-    #[allow(dead_code)]
     pub fn simple_progress_bar(len: u64) -> ProgressBar {
+        if !Self::shows(Verbosity::Normal) {
+            return ProgressBar::hidden();
+        }
         ProgressBar::new(len)
     }
     
     /// Show formatted package information
     pub fn package_info(name: &str, version: &str, arch: &str) {
+        if !Self::shows(Verbosity::Normal) {
+            return;
+        }
         if Self::colors_enabled() {
             println!("  {} {} {} ({})", 
                 "📦".cyan(), 
@@ -158,6 +278,9 @@ impl Output {
     
     /// Show download information
     pub fn download_info(name: &str, size: &str) {
+        if !Self::shows(Verbosity::Normal) {
+            return;
+        }
         if Self::colors_enabled() {
             println!("  {} {} {}", 
                 "⬇".green(), 
@@ -171,6 +294,9 @@ impl Output {
     
     /// Show installation information
     pub fn install_info(name: &str, version: &str) {
+        if !Self::shows(Verbosity::Normal) {
+            return;
+        }
         if Self::colors_enabled() {
             println!("  {} {} {}", 
                 "🔧".magenta(), 
@@ -184,6 +310,9 @@ impl Output {
     
     /// Show repository information
     pub fn repo_info(url: &str) {
+        if !Self::shows(Verbosity::Normal) {
+            return;
+        }
         if Self::colors_enabled() {
             println!("  {} {}", "📥".blue(), url.bold());
         } else {
@@ -193,6 +322,9 @@ impl Output {
     
     /// Show a summary
     pub fn summary(title: &str, count: usize) {
+        if !Self::shows(Verbosity::Normal) {
+            return;
+        }
         if Self::colors_enabled() {
             println!("\n{} {} {}", 
                 "✓".green().bold(),
@@ -206,6 +338,9 @@ impl Output {
     
     /// Show a progress message (for verbose output)
     pub fn progress_message(msg: &str) {
+        if !Self::shows(Verbosity::Normal) {
+            return;
+        }
         if Self::colors_enabled() {
             print!("\r{} {}", "⏳".yellow(), msg.dimmed());
             use std::io::Write;
@@ -218,8 +353,10 @@ impl Output {
     }
     
     /// Show a URL
-    #[allow(dead_code)]
     pub fn url(url: &str) {
+        if !Self::shows(Verbosity::Normal) {
+            return;
+        }
         if Self::colors_enabled() {
             println!("  {} {}", "🔗".blue(), url.dimmed());
         } else {
@@ -228,3 +365,29 @@ impl Output {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_verbosity_levels() {
+        assert_eq!(Verbosity::from_flags(true, 0), Verbosity::Quiet);
+        assert_eq!(Verbosity::from_flags(false, 0), Verbosity::Normal);
+        assert_eq!(Verbosity::from_flags(false, 1), Verbosity::Verbose);
+        assert_eq!(Verbosity::from_flags(false, 3), Verbosity::Debug);
+        
+        // What each level shows: (level, normal messages, -v details, -vv internals)
+        let levels = [
+            (Verbosity::Quiet, false, false, false),
+            (Verbosity::Normal, true, false, false),
+            (Verbosity::Verbose, true, true, false),
+            (Verbosity::Debug, true, true, true),
+        ];
+        for (level, normal, verbose, debug) in levels {
+            assert_eq!(Verbosity::from_u8(level as u8), level);
+            assert_eq!(level.shows(Verbosity::Normal), normal, "{:?}", level);
+            assert_eq!(level.shows(Verbosity::Verbose), verbose, "{:?}", level);
+            assert_eq!(level.shows(Verbosity::Debug), debug, "{:?}", level);
+        }
+    }
+}
@@ -1,13 +1,59 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs::File;
-use std::io::{Read, BufReader, BufWriter};
+use std::io::{Read, BufReader, BufWriter, Seek};
+use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
 use zstd::stream::{Decoder, Encoder};
 use tar::Archive;
 
-#[allow(dead_code)]
-const APX_MAGIC: &[u8] = b"APX\x01";
+/// Magic und Formatversion neu erstellter .apx-Pakete
+///
+/// Version 2 speichert die Längen von Metadaten und Inhalt als u64; Version 1 nutzte
+/// u32 und war damit auf 4 GiB begrenzt. Ab Version 3 deckt die Signatur auch Header
+/// und Längenfelder ab. Gelesen werden alle drei Versionen.
+pub const APX_MAGIC: &[u8] = b"APX\x03";
+
+/// Obergrenze für metadata.json.zst, damit beschädigte Längenfelder keine riesigen
+/// Allokationen auslösen
+const MAX_METADATA_LEN: u64 = 64 * 1024 * 1024;
+
+/// Liest Magic und Formatversion eines .apx-Pakets (1 bis 3)
+fn read_apx_header(reader: &mut impl Read) -> Result<u8> {
+    let mut header = [0u8; 4];
+    reader.read_exact(&mut header)?;
+    if header[..3] != APX_MAGIC[..3] {
+        return Err(anyhow::anyhow!("Invalid APX magic"));
+    }
+    match header[3] {
+        version @ 1..=3 => Ok(version),
+        version => Err(anyhow::anyhow!("Unsupported APX format version {}", version)),
+    }
+}
+
+/// Liest ein Längenfeld (little-endian; v1: 4 Byte, ab v2: 8 Byte)
+fn read_apx_length(reader: &mut impl Read, version: u8) -> Result<u64> {
+    if version == 1 {
+        let mut bytes = [0u8; 4];
+        reader.read_exact(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes) as u64)
+    } else {
+        let mut bytes = [0u8; 8];
+        reader.read_exact(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+/// Liest die komprimierten Metadaten hinter dem Längenfeld
+fn read_apx_metadata(reader: &mut impl Read, version: u8) -> Result<Vec<u8>> {
+    let metadata_len = read_apx_length(reader, version)?;
+    if metadata_len > MAX_METADATA_LEN {
+        return Err(anyhow::anyhow!("APX metadata too large ({} bytes)", metadata_len));
+    }
+    let mut metadata = vec![0u8; metadata_len as usize];
+    reader.read_exact(&mut metadata)?;
+    Ok(metadata)
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PackageManifest {
@@ -26,6 +72,20 @@ pub struct PackageManifest {
     pub filename: Option<String>, // Pfad zum .deb-Paket im Repository (z.B. "pool/main/m/micro/micro_2.0.11-1_amd64.deb")
     #[serde(default)]
     pub repo_id: Option<i64>, // ID des Repositories
+    #[serde(default)]
+    pub maintainer: Option<String>,
+    #[serde(default)]
+    pub homepage: Option<String>,
+    #[serde(default)]
+    pub section: Option<String>,
+    #[serde(default)]
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub installed_size: Option<u64>, // in KiB, wie Installed-Size im Packages-Index
+    #[serde(default)]
+    pub recommends: Vec<String>,
+    #[serde(default)]
+    pub suggests: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +94,9 @@ pub struct FileEntry {
     pub checksum: String,
     pub size: u64,
     pub mode: u32,
+    /// Ziel eines Symlinks; None für reguläre Dateien
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<String>,
 }
 
 pub struct ApxPackage {
@@ -46,21 +109,9 @@ impl ApxPackage {
     pub fn open(apx_path: &Path) -> Result<Self> {
         let mut file = BufReader::new(File::open(apx_path)?);
         
-        // Lese Header (Magic + Version)
-        let mut header = [0u8; 4];
-        file.read_exact(&mut header)?;
-        if &header[..3] != &APX_MAGIC[..3] {
-            return Err(anyhow::anyhow!("Invalid APX magic"));
-        }
-        
-        // Lese metadata Länge (4 bytes, little-endian)
-        let mut metadata_len_bytes = [0u8; 4];
-        file.read_exact(&mut metadata_len_bytes)?;
-        let metadata_len = u32::from_le_bytes(metadata_len_bytes) as usize;
-        
-        // Lese metadata.json.zst
-        let mut metadata_compressed = vec![0u8; metadata_len];
-        file.read_exact(&mut metadata_compressed)?;
+        // Lese Header (Magic + Version) und metadata.json.zst
+        let version = read_apx_header(&mut file)?;
+        let metadata_compressed = read_apx_metadata(&mut file, version)?;
         
         // Dekomprimiere metadata.json
         let metadata_json = Self::try_decode_zstd(&metadata_compressed)?;
@@ -75,7 +126,6 @@ impl ApxPackage {
     }
     
     /// Versucht zstd-Daten zu dekodieren
-    #[allow(dead_code)]
     fn try_decode_zstd(data: &[u8]) -> Result<String> {
         let decoder = Decoder::new(data)?;
         let mut reader = BufReader::new(decoder);
@@ -86,54 +136,49 @@ impl ApxPackage {
     
     /// Extrahiert den Inhalt des Pakets in ein Zielverzeichnis
     pub fn extract_to(&self, dest_dir: &Path) -> Result<()> {
-        use std::fs;
-        
         let mut file = BufReader::new(File::open(&self.content_path)?);
         
-        // Überspringe Header
-        let mut header = [0u8; 4];
-        file.read_exact(&mut header)?;
-        
-        // Lese metadata Länge
-        let mut metadata_len_bytes = [0u8; 4];
-        file.read_exact(&mut metadata_len_bytes)?;
-        let metadata_len = u32::from_le_bytes(metadata_len_bytes) as usize;
-        
-        // Überspringe metadata (wir haben es bereits beim Öffnen gelesen)
-        use std::io::{Seek, SeekFrom};
-        file.seek(SeekFrom::Current(metadata_len as i64))?;
-        
-        // Lese content Länge
-        let mut content_len_bytes = [0u8; 4];
-        file.read_exact(&mut content_len_bytes)?;
-        let content_len = u32::from_le_bytes(content_len_bytes) as usize;
-        
-        // Lese content.tar.zst
-        let mut content_data = vec![0u8; content_len];
-        file.read_exact(&mut content_data)?;
-        
-        // Dekomprimiere content.tar.zst
-        let decoder = Decoder::new(content_data.as_slice())?;
+        // Überspringe Header und metadata (wir haben es bereits beim Öffnen gelesen)
+        let version = read_apx_header(&mut file)?;
+        let metadata_len = read_apx_length(&mut file, version)?;
+        file.seek_relative(i64::try_from(metadata_len)?)?;
+        
+        // content.tar.zst wird direkt aus der Datei dekomprimiert, statt es erst
+        // vollständig in den Speicher zu lesen; `take` begrenzt den Decoder auf den Block
+        let content_len = read_apx_length(&mut file, version)?;
+        let decoder = crate::extract::LimitedReader::with_default_limit(
+            Decoder::with_buffer(file.take(content_len))?,
+            format!("content of {}", self.content_path.display()),
+        );
         let mut tar_archive = Archive::new(decoder);
         
-        // Stelle sicher, dass das Zielverzeichnis existiert
-        fs::create_dir_all(dest_dir)?;
-        
-        // Extrahiere tar-Archiv
-        tar_archive.unpack(dest_dir)?;
-        
-        Ok(())
+        // Extrahiere tar-Archiv (ohne absolute Pfade und `..`-Einträge)
+        crate::extract::unpack_tar(&mut tar_archive, dest_dir)
+            .with_context(|| format!("Failed to extract {}", self.content_path.display()))
     }
     
     /// Verifiziert die Checksummen aller Dateien
     pub fn verify_checksums(&self, extracted_dir: &Path) -> Result<()> {
-        use sha2::{Sha256, Digest};
         use hex;
         use std::fs;
         
         for file_entry in &self.manifest.files {
             let file_path = extracted_dir.join(&file_entry.path);
             
+            if let Some(expected) = &file_entry.link_target {
+                let target = fs::read_link(&file_path)
+                    .with_context(|| format!("Symlink not found: {}", file_entry.path))?;
+                if target.to_string_lossy() != *expected {
+                    return Err(anyhow::anyhow!(
+                        "Symlink target mismatch for {}: expected {}, got {}",
+                        file_entry.path,
+                        expected,
+                        target.display()
+                    ));
+                }
+                continue;
+            }
+            
             if !file_path.exists() {
                 return Err(anyhow::anyhow!("File not found: {}", file_entry.path));
             }
@@ -166,33 +211,47 @@ impl ApxPackage {
     }
     
     /// Verifiziert die Signatur eines .apx-Pakets
+    ///
+    /// Ab Format v3 ist der SHA-256-Hash über alles vor der Signatur signiert (Magic,
+    /// Version, Längenfelder, metadata und content); er wird beim Lesen berechnet.
+    /// Ältere Pakete signieren nur `metadata || content`.
     pub fn verify_signature(&self, apx_path: &Path, verifier: &crate::verifier::PackageVerifier) -> Result<()> {
         use std::io::Read;
         use ed25519_dalek::{Signature, Verifier};
         
         let mut file = BufReader::new(File::open(apx_path)?);
-        
-        // Überspringe Header
-        let mut header = [0u8; 4];
-        file.read_exact(&mut header)?;
-        
-        // Lese metadata Länge
-        let mut metadata_len_bytes = [0u8; 4];
-        file.read_exact(&mut metadata_len_bytes)?;
-        let metadata_len = u32::from_le_bytes(metadata_len_bytes) as usize;
-        
-        // Lese metadata
-        let mut metadata_data = vec![0u8; metadata_len];
-        file.read_exact(&mut metadata_data)?;
-        
-        // Lese content Länge
-        let mut content_len_bytes = [0u8; 4];
-        file.read_exact(&mut content_len_bytes)?;
-        let content_len = u32::from_le_bytes(content_len_bytes) as usize;
-        
-        // Lese content
-        let mut content_data = vec![0u8; content_len];
-        file.read_exact(&mut content_data)?;
+        let version = read_apx_header(&mut file)?;
+        
+        let data_to_verify = if version >= 3 {
+            let metadata_len = read_apx_length(&mut file, version)?;
+            file.seek_relative(i64::try_from(metadata_len)?)?;
+            let content_len = read_apx_length(&mut file, version)?;
+            let signed_len = (APX_MAGIC.len() as u64 + 16)
+                .checked_add(metadata_len)
+                .and_then(|len| len.checked_add(content_len))
+                .ok_or_else(|| anyhow::anyhow!("Invalid APX length fields"))?;
+            
+            file.rewind()?;
+            let mut hasher = Sha256::new();
+            if std::io::copy(&mut (&mut file).take(signed_len), &mut hasher)? != signed_len {
+                return Err(anyhow::anyhow!("Truncated APX content"));
+            }
+            hasher.finalize().to_vec()
+        } else {
+            // Lese metadata und content in einen gemeinsamen Puffer: Ed25519 signiert die
+            // Nachricht selbst (nicht deren Hash), daher muss sie vollständig vorliegen
+            let mut data = read_apx_metadata(&mut file, version)?;
+            let content_len = read_apx_length(&mut file, version)?;
+            // Die Länge darf nicht über das Dateiende hinauszeigen (keine Riesen-Allokation)
+            if content_len > file.get_ref().metadata()?.len() {
+                return Err(anyhow::anyhow!("Truncated APX content"));
+            }
+            data.reserve_exact(usize::try_from(content_len)?);
+            if (&mut file).take(content_len).read_to_end(&mut data)? as u64 != content_len {
+                return Err(anyhow::anyhow!("Truncated APX content"));
+            }
+            data
+        };
         
         // Lese Signatur (64 bytes)
         let mut signature_bytes = [0u8; 64];
@@ -200,13 +259,8 @@ impl ApxPackage {
             return Err(anyhow::anyhow!("Package is not signed"));
         }
         
-        // Kombiniere metadata + content für Verifikation
-        let mut data_to_verify = Vec::new();
-        data_to_verify.extend_from_slice(&metadata_data);
-        data_to_verify.extend_from_slice(&content_data);
-        
         // Verifiziere mit allen vertrauenswürdigen Schlüsseln
-        if verifier.trusted_key_count() == 0 {
+        if verifier.get_trusted_keys().next().is_none() {
             return Err(anyhow::anyhow!("No trusted keys available for verification"));
         }
         
@@ -222,62 +276,245 @@ impl ApxPackage {
     }
 }
 
+/// Prüft einen Paketnamen nach Debian Policy 5.6.1 (`[a-z0-9][a-z0-9+.-]*`)
+///
+/// Name, Version und Architektur landen unverändert in Cache-Dateinamen und URLs; ein
+/// präparierter Index darf darüber keine Pfade außerhalb des Caches erreichen.
+pub fn validate_package_name(name: &str) -> Result<()> {
+    let valid = name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '+' | '.' | '-'));
+    if !valid {
+        return Err(anyhow::anyhow!("Invalid package name {:?}", name));
+    }
+    Ok(())
+}
+
+/// Prüft eine Version nach Debian Policy 5.6.12 (Epoch, Upstream-Version, Revision)
+pub fn validate_version(version: &str) -> Result<()> {
+    let valid = version.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && version.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '~' | '-' | ':'));
+    if !valid {
+        return Err(anyhow::anyhow!("Invalid package version {:?}", version));
+    }
+    Ok(())
+}
+
+/// Prüft einen Architekturnamen (`amd64`, `all`, `kfreebsd-i386`, …)
+pub fn validate_architecture(arch: &str) -> Result<()> {
+    if arch.is_empty() || !arch.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+        return Err(anyhow::anyhow!("Invalid architecture {:?}", arch));
+    }
+    Ok(())
+}
+
+/// Prüft das `Filename`-Feld eines Repositories (z.B. `pool/main/h/hello/hello_1.0_amd64.deb`)
+///
+/// Erlaubt sind relative Pfade aus Segmenten mit Zeichen, die in Paketdateinamen vorkommen
+/// (`%` für URL-kodierte Epochs); `.`- und `..`-Segmente werden abgelehnt.
+pub fn validate_repo_filename(filename: &str) -> Result<()> {
+    let valid = !filename.starts_with('/')
+        && filename.split('/').all(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '~' | '-' | '_' | ':' | '%'))
+        });
+    if !valid {
+        return Err(anyhow::anyhow!("Invalid repository filename {:?}", filename));
+    }
+    Ok(())
+}
+
+impl PackageManifest {
+    /// Prüft Name, Version und Architektur, bevor daraus Cache-Pfade gebaut werden
+    pub fn validate_identity(&self) -> Result<()> {
+        validate_package_name(&self.name)?;
+        validate_version(&self.version)?;
+        validate_architecture(&self.arch)
+    }
+}
+
+/// Änderung des belegten Speicherplatzes durch einen Plan in Bytes (negativ: wird frei)
+///
+/// Neue Pakete zählen mit ihrer installierten Größe, Upgrades mit der Differenz zur
+/// installierten Version und Entfernungen mit der Größe der installierten Version.
+/// Pakete ohne `Installed-Size` tragen nichts bei. Gerechnet wird in KiB, das Ergebnis
+/// wird für die Anzeige in Bytes umgerechnet.
+pub fn disk_usage_delta(installs: &[PackageManifest], removals: &[String], installed: &[PackageManifest]) -> i64 {
+    let installed_size = |name: &str| -> i64 {
+        installed.iter()
+            .find(|pkg| pkg.name == name)
+            .and_then(|pkg| pkg.installed_size)
+            .unwrap_or(0) as i64
+    };
+    
+    let added: i64 = installs.iter()
+        .map(|pkg| pkg.installed_size.map_or(0, |size| size as i64 - installed_size(&pkg.name)))
+        .sum();
+    let freed: i64 = removals.iter().map(|name| installed_size(name)).sum();
+    (added - freed).saturating_mul(1024)
+}
+
+/// Prüft, ob ein Install-Argument eine lokale Paketdatei statt eines Paketnamens ist
+pub fn is_local_package_path(arg: &str) -> bool {
+    arg.ends_with(".deb")
+        || arg.ends_with(".apx")
+        || arg.starts_with("./")
+        || arg.starts_with("../")
+        || arg.starts_with('/')
+}
+
+/// Liest die Metadaten einer lokalen .deb- oder .apx-Datei (`apt-ng install ./foo.deb`)
+///
+/// Größe und SHA256 werden aus der Datei selbst bestimmt, `filename` ist ihr Pfad.
+/// Ein Repository gibt es nicht (`repo_id` ist None).
+pub fn read_local_package(path: &Path) -> Result<PackageManifest> {
+    let mut manifest = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("apx") => ApxPackage::open(path)?.manifest,
+        Some("deb") => read_deb_control(path)?,
+        _ => return Err(anyhow::anyhow!("{} is neither a .deb nor an .apx file", path.display())),
+    };
+    manifest.validate_identity()
+        .with_context(|| path.display().to_string())?;
+    
+    manifest.size = std::fs::metadata(path)?.len();
+    manifest.checksum = crate::cache::Cache::calculate_file_checksum(path)?;
+    manifest.filename = Some(path.display().to_string());
+    manifest.repo_id = None;
+    Ok(manifest)
+}
+
+/// Liest die Control-Felder einer .deb-Datei
+fn read_deb_control(path: &Path) -> Result<PackageManifest> {
+    let control = crate::deb::DebArchive::open(path)?.control()?;
+    
+    // Die Control-Datei hat dasselbe Format wie ein Absatz der Packages-Datei
+    crate::apt_parser::parse_packages_file(&control)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("{} has no control metadata", path.display()))
+}
+
+/// Sammelt alle Dateien unterhalb von `source_dir` mit SHA256, Größe und Modus
+///
+/// Symlinks werden nicht verfolgt, sondern mit ihrem Ziel und leerer Prüfsumme erfasst.
+/// Ergebnis ist die `files`-Liste eines Manifests (sortiert nach Pfad). Verzeichnisse in
+/// `exclude` (relativ zu `source_dir`, z.B. `DEBIAN`) gehören nicht zum Paketinhalt.
+pub fn collect_file_entries(source_dir: &Path, exclude: &[&str]) -> Result<Vec<FileEntry>> {
+    use std::os::unix::fs::PermissionsExt;
+    
+    let mut files = Vec::new();
+    let mut pending = vec![source_dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let relative = path.strip_prefix(source_dir)?.to_string_lossy().to_string();
+            if exclude.contains(&relative.as_str()) {
+                continue;
+            }
+            
+            let metadata = std::fs::symlink_metadata(&path)?;
+            if metadata.is_dir() {
+                pending.push(path);
+            } else if metadata.is_file() {
+                files.push(FileEntry {
+                    path: relative,
+                    checksum: crate::cache::Cache::calculate_file_checksum(&path)?,
+                    size: metadata.len(),
+                    mode: metadata.permissions().mode(),
+                    link_target: None,
+                });
+            } else if metadata.is_symlink() {
+                files.push(FileEntry {
+                    path: relative,
+                    checksum: String::new(),
+                    size: 0,
+                    mode: metadata.permissions().mode(),
+                    link_target: Some(std::fs::read_link(&path)?.to_string_lossy().into_owned()),
+                });
+            }
+        }
+    }
+    
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
 /// Erstellt ein .apx-Paket aus einem Verzeichnis
-#[allow(dead_code)]
 pub fn create_apx_package(
     source_dir: &Path,
     manifest: PackageManifest,
     output_path: &Path,
     sign_key: Option<&[u8]>,
 ) -> Result<()> {
-    use std::io::Write;
+    let output = File::options().read(true).write(true).create(true).truncate(true).open(output_path)?;
+    write_apx_package(source_dir, &manifest, output, sign_key)
+}
+
+/// Schreibt ein .apx-Paket nach `output`
+///
+/// content.tar.zst wird direkt in die Ausgabe gestreamt und seine Länge danach
+/// eingetragen; die Signatur wird anschließend blockweise über das Geschriebene berechnet,
+/// sodass auch große Pakete nie vollständig im Speicher liegen.
+fn write_apx_package<F: Read + std::io::Write + Seek>(
+    source_dir: &Path,
+    manifest: &PackageManifest,
+    output: F,
+    sign_key: Option<&[u8]>,
+) -> Result<()> {
+    use std::io::{SeekFrom, Write};
     use tar::Builder;
     
-    let mut output = BufWriter::new(File::create(output_path)?);
-    
-    // Schreibe Header
+    let mut output = BufWriter::new(output);
     output.write_all(APX_MAGIC)?;
     
     // Komprimiere und schreibe metadata.json
-    let metadata_json = serde_json::to_string(&manifest)?;
+    let metadata_json = serde_json::to_string(manifest)?;
     let mut encoder = Encoder::new(Vec::new(), 3)?;
     encoder.write_all(metadata_json.as_bytes())?;
     let metadata_compressed = encoder.finish()?;
     
-    // Schreibe Länge von metadata.json.zst (4 bytes, little-endian)
-    let metadata_len = metadata_compressed.len() as u32;
-    output.write_all(&metadata_len.to_le_bytes())?;
+    // Schreibe Länge von metadata.json.zst (8 bytes, little-endian)
+    output.write_all(&(metadata_compressed.len() as u64).to_le_bytes())?;
     output.write_all(&metadata_compressed)?;
     
-    // Komprimiere und schreibe content.tar.zst
-    let mut content_tar = Vec::new();
+    // Platzhalter für die Länge von content.tar.zst, die erst nach dem Packen feststeht
+    let content_len_pos = output.stream_position()?;
+    output.write_all(&0u64.to_le_bytes())?;
+    
+    // Packe und komprimiere content.tar direkt in die Ausgabe
     {
-        let mut builder = Builder::new(&mut content_tar);
-        // Füge alle Dateien aus source_dir hinzu
-        for entry in std::fs::read_dir(source_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            let relative_path = path.strip_prefix(source_dir)
-                .map_err(|e| anyhow::anyhow!("Failed to get relative path: {}", e))?;
-            
-            if path.is_file() {
-                builder.append_file(relative_path, &mut File::open(&path)?)?;
-            } else if path.is_dir() {
-                builder.append_dir_all(relative_path, &path)?;
+        let mut builder = Builder::new(Encoder::new(&mut output, 3)?);
+        // Symlinks als Links packen statt ihr Ziel zu kopieren
+        builder.follow_symlinks(false);
+        if !manifest.files.is_empty() {
+            // Enthält das Manifest eine Dateiliste, wird genau diese gepackt
+            for entry in &manifest.files {
+                builder.append_path_with_name(source_dir.join(&entry.path), &entry.path)?;
+            }
+        } else {
+            // Füge alle Dateien aus source_dir hinzu
+            for entry in std::fs::read_dir(source_dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                let relative_path = path.strip_prefix(source_dir)
+                    .context("Failed to get relative path")?;
+                
+                if path.is_file() {
+                    builder.append_file(relative_path, &mut File::open(&path)?)?;
+                } else if path.is_dir() {
+                    builder.append_dir_all(relative_path, &path)?;
+                }
             }
         }
-        builder.finish()?;
+        builder.into_inner()?.finish()?;
     }
     
-    // Komprimiere content.tar
-    let mut content_encoder = Encoder::new(Vec::new(), 3)?;
-    content_encoder.write_all(&content_tar)?;
-    let content_compressed = content_encoder.finish()?;
-    
-    // Schreibe Länge von content.tar.zst (4 bytes, little-endian)
-    let content_len = content_compressed.len() as u32;
-    output.write_all(&content_len.to_le_bytes())?;
-    output.write_all(&content_compressed)?;
+    // Trage die Länge von content.tar.zst nach (8 bytes, little-endian)
+    let content_end = output.stream_position()?;
+    output.seek(SeekFrom::Start(content_len_pos))?;
+    output.write_all(&(content_end - content_len_pos - 8).to_le_bytes())?;
+    let mut output = output.into_inner().map_err(|e| e.into_error())?;
     
     // Füge Signatur hinzu, falls Schlüssel vorhanden
     if let Some(key_bytes) = sign_key {
@@ -288,14 +525,14 @@ pub fn create_apx_package(
         let signing_key = SigningKey::from_bytes(key_bytes.try_into()
             .map_err(|_| anyhow::anyhow!("Invalid signing key length"))?);
         
-        // Signiere metadata + content
-        let mut data_to_sign = Vec::new();
-        data_to_sign.extend_from_slice(&metadata_compressed);
-        data_to_sign.extend_from_slice(&content_compressed);
-        
-        let signature = signing_key.sign(&data_to_sign);
+        // Signiere den Hash über Header, Längenfelder, metadata und content
+        let mut hasher = Sha256::new();
+        output.rewind()?;
+        std::io::copy(&mut (&mut output).take(content_end), &mut hasher)?;
+        let signature = signing_key.sign(&hasher.finalize());
         
         // Schreibe Signatur (64 bytes)
+        output.seek(SeekFrom::Start(content_end))?;
         output.write_all(signature.to_bytes().as_slice())?;
     }
     
@@ -306,7 +543,6 @@ pub fn create_apx_package(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::TempDir;
     
     #[test]
     fn test_manifest_serialization() {
@@ -322,12 +558,360 @@ mod tests {
             size: 1024,
             checksum: "abc123".to_string(),
             timestamp: 1234567890,
+            filename: None,
+            repo_id: None,
+            maintainer: None,
+            homepage: None,
+            section: None,
+            priority: None,
+            installed_size: None,
+            recommends: vec![],
+            suggests: vec![],
         };
         
         let json = serde_json::to_string(&manifest).unwrap();
         let parsed: PackageManifest = serde_json::from_str(&json).unwrap();
         assert_eq!(parsed.name, "test-package");
     }
+    
+    #[test]
+    fn test_manifest_without_optional_fields() {
+        // Ältere Manifeste ohne Maintainer/Homepage/Section/Priority müssen weiterhin lesbar sein
+        let json = r#"{"name":"old","version":"1.0","arch":"all","provides":[],"depends":[],
+            "conflicts":[],"replaces":[],"files":[],"size":0,"checksum":"","timestamp":0}"#;
+        let parsed: PackageManifest = serde_json::from_str(json).unwrap();
+        assert!(parsed.maintainer.is_none());
+        assert!(parsed.section.is_none());
+    }
+    
+    #[test]
+    fn test_read_local_apx_package() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let content = dir.path().join("content");
+        std::fs::create_dir_all(content.join("usr/bin")).unwrap();
+        std::fs::write(content.join("usr/bin/hello"), b"#!/bin/sh\necho hello\n").unwrap();
+        
+        let manifest = PackageManifest {
+            name: "hello".to_string(),
+            version: "1.0-1".to_string(),
+            arch: "amd64".to_string(),
+            provides: vec![],
+            depends: vec!["libc6 (>= 2.31)".to_string()],
+            conflicts: vec![],
+            replaces: vec![],
+            files: vec![],
+            size: 0,
+            checksum: String::new(),
+            timestamp: 0,
+            filename: None,
+            repo_id: Some(3),
+            maintainer: None,
+            homepage: None,
+            section: None,
+            priority: None,
+            installed_size: None,
+            recommends: vec![],
+            suggests: vec![],
+        };
+        let apx_path = dir.path().join("hello_1.0-1_amd64.apx");
+        create_apx_package(&content, manifest, &apx_path, None).unwrap();
+        
+        let local = read_local_package(&apx_path).unwrap();
+        assert_eq!(local.name, "hello");
+        assert_eq!(local.depends, ["libc6 (>= 2.31)"]);
+        assert_eq!(local.size, std::fs::metadata(&apx_path).unwrap().len());
+        assert_eq!(local.checksum, crate::cache::Cache::calculate_file_checksum(&apx_path).unwrap());
+        assert_eq!(local.filename.as_deref(), apx_path.to_str());
+        assert_eq!(local.repo_id, None);
+        
+        assert!(is_local_package_path("./hello_1.0-1_amd64.deb"));
+        assert!(is_local_package_path("/tmp/hello"));
+        assert!(!is_local_package_path("hello"));
+        assert!(read_local_package(&dir.path().join("hello.txt")).is_err());
+    }
+    
+    #[test]
+    fn test_apx_round_trip_preserves_files_and_modes() {
+        use std::os::unix::fs::PermissionsExt;
+        use ed25519_dalek::SigningKey;
+        
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("usr/bin")).unwrap();
+        std::fs::create_dir_all(root.join("usr/share/doc/hello")).unwrap();
+        std::fs::create_dir_all(root.join("DEBIAN")).unwrap();
+        std::fs::write(root.join("usr/bin/hello"), b"#!/bin/sh\necho hello\n").unwrap();
+        std::fs::set_permissions(root.join("usr/bin/hello"), std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(root.join("usr/share/doc/hello/README"), b"hello docs\n").unwrap();
+        std::fs::write(root.join("DEBIAN/control"), b"Package: hello\nVersion: 1.0-1\n").unwrap();
+        std::os::unix::fs::symlink("hello", root.join("usr/bin/hi")).unwrap();
+        
+        let files = collect_file_entries(&root, &["DEBIAN"]).unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["usr/bin/hello", "usr/bin/hi", "usr/share/doc/hello/README"]);
+        assert_eq!(files[1].link_target.as_deref(), Some("hello"));
+        assert!(files[0].link_target.is_none());
+        
+        let mut manifest = crate::apt_parser::parse_packages_file("Package: hello\nVersion: 1.0-1\nArchitecture: amd64\n")
+            .unwrap()
+            .remove(0);
+        manifest.files = files;
+        
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let apx_path = dir.path().join("hello.apx");
+        create_apx_package(&root, manifest, &apx_path, Some(&signing_key.to_bytes())).unwrap();
+        
+        let apx = ApxPackage::open(&apx_path).unwrap();
+        assert_eq!(apx.manifest.name, "hello");
+        assert_eq!(apx.manifest.files.len(), 3);
+        
+        let extracted = dir.path().join("extracted");
+        apx.extract_to(&extracted).unwrap();
+        apx.verify_checksums(&extracted).unwrap();
+        assert!(!extracted.join("DEBIAN").exists());
+        let mode = std::fs::metadata(extracted.join("usr/bin/hello")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert_eq!(std::fs::read_link(extracted.join("usr/bin/hi")).unwrap(), Path::new("hello"));
+        
+        let keys_dir = dir.path().join("keys");
+        std::fs::create_dir_all(&keys_dir).unwrap();
+        let mut verifier = crate::verifier::PackageVerifier::new(&keys_dir).unwrap();
+        verifier.add_trusted_key(signing_key.verifying_key().as_bytes()).unwrap();
+        apx.verify_signature(&apx_path, &verifier).unwrap();
+    }
+    
+    #[test]
+    fn test_apx_lengths_are_64_bit_and_v1_still_opens() {
+        // Längenfelder über 4 GiB dürfen nicht mehr abgeschnitten werden
+        let large = 5 * 1024 * 1024 * 1024u64;
+        let mut header = APX_MAGIC.to_vec();
+        header.extend_from_slice(&large.to_le_bytes());
+        let mut reader = &header[..];
+        let version = read_apx_header(&mut reader).unwrap();
+        assert_eq!(version, APX_MAGIC[3]);
+        assert_eq!(read_apx_length(&mut reader, version).unwrap(), large);
+        // ...und eine unplausibel große metadata-Länge wird abgelehnt statt alloziert
+        assert!(read_apx_metadata(&mut &header[4..], version).is_err());
+        assert!(read_apx_header(&mut &b"APX\x04"[..]).is_err());
+        
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("usr/bin")).unwrap();
+        std::fs::write(root.join("usr/bin/hello"), b"hello\n").unwrap();
+        let manifest = crate::apt_parser::parse_packages_file("Package: hello\nVersion: 1.0-1\nArchitecture: amd64\n")
+            .unwrap()
+            .remove(0);
+        let current_path = dir.path().join("hello-current.apx");
+        create_apx_package(&root, manifest, &current_path, None).unwrap();
+        
+        // Schreibe dasselbe Paket im alten Format mit u32-Längen
+        let current = std::fs::read(&current_path).unwrap();
+        assert_eq!(&current[..4], APX_MAGIC);
+        let mut reader = &current[4..];
+        let metadata = read_apx_metadata(&mut reader, APX_MAGIC[3]).unwrap();
+        let content_len = read_apx_length(&mut reader, APX_MAGIC[3]).unwrap();
+        let content = &reader[..content_len as usize];
+        let mut v1 = b"APX\x01".to_vec();
+        v1.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+        v1.extend_from_slice(&metadata);
+        v1.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        v1.extend_from_slice(content);
+        let v1_path = dir.path().join("hello-v1.apx");
+        std::fs::write(&v1_path, v1).unwrap();
+        
+        let apx = ApxPackage::open(&v1_path).unwrap();
+        assert_eq!(apx.manifest.name, "hello");
+        let extracted = dir.path().join("extracted");
+        apx.extract_to(&extracted).unwrap();
+        assert_eq!(std::fs::read(extracted.join("usr/bin/hello")).unwrap(), b"hello\n");
+    }
+    
+    #[test]
+    fn test_large_apx_package_extracts_streaming() {
+        // 256 MiB Nutzdaten: extract_to dekomprimiert direkt aus der Datei
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("usr/share/big")).unwrap();
+        let big = std::fs::File::create(root.join("usr/share/big/data.bin")).unwrap();
+        big.set_len(256 * 1024 * 1024).unwrap();
+        std::fs::write(root.join("usr/share/big/README"), b"large package\n").unwrap();
+        
+        let mut manifest = crate::apt_parser::parse_packages_file("Package: big\nVersion: 1.0-1\nArchitecture: all\n")
+            .unwrap()
+            .remove(0);
+        manifest.files = collect_file_entries(&root, &[]).unwrap();
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[9u8; 32]);
+        let apx_path = dir.path().join("big.apx");
+        create_apx_package(&root, manifest, &apx_path, Some(&signing_key.to_bytes())).unwrap();
+        
+        let apx = ApxPackage::open(&apx_path).unwrap();
+        let extracted = dir.path().join("extracted");
+        apx.extract_to(&extracted).unwrap();
+        assert_eq!(std::fs::metadata(extracted.join("usr/share/big/data.bin")).unwrap().len(), 256 * 1024 * 1024);
+        assert_eq!(std::fs::read(extracted.join("usr/share/big/README")).unwrap(), b"large package\n");
+        
+        let keys_dir = dir.path().join("keys");
+        std::fs::create_dir_all(&keys_dir).unwrap();
+        let mut verifier = crate::verifier::PackageVerifier::new(&keys_dir).unwrap();
+        verifier.add_trusted_key(signing_key.verifying_key().as_bytes()).unwrap();
+        apx.verify_signature(&apx_path, &verifier).unwrap();
+    }
+    
+    /// Datei, die sich die größte einzelne Schreib- bzw. Leseoperation merkt
+    struct ChunkRecorder {
+        file: File,
+        largest_write: usize,
+        largest_read: usize,
+    }
+    
+    impl std::io::Write for ChunkRecorder {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.largest_write = self.largest_write.max(buf.len());
+            self.file.write(buf)
+        }
+        
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.file.flush()
+        }
+    }
+    
+    impl Read for ChunkRecorder {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let read = self.file.read(buf)?;
+            self.largest_read = self.largest_read.max(read);
+            Ok(read)
+        }
+    }
+    
+    impl Seek for ChunkRecorder {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.file.seek(pos)
+        }
+    }
+    
+    #[test]
+    fn test_apx_payload_is_written_in_chunks() {
+        // 16 MiB nicht komprimierbare Nutzdaten: ein im Speicher gepacktes content.tar.zst
+        // landete in einem einzigen Schreibvorgang dieser Größe
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("usr/share/big")).unwrap();
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let payload: Vec<u8> = (0..16 * 1024 * 1024).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        }).collect();
+        std::fs::write(root.join("usr/share/big/data.bin"), &payload).unwrap();
+        
+        let mut manifest = crate::apt_parser::parse_packages_file("Package: big\nVersion: 1.0-1\nArchitecture: all\n")
+            .unwrap()
+            .remove(0);
+        manifest.files = collect_file_entries(&root, &[]).unwrap();
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[3u8; 32]);
+        let apx_path = dir.path().join("big.apx");
+        let mut recorder = ChunkRecorder {
+            file: File::options().read(true).write(true).create(true).truncate(true).open(&apx_path).unwrap(),
+            largest_write: 0,
+            largest_read: 0,
+        };
+        write_apx_package(&root, &manifest, &mut recorder, Some(&signing_key.to_bytes())).unwrap();
+        assert!(recorder.largest_write <= 1024 * 1024, "largest write: {} bytes", recorder.largest_write);
+        assert!(recorder.largest_read > 0 && recorder.largest_read <= 1024 * 1024, "largest read: {} bytes", recorder.largest_read);
+        
+        let apx = ApxPackage::open(&apx_path).unwrap();
+        let extracted = dir.path().join("extracted");
+        apx.extract_to(&extracted).unwrap();
+        assert!(std::fs::read(extracted.join("usr/share/big/data.bin")).unwrap() == payload);
+        
+        let keys_dir = dir.path().join("keys");
+        std::fs::create_dir_all(&keys_dir).unwrap();
+        let mut verifier = crate::verifier::PackageVerifier::new(&keys_dir).unwrap();
+        verifier.add_trusted_key(signing_key.verifying_key().as_bytes()).unwrap();
+        apx.verify_signature(&apx_path, &verifier).unwrap();
+    }
+    
+    #[test]
+    fn test_apx_signature_covers_header_and_lengths() {
+        use ed25519_dalek::SigningKey;
+        
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("usr/bin")).unwrap();
+        std::fs::write(root.join("usr/bin/hello"), b"hello\n").unwrap();
+        let manifest = crate::apt_parser::parse_packages_file("Package: hello\nVersion: 1.0-1\nArchitecture: amd64\n")
+            .unwrap()
+            .remove(0);
+        let signing_key = SigningKey::from_bytes(&[5u8; 32]);
+        let apx_path = dir.path().join("hello.apx");
+        create_apx_package(&root, manifest, &apx_path, Some(&signing_key.to_bytes())).unwrap();
+        
+        let keys_dir = dir.path().join("keys");
+        std::fs::create_dir_all(&keys_dir).unwrap();
+        let mut verifier = crate::verifier::PackageVerifier::new(&keys_dir).unwrap();
+        verifier.add_trusted_key(signing_key.verifying_key().as_bytes()).unwrap();
+        let apx = ApxPackage::open(&apx_path).unwrap();
+        apx.verify_signature(&apx_path, &verifier).unwrap();
+        
+        let original = std::fs::read(&apx_path).unwrap();
+        let tampered_path = dir.path().join("tampered.apx");
+        let tampered = |patch: &dyn Fn(&mut Vec<u8>)| {
+            let mut bytes = original.clone();
+            patch(&mut bytes);
+            std::fs::write(&tampered_path, &bytes).unwrap();
+            apx.verify_signature(&tampered_path, &verifier)
+        };
+        
+        // Geänderte Längenfelder (hier: oberstes Byte der content-Länge)
+        let metadata_len = u64::from_le_bytes(original[4..12].try_into().unwrap()) as usize;
+        assert!(tampered(&|bytes| bytes[12 + metadata_len + 7] ^= 0x01).is_err());
+        assert!(tampered(&|bytes| bytes[11] ^= 0x01).is_err());
+        // Herabstufen auf v2 (alte Signaturregel) darf die Prüfung nicht umgehen
+        assert!(tampered(&|bytes| bytes[3] = 2).is_err());
+        // Die unveränderte Kopie ist weiterhin gültig
+        assert!(tampered(&|_| {}).is_ok());
+    }
+    
+    #[test]
+    fn test_validate_package_fields() {
+        assert!(validate_package_name("libstdc++6").is_ok());
+        assert!(validate_package_name("python3.11-minimal").is_ok());
+        assert!(validate_package_name("../etc").is_err());
+        assert!(validate_package_name("Hello").is_err());
+        assert!(validate_package_name("").is_err());
+        
+        assert!(validate_version("1:2.36-9+deb12u4~bpo1").is_ok());
+        assert!(validate_version("1.0/../../x").is_err());
+        assert!(validate_architecture("amd64").is_ok());
+        assert!(validate_architecture("all/..").is_err());
+        
+        assert!(validate_repo_filename("pool/main/h/hello/hello_2.10-3_amd64.deb").is_ok());
+        assert!(validate_repo_filename("pool/main/s/systemd/libsystemd0_252.22-1%7edeb12u1_amd64.deb").is_ok());
+        // Präparierte Filename-Felder aus einem manipulierten Index
+        assert!(validate_repo_filename("../../../etc/cron.d/evil.deb").is_err());
+        assert!(validate_repo_filename("pool/main/../../../../root/.ssh/authorized_keys").is_err());
+        assert!(validate_repo_filename("/etc/passwd").is_err());
+        assert!(validate_repo_filename("pool//hello.deb").is_err());
+        assert!(validate_repo_filename("pool/main/hello.deb?x=1").is_err());
+    }
+    
+    #[test]
+    fn test_disk_usage_delta() {
+        let installed = crate::apt_parser::parse_packages_file(
+            "Package: old-lib\nVersion: 1.0\nInstalled-Size: 300\n\n\
+             Package: tool\nVersion: 1.0\nInstalled-Size: 100\n"
+        ).unwrap();
+        let plan = crate::apt_parser::parse_packages_file(
+            "Package: tool\nVersion: 2.0\nInstalled-Size: 150\n\n\
+             Package: new-lib\nVersion: 1.0\nInstalled-Size: 20\n\n\
+             Package: unknown-size\nVersion: 1.0\n"
+        ).unwrap();
+        assert_eq!(plan[0].installed_size, Some(150));
+        
+        // +50 KiB Upgrade, +20 KiB neu, -300 KiB entfernt
+        assert_eq!(disk_usage_delta(&plan, &["old-lib".to_string()], &installed), -230 * 1024);
+        assert_eq!(disk_usage_delta(&plan, &[], &installed), 70 * 1024);
+        assert_eq!(disk_usage_delta(&[], &["tool".to_string(), "not-installed".to_string()], &installed), -100 * 1024);
+    }
 }
-
-
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use crate::downloader::MirrorStats;
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -12,16 +13,192 @@ pub struct Repository {
     pub enabled: bool,
     pub last_probe_ms: Option<u64>,
     pub rtt_ms: Option<u64>,
+    pub throughput_bps: Option<u64>,
     pub suite: Option<String>,
     pub components: Vec<String>,
+    /// Codename aus der Release-Datei des Repositories (z.B. "bookworm")
+    pub codename: Option<String>,
+    /// Architekturen, deren Paketindizes geladen werden (leer: `Config::architectures`)
+    #[serde(default)]
+    pub architectures: Vec<String>,
+    /// Schlüsselbund, gegen den allein die Release-Datei geprüft wird (`signed-by=`)
+    #[serde(default)]
+    pub signed_by: Option<String>,
+    /// `Some(true)`: Signaturen nicht prüfen, `Some(false)`: immer prüfen, `None`: globale Vorgabe
+    #[serde(default)]
+    pub trusted: Option<bool>,
+}
+
+/// Wie `update` die Release-Datei eines Repositories prüft
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignaturePolicy {
+    /// Nur gegen die Schlüssel aus diesem Schlüsselbund
+    SignedBy(std::path::PathBuf),
+    /// Gegen alle vertrauenswürdigen Schlüssel
+    Required,
+    /// Keine Signaturprüfung, das Repository ist bewusst als vertrauenswürdig markiert
+    Skip,
+}
+
+/// Zugangsdaten für ein Repository
+#[derive(Debug, Clone, PartialEq)]
+pub enum Credentials {
+    Basic { login: String, password: String },
+    Bearer(String),
+}
+
+/// Ein Eintrag aus /etc/apt/auth.conf bzw. auth.conf.d/ (netrc-Format) oder ein Token aus der Config
+#[derive(Debug, Clone)]
+pub struct AuthEntry {
+    /// `host[:port][/path]` (nur für https), optional mit Schema (`https://host/path`)
+    pub machine: String,
+    pub credentials: Credentials,
+}
+
+impl AuthEntry {
+    /// Prüft, ob der Eintrag für die URL gilt (Host und Pfad-Präfix wie bei apt)
+    fn matches(&self, url: &str) -> bool {
+        let machine = self.machine.trim_end_matches('/');
+        // Mit Schema muss auch das Schema übereinstimmen; ohne Schema gilt der Eintrag wie
+        // bei apt nur für https, damit Zugangsdaten nie unverschlüsselt übertragen werden
+        let target = if machine.contains("://") {
+            url
+        } else {
+            match url.strip_prefix("https://") {
+                Some(rest) => rest,
+                None => return false,
+            }
+        };
+        
+        match target.strip_prefix(machine) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+}
+
+/// Alle bekannten Zugangsdaten für Repositories
+#[derive(Debug, Clone, Default)]
+pub struct RepoAuth {
+    entries: Vec<AuthEntry>,
+}
+
+impl RepoAuth {
+    /// Lädt `<apt_dir>/auth.conf` und `<apt_dir>/auth.conf.d/*.conf`.
+    /// Tokens aus der Config (url, token) haben Vorrang vor gleich spezifischen auth.conf-Einträgen.
+    pub fn load(apt_dir: &Path, tokens: &[(String, String)]) -> Self {
+        let mut auth = RepoAuth::default();
+        
+        for (url, token) in tokens {
+            auth.entries.push(AuthEntry {
+                machine: url.clone(),
+                credentials: Credentials::Bearer(token.clone()),
+            });
+        }
+        
+        let mut files = vec![apt_dir.join("auth.conf")];
+        if let Ok(entries) = fs::read_dir(apt_dir.join("auth.conf.d")) {
+            let mut conf_files: Vec<_> = entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("conf"))
+                .collect();
+            conf_files.sort();
+            files.extend(conf_files);
+        }
+        
+        for file in files {
+            // Nicht lesbare Dateien (z.B. ohne root-Rechte) werden übersprungen
+            if let Ok(content) = fs::read_to_string(&file) {
+                auth.entries.extend(Self::parse_auth_conf(&content));
+            }
+        }
+        
+        auth
+    }
+    
+    /// Parst eine auth.conf im netrc-Format (machine/login/password)
+    pub fn parse_auth_conf(content: &str) -> Vec<AuthEntry> {
+        let tokens: Vec<&str> = content
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(|line| line.split_whitespace())
+            .collect();
+        
+        let mut entries = Vec::new();
+        let mut machine: Option<&str> = None;
+        let mut login: Option<&str> = None;
+        let mut password: Option<&str> = None;
+        
+        let mut flush = |machine: &mut Option<&str>, login: &mut Option<&str>, password: &mut Option<&str>| {
+            if let (Some(m), Some(l), Some(p)) = (machine.take(), login.take(), password.take()) {
+                entries.push(AuthEntry {
+                    machine: m.to_string(),
+                    credentials: Credentials::Basic { login: l.to_string(), password: p.to_string() },
+                });
+            }
+            *login = None;
+            *password = None;
+        };
+        
+        let mut iter = tokens.into_iter();
+        while let Some(token) = iter.next() {
+            match token {
+                "machine" => {
+                    flush(&mut machine, &mut login, &mut password);
+                    machine = iter.next();
+                }
+                "login" => login = iter.next(),
+                "password" => password = iter.next(),
+                _ => {}
+            }
+        }
+        flush(&mut machine, &mut login, &mut password);
+        
+        entries
+    }
+    
+    /// Liefert die Zugangsdaten für eine URL (spezifischster Eintrag gewinnt)
+    pub fn credentials_for(&self, url: &str) -> Option<&Credentials> {
+        let mut best: Option<&AuthEntry> = None;
+        for entry in self.entries.iter().filter(|e| e.matches(url)) {
+            if best.is_none_or(|b| entry.machine.len() > b.machine.len()) {
+                best = Some(entry);
+            }
+        }
+        best.map(|e| &e.credentials)
+    }
 }
 
 impl Repository {
+    /// Neues Repository wie bei `apt-ng repo add`
+    ///
+    /// Ohne Suite wird sie beim `update` erkannt, ohne Komponenten gilt `main`, ohne
+    /// Architekturen `Config::architectures`.
+    pub fn new(url: &str, suite: Option<&str>, components: &[String], architectures: &[String]) -> Self {
+        Repository {
+            id: None,
+            url: url.to_string(),
+            priority: 500,
+            enabled: true,
+            last_probe_ms: None,
+            rtt_ms: None,
+            throughput_bps: None,
+            codename: None,
+            suite: suite.map(str::to_string),
+            components: if components.is_empty() { vec!["main".to_string()] } else { components.to_vec() },
+            architectures: architectures.to_vec(),
+            signed_by: None,
+            trusted: None,
+        }
+    }
+    
     /// Fügt ein Repository zur Datenbank hinzu
     pub fn add_to_db(conn: &Connection, repo: &Repository) -> Result<()> {
         conn.execute(
-            "INSERT OR REPLACE INTO repos (url, priority, last_probe_ms, rtt_ms, enabled, suite, components)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            "INSERT OR REPLACE INTO repos (url, priority, last_probe_ms, rtt_ms, enabled, suite, components, throughput_bps, codename, architectures,
+             signed_by, trusted)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
             rusqlite::params![
                 repo.url,
                 repo.priority,
@@ -29,42 +206,30 @@ impl Repository {
                 repo.rtt_ms,
                 if repo.enabled { 1 } else { 0 },
                 repo.suite.as_ref(),
-                serde_json::to_string(&repo.components).ok()
+                serde_json::to_string(&repo.components).ok(),
+                repo.throughput_bps,
+                repo.codename.as_ref(),
+                serde_json::to_string(&repo.architectures).ok(),
+                repo.signed_by.as_ref(),
+                repo.trusted
             ],
         )?;
         Ok(())
     }
     
     /// Entfernt ein Repository aus der Datenbank
-    #[allow(dead_code)]
     pub fn remove_from_db(conn: &Connection, url: &str) -> Result<()> {
         conn.execute("DELETE FROM repos WHERE url = ?1", [url])?;
         Ok(())
     }
     
-    /// Lädt alle Repositories aus der Datenbank
+    /// Lädt alle aktivierten Repositories aus der Datenbank
     pub fn load_all(conn: &Connection) -> Result<Vec<Repository>> {
         let mut stmt = conn.prepare(
-            "SELECT id, url, priority, last_probe_ms, rtt_ms, enabled, suite, components FROM repos WHERE enabled = 1 ORDER BY priority ASC, rtt_ms ASC"
+            "SELECT id, url, priority, last_probe_ms, rtt_ms, enabled, suite, components, throughput_bps, codename, architectures, signed_by, trusted FROM repos WHERE enabled = 1 ORDER BY priority ASC, rtt_ms ASC"
         )?;
         
-        let repos = stmt.query_map([], |row| {
-            let components_str: Option<String> = row.get(7)?;
-            let components = components_str
-                .map(|s| serde_json::from_str(&s).unwrap_or_default())
-                .unwrap_or_default();
-            
-            Ok(Repository {
-                id: row.get(0)?,
-                url: row.get(1)?,
-                priority: row.get(2)?,
-                enabled: row.get::<_, i32>(5)? != 0,
-                last_probe_ms: row.get(3)?,
-                rtt_ms: row.get(4)?,
-                suite: row.get(6)?,
-                components,
-            })
-        })?;
+        let repos = stmt.query_map([], Self::from_row)?;
         
         let mut result = Vec::new();
         for repo in repos {
@@ -73,41 +238,197 @@ impl Repository {
         Ok(result)
     }
     
+    /// Lädt alle Repositories inklusive deaktivierter (für `repo list`)
+    pub fn load_all_including_disabled(conn: &Connection) -> Result<Vec<Repository>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, url, priority, last_probe_ms, rtt_ms, enabled, suite, components, throughput_bps, codename, architectures, signed_by, trusted FROM repos ORDER BY enabled DESC, priority ASC, rtt_ms ASC"
+        )?;
+        
+        let repos = stmt.query_map([], Self::from_row)?;
+        
+        let mut result = Vec::new();
+        for repo in repos {
+            result.push(repo?);
+        }
+        Ok(result)
+    }
+    
+    /// Aktiviert oder deaktiviert ein Repository, ohne es zu löschen.
+    /// Gibt `false` zurück, wenn kein Repository mit dieser URL existiert.
+    pub fn set_enabled(conn: &Connection, url: &str, enabled: bool) -> Result<bool> {
+        let changed = conn.execute(
+            "UPDATE repos SET enabled = ?1 WHERE url = ?2",
+            rusqlite::params![if enabled { 1 } else { 0 }, url],
+        )?;
+        Ok(changed > 0)
+    }
+    
+    /// Speichert den aus der Release-Datei ermittelten Codename
+    pub fn set_codename(conn: &Connection, url: &str, codename: &str) -> Result<()> {
+        conn.execute(
+            "UPDATE repos SET codename = ?1 WHERE url = ?2",
+            rusqlite::params![codename, url],
+        )?;
+        Ok(())
+    }
+    
+    /// Prüft, ob das Repository im lokalen Dateisystem liegt (file:// oder copy://)
+    pub fn is_local(&self) -> bool {
+        crate::downloader::local_path(&self.url).is_some()
+    }
+    
+    /// Suite-Pfad unter `dists/`: aus sources.list, sonst der gespeicherte Codename
+    pub fn suite_path(&self) -> Option<&str> {
+        self.suite.as_deref().or(self.codename.as_deref())
+    }
+    
+    /// Suites unter `dists/`, die `update` der Reihe nach probiert
+    ///
+    /// Ohne Suite aus sources.list oder gespeicherten Codename gelten die Konventionen des
+    /// Archivs: Sicherheitsarchive (`security.debian.org`, `security.ubuntu.com`) veröffentlichen
+    /// `<codename>-security` (ältere Debian-Releases `<codename>/updates`), Ubuntu-Archive und
+    /// PPAs kennen nur Codenamen, Debian-Mirrors zusätzlich `stable`. Mirrors, die sich nicht
+    /// an der URL erkennen lassen, folgen der laufenden Distribution.
+    pub fn suite_candidates(&self, distro: &crate::system::Distro) -> Vec<String> {
+        if let Some(suite) = self.suite_path() {
+            return vec![suite.to_string()];
+        }
+        
+        let url = self.url.to_ascii_lowercase();
+        let ubuntu = is_ubuntu_archive(&url) || (distro.is_ubuntu() && !url.contains("debian"));
+        let codename = match &distro.ubuntu_codename {
+            Some(ubuntu_codename) if ubuntu => ubuntu_codename.as_str(),
+            _ => distro.codename.as_str(),
+        };
+        let security = base_url(&url).contains("://security.") || url.contains("-security");
+        
+        match (security, ubuntu) {
+            (true, true) => vec![format!("{}-security", codename)],
+            (true, false) => vec![format!("{}-security", codename), format!("{}/updates", codename)],
+            (false, true) => vec![codename.to_string()],
+            (false, false) => {
+                let mut candidates = vec![codename.to_string()];
+                if codename != "stable" {
+                    candidates.push("stable".to_string());
+                }
+                candidates
+            }
+        }
+    }
+    
+    /// Datei, gegen die `repo update` RTT und Throughput misst: die Release-Datei der ersten Suite
+    pub fn probe_url(&self, distro: &crate::system::Distro) -> String {
+        if self.is_local() {
+            return self.url.clone();
+        }
+        let suite = self.suite_candidates(distro).remove(0);
+        format!("{}/dists/{}/Release", self.url.trim_end_matches('/'), suite)
+    }
+    
+    /// Signaturrichtlinie für `update`
+    ///
+    /// `trusted=yes` schaltet die Prüfung ab, ein `signed-by`-Schlüsselbund beschränkt sie auf
+    /// dessen Schlüssel, `trusted=no` erzwingt sie. Alle übrigen Repositories folgen
+    /// `require_by_default`.
+    pub fn signature_policy(&self, require_by_default: bool) -> SignaturePolicy {
+        match (self.trusted, &self.signed_by) {
+            (Some(true), _) => SignaturePolicy::Skip,
+            (_, Some(keyring)) => SignaturePolicy::SignedBy(keyring.into()),
+            (Some(false), None) => SignaturePolicy::Required,
+            (None, None) if require_by_default => SignaturePolicy::Required,
+            (None, None) => SignaturePolicy::Skip,
+        }
+    }
+    
+    /// Gehört das Repository zum Release `release` (Suite oder Codename, für `--target-release`)?
+    pub fn matches_release(&self, release: &str) -> bool {
+        self.suite.as_deref() == Some(release) || self.codename.as_deref() == Some(release)
+    }
+    
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Repository> {
+        let json_list = |idx: usize| -> rusqlite::Result<Vec<String>> {
+            Ok(row.get::<_, Option<String>>(idx)?
+                .map(|s| serde_json::from_str(&s).unwrap_or_default())
+                .unwrap_or_default())
+        };
+        let components = json_list(7)?;
+        
+        Ok(Repository {
+            id: row.get(0)?,
+            url: row.get(1)?,
+            priority: row.get(2)?,
+            enabled: row.get::<_, i32>(5)? != 0,
+            last_probe_ms: row.get(3)?,
+            rtt_ms: row.get(4)?,
+            throughput_bps: row.get(8)?,
+            suite: row.get(6)?,
+            components,
+            codename: row.get(9)?,
+            architectures: json_list(10)?,
+            signed_by: row.get(11)?,
+            trusted: row.get(12)?,
+        })
+    }
+    
     /// Wählt das beste Repository basierend auf Performance aus
-    #[allow(dead_code)]
+    /// (Priorität, dann derselbe Score aus RTT und Throughput wie `MirrorStats::score`)
     pub fn select_best_mirror(conn: &Connection, base_url: &str) -> Result<Option<Repository>> {
         // Finde alle Repositories mit ähnlicher Base-URL (verschiedene Mirrors)
         let mut stmt = conn.prepare(
-            "SELECT id, url, priority, last_probe_ms, rtt_ms, enabled, suite, components 
+            "SELECT id, url, priority, last_probe_ms, rtt_ms, enabled, suite, components, throughput_bps, codename, architectures, signed_by, trusted
              FROM repos 
              WHERE enabled = 1 AND url LIKE ?1
-             ORDER BY priority ASC, rtt_ms ASC, last_probe_ms DESC
-             LIMIT 1"
+             ORDER BY priority ASC, last_probe_ms DESC"
         )?;
         
         let pattern = format!("{}%", base_url);
-        let result = stmt.query_row([&pattern], |row| {
-            let components_str: Option<String> = row.get(7)?;
-            let components = components_str
-                .map(|s| serde_json::from_str(&s).unwrap_or_default())
-                .unwrap_or_default();
-            
-            Ok(Repository {
-                id: row.get(0)?,
-                url: row.get(1)?,
-                priority: row.get(2)?,
-                enabled: row.get::<_, i32>(5)? != 0,
-                last_probe_ms: row.get(3)?,
-                rtt_ms: row.get(4)?,
-                suite: row.get(6)?,
-                components,
-            })
-        });
+        let mut candidates = Vec::new();
+        for repo in stmt.query_map([&pattern], Self::from_row)? {
+            candidates.push(repo?);
+        }
         
-        match result {
-            Ok(repo) => Ok(Some(repo)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e.into()),
+        // min_by liefert bei Gleichstand das erste Element, die SQL-Sortierung bleibt also Tiebreaker
+        Ok(candidates.into_iter().min_by(|a, b| {
+            a.priority.cmp(&b.priority)
+                .then(a.score().partial_cmp(&b.score()).unwrap_or(std::cmp::Ordering::Equal))
+        }))
+    }
+    
+    /// Aktivierte Repositories, die dasselbe Archiv wie `repo_id` spiegeln, inklusive ihm selbst
+    ///
+    /// Als Mirror gilt ein Repository mit gleicher Suite und gleichem Codename; Pfade wie
+    /// `pool/...` sind dort identisch. Ohne Suite oder Codename bleibt nur das Repository
+    /// selbst. Sortiert wie bei `select_best_mirror` (Priorität, dann Score).
+    pub fn mirrors_of(conn: &Connection, repo_id: i64) -> Result<Vec<Repository>> {
+        let repos = Self::load_all(conn)?;
+        let Some(repo) = repos.iter().find(|r| r.id == Some(repo_id)).cloned() else {
+            return Ok(Vec::new());
+        };
+        
+        let same_archive = |other: &Repository| {
+            repo.suite.is_some() && repo.codename.is_some()
+                && other.suite == repo.suite && other.codename == repo.codename
+        };
+        let mut mirrors: Vec<Repository> = repos.into_iter()
+            .filter(|other| other.id == repo.id || same_archive(other))
+            .collect();
+        mirrors.sort_by(|a, b| {
+            a.priority.cmp(&b.priority)
+                .then(a.score().partial_cmp(&b.score()).unwrap_or(std::cmp::Ordering::Equal))
+        });
+        Ok(mirrors)
+    }
+    
+    /// Score für die Mirror-Auswahl (niedriger ist besser). Noch nicht geprobte Mirrors kommen zuletzt.
+    pub fn score(&self) -> f64 {
+        match self.rtt_ms {
+            Some(rtt_ms) => MirrorStats {
+                url: self.url.clone(),
+                rtt_ms,
+                throughput: self.throughput_bps.unwrap_or(0),
+                failed: false,
+            }.score(),
+            None => f64::INFINITY,
         }
     }
     
@@ -116,15 +437,17 @@ impl Repository {
         conn: &Connection,
         url: &str,
         rtt_ms: u64,
+        throughput_bps: u64,
     ) -> Result<()> {
         conn.execute(
-            "UPDATE repos SET last_probe_ms = ?1, rtt_ms = ?2 WHERE url = ?3",
+            "UPDATE repos SET last_probe_ms = ?1, rtt_ms = ?2, throughput_bps = ?3 WHERE url = ?4",
             rusqlite::params![
                 std::time::SystemTime::now()
                     .duration_since(std::time::UNIX_EPOCH)
                     .unwrap()
                     .as_millis() as u64,
                 rtt_ms,
+                throughput_bps,
                 url
             ],
         )?;
@@ -216,9 +539,25 @@ impl Repository {
         }
         idx += 1;
         
-        // Überspringe [options] falls vorhanden
+        // [options] auswerten: arch= schränkt die Architekturen ein, signed-by= legt den
+        // Schlüsselbund fest, trusted= schaltet die Signaturprüfung ab bzw. erzwingt sie,
+        // der Rest wird übersprungen
+        let mut architectures = Vec::new();
+        let mut signed_by = None;
+        let mut trusted = None;
         if parts[idx].starts_with('[') {
-            while idx < parts.len() && !parts[idx].ends_with(']') {
+            while idx < parts.len() {
+                let option = parts[idx].trim_start_matches('[').trim_end_matches(']');
+                if let Some(value) = option.strip_prefix("arch=") {
+                    architectures = split_list(value);
+                } else if let Some(value) = option.strip_prefix("signed-by=") {
+                    signed_by = Some(value.to_string());
+                } else if let Some(value) = option.strip_prefix("trusted=") {
+                    trusted = parse_apt_bool(value);
+                }
+                if parts[idx].ends_with(']') {
+                    break;
+                }
                 idx += 1;
             }
             idx += 1;
@@ -234,9 +573,9 @@ impl Repository {
         // Konvertiere apt-URI zu HTTP-URL falls nötig
         let url = if uri.starts_with("http://") || uri.starts_with("https://") {
             uri
-        } else if uri.starts_with("file://") {
-            // Lokale Repositories werden übersprungen
-            return None;
+        } else if uri.starts_with("file://") || uri.starts_with("copy://") {
+            // Lokale Repositories (Mirror auf Platte, Offline-Installationen)
+            uri
         } else {
             // cdrom und andere werden übersprungen
             return None;
@@ -265,10 +604,114 @@ impl Repository {
             enabled: true,
             last_probe_ms: None,
             rtt_ms: None,
+            throughput_bps: None,
+            codename: None,
             suite: Some(suite),
             components,
+            architectures,
+            signed_by,
+            trusted,
+        })
+    }
+}
+
+/// Boolescher Wert einer sources.list-Option wie apt (`yes`/`no`, `true`/`false`, ...)
+fn parse_apt_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" | "true" | "with" | "on" | "enable" => Some(true),
+        "no" | "false" | "without" | "off" | "disable" => Some(false),
+        _ => None,
+    }
+}
+
+/// Zerlegt eine Liste wie `main,contrib` oder `main contrib` (Komma oder Leerzeichen getrennt)
+pub fn split_list(value: &str) -> Vec<String> {
+    value.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Ubuntu-Archiv (`archive.ubuntu.com/ubuntu`, `ports.ubuntu.com/ubuntu-ports`) oder Launchpad-PPA?
+pub fn is_ubuntu_archive(url: &str) -> bool {
+    let url = url.to_ascii_lowercase();
+    let host = base_url(&url);
+    host.ends_with(".ubuntu.com")
+        || host.ends_with(".launchpadcontent.net")
+        || host.ends_with("ppa.launchpad.net")
+        || url.trim_end_matches('/').ends_with("/ubuntu")
+        || url.trim_end_matches('/').ends_with("/ubuntu-ports")
+}
+
+/// Erweitert die Kurzform `ppa:<owner>/<name>` zur Repository-URL auf launchpadcontent.net
+pub fn expand_ppa(url: &str) -> Option<String> {
+    let (owner, name) = url.strip_prefix("ppa:")?.split_once('/')?;
+    if owner.is_empty() || name.is_empty() || name.contains('/') {
+        return None;
+    }
+    Some(format!("https://ppa.launchpadcontent.net/{}/{}/ubuntu", owner, name))
+}
+
+/// Mögliche Pfade der Packages-Datei einer Komponente, bevorzugt komprimiert
+///
+/// Debian, Ubuntu und PPAs verwenden dasselbe Layout `dists/<suite>/<component>/binary-<arch>/`.
+pub fn packages_paths(suite: &str, component: &str, arch: &str) -> Vec<String> {
+    ["Packages.xz", "Packages.gz", "Packages"].iter()
+        .map(|name| format!("dists/{}/{}/binary-{}/{}", suite, component, arch, name))
+        .collect()
+}
+
+/// Mögliche Pfade des Contents-Index einer Komponente
+///
+/// Aktuelle Debian- und Ubuntu-Archive legen ihn je Komponente ab, ältere und
+/// einfache Repositories direkt unter `dists/<suite>/`.
+pub fn contents_paths(suite: &str, component: &str, arch: &str) -> Vec<String> {
+    ["Contents-{arch}.xz", "Contents-{arch}.gz"].iter()
+        .flat_map(|name| {
+            let name = name.replace("{arch}", arch);
+            [format!("dists/{}/{}/{}", suite, component, name), format!("dists/{}/{}", suite, name)]
         })
+        .collect()
+}
+
+/// Basis-URL (Schema und Host) einer URL, unter der `select_best_mirror` Mirrors gruppiert
+///
+/// `http://deb.debian.org/debian/pool/x.deb` → `http://deb.debian.org`; ohne Schema
+/// endet die Basis vor dem ersten `/`.
+pub fn base_url(url: &str) -> &str {
+    match url.find('/') {
+        Some(slash_pos) if url[slash_pos..].starts_with("//") => {
+            match url[slash_pos + 2..].find('/') {
+                Some(end_pos) => &url[..slash_pos + 2 + end_pos],
+                None => url,
+            }
+        }
+        Some(slash_pos) => &url[..slash_pos],
+        None => url,
+    }
+}
+
+/// Mögliche URLs des Debian-Changelogs für ein Paket aus dem Pool
+///
+/// Changelogs liegen auf den Changelog-Servern unter demselben Pool-Pfad wie das Paket
+/// (`pool/main/n/nginx/` → Quellpaket `nginx`); die Epoche ist nicht Teil des Pfads.
+/// Als letzte Möglichkeit wird eine `.changelog`-Datei neben dem Paket im Repository probiert.
+pub fn changelog_urls(repo_url: &str, filename: &str, version: &str) -> Vec<String> {
+    let pool_dir = match filename.trim_start_matches('/').rsplit_once('/') {
+        Some((dir, _)) => dir,
+        None => return Vec::new(),
+    };
+    let source = pool_dir.rsplit('/').next().unwrap_or(pool_dir);
+    let version = version.split_once(':').map(|(_, v)| v).unwrap_or(version);
+    
+    let mut urls = Vec::new();
+    if repo_url.contains("ubuntu.com") {
+        urls.push(format!("https://changelogs.ubuntu.com/changelogs/{}/{}_{}/changelog", pool_dir, source, version));
+    } else if let Some(path) = pool_dir.strip_prefix("pool/") {
+        urls.push(format!("https://metadata.ftp-master.debian.org/changelogs/{}/{}_{}_changelog", path, source, version));
     }
+    urls.push(format!("{}/{}/{}_{}.changelog", repo_url.trim_end_matches('/'), pool_dir, source, version));
+    urls
 }
 
 #[cfg(test)]
@@ -288,7 +731,12 @@ mod tests {
                 rtt_ms INTEGER,
                 enabled INTEGER DEFAULT 1,
                 suite TEXT,
-                components TEXT
+                components TEXT,
+                throughput_bps INTEGER,
+                codename TEXT,
+                architectures TEXT,
+                signed_by TEXT,
+                trusted INTEGER
             )",
             [],
         ).unwrap();
@@ -300,8 +748,13 @@ mod tests {
             enabled: true,
             last_probe_ms: None,
             rtt_ms: None,
+            throughput_bps: None,
+            codename: None,
             suite: Some("stable".to_string()),
             components: vec!["main".to_string()],
+            architectures: Vec::new(),
+            signed_by: None,
+            trusted: None,
         };
         
         Repository::add_to_db(&conn, &repo).unwrap();
@@ -310,6 +763,160 @@ mod tests {
         assert_eq!(repos[0].url, "https://example.com/repo");
     }
     
+    #[test]
+    fn test_repo_add_with_suite_and_components() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = crate::index::Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        
+        let repo = Repository::new("https://example.com/debian", Some("testing"), &split_list("main contrib"), &split_list("amd64,arm64"));
+        Repository::add_to_db(index.conn(), &repo).unwrap();
+        
+        let repos = Repository::load_all(index.conn()).unwrap();
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].suite.as_deref(), Some("testing"));
+        assert_eq!(repos[0].suite_path(), Some("testing"));
+        assert_eq!(repos[0].components, vec!["main", "contrib"]);
+        assert_eq!(repos[0].architectures, vec!["amd64", "arm64"]);
+        
+        // Ohne Angaben: Suite automatisch erkennen, Komponente main
+        let repo = Repository::new("https://example.com/other", None, &[], &[]);
+        assert_eq!(repo.suite, None);
+        assert_eq!(repo.components, vec!["main"]);
+    }
+    
+    #[test]
+    fn test_repo_disable_keeps_entry() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE repos (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                url TEXT NOT NULL UNIQUE,
+                priority INTEGER DEFAULT 500,
+                last_probe_ms INTEGER,
+                rtt_ms INTEGER,
+                enabled INTEGER DEFAULT 1,
+                suite TEXT,
+                components TEXT,
+                throughput_bps INTEGER,
+                codename TEXT,
+                architectures TEXT,
+                signed_by TEXT,
+                trusted INTEGER
+            )",
+            [],
+        ).unwrap();
+        
+        let repo = Repository {
+            id: None,
+            url: "https://example.com/repo".to_string(),
+            priority: 500,
+            enabled: true,
+            last_probe_ms: None,
+            rtt_ms: None,
+            throughput_bps: None,
+            codename: None,
+            suite: None,
+            components: vec!["main".to_string()],
+            architectures: Vec::new(),
+            signed_by: None,
+            trusted: None,
+        };
+        Repository::add_to_db(&conn, &repo).unwrap();
+        
+        assert!(Repository::set_enabled(&conn, "https://example.com/repo", false).unwrap());
+        assert!(Repository::load_all(&conn).unwrap().is_empty());
+        let all = Repository::load_all_including_disabled(&conn).unwrap();
+        assert_eq!(all.len(), 1);
+        assert!(!all[0].enabled);
+        
+        assert!(Repository::set_enabled(&conn, "https://example.com/repo", true).unwrap());
+        assert_eq!(Repository::load_all(&conn).unwrap().len(), 1);
+        
+        assert!(!Repository::set_enabled(&conn, "https://unknown.example.com", true).unwrap());
+    }
+    
+    #[test]
+    fn test_best_mirror_prefers_bandwidth_over_rtt() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE repos (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                url TEXT NOT NULL UNIQUE,
+                priority INTEGER DEFAULT 500,
+                last_probe_ms INTEGER,
+                rtt_ms INTEGER,
+                enabled INTEGER DEFAULT 1,
+                suite TEXT,
+                components TEXT,
+                throughput_bps INTEGER,
+                codename TEXT,
+                architectures TEXT,
+                signed_by TEXT,
+                trusted INTEGER
+            )",
+            [],
+        ).unwrap();
+        
+        for url in ["https://mirror.example.com/near", "https://mirror.example.com/fast"] {
+            let repo = Repository {
+                id: None,
+                url: url.to_string(),
+                priority: 500,
+                enabled: true,
+                last_probe_ms: None,
+                rtt_ms: None,
+                throughput_bps: None,
+                codename: None,
+                suite: None,
+                components: vec!["main".to_string()],
+                architectures: Vec::new(),
+                signed_by: None,
+                trusted: None,
+            };
+            Repository::add_to_db(&conn, &repo).unwrap();
+        }
+        
+        // Niedrige RTT, aber nur 100 KB/s
+        Repository::update_probe_stats(&conn, "https://mirror.example.com/near", 10, 100 * 1024).unwrap();
+        // Höhere RTT, aber 10 MB/s
+        Repository::update_probe_stats(&conn, "https://mirror.example.com/fast", 50, 10 * 1024 * 1024).unwrap();
+        
+        let best = Repository::select_best_mirror(&conn, "https://mirror.example.com").unwrap().unwrap();
+        assert_eq!(best.url, "https://mirror.example.com/fast");
+        assert_eq!(best.throughput_bps, Some(10 * 1024 * 1024));
+    }
+    
+    #[test]
+    fn test_auth_conf_matching() {
+        let entries = RepoAuth::parse_auth_conf(
+            "# private mirror\n\
+             machine example.com/private login alice password secret\n\
+             machine https://other.example.org\n  login bob\n  password hunter2\n",
+        );
+        assert_eq!(entries.len(), 2);
+        
+        let auth = RepoAuth {
+            entries: std::iter::once(AuthEntry {
+                machine: "example.com/private/debian".to_string(),
+                credentials: Credentials::Bearer("tok".to_string()),
+            }).chain(entries).collect(),
+        };
+        
+        assert_eq!(
+            auth.credentials_for("https://example.com/private/pool/a.deb"),
+            Some(&Credentials::Basic { login: "alice".to_string(), password: "secret".to_string() })
+        );
+        assert_eq!(
+            auth.credentials_for("https://example.com/private/debian/dists/stable/InRelease"),
+            Some(&Credentials::Bearer("tok".to_string()))
+        );
+        assert!(auth.credentials_for("https://example.com/privateer/x").is_none());
+        // Einträge ohne Schema gelten nicht für unverschlüsseltes http
+        assert!(auth.credentials_for("http://example.com/private/pool/a.deb").is_none());
+        assert!(auth.credentials_for("http://other.example.org/debian").is_none());
+        assert!(auth.credentials_for("https://other.example.org/debian").is_some());
+    }
+    
     #[test]
     fn test_parse_apt_line() {
         let repo = Repository::parse_apt_line("deb https://deb.debian.org/debian bookworm main").unwrap();
@@ -317,7 +924,126 @@ mod tests {
         
         let repo = Repository::parse_apt_line("deb [arch=amd64] https://deb.debian.org/debian bookworm main").unwrap();
         assert_eq!(repo.url, "https://deb.debian.org/debian");
+        assert_eq!(repo.architectures, vec!["amd64"]);
+        
+        let repo = Repository::parse_apt_line(
+            "deb [signed-by=/usr/share/keyrings/x.gpg arch=amd64,arm64] https://example.com/apt stable main").unwrap();
+        assert_eq!(repo.url, "https://example.com/apt");
+        assert_eq!(repo.architectures, vec!["amd64", "arm64"]);
+        assert_eq!(repo.signed_by.as_deref(), Some("/usr/share/keyrings/x.gpg"));
+        assert_eq!(repo.trusted, None);
         
         assert!(Repository::parse_apt_line("deb file:///mnt/cdrom").is_none());
+        
+        let repo = Repository::parse_apt_line("deb file:///srv/mirror/debian bookworm main").unwrap();
+        assert_eq!(repo.url, "file:///srv/mirror/debian");
+        assert!(repo.is_local());
+        assert_eq!(repo.suite.as_deref(), Some("bookworm"));
+    }
+    
+    #[test]
+    fn test_changelog_urls() {
+        let urls = changelog_urls(
+            "http://deb.debian.org/debian",
+            "pool/main/n/nginx/nginx-common_1.22.1-9_all.deb",
+            "1:1.22.1-9",
+        );
+        assert_eq!(urls[0], "https://metadata.ftp-master.debian.org/changelogs/main/n/nginx/nginx_1.22.1-9_changelog");
+        assert_eq!(urls[1], "http://deb.debian.org/debian/pool/main/n/nginx/nginx_1.22.1-9.changelog");
+        
+        let urls = changelog_urls("http://archive.ubuntu.com/ubuntu", "pool/main/c/curl/curl_8.5.0-2ubuntu10_amd64.deb", "8.5.0-2ubuntu10");
+        assert_eq!(urls[0], "https://changelogs.ubuntu.com/changelogs/pool/main/c/curl/curl_8.5.0-2ubuntu10/changelog");
+        
+        assert!(changelog_urls("http://example.com", "foo.deb", "1.0").is_empty());
+    }
+    
+    #[test]
+    fn test_ubuntu_and_ppa_layout() {
+        let distro = |id: &str, codename: &str, ubuntu: Option<&str>| crate::system::Distro {
+            id: id.to_string(),
+            codename: codename.to_string(),
+            ubuntu_codename: ubuntu.map(str::to_string),
+        };
+        let debian = distro("debian", "bookworm", None);
+        let jammy = distro("ubuntu", "jammy", Some("jammy"));
+        let suites = |url: &str, distro: &crate::system::Distro| Repository::new(url, None, &[], &[]).suite_candidates(distro);
+        assert_eq!(suites("http://deb.debian.org/debian", &debian), ["bookworm", "stable"]);
+        assert_eq!(suites("http://security.debian.org/debian-security", &debian), ["bookworm-security", "bookworm/updates"]);
+        assert_eq!(suites("http://archive.ubuntu.com/ubuntu/", &jammy), ["jammy"]);
+        assert_eq!(suites("http://ports.ubuntu.com/ubuntu-ports", &jammy), ["jammy"]);
+        assert_eq!(suites("http://security.ubuntu.com/ubuntu", &jammy), ["jammy-security"]);
+        assert_eq!(suites("https://ppa.launchpadcontent.net/deadsnakes/ppa/ubuntu", &jammy), ["jammy"]);
+        // Unbekannte Mirrors folgen der Distribution, Derivate nutzen den Ubuntu-Codename
+        let mint = distro("linuxmint", "virginia", Some("jammy"));
+        assert_eq!(suites("http://mirror.example.com/", &mint), ["jammy"]);
+        assert_eq!(suites("http://mirror.example.com/", &debian), ["bookworm", "stable"]);
+        // Eine explizite Suite hat immer Vorrang
+        let repo = Repository::new("http://archive.ubuntu.com/ubuntu", Some("noble-updates"), &[], &[]);
+        assert_eq!(repo.suite_candidates(&debian), ["noble-updates"]);
+        
+        assert_eq!(expand_ppa("ppa:deadsnakes/ppa").as_deref(), Some("https://ppa.launchpadcontent.net/deadsnakes/ppa/ubuntu"));
+        assert_eq!(expand_ppa("ppa:broken"), None);
+        assert_eq!(expand_ppa("http://deb.debian.org/debian"), None);
+        
+        assert_eq!(packages_paths("jammy-security", "universe", "arm64"), [
+            "dists/jammy-security/universe/binary-arm64/Packages.xz",
+            "dists/jammy-security/universe/binary-arm64/Packages.gz",
+            "dists/jammy-security/universe/binary-arm64/Packages",
+        ]);
+    }
+    
+    #[test]
+    fn test_base_url() {
+        assert_eq!(base_url("http://deb.debian.org/debian/pool/main/x.deb"), "http://deb.debian.org");
+        assert_eq!(base_url("https://mirror.example.com"), "https://mirror.example.com");
+        assert_eq!(base_url("mirror.example.com/debian"), "mirror.example.com");
+    }
+    
+    #[test]
+    fn test_signature_policy() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let index = crate::index::Index::new(temp_dir.path().join("index.db").to_str().unwrap()).unwrap();
+        
+        let policy = |signed_by: Option<&str>, trusted: Option<bool>, require_by_default: bool| {
+            let mut repo = Repository::new("https://example.com/apt", Some("stable"), &[], &[]);
+            repo.signed_by = signed_by.map(str::to_string);
+            repo.trusted = trusted;
+            // Die Richtlinie übersteht das Speichern in der Datenbank
+            Repository::add_to_db(index.conn(), &repo).unwrap();
+            let stored = Repository::load_all(index.conn()).unwrap().remove(0);
+            assert_eq!((stored.signed_by.as_deref(), stored.trusted), (signed_by, trusted));
+            stored.signature_policy(require_by_default)
+        };
+        let keyring = || SignaturePolicy::SignedBy("/usr/share/keyrings/x.gpg".into());
+        
+        for require_by_default in [true, false] {
+            // Ein eigener Schlüsselbund gilt unabhängig von der globalen Vorgabe
+            assert_eq!(policy(Some("/usr/share/keyrings/x.gpg"), None, require_by_default), keyring());
+            assert_eq!(policy(Some("/usr/share/keyrings/x.gpg"), Some(false), require_by_default), keyring());
+            // trusted=yes schaltet die Prüfung auch mit Schlüsselbund ab
+            assert_eq!(policy(Some("/usr/share/keyrings/x.gpg"), Some(true), require_by_default), SignaturePolicy::Skip);
+            assert_eq!(policy(None, Some(true), require_by_default), SignaturePolicy::Skip);
+            assert_eq!(policy(None, Some(false), require_by_default), SignaturePolicy::Required);
+        }
+        
+        // Alle übrigen folgen der globalen Vorgabe
+        assert_eq!(policy(None, None, true), SignaturePolicy::Required);
+        assert_eq!(policy(None, None, false), SignaturePolicy::Skip);
+    }
+    
+    #[test]
+    fn test_parse_apt_line_trusted_option() {
+        let repo = Repository::parse_apt_line("deb [trusted=yes] file:///srv/local-repo ./").unwrap();
+        assert_eq!(repo.trusted, Some(true));
+        assert_eq!(repo.signature_policy(true), SignaturePolicy::Skip);
+        
+        // trusted=no erzwingt die Prüfung auch ohne globale Schlüssel
+        let repo = Repository::parse_apt_line("deb [ arch=amd64 trusted=no ] https://example.com/apt stable main").unwrap();
+        assert_eq!(repo.trusted, Some(false));
+        assert_eq!(repo.architectures, vec!["amd64"]);
+        assert_eq!(repo.signature_policy(false), SignaturePolicy::Required);
+        
+        let repo = Repository::parse_apt_line("deb [trusted=maybe] https://example.com/apt stable main").unwrap();
+        assert_eq!(repo.trusted, None);
     }
 }
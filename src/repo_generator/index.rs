@@ -1,76 +1,146 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
 use std::fs;
 use crate::package::{PackageManifest, ApxPackage};
 use sha2::{Sha256, Digest};
 use hex;
 
-/// Generates Packages files for repositories
+/// Compressed variants written next to every Packages file
+const COMPRESSED_SUFFIXES: [&str; 2] = ["gz", "xz"];
+
+/// Generates Packages and Release files for repositories
 pub struct RepositoryIndexGenerator {
     package_dir: PathBuf,
     suite: String,
     component: String,
+    architectures: Vec<String>,
+}
+
+/// A Packages file written for one architecture
+#[derive(Debug, Clone)]
+pub struct ArchIndex {
+    pub arch: String,
+    pub path: PathBuf,
+    pub package_count: usize,
+}
+
+/// A package found in the pool
+struct PoolPackage {
+    name: String,
+    version: String,
     arch: String,
+    /// Packages stanza without the trailing blank line
+    entry: String,
 }
 
 impl RepositoryIndexGenerator {
     /// Create a new index generator
+    ///
+    /// With an empty `architectures` list, every architecture found in the pool gets an index.
     pub fn new(
         package_dir: impl AsRef<Path>,
         suite: impl Into<String>,
         component: impl Into<String>,
-        arch: impl Into<String>,
+        architectures: Vec<String>,
     ) -> Self {
         RepositoryIndexGenerator {
             package_dir: package_dir.as_ref().to_path_buf(),
             suite: suite.into(),
             component: component.into(),
-            arch: arch.into(),
+            architectures,
         }
     }
 
-    /// Generate Packages file from directory
-    pub fn generate_packages_file(&self, output_path: impl AsRef<Path>) -> Result<()> {
-        let mut packages_content = String::new();
-        
-        // Scan directory for .apx and .deb files
-        let packages = self.scan_packages()?;
+    /// Directory holding the Release file (`dists/<suite>`)
+    pub fn release_dir(&self) -> PathBuf {
+        self.package_dir.join("dists").join(&self.suite)
+    }
+
+    /// Scan the pool and write `binary-<arch>/Packages` for every architecture plus a combined Release
+    ///
+    /// Architecture `all` packages are part of every architecture's index, as in Debian. A
+    /// `binary-all` index is only written when `all` is requested explicitly or the pool has
+    /// no architecture-specific packages.
+    pub fn generate(&self) -> Result<Vec<ArchIndex>> {
+        let mut packages = self.scan_packages()?;
+        packages.sort_by(|a, b| (&a.name, &a.version, &a.arch).cmp(&(&b.name, &b.version, &b.arch)));
         
-        for pkg in packages {
-            packages_content.push_str(&self.format_package_entry(&pkg)?);
-            packages_content.push_str("\n");
+        let mut indices = Vec::new();
+        for arch in self.target_architectures(&packages) {
+            let output_dir = self.release_dir()
+                .join(&self.component)
+                .join(format!("binary-{}", arch));
+            fs::create_dir_all(&output_dir)?;
+            
+            let mut content = String::new();
+            let mut package_count = 0;
+            for pkg in packages.iter().filter(|pkg| pkg.arch == arch || (pkg.arch == "all" && arch != "all")) {
+                content.push_str(&pkg.entry);
+                content.push('\n');
+                package_count += 1;
+            }
+            
+            let path = output_dir.join("Packages");
+            fs::write(&path, &content)?;
+            write_compressed(&path, content.as_bytes())?;
+            indices.push(ArchIndex { arch, path, package_count });
         }
         
-        // Write Packages file
-        fs::write(output_path, packages_content)?;
+        self.generate_release_file(&indices)?;
         
-        Ok(())
+        Ok(indices)
     }
 
-    /// Scan directory for package files
-    fn scan_packages(&self) -> Result<Vec<PackageManifest>> {
+    /// Architectures to write an index for
+    fn target_architectures(&self, packages: &[PoolPackage]) -> Vec<String> {
+        if !self.architectures.is_empty() {
+            let requested: BTreeSet<&String> = self.architectures.iter().collect();
+            return requested.into_iter().cloned().collect();
+        }
+        
+        let found: BTreeSet<&str> = packages.iter()
+            .map(|pkg| pkg.arch.as_str())
+            .filter(|arch| *arch != "all")
+            .collect();
+        if found.is_empty() && !packages.is_empty() {
+            return vec!["all".to_string()];
+        }
+        found.into_iter().map(str::to_string).collect()
+    }
+
+    /// Scan the pool (recursively, without `dists/`) for .apx and .deb files
+    fn scan_packages(&self) -> Result<Vec<PoolPackage>> {
         let mut packages = Vec::new();
         
         if !self.package_dir.exists() {
             return Ok(packages);
         }
         
-        for entry in fs::read_dir(&self.package_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.is_file() {
-                if let Some(ext) = path.extension() {
-                    if ext == "apx" {
+        let mut pending = vec![self.package_dir.clone()];
+        while let Some(dir) = pending.pop() {
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                
+                if path.is_dir() {
+                    if path != self.package_dir.join("dists") {
+                        pending.push(path);
+                    }
+                    continue;
+                }
+                
+                match path.extension().and_then(|ext| ext.to_str()) {
+                    Some("apx") => {
                         // Try to load APX package
                         if let Ok(apx) = ApxPackage::open(&path) {
-                            packages.push(apx.manifest);
+                            packages.push(self.apx_entry(&path, &apx.manifest)?);
                         }
-                    } else if ext == "deb" {
-                        // For .deb files, we'd need to extract metadata
-                        // This is a simplified version - in production would use dpkg-deb
-                        // For now, skip .deb files or implement proper parsing
                     }
+                    Some("deb") => {
+                        packages.push(self.deb_entry(&path)
+                            .with_context(|| format!("Failed to read {}", path.display()))?);
+                    }
+                    _ => {}
                 }
             }
         }
@@ -78,8 +148,44 @@ impl RepositoryIndexGenerator {
         Ok(packages)
     }
 
+    /// Packages stanza of a .deb: its control file plus location, size and hashes
+    fn deb_entry(&self, path: &Path) -> Result<PoolPackage> {
+        let control = crate::deb::DebArchive::open(path)?.control()?;
+        let manifest = crate::apt_parser::parse_packages_file(&control)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("control file lacks Package or Version"))?;
+        
+        let mut entry = control.trim_end().to_string();
+        entry.push('\n');
+        entry.push_str(&self.file_fields(path)?);
+        
+        Ok(PoolPackage { name: manifest.name, version: manifest.version, arch: manifest.arch, entry })
+    }
+
+    fn apx_entry(&self, path: &Path, manifest: &PackageManifest) -> Result<PoolPackage> {
+        Ok(PoolPackage {
+            name: manifest.name.clone(),
+            version: manifest.version.clone(),
+            arch: manifest.arch.clone(),
+            entry: self.format_package_entry(manifest, &self.file_fields(path)?),
+        })
+    }
+
+    /// Filename (relative to the repository root), Size and hashes of a pool file
+    fn file_fields(&self, path: &Path) -> Result<String> {
+        let data = fs::read(path)?;
+        let filename = path.strip_prefix(&self.package_dir).unwrap_or(path);
+        let hashes = FileHashes::of(&data);
+        
+        Ok(format!(
+            "Filename: {}\nSize: {}\nMD5sum: {}\nSHA1: {}\nSHA256: {}\n",
+            filename.display(), data.len(), hashes.md5, hashes.sha1, hashes.sha256
+        ))
+    }
+
     /// Format a package entry in Packages file format
-    fn format_package_entry(&self, manifest: &PackageManifest) -> Result<String> {
+    fn format_package_entry(&self, manifest: &PackageManifest, file_fields: &str) -> String {
         let mut entry = String::new();
         
         entry.push_str(&format!("Package: {}\n", manifest.name));
@@ -98,60 +204,180 @@ impl RepositoryIndexGenerator {
             entry.push_str(&format!("Conflicts: {}\n", manifest.conflicts.join(", ")));
         }
         
-        entry.push_str(&format!("Size: {}\n", manifest.size));
-        entry.push_str(&format!("SHA256: {}\n", manifest.checksum));
+        entry.push_str(file_fields);
+        
+        entry
+    }
+
+    /// Write `dists/<suite>/Release` listing every Packages file (plain and compressed) with size and hashes
+    fn generate_release_file(&self, indices: &[ArchIndex]) -> Result<()> {
+        let release_dir = self.release_dir();
+        let mut files = Vec::new();
+        for index in indices {
+            let variants = COMPRESSED_SUFFIXES.iter().map(|suffix| index.path.with_extension(suffix));
+            for path in std::iter::once(index.path.clone()).chain(variants) {
+                let data = fs::read(&path)?;
+                let name = path.strip_prefix(&release_dir).unwrap_or(&path).display().to_string();
+                files.push((name, data.len(), FileHashes::of(&data)));
+            }
+        }
         
-        if let Some(ref filename) = manifest.filename {
-            entry.push_str(&format!("Filename: {}\n", filename));
+        // Format Release file
+        let architectures: Vec<&str> = indices.iter().map(|index| index.arch.as_str()).collect();
+        let mut release_content = String::new();
+        release_content.push_str(&format!("Suite: {}\n", self.suite));
+        release_content.push_str(&format!("Components: {}\n", self.component));
+        release_content.push_str(&format!("Architectures: {}\n", architectures.join(" ")));
+        release_content.push_str(&format!("Date: {}\n",
+            chrono::Utc::now().format("%a, %d %b %Y %H:%M:%S UTC")));
+        release_content.push_str("MD5Sum:\n");
+        for (name, size, hashes) in &files {
+            release_content.push_str(&format!(" {} {} {}\n", hashes.md5, size, name));
+        }
+        release_content.push_str("SHA1:\n");
+        for (name, size, hashes) in &files {
+            release_content.push_str(&format!(" {} {} {}\n", hashes.sha1, size, name));
+        }
+        release_content.push_str("SHA256:\n");
+        for (name, size, hashes) in &files {
+            release_content.push_str(&format!(" {} {} {}\n", hashes.sha256, size, name));
         }
         
-        entry.push_str("\n");
+        fs::create_dir_all(&release_dir)?;
+        fs::write(release_dir.join("Release"), release_content)?;
         
-        Ok(entry)
+        Ok(())
     }
+}
 
-    /// Generate Release file with checksums
-    pub fn generate_release_file(
-        &self,
-        packages_path: &Path,
-        output_path: impl AsRef<Path>,
-    ) -> Result<()> {
-        let packages_content = fs::read_to_string(packages_path)?;
-        
-        // Calculate checksums
-        let md5_sum = hex::encode(md5::compute(&packages_content).0);
+/// Write `<path>.gz` and `<path>.xz` with the given content
+fn write_compressed(path: &Path, content: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+    gz.write_all(content)?;
+    fs::write(path.with_extension("gz"), gz.finish()?)?;
+
+    let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+    xz.write_all(content)?;
+    fs::write(path.with_extension("xz"), xz.finish()?)?;
+
+    Ok(())
+}
+
+/// Hashes listed for a file in Packages and Release
+struct FileHashes {
+    md5: String,
+    sha1: String,
+    sha256: String,
+}
+
+impl FileHashes {
+    fn of(data: &[u8]) -> Self {
+        FileHashes {
+            md5: hex::encode(md5::compute(data).0),
+            sha1: hex::encode(sha1::Sha1::digest(data)),
+            sha256: hex::encode(Sha256::digest(data)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    /// Minimal .deb with only debian-binary and a control archive
+    fn write_deb(path: &Path, name: &str, arch: &str) {
+        let control = format!("Package: {}\nVersion: 1.0\nArchitecture: {}\nDescription: fixture\n", name, arch);
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(control.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, "./control", control.as_bytes()).unwrap();
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&builder.into_inner().unwrap()).unwrap();
         
-        let mut sha1_hasher = sha1::Sha1::new();
-        sha1_hasher.update(&packages_content);
-        let sha1_sum = hex::encode(sha1_hasher.finalize());
+        let mut deb = b"!<arch>\n".to_vec();
+        for (member, data) in [("debian-binary", b"2.0\n".to_vec()), ("control.tar.gz", gz.finish().unwrap())] {
+            writeln!(deb, "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`", format!("{}/", member), 0, 0, 0, 100644, data.len()).unwrap();
+            deb.extend_from_slice(&data);
+            if data.len() % 2 == 1 {
+                deb.push(b'\n');
+            }
+        }
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, deb).unwrap();
+    }
+
+    #[test]
+    fn test_generate_mixed_architecture_pool() {
+        let temp_dir = TempDir::new().unwrap();
+        let pool = temp_dir.path();
+        write_deb(&pool.join("pool/main/h/hello/hello_1.0_amd64.deb"), "hello", "amd64");
+        write_deb(&pool.join("pool/main/h/hello/hello_1.0_arm64.deb"), "hello", "arm64");
+        write_deb(&pool.join("pool/main/h/hello-doc/hello-doc_1.0_all.deb"), "hello-doc", "all");
         
-        let mut sha256_hasher = Sha256::new();
-        sha256_hasher.update(&packages_content);
-        let sha256_sum = hex::encode(sha256_hasher.finalize());
+        let generator = RepositoryIndexGenerator::new(pool, "stable", "main", Vec::new());
+        let indices = generator.generate().unwrap();
+        let arches: Vec<&str> = indices.iter().map(|index| index.arch.as_str()).collect();
+        assert_eq!(arches, ["amd64", "arm64"]);
         
-        let file_size = packages_content.len();
-        let packages_filename = packages_path.file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("Packages");
+        // Each architecture gets its own package plus the arch-independent one
+        for index in &indices {
+            assert_eq!(index.package_count, 2);
+            let packages = crate::apt_parser::parse_packages_file(&fs::read_to_string(&index.path).unwrap()).unwrap();
+            let mut found: Vec<(&str, &str)> = packages.iter().map(|pkg| (pkg.name.as_str(), pkg.arch.as_str())).collect();
+            found.sort();
+            assert_eq!(found, [("hello", index.arch.as_str()), ("hello-doc", "all")]);
+            
+            let deb = packages.iter().find(|pkg| pkg.name == "hello").unwrap();
+            let filename = deb.filename.as_ref().unwrap();
+            assert_eq!(filename, &format!("pool/main/h/hello/hello_1.0_{}.deb", index.arch));
+            let data = fs::read(pool.join(filename)).unwrap();
+            assert_eq!(deb.size, data.len() as u64);
+            assert_eq!(deb.checksum, hex::encode(Sha256::digest(&data)));
+        }
         
-        // Format Release file
-        let mut release_content = String::new();
-        release_content.push_str(&format!("Suite: {}\n", self.suite));
-        release_content.push_str(&format!("Component: {}\n", self.component));
-        release_content.push_str(&format!("Architecture: {}\n", self.arch));
-        release_content.push_str(&format!("Date: {}\n", 
-            chrono::Utc::now().format("%a, %d %b %Y %H:%M:%S %Z")));
-        release_content.push_str("\n");
-        release_content.push_str(&format!(" {} {} {} {}\n",
-            md5_sum, file_size, packages_filename, "Packages"));
-        release_content.push_str(&format!(" {} {} {} {}\n",
-            sha1_sum, file_size, packages_filename, "Packages"));
-        release_content.push_str(&format!(" {} {} {} {}\n",
-            sha256_sum, file_size, packages_filename, "Packages"));
-        
-        fs::write(output_path, release_content)?;
+        // The Release file references every Packages file with its real size and hash
+        let release = fs::read_to_string(generator.release_dir().join("Release")).unwrap();
+        let info = crate::apt_parser::parse_release_file(&release);
+        assert_eq!(info.architectures, ["amd64", "arm64"]);
+        assert_eq!(info.components, ["main"]);
+        let sha256_section = release.split("SHA256:\n").nth(1).unwrap();
+        for index in &indices {
+            let data = fs::read(&index.path).unwrap();
+            let line = format!(" {} {} main/binary-{}/Packages\n", hex::encode(Sha256::digest(&data)), data.len(), index.arch);
+            assert!(sha256_section.contains(&line), "missing {:?} in {}", line, release);
+        }
         
-        Ok(())
+        // Every hash section lists the plain and compressed Packages files byte-for-byte
+        let mut listed = 0;
+        for (section, hash) in [("MD5Sum", 0), ("SHA1", 1), ("SHA256", 2)] {
+            let entries = release.split(&format!("{}:\n", section)).nth(1).unwrap()
+                .lines()
+                .take_while(|line| line.starts_with(' '))
+                .map(|line| line.split_whitespace().collect::<Vec<_>>());
+            for entry in entries {
+                let data = fs::read(generator.release_dir().join(entry[2])).unwrap();
+                let hashes = FileHashes::of(&data);
+                assert_eq!(entry[0], [&hashes.md5, &hashes.sha1, &hashes.sha256][hash], "{} of {}", section, entry[2]);
+                assert_eq!(entry[1], data.len().to_string());
+                listed += 1;
+            }
+        }
+        assert_eq!(listed, 3 * 3 * indices.len());
+        let compressed = fs::read(indices[0].path.with_extension("xz")).unwrap();
+        let mut decompressed = String::new();
+        std::io::Read::read_to_string(&mut xz2::read::XzDecoder::new(&compressed[..]), &mut decompressed).unwrap();
+        assert_eq!(decompressed, fs::read_to_string(&indices[0].path).unwrap());
+
+        // Explicitly requested architectures, including a binary-all index
+        let generator = RepositoryIndexGenerator::new(pool, "stable", "main", vec!["all".to_string(), "amd64".to_string()]);
+        let indices = generator.generate().unwrap();
+        let counts: Vec<(&str, usize)> = indices.iter().map(|index| (index.arch.as_str(), index.package_count)).collect();
+        assert_eq!(counts, [("all", 1), ("amd64", 2)]);
     }
 }
-
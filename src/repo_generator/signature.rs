@@ -1,10 +1,16 @@
 use anyhow::Result;
 use std::path::Path;
 use std::fs;
-use ed25519_dalek::{SigningKey, Signer};
+use ed25519_dalek::SigningKey;
 use std::convert::TryInto;
+use crate::openpgp;
 
 /// Signs repository Release files with Ed25519
+///
+/// The key file holds the raw 32-byte Ed25519 secret key, as written by
+/// `apt-ng-build generate-key` (`<name>.key`). The signatures are OpenPGP v4
+/// EdDSA signatures; clients verify them with the matching `<name>.pub` in
+/// their trusted keys directory.
 pub struct RepositorySigner {
     signing_key: SigningKey,
 }
@@ -16,39 +22,81 @@ impl RepositorySigner {
         if key_bytes.len() != 32 {
             return Err(anyhow::anyhow!("Invalid key length: expected 32 bytes, got {}", key_bytes.len()));
         }
-        
+
         let key_array: [u8; 32] = key_bytes.as_slice().try_into()
             .map_err(|_| anyhow::anyhow!("Failed to convert key bytes"))?;
-        
+
         let signing_key = SigningKey::from_bytes(&key_array);
-        
+
         Ok(RepositorySigner { signing_key })
     }
 
-    /// Sign a Release file and create InRelease (signed inline) and Release.gpg files
+    /// Sign a Release file and create InRelease (clearsigned) and Release.gpg (detached) files
     pub fn sign_release(&self, release_path: &Path, output_dir: &Path) -> Result<()> {
         let release_content = fs::read_to_string(release_path)?;
-        
-        // Sign the release content
-        let signature = self.signing_key.sign(release_content.as_bytes());
-        let signature_bytes = signature.to_bytes();
-        
-        // Create InRelease (inline signed)
-        let mut inrelease_content = release_content.clone();
-        inrelease_content.push_str("\n");
-        inrelease_content.push_str("-----BEGIN PGP SIGNATURE-----\n");
-        use base64::Engine;
-        inrelease_content.push_str(&base64::engine::general_purpose::STANDARD.encode(&signature_bytes));
-        inrelease_content.push_str("\n-----END PGP SIGNATURE-----\n");
-        
-        let inrelease_path = output_dir.join("InRelease");
-        fs::write(&inrelease_path, inrelease_content)?;
-        
-        // Create Release.gpg (detached signature)
-        let release_gpg_path = output_dir.join("Release.gpg");
-        fs::write(&release_gpg_path, signature_bytes)?;
-        
+        let now = chrono::Utc::now();
+
+        // Release.gpg signs the exact bytes of Release
+        let detached = openpgp::sign_ed25519(&self.signing_key, release_content.as_bytes(), false, now);
+        fs::write(output_dir.join("Release.gpg"), openpgp::armor("SIGNATURE", &detached))?;
+
+        fs::write(output_dir.join("InRelease"), self.clearsign(&release_content, now))?;
+
         Ok(())
     }
+
+    /// Clearsigned message (RFC 4880, section 7) with a text signature over `text`
+    fn clearsign(&self, text: &str, now: chrono::DateTime<chrono::Utc>) -> String {
+        // The signed text has no trailing whitespace and no final line ending
+        let lines: Vec<&str> = text.lines().map(|line| line.trim_end_matches([' ', '\t'])).collect();
+        let signature = openpgp::sign_ed25519(&self.signing_key, lines.join("\n").as_bytes(), true, now);
+
+        let mut message = String::from("-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA512\n\n");
+        for line in lines {
+            if line.starts_with('-') {
+                message.push_str("- ");
+            }
+            message.push_str(line);
+            message.push('\n');
+        }
+        message.push_str(&openpgp::armor("SIGNATURE", &signature));
+        message
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verifier::PackageVerifier;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_signed_release_verifies_with_public_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let key_path = temp_dir.path().join("repo.key");
+        let signing_key = SigningKey::from_bytes(&[5; 32]);
+        fs::write(&key_path, signing_key.to_bytes()).unwrap();
+
+        let release = "Suite: stable\nComponents: main\nSHA256:\n 0000 12 main/binary-amd64/Packages\n";
+        let release_path = temp_dir.path().join("Release");
+        fs::write(&release_path, release).unwrap();
+        RepositorySigner::from_key_file(&key_path).unwrap()
+            .sign_release(&release_path, temp_dir.path())
+            .unwrap();
+
+        let inrelease = fs::read(temp_dir.path().join("InRelease")).unwrap();
+        let release_gpg = fs::read(temp_dir.path().join("Release.gpg")).unwrap();
+        let verifier = PackageVerifier::with_trusted_key(signing_key.verifying_key().as_bytes()).unwrap();
+        let verified = verifier.verify_release_file(&inrelease, None).unwrap();
+        assert_eq!(verified.text(), release);
+        assert_eq!(verified.sha256_entries().len(), 1);
+        verifier.verify_release_file(release.as_bytes(), Some(&release_gpg)).unwrap();
+
+        // Another key, or a modified Release file, is rejected
+        let other = PackageVerifier::with_trusted_key(SigningKey::from_bytes(&[6; 32]).verifying_key().as_bytes()).unwrap();
+        assert!(other.verify_release_file(&inrelease, None).is_err());
+        let tampered = String::from_utf8(inrelease).unwrap().replace("Suite: stable", "Suite: stale");
+        assert!(verifier.verify_release_file(tampered.as_bytes(), None).is_err());
+        assert!(verifier.verify_release_file(b"Suite: stale\n", Some(&release_gpg)).is_err());
+    }
+}
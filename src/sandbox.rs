@@ -11,6 +11,8 @@ pub struct SandboxConfig {
     pub cpu_limit: Option<f64>,     // z.B. 0.5 für 50%
     pub read_only_paths: Vec<String>,
     pub writable_paths: Vec<String>,
+    /// Pakete, deren Skripte trotz Sandbox Netzwerkzugriff erhalten
+    pub network_allowed_packages: Vec<String>,
 }
 
 impl Default for SandboxConfig {
@@ -22,6 +24,7 @@ impl Default for SandboxConfig {
             cpu_limit: Some(1.0),                  // 100% CPU default
             read_only_paths: vec![],
             writable_paths: vec!["/tmp".to_string()],
+            network_allowed_packages: vec![],
         }
     }
 }
@@ -33,11 +36,29 @@ pub struct Sandbox {
 
 impl Sandbox {
     /// Erstellt eine neue Sandbox-Instanz
-    #[allow(dead_code)]
     pub fn new(config: SandboxConfig) -> Self {
         Sandbox { config }
     }
 
+    /// Gibt die Sandbox-Konfiguration zurück
+    pub fn config(&self) -> &SandboxConfig {
+        &self.config
+    }
+
+    /// Prüft, ob die Skripte dieses Pakets Netzwerkzugriff erhalten
+    pub fn grants_network_to(&self, package_name: &str) -> bool {
+        !self.config.network_allowed
+            && self.config.network_allowed_packages.iter().any(|p| p == package_name)
+    }
+
+    /// Erstellt eine Sandbox mit Netzwerkzugriff, sonst gleicher Konfiguration
+    pub fn with_network(&self) -> Sandbox {
+        Sandbox::new(SandboxConfig {
+            network_allowed: true,
+            ..self.config.clone()
+        })
+    }
+
     /// Prüft ob Bubblewrap verfügbar ist
     pub fn check_bubblewrap_available() -> bool {
         Command::new("bwrap")
@@ -163,9 +184,9 @@ impl Sandbox {
         bwrap_args.push("--die-with-parent".to_string());
         bwrap_args.push("--as-pid-1".to_string());
 
-        // Network sandbox
-        if !self.config.network_allowed {
-            bwrap_args.push("--unshare-net".to_string());
+        // Network sandbox (--unshare-all includes the network namespace)
+        if self.config.network_allowed {
+            bwrap_args.push("--share-net".to_string());
         }
 
         // Filesystem sandbox
@@ -272,8 +293,9 @@ impl Sandbox {
     /// Setup Netzwerk-Sandbox
     #[allow(dead_code)]
     fn setup_network_sandbox(&self, cmd: &mut Command) {
-        if !self.config.network_allowed {
-            cmd.arg("--unshare-net");
+        // --unshare-all schließt den Netzwerk-Namespace bereits ein
+        if self.config.network_allowed {
+            cmd.arg("--share-net");
         }
     }
 }
@@ -290,6 +312,18 @@ mod tests {
         assert!(config.memory_limit.is_some());
     }
 
+    #[test]
+    fn test_network_allowlist() {
+        let sandbox = Sandbox::new(SandboxConfig {
+            network_allowed_packages: vec!["licensed-model".to_string()],
+            ..SandboxConfig::default()
+        });
+        assert!(sandbox.grants_network_to("licensed-model"));
+        assert!(!sandbox.grants_network_to("nginx"));
+        assert!(sandbox.with_network().config().network_allowed);
+        assert!(!sandbox.config().network_allowed);
+    }
+
     #[test]
     fn test_check_bubblewrap_available() {
         // Dies ist ein einfacher Test der prüft ob die Funktion aufgerufen werden kann
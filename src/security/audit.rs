@@ -7,7 +7,14 @@ pub struct SecurityAudit;
 impl SecurityAudit {
     /// Run a complete security audit
     pub fn run() -> Result<SecurityAuditResult> {
-        let checks = run_all_checks()?;
+        Self::run_with(Vec::new())
+    }
+    
+    /// Run a complete security audit including additional check results
+    /// (e.g. vulnerability findings for installed packages)
+    pub fn run_with(extra_checks: Vec<SecurityCheckResult>) -> Result<SecurityAuditResult> {
+        let mut checks = run_all_checks()?;
+        checks.extend(extra_checks);
         
         let total_checks = checks.len();
         let passed_checks = checks.iter().filter(|c| c.passed).count();
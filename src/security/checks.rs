@@ -39,7 +39,8 @@ impl SecurityCheck for SignatureVerificationCheck {
         use crate::verifier::PackageVerifier;
         
         let config = Config::load(None)?;
-        let verifier = PackageVerifier::new(config.trusted_keys_dir())?;
+        let mut verifier = PackageVerifier::new(config.trusted_keys_dir())?;
+        verifier.load_openpgp_keyrings(&config.keyring_paths());
         let key_count = verifier.trusted_key_count();
         
         let passed = key_count > 0;
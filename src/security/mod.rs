@@ -1,6 +1,7 @@
 pub mod audit;
 pub mod report;
 pub mod checks;
+pub mod vulnerabilities;
 
 pub use audit::SecurityAudit;
 pub use report::SecurityReport;
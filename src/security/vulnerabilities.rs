@@ -0,0 +1,551 @@
+use anyhow::{Context, Result};
+use crate::security::checks::{SecurityCheckResult, Severity};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::Path;
+
+const OSV_API_URL: &str = "https://api.osv.dev/v1";
+const OSV_ECOSYSTEM: &str = "Debian";
+/// OSV.dev accepts at most 1000 queries per batch request
+const OSV_BATCH_SIZE: usize = 1000;
+/// Number of advisory details fetched concurrently
+const OSV_DETAIL_CONCURRENCY: usize = 16;
+
+/// A known vulnerability affecting an installed package
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Advisory {
+    pub id: String,
+    pub package: String,
+    pub version: String,
+    pub summary: String,
+    pub severity: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+impl Advisory {
+    /// Map the advisory severity to the audit severity
+    pub fn audit_severity(&self) -> Severity {
+        match self.severity.to_ascii_lowercase().as_str() {
+            "critical" => Severity::Critical,
+            "high" => Severity::High,
+            "moderate" | "medium" => Severity::Medium,
+            "low" | "unimportant" => Severity::Low,
+            _ => Severity::Medium,
+        }
+    }
+
+    /// CVE identifiers of this advisory (the id itself or its aliases)
+    pub fn cve_ids(&self) -> Vec<&str> {
+        std::iter::once(self.id.as_str())
+            .chain(self.aliases.iter().map(|a| a.as_str()))
+            .filter(|id| id.starts_with("CVE-"))
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct OsvBatchResponse {
+    results: Vec<OsvBatchResult>,
+}
+
+#[derive(Deserialize)]
+struct OsvBatchResult {
+    #[serde(default)]
+    vulns: Vec<OsvVulnRef>,
+}
+
+#[derive(Deserialize)]
+struct OsvVulnRef {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct OsvVuln {
+    id: String,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    details: Option<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    database_specific: Option<serde_json::Value>,
+    #[serde(default)]
+    affected: Vec<OsvAffected>,
+}
+
+#[derive(Deserialize)]
+struct OsvAffected {
+    #[serde(default)]
+    ecosystem_specific: Option<serde_json::Value>,
+}
+
+impl OsvVuln {
+    /// Severity as reported by the source database, falling back to Debian's urgency
+    fn severity(&self) -> String {
+        let database_severity = self.database_specific.as_ref()
+            .and_then(|v| v.get("severity"))
+            .and_then(|v| v.as_str());
+        let debian_urgency = self.affected.iter()
+            .filter_map(|a| a.ecosystem_specific.as_ref())
+            .filter_map(|v| v.get("urgency"))
+            .filter_map(|v| v.as_str())
+            .next();
+
+        database_severity
+            .or(debian_urgency)
+            .unwrap_or("unknown")
+            .to_ascii_lowercase()
+    }
+
+    fn summary(&self) -> String {
+        self.summary.clone()
+            .or_else(|| self.details.as_ref().and_then(|d| d.lines().next().map(|l| l.to_string())))
+            .unwrap_or_default()
+    }
+}
+
+/// Advisories together with the packages they were looked up for
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AdvisorySnapshot {
+    scanned: Vec<(String, String)>,
+    advisories: Vec<Advisory>,
+}
+
+/// Advisories from a snapshot that apply to the installed packages
+#[derive(Debug, Default)]
+pub struct SnapshotScan {
+    pub advisories: Vec<Advisory>,
+    /// Installed (name, version) pairs the snapshot has no results for
+    pub unscanned: Vec<(String, String)>,
+}
+
+impl SnapshotScan {
+    /// Attribute the results for source packages to the installed binaries built from them
+    pub fn for_binaries(self, packages: &[SystemPackage]) -> SnapshotScan {
+        fn built_from<'a>(packages: &'a [SystemPackage], source: &'a str, version: &'a str) -> impl Iterator<Item = &'a SystemPackage> {
+            packages.iter().filter(move |package| package.source == source && package.source_version == version)
+        }
+        SnapshotScan {
+            advisories: self.advisories.iter()
+                .flat_map(|advisory| built_from(packages, &advisory.package, &advisory.version).map(|package| Advisory {
+                    package: package.name.clone(),
+                    version: package.version.clone(),
+                    ..advisory.clone()
+                }))
+                .collect(),
+            unscanned: self.unscanned.iter()
+                .flat_map(|(source, version)| built_from(packages, source, version))
+                .map(|package| (package.name.clone(), package.version.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// Queries OSV.dev for vulnerabilities in installed Debian packages
+pub struct VulnerabilityScanner {
+    client: reqwest::Client,
+    api_url: String,
+}
+
+impl VulnerabilityScanner {
+    pub fn new() -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent("apt-ng-security-audit")
+            .timeout(std::time::Duration::from_secs(30))
+            .build()?;
+
+        Ok(VulnerabilityScanner { client, api_url: OSV_API_URL.to_string() })
+    }
+
+    /// Use a different OSV-compatible API endpoint
+    pub fn with_api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Query the OSV.dev batch API for all given (source package, version) pairs
+    ///
+    /// The details of all reported advisories are fetched concurrently once the batch
+    /// queries are done.
+    pub async fn scan(&self, packages: &[(String, String)]) -> Result<Vec<Advisory>> {
+        let mut matches: Vec<(&(String, String), Vec<String>)> = Vec::new();
+
+        for chunk in packages.chunks(OSV_BATCH_SIZE) {
+            let queries: Vec<serde_json::Value> = chunk.iter().map(|(name, version)| {
+                serde_json::json!({
+                    "package": { "name": name, "ecosystem": OSV_ECOSYSTEM },
+                    "version": version,
+                })
+            }).collect();
+
+            let response: OsvBatchResponse = self.client
+                .post(format!("{}/querybatch", self.api_url))
+                .json(&serde_json::json!({ "queries": queries }))
+                .send()
+                .await
+                .context("Failed to query OSV.dev")?
+                .error_for_status()?
+                .json()
+                .await
+                .context("Invalid response from OSV.dev")?;
+
+            // Results are returned in query order
+            for (package, result) in chunk.iter().zip(response.results) {
+                if !result.vulns.is_empty() {
+                    matches.push((package, result.vulns.into_iter().map(|v| v.id).collect()));
+                }
+            }
+        }
+
+        let ids: BTreeSet<&String> = matches.iter().flat_map(|(_, ids)| ids).collect();
+        let details: BTreeMap<&String, OsvVuln> = stream::iter(ids)
+            .map(|id| async move { self.fetch_vulnerability(id).await.map(|vuln| (id, vuln)) })
+            .buffer_unordered(OSV_DETAIL_CONCURRENCY)
+            .try_collect()
+            .await?;
+
+        Ok(matches.iter().flat_map(|((name, version), ids)| {
+            let details = &details;
+            ids.iter().map(move |id| {
+                let vuln = &details[id];
+                Advisory {
+                    id: vuln.id.clone(),
+                    package: name.clone(),
+                    version: version.clone(),
+                    summary: vuln.summary(),
+                    severity: vuln.severity(),
+                    aliases: vuln.aliases.clone(),
+                }
+            })
+        }).collect())
+    }
+
+    async fn fetch_vulnerability(&self, id: &str) -> Result<OsvVuln> {
+        let vuln = self.client
+            .get(format!("{}/vulns/{}", self.api_url, id))
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch advisory {}", id))?
+            .error_for_status()?
+            .json()
+            .await
+            .with_context(|| format!("Invalid advisory {}", id))?;
+
+        Ok(vuln)
+    }
+}
+
+/// An installed binary package and the source package it was built from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemPackage {
+    pub name: String,
+    pub version: String,
+    pub source: String,
+    pub source_version: String,
+}
+
+/// List installed system packages from the dpkg database
+pub fn installed_system_packages() -> Result<Vec<SystemPackage>> {
+    Ok(crate::dpkg::installed_packages()?
+        .into_iter()
+        .filter(|package| package.is_installed() && !package.version.is_empty())
+        .map(|package| {
+            let (source, source_version) = package.source_package();
+            SystemPackage {
+                source: source.to_string(),
+                source_version: source_version.to_string(),
+                name: package.name,
+                version: package.version,
+            }
+        })
+        .collect())
+}
+
+/// Distinct (source, version) pairs of the installed packages
+///
+/// Debian advisories on OSV.dev are filed under the source package, so binaries such
+/// as `libssl3` are looked up as `openssl`.
+pub fn source_packages(packages: &[SystemPackage]) -> Vec<(String, String)> {
+    packages.iter()
+        .map(|package| (package.source.clone(), package.source_version.clone()))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Save the advisories and the scanned packages as a snapshot for offline audits
+pub fn save_snapshot(path: &Path, scanned: &[(String, String)], advisories: &[Advisory]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let snapshot = AdvisorySnapshot { scanned: scanned.to_vec(), advisories: advisories.to_vec() };
+    fs::write(path, serde_json::to_string_pretty(&snapshot)?)?;
+    Ok(())
+}
+
+/// Load the advisories from a snapshot that still apply to the installed packages
+///
+/// Installed packages the snapshot was not taken for (new installs, upgrades) are
+/// returned as unscanned: their vulnerability status is unknown, not clean.
+pub fn load_snapshot(path: &Path, packages: &[(String, String)]) -> Result<SnapshotScan> {
+    let snapshot = if path.exists() {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str::<AdvisorySnapshot>(&content)
+            .or_else(|_| serde_json::from_str::<Vec<Advisory>>(&content)
+                .map(|advisories| AdvisorySnapshot { scanned: Vec::new(), advisories }))
+            .with_context(|| format!("Invalid advisory snapshot {}", path.display()))?
+    } else {
+        AdvisorySnapshot::default()
+    };
+
+    let scanned: HashSet<&(String, String)> = snapshot.scanned.iter().collect();
+    Ok(SnapshotScan {
+        advisories: snapshot.advisories.iter()
+            .filter(|a| packages.iter().any(|(name, version)| *name == a.package && *version == a.version))
+            .cloned()
+            .collect(),
+        unscanned: packages.iter()
+            .filter(|package| !scanned.contains(package))
+            .cloned()
+            .collect(),
+    })
+}
+
+fn severity_rank(severity: &Severity) -> u8 {
+    match severity {
+        Severity::Critical => 4,
+        Severity::High => 3,
+        Severity::Medium => 2,
+        Severity::Low => 1,
+        Severity::Info => 0,
+    }
+}
+
+/// Turn advisories into audit check results (one per affected package)
+///
+/// Packages in `unscanned` are reported as unknown instead of counting as clean.
+pub fn to_check_results(
+    advisories: &[Advisory],
+    scanned_packages: usize,
+    unscanned: &[(String, String)],
+) -> Vec<SecurityCheckResult> {
+    let mut results = Vec::new();
+    if !unscanned.is_empty() {
+        results.push(SecurityCheckResult {
+            check_name: "unscanned_packages".to_string(),
+            severity: Severity::Low,
+            passed: false,
+            message: format!("Vulnerability status of {} installed package(s) is unknown (not in the advisory snapshot).",
+                unscanned.len()),
+            details: Some(unscanned.iter()
+                .map(|(name, version)| format!("{} {}", name, version))
+                .collect::<Vec<_>>()
+                .join(", ")),
+        });
+    }
+
+    if advisories.is_empty() {
+        if scanned_packages > 0 {
+            results.push(SecurityCheckResult {
+                check_name: "installed_package_vulnerabilities".to_string(),
+                severity: Severity::Info,
+                passed: true,
+                message: format!("No known vulnerabilities in {} installed package(s).", scanned_packages),
+                details: None,
+            });
+        }
+        return results;
+    }
+
+    let mut by_package: BTreeMap<(&str, &str), Vec<&Advisory>> = BTreeMap::new();
+    for advisory in advisories {
+        by_package.entry((&advisory.package, &advisory.version)).or_default().push(advisory);
+    }
+
+    results.extend(by_package.into_iter().map(|((name, version), advisories)| {
+        let severity = advisories.iter()
+            .map(|a| a.audit_severity())
+            .max_by_key(severity_rank)
+            .unwrap_or(Severity::Medium);
+
+        let details = advisories.iter().map(|a| {
+            let cves = a.cve_ids();
+            let id = if cves.is_empty() || cves == [a.id.as_str()] {
+                a.id.clone()
+            } else {
+                format!("{} ({})", a.id, cves.join(", "))
+            };
+            format!("{} [{}] {}", id, a.severity, a.summary)
+        }).collect::<Vec<_>>().join("; ");
+
+        SecurityCheckResult {
+            check_name: format!("vulnerability:{}", name),
+            severity,
+            passed: false,
+            message: format!("{} {} has {} known vulnerabilit{}", name, version, advisories.len(),
+                if advisories.len() == 1 { "y" } else { "ies" }),
+            details: Some(details),
+        }
+    }));
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn advisory(id: &str, package: &str, severity: &str) -> Advisory {
+        Advisory {
+            id: id.to_string(),
+            package: package.to_string(),
+            version: "1.0-1".to_string(),
+            summary: "test".to_string(),
+            severity: severity.to_string(),
+            aliases: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_osv_severity_from_debian_urgency() {
+        let vuln: OsvVuln = serde_json::from_str(r#"{
+            "id": "DSA-1234-1",
+            "aliases": ["CVE-2024-0001"],
+            "affected": [{"ecosystem_specific": {"urgency": "high"}}]
+        }"#).unwrap();
+        assert_eq!(vuln.severity(), "high");
+    }
+
+    #[test]
+    fn test_check_results_grouped_by_package() {
+        let advisories = vec![
+            advisory("CVE-2024-0001", "openssl", "critical"),
+            advisory("CVE-2024-0002", "openssl", "low"),
+            advisory("CVE-2024-0003", "curl", "medium"),
+        ];
+
+        let results = to_check_results(&advisories, 10, &[]);
+        assert_eq!(results.len(), 2);
+        let openssl = results.iter().find(|r| r.check_name == "vulnerability:openssl").unwrap();
+        assert_eq!(openssl.severity, Severity::Critical);
+        assert!(!openssl.passed);
+    }
+
+    #[test]
+    fn test_snapshot_filters_upgraded_packages() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("advisories.json");
+        let still_installed = vec![("openssl".to_string(), "1.0-1".to_string())];
+        save_snapshot(&path, &still_installed, &[advisory("CVE-2024-0001", "openssl", "high")]).unwrap();
+
+        let scan = load_snapshot(&path, &still_installed).unwrap();
+        assert_eq!(scan.advisories.len(), 1);
+        assert!(scan.unscanned.is_empty());
+
+        // The upgraded version was never looked up: unknown, not clean
+        let upgraded = vec![("openssl".to_string(), "1.0-2".to_string())];
+        let scan = load_snapshot(&path, &upgraded).unwrap();
+        assert!(scan.advisories.is_empty());
+        assert_eq!(scan.unscanned, upgraded);
+
+        let results = to_check_results(&scan.advisories, 0, &scan.unscanned);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].check_name, "unscanned_packages");
+        assert!(!results[0].passed);
+    }
+
+    #[test]
+    fn test_source_findings_are_attributed_to_binaries() {
+        let package = |name: &str, version: &str, source: &str| SystemPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: source.to_string(),
+            source_version: "3.0.11-1".to_string(),
+        };
+        let packages = vec![
+            package("libssl3", "3.0.11-1+b1", "openssl"),
+            package("openssl", "3.0.11-1", "openssl"),
+            package("curl", "3.0.11-1", "curl"),
+        ];
+        assert_eq!(source_packages(&packages), [
+            ("curl".to_string(), "3.0.11-1".to_string()),
+            ("openssl".to_string(), "3.0.11-1".to_string()),
+        ]);
+
+        let mut finding = advisory("CVE-2024-0001", "openssl", "high");
+        finding.version = "3.0.11-1".to_string();
+        let scan = SnapshotScan {
+            advisories: vec![finding],
+            unscanned: vec![("curl".to_string(), "3.0.11-1".to_string())],
+        }.for_binaries(&packages);
+
+        let affected: Vec<(&str, &str)> = scan.advisories.iter().map(|a| (a.package.as_str(), a.version.as_str())).collect();
+        assert_eq!(affected, [("libssl3", "3.0.11-1+b1"), ("openssl", "3.0.11-1")]);
+        assert_eq!(scan.unscanned, [("curl".to_string(), "3.0.11-1".to_string())]);
+    }
+
+    /// OSV-like server that answers every advisory after a delay and records how many
+    /// advisory requests were in flight at once
+    async fn slow_osv_server(in_flight_max: std::sync::Arc<std::sync::atomic::AtomicUsize>) -> String {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let in_flight = std::sync::Arc::new(AtomicUsize::new(0));
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let (in_flight, in_flight_max) = (in_flight.clone(), in_flight_max.clone());
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buffer = [0u8; 4096];
+                    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match socket.read(&mut buffer).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buffer[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request).into_owned();
+                    let body = if request.starts_with("POST /querybatch") {
+                        r#"{"results":[{"vulns":[{"id":"CVE-1"},{"id":"CVE-2"}]},{"vulns":[{"id":"CVE-3"},{"id":"CVE-1"}]},{}]}"#.to_string()
+                    } else {
+                        let id = request.split_whitespace().nth(1).unwrap().trim_start_matches("/vulns/").to_string();
+                        let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        in_flight_max.fetch_max(current, Ordering::SeqCst);
+                        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                        format!(r#"{{"id":"{}","summary":"test","database_specific":{{"severity":"HIGH"}}}}"#, id)
+                    };
+                    let response = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        base
+    }
+
+    #[tokio::test]
+    async fn test_advisory_details_are_fetched_concurrently() {
+        let in_flight_max = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let base = slow_osv_server(in_flight_max.clone()).await;
+        let packages: Vec<(String, String)> = ["openssl", "curl", "bash"].iter()
+            .map(|name| (name.to_string(), "1.0-1".to_string()))
+            .collect();
+
+        let advisories = VulnerabilityScanner::new().unwrap()
+            .with_api_url(&base)
+            .scan(&packages)
+            .await
+            .unwrap();
+
+        let found: Vec<(&str, &str)> = advisories.iter().map(|a| (a.package.as_str(), a.id.as_str())).collect();
+        assert_eq!(found, [("openssl", "CVE-1"), ("openssl", "CVE-2"), ("curl", "CVE-3"), ("curl", "CVE-1")]);
+        assert!(advisories.iter().all(|a| a.severity == "high"));
+        // Three distinct advisories, all requested at the same time
+        assert_eq!(in_flight_max.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+}
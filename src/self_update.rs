@@ -3,13 +3,27 @@ use serde::Deserialize;
 use std::path::{Path, PathBuf};
 use std::fs;
 use std::env;
-use std::process::Command;
 use sha2::{Sha256, Digest};
 use hex;
 
-const GITHUB_API_URL: &str = "https://api.github.com/repos/KyoshiHikari/apt-ng/releases/latest";
+pub const GITHUB_API_URL: &str = "https://api.github.com/repos/KyoshiHikari/apt-ng/releases/latest";
 const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Höchstens so oft wird nach Befehlen nach einer neuen Version gesucht
+pub const UPDATE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+/// Gesetzt schaltet diese Umgebungsvariable den Hinweis auf neue Versionen ab
+pub const NO_UPDATE_CHECK_ENV: &str = "APT_NG_NO_UPDATE_CHECK";
+
+/// Öffentlicher Ed25519-Schlüssel der Release-Signaturen (64 Hex-Zeichen)
+///
+/// Release-Builds betten ihn über die Umgebungsvariable `APT_NG_RELEASE_KEY` zur Build-Zeit ein;
+/// ohne ihn kann `self-update` nur mit `--allow-unsigned-update` installieren.
+const RELEASE_KEY_HEX: Option<&str> = option_env!("APT_NG_RELEASE_KEY");
+
+/// Zeitlimit der Suche nach Befehlen, damit sie den Befehl nie merklich verzögert
+const QUICK_CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
 #[derive(Debug, Deserialize)]
 pub struct GitHubRelease {
     pub tag_name: String,
@@ -48,14 +62,23 @@ impl SelfUpdater {
     /// Calculate SHA256 checksum of the current binary
     pub fn get_current_binary_checksum() -> Result<String> {
         let exe_path = SelfUpdater::get_current_binary_path()?;
-        let data = fs::read(&exe_path)
-            .context("Failed to read current binary")?;
-        
-        let mut hasher = Sha256::new();
-        hasher.update(&data);
-        let checksum = hex::encode(hasher.finalize());
-        
-        Ok(checksum)
+        SelfUpdater::file_checksum(&exe_path).context("Failed to read current binary")
+    }
+
+    /// SHA256-Prüfsumme einer Datei (Hex, Kleinbuchstaben)
+    pub fn file_checksum(path: &Path) -> Result<String> {
+        let data = fs::read(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(hex::encode(Sha256::digest(&data)))
+    }
+
+    /// Ob das Release installiert werden soll
+    ///
+    /// Entscheidet allein der Versionsvergleich; die Prüfsumme eines Release-Assets
+    /// (meist ein Archiv) ist mit der des laufenden Binaries nicht vergleichbar.
+    /// Mit `force` wird auch dieselbe oder eine ältere Version installiert.
+    pub fn update_needed(current_version: &str, latest_version: &str, force: bool) -> bool {
+        force || SelfUpdater::compare_versions(current_version, latest_version) == std::cmp::Ordering::Less
     }
 
     /// Get SHA256 checksum from GitHub release (from release notes or checksums file)
@@ -87,30 +110,8 @@ impl SelfUpdater {
                 
                 if response.status().is_success() {
                     let checksums_text = response.text().await?;
-                    
-                    // Parse checksums file (format: SHA256  filename or SHA256 *filename)
-                    for line in checksums_text.lines() {
-                        let line = line.trim();
-                        if line.is_empty() || line.starts_with('#') {
-                            continue;
-                        }
-                        
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        if parts.len() >= 2 {
-                            let checksum = parts[0];
-                            // Filename might be prefixed with * or space
-                            let filename = parts[1].trim_start_matches('*').trim_start();
-                            
-                            // Check if this checksum matches our asset (exact match or contains)
-                            if filename == asset.name || 
-                               filename.contains(&asset.name) || 
-                               asset.name.contains(filename) {
-                                // Validate checksum format
-                                if checksum.len() == 64 && checksum.chars().all(|c| c.is_ascii_hexdigit()) {
-                                    return Ok(Some(checksum.to_string()));
-                                }
-                            }
-                        }
+                    if let Some(checksum) = SelfUpdater::checksum_from_sums(&checksums_text, &asset.name) {
+                        return Ok(Some(checksum));
                     }
                 }
             }
@@ -137,61 +138,155 @@ impl SelfUpdater {
         Ok(None)
     }
 
-    /// Check if update is available by comparing SHA256 checksums
+    /// Sucht die Prüfsumme eines Assets in einer SHA256SUMS-Datei
+    ///
+    /// Format je Zeile: `<sha256>  <dateiname>` oder `<sha256> *<dateiname>` (Binärmodus).
+    /// Ein exakter Dateiname hat Vorrang vor Teilübereinstimmungen.
+    pub fn checksum_from_sums(checksums_text: &str, asset_name: &str) -> Option<String> {
+        let entries: Vec<(&str, &str)> = checksums_text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let checksum = parts.next()?;
+                let filename = parts.next()?.trim_start_matches('*');
+                let valid = checksum.len() == 64 && checksum.chars().all(|c| c.is_ascii_hexdigit());
+                valid.then_some((checksum, filename))
+            })
+            .collect();
+        
+        entries.iter()
+            .find(|(_, filename)| *filename == asset_name)
+            .or_else(|| entries.iter().find(|(_, filename)| filename.contains(asset_name) || asset_name.contains(filename)))
+            .map(|(checksum, _)| checksum.to_lowercase())
+    }
+    
+    /// Prüft eine heruntergeladene Datei gegen die SHA256-Prüfsumme aus dem Release
+    pub fn verify_checksum(path: &Path, expected: &str) -> Result<()> {
+        let actual = SelfUpdater::file_checksum(path)?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(crate::error::AptNgError::ChecksumMismatch {
+                expected: expected.to_string(),
+                actual,
+            }.into());
+        }
+        Ok(())
+    }
+
+    /// Verifier für Release-Signaturen mit dem eingebetteten Schlüssel
+    pub fn release_verifier() -> Result<crate::verifier::PackageVerifier> {
+        let key_hex = RELEASE_KEY_HEX
+            .ok_or_else(|| anyhow::anyhow!("This build of apt-ng has no release signing key embedded"))?;
+        SelfUpdater::verifier_for_key(key_hex)
+    }
+
+    fn verifier_for_key(key_hex: &str) -> Result<crate::verifier::PackageVerifier> {
+        let key = hex::decode(key_hex.trim()).context("Invalid release signing key")?;
+        Ok(crate::verifier::PackageVerifier::with_trusted_key(&key)?)
+    }
+
+    /// Abgesetzte Signatur eines Assets (`<asset>.sig`, 64 Byte Ed25519 wie von `apt-ng-build sign`)
+    pub fn find_signature_asset<'a>(&self, release: &'a GitHubRelease, asset: &ReleaseAsset) -> Option<&'a ReleaseAsset> {
+        let name = format!("{}.sig", asset.name);
+        release.assets.iter().find(|candidate| candidate.name == name)
+    }
+
+    /// Prüft die Signatur einer heruntergeladenen Datei
+    ///
+    /// Anders als die Prüfsumme, die vom selben Ort wie das Binary stammt, lässt sich die
+    /// Signatur ohne den privaten Release-Schlüssel nicht fälschen.
+    pub fn verify_signature(verifier: &crate::verifier::PackageVerifier, path: &Path, signature: &[u8]) -> Result<()> {
+        let data = fs::read(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        verifier.verify_package_signature(&data, signature)?;
+        Ok(())
+    }
+
+    /// Lädt ein kleines Asset (z.B. eine Signatur) in den Speicher
+    pub async fn download_asset_bytes(&self, asset: &ReleaseAsset) -> Result<Vec<u8>> {
+        let response = self.client
+            .get(&asset.browser_download_url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to download {}", asset.name))?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Download of {} failed with status: {}", asset.name, response.status()));
+        }
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// Check if an update for this architecture is available (per Versionsvergleich)
     pub async fn check_update_available(&self) -> Result<bool> {
-        let current_checksum = SelfUpdater::get_current_binary_checksum()?;
         let arch = SelfUpdater::get_architecture()?;
         
         let release = self.check_for_latest_version().await?;
-        let asset = self.find_asset_for_architecture(&release, &arch)
-            .ok_or_else(|| anyhow::anyhow!("No binary found for architecture: {}", arch))?;
-        
-        // Try to get checksum from GitHub
-        if let Some(latest_checksum) = self.get_latest_binary_checksum(asset).await? {
-            return Ok(current_checksum != latest_checksum);
+        if self.find_asset_for_architecture(&release, &arch).is_none() {
+            return Err(anyhow::anyhow!("No binary found for architecture: {}", arch));
         }
         
-        // If checksum not available, fallback to version comparison
-        let latest_version = release.tag_name.trim_start_matches('v');
-        let comparison = SelfUpdater::compare_versions(&SelfUpdater::get_current_version(), latest_version);
-        Ok(comparison == std::cmp::Ordering::Less)
+        Ok(SelfUpdater::update_needed(&SelfUpdater::get_current_version(), &release.tag_name, false))
     }
 
-    /// Quick check for updates (non-blocking, returns immediately if GitHub API is slow)
-    pub async fn quick_check_update_available(&self) -> Option<bool> {
-        use tokio::time::{timeout, Duration};
-        
-        // Set a longer timeout (5 seconds) to give GitHub API more time
-        match timeout(Duration::from_secs(5), self.check_update_available()).await {
-            Ok(Ok(available)) => Some(available),
+    /// Neueres Release als die laufende Version (Tag wie `v1.2.0`), per Versionsvergleich
+    ///
+    /// Anders als `check_update_available` ohne Suche nach einem passenden Asset.
+    pub async fn newer_release(&self) -> Result<Option<String>> {
+        let release = self.check_for_latest_version().await?;
+        let comparison = SelfUpdater::compare_versions(&SelfUpdater::get_current_version(), &release.tag_name);
+        Ok((comparison == std::cmp::Ordering::Less).then_some(release.tag_name))
+    }
+
+    /// Quick check for updates: gibt nach spätestens zwei Sekunden auf, Fehler werden verschluckt
+    pub async fn quick_check_update_available(&self) -> Option<String> {
+        match tokio::time::timeout(QUICK_CHECK_TIMEOUT, self.newer_release()).await {
+            Ok(Ok(newer)) => newer,
             Ok(Err(e)) => {
-                // Log error in debug mode but don't show to user
-                // Use the error to determine if we should retry or just ignore
-                let error_msg = format!("{}", e);
-                #[cfg(debug_assertions)]
-                eprintln!("Update check error: {}", error_msg);
-                // For network errors, we might want to retry, but for now just ignore
-                if error_msg.contains("timeout") || error_msg.contains("network") {
-                    // Network issues - silently ignore
-                }
-                None // Error checking, don't show update message
+                tracing::debug!(error = %e, "update check failed");
+                None
             }
             Err(_) => None, // Timeout, don't block
         }
     }
 
+    /// Ob die tägliche Suche nach einer neuen Version fällig ist (Zeitstempel in `stamp`)
+    pub fn update_check_due(stamp: &Path, now: std::time::SystemTime) -> bool {
+        let last = fs::read_to_string(stamp)
+            .ok()
+            .and_then(|content| content.trim().parse::<u64>().ok());
+        let now = now.duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        last.is_none_or(|last| now.saturating_sub(last) >= UPDATE_CHECK_INTERVAL.as_secs())
+    }
+
+    /// Vermerkt eine Suche; auch erfolglose zählen, damit sie nicht bei jedem Befehl wiederholt wird
+    pub fn record_update_check(stamp: &Path, now: std::time::SystemTime) -> Result<()> {
+        if let Some(parent) = stamp.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let now = now.duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        fs::write(stamp, now.to_string())?;
+        Ok(())
+    }
+
+    /// Merkt sich ein gefundenes Release, damit ein späterer Aufruf darauf hinweist
+    pub fn record_update_notice(path: &Path, tag: &str) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, tag)?;
+        Ok(())
+    }
+
+    /// Liefert ein gemerktes Release genau einmal, solange es neuer als die laufende Version ist
+    pub fn take_update_notice(path: &Path) -> Option<String> {
+        let tag = fs::read_to_string(path).ok()?.trim().to_string();
+        let _ = fs::remove_file(path);
+        let newer = SelfUpdater::compare_versions(&SelfUpdater::get_current_version(), &tag) == std::cmp::Ordering::Less;
+        (!tag.is_empty() && newer).then_some(tag)
+    }
+
     /// Detect current system architecture
     pub fn get_architecture() -> Result<String> {
-        // Try to detect architecture using uname
-        let output = Command::new("uname")
-            .arg("-m")
-            .output()
-            .context("Failed to execute uname")?;
-        
-        let arch = String::from_utf8(output.stdout)
-            .context("Invalid UTF-8 in uname output")?
-            .trim()
-            .to_string();
+        let arch = crate::system::machine_name()?;
 
         // Map common architectures to Rust target triplets
         let target_triplet = match arch.as_str() {
@@ -273,25 +368,25 @@ impl SelfUpdater {
     }
 
     /// Find asset for current architecture
+    ///
+    /// Prüfsummen- und Signaturdateien (`SHA256SUMS`, `*.sig`, ...) sind keine Kandidaten.
     pub fn find_asset_for_architecture<'a>(&self, release: &'a GitHubRelease, arch: &str) -> Option<&'a ReleaseAsset> {
+        let binaries = || release.assets.iter().filter(|asset| !SelfUpdater::is_metadata_asset(&asset.name));
+
         // Try exact match first
-        for asset in &release.assets {
-            if asset.name.contains(arch) {
-                return Some(asset);
-            }
+        if let Some(asset) = binaries().find(|asset| asset.name.contains(arch)) {
+            return Some(asset);
         }
 
         // Try partial matches
-        let arch_parts: Vec<&str> = arch.split('-').collect();
-        if let Some(first_part) = arch_parts.first() {
-            for asset in &release.assets {
-                if asset.name.contains(first_part) {
-                    return Some(asset);
-                }
-            }
-        }
+        let first_part = arch.split('-').next()?;
+        binaries().find(|asset| asset.name.contains(first_part))
+    }
 
-        None
+    /// Prüfsummen- oder Signaturdatei statt Binary
+    fn is_metadata_asset(name: &str) -> bool {
+        const SUFFIXES: [&str; 5] = [".sha256", ".sig", ".minisig", ".asc", ".txt"];
+        name.contains("SHA256") || name.contains("checksums") || SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
     }
 
     /// Download binary from GitHub release
@@ -397,3 +492,158 @@ impl SelfUpdater {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_versions() {
+        use std::cmp::Ordering;
+
+        assert_eq!(SelfUpdater::compare_versions("0.9.0", "v0.10.0"), Ordering::Less);
+        assert_eq!(SelfUpdater::compare_versions("v1.2.3", "1.2.3"), Ordering::Equal);
+        assert_eq!(SelfUpdater::compare_versions("1.3", "1.2.9"), Ordering::Greater);
+        assert_eq!(SelfUpdater::compare_versions("2.0.0", "1.99.99"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_find_asset_skips_checksums_and_signatures() {
+        let asset = |name: &str| ReleaseAsset {
+            name: name.to_string(),
+            browser_download_url: format!("https://example.org/{}", name),
+            size: 1,
+            digest: None,
+        };
+        let release = GitHubRelease {
+            tag_name: "v1.0.0".to_string(),
+            assets: vec![
+                asset("SHA256SUMS"),
+                asset("apt-ng-x86_64-unknown-linux-gnu.tar.gz.sig"),
+                asset("apt-ng-aarch64-unknown-linux-gnu.tar.gz"),
+                asset("apt-ng-x86_64-unknown-linux-gnu.tar.gz"),
+            ],
+            body: None,
+        };
+        let updater = SelfUpdater::new().unwrap();
+
+        let found = updater.find_asset_for_architecture(&release, "x86_64-unknown-linux-gnu").unwrap();
+        assert_eq!(found.name, "apt-ng-x86_64-unknown-linux-gnu.tar.gz");
+        let found = updater.find_asset_for_architecture(&release, "aarch64-unknown-linux-musl").unwrap();
+        assert_eq!(found.name, "apt-ng-aarch64-unknown-linux-gnu.tar.gz");
+        assert!(updater.find_asset_for_architecture(&release, "riscv64gc-unknown-linux-gnu").is_none());
+    }
+
+    #[test]
+    fn test_update_check_rate_limit() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let stamp = temp_dir.path().join("state").join("last-update-check");
+        let now = std::time::SystemTime::now();
+        let hours = |h: u64| std::time::Duration::from_secs(h * 60 * 60);
+
+        assert!(SelfUpdater::update_check_due(&stamp, now));
+        SelfUpdater::record_update_check(&stamp, now).unwrap();
+        assert!(!SelfUpdater::update_check_due(&stamp, now + hours(1)));
+        assert!(SelfUpdater::update_check_due(&stamp, now + hours(25)));
+
+        fs::write(&stamp, "garbage").unwrap();
+        assert!(SelfUpdater::update_check_due(&stamp, now));
+    }
+
+    #[test]
+    fn test_update_notice_is_shown_once() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let notice = temp_dir.path().join("state").join("available-update");
+
+        assert_eq!(SelfUpdater::take_update_notice(&notice), None);
+        SelfUpdater::record_update_notice(&notice, "v999.0.0").unwrap();
+        assert_eq!(SelfUpdater::take_update_notice(&notice).as_deref(), Some("v999.0.0"));
+        assert_eq!(SelfUpdater::take_update_notice(&notice), None);
+
+        // Inzwischen installierte oder ältere Releases werden nicht mehr gemeldet
+        SelfUpdater::record_update_notice(&notice, &format!("v{}", SelfUpdater::get_current_version())).unwrap();
+        assert_eq!(SelfUpdater::take_update_notice(&notice), None);
+        assert!(!notice.exists());
+    }
+
+    #[test]
+    fn test_verify_signature_with_release_key() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = temp_dir.path().join("apt-ng-x86_64-unknown-linux-gnu.tar.gz");
+        fs::write(&archive, b"release archive").unwrap();
+
+        let release_key = SigningKey::from_bytes(&[7; 32]);
+        let verifier = SelfUpdater::verifier_for_key(&hex::encode(release_key.verifying_key().as_bytes())).unwrap();
+        let signature = release_key.sign(b"release archive").to_bytes();
+        SelfUpdater::verify_signature(&verifier, &archive, &signature).unwrap();
+
+        // Matching checksums do not help a release signed with another key
+        let attacker_key = SigningKey::from_bytes(&[9; 32]);
+        let forged = attacker_key.sign(b"release archive").to_bytes();
+        assert!(SelfUpdater::verify_signature(&verifier, &archive, &forged).is_err());
+
+        fs::write(&archive, b"tampered archive").unwrap();
+        assert!(SelfUpdater::verify_signature(&verifier, &archive, &signature).is_err());
+        assert!(SelfUpdater::verifier_for_key("not hex").is_err());
+    }
+
+    #[test]
+    fn test_checksum_from_sums() {
+        let sums = format!(
+            "# apt-ng release checksums\n{}  apt-ng-x86_64-unknown-linux-gnu.tar.gz.sig\n{} *apt-ng-x86_64-unknown-linux-gnu.tar.gz\n{}  apt-ng-aarch64-unknown-linux-gnu.tar.gz\nnot-a-checksum  apt-ng\n",
+            "a".repeat(64), "B".repeat(64), "c".repeat(64)
+        );
+
+        // Exakter Name vor Teilübereinstimmung, Binärmodus-Stern wird entfernt
+        assert_eq!(
+            SelfUpdater::checksum_from_sums(&sums, "apt-ng-x86_64-unknown-linux-gnu.tar.gz"),
+            Some("b".repeat(64))
+        );
+        assert_eq!(SelfUpdater::checksum_from_sums(&sums, "apt-ng-armv7-unknown-linux-gnueabihf.tar.gz"), None);
+    }
+
+    #[test]
+    fn test_update_decided_by_version_not_archive_checksum() {
+        assert!(SelfUpdater::update_needed("0.9.0", "v0.10.0", false));
+        assert!(!SelfUpdater::update_needed("1.2.3", "v1.2.3", false));
+        assert!(!SelfUpdater::update_needed("1.3.0", "1.2.9", false));
+        assert!(SelfUpdater::update_needed("1.2.3", "v1.2.3", true));
+
+        // Das Archiv hat eine andere Prüfsumme als das darin enthaltene, identische Binary
+        let temp_dir = tempfile::tempdir().unwrap();
+        let current = temp_dir.path().join("current");
+        fs::write(&current, b"apt-ng 1.2.3").unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(12);
+        header.set_mode(0o755);
+        header.set_cksum();
+        let mut builder = tar::Builder::new(Vec::new());
+        builder.append_data(&mut header, "apt-ng", &b"apt-ng 1.2.3"[..]).unwrap();
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut gz, &builder.into_inner().unwrap()).unwrap();
+        let archive = temp_dir.path().join("apt-ng-x86_64-unknown-linux-gnu.tar.gz");
+        fs::write(&archive, gz.finish().unwrap()).unwrap();
+
+        let extracted = temp_dir.path().join("apt-ng-new");
+        SelfUpdater::new().unwrap().extract_binary(&archive, &extracted).unwrap();
+        let current_checksum = SelfUpdater::file_checksum(&current).unwrap();
+        assert_ne!(SelfUpdater::file_checksum(&archive).unwrap(), current_checksum);
+        assert_eq!(SelfUpdater::file_checksum(&extracted).unwrap(), current_checksum);
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let binary = temp_dir.path().join("apt-ng");
+        fs::write(&binary, b"new apt-ng binary").unwrap();
+        let checksum = hex::encode(Sha256::digest(b"new apt-ng binary"));
+
+        SelfUpdater::verify_checksum(&binary, &checksum.to_uppercase()).unwrap();
+        let err = SelfUpdater::verify_checksum(&binary, &"0".repeat(64)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<crate::error::AptNgError>(),
+            Some(crate::error::AptNgError::ChecksumMismatch { .. })
+        ));
+    }
+}
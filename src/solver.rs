@@ -1,21 +1,18 @@
-use anyhow::Result;
+use crate::error::{AptNgError, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use std::process::Command;
-use std::sync::{Arc, Mutex};
+use std::sync::OnceLock;
 use crate::package::PackageManifest;
-use crate::apt_parser::parse_dependency_rule;
+use crate::apt_parser::{parse_dependency_rule, Provide};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[allow(dead_code)]
 pub struct PackageSpec {
     pub name: String,
     pub version: Option<String>,
     pub arch: Option<String>,
 }
 
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DependencyRule {
     pub name: String,
     pub version_constraint: Option<String>,
@@ -23,47 +20,133 @@ pub struct DependencyRule {
 }
 
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct PackageInfo {
     pub name: String,
     pub version: String,
     pub arch: String,
-    pub provides: Vec<String>,
+    pub provides: Vec<Provide>,
     pub depends: Vec<DependencyRule>,
-    pub conflicts: Vec<String>,
-    pub replaces: Vec<String>,
+    /// Conflicts und Breaks, jeweils mit optionaler Versionsangabe
+    pub conflicts: Vec<DependencyRule>,
+    /// Replaces, jeweils mit optionaler Versionsangabe
+    pub replaces: Vec<DependencyRule>,
+    /// Suggests, je Eintrag die Alternativen; nur mit `set_install_suggests` berücksichtigt
+    pub suggests: Vec<Vec<DependencyRule>>,
+    /// Repository, aus dem das Paket stammt (None bei lokalen Dateien)
+    pub repo_id: Option<i64>,
+}
+
+impl PackageInfo {
+    /// Prüft, ob dieses Paket eine Abhängigkeit erfüllt – über seinen eigenen Namen
+    /// oder ein Provides
+    ///
+    /// Wie bei dpkg erfüllen unversionierte Provides keine versionierten Abhängigkeiten;
+    /// bei versionierten Provides wird die bereitgestellte Version geprüft.
+    pub fn satisfies(&self, dep: &DependencyRule) -> bool {
+        if self.name == dep.name {
+            return match dep.version_constraint {
+                Some(ref constraint) => DependencySolver::version_matches(&self.version, constraint),
+                None => true,
+            };
+        }
+        
+        self.provides_matching(&dep.name, dep.version_constraint.as_deref())
+    }
+    
+    /// Prüft, ob dieses Paket `name` per Provides bereitstellt und die dort angegebene
+    /// Version `constraint` erfüllt (nicht die Version des Pakets selbst)
+    fn provides_matching(&self, name: &str, constraint: Option<&str>) -> bool {
+        self.provides.iter()
+            .filter(|provided| provided.name == name)
+            .any(|provided| match (constraint, &provided.version) {
+                (None, _) => true,
+                (Some(constraint), Some(version)) => DependencySolver::version_matches(version, constraint),
+                (Some(_), None) => false,
+            })
+    }
+    
+    /// Prüft, ob dieses Paket per Conflicts/Breaks mit `other` kollidiert
+    ///
+    /// Wie bei dpkg zählt ein Konflikt mit sich selbst nicht (z.B. `Provides: mta`
+    /// zusammen mit `Conflicts: mta`).
+    pub fn conflicts_with(&self, other: &PackageInfo) -> bool {
+        self.name != other.name && self.conflicts.iter().any(|conflict| other.satisfies(conflict))
+    }
+    
+    /// Namen aller Pakete (einschließlich virtueller), die dieses Paket bereitstellt
+    fn provided_names(&self) -> impl Iterator<Item = &str> {
+        self.provides.iter().map(|provided| provided.name.as_str())
+    }
 }
 
+/// Ergebnis einer Auflösung, nach Aktion getrennt
+///
+/// Die Einordnung stützt sich auf die per `set_installed_versions` gesetzten Versionen;
+/// bereits in derselben Version installierte Pakete tauchen nur in `closure` auf. Die
+/// gemeinsame Installationsreihenfolge von `to_install` und `to_upgrade` liefert
+/// `install_order`.
 #[derive(Debug)]
-#[allow(dead_code)]
 pub struct Solution {
+    /// Neu zu installierende Pakete (auch Downgrades)
     pub to_install: Vec<PackageInfo>,
+    /// Installierte Pakete, die wegen Conflicts/Breaks weichen müssen oder als veraltet
+    /// ersetzt werden, alphabetisch
     pub to_remove: Vec<String>,
+    /// Installierte Pakete, die auf eine neuere Version gehoben werden
     pub to_upgrade: Vec<PackageInfo>,
+    /// Vollständige Hülle der Lösung in Installationsreihenfolge, einschließlich der
+    /// installierten Pakete, mit denen Abhängigkeiten bereits erfüllt sind
+    pub closure: Vec<PackageInfo>,
+}
+
+impl Solution {
+    /// Neu zu installierende und zu aktualisierende Pakete in Installationsreihenfolge
+    /// (Abhängigkeiten zuerst)
+    pub fn install_order(&self) -> Vec<&PackageInfo> {
+        let changed = |pkg: &PackageInfo| {
+            self.to_install.iter()
+                .chain(&self.to_upgrade)
+                .any(|other| other.name == pkg.name && other.version == pkg.version && other.arch == pkg.arch)
+        };
+        self.closure.iter().filter(|pkg| changed(pkg)).collect()
+    }
 }
 
-#[allow(dead_code)]
 pub struct DependencySolver {
     packages: HashMap<String, Vec<PackageInfo>>,
     installed_packages: HashSet<String>,
+    installed_versions: HashMap<String, String>,
     installed_provides: HashMap<String, Vec<String>>, // Maps dependency name to list of installed packages that provide it
+    target_repos: HashSet<i64>,
+    /// Nicht von apt-ng verwaltete Systempakete, beim ersten Bedarf einmal gelesen
+    system: OnceLock<crate::dpkg::SystemSnapshot>,
+    install_suggests: bool,
+    /// Installierte Pakete, die in keinem Repository mehr verfügbar sind
+    obsolete: HashSet<String>,
+    /// Versionen (Name, Version), die upstream nicht mehr angeboten werden
+    unavailable: HashSet<(String, String)>,
 }
 
 impl DependencySolver {
-    #[allow(dead_code)]
     pub fn new() -> Self {
         DependencySolver {
             packages: HashMap::new(),
             installed_packages: HashSet::new(),
+            installed_versions: HashMap::new(),
             installed_provides: HashMap::new(),
+            target_repos: HashSet::new(),
+            system: OnceLock::new(),
+            install_suggests: false,
+            obsolete: HashSet::new(),
+            unavailable: HashSet::new(),
         }
     }
     
     /// Set the list of already-installed packages
     /// Dependencies satisfied by these packages will be skipped during resolution
-    #[allow(dead_code)]
     pub fn set_installed_packages(&mut self, installed: HashSet<String>) {
         self.installed_packages = installed;
+        self.installed_versions.clear();
         // Rebuild installed_provides map
         self.installed_provides.clear();
         for (pkg_name, pkgs) in &self.packages {
@@ -76,9 +159,9 @@ impl DependencySolver {
                         .push(pkg.name.clone());
                     
                     // Add explicit provides
-                    for provided in &pkg.provides {
+                    for provided in pkg.provided_names() {
                         self.installed_provides
-                            .entry(provided.clone())
+                            .entry(provided.to_string())
                             .or_insert_with(Vec::new)
                             .push(pkg.name.clone());
                     }
@@ -87,6 +170,59 @@ impl DependencySolver {
         }
     }
     
+    /// Setzt die installierten Pakete samt ihrer Version
+    ///
+    /// Wie `set_installed_packages`; zusätzlich lassen sich versionierte Conflicts/Breaks
+    /// gegen den tatsächlich installierten Stand prüfen.
+    pub fn set_installed_versions(&mut self, installed: HashMap<String, String>) {
+        self.set_installed_packages(installed.keys().cloned().collect());
+        self.installed_versions = installed;
+    }
+    
+    /// Setzt den Stand der Systempakete (dpkg), statt ihn beim ersten Bedarf zu lesen
+    pub fn set_system_snapshot(&mut self, snapshot: crate::dpkg::SystemSnapshot) {
+        self.system = OnceLock::from(snapshot);
+    }
+    
+    /// Bevorzugt Versionen aus diesen Repositories (`--target-release`)
+    ///
+    /// Ihre Kandidaten gewinnen auch gegen neuere Versionen aus anderen Repositories,
+    /// solange sie die installierte Version nicht unterschreiten. Leer: stets die neueste.
+    pub fn set_target_repos(&mut self, repo_ids: HashSet<i64>) {
+        self.target_repos = repo_ids;
+    }
+    
+    /// Die installierte Fassung eines Pakets; ohne bekannte Version die erste im Solver
+    fn installed_package(&self, name: &str) -> Option<&PackageInfo> {
+        if !self.installed_packages.contains(name) {
+            return None;
+        }
+        let packages = self.packages.get(name)?;
+        match self.installed_versions.get(name) {
+            Some(version) => packages.iter().find(|pkg| pkg.version == *version),
+            None => packages.first(),
+        }
+    }
+    
+    /// Setzt die installierten Pakete, die upstream nicht mehr verfügbar sind
+    ///
+    /// Ersetzt ein ausgewähltes Paket eines davon per Replaces, wird es entfernt.
+    pub fn set_obsolete_packages(&mut self, obsolete: HashSet<String>) {
+        self.obsolete = obsolete;
+    }
+    
+    /// Setzt die Versionen (Name, Version), die kein Repository mehr anbietet
+    ///
+    /// Sie kommen nicht mehr als Kandidaten infrage; nur die installierte Version bleibt wählbar.
+    pub fn set_unavailable_versions(&mut self, unavailable: HashSet<(String, String)>) {
+        self.unavailable = unavailable;
+    }
+    
+    /// Nimmt die Suggests der ausgewählten Pakete mit in die Installation auf
+    pub fn set_install_suggests(&mut self, install_suggests: bool) {
+        self.install_suggests = install_suggests;
+    }
+    
     /// Convert PackageManifest to PackageInfo, parsing all dependency strings
     pub fn manifest_to_package_info(manifest: &PackageManifest) -> Result<PackageInfo> {
         // Parse depends strings into DependencyRule structs
@@ -98,29 +234,61 @@ impl DependencySolver {
             depends_rules.extend(rules);
         }
         
-        // Parse conflicts (usually simple package names, but may have version constraints)
+        // Parse conflicts (Conflicts and Breaks, version constraints are kept)
         let mut conflicts = Vec::new();
         for conflict_str in &manifest.conflicts {
-            let rules = parse_dependency_rule(conflict_str)?;
-            // For conflicts, we typically just need the package name
-            for rule in rules {
-                conflicts.push(rule.name);
-            }
+            conflicts.extend(parse_dependency_rule(conflict_str)?);
         }
         
+        // Replaces dient nur dem Erkennen veralteter Pakete: unlesbare Einträge werden ignoriert
+        let replaces = manifest.replaces.iter()
+            .filter_map(|replace| parse_dependency_rule(replace).ok())
+            .flatten()
+            .collect();
+        
+        // Suggests sind optional: unlesbare Einträge werden ignoriert statt das Paket zu verwerfen
+        let suggests = manifest.suggests.iter()
+            .filter_map(|suggest| parse_dependency_rule(suggest).ok())
+            .filter(|alternatives| !alternatives.is_empty())
+            .collect();
+        
         Ok(PackageInfo {
             name: manifest.name.clone(),
             version: manifest.version.clone(),
             arch: manifest.arch.clone(),
-            provides: manifest.provides.clone(),
+            provides: manifest.provides.iter().map(|p| Provide::parse(p)).collect(),
             depends: depends_rules,
             conflicts,
-            replaces: manifest.replaces.clone(),
+            replaces,
+            suggests,
+            repo_id: manifest.repo_id,
         })
     }
     
+    /// Baut einen Solver aus Manifesten, z.B. aus `Index::get_all_packages`
+    ///
+    /// Die Abhängigkeiten werden dabei genau einmal geparst; der Solver kann danach für
+    /// mehrere `solve`-Aufrufe wiederverwendet werden. Pakete, deren Abhängigkeiten sich
+    /// nicht parsen lassen, werden übersprungen und mit ihrem Fehler zurückgegeben.
+    pub fn from_manifests<'a>(manifests: impl IntoIterator<Item = &'a PackageManifest>) -> (Self, Vec<(String, AptNgError)>) {
+        let mut solver = DependencySolver::new();
+        let skipped = solver.add_manifests(manifests);
+        (solver, skipped)
+    }
+    
+    /// Fügt weitere Manifeste hinzu; liefert die übersprungenen Pakete wie `from_manifests`
+    pub fn add_manifests<'a>(&mut self, manifests: impl IntoIterator<Item = &'a PackageManifest>) -> Vec<(String, AptNgError)> {
+        let mut skipped = Vec::new();
+        for manifest in manifests {
+            match Self::manifest_to_package_info(manifest) {
+                Ok(pkg_info) => self.add_package(pkg_info),
+                Err(e) => skipped.push((manifest.name.clone(), e)),
+            }
+        }
+        skipped
+    }
+    
     /// Fügt ein Paket zum Solver hinzu
-    #[allow(dead_code)]
     pub fn add_package(&mut self, pkg: PackageInfo) {
         let is_installed = self.installed_packages.contains(&pkg.name);
         
@@ -138,9 +306,9 @@ impl DependencySolver {
                 .push(pkg.name.clone());
             
             // Add explicit provides
-            for provided in &pkg.provides {
+            for provided in pkg.provided_names() {
                 self.installed_provides
-                    .entry(provided.clone())
+                    .entry(provided.to_string())
                     .or_insert_with(Vec::new)
                     .push(pkg.name.clone());
             }
@@ -148,7 +316,6 @@ impl DependencySolver {
     }
     
     /// Löst Abhängigkeiten für die angeforderten Pakete
-    #[allow(dead_code)]
     pub fn solve(&self, requested: &[PackageSpec]) -> Result<Solution> {
         self.solve_parallel(requested, false)
     }
@@ -170,6 +337,174 @@ impl DependencySolver {
         }
     }
     
+    /// Löst Abhängigkeiten mit höchstens `jobs` Threads (1 = sequentiell)
+    ///
+    /// Verwendet einen eigenen rayon-Pool statt des globalen, damit die konfigurierte
+    /// Obergrenze auch für die parallele Auflösung gilt.
+    pub fn solve_with_jobs(&self, requested: &[PackageSpec], jobs: usize) -> Result<Solution> {
+        if jobs <= 1 {
+            return self.solve_sequential(requested);
+        }
+        
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build()
+            .map_err(|e| AptNgError::Other(anyhow::Error::new(e)))?;
+        pool.install(|| self.solve_parallel_impl(requested))
+    }
+    
+    /// Prüft, ob ein Upgrade zurückgehalten werden muss, und liefert den Grund
+    ///
+    /// Ein Upgrade wird zurückgehalten, wenn seine Abhängigkeiten nicht auflösbar sind
+    /// oder ein dafür nötiges Paket mit einem installierten Paket kollidiert, das dann
+    /// entfernt werden müsste.
+    pub fn kept_back_reason(&self, spec: &PackageSpec) -> Option<String> {
+        let solution = match self.solve(std::slice::from_ref(spec)) {
+            Ok(solution) => solution,
+            Err(e) => return Some(Self::failure_reason(e)),
+        };
+        
+        if solution.to_remove.is_empty() {
+            None
+        } else {
+            Some(format!("upgrade would require removing {}", solution.to_remove.join(", ")))
+        }
+    }
+    
+    /// Wie `kept_back_reason`, hält aber nur unauflösbare Upgrades zurück
+    ///
+    /// Für `full-upgrade`: nötige Entfernungen sind dort erlaubt.
+    pub fn unresolvable_reason(&self, spec: &PackageSpec) -> Option<String> {
+        self.solve(std::slice::from_ref(spec)).err().map(Self::failure_reason)
+    }
+    
+    fn failure_reason(error: AptNgError) -> String {
+        match error {
+            AptNgError::UnresolvedDependency { dependency, .. } => {
+                format!("dependency {} cannot be satisfied", dependency)
+            }
+            AptNgError::NoMatchingVersion { name, constraint } => {
+                format!("no version of {} matches {}", name, constraint)
+            }
+            AptNgError::Conflict(conflicts) => {
+                format!("conflicting dependencies: {}", conflicts.join(", "))
+            }
+            e => e.to_string(),
+        }
+    }
+    
+    /// Teilt die aufgelösten Pakete in Neuinstallationen, Upgrades und Entfernungen auf
+    fn categorize(&self, selection: Vec<PackageInfo>) -> Solution {
+        let to_remove = self.removals_for(&selection);
+        let closure = self.closure(&selection);
+        let mut to_install = Vec::new();
+        let mut to_upgrade = Vec::new();
+        
+        for pkg in selection {
+            match self.installed_versions.get(&pkg.name) {
+                Some(installed) => match Self::compare_versions(&pkg.version, installed) {
+                    std::cmp::Ordering::Greater => to_upgrade.push(pkg),
+                    std::cmp::Ordering::Equal => {}
+                    std::cmp::Ordering::Less => to_install.push(pkg),
+                },
+                None => to_install.push(pkg),
+            }
+        }
+        
+        Solution { to_install, to_remove, to_upgrade, closure }
+    }
+    
+    /// Ergänzt die Auswahl um die installierten Pakete, die ihre Abhängigkeiten erfüllen
+    ///
+    /// Die Auflösung überspringt bereits installierte Abhängigkeiten; für ein Lockfile wird
+    /// die ganze Hülle gebraucht. Abhängigkeiten stehen vor ihren Nutzern, Systempakete
+    /// außerhalb von apt-ng bleiben außen vor.
+    fn closure(&self, selection: &[PackageInfo]) -> Vec<PackageInfo> {
+        fn visit<'a>(
+            solver: &'a DependencySolver,
+            pkg: &'a PackageInfo,
+            selection: &'a [PackageInfo],
+            visited: &mut HashSet<&'a str>,
+            closure: &mut Vec<PackageInfo>,
+        ) {
+            if !visited.insert(pkg.name.as_str()) {
+                return;
+            }
+            for dep in &pkg.depends {
+                let provider = selection.iter()
+                    .find(|selected| selected.satisfies(dep))
+                    .or_else(|| {
+                        std::iter::once(&dep.name)
+                            .chain(solver.installed_provides.get(&dep.name).into_iter().flatten())
+                            .filter_map(|name| solver.installed_package(name))
+                            .find(|installed| installed.satisfies(dep))
+                    });
+                if let Some(provider) = provider {
+                    visit(solver, provider, selection, visited, closure);
+                }
+            }
+            closure.push(pkg.clone());
+        }
+        
+        let mut visited = HashSet::new();
+        let mut closure = Vec::with_capacity(selection.len());
+        for pkg in selection {
+            visit(self, pkg, selection, &mut visited, &mut closure);
+        }
+        closure
+    }
+    
+    /// Ermittelt die installierten Pakete, die für die Auswahl entfernt werden müssen
+    ///
+    /// Das sind Pakete, die per Conflicts/Breaks mit einem ausgewählten Paket kollidieren
+    /// (in beide Richtungen), sowie installierte Pakete, deren Abhängigkeiten danach nicht
+    /// mehr erfüllt wären, und veraltete Pakete (`set_obsolete_packages`), die ein
+    /// ausgewähltes Paket per Replaces ablöst. Ausgewählte Pakete ersetzen ihre installierte Fassung und werden
+    /// nie entfernt. Das Ergebnis ist alphabetisch sortiert.
+    pub fn removals_for(&self, selection: &[PackageInfo]) -> Vec<String> {
+        let selected: HashSet<&str> = selection.iter().map(|pkg| pkg.name.as_str()).collect();
+        let before: Vec<&PackageInfo> = self.installed_packages.iter()
+            .filter_map(|name| self.installed_package(name))
+            .collect();
+        let kept: Vec<&PackageInfo> = before.iter()
+            .copied()
+            .filter(|pkg| !selected.contains(pkg.name.as_str()))
+            .collect();
+        
+        let mut removed: HashSet<&str> = kept.iter()
+            .filter(|old| selection.iter().any(|new| new.conflicts_with(old) || old.conflicts_with(new)))
+            .map(|old| old.name.as_str())
+            .collect();
+        removed.extend(kept.iter()
+            .filter(|old| self.obsolete.contains(&old.name))
+            .filter(|old| selection.iter().any(|new| new.replaces.iter().any(|replace| old.satisfies(replace))))
+            .map(|old| old.name.as_str()));
+        
+        // Abhängigkeiten, die vorher erfüllt waren und es danach nicht mehr sind, ziehen
+        // das abhängige Paket mit, bis sich nichts mehr ändert
+        loop {
+            let satisfied_after = |dep: &DependencyRule| {
+                selection.iter().any(|pkg| pkg.satisfies(dep))
+                    || kept.iter().any(|pkg| !removed.contains(pkg.name.as_str()) && pkg.satisfies(dep))
+            };
+            let broken: Vec<&str> = kept.iter()
+                .filter(|pkg| !removed.contains(pkg.name.as_str()))
+                .filter(|pkg| pkg.depends.iter().any(|dep| {
+                    before.iter().any(|old| old.satisfies(dep)) && !satisfied_after(dep)
+                }))
+                .map(|pkg| pkg.name.as_str())
+                .collect();
+            if broken.is_empty() {
+                break;
+            }
+            removed.extend(broken);
+        }
+        
+        let mut removed: Vec<String> = removed.into_iter().map(String::from).collect();
+        removed.sort();
+        removed
+    }
+    
     /// Sequenzielle Dependency-Resolution (Standard)
     fn solve_sequential(&self, requested: &[PackageSpec]) -> Result<Solution> {
         let mut to_install = Vec::new();
@@ -177,205 +512,236 @@ impl DependencySolver {
         let mut conflicts = Vec::new();
         
         for spec in requested {
-            if let Some(packages) = self.packages.get(&spec.name) {
-                // Wähle die passende Version
-                let pkg = self.select_best_version(packages, spec)?;
-                
-                // Always resolve dependencies for requested packages, even if already installed
-                // This ensures upgrades are handled correctly
-                if !visited.contains(&pkg.name) {
-                    self.resolve_dependencies(&pkg, &mut to_install, &mut visited, &mut conflicts)?;
-                } else {
-                    // Package was already visited (as a dependency), but we still need to add it
-                    // if it was explicitly requested and not already in to_install
-                    if !to_install.iter().any(|p| p.name == pkg.name) {
-                        to_install.push(pkg.clone());
-                    }
-                }
+            // Wähle die passende Version (bei virtuellen Namen den Anbieter)
+            let pkg = self.select_requested(spec)?;
+            
+            // Always resolve dependencies for requested packages, even if already installed
+            // This ensures upgrades are handled correctly
+            if !visited.contains(&pkg.name) {
+                self.resolve_dependencies(&pkg, &mut to_install, &mut visited, &mut conflicts)?;
             } else {
-                return Err(anyhow::anyhow!("Package not found: {}", spec.name));
+                // Package was already visited (as a dependency), but we still need to add it
+                // if it was explicitly requested and not already in to_install
+                if !to_install.iter().any(|p| p.name == pkg.name) {
+                    to_install.push(pkg.clone());
+                }
             }
         }
         
         // Prüfe auf Konflikte
         if !conflicts.is_empty() {
-            return Err(anyhow::anyhow!("Conflicts detected: {:?}", conflicts));
+            return Err(AptNgError::Conflict(conflicts));
         }
         
-        Ok(Solution {
-            to_install,
-            to_remove: Vec::new(),
-            to_upgrade: Vec::new(),
-        })
+        Ok(self.categorize(self.with_suggests(to_install)))
     }
     
     /// Parallele Dependency-Resolution mit rayon
+    ///
+    /// Arbeitet den Abhängigkeitsgraphen ebenenweise als Work-Queue ab: Die teure Auflösung
+    /// einzelner Abhängigkeiten (inkl. Systemabfragen) läuft parallel und ohne Sperren, nur
+    /// der koordinierende Thread pflegt `visited` und die gefundenen Kanten. Die
+    /// Installationsreihenfolge (Abhängigkeiten zuerst) ergibt sich danach aus den Kanten.
     fn solve_parallel_impl(&self, requested: &[PackageSpec]) -> Result<Solution> {
         use rayon::prelude::*;
         
-        // Thread-safe Collections für parallele Zugriffe
-        let to_install = Arc::new(Mutex::new(Vec::new()));
-        let visited = Arc::new(Mutex::new(HashSet::new()));
-        let conflicts = Arc::new(Mutex::new(Vec::new()));
-        
-        // Parallele Verarbeitung der angeforderten Pakete
-        let results: Result<Vec<()>> = requested.par_iter()
-            .map(|spec| {
-                if let Some(packages) = self.packages.get(&spec.name) {
-                    // Wähle die passende Version
-                    let pkg = self.select_best_version(packages, spec)?;
-                    
-                    // Prüfe ob bereits besucht
-                    let visited_guard = visited.lock().unwrap();
-                    if !visited_guard.contains(&pkg.name) {
-                        drop(visited_guard);
-                        
-                        // Resolve dependencies (thread-safe)
-                        self.resolve_dependencies_parallel(
-                            &pkg,
-                            &to_install,
-                            &visited,
-                            &conflicts,
-                        )?;
-                    } else {
-                        // Package was already visited, but we still need to add it if requested
-                        let mut to_install_guard = to_install.lock().unwrap();
-                        if !to_install_guard.iter().any(|p| p.name == pkg.name) {
-                            to_install_guard.push(pkg.clone());
-                        }
-                    }
-                    
-                    Ok(())
-                } else {
-                    Err(anyhow::anyhow!("Package not found: {}", spec.name))
-                }
-            })
+        let roots = requested.iter()
+            .map(|spec| self.select_requested(spec))
+            .collect::<Result<Vec<&PackageInfo>>>()?;
+        
+        let mut visited: HashSet<&str> = HashSet::new();
+        let mut edges: HashMap<&str, Vec<&PackageInfo>> = HashMap::new();
+        // Jede Abhängigkeit wird nur einmal aufgelöst, auch wenn viele Pakete sie teilen
+        let mut resolved: HashMap<&DependencyRule, Option<&PackageInfo>> = HashMap::new();
+        let mut frontier: Vec<&PackageInfo> = roots.iter()
+            .copied()
+            .filter(|pkg| visited.insert(pkg.name.as_str()))
             .collect();
         
-        results?;
+        while !frontier.is_empty() {
+            let mut queued = HashSet::new();
+            let queue: Vec<&DependencyRule> = frontier.iter()
+                .flat_map(|pkg| pkg.depends.iter())
+                .filter(|dep| !resolved.contains_key(dep) && queued.insert(*dep))
+                .collect();
+            
+            let results = queue.par_iter()
+                .map(|dep| self.resolve_dependency(dep).map(|pkg| (*dep, pkg)))
+                .collect::<Result<Vec<_>>>()?;
+            resolved.extend(results);
+            
+            let mut next = Vec::new();
+            for pkg in &frontier {
+                let deps: Vec<&PackageInfo> = pkg.depends.iter()
+                    .filter_map(|dep| resolved[dep])
+                    .collect();
+                next.extend(deps.iter().copied().filter(|dep| visited.insert(dep.name.as_str())));
+                edges.insert(pkg.name.as_str(), deps);
+            }
+            frontier = next;
+        }
         
-        // Sammle Ergebnisse
-        let to_install = Arc::try_unwrap(to_install).unwrap().into_inner().unwrap();
-        let conflicts = Arc::try_unwrap(conflicts).unwrap().into_inner().unwrap();
+        let to_install = Self::install_order(&roots, &edges);
         
-        // Prüfe auf Konflikte
+        // Prüfe auf Konflikte innerhalb der Auswahl
+        let conflicts: Vec<String> = to_install.iter()
+            .flat_map(|pkg| {
+                to_install.iter()
+                    .filter(|other| pkg.conflicts_with(other))
+                    .map(move |other| format!("{} conflicts with {}", pkg.name, other.name))
+            })
+            .collect();
         if !conflicts.is_empty() {
-            return Err(anyhow::anyhow!("Conflicts detected: {:?}", conflicts));
+            return Err(AptNgError::Conflict(conflicts));
         }
         
-        Ok(Solution {
-            to_install,
-            to_remove: Vec::new(),
-            to_upgrade: Vec::new(),
-        })
+        Ok(self.categorize(self.with_suggests(to_install)))
     }
     
-    /// Parallele Version von resolve_dependencies mit thread-safe Collections
-    fn resolve_dependencies_parallel(
-        &self,
-        pkg: &PackageInfo,
-        to_install: &Arc<Mutex<Vec<PackageInfo>>>,
-        visited: &Arc<Mutex<HashSet<String>>>,
-        conflicts: &Arc<Mutex<Vec<String>>>,
-    ) -> Result<()> {
-        // Prüfe ob bereits besucht
-        {
-            let mut visited_guard = visited.lock().unwrap();
-            if visited_guard.contains(&pkg.name) {
-                return Ok(());
-            }
-            visited_guard.insert(pkg.name.clone());
+    /// Ergänzt die Auswahl um die Suggests ihrer Pakete, sofern `install_suggests` gesetzt ist
+    ///
+    /// Suggests sind keine harten Abhängigkeiten: Fehlt ein Vorschlag, lassen sich seine
+    /// Abhängigkeiten nicht auflösen oder kollidiert er mit der Auswahl, wird er übersprungen.
+    /// Von den Alternativen eines Eintrags wird die erste installierbare genommen.
+    fn with_suggests(&self, mut selection: Vec<PackageInfo>) -> Vec<PackageInfo> {
+        if !self.install_suggests {
+            return selection;
         }
         
-        // Prüfe Konflikte
-        {
-            let to_install_guard = to_install.lock().unwrap();
-            for conflict in &pkg.conflicts {
-                if to_install_guard.iter().any(|p| p.name == *conflict) {
-                    let mut conflicts_guard = conflicts.lock().unwrap();
-                    conflicts_guard.push(format!("{} conflicts with {}", pkg.name, conflict));
+        let suggests: Vec<Vec<DependencyRule>> = selection.iter()
+            .flat_map(|pkg| pkg.suggests.iter().cloned())
+            .collect();
+        for alternatives in suggests {
+            let satisfied = alternatives.iter().any(|dep| {
+                selection.iter().any(|pkg| pkg.satisfies(dep)) || self.is_dependency_satisfied_by_installed(dep)
+            });
+            if satisfied {
+                continue;
+            }
+            
+            for dep in &alternatives {
+                let spec = PackageSpec { name: dep.name.clone(), version: None, arch: dep.arch.clone() };
+                let Ok(candidate) = self.select_requested(&spec) else { continue };
+                if !candidate.satisfies(dep) {
+                    continue;
+                }
+                
+                let mut added = Vec::new();
+                let mut visited: HashSet<String> = selection.iter().map(|pkg| pkg.name.clone()).collect();
+                let mut conflicts = Vec::new();
+                if self.resolve_dependencies(candidate, &mut added, &mut visited, &mut conflicts).is_err() || !conflicts.is_empty() {
+                    continue;
+                }
+                let clashes = added.iter().any(|new| {
+                    selection.iter().any(|pkg| pkg.conflicts_with(new) || new.conflicts_with(pkg))
+                });
+                if !clashes {
+                    selection.extend(added);
+                    break;
                 }
             }
         }
+        selection
+    }
+    
+    /// Bringt die aufgelösten Pakete in Installationsreihenfolge (Abhängigkeiten zuerst)
+    ///
+    /// Iterativer Post-Order-Durchlauf, damit tiefe Ketten den Stack nicht sprengen;
+    /// Zyklen werden wie im sequentiellen Solver beim ersten Wiederbesuch abgeschnitten.
+    fn install_order(roots: &[&PackageInfo], edges: &HashMap<&str, Vec<&PackageInfo>>) -> Vec<PackageInfo> {
+        let mut order = Vec::new();
+        let mut entered = HashSet::new();
+        let mut placed = HashSet::new();
+        let mut stack: Vec<(&PackageInfo, bool)> = roots.iter().rev().map(|pkg| (*pkg, false)).collect();
         
-        // Parallele Verarbeitung der Dependencies mit rayon
-        use rayon::prelude::*;
-        
-        let dep_results: Result<Vec<()>> = pkg.depends.par_iter()
-            .map(|dep| {
-                // Check if dependency is already satisfied by an installed package
-                if self.is_dependency_satisfied_by_installed(dep) {
-                    return Ok(()); // Skip this dependency
+        while let Some((pkg, expanded)) = stack.pop() {
+            if expanded {
+                if placed.insert(pkg.name.as_str()) {
+                    order.push(pkg.clone());
                 }
-                
-                // Try to find package by name
-                if let Some(packages) = self.packages.get(&dep.name) {
-                    let dep_pkg = self.select_best_version(packages, &PackageSpec {
-                        name: dep.name.clone(),
-                        version: dep.version_constraint.clone(),
-                        arch: dep.arch.clone(),
-                    })?;
-                    
-                    self.resolve_dependencies_parallel(dep_pkg, to_install, visited, conflicts)?;
-                } else {
-                    // Check if any package provides this dependency
-                    // Parallele Suche durch alle Pakete
-                    let mut found = false;
-                    let packages_vec: Vec<_> = self.packages.iter().collect();
-                    
-                    for (_, pkgs) in &packages_vec {
-                        for pkg_candidate in pkgs.iter() {
-                            let provides_dep = pkg_candidate.name == dep.name || 
-                                              pkg_candidate.provides.contains(&dep.name);
-                            
-                            if provides_dep {
-                                // Check version constraint if specified
-                                if let Some(ref constraint) = dep.version_constraint {
-                                    if !Self::version_matches(&pkg_candidate.version, constraint) {
-                                        continue;
-                                    }
-                                }
-                                self.resolve_dependencies_parallel(pkg_candidate, to_install, visited, conflicts)?;
-                                found = true;
-                                break;
-                            }
-                        }
-                        if found {
-                            break;
-                        }
-                    }
-                    
-                    if !found {
-                        // Last resort: check if dependency is satisfied by a system package
-                        if Self::is_package_installed_on_system(&dep.name) || 
-                           Self::is_dependency_provided_by_system(&dep.name) {
-                            return Ok(()); // Dependency satisfied by system package
-                        }
-                        
-                        return Err(anyhow::anyhow!("Dependency not found: {}", dep.name));
+                continue;
+            }
+            if !entered.insert(pkg.name.as_str()) {
+                continue;
+            }
+            
+            stack.push((pkg, true));
+            if let Some(deps) = edges.get(pkg.name.as_str()) {
+                for dep in deps.iter().rev() {
+                    if !entered.contains(dep.name.as_str()) {
+                        stack.push((*dep, false));
                     }
                 }
-                
-                Ok(())
-            })
-            .collect();
-        
-        dep_results?;
-        
-        // Füge Paket hinzu, wenn noch nicht vorhanden
-        {
-            let mut to_install_guard = to_install.lock().unwrap();
-            if !to_install_guard.iter().any(|p| p.name == pkg.name) {
-                to_install_guard.push(pkg.clone());
             }
         }
         
-        Ok(())
+        order
+    }
+    
+    /// Paket für eine Anforderung, ohne seine Abhängigkeiten aufzulösen
+    ///
+    /// Wählt wie `solve` die Version (Pins, Zielrepositories, Architektur) und bei
+    /// virtuellen Namen den Anbieter; wird von `download` ohne `--resolve-deps` genutzt.
+    pub fn candidate(&self, spec: &PackageSpec) -> Result<&PackageInfo> {
+        self.select_requested(spec)
+    }
+    
+    /// Paket für eine Anforderung des Nutzers
+    ///
+    /// Ist `spec.name` nur ein virtueller Paketname, wird sein einziger Anbieter gewählt,
+    /// bei mehreren der bereits installierte. Bleibt die Wahl offen, liefert die Methode
+    /// `AptNgError::VirtualPackage` mit allen Anbietern.
+    fn select_requested(&self, spec: &PackageSpec) -> Result<&PackageInfo> {
+        if let Some(packages) = self.packages.get(&spec.name) {
+            return self.select_best_version(packages, spec);
+        }
+        
+        let providers = self.providers_of(spec);
+        let installed: Vec<&PackageInfo> = providers.iter()
+            .copied()
+            .filter(|pkg| self.installed_packages.contains(&pkg.name))
+            .collect();
+        match (providers.as_slice(), installed.as_slice()) {
+            ([], _) => Err(AptNgError::PackageNotFound(spec.name.clone())),
+            ([only], _) | (_, [only]) => Ok(only),
+            _ => Err(AptNgError::VirtualPackage {
+                name: spec.name.clone(),
+                providers: providers.iter().map(|pkg| pkg.name.clone()).collect(),
+            }),
+        }
+    }
+    
+    /// Reale Pakete, die eine Anforderung per Provides erfüllen (je Paket die neueste
+    /// passende Version), alphabetisch
+    pub fn providers_of(&self, spec: &PackageSpec) -> Vec<&PackageInfo> {
+        let mut providers: Vec<&PackageInfo> = self.packages.values()
+            .filter_map(|versions| {
+                versions.iter()
+                    .filter(|pkg| pkg.provides_matching(&spec.name, spec.version.as_deref()))
+                    .filter(|pkg| spec.arch.as_ref().is_none_or(|arch| pkg.arch == *arch || pkg.arch == "all"))
+                    .max_by(|a, b| Self::compare_versions(&a.version, &b.version))
+            })
+            .collect();
+        providers.sort_by(|a, b| a.name.cmp(&b.name));
+        providers
+    }
+    
+    /// Anforderung an den gewählten Anbieter eines virtuellen Pakets
+    ///
+    /// Die Version wird auf die Fassung festgelegt, deren Provides `spec` erfüllt; ohne
+    /// passenden Anbieter gilt die neueste Version.
+    pub fn provider_spec(&self, spec: &PackageSpec, provider: &str) -> PackageSpec {
+        let version = self.providers_of(spec)
+            .into_iter()
+            .find(|pkg| pkg.name == provider)
+            .filter(|_| spec.version.is_some())
+            .map(|pkg| format!("={}", pkg.version));
+        PackageSpec { name: provider.to_string(), version, arch: spec.arch.clone() }
     }
     
-    #[allow(dead_code)]
     fn select_best_version<'a>(&self, packages: &'a [PackageInfo], spec: &PackageSpec) -> Result<&'a PackageInfo> {
         // Filter packages by architecture if specified
+        let installed = self.installed_versions.get(&spec.name);
         let mut candidates: Vec<&PackageInfo> = packages.iter()
             .filter(|p| {
                 if let Some(ref arch) = spec.arch {
@@ -384,6 +750,8 @@ impl DependencySolver {
                     true
                 }
             })
+            // Upstream verschwundene Versionen lassen sich nicht mehr herunterladen
+            .filter(|p| installed == Some(&p.version) || !self.unavailable.contains(&(p.name.clone(), p.version.clone())))
             .collect();
         
         // If version constraint specified, filter by it
@@ -397,245 +765,169 @@ impl DependencySolver {
         }
         
         if candidates.is_empty() {
-            return Err(anyhow::anyhow!("No matching package found for {} {}", spec.name, spec.version.as_deref().unwrap_or("any version")));
+            return Err(AptNgError::NoMatchingVersion {
+                name: spec.name.clone(),
+                constraint: spec.version.clone().unwrap_or_else(|| "any version".to_string()),
+            });
+        }
+        
+        // Kandidaten aus dem Ziel-Release haben Vorrang, führen aber nie zu einem Downgrade
+        let targeted: Vec<&PackageInfo> = candidates.iter()
+            .copied()
+            .filter(|p| p.repo_id.is_some_and(|id| self.target_repos.contains(&id)))
+            .filter(|p| installed.is_none_or(|v| Self::compare_versions(&p.version, v) != std::cmp::Ordering::Less))
+            .collect();
+        let from_target = !targeted.is_empty();
+        if from_target {
+            candidates = targeted;
         }
         
         // Select newest version that matches constraints
-        candidates.iter()
+        let selected = candidates.iter()
             .max_by(|a, b| Self::compare_versions(&a.version, &b.version))
             .copied()
-            .ok_or_else(|| anyhow::anyhow!("No matching package found"))
+            .ok_or_else(|| AptNgError::PackageNotFound(spec.name.clone()))?;
+        tracing::debug!(
+            package = %spec.name,
+            constraint = spec.version.as_deref(),
+            version = %selected.version,
+            candidates = candidates.len(),
+            from_target,
+            "selected version"
+        );
+        Ok(selected)
     }
     
     /// Compare two Debian package versions
     /// Returns: Ordering::Less if v1 < v2, Ordering::Greater if v1 > v2, Ordering::Equal if v1 == v2
+    ///
+    /// Vergleich nach Debian Policy 5.6.12: Format `[epoch:]upstream-version[-debian-revision]`,
+    /// Epoch numerisch, Upstream-Version und Revision jeweils mit `compare_fragment`.
     pub fn compare_versions(v1: &str, v2: &str) -> std::cmp::Ordering {
-        // Simple version comparison - for production use, consider using debian-version crate
-        // Format: [epoch:]upstream-version[-debian-revision]
-        // This is a simplified implementation
-        
-        let parse_version = |v: &str| -> (u64, Vec<u64>, Vec<u64>) {
-            // Split epoch
-            let (epoch, rest) = if let Some(colon_pos) = v.find(':') {
-                let e = v[..colon_pos].parse::<u64>().unwrap_or(0);
-                (e, &v[colon_pos + 1..])
-            } else {
-                (0, v)
-            };
-            
-            // Split upstream and debian revision
-            let (upstream, debian) = if let Some(dash_pos) = rest.rfind('-') {
-                (&rest[..dash_pos], &rest[dash_pos + 1..])
-            } else {
-                (rest, "")
+        fn parse_version(v: &str) -> (u64, &str, &str) {
+            let v = v.trim();
+            // Split epoch (nur, wenn vor dem Doppelpunkt tatsächlich eine Zahl steht)
+            let (epoch, rest) = match v.split_once(':') {
+                Some((e, rest)) if e.bytes().all(|c| c.is_ascii_digit()) => (e.parse::<u64>().unwrap_or(0), rest),
+                _ => (0, v),
             };
             
-            // Parse upstream version (split by . and non-digit separators)
-            let upstream_parts: Vec<u64> = upstream
-                .split(|c: char| !c.is_ascii_digit())
-                .filter_map(|s| s.parse::<u64>().ok())
-                .collect();
-            
-            // Parse debian revision
-            let debian_parts: Vec<u64> = debian
-                .split(|c: char| !c.is_ascii_digit())
-                .filter_map(|s| s.parse::<u64>().ok())
-                .collect();
-            
-            (epoch, upstream_parts, debian_parts)
-        };
+            // Split upstream and debian revision (am letzten Bindestrich)
+            match rest.rsplit_once('-') {
+                Some((upstream, debian)) => (epoch, upstream, debian),
+                None => (epoch, rest, ""),
+            }
+        }
         
         let (e1, u1, d1) = parse_version(v1);
         let (e2, u2, d2) = parse_version(v2);
         
-        // Compare epoch
-        match e1.cmp(&e2) {
-            std::cmp::Ordering::Equal => {}
-            other => return other,
-        }
-        
-        // Compare upstream versions
-        for (a, b) in u1.iter().zip(u2.iter()) {
-            match a.cmp(b) {
-                std::cmp::Ordering::Equal => {}
-                other => return other,
+        e1.cmp(&e2)
+            .then_with(|| Self::compare_fragment(u1, u2))
+            .then_with(|| Self::compare_fragment(d1, d2))
+    }
+    
+    /// Vergleicht Upstream-Version oder Revision wie dpkg (`verrevcmp`)
+    ///
+    /// Abwechselnd wird ein Nicht-Ziffern-Teil zeichenweise verglichen – `~` vor allem, auch
+    /// vor dem Ende, Buchstaben vor allen anderen Zeichen – und danach ein Ziffernteil
+    /// numerisch (fehlende Ziffern zählen als 0).
+    fn compare_fragment(a: &str, b: &str) -> std::cmp::Ordering {
+        let weight = |c: Option<&u8>| -> i32 {
+            match c {
+                Some(b'~') => -1,
+                None => 0,
+                Some(c) if c.is_ascii_digit() => 0,
+                Some(c) if c.is_ascii_alphabetic() => i32::from(*c),
+                Some(c) => i32::from(*c) + 256,
             }
-        }
-        
-        // If one has more parts, it's newer
-        match u1.len().cmp(&u2.len()) {
-            std::cmp::Ordering::Equal => {}
-            other => return other,
-        }
+        };
+        let digits = |s: &[u8]| s.iter().take_while(|c| c.is_ascii_digit()).count();
         
-        // Compare debian revisions
-        for (a, b) in d1.iter().zip(d2.iter()) {
-            match a.cmp(b) {
-                std::cmp::Ordering::Equal => {}
-                other => return other,
+        let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+        while !a.is_empty() || !b.is_empty() {
+            while a.first().is_some_and(|c| !c.is_ascii_digit()) || b.first().is_some_and(|c| !c.is_ascii_digit()) {
+                let (wa, wb) = (weight(a.first()), weight(b.first()));
+                if wa != wb {
+                    return wa.cmp(&wb);
+                }
+                // Gleiches Gewicht heißt hier: auf beiden Seiten dasselbe Nicht-Ziffern-Zeichen
+                a = &a[1..];
+                b = &b[1..];
+            }
+            
+            let (na, nb) = (digits(a), digits(b));
+            // Ohne Umweg über u64, damit beliebig lange Zahlen (z.B. Datumsstempel) funktionieren
+            let trim = |s: &[u8]| -> Vec<u8> { s.iter().copied().skip_while(|c| *c == b'0').collect() };
+            let (da, db) = (trim(&a[..na]), trim(&b[..nb]));
+            let ordering = da.len().cmp(&db.len()).then_with(|| da.cmp(&db));
+            if ordering != std::cmp::Ordering::Equal {
+                return ordering;
             }
+            a = &a[na..];
+            b = &b[nb..];
         }
-        
-        d1.len().cmp(&d2.len())
+        std::cmp::Ordering::Equal
+    }
+    
+    /// Prüft, ob sich Epoch oder die erste Zahl der Upstream-Version ändert (z.B. 1.9 -> 2.0)
+    pub fn is_major_change(from: &str, to: &str) -> bool {
+        let major = |v: &str| -> (u64, Option<u64>) {
+            let (epoch, rest) = match v.split_once(':') {
+                Some((e, rest)) => (e.parse::<u64>().unwrap_or(0), rest),
+                None => (0, v),
+            };
+            let first = rest
+                .split(|c: char| !c.is_ascii_digit())
+                .find(|s| !s.is_empty())
+                .and_then(|s| s.parse::<u64>().ok());
+            (epoch, first)
+        };
+        major(from) != major(to)
     }
     
     /// Check if a version matches a constraint
+    ///
+    /// Format: ">= 1.0", "= 2.5", "<< 3.0" usw., Leerzeichen nach dem Operator sind optional
+    /// (">=1.0"). Wie in Debian Policy 7.1 sind die veralteten `<` und `>` nicht strikt.
     fn version_matches(version: &str, constraint: &str) -> bool {
-        // Parse constraint (format: ">= 1.0", "= 2.5", "<< 3.0", etc.)
+        use std::cmp::Ordering::{Equal, Greater, Less};
+        
         let constraint = constraint.trim();
+        let operator_len = constraint
+            .find(|c: char| !matches!(c, '<' | '>' | '='))
+            .unwrap_or(constraint.len());
+        let (operator, required) = constraint.split_at(operator_len);
+        let ordering = Self::compare_versions(version, required.trim());
         
-        if constraint.starts_with(">=") {
-            let req_version = constraint[2..].trim();
-            Self::compare_versions(version, req_version) != std::cmp::Ordering::Less
-        } else if constraint.starts_with("<=") {
-            let req_version = constraint[2..].trim();
-            Self::compare_versions(version, req_version) != std::cmp::Ordering::Greater
-        } else if constraint.starts_with(">>") {
-            let req_version = constraint[2..].trim();
-            Self::compare_versions(version, req_version) == std::cmp::Ordering::Greater
-        } else if constraint.starts_with("<<") {
-            let req_version = constraint[2..].trim();
-            Self::compare_versions(version, req_version) == std::cmp::Ordering::Less
-        } else if constraint.starts_with(">") {
-            let req_version = constraint[1..].trim();
-            Self::compare_versions(version, req_version) == std::cmp::Ordering::Greater
-        } else if constraint.starts_with("<") {
-            let req_version = constraint[1..].trim();
-            Self::compare_versions(version, req_version) == std::cmp::Ordering::Less
-        } else if constraint.starts_with("=") {
-            let req_version = constraint[1..].trim();
-            Self::compare_versions(version, req_version) == std::cmp::Ordering::Equal
-        } else {
+        match operator {
+            ">=" | ">" => ordering != Less,
+            "<=" | "<" => ordering != Greater,
+            ">>" => ordering == Greater,
+            "<<" => ordering == Less,
             // No operator, treat as exact match
-            Self::compare_versions(version, constraint) == std::cmp::Ordering::Equal
+            "=" | "" => ordering == Equal,
+            _ => false,
         }
     }
     
-    /// Check if a package is installed on the system (via dpkg)
-    /// Get version of a system package using dpkg -l
-    fn get_system_package_version(package_name: &str) -> Option<String> {
-        use std::process::Command;
-        
-        // Use dpkg-query to get version
-        let output = Command::new("dpkg-query")
-            .arg("-W")
-            .arg("-f=${Version}")
-            .arg(package_name)
-            .output();
-        
-        match output {
-            Ok(output) if output.status.success() => {
-                let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                if !version.is_empty() {
-                    Some(version)
-                } else {
-                    None
-                }
-            }
-            _ => None,
-        }
+    /// Systempakete aus einer einzigen dpkg-Abfrage statt eines Prozesses pro Abhängigkeit
+    fn system(&self) -> &crate::dpkg::SystemSnapshot {
+        self.system.get_or_init(crate::dpkg::SystemSnapshot::load)
     }
     
-    fn is_package_installed_on_system(package_name: &str) -> bool {
-        // Check if package is installed using dpkg-query
-        let output = Command::new("dpkg-query")
-            .arg("-W")
-            .arg("-f=${Status}")
-            .arg(package_name)
-            .output();
-        
-        if let Ok(output) = output {
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                // Status should contain "installed" for installed packages
-                return stdout.contains("installed");
-            }
-        }
-        
-        // Fallback: try dpkg -l
-        let output = Command::new("dpkg")
-            .arg("-l")
-            .arg(package_name)
-            .output();
-        
-        if let Ok(output) = output {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            // dpkg -l output format: "ii  package-name  version  description"
-            // "ii" means installed and configured
-            stdout.lines().any(|line| {
-                line.starts_with("ii") && line.split_whitespace().nth(1) == Some(package_name)
-            })
-        } else {
-            false
-        }
-    }
-    
-    /// Check if any system package provides a dependency (via apt-cache)
-    fn is_dependency_provided_by_system(dep_name: &str) -> bool {
-        // First, try dpkg-query to check if any installed package provides this
-        // This is faster and more reliable than apt-cache
-        let output = Command::new("dpkg-query")
-            .arg("-W")
-            .arg("-f=${Package} ${Provides}\n")
-            .output();
-        
-        if let Ok(output) = output {
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                for line in stdout.lines() {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 2 {
-                        let package_name = parts[0];
-                        // Check if this package provides the dependency
-                        for part in parts.iter().skip(1) {
-                            // Provides format: "libqt5core5t64 (= 5.15.13+dfsg-1)" or just "libqt5core5t64"
-                            let provided = part.split('(').next().unwrap_or(part).trim();
-                            if provided == dep_name {
-                                if Self::is_package_installed_on_system(package_name) {
-                                    return true;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        
-        // Fallback: Use apt-cache to find packages that provide this dependency
-        let output = Command::new("apt-cache")
-            .arg("showpkg")
-            .arg(dep_name)
-            .output();
-        
-        if let Ok(output) = output {
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                // apt-cache showpkg shows providers in the output
-                // Look for "Reverse Provides:" section
-                let mut in_reverse_provides = false;
-                for line in stdout.lines() {
-                    if line.starts_with("Reverse Provides:") {
-                        in_reverse_provides = true;
-                        continue;
-                    }
-                    if in_reverse_provides {
-                        if line.trim().is_empty() {
-                            break;
-                        }
-                        // Check if any provider is installed
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        if !parts.is_empty() {
-                            let provider_name = parts[0];
-                            if Self::is_package_installed_on_system(provider_name) {
-                                return true;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        
-        false
+    /// Installierte Version eines Systempakets (dpkg-query bzw. dpkg-Statusdatei)
+    fn get_system_package_version(&self, package_name: &str) -> Option<&str> {
+        self.system().installed_version(package_name)
+    }
+    
+    fn is_package_installed_on_system(&self, package_name: &str) -> bool {
+        self.system().is_installed(package_name)
+    }
+    
+    /// Check if any system package provides a dependency (Provides in the dpkg database)
+    fn is_dependency_provided_by_system(&self, dep_name: &str) -> bool {
+        self.system().is_provided(dep_name)
     }
     
     /// Check if a dependency is satisfied by an already-installed package
@@ -644,6 +936,10 @@ impl DependencySolver {
         if self.installed_packages.contains(&dep.name) {
             // If version constraint specified, we need to check versions
             if let Some(ref constraint) = dep.version_constraint {
+                // Mit bekannter Version zählt nur die tatsächlich installierte
+                if let Some(installed) = self.installed_versions.get(&dep.name) {
+                    return Self::version_matches(installed, constraint);
+                }
                 if let Some(pkgs) = self.packages.get(&dep.name) {
                     for pkg in pkgs {
                         if Self::version_matches(&pkg.version, constraint) {
@@ -659,15 +955,12 @@ impl DependencySolver {
         // Check if any installed package provides this dependency
         if let Some(providers) = self.installed_provides.get(&dep.name) {
             if !providers.is_empty() {
-                // If version constraint specified, we need to check versions
-                if let Some(ref constraint) = dep.version_constraint {
-                    // Find the providing package and check its version
+                // If version constraint specified, the provided version must match it
+                if dep.version_constraint.is_some() {
                     for provider_name in providers {
                         if let Some(pkgs) = self.packages.get(provider_name) {
-                            for pkg in pkgs {
-                                if Self::version_matches(&pkg.version, constraint) {
-                                    return true;
-                                }
+                            if pkgs.iter().any(|pkg| pkg.satisfies(dep)) {
+                                return true;
                             }
                         }
                     }
@@ -680,11 +973,11 @@ impl DependencySolver {
         
         // Check if dependency is satisfied by a system package (not managed by apt-ng)
         // This handles cases where packages are installed via apt/dpkg but not tracked by apt-ng
-        if Self::is_package_installed_on_system(&dep.name) {
+        if self.is_package_installed_on_system(&dep.name) {
             // Check version constraint if specified
             if let Some(ref constraint) = dep.version_constraint {
-                if let Some(installed_version) = Self::get_system_package_version(&dep.name) {
-                    if !Self::version_matches(&installed_version, constraint) {
+                if let Some(installed_version) = self.get_system_package_version(&dep.name) {
+                    if !Self::version_matches(installed_version, constraint) {
                         return false; // Version constraint not satisfied
                     }
                 }
@@ -693,14 +986,136 @@ impl DependencySolver {
         }
         
         // Check if any system package provides this dependency
-        if Self::is_dependency_provided_by_system(&dep.name) {
+        if self.is_dependency_provided_by_system(&dep.name) {
             return true;
         }
         
         false
     }
     
-    #[allow(dead_code)]
+    /// Löst eine einzelne Abhängigkeit auf
+    ///
+    /// `Ok(None)`, wenn sie bereits durch ein installiertes oder ein System-Paket erfüllt ist,
+    /// sonst das zu installierende Paket. Greift auf keinen veränderlichen Zustand zu und kann
+    /// daher vom parallelen Solver ohne Sperren aufgerufen werden.
+    fn resolve_dependency(&self, dep: &DependencyRule) -> Result<Option<&PackageInfo>> {
+        // Check if dependency is already satisfied by an installed package
+        if self.is_dependency_satisfied_by_installed(dep) {
+            return Ok(None);
+        }
+        
+        // Try to find package by name
+        if let Some(packages) = self.packages.get(&dep.name) {
+            let dep_pkg = self.select_best_version(packages, &PackageSpec {
+                name: dep.name.clone(),
+                version: dep.version_constraint.clone(),
+                arch: dep.arch.clone(),
+            })?;
+            return Ok(Some(dep_pkg));
+        }
+        
+        // Check if any package provides this dependency
+        // Versionierte Abhängigkeiten werden nur von versionierten Provides erfüllt
+        for pkgs in self.packages.values() {
+            if let Some(provider) = pkgs.iter().find(|candidate| candidate.satisfies(dep)) {
+                tracing::debug!(dependency = %dep.name, provider = %provider.name, version = %provider.version, "dependency provided");
+                return Ok(Some(provider));
+            }
+        }
+        
+        // Last resort: check if dependency is satisfied by a system package
+        // This handles cases where packages are installed via apt/dpkg but not tracked by apt-ng
+        if self.is_package_installed_on_system(&dep.name) || 
+           self.is_dependency_provided_by_system(&dep.name) {
+            return Ok(None);
+        }
+        
+        // Try to find similar package names that might satisfy this dependency
+        // This handles transitional packages (e.g., libqt5core5t64 -> libqt5core5a)
+        // Simple approach: find packages that start with a common prefix
+        // For "libqt5core5t64", look for packages starting with "libqt5core5"
+        let mut similar_packages: Vec<(&String, &PackageInfo)> = Vec::new();
+        
+        // Try different base name extraction strategies
+        let mut bases = Vec::new();
+        
+        // Strategy 1: Remove trailing alphanumeric: "libqt5core5t64" -> "libqt5core5"
+        bases.push(dep.name.trim_end_matches(|c: char| c.is_ascii_alphanumeric() && c != '5'));
+        
+        // Strategy 2: Remove trailing digits and letters: "libqt5core5t64" -> "libqt5core5"
+        bases.push(dep.name.trim_end_matches(|c: char| c.is_ascii_alphabetic()));
+        
+        // Strategy 3: Use first part before last digit sequence
+        let mut base_str = dep.name.clone();
+        while base_str.len() > 5 && base_str.chars().last().map(|c| c.is_ascii_alphanumeric()).unwrap_or(false) {
+            base_str.pop();
+        }
+        bases.push(&base_str);
+        
+        for dep_base in bases {
+            if dep_base.len() < 5 {
+                continue; // Skip too short bases
+            }
+            
+            // Look for packages that start with the base name
+            for (pkg_name, pkgs) in &self.packages {
+                if pkg_name.starts_with(dep_base) && *pkg_name != dep.name {
+                    if let Some(pkg) = pkgs.first() {
+                        similar_packages.push((pkg_name, pkg));
+                    }
+                }
+            }
+            
+            if !similar_packages.is_empty() {
+                break; // Found similar packages, stop searching
+            }
+        }
+        
+        // If we found similar packages, try to use the first one
+        if let Some((_similar_name, similar_pkg)) = similar_packages.first() {
+            // Check version constraint if specified
+            let version_ok = match dep.version_constraint {
+                Some(ref constraint) => Self::version_matches(&similar_pkg.version, constraint),
+                None => true,
+            };
+            
+            if version_ok {
+                // Use the similar package as a substitute
+                return Ok(Some(*similar_pkg));
+            }
+        }
+        
+        // Try to find packages that provide this dependency for better error message
+        let mut providers = Vec::new();
+        let mut installed_providers = Vec::new();
+        
+        for (pkg_name, pkgs) in &self.packages {
+            for pkg in pkgs {
+                if pkg.provided_names().any(|p| p == dep.name) || pkg.name == dep.name {
+                    if self.installed_packages.contains(pkg_name) {
+                        installed_providers.push(format!("{} (installed)", pkg_name));
+                    } else {
+                        providers.push(pkg_name.clone());
+                    }
+                    break;
+                }
+            }
+        }
+        
+        let mut hint = String::new();
+        if !installed_providers.is_empty() {
+            hint.push_str(&format!(" (installed providers: {})", installed_providers.join(", ")));
+        }
+        if !providers.is_empty() {
+            hint.push_str(&format!(" (available providers: {})", providers.join(", ")));
+        }
+        if !similar_packages.is_empty() {
+            hint.push_str(&format!(" (similar packages found: {})", similar_packages.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>().join(", ")));
+        }
+        
+        Err(AptNgError::UnresolvedDependency { dependency: dep.name.clone(), hint })
+    }
+    
     fn resolve_dependencies(
         &self,
         pkg: &PackageInfo,
@@ -715,152 +1130,14 @@ impl DependencySolver {
         visited.insert(pkg.name.clone());
         
         // Prüfe Konflikte
-        for conflict in &pkg.conflicts {
-            if to_install.iter().any(|p| p.name == *conflict) {
-                conflicts.push(format!("{} conflicts with {}", pkg.name, conflict));
-            }
+        for other in to_install.iter().filter(|other| pkg.conflicts_with(other)) {
+            conflicts.push(format!("{} conflicts with {}", pkg.name, other.name));
         }
         
         // Löse Abhängigkeiten
         for dep in &pkg.depends {
-            // Check if dependency is already satisfied by an installed package
-            if self.is_dependency_satisfied_by_installed(dep) {
-                continue; // Skip this dependency, it's already satisfied
-            }
-            
-            // Try to find package by name
-            if let Some(packages) = self.packages.get(&dep.name) {
-                let dep_pkg = self.select_best_version(packages, &PackageSpec {
-                    name: dep.name.clone(),
-                    version: dep.version_constraint.clone(),
-                    arch: dep.arch.clone(),
-                })?;
-                
+            if let Some(dep_pkg) = self.resolve_dependency(dep)? {
                 self.resolve_dependencies(dep_pkg, to_install, visited, conflicts)?;
-            } else {
-                // Check if any package provides this dependency
-                // In Debian, every package implicitly provides its own name
-                let mut found = false;
-                for (_, pkgs) in &self.packages {
-                    for pkg_candidate in pkgs {
-                        // Check if package name matches dependency (implicit provide)
-                        let provides_dep = pkg_candidate.name == dep.name || 
-                                          pkg_candidate.provides.contains(&dep.name);
-                        
-                        if provides_dep {
-                            // Check version constraint if specified
-                            if let Some(ref constraint) = dep.version_constraint {
-                                if !Self::version_matches(&pkg_candidate.version, constraint) {
-                                    continue;
-                                }
-                            }
-                            self.resolve_dependencies(pkg_candidate, to_install, visited, conflicts)?;
-                            found = true;
-                            break;
-                        }
-                    }
-                    if found {
-                        break;
-                    }
-                }
-                
-                if !found {
-                    // Last resort: check if dependency is satisfied by a system package
-                    // This handles cases where packages are installed via apt/dpkg but not tracked by apt-ng
-                    if Self::is_package_installed_on_system(&dep.name) || 
-                       Self::is_dependency_provided_by_system(&dep.name) {
-                        // Dependency is satisfied by system package, skip it
-                        continue;
-                    }
-                    
-                    // Try to find similar package names that might satisfy this dependency
-                    // This handles transitional packages (e.g., libqt5core5t64 -> libqt5core5a)
-                    // Simple approach: find packages that start with a common prefix
-                    // For "libqt5core5t64", look for packages starting with "libqt5core5"
-                    let mut similar_packages = Vec::new();
-                    
-                    // Try different base name extraction strategies
-                    let mut bases = Vec::new();
-                    
-                    // Strategy 1: Remove trailing alphanumeric: "libqt5core5t64" -> "libqt5core5"
-                    bases.push(dep.name.trim_end_matches(|c: char| c.is_ascii_alphanumeric() && c != '5'));
-                    
-                    // Strategy 2: Remove trailing digits and letters: "libqt5core5t64" -> "libqt5core5"
-                    bases.push(dep.name.trim_end_matches(|c: char| c.is_ascii_alphabetic()));
-                    
-                    // Strategy 3: Use first part before last digit sequence
-                    let mut base_str = dep.name.clone();
-                    while base_str.len() > 5 && base_str.chars().last().map(|c| c.is_ascii_alphanumeric()).unwrap_or(false) {
-                        base_str.pop();
-                    }
-                    bases.push(&base_str);
-                    
-                    for dep_base in bases {
-                        if dep_base.len() < 5 {
-                            continue; // Skip too short bases
-                        }
-                        
-                        // Look for packages that start with the base name
-                        for (pkg_name, pkgs) in &self.packages {
-                            if pkg_name.starts_with(dep_base) && *pkg_name != dep.name {
-                                for pkg in pkgs {
-                                    similar_packages.push((pkg_name.clone(), pkg.clone()));
-                                    break;
-                                }
-                            }
-                        }
-                        
-                        if !similar_packages.is_empty() {
-                            break; // Found similar packages, stop searching
-                        }
-                    }
-                    
-                    // If we found similar packages, try to use the first one
-                    if !similar_packages.is_empty() {
-                        let (_similar_name, similar_pkg) = &similar_packages[0];
-                        // Check version constraint if specified
-                        let mut version_ok = true;
-                        if let Some(ref constraint) = dep.version_constraint {
-                            version_ok = Self::version_matches(&similar_pkg.version, constraint);
-                        }
-                        
-                        if version_ok {
-                            // Use the similar package as a substitute
-                            self.resolve_dependencies(similar_pkg, to_install, visited, conflicts)?;
-                            continue;
-                        }
-                    }
-                    
-                    // Try to find packages that provide this dependency for better error message
-                    let mut providers = Vec::new();
-                    let mut installed_providers = Vec::new();
-                    
-                    for (pkg_name, pkgs) in &self.packages {
-                        for pkg in pkgs {
-                            if pkg.provides.contains(&dep.name) || pkg.name == dep.name {
-                                if self.installed_packages.contains(pkg_name) {
-                                    installed_providers.push(format!("{} (installed)", pkg_name));
-                                } else {
-                                    providers.push(pkg_name.clone());
-                                }
-                                break;
-                            }
-                        }
-                    }
-                    
-                    let mut error_msg = format!("Dependency not found: {}", dep.name);
-                    if !installed_providers.is_empty() {
-                        error_msg.push_str(&format!(" (installed providers: {})", installed_providers.join(", ")));
-                    }
-                    if !providers.is_empty() {
-                        error_msg.push_str(&format!(" (available providers: {})", providers.join(", ")));
-                    }
-                    if !similar_packages.is_empty() {
-                        error_msg.push_str(&format!(" (similar packages found: {})", similar_packages.iter().map(|(n, _)| n.clone()).collect::<Vec<_>>().join(", ")));
-                    }
-                    
-                    return Err(anyhow::anyhow!(error_msg));
-                }
             }
         }
         
@@ -890,6 +1167,8 @@ mod tests {
             depends: vec![],
             conflicts: vec![],
             replaces: vec![],
+            suggests: vec![],
+            repo_id: None,
         };
         
         solver.add_package(pkg);
@@ -902,5 +1181,487 @@ mod tests {
         
         assert_eq!(solution.to_install.len(), 1);
     }
+    
+    #[test]
+    fn test_kept_back_reason() {
+        let pkg = |name: &str, version: &str, depends: &[&str], conflicts: &[&str]| PackageInfo {
+            name: name.to_string(),
+            version: version.to_string(),
+            arch: "amd64".to_string(),
+            provides: vec![],
+            depends: depends.iter().map(|d| DependencyRule {
+                name: d.to_string(),
+                version_constraint: None,
+                arch: None,
+            }).collect(),
+            conflicts: conflicts.iter().map(|c| DependencyRule {
+                name: c.to_string(),
+                version_constraint: None,
+                arch: None,
+            }).collect(),
+            replaces: vec![],
+            suggests: vec![],
+            repo_id: None,
+        };
+        let spec = |name: &str, version: &str| PackageSpec {
+            name: name.to_string(),
+            version: Some(version.to_string()),
+            arch: None,
+        };
+        
+        let mut solver = DependencySolver::new();
+        solver.add_package(pkg("editor", "2.0", &[], &[]));
+        solver.add_package(pkg("mailer", "2.0", &["libmail-new"], &[]));
+        solver.add_package(pkg("libmail-new", "1.0", &[], &["libmail-old"]));
+        solver.add_package(pkg("libmail-old", "1.0", &[], &[]));
+        solver.add_package(pkg("browser", "2.0", &["apt-ng-test-missing-dependency"], &[]));
+        solver.set_installed_packages(["libmail-old".to_string()].into_iter().collect());
+        
+        assert_eq!(solver.kept_back_reason(&spec("editor", "2.0")), None);
+        assert_eq!(
+            solver.kept_back_reason(&spec("mailer", "2.0")).as_deref(),
+            Some("upgrade would require removing libmail-old")
+        );
+        assert_eq!(
+            solver.kept_back_reason(&spec("browser", "2.0")).as_deref(),
+            Some("dependency apt-ng-test-missing-dependency cannot be satisfied")
+        );
+    }
+    
+    #[test]
+    fn test_versioned_provides_satisfy_versioned_depends() {
+        let packages = crate::apt_parser::parse_packages_file(
+            "Package: postfix\nVersion: 3.7.0\nArchitecture: amd64\nProvides: mta (= 1.0)\n\n\
+             Package: mutt\nVersion: 2.2.0\nArchitecture: amd64\nDepends: mta (>= 1.0)\n"
+        ).unwrap();
+        
+        let mut solver = DependencySolver::new();
+        for manifest in &packages {
+            solver.add_package(DependencySolver::manifest_to_package_info(manifest).unwrap());
+        }
+        
+        let solution = solver.solve(&[PackageSpec {
+            name: "mutt".to_string(),
+            version: None,
+            arch: None,
+        }]).unwrap();
+        
+        let names: Vec<&str> = solution.to_install.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["postfix", "mutt"]);
+        
+        let rule = |constraint: &str| DependencyRule {
+            name: "mta".to_string(),
+            version_constraint: Some(constraint.to_string()),
+            arch: None,
+        };
+        let postfix = &solver.packages["postfix"][0];
+        assert!(!postfix.satisfies(&rule(">= 2.0")));
+        
+        // Unversionierte Provides erfüllen keine versionierten Abhängigkeiten
+        let mut unversioned = postfix.clone();
+        unversioned.provides = vec![Provide::parse("mta")];
+        assert!(!unversioned.satisfies(&rule(">= 1.0")));
+    }
+    
+    #[test]
+    fn test_from_manifests_builds_reusable_solver() {
+        let content = "Package: app\nVersion: 2.0\nDepends: libapp (>= 1.0)\n\nPackage: libapp\nVersion: 1.5\n\n";
+        let manifests = crate::apt_parser::parse_packages_file(content).unwrap();
+        let (mut solver, skipped) = DependencySolver::from_manifests(&manifests);
+        assert!(skipped.is_empty());
+        
+        let requested = [PackageSpec { name: "app".to_string(), version: None, arch: None }];
+        let first = solver.solve(&requested).unwrap();
+        let names: Vec<&str> = first.to_install.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["libapp", "app"]);
+        
+        // Derselbe Solver bedient weitere Aufrufe, auch nach dem Markieren installierter Pakete
+        solver.set_installed_packages(["libapp".to_string()].into_iter().collect());
+        let second = solver.solve(&requested).unwrap();
+        assert_eq!(second.to_install.len(), 1);
+        assert_eq!(second.to_install[0].name, "app");
+    }
+    
+    #[test]
+    fn test_full_upgrade_removes_conflicting_packages() {
+        let manifests = crate::apt_parser::parse_packages_file(
+            "Package: app\nVersion: 1.0\nDepends: libfoo1\n\n\
+             Package: app\nVersion: 2.0\nDepends: libfoo2\n\n\
+             Package: libfoo1\nVersion: 1.0\n\n\
+             Package: libfoo2\nVersion: 2.0\nConflicts: libfoo1\n\n\
+             Package: plugin\nVersion: 1.0\nDepends: libfoo1\n\n\
+             Package: tool\nVersion: 1.0\n\n\
+             Package: tool\nVersion: 2.0\nBreaks: helper (<< 2.0), other (<< 2.0)\n\n\
+             Package: helper\nVersion: 1.0\n\n\
+             Package: other\nVersion: 2.5\n\n\
+             Package: legacy\nVersion: 1.0\nConflicts: app:any (>= 2.0)\n\n"
+        ).unwrap();
+        let (mut solver, skipped) = DependencySolver::from_manifests(&manifests);
+        assert!(skipped.is_empty());
+        solver.set_installed_versions(
+            [("app", "1.0"), ("libfoo1", "1.0"), ("plugin", "1.0"), ("tool", "1.0"),
+             ("helper", "1.0"), ("other", "2.5"), ("legacy", "1.0")]
+                .iter()
+                .map(|(name, version)| (name.to_string(), version.to_string()))
+                .collect()
+        );
+        
+        let spec = |name: &str| PackageSpec { name: name.to_string(), version: Some("2.0".to_string()), arch: None };
+        let solution = solver.solve(&[spec("app"), spec("tool")]).unwrap();
+        
+        // libfoo1 per Conflicts, plugin als Folge, helper per versioniertem Breaks,
+        // legacy per Conflicts in Gegenrichtung; other ist bereits neu genug
+        assert_eq!(solution.to_remove, ["helper", "legacy", "libfoo1", "plugin"]);
+        let names = |pkgs: &[PackageInfo]| pkgs.iter().map(|pkg| pkg.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&solution.to_install), ["libfoo2"]);
+        assert_eq!(names(&solution.to_upgrade), ["app", "tool"]);
+        
+        // Das normale Upgrade hält app dagegen zurück
+        assert!(solver.kept_back_reason(&spec("app")).is_some());
+        assert_eq!(solver.unresolvable_reason(&spec("app")), None);
+    }
+    
+    #[test]
+    fn test_full_upgrade_removes_obsolete_replaced_packages() {
+        let manifests = crate::apt_parser::parse_packages_file(
+            "Package: app\nVersion: 1.0\nDepends: libfoo1\n\n\
+             Package: app\nVersion: 2.0\nDepends: libfoo2\n\n\
+             Package: libfoo1\nVersion: 1.0\n\n\
+             Package: libfoo2\nVersion: 2.0\nReplaces: libfoo1 (<< 2.0), libbar1\n\n\
+             Package: libbar1\nVersion: 1.0\n\n"
+        ).unwrap();
+        let (mut solver, _) = DependencySolver::from_manifests(&manifests);
+        solver.set_installed_versions(
+            [("app", "1.0"), ("libfoo1", "1.0"), ("libbar1", "1.0")]
+                .iter()
+                .map(|(name, version)| (name.to_string(), version.to_string()))
+                .collect()
+        );
+        let spec = PackageSpec { name: "app".to_string(), version: Some("2.0".to_string()), arch: None };
+        
+        // Ohne Angabe veralteter Pakete bleibt alles installiert
+        assert!(solver.solve(std::slice::from_ref(&spec)).unwrap().to_remove.is_empty());
+        
+        // Nur das upstream verschwundene libfoo1 wird durch libfoo2 abgelöst
+        solver.set_obsolete_packages(["libfoo1".to_string()].into_iter().collect());
+        assert_eq!(solver.solve(std::slice::from_ref(&spec)).unwrap().to_remove, ["libfoo1"]);
+    }
+    
+    #[test]
+    fn test_unavailable_versions_are_not_selected() {
+        let manifests = crate::apt_parser::parse_packages_file(
+            "Package: tool\nVersion: 1.0\n\n\
+             Package: tool\nVersion: 2.0\n\n"
+        ).unwrap();
+        let (mut solver, _) = DependencySolver::from_manifests(&manifests);
+        solver.set_unavailable_versions([("tool".to_string(), "1.0".to_string())].into_iter().collect());
+        let pinned = PackageSpec { name: "tool".to_string(), version: Some("1.0".to_string()), arch: None };
+        let latest = PackageSpec { name: "tool".to_string(), version: None, arch: None };
+        
+        assert!(matches!(solver.candidate(&pinned), Err(AptNgError::NoMatchingVersion { .. })));
+        assert!(solver.solve(std::slice::from_ref(&pinned)).is_err());
+        assert_eq!(solver.candidate(&latest).unwrap().version, "2.0");
+        
+        // Die installierte Version bleibt wählbar
+        solver.set_installed_versions([("tool".to_string(), "1.0".to_string())].into_iter().collect());
+        assert_eq!(solver.candidate(&pinned).unwrap().version, "1.0");
+    }
+    
+    #[test]
+    fn test_install_order_spans_installs_and_upgrades() {
+        let manifests = crate::apt_parser::parse_packages_file(
+            "Package: app\nVersion: 1.0\n\n\
+             Package: app\nVersion: 2.0\n\n\
+             Package: plugin\nVersion: 1.0\nDepends: app\n\n"
+        ).unwrap();
+        let (mut solver, _) = DependencySolver::from_manifests(&manifests);
+        solver.set_installed_versions([("app".to_string(), "1.0".to_string())].into_iter().collect());
+        
+        let requested = [
+            PackageSpec { name: "plugin".to_string(), version: None, arch: None },
+            PackageSpec { name: "app".to_string(), version: Some("2.0".to_string()), arch: None },
+        ];
+        for jobs in [1, 4] {
+            let solution = solver.solve_with_jobs(&requested, jobs).unwrap();
+            // Das Upgrade von app kommt vor der Neuinstallation von plugin, die es braucht
+            let order: Vec<&str> = solution.install_order().iter().map(|pkg| pkg.name.as_str()).collect();
+            assert_eq!(order, ["app", "plugin"]);
+        }
+    }
+    
+    #[test]
+    fn test_solution_categories() {
+        let manifests = crate::apt_parser::parse_packages_file(
+            "Package: app\nVersion: 1.0\n\n\
+             Package: app\nVersion: 2.0\nDepends: newlib, tool\n\n\
+             Package: newlib\nVersion: 1.0\nConflicts: libold\n\n\
+             Package: libold\nVersion: 1.0\n\n\
+             Package: util\nVersion: 1.0\n\n\
+             Package: util\nVersion: 2.0\n\n\
+             Package: tool\nVersion: 1.0\n\n"
+        ).unwrap();
+        let (mut solver, _) = DependencySolver::from_manifests(&manifests);
+        solver.set_installed_versions(
+            [("app", "1.0"), ("libold", "1.0"), ("util", "1.0"), ("tool", "1.0")]
+                .iter()
+                .map(|(name, version)| (name.to_string(), version.to_string()))
+                .collect()
+        );
+        
+        let spec = |name: &str| PackageSpec { name: name.to_string(), version: None, arch: None };
+        let names = |pkgs: &[PackageInfo]| pkgs.iter().map(|pkg| pkg.name.clone()).collect::<Vec<_>>();
+        for jobs in [1, 4] {
+            let solution = solver.solve_with_jobs(&[spec("app"), spec("util"), spec("tool")], jobs).unwrap();
+            // tool ist bereits in dieser Version installiert und taucht nicht auf
+            assert_eq!(names(&solution.to_install), ["newlib"]);
+            assert_eq!(names(&solution.to_upgrade), ["app", "util"]);
+            assert_eq!(solution.to_remove, ["libold"]);
+        }
+        
+        // Die Hülle enthält auch die übersprungene, bereits installierte Abhängigkeit tool
+        let solution = solver.solve(&[spec("app")]).unwrap();
+        assert_eq!(names(&solution.to_install), ["newlib"]);
+        assert_eq!(names(&solution.closure), ["newlib", "tool", "app"]);
+        
+        // Ohne bekannte Versionen bleibt alles in to_install
+        solver.set_installed_packages(HashSet::new());
+        let solution = solver.solve(&[spec("util")]).unwrap();
+        assert_eq!(names(&solution.to_install), ["util"]);
+        assert!(solution.to_upgrade.is_empty() && solution.to_remove.is_empty());
+    }
+    
+    #[test]
+    fn test_parallel_solver_wide_fan_out() {
+        // root -> 24 mid-Pakete -> jeweils alle gemeinsamen Blätter und eine Kette mit Zyklus
+        let width = 24;
+        let mut content = format!(
+            "Package: root\nVersion: 1.0\nDepends: {}\n\n",
+            (0..width).map(|i| format!("mid-{}", i)).collect::<Vec<_>>().join(", ")
+        );
+        for i in 0..width {
+            content.push_str(&format!(
+                "Package: mid-{}\nVersion: 1.0\nDepends: leaf-0, leaf-1, leaf-2, leaf-3, chain-0\n\n", i
+            ));
+        }
+        for i in 0..4 {
+            content.push_str(&format!("Package: leaf-{}\nVersion: 1.0\n\n", i));
+        }
+        for i in 0..3 {
+            content.push_str(&format!("Package: chain-{}\nVersion: 1.0\nDepends: chain-{}\n\n", i, (i + 1) % 3));
+        }
+        
+        let mut solver = DependencySolver::new();
+        for manifest in crate::apt_parser::parse_packages_file(&content).unwrap() {
+            solver.add_package(DependencySolver::manifest_to_package_info(&manifest).unwrap());
+        }
+        let requested = [PackageSpec { name: "root".to_string(), version: None, arch: None }];
+        let solution = solver.solve_with_jobs(&requested, 8).unwrap();
+        
+        let names: HashSet<&str> = solution.to_install.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(solution.to_install.len(), 1 + width + 4 + 3);
+        assert_eq!(names.len(), solution.to_install.len());
+        assert_eq!(solution.to_install.last().unwrap().name, "root");
+        
+        // Abhängigkeiten stehen vor den Paketen, die sie brauchen (Zyklus ausgenommen)
+        let position: HashMap<&str, usize> = solution.to_install.iter()
+            .enumerate()
+            .map(|(i, p)| (p.name.as_str(), i))
+            .collect();
+        for pkg in solution.to_install.iter().filter(|p| !p.name.starts_with("chain-")) {
+            for dep in &pkg.depends {
+                assert!(position[dep.name.as_str()] < position[pkg.name.as_str()], "{} before {}", dep.name, pkg.name);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_requested_virtual_package() {
+        let manifests = crate::apt_parser::parse_packages_file(
+            "Package: postfix\nVersion: 3.7\nProvides: mail-transport-agent\n\n\
+             Package: exim4-daemon-light\nVersion: 4.96\nProvides: mail-transport-agent\n\n\
+             Package: mailx\nVersion: 8.1\nProvides: mail-reader\n\n"
+        ).unwrap();
+        let (mut solver, _) = DependencySolver::from_manifests(&manifests);
+        let spec = |name: &str| PackageSpec { name: name.to_string(), version: None, arch: None };
+        let names = |pkgs: &[PackageInfo]| pkgs.iter().map(|pkg| pkg.name.clone()).collect::<Vec<_>>();
+        
+        // Mehrere Anbieter: der Aufrufer muss wählen
+        for jobs in [1, 4] {
+            match solver.solve_with_jobs(&[spec("mail-transport-agent")], jobs) {
+                Err(AptNgError::VirtualPackage { name, providers }) => {
+                    assert_eq!(name, "mail-transport-agent");
+                    assert_eq!(providers, ["exim4-daemon-light", "postfix"]);
+                }
+                other => panic!("expected VirtualPackage, got {:?}", other),
+            }
+        }
+        
+        // Ein einziger Anbieter wird direkt gewählt
+        assert_eq!(names(&solver.solve(&[spec("mail-reader")]).unwrap().to_install), ["mailx"]);
+        
+        // Ist ein Anbieter bereits installiert, bleibt es bei ihm
+        solver.set_installed_versions([("postfix".to_string(), "3.0".to_string())].into_iter().collect());
+        assert_eq!(names(&solver.solve(&[spec("mail-transport-agent")]).unwrap().to_upgrade), ["postfix"]);
+    }
+    
+    #[test]
+    fn test_provider_matches_the_provided_version() {
+        let manifests = crate::apt_parser::parse_packages_file(
+            "Package: mawk\nVersion: 1.3\nProvides: awk (= 2.0)\n\n\
+             Package: mawk\nVersion: 1.4\nProvides: awk (= 1.0)\n\n\
+             Package: gawk\nVersion: 5.2\nProvides: awk (= 2.0)\n\n"
+        ).unwrap();
+        let (solver, _) = DependencySolver::from_manifests(&manifests);
+        let awk = PackageSpec { name: "awk".to_string(), version: Some(">= 2.0".to_string()), arch: None };
+        let versions = |pkgs: Vec<&PackageInfo>| pkgs.iter().map(|pkg| format!("{} {}", pkg.name, pkg.version)).collect::<Vec<_>>();
+        
+        // Maßgeblich ist die Version im Provides, nicht die des Pakets
+        assert_eq!(versions(solver.providers_of(&awk)), ["gawk 5.2", "mawk 1.3"]);
+        let exact = PackageSpec { version: Some("1.0".to_string()), ..awk.clone() };
+        assert_eq!(versions(solver.providers_of(&exact)), ["mawk 1.4"]);
+        
+        // Der gewählte Anbieter bleibt bei der Fassung, deren Provides passt
+        let pinned = solver.provider_spec(&awk, "mawk");
+        assert_eq!(pinned.version.as_deref(), Some("=1.3"));
+        assert_eq!(solver.candidate(&pinned).unwrap().version, "1.3");
+        let any = PackageSpec { version: None, ..awk.clone() };
+        assert_eq!(solver.provider_spec(&any, "mawk").version, None);
+    }
+    
+    #[test]
+    fn test_target_release() {
+        // Dasselbe Paket in stable (Repo 1) und testing (Repo 2)
+        let mut manifests = crate::apt_parser::parse_packages_file(
+            "Package: libfoo\nVersion: 1.0\n\n\
+             Package: foo\nVersion: 1.0\nDepends: libfoo\n\n\
+             Package: libfoo\nVersion: 2.0\n\n\
+             Package: foo\nVersion: 2.0\nDepends: libfoo\n\n"
+        ).unwrap();
+        for (i, manifest) in manifests.iter_mut().enumerate() {
+            manifest.repo_id = Some(if i < 2 { 1 } else { 2 });
+        }
+        let (mut solver, _) = DependencySolver::from_manifests(&manifests);
+        let spec = PackageSpec { name: "foo".to_string(), version: None, arch: None };
+        let versions = |solution: Solution| solution.to_install.iter()
+            .chain(&solution.to_upgrade)
+            .map(|pkg| format!("{} {}", pkg.name, pkg.version))
+            .collect::<Vec<_>>();
+        
+        // Ohne Ziel gewinnt die neueste Version
+        assert_eq!(versions(solver.solve(std::slice::from_ref(&spec)).unwrap()), ["libfoo 2.0", "foo 2.0"]);
+        
+        // Mit -t stable auch für die Abhängigkeiten die Version aus stable
+        solver.set_target_repos([1].into_iter().collect());
+        for jobs in [1, 4] {
+            assert_eq!(versions(solver.solve_with_jobs(std::slice::from_ref(&spec), jobs).unwrap()), ["libfoo 1.0", "foo 1.0"]);
+        }
+        
+        // Eine bereits neuere installierte Version wird nicht zurückgestuft
+        solver.set_installed_versions([("foo".to_string(), "2.0".to_string())].into_iter().collect());
+        assert_eq!(versions(solver.solve(std::slice::from_ref(&spec)).unwrap()), ["libfoo 1.0"]);
+    }
+    
+    #[test]
+    fn test_system_packages_from_snapshot() {
+        let manifests = crate::apt_parser::parse_packages_file(
+            "Package: app\nVersion: 1.0\nDepends: libc6 (>= 2.30), awk\n\n"
+        ).unwrap();
+        let spec = PackageSpec { name: "app".to_string(), version: None, arch: None };
+        let system = crate::dpkg::parse_status(
+            "Package: libc6\nStatus: install ok installed\nVersion: 2.36-9\n\n\
+             Package: mawk\nStatus: install ok installed\nVersion: 1.3.4\nProvides: awk\n"
+        );
+        
+        // Systempakete und ihre Provides erfüllen Abhängigkeiten ohne eigenen Kandidaten
+        let (mut solver, _) = DependencySolver::from_manifests(&manifests);
+        solver.set_system_snapshot(crate::dpkg::SystemSnapshot::from_packages(system));
+        let solution = solver.solve(std::slice::from_ref(&spec)).unwrap();
+        assert_eq!(solution.to_install.iter().map(|pkg| pkg.name.as_str()).collect::<Vec<_>>(), ["app"]);
+        
+        let (mut solver, _) = DependencySolver::from_manifests(&manifests);
+        solver.set_system_snapshot(crate::dpkg::SystemSnapshot::default());
+        assert!(solver.solve(std::slice::from_ref(&spec)).is_err());
+    }
+    
+    #[test]
+    fn test_is_major_change() {
+        assert!(DependencySolver::is_major_change("1.9.2-1", "2.0.0-1"));
+        assert!(DependencySolver::is_major_change("2.1", "1:2.1"));
+        assert!(DependencySolver::is_major_change("v10.4", "v11.0"));
+        assert!(!DependencySolver::is_major_change("2.36-9", "2.36-9+deb12u4"));
+        assert!(!DependencySolver::is_major_change("1:9.0.1", "1:9.2"));
+    }
+    
+    #[test]
+    fn test_install_suggests() {
+        let manifests = crate::apt_parser::parse_packages_file(
+            "Package: nginx\nVersion: 1.22\nSuggests: fcgiwrap | spawn-fcgi, nginx-doc, missing-extra\n\n\
+             Package: fcgiwrap\nVersion: 1.1\nDepends: libfcgi0\n\n\
+             Package: spawn-fcgi\nVersion: 1.6\n\n\
+             Package: libfcgi0\nVersion: 2.4\n\n\
+             Package: nginx-doc\nVersion: 1.22\nConflicts: nginx\n\n"
+        ).unwrap();
+        let requested = [PackageSpec { name: "nginx".to_string(), version: None, arch: None }];
+        let names = |solution: &Solution| solution.to_install.iter().map(|pkg| pkg.name.clone()).collect::<Vec<_>>();
+        
+        let (mut solver, _) = DependencySolver::from_manifests(&manifests);
+        assert_eq!(names(&solver.solve(&requested).unwrap()), ["nginx"]);
+        
+        // Erste installierbare Alternative samt Abhängigkeiten; Konflikte und fehlende
+        // Pakete werden übersprungen statt die Auflösung scheitern zu lassen
+        solver.set_install_suggests(true);
+        assert_eq!(names(&solver.solve(&requested).unwrap()), ["nginx", "libfcgi0", "fcgiwrap"]);
+        assert_eq!(names(&solver.solve_with_jobs(&requested, 4).unwrap()), ["nginx", "libfcgi0", "fcgiwrap"]);
+    }
+    
+    #[test]
+    fn test_compare_versions_debian_policy() {
+        use std::cmp::Ordering::{Equal, Greater, Less};
+        
+        let cases = [
+            ("1:1.0", "2.0", Greater),
+            ("0:2.0", "2.0", Equal),
+            ("1.0~rc1", "1.0", Less),
+            ("1.0~~", "1.0~", Less),
+            ("1.0~rc1-1", "1.0-1", Less),
+            ("1.0a", "1.0", Greater),
+            ("1.0a", "1.0+", Less),
+            ("1.2.10", "1.2.9", Greater),
+            ("2.36-9+deb12u4", "2.36-9", Greater),
+            ("1.0-1", "1.0-1ubuntu1", Less),
+            ("20240101120000", "20231231235959", Greater),
+            ("1.001", "1.1", Equal),
+            ("1.0", "1.0-0", Equal),
+            ("2:1.0-1", "1:9.9-9", Greater),
+        ];
+        for (a, b, expected) in cases {
+            assert_eq!(DependencySolver::compare_versions(a, b), expected, "{} vs {}", a, b);
+            assert_eq!(DependencySolver::compare_versions(b, a), expected.reverse(), "{} vs {}", b, a);
+        }
+    }
+    
+    #[test]
+    fn test_version_matches_constraints() {
+        let matches = DependencySolver::version_matches;
+        
+        assert!(matches("1:2.0", ">= 1:2.0"));
+        assert!(!matches("2.5", ">= 1:2.0"));
+        assert!(matches("1.0~beta", "<< 1.0"));
+        assert!(!matches("1.0", "<< 1.0~"));
+        assert!(matches("1.0~rc1", "<< 1.0~rc2"));
+        assert!(matches("2.0a", ">> 2.0"));
+        assert!(!matches("2.0", ">> 2.0"));
+        assert!(matches("2.0", "<= 2.0"));
+        assert!(!matches("2.0.1", "<= 2.0"));
+        assert!(matches("1.2-3", "= 1.2-3"));
+        assert!(!matches("1.2-3", "= 1.2-4"));
+        assert!(matches("1.2-3", "1.2-3"));
+        
+        // Ohne Leerzeichen und mit den veralteten, nicht strikten Operatoren
+        assert!(matches("1.0", ">=1.0"));
+        assert!(matches("1.1", ">>1.0"));
+        assert!(matches("1.0", "  <<2.0 "));
+        assert!(matches("1.0", "> 1.0"));
+        assert!(matches("1.0", "< 1.0"));
+    }
 }
-
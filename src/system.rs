@@ -1,77 +1,194 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::fs;
 use std::path::Path;
 
+/// Erkannte Distribution, bestimmt Suite und Archiv-Konventionen beim `update`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Distro {
+    /// `ID` aus /etc/os-release, z.B. `debian` oder `ubuntu`
+    pub id: String,
+    /// Suite der Distribution (`VERSION_CODENAME`), z.B. `bookworm` oder `jammy`
+    pub codename: String,
+    /// Ubuntu-Codename für Ubuntu-Archive und PPAs, auch auf Derivaten wie Linux Mint
+    pub ubuntu_codename: Option<String>,
+}
+
+impl Distro {
+    /// Ubuntu oder ein Derivat: Ubuntu-Konventionen (kein `stable`, `<codename>-security`)
+    pub fn is_ubuntu(&self) -> bool {
+        self.ubuntu_codename.is_some()
+    }
+}
+
+/// Erkennt Distribution und Suite
+///
+/// Primäre Quelle ist /etc/os-release; fehlt dort der Codename (z.B. Debian sid), folgen
+/// /etc/debian_version und die erste Zeile aus /etc/apt/sources.list, zuletzt `stable`.
+pub fn detect_distro() -> Distro {
+    let mut distro = parse_os_release(&fs::read_to_string("/etc/os-release").unwrap_or_default());
+    if distro.codename.is_empty() {
+        distro.codename = fs::read_to_string("/etc/debian_version").ok()
+            .and_then(|content| codename_from_debian_version(&content))
+            .or_else(suite_from_sources_list)
+            .unwrap_or_else(|| "stable".to_string());
+    }
+    distro
+}
+
 /// Erkennt die Debian-Version und gibt den Suite-Namen zurück
 pub fn detect_debian_suite() -> Result<String> {
-    // Versuche zuerst /etc/os-release
-    let os_release = Path::new("/etc/os-release");
-    if os_release.exists() {
-        if let Ok(content) = fs::read_to_string(os_release) {
-            for line in content.lines() {
-                if line.starts_with("VERSION_CODENAME=") {
-                    let suite = line.split('=').nth(1)
-                        .map(|s| s.trim_matches('"').trim().to_string())
-                        .unwrap_or_else(|| "stable".to_string());
-                    return Ok(suite);
+    Ok(detect_distro().codename)
+}
+
+/// Parst eine os-release-Datei; der Codename bleibt leer, wenn sie keinen nennt
+///
+/// Auf Derivaten wie Linux Mint ist `VERSION_CODENAME` der eigene Name; die passende
+/// Ubuntu-Suite steht dann in `UBUNTU_CODENAME`.
+pub fn parse_os_release(content: &str) -> Distro {
+    let field = |key: &str| content.lines()
+        .find_map(|line| line.trim().strip_prefix(key)?.strip_prefix('='))
+        .map(|value| value.trim().trim_matches(|c| c == '"' || c == '\'').to_string())
+        .filter(|value| !value.is_empty());
+    
+    // Ohne ID gilt laut os-release(5) "linux"
+    let id = field("ID").unwrap_or_else(|| "linux".to_string());
+    let codename = field("VERSION_CODENAME").unwrap_or_default();
+    let ubuntu_like = id == "ubuntu"
+        || field("ID_LIKE").is_some_and(|like| like.split_whitespace().any(|id| id == "ubuntu"));
+    let ubuntu_codename = field("UBUNTU_CODENAME")
+        .or_else(|| Some(codename.clone()).filter(|codename| ubuntu_like && !codename.is_empty()));
+    
+    Distro { id, codename, ubuntu_codename }
+}
+
+/// Codename aus /etc/debian_version (`12.5` → `bookworm`, `trixie/sid` → `trixie`)
+pub fn codename_from_debian_version(content: &str) -> Option<String> {
+    let version = content.trim();
+    // testing/unstable tragen den Codename des kommenden Releases
+    if let Some((codename, _)) = version.split_once('/') {
+        return Some(codename.to_string()).filter(|codename| !codename.is_empty());
+    }
+    let codename = match version.split('.').next()? {
+        "13" => "trixie",
+        "12" => "bookworm",
+        "11" => "bullseye",
+        "10" => "buster",
+        "9" => "stretch",
+        _ => return None,
+    };
+    Some(codename.to_string())
+}
+
+/// Suite der ersten `deb`-Zeile aus /etc/apt/sources.list
+fn suite_from_sources_list() -> Option<String> {
+    let content = fs::read_to_string(Path::new("/etc/apt/sources.list")).ok()?;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        
+        // Parse erste deb-Zeile
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() >= 3 && (parts[0] == "deb" || parts[0] == "deb-src") {
+            let mut idx = 1;
+            // Überspringe [options]
+            if parts[idx].starts_with('[') {
+                while idx < parts.len() && !parts[idx].ends_with(']') {
+                    idx += 1;
                 }
+                idx += 1;
+            }
+            // Suite ist der Teil nach der URL
+            if let Some(suite) = parts.get(idx + 1) {
+                return Some(suite.to_string());
             }
         }
     }
-    
-    // Versuche /etc/debian_version
-    let debian_version = Path::new("/etc/debian_version");
-    if debian_version.exists() {
-        if let Ok(content) = fs::read_to_string(debian_version) {
-            let version = content.trim();
-            // Mappe bekannte Versionen zu Suite-Namen
-            let suite = match version {
-                v if v.starts_with("bookworm") => "bookworm",
-                v if v.starts_with("bullseye") => "bullseye",
-                v if v.starts_with("buster") => "buster",
-                v if v.starts_with("stretch") => "stretch",
-                v if v.starts_with("12") => "bookworm",
-                v if v.starts_with("11") => "bullseye",
-                v if v.starts_with("10") => "buster",
-                v if v.starts_with("9") => "stretch",
-                _ => "stable",
-            };
-            return Ok(suite.to_string());
-        }
+    None
+}
+
+/// Hardware-Name der Maschine laut `uname -m`, z.B. `x86_64` oder `aarch64`
+pub fn machine_name() -> Result<String> {
+    let output = std::process::Command::new("uname")
+        .arg("-m")
+        .output()
+        .context("Failed to execute uname")?;
+    Ok(String::from_utf8(output.stdout)
+        .context("Invalid UTF-8 in uname output")?
+        .trim()
+        .to_string())
+}
+
+/// Mappt die Ausgabe von `uname -m` auf den Debian-Architekturnamen
+pub fn debian_arch_from_uname(machine: &str) -> Option<&'static str> {
+    let arch = match machine {
+        "x86_64" | "amd64" => "amd64",
+        "aarch64" | "arm64" => "arm64",
+        "armv7l" | "armv7" | "armv8l" => "armhf",
+        "armv6l" | "armv5tel" | "armv5tejl" => "armel",
+        "i386" | "i486" | "i586" | "i686" => "i386",
+        "ppc64le" => "ppc64el",
+        "ppc64" => "ppc64",
+        "s390x" => "s390x",
+        "riscv64" => "riscv64",
+        "mips64" => "mips64",
+        "loongarch64" => "loong64",
+        _ => return None,
+    };
+    Some(arch)
+}
+
+/// Erkennt die native Debian-Architektur (dpkg, sonst uname -m)
+pub fn detect_native_architecture() -> String {
+    if let Some(arch) = run_dpkg_arch_query("--print-architecture").into_iter().next() {
+        return arch;
     }
     
-    // Fallback: Versuche aus sources.list zu lesen
-    let sources_list = Path::new("/etc/apt/sources.list");
-    if sources_list.exists() {
-        if let Ok(content) = fs::read_to_string(sources_list) {
-            for line in content.lines() {
-                let line = line.trim();
-                if line.is_empty() || line.starts_with('#') {
-                    continue;
-                }
-                
-                // Parse erste deb-Zeile
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 3 && (parts[0] == "deb" || parts[0] == "deb-src") {
-                    let mut idx = 1;
-                    // Überspringe [options]
-                    if parts[idx].starts_with('[') {
-                        while idx < parts.len() && !parts[idx].ends_with(']') {
-                            idx += 1;
-                        }
-                        idx += 1;
-                    }
-                    if idx < parts.len() {
-                        // Suite ist der Teil nach der URL
-                        return Ok(parts[idx].to_string());
-                    }
-                }
-            }
+    machine_name()
+        .ok()
+        .and_then(|machine| debian_arch_from_uname(&machine))
+        .unwrap_or("amd64")
+        .to_string()
+}
+
+/// Gibt die per `dpkg --add-architecture` aktivierten Fremdarchitekturen zurück
+pub fn detect_foreign_architectures() -> Vec<String> {
+    run_dpkg_arch_query("--print-foreign-architectures")
+}
+
+/// Architekturen, deren Paketindizes geladen werden: native, fremde und immer `all`
+pub fn detect_architectures() -> Vec<String> {
+    let mut architectures = vec![detect_native_architecture()];
+    architectures.extend(detect_foreign_architectures());
+    with_arch_all(architectures)
+}
+
+/// Entfernt Duplikate und hängt `all` an, falls es fehlt
+pub fn with_arch_all(architectures: Vec<String>) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for arch in architectures {
+        if !arch.is_empty() && !result.contains(&arch) {
+            result.push(arch);
         }
     }
-    
-    // Fallback zu "stable"
-    Ok("stable".to_string())
+    if !result.iter().any(|a| a == "all") {
+        result.push("all".to_string());
+    }
+    result
+}
+
+fn run_dpkg_arch_query(flag: &str) -> Vec<String> {
+    if !crate::dpkg::available(crate::dpkg::Tool::Dpkg) {
+        return Vec::new();
+    }
+    match std::process::Command::new("dpkg").arg(flag).output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
 }
 
 #[cfg(test)]
@@ -84,5 +201,61 @@ mod tests {
         let suite = detect_debian_suite().unwrap();
         assert!(!suite.is_empty());
     }
+    
+    #[test]
+    fn test_parse_os_release() {
+        let bookworm = parse_os_release(r#"PRETTY_NAME="Debian GNU/Linux 12 (bookworm)"
+NAME="Debian GNU/Linux"
+VERSION_ID="12"
+VERSION="12 (bookworm)"
+VERSION_CODENAME=bookworm
+ID=debian
+HOME_URL="https://www.debian.org/"
+"#);
+        assert_eq!(bookworm, Distro { id: "debian".to_string(), codename: "bookworm".to_string(), ubuntu_codename: None });
+        assert!(!bookworm.is_ubuntu());
+        
+        let jammy = parse_os_release(r#"PRETTY_NAME="Ubuntu 22.04.4 LTS"
+NAME="Ubuntu"
+VERSION_ID="22.04"
+VERSION="22.04.4 LTS (Jammy Jellyfish)"
+VERSION_CODENAME=jammy
+ID=ubuntu
+ID_LIKE=debian
+UBUNTU_CODENAME=jammy
+"#);
+        assert_eq!(jammy.id, "ubuntu");
+        assert_eq!(jammy.codename, "jammy");
+        assert!(jammy.is_ubuntu());
+        
+        // Derivate: eigener Codename, Ubuntu-Suite für Archive und PPAs
+        let mint = parse_os_release("ID=linuxmint\nID_LIKE=\"ubuntu debian\"\nVERSION_CODENAME=virginia\nUBUNTU_CODENAME=jammy\n");
+        assert_eq!((mint.codename.as_str(), mint.ubuntu_codename.as_deref()), ("virginia", Some("jammy")));
+        let pop = parse_os_release("ID=pop\nID_LIKE=\"ubuntu debian\"\nVERSION_CODENAME=noble\n");
+        assert_eq!(pop.ubuntu_codename.as_deref(), Some("noble"));
+        
+        // Debian sid nennt keinen Codename, dann entscheidet /etc/debian_version
+        let sid = parse_os_release("PRETTY_NAME=\"Debian GNU/Linux trixie/sid\"\nID=debian\n");
+        assert_eq!(sid.codename, "");
+        assert_eq!(parse_os_release("").id, "linux");
+        assert_eq!(codename_from_debian_version("trixie/sid\n").as_deref(), Some("trixie"));
+        assert_eq!(codename_from_debian_version("12.5\n").as_deref(), Some("bookworm"));
+        assert_eq!(codename_from_debian_version("8.11"), None);
+    }
+    
+    #[test]
+    fn test_debian_arch_from_uname() {
+        assert_eq!(debian_arch_from_uname("x86_64"), Some("amd64"));
+        assert_eq!(debian_arch_from_uname("aarch64"), Some("arm64"));
+        assert_eq!(debian_arch_from_uname("armv7l"), Some("armhf"));
+        assert_eq!(debian_arch_from_uname("i686"), Some("i386"));
+        assert_eq!(debian_arch_from_uname("ppc64le"), Some("ppc64el"));
+        assert_eq!(debian_arch_from_uname("mips64"), Some("mips64"));
+        assert_eq!(debian_arch_from_uname("sparc64"), None);
+        
+        assert_eq!(
+            with_arch_all(vec!["arm64".to_string(), "armhf".to_string(), "arm64".to_string()]),
+            vec!["arm64", "armhf", "all"]
+        );
+    }
 }
-
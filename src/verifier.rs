@@ -1,17 +1,84 @@
-use anyhow::Result;
+use crate::error::{AptNgError, Result};
+use crate::openpgp::{OpenPgpKey, OpenPgpSignature};
+use chrono::{DateTime, Utc};
 use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::convert::TryInto;
 
-#[allow(dead_code)]
+/// Ein vertrauenswürdiger Schlüssel mit optionalem Ablaufdatum
+///
+/// Das Ablaufdatum wird aus einer Datei `<name>.expires` neben `<name>.pub`
+/// gelesen (RFC 3339, z.B. `2026-01-31T00:00:00Z`).
+#[derive(Debug, Clone)]
+pub struct TrustedKey {
+    pub name: String,
+    pub key: VerifyingKey,
+    pub expires: Option<DateTime<Utc>>,
+}
+
+impl TrustedKey {
+    /// Kurzer Fingerprint (erste 8 Bytes des Schlüssels, hex)
+    pub fn fingerprint(&self) -> String {
+        hex::encode(&self.key.as_bytes()[..8])
+    }
+    
+    /// Prüft, ob der Schlüssel zum angegebenen Zeitpunkt abgelaufen ist
+    pub fn is_expired_at(&self, time: DateTime<Utc>) -> bool {
+        self.expires.map(|expires| expires <= time).unwrap_or(false)
+    }
+}
+
+/// Fehler bei der Signaturprüfung, die vom Aufrufer unterschieden werden müssen
+#[derive(Debug)]
+pub enum SignatureError {
+    /// Keine vertrauenswürdigen Schlüssel vorhanden
+    NoTrustedKeys,
+    /// Die Signatur hat ein ungültiges Format
+    Malformed(String),
+    /// Die Signatur passt zu keinem vertrauenswürdigen Schlüssel
+    Invalid,
+    /// Die Signatur stammt von einem abgelaufenen Schlüssel
+    KeyExpired { key: String, expired_at: DateTime<Utc> },
+    /// Die Signatur wurde erst nach Ablauf des Schlüssels erstellt
+    SignedAfterExpiry { key: String, expired_at: DateTime<Utc>, signed_at: DateTime<Utc> },
+    /// Die Signatur stammt von einem widerrufenen Schlüssel
+    KeyRevoked { key: String },
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureError::NoTrustedKeys => write!(f, "No trusted keys available"),
+            SignatureError::Malformed(reason) => write!(f, "Malformed signature: {}", reason),
+            SignatureError::Invalid => write!(f, "Signature verification failed with all trusted keys"),
+            SignatureError::KeyExpired { key, expired_at } => {
+                write!(f, "Signed by expired key {} (expired {})", key, expired_at.format("%Y-%m-%d"))
+            }
+            SignatureError::SignedAfterExpiry { key, expired_at, signed_at } => write!(
+                f,
+                "Signature created {} but key {} expired {}",
+                signed_at.format("%Y-%m-%d"),
+                key,
+                expired_at.format("%Y-%m-%d")
+            ),
+            SignatureError::KeyRevoked { key } => write!(f, "Signed by revoked key {}", key),
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
 pub struct PackageVerifier {
-    trusted_keys: Vec<VerifyingKey>,
+    trusted_keys: Vec<TrustedKey>,
+    /// Schlüssel aus OpenPGP-Schlüsselbunden (z.B. apts trusted.gpg.d) für Release-Signaturen
+    openpgp_keys: Vec<OpenPgpKey>,
+    allow_expired_keys: bool,
 }
 
 impl PackageVerifier {
     /// Erstellt einen neuen Verifier mit vertrauenswürdigen Schlüsseln
-    #[allow(dead_code)]
     pub fn new(trusted_keys_dir: &Path) -> Result<Self> {
         let mut trusted_keys = Vec::new();
         
@@ -25,7 +92,18 @@ impl PackageVerifier {
                         if key_bytes.len() == 32 {
                             if let Ok(key_bytes_array) = key_bytes.as_slice().try_into() {
                                 if let Ok(key) = VerifyingKey::from_bytes(&key_bytes_array) {
-                                    trusted_keys.push(key);
+                                    let name = path.file_stem()
+                                        .and_then(|s| s.to_str())
+                                        .unwrap_or("unknown")
+                                        .to_string();
+                                    // Ein unlesbares Ablaufdatum macht den Schlüssel unbrauchbar,
+                                    // nicht den ganzen Verifier
+                                    match Self::read_key_expiry(&path.with_extension("expires")) {
+                                        Ok(expires) => trusted_keys.push(TrustedKey { name, key, expires }),
+                                        Err(e) => crate::output::Output::warning(&format!(
+                                            "Ignoring trusted key {}: {}", path.display(), e
+                                        )),
+                                    }
                                 }
                             }
                         }
@@ -34,11 +112,43 @@ impl PackageVerifier {
             }
         }
         
-        Ok(PackageVerifier { trusted_keys })
+        Ok(PackageVerifier { trusted_keys, openpgp_keys: Vec::new(), allow_expired_keys: false })
+    }
+    
+    /// Verifier, der nur die Schlüssel eines Schlüsselbunds kennt (`signed-by=` eines Repositories)
+    pub fn with_keyring(keyring: &Path) -> Result<Self> {
+        let mut verifier = PackageVerifier { trusted_keys: Vec::new(), openpgp_keys: Vec::new(), allow_expired_keys: false };
+        if verifier.load_openpgp_keyrings(&[keyring.to_path_buf()]) == 0 {
+            return Err(AptNgError::InvalidKey(format!("no usable keys in {}", keyring.display())));
+        }
+        Ok(verifier)
+    }
+    
+    /// Verifier, der genau einem Ed25519-Schlüssel vertraut (z.B. dem eingebetteten Release-Schlüssel)
+    pub fn with_trusted_key(key_bytes: &[u8]) -> Result<Self> {
+        let mut verifier = PackageVerifier { trusted_keys: Vec::new(), openpgp_keys: Vec::new(), allow_expired_keys: false };
+        verifier.add_trusted_key(key_bytes)?;
+        Ok(verifier)
+    }
+    
+    /// Liest das Ablaufdatum eines Schlüssels, falls eine `.expires`-Datei existiert
+    fn read_key_expiry(path: &Path) -> Result<Option<DateTime<Utc>>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        
+        let content = fs::read_to_string(path)?;
+        let expires = DateTime::parse_from_rfc3339(content.trim())
+            .map_err(|e| AptNgError::InvalidKey(format!("invalid expiry in {}: {}", path.display(), e)))?;
+        Ok(Some(expires.with_timezone(&Utc)))
+    }
+    
+    /// Erlaubt Signaturen von abgelaufenen Schlüsseln (für nachhinkende Mirrors)
+    pub fn set_allow_expired_keys(&mut self, allow: bool) {
+        self.allow_expired_keys = allow;
     }
     
     /// Verifiziert eine Signatur gegen die Metadaten
-    #[allow(dead_code)]
     pub fn verify_signature(
         &self,
         metadata: &[u8],
@@ -46,76 +156,247 @@ impl PackageVerifier {
         key: &VerifyingKey,
     ) -> Result<()> {
         let signature_bytes_array: [u8; 64] = signature_bytes.try_into()
-            .map_err(|_| anyhow::anyhow!("Invalid signature length: expected 64 bytes"))?;
+            .map_err(|_| SignatureError::Malformed("invalid length, expected 64 bytes".to_string()))?;
         let signature = Signature::from_bytes(&signature_bytes_array);
         
         key.verify(metadata, &signature)
-            .map_err(|e| anyhow::anyhow!("Signature verification failed: {}", e))?;
+            .map_err(|_| SignatureError::Invalid)?;
         
         Ok(())
     }
     
     /// Verifiziert eine Signatur gegen alle vertrauenswürdigen Schlüssel
-    #[allow(dead_code)]
     pub fn verify_with_trusted_keys(
         &self,
         metadata: &[u8],
         signature_bytes: &[u8],
+    ) -> Result<()> {
+        self.verify_at(metadata, signature_bytes, None)
+    }
+    
+    /// Verifiziert eine Release-Datei samt Ablaufprüfung
+    ///
+    /// `signature_bytes` ist entweder eine rohe Ed25519-Signatur (64 Bytes) oder eine
+    /// OpenPGP-Signatur wie `Release.gpg` (binär oder ASCII-armored).
+    /// Das `Date`-Feld der Release-Datei gilt als Erstellungszeitpunkt der Signatur.
+    /// Eine Signatur, die nach Ablauf des Schlüssels erstellt wurde, wird immer abgelehnt,
+    /// auch wenn abgelaufene Schlüssel erlaubt sind.
+    pub fn verify_release(&self, release_data: &[u8], signature_bytes: &[u8]) -> Result<()> {
+        if signature_bytes.len() != 64 {
+            let signatures = crate::openpgp::parse_signatures(signature_bytes)?;
+            return self.verify_openpgp(release_data, &signatures);
+        }
+        let signed_at = parse_release_date(&String::from_utf8_lossy(release_data));
+        self.verify_at(release_data, signature_bytes, signed_at)
+    }
+    
+    /// Verifiziert eine clearsigned InRelease-Datei und gibt den signierten Text zurück
+    ///
+    /// Nur der zurückgegebene Text ist authentifiziert; Kopfzeilen und Hash-Listen
+    /// sollten daraus gelesen werden, nicht aus der Datei.
+    pub fn verify_inrelease(&self, content: &[u8]) -> Result<String> {
+        let clearsigned = crate::openpgp::parse_clearsigned(content)?;
+        self.verify_openpgp(clearsigned.signed_data(), &clearsigned.signatures)?;
+        Ok(clearsigned.text)
+    }
+    
+    /// Verifiziert InRelease (`detached_signature` = None) oder Release samt `Release.gpg`
+    ///
+    /// Das Ergebnis enthält ausschließlich die signierten Bytes; Hash-Listen werden nur
+    /// daraus gelesen, nie aus der heruntergeladenen Datei.
+    pub fn verify_release_file(&self, content: &[u8], detached_signature: Option<&[u8]>) -> Result<VerifiedRelease> {
+        let text = match detached_signature {
+            None => self.verify_inrelease(content)?,
+            Some(signature) => {
+                self.verify_release(content, signature)?;
+                String::from_utf8(content.to_vec())
+                    .map_err(|_| AptNgError::VerificationFailed("Release file is not UTF-8".to_string()))?
+            }
+        };
+        Ok(VerifiedRelease { text })
+    }
+    
+    /// Prüft OpenPGP-Signaturen gegen die geladenen Schlüsselbunde; eine gültige genügt
+    ///
+    /// Widerrufene Schlüssel werden abgelehnt, abgelaufene wie bei den Ed25519-Schlüsseln
+    /// behandelt. Rohe Ed25519-Schlüssel (`*.pub`) prüfen ebenfalls EdDSA-Signaturen, wie sie
+    /// `apt-ng repo generate --key` erzeugt; dabei gilt ihr Ablaufdatum.
+    fn verify_openpgp(&self, data: &[u8], signatures: &[OpenPgpSignature]) -> Result<()> {
+        if self.openpgp_keys.is_empty() && self.trusted_keys.is_empty() {
+            return Err(SignatureError::NoTrustedKeys.into());
+        }
+        if signatures.is_empty() {
+            return Err(SignatureError::Malformed("no supported OpenPGP signature found".to_string()).into());
+        }
+        
+        // Eine gültige Signatur genügt; sonst wird der erste Ablehnungsgrund gemeldet
+        let now = Utc::now();
+        let mut rejected = None;
+        for signature in signatures {
+            for key in self.openpgp_keys.iter().filter(|key| signature.may_be_from(key)) {
+                if !signature.verify(key, data) {
+                    continue;
+                }
+                let checked = if key.revoked {
+                    Err(SignatureError::KeyRevoked { key: key.fingerprint_hex() }.into())
+                } else {
+                    self.check_key_expiry(&key.fingerprint_hex(), key.expires, signature.created, now)
+                };
+                match checked {
+                    Ok(()) => return Ok(()),
+                    Err(e) => {
+                        rejected.get_or_insert(e);
+                    }
+                }
+            }
+        }
+        
+        for trusted in &self.trusted_keys {
+            let key = crate::openpgp::ed25519_key(&trusted.key);
+            if let Some(signature) = signatures.iter().find(|signature| signature.verify(&key, data)) {
+                return self.check_key_expiry(&trusted.name, trusted.expires, signature.created, now);
+            }
+        }
+        
+        Err(rejected.unwrap_or_else(|| SignatureError::Invalid.into()))
+    }
+    
+    fn verify_at(
+        &self,
+        metadata: &[u8],
+        signature_bytes: &[u8],
+        signed_at: Option<DateTime<Utc>>,
     ) -> Result<()> {
         if self.trusted_keys.is_empty() {
-            return Err(anyhow::anyhow!("No trusted keys available"));
+            return Err(SignatureError::NoTrustedKeys.into());
         }
         
-        for key in &self.trusted_keys {
-            if self.verify_signature(metadata, signature_bytes, key).is_ok() {
-                return Ok(());
+        let now = Utc::now();
+        for trusted in &self.trusted_keys {
+            if self.verify_signature(metadata, signature_bytes, &trusted.key).is_err() {
+                continue;
             }
+            
+            return self.check_key_expiry(&trusted.name, trusted.expires, signed_at, now);
+        }
+        
+        Err(SignatureError::Invalid.into())
+    }
+    
+    /// Lehnt Signaturen abgelaufener Schlüssel ab (nach Ablauf erstellte immer)
+    fn check_key_expiry(
+        &self,
+        key: &str,
+        expires: Option<DateTime<Utc>>,
+        signed_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        let Some(expired_at) = expires else {
+            return Ok(());
+        };
+        if let Some(signed_at) = signed_at.filter(|signed_at| *signed_at > expired_at) {
+            return Err(SignatureError::SignedAfterExpiry { key: key.to_string(), expired_at, signed_at }.into());
+        }
+        
+        if expired_at <= now && !self.allow_expired_keys {
+            return Err(SignatureError::KeyExpired { key: key.to_string(), expired_at }.into());
         }
         
-        Err(anyhow::anyhow!("Signature verification failed with all trusted keys"))
+        Ok(())
     }
     
     /// Fügt einen neuen vertrauenswürdigen Schlüssel hinzu
-    #[allow(dead_code)]
     pub fn add_trusted_key(&mut self, key_bytes: &[u8]) -> Result<()> {
+        self.add_trusted_key_with_expiry(key_bytes, None)
+    }
+    
+    /// Fügt einen neuen vertrauenswürdigen Schlüssel mit Ablaufdatum hinzu
+    pub fn add_trusted_key_with_expiry(&mut self, key_bytes: &[u8], expires: Option<DateTime<Utc>>) -> Result<()> {
         if key_bytes.len() != 32 {
-            return Err(anyhow::anyhow!("Invalid key length: expected 32 bytes"));
+            return Err(AptNgError::InvalidKey("invalid length, expected 32 bytes".to_string()));
         }
         let key_bytes_array: [u8; 32] = key_bytes.try_into()
-            .map_err(|_| anyhow::anyhow!("Invalid key format"))?;
-        let key = VerifyingKey::from_bytes(&key_bytes_array)?;
-        self.trusted_keys.push(key);
+            .map_err(|_| AptNgError::InvalidKey("invalid key format".to_string()))?;
+        let key = VerifyingKey::from_bytes(&key_bytes_array)
+            .map_err(|e| AptNgError::InvalidKey(e.to_string()))?;
+        let name = hex::encode(&key.as_bytes()[..8]);
+        self.trusted_keys.push(TrustedKey { name, key, expires });
         Ok(())
     }
     
-    /// Gibt die Anzahl der vertrauenswürdigen Schlüssel zurück
+    /// Lädt OpenPGP-Schlüsselbunde aus Dateien oder Verzeichnissen (`*.gpg`, `*.asc`)
+    ///
+    /// Fehlende Pfade und nicht lesbare Dateien werden übersprungen (wie bei den
+    /// Ed25519-Schlüsseln). Liefert die Anzahl geladener Schlüssel.
+    pub fn load_openpgp_keyrings(&mut self, paths: &[PathBuf]) -> usize {
+        let mut loaded = 0;
+        for path in paths {
+            let mut files = Vec::new();
+            if path.is_dir() {
+                if let Ok(entries) = fs::read_dir(path) {
+                    files.extend(entries.flatten().map(|entry| entry.path()).filter(|file| {
+                        file.is_file() && matches!(file.extension().and_then(|s| s.to_str()), Some("gpg" | "asc"))
+                    }));
+                }
+                files.sort();
+            } else if path.is_file() {
+                files.push(path.clone());
+            }
+            
+            for file in files {
+                if let Ok(data) = fs::read(&file) {
+                    loaded += self.add_openpgp_keyring(&data).unwrap_or(0);
+                }
+            }
+        }
+        loaded
+    }
+    
+    /// Fügt alle Schlüssel eines OpenPGP-Schlüsselbunds hinzu; liefert deren Anzahl
+    pub fn add_openpgp_keyring(&mut self, data: &[u8]) -> Result<usize> {
+        let keys = crate::openpgp::parse_keyring(data)?;
+        let count = keys.len();
+        for key in keys {
+            if !self.openpgp_keys.iter().any(|known| known.fingerprint == key.fingerprint) {
+                self.openpgp_keys.push(key);
+            }
+        }
+        Ok(count)
+    }
+    
+    /// Gibt die Anzahl der vertrauenswürdigen Schlüssel zurück (Ed25519 und OpenPGP)
     pub fn trusted_key_count(&self) -> usize {
-        self.trusted_keys.len()
+        self.trusted_keys.len() + self.openpgp_keys.len()
+    }
+    
+    /// Gibt die geladenen OpenPGP-Schlüssel zurück
+    pub fn openpgp_keys(&self) -> &[OpenPgpKey] {
+        &self.openpgp_keys
     }
     
-    /// Gibt eine Referenz auf alle vertrauenswürdigen Schlüssel zurück
     /// Gibt alle vertrauenswürdigen Schlüssel zurück
-    #[allow(dead_code)]
-    pub fn get_trusted_keys(&self) -> &[VerifyingKey] {
+    pub fn get_trusted_keys(&self) -> impl Iterator<Item = &VerifyingKey> {
+        self.trusted_keys.iter().map(|trusted| &trusted.key)
+    }
+    
+    /// Gibt alle vertrauenswürdigen Schlüssel samt Name und Ablaufdatum zurück
+    pub fn trusted_key_info(&self) -> &[TrustedKey] {
         &self.trusted_keys
     }
     
     /// Fügt einen Schlüssel aus einer Datei hinzu
-    #[allow(dead_code)]
     pub fn add_key_from_file(&mut self, key_path: &Path) -> Result<()> {
         let key_bytes = std::fs::read(key_path)?;
         self.add_trusted_key(&key_bytes)
     }
     
     /// Speichert einen Schlüssel in eine Datei
-    #[allow(dead_code)]
     pub fn save_key_to_file(&self, key: &VerifyingKey, path: &Path) -> Result<()> {
         std::fs::write(path, key.as_bytes())?;
         Ok(())
     }
     
     /// Verifiziert ein Paket-Signatur
-    #[allow(dead_code)]
     pub fn verify_package_signature(
         &self,
         metadata: &[u8],
@@ -123,6 +404,178 @@ impl PackageVerifier {
     ) -> Result<()> {
         self.verify_with_trusted_keys(metadata, signature_bytes)
     }
+    
+    /// Prüft heruntergeladene Pakete vor der Installation mit höchstens `jobs` Threads
+    ///
+    /// .apx-Pakete werden auf ihre Signatur geprüft (sofern Schlüssel vorhanden sind), alle
+    /// übrigen gegen die Checksumme aus dem Packages-Index. Statt beim ersten Fehler
+    /// abzubrechen, werden alle Fehlschläge gesammelt (in Eingabereihenfolge); `on_checked`
+    /// wird nach jedem geprüften Paket aufgerufen, z.B. für einen Fortschrittsbalken.
+    pub fn verify_packages(
+        &self,
+        packages: &[PackageCheck],
+        jobs: usize,
+        on_checked: impl Fn(&PackageCheck) + Sync,
+    ) -> Result<Vec<PackageCheckFailure>> {
+        use rayon::prelude::*;
+        
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs.max(1))
+            .build()
+            .map_err(|e| AptNgError::Other(anyhow::Error::new(e)))?;
+        let failures = pool.install(|| {
+            packages.par_iter()
+                .filter_map(|package| {
+                    let result = self.verify_package_file(package);
+                    on_checked(package);
+                    result.err().map(|reason| PackageCheckFailure { name: package.name.clone(), reason })
+                })
+                .collect()
+        });
+        Ok(failures)
+    }
+    
+    fn verify_package_file(&self, package: &PackageCheck) -> std::result::Result<(), String> {
+        if package.path.extension().is_some_and(|ext| ext == "apx") {
+            if self.trusted_key_count() == 0 {
+                return Ok(());
+            }
+            return crate::package::ApxPackage::open(&package.path)
+                .and_then(|apx| apx.verify_signature(&package.path, self))
+                .map_err(|e| format!("signature verification failed: {}", e));
+        }
+        if package.checksum.is_empty() {
+            return Ok(());
+        }
+        
+        let actual = file_checksum(&package.path, package.checksum.len())
+            .map_err(|e| format!("cannot read {}: {}", package.path.display(), e))?;
+        if actual.eq_ignore_ascii_case(&package.checksum) {
+            Ok(())
+        } else {
+            Err(format!("checksum mismatch: expected {}, got {}", package.checksum, actual))
+        }
+    }
+}
+
+/// Paketdatei aus dem Cache für `PackageVerifier::verify_packages`
+#[derive(Debug, Clone)]
+pub struct PackageCheck {
+    pub name: String,
+    pub path: PathBuf,
+    /// Checksumme aus dem Packages-Index (SHA256 oder bei alten Repositories MD5, leer: keine)
+    pub checksum: String,
+}
+
+/// Fehlgeschlagene Prüfung eines Pakets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageCheckFailure {
+    pub name: String,
+    pub reason: String,
+}
+
+/// SHA256 einer Datei, MD5 wenn die erwartete Checksumme 32 Zeichen lang ist
+fn file_checksum(path: &Path, expected_len: usize) -> anyhow::Result<String> {
+    if expected_len != 32 {
+        return crate::cache::Cache::calculate_file_checksum(path);
+    }
+    use std::io::Read;
+    let mut file = fs::File::open(path)?;
+    let mut context = md5::Context::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        context.consume(&buffer[..bytes_read]);
+    }
+    Ok(format!("{:x}", context.compute()))
+}
+
+/// Eintrag im `SHA256`-Abschnitt einer Release-Datei
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseFileHash {
+    /// Pfad relativ zu `dists/<suite>/`, z.B. `main/binary-amd64/Packages.xz`
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Signaturgeprüfter Text einer Release- oder InRelease-Datei
+///
+/// Entsteht nur über `PackageVerifier::verify_release_file`. Die Hash-Listen sind nur
+/// hierüber zugänglich, damit kein ungeprüfter Dateiinhalt als vertrauenswürdig gilt.
+#[derive(Debug, Clone)]
+pub struct VerifiedRelease {
+    text: String,
+}
+
+impl VerifiedRelease {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    
+    /// Kopfdaten (Suite, Codename, Date, ...)
+    pub fn info(&self) -> crate::apt_parser::ReleaseInfo {
+        crate::apt_parser::parse_release_file(&self.text)
+    }
+    
+    /// Alle Einträge des `SHA256`-Abschnitts
+    pub fn sha256_entries(&self) -> Vec<ReleaseFileHash> {
+        let mut entries = Vec::new();
+        let mut in_section = false;
+        for line in self.text.lines() {
+            if !line.starts_with(' ') {
+                in_section = line.trim_end() == "SHA256:";
+                continue;
+            }
+            if !in_section {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if let [sha256, size, path] = fields[..] {
+                if let Ok(size) = size.parse() {
+                    entries.push(ReleaseFileHash {
+                        path: path.to_string(),
+                        size,
+                        sha256: sha256.to_ascii_lowercase(),
+                    });
+                }
+            }
+        }
+        entries
+    }
+    
+    /// Prüft eine heruntergeladene Indexdatei (z.B. `main/binary-amd64/Packages.xz`)
+    /// gegen Größe und SHA256 aus dem signierten Text
+    pub fn check_index_file(&self, path: &str, file: &Path) -> Result<()> {
+        let expected = self.sha256_entries().into_iter()
+            .find(|entry| entry.path == path)
+            .ok_or_else(|| AptNgError::VerificationFailed(format!("{} is not listed in the signed Release file", path)))?;
+        
+        let size = fs::metadata(file)?.len();
+        if size != expected.size {
+            return Err(AptNgError::VerificationFailed(format!(
+                "{} has {} bytes, the signed Release file lists {}", path, size, expected.size
+            )));
+        }
+        let actual = crate::cache::Cache::calculate_file_checksum(file)?;
+        if actual != expected.sha256 {
+            return Err(AptNgError::ChecksumMismatch { expected: expected.sha256, actual });
+        }
+        Ok(())
+    }
+}
+
+/// Liest das `Date`-Feld einer Release-Datei (RFC 2822, z.B. `Sat, 10 Aug 2024 09:09:44 UTC`)
+fn parse_release_date(release: &str) -> Option<DateTime<Utc>> {
+    let value = release.lines()
+        .find_map(|line| line.strip_prefix("Date:"))?
+        .trim();
+    let value = value.strip_suffix(" UTC").map(|v| format!("{} +0000", v))
+        .unwrap_or_else(|| value.to_string());
+    DateTime::parse_from_rfc2822(&value).ok().map(|date| date.with_timezone(&Utc))
 }
 
 #[cfg(test)]
@@ -158,5 +611,349 @@ mod tests {
         // Verifiziere Signatur
         assert!(verifier.verify_with_trusted_keys(message, signature.to_bytes().as_slice()).is_ok());
     }
-}
+    
+    #[test]
+    fn test_expired_key_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut verifier = PackageVerifier::new(temp_dir.path()).unwrap();
+        
+        use rand::rngs::OsRng;
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let expired = Utc::now() - chrono::Duration::days(1);
+        verifier.add_trusted_key_with_expiry(signing_key.verifying_key().as_bytes(), Some(expired)).unwrap();
+        
+        let release = b"Origin: Test\nDate: Mon, 01 Jan 2024 00:00:00 UTC\n";
+        let signature = signing_key.sign(release);
+        
+        let err = verifier.verify_release(release, signature.to_bytes().as_slice()).unwrap_err();
+        assert!(matches!(err, AptNgError::Signature(SignatureError::KeyExpired { .. })));
+        
+        // Mit --allow-expired-keys wird die Signatur akzeptiert
+        verifier.set_allow_expired_keys(true);
+        assert!(verifier.verify_release(release, signature.to_bytes().as_slice()).is_ok());
+    }
+    
+    #[test]
+    fn test_key_with_invalid_expiry_is_skipped() {
+        let temp_dir = TempDir::new().unwrap();
+        use rand::rngs::OsRng;
+        let broken = SigningKey::generate(&mut OsRng);
+        let valid = SigningKey::generate(&mut OsRng);
+        fs::write(temp_dir.path().join("broken.pub"), broken.verifying_key().as_bytes()).unwrap();
+        fs::write(temp_dir.path().join("broken.expires"), "next tuesday").unwrap();
+        fs::write(temp_dir.path().join("valid.pub"), valid.verifying_key().as_bytes()).unwrap();
+        
+        // Die übrigen Schlüssel bleiben nutzbar, der mit kaputtem Ablaufdatum nicht
+        let verifier = PackageVerifier::new(temp_dir.path()).unwrap();
+        let release = b"Origin: Test\n";
+        assert!(verifier.verify_release(release, valid.sign(release).to_bytes().as_slice()).is_ok());
+        assert!(verifier.verify_release(release, broken.sign(release).to_bytes().as_slice()).is_err());
+    }
+    
+    #[test]
+    fn test_signature_after_key_expiry_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut verifier = PackageVerifier::new(temp_dir.path()).unwrap();
+        verifier.set_allow_expired_keys(true);
+        
+        use rand::rngs::OsRng;
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let expired = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        verifier.add_trusted_key_with_expiry(signing_key.verifying_key().as_bytes(), Some(expired)).unwrap();
+        
+        let release = b"Origin: Test\nDate: Sat, 10 Aug 2024 09:09:44 UTC\n";
+        let signature = signing_key.sign(release);
+        
+        let err = verifier.verify_release(release, signature.to_bytes().as_slice()).unwrap_err();
+        assert!(matches!(err, AptNgError::Signature(SignatureError::SignedAfterExpiry { .. })));
+    }
+    
+    /// Ed25519-Schlüssel und Signaturen, erzeugt mit GnuPG 2.2
+    const OPENPGP_KEY: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----
+
+mDMEatJQ6BYJKwYBBAHaRw8BAQdAPplopCcT9jJM2Maam9e2AI1ObfuX++4AShWM
+bsELtG20IWFwdC1uZyB0ZXN0IDx0ZXN0QGFwdC1uZy5pbnZhbGlkPoiQBBMWCAA4
+FiEEokiNlikIckR73cCk3utjhmXz4VQFAmrSUOgCGwMFCwkIBwIGFQoJCAsCBBYC
+AwECHgECF4AACgkQ3utjhmXz4VQ6yQD+Mz4ZGELVu6ZVazbH3XoDUZ/S+WcRkPv5
+nHOYKr5I1QEBAKj2sTgvV7ay6end0qSgCpRZu7feeFehHlWgm/TsKcAC
+=Rfrl
+-----END PGP PUBLIC KEY BLOCK-----
+";
+    const OPENPGP_RELEASE: &str = "Origin: apt-ng\nSuite: stable\nDate: Sat, 10 Aug 2024 09:09:44 UTC\n";
+    const OPENPGP_INRELEASE: &str = "-----BEGIN PGP SIGNED MESSAGE-----
+Hash: SHA256
+
+Origin: apt-ng
+Suite: stable
+Date: Sat, 10 Aug 2024 09:09:44 UTC
+-----BEGIN PGP SIGNATURE-----
+
+iHUEARYIAB0WIQSiSI2WKQhyRHvdwKTe62OGZfPhVAUCatJQ6AAKCRDe62OGZfPh
+VL4dAQCMEOrBCrcjbhIjPKcxyT2YHYnl3NBxjtUSB8niSUZv3QEA0wYkBfnwFkue
+38SMEjJMvqh1pixxajocKA9VlMkv4gI=
+=I9sA
+-----END PGP SIGNATURE-----
+";
+    const OPENPGP_RELEASE_GPG: &str = "-----BEGIN PGP SIGNATURE-----
 
+iHUEABYKAB0WIQSiSI2WKQhyRHvdwKTe62OGZfPhVAUCatJQ6AAKCRDe62OGZfPh
+VPyFAQDwpScYn2ogV6SXstWET2vXlM9MapWV6eqVORu/9SBlewEAvYL4n5OI9vcf
+MY6yCtJgfIHg5qBxSo2G7rXljqD6IgQ=
+=uJ+b
+-----END PGP SIGNATURE-----
+";
+    
+    /// Schlüssel vom 01.01.2024 mit einem Tag Gültigkeit; Signatur vom selben Tag (GnuPG 2.2)
+    const OPENPGP_EXPIRED_KEY: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----
+
+mDMEZZIAgBYJKwYBBAHaRw8BAQdAL7ARvlH24P84LxwHxVUhU6F2wHYxGqlSKFUa
+U5hCCeq0J2FwdC1uZyBleHBpcmVkIDxleHBpcmVkQGFwdC1uZy5pbnZhbGlkPoiW
+BBMWCAA+FiEEBNlXiCb4zMR1eskGZAgLRo4GBOYFAmWSAIACGwMFCQABUYAFCwkI
+BwIGFQoJCAsCBBYCAwECHgECF4AACgkQZAgLRo4GBOZDiwD/ZmmOw2ydMcaYO2lJ
+sLJi5BzHO327Z17SXL+oSzVc5hEA/3DTWw8dfokMuynoWSEmuUxuR2SbrdjvUUGH
+886S0pYE
+=pzS9
+-----END PGP PUBLIC KEY BLOCK-----
+";
+    const OPENPGP_EXPIRED_RELEASE_GPG: &str = "-----BEGIN PGP SIGNATURE-----
+
+iI0EABYIADUWIQQE2VeIJvjMxHV6yQZkCAtGjgYE5gUCZZKpQBccZXhwaXJlZEBh
+cHQtbmcuaW52YWxpZAAKCRBkCAtGjgYE5hn6AQD9rEj35hTmwiywp+7d+a+02Puc
+HuWRNoEIQzYBWLRW5QD8CYnF1MDHeb2eGY4kSkThne4Fe7e5CA7iKSDUFKdLJwk=
+=VkBh
+-----END PGP SIGNATURE-----
+";
+    /// Schlüssel mit importiertem Widerrufszertifikat und vorher erstellter Signatur
+    const OPENPGP_REVOKED_KEY: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----
+
+mDMEatJ7+RYJKwYBBAHaRw8BAQdA1zNeu1djIabfL/i3sMR0SUV4pp3Bqol6WzCV
+benkXWyIeAQgFggAIBYhBLXW6K2kv39mKklKPGhEIz/m3W3UBQJq0nv5Ah0AAAoJ
+EGhEIz/m3W3UJp8BAJXSG6HY5Jikh16vwyljDbPo9FTGMF5KjsknMoRLvWegAP41
+LBTAfeiajOUIEsnI2Oss39q1qUiHx71eWNLMGU7LCrQnYXB0LW5nIHJldm9rZWQg
+PHJldm9rZWRAYXB0LW5nLmludmFsaWQ+iJAEExYIADgWIQS11uitpL9/ZipJSjxo
+RCM/5t1t1AUCatJ7+QIbAwULCQgHAgYVCgkICwIEFgIDAQIeAQIXgAAKCRBoRCM/
+5t1t1MtdAP9MxdboyUafPm3rWgjGhAcG7lUGr1sT2d+e7d2S3H31kAD/Yrl9Gl3U
+rG5rgRGYBwU9cZFQQdyGzyeArjavNHNyEgM=
+=JRvN
+-----END PGP PUBLIC KEY BLOCK-----
+";
+    const OPENPGP_REVOKED_RELEASE_GPG: &str = "-----BEGIN PGP SIGNATURE-----
+
+iI0EABYIADUWIQS11uitpL9/ZipJSjxoRCM/5t1t1AUCatJ7+RcccmV2b2tlZEBh
+cHQtbmcuaW52YWxpZAAKCRBoRCM/5t1t1OpUAP9Z0B9AovzX/fhiHCi9HImzcgFD
+KU9Sa3hgcy1lMtkFNQD5AefYZWdPGbmO7QC8NWBEPjxAlPErzpgFGUjbErIyVAo=
+=BHdw
+-----END PGP SIGNATURE-----
+";
+    const OPENPGP_SHORT_RELEASE: &str = "Origin: apt-ng
+Suite: stable
+";
+    
+    #[test]
+    fn test_openpgp_expired_and_revoked_keys_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut verifier = PackageVerifier::new(temp_dir.path()).unwrap();
+        verifier.add_openpgp_keyring(OPENPGP_EXPIRED_KEY.as_bytes()).unwrap();
+        verifier.add_openpgp_keyring(OPENPGP_REVOKED_KEY.as_bytes()).unwrap();
+        let keys = verifier.openpgp_keys();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].expires, DateTime::parse_from_rfc3339("2024-01-02T00:00:00Z").ok().map(|date| date.with_timezone(&Utc)));
+        assert!(keys[0].is_expired_at(Utc::now()) && !keys[0].revoked);
+        assert!(keys[1].revoked && keys[1].expires.is_none());
+        
+        let release = OPENPGP_SHORT_RELEASE.as_bytes();
+        let err = verifier.verify_release(release, OPENPGP_EXPIRED_RELEASE_GPG.as_bytes()).unwrap_err();
+        assert!(matches!(err, AptNgError::Signature(SignatureError::KeyExpired { .. })), "{}", err);
+        let err = verifier.verify_release(release, OPENPGP_REVOKED_RELEASE_GPG.as_bytes()).unwrap_err();
+        assert!(matches!(err, AptNgError::Signature(SignatureError::KeyRevoked { .. })), "{}", err);
+        
+        // Vor dem Ablauf erstellte Signaturen gelten mit --allow-expired-keys, widerrufene nie
+        verifier.set_allow_expired_keys(true);
+        verifier.verify_release(release, OPENPGP_EXPIRED_RELEASE_GPG.as_bytes()).unwrap();
+        assert!(verifier.verify_release(release, OPENPGP_REVOKED_RELEASE_GPG.as_bytes()).is_err());
+    }
+    
+    #[test]
+    fn test_openpgp_keyring_verifies_release_signatures() {
+        let temp_dir = TempDir::new().unwrap();
+        let keyrings = temp_dir.path().join("trusted.gpg.d");
+        std::fs::create_dir_all(&keyrings).unwrap();
+        std::fs::write(keyrings.join("apt-ng-test.asc"), OPENPGP_KEY).unwrap();
+        std::fs::write(keyrings.join("README"), "not a keyring").unwrap();
+        
+        let mut verifier = PackageVerifier::new(temp_dir.path()).unwrap();
+        use rand::rngs::OsRng;
+        verifier.add_trusted_key(SigningKey::generate(&mut OsRng).verifying_key().as_bytes()).unwrap();
+        let missing = temp_dir.path().join("missing");
+        assert_eq!(verifier.load_openpgp_keyrings(&[keyrings, missing]), 1);
+        assert_eq!(verifier.trusted_key_count(), 2);
+        
+        let key = &verifier.openpgp_keys()[0];
+        assert_eq!(key.fingerprint_hex(), "A2488D96290872447BDDC0A4DEEB638665F3E154");
+        assert_eq!(key.user_id.as_deref(), Some("apt-ng test <test@apt-ng.invalid>"));
+        
+        // InRelease (clearsigned) liefert den verifizierten Text
+        assert_eq!(verifier.verify_inrelease(OPENPGP_INRELEASE.as_bytes()).unwrap(), OPENPGP_RELEASE);
+        let tampered = OPENPGP_INRELEASE.replace("Suite: stable", "Suite: unstable");
+        assert!(matches!(
+            verifier.verify_inrelease(tampered.as_bytes()),
+            Err(AptNgError::Signature(SignatureError::Invalid))
+        ));
+        
+        // Release + Release.gpg (abgetrennte Signatur)
+        verifier.verify_release(OPENPGP_RELEASE.as_bytes(), OPENPGP_RELEASE_GPG.as_bytes()).unwrap();
+        assert!(verifier.verify_release(b"Origin: other\n", OPENPGP_RELEASE_GPG.as_bytes()).is_err());
+        
+        // Ohne geladenen Schlüsselbund gibt es keinen passenden Schlüssel
+        let empty = PackageVerifier::new(temp_dir.path()).unwrap();
+        assert!(matches!(
+            empty.verify_inrelease(OPENPGP_INRELEASE.as_bytes()),
+            Err(AptNgError::Signature(SignatureError::NoTrustedKeys))
+        ));
+    }
+    
+    /// Zweiter Testschlüssel; die InRelease-Datei enthält einen `SHA256`-Abschnitt
+    const OPENPGP_HASHES_KEY: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----
+
+mDMEatJr+RYJKwYBBAHaRw8BAQdAX+B9kAb2wgq3hIFrC9lyUx1LqRYEWhNb6a8c
+FjD2o0G0IWFwdC1uZyB0ZXN0IDx0ZXN0QGFwdC1uZy5pbnZhbGlkPoiQBBMWCAA4
+FiEExTSBhEarEnETe2GGBtedjFz0hpsFAmrSa/kCGwMFCwkIBwIGFQoJCAsCBBYC
+AwECHgECF4AACgkQBtedjFz0hptK7gD+I8GhEGabl8zBMu3I4xqnu2jMzkYJtroF
+lA5MZnbeTZoA/1xl+m6cMSBFzxEzoxJ4NOBLT96c2+CqP6Hka/xse1UN
+=I8gE
+-----END PGP PUBLIC KEY BLOCK-----
+";
+    const OPENPGP_INRELEASE_WITH_HASHES: &str = "-----BEGIN PGP SIGNED MESSAGE-----
+Hash: SHA256
+
+Origin: apt-ng
+Suite: stable
+Date: Sat, 10 Aug 2024 09:09:44 UTC
+SHA256:
+ ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad 3 main/binary-amd64/Packages
+ 0d1b08c34858921bc7c662b228acb7ba99fe3a5c50d8c0a5cbd0a1b9d3aa1e5a 40 main/binary-amd64/Packages.gz
+-----BEGIN PGP SIGNATURE-----
+
+iHUEARYIAB0WIQTFNIGERqsScRN7YYYG152MXPSGmwUCatJsEgAKCRAG152MXPSG
+m34eAQCaUWtS3+azYLDCVv9/T5LH/LdilKHAUqvdW8+C5fQ8tAEAzqRirpOSxMvC
+0PfiX6wXIamrclj75yrrTV222oMPtwI=
+=YoFn
+-----END PGP SIGNATURE-----
+";
+    
+    #[test]
+    fn test_release_hashes_come_from_verified_text() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut verifier = PackageVerifier::new(temp_dir.path()).unwrap();
+        assert_eq!(verifier.add_openpgp_keyring(OPENPGP_HASHES_KEY.as_bytes()).unwrap(), 1);
+        
+        let verified = verifier.verify_release_file(OPENPGP_INRELEASE_WITH_HASHES.as_bytes(), None).unwrap();
+        assert_eq!(verified.info().suite.as_deref(), Some("stable"));
+        let entries = verified.sha256_entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], ReleaseFileHash {
+            path: "main/binary-amd64/Packages".to_string(),
+            size: 3,
+            sha256: "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_string(),
+        });
+        
+        // Heruntergeladene Indexdateien werden gegen den signierten Text geprüft
+        let packages = temp_dir.path().join("Packages");
+        std::fs::write(&packages, "abc").unwrap();
+        verified.check_index_file("main/binary-amd64/Packages", &packages).unwrap();
+        std::fs::write(&packages, "abd").unwrap();
+        assert!(matches!(
+            verified.check_index_file("main/binary-amd64/Packages", &packages),
+            Err(AptNgError::ChecksumMismatch { .. })
+        ));
+        assert!(verified.check_index_file("main/binary-amd64/Packages.xz", &packages).is_err());
+        
+        // Nach dem Signieren veränderte Hash-Liste: die Signatur passt nicht mehr
+        let evil = "88d4266fd4e6338d13b845fcf289579d209c897823b9217da3e161936f031589";
+        let tampered = OPENPGP_INRELEASE_WITH_HASHES.replace(&entries[0].sha256, evil);
+        assert!(matches!(
+            verifier.verify_release_file(tampered.as_bytes(), None),
+            Err(AptNgError::Signature(SignatureError::Invalid))
+        ));
+        
+        // Unsignierter Text vor oder nach der Signatur wird nie als Hash-Liste gelesen
+        let injected = format!("SHA256:\n {} 4 main/binary-amd64/Packages.xz\n", evil);
+        let appended = format!("{}{}", OPENPGP_INRELEASE_WITH_HASHES, injected);
+        let verified = verifier.verify_release_file(appended.as_bytes(), None).unwrap();
+        assert_eq!(verified.sha256_entries(), entries);
+        assert!(verified.check_index_file("main/binary-amd64/Packages.xz", &packages).is_err());
+        let prepended = format!("{}{}", injected, OPENPGP_INRELEASE_WITH_HASHES);
+        assert!(verifier.verify_release_file(prepended.as_bytes(), None).is_err());
+        
+        // Release + Release.gpg: nur die signierten Bytes zählen
+        assert!(verifier.verify_release_file(b"SHA256:\n", Some(b"not a signature")).is_err());
+    }
+    
+    #[test]
+    fn test_verify_packages_collects_all_failures() {
+        use rand::rngs::OsRng;
+        let temp_dir = TempDir::new().unwrap();
+        let mut verifier = PackageVerifier::new(&temp_dir.path().join("keys")).unwrap();
+        let signing_key = SigningKey::generate(&mut OsRng);
+        verifier.add_trusted_key(signing_key.verifying_key().as_bytes()).unwrap();
+        
+        let root = temp_dir.path().join("root");
+        std::fs::create_dir_all(root.join("usr/bin")).unwrap();
+        std::fs::write(root.join("usr/bin/hello"), b"hello\n").unwrap();
+        let manifest = crate::apt_parser::parse_packages_file("Package: hello\nVersion: 1.0\nArchitecture: amd64\n")
+            .unwrap()
+            .remove(0);
+        let signed = temp_dir.path().join("hello.apx");
+        crate::package::create_apx_package(&root, manifest.clone(), &signed, Some(&signing_key.to_bytes())).unwrap();
+        let unsigned = temp_dir.path().join("unsigned.apx");
+        crate::package::create_apx_package(&root, manifest, &unsigned, None).unwrap();
+        
+        let deb = |name: &str, content: &[u8]| {
+            let path = temp_dir.path().join(format!("{}.deb", name));
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+        let check = |name: &str, path: PathBuf, checksum: &str| PackageCheck {
+            name: name.to_string(),
+            path,
+            checksum: checksum.to_string(),
+        };
+        let packages = vec![
+            check("hello", signed, ""),
+            check("good", deb("good", b"deb"), &crate::cache::Cache::calculate_checksum(b"deb")),
+            check("corrupt", deb("corrupt", b"truncated"), &crate::cache::Cache::calculate_checksum(b"deb")),
+            check("legacy", deb("legacy", b"deb"), &format!("{:x}", md5::compute(b"deb"))),
+            check("unsigned", unsigned, ""),
+            check("missing", temp_dir.path().join("missing.deb"), &crate::cache::Cache::calculate_checksum(b"deb")),
+            check("unchecked", deb("unchecked", b"anything"), ""),
+        ];
+        
+        let checked = std::sync::atomic::AtomicUsize::new(0);
+        let failures = verifier.verify_packages(&packages, 4, |_| {
+            checked.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }).unwrap();
+        assert_eq!(checked.into_inner(), packages.len());
+        assert_eq!(
+            failures.iter().map(|failure| failure.name.as_str()).collect::<Vec<_>>(),
+            ["corrupt", "unsigned", "missing"]
+        );
+        assert!(failures[0].reason.starts_with("checksum mismatch"));
+        assert!(failures[1].reason.starts_with("signature verification failed"));
+    }
+    
+    #[test]
+    fn test_keyring_verifier_only_trusts_its_keyring() {
+        let temp_dir = TempDir::new().unwrap();
+        let keyring = temp_dir.path().join("example.asc");
+        std::fs::write(&keyring, OPENPGP_KEY).unwrap();
+        
+        let verifier = PackageVerifier::with_keyring(&keyring).unwrap();
+        assert_eq!(verifier.verify_inrelease(OPENPGP_INRELEASE.as_bytes()).unwrap(), OPENPGP_RELEASE);
+        // Von einem anderen Schlüssel signiert, der nur global vertrauenswürdig sein könnte
+        assert!(verifier.verify_release_file(OPENPGP_INRELEASE_WITH_HASHES.as_bytes(), None).is_err());
+        
+        std::fs::write(&keyring, "not a keyring").unwrap();
+        assert!(PackageVerifier::with_keyring(&keyring).is_err());
+        assert!(PackageVerifier::with_keyring(&temp_dir.path().join("missing.gpg")).is_err());
+    }
+}
@@ -13,8 +13,13 @@ async fn test_update_command() {
         enabled: true,
         last_probe_ms: None,
         rtt_ms: None,
+        throughput_bps: None,
+        codename: None,
         suite: Some("stable".to_string()),
         components: vec!["main".to_string()],
+        architectures: Vec::new(),
+        signed_by: None,
+        trusted: None,
     };
     
     Repository::add_to_db(env.index.conn(), &repo).unwrap();
@@ -83,8 +88,13 @@ async fn test_repo_add() {
         enabled: true,
         last_probe_ms: None,
         rtt_ms: None,
+        throughput_bps: None,
+        codename: None,
         suite: None,
         components: vec!["main".to_string()],
+        architectures: Vec::new(),
+        signed_by: None,
+        trusted: None,
     };
     
     Repository::add_to_db(env.index.conn(), &repo).unwrap();
@@ -132,8 +142,13 @@ async fn test_install_dry_run() {
         enabled: true,
         last_probe_ms: None,
         rtt_ms: None,
+        throughput_bps: None,
+        codename: None,
         suite: Some("stable".to_string()),
         components: vec!["main".to_string()],
+        architectures: Vec::new(),
+        signed_by: None,
+        trusted: None,
     };
     
     Repository::add_to_db(env.index.conn(), &repo).unwrap();
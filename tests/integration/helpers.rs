@@ -31,6 +31,8 @@ pub fn create_test_config(repo_url: &str, temp_dir: &Path) -> apt_ng::config::Co
             state_dir,
             cache_dir,
             trusted_keys_dir,
+            install_root: temp_dir.join("root"),
+            tmp_dir: None,
         },
         jobs: Some(2),
         repos: vec![apt_ng::config::RepoConfig {
@@ -19,8 +19,13 @@ async fn test_repository_formats() {
             enabled: true,
             last_probe_ms: None,
             rtt_ms: None,
+            throughput_bps: None,
+            codename: None,
             suite: Some(suite.to_string()),
             components,
+            architectures: Vec::new(),
+            signed_by: None,
+            trusted: None,
         };
         
         Repository::add_to_db(env.index.conn(), &repo).unwrap();
@@ -42,8 +47,13 @@ async fn test_security_repository() {
         enabled: true,
         last_probe_ms: None,
         rtt_ms: None,
+        throughput_bps: None,
+        codename: None,
         suite: Some("stable-security".to_string()),
         components: vec!["main".to_string()],
+        architectures: Vec::new(),
+        signed_by: None,
+        trusted: None,
     };
     
     Repository::add_to_db(env.index.conn(), &security_repo).unwrap();
@@ -64,8 +74,13 @@ async fn test_repository_components() {
         enabled: true,
         last_probe_ms: None,
         rtt_ms: None,
+        throughput_bps: None,
+        codename: None,
         suite: Some("stable".to_string()),
         components: vec!["main".to_string(), "contrib".to_string(), "non-free".to_string()],
+        architectures: Vec::new(),
+        signed_by: None,
+        trusted: None,
     };
     
     Repository::add_to_db(env.index.conn(), &repo).unwrap();
@@ -91,8 +106,13 @@ async fn test_repository_priority() {
             enabled: true,
             last_probe_ms: None,
             rtt_ms: None,
+            throughput_bps: None,
+            codename: None,
             suite: None,
             components: vec!["main".to_string()],
+            architectures: Vec::new(),
+            signed_by: None,
+            trusted: None,
         },
         Repository {
             id: None,
@@ -101,8 +121,13 @@ async fn test_repository_priority() {
             enabled: true,
             last_probe_ms: None,
             rtt_ms: None,
+            throughput_bps: None,
+            codename: None,
             suite: None,
             components: vec!["main".to_string()],
+            architectures: Vec::new(),
+            signed_by: None,
+            trusted: None,
         },
     ];
     
@@ -130,8 +155,13 @@ async fn test_repository_suite_variations() {
             enabled: true,
             last_probe_ms: None,
             rtt_ms: None,
+            throughput_bps: None,
+            codename: None,
             suite: Some(suite.to_string()),
             components: vec!["main".to_string()],
+            architectures: Vec::new(),
+            signed_by: None,
+            trusted: None,
         };
         
         Repository::add_to_db(env.index.conn(), &repo).unwrap();
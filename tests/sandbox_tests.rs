@@ -0,0 +1,60 @@
+use apt_ng::installer::{HookType, Installer};
+use apt_ng::sandbox::{Sandbox, SandboxConfig};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Baut ein minimales .deb-Paket mit dem angegebenen postinst-Skript
+fn build_deb_with_postinst(dir: &Path, postinst: &str) -> std::path::PathBuf {
+    let pkg_dir = dir.join("netcheck");
+    let debian_dir = pkg_dir.join("DEBIAN");
+    fs::create_dir_all(&debian_dir).unwrap();
+
+    fs::write(
+        debian_dir.join("control"),
+        "Package: netcheck\nVersion: 1.0\nArchitecture: all\nMaintainer: Test <test@example.com>\nDescription: test\n",
+    ).unwrap();
+
+    let postinst_path = debian_dir.join("postinst");
+    fs::write(&postinst_path, postinst).unwrap();
+    fs::set_permissions(&postinst_path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let deb_path = dir.join("netcheck_1.0_all.deb");
+    let status = Command::new("dpkg-deb")
+        .arg("--build")
+        .arg(&pkg_dir)
+        .arg(&deb_path)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success());
+
+    deb_path
+}
+
+#[tokio::test]
+async fn test_network_postinst_fails_in_default_sandbox() {
+    if !Sandbox::check_bubblewrap_available() || Command::new("dpkg-deb").arg("--version").output().is_err() {
+        eprintln!("Skipping: bwrap or dpkg-deb not available");
+        return;
+    }
+
+    let temp_dir = TempDir::new().unwrap();
+    // Das Skript benötigt eine Route nach außen; im Netzwerk-Namespace der Sandbox gibt es keine
+    let deb_path = build_deb_with_postinst(
+        temp_dir.path(),
+        "#!/bin/sh\n[ \"$(wc -l < /proc/net/route)\" -gt 1 ] || { echo 'network unreachable' >&2; exit 1; }\n",
+    );
+
+    let config = SandboxConfig::default();
+    assert!(!config.network_allowed);
+
+    let install_root = temp_dir.path().join("root");
+    fs::create_dir_all(&install_root).unwrap();
+    let installer = Installer::new_with_sandbox(1, &install_root, Some(config));
+
+    let result = installer.run_hook(HookType::PostInstall, &deb_path, false).await;
+    assert!(result.is_err(), "network-using postinst must fail in the default sandbox");
+}
@@ -0,0 +1,512 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// Führt apt-ng im Nutzermodus aus; alle XDG-Verzeichnisse liegen unter `home`
+fn apt_ng_user(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_apt-ng"))
+        .arg("--user")
+        .args(args)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_STATE_HOME", home.join("state"))
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_update_and_search_with_user_cache() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path().join("home");
+    let repo_dir = temp_dir.path().join("repo");
+    fs::create_dir_all(repo_dir.join("dists/stable/main/binary-all")).unwrap();
+    fs::write(repo_dir.join("dists/stable/Release"), "Suite: stable\nCodename: stable\n").unwrap();
+    fs::write(
+        repo_dir.join("dists/stable/main/binary-all/Packages"),
+        "Package: userland-hello\nVersion: 1.0\nArchitecture: all\nFilename: pool/main/u/userland-hello_1.0_all.deb\n\
+         Size: 3\nSHA256: 0000\nDescription: greeting for unprivileged users\n\n",
+    ).unwrap();
+    let repo_url = format!("file://{}", repo_dir.display());
+
+    let output = apt_ng_user(&home, &["repo", "add", &repo_url, "--trusted", "yes"]);
+    assert!(output.status.success(), "repo add failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // Die Konfiguration wurde mit den Nutzerpfaden angelegt; das lokale Repository ist unsigniert
+    let config_path = home.join("config/apt-ng/config.toml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    assert!(config.contains(&home.join("state/apt-ng").display().to_string()));
+    fs::write(&config_path, format!("{}\n[keyrings]\napt_keyrings = false\n", config)).unwrap();
+
+    let output = apt_ng_user(&home, &["update"]);
+    assert!(output.status.success(), "update failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = apt_ng_user(&home, &["search", "userland"]);
+    assert!(output.status.success(), "search failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("userland-hello"));
+
+    // Index, Sperre und Cache liegen ausschließlich in den XDG-Verzeichnissen
+    assert!(home.join("state/apt-ng/index.db").exists());
+    assert!(home.join("cache/apt-ng").is_dir());
+    assert!(home.join("data/apt-ng/root").is_dir());
+}
+
+/// Packt die Dateien als tar.gz, wie es die Member einer .deb erwarten
+fn tar_gz(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (name, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, name, *data).unwrap();
+    }
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(&builder.into_inner().unwrap()).unwrap();
+    gz.finish().unwrap()
+}
+
+/// Schreibt eine minimale .deb (Control-Datei und leeres Datenarchiv)
+fn write_deb(path: &Path, control: &str) {
+    write_deb_with_data(path, control, tar_gz(&[]));
+}
+
+/// Wie `write_deb`, mit beliebigem Inhalt als data.tar.gz
+fn write_deb_with_data(path: &Path, control: &str, data: Vec<u8>) {
+    let members = [
+        ("debian-binary", b"2.0\n".to_vec()),
+        ("control.tar.gz", tar_gz(&[("./control", control.as_bytes())])),
+        ("data.tar.gz", data),
+    ];
+
+    let mut deb = b"!<arch>\n".to_vec();
+    for (member, data) in members {
+        writeln!(deb, "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`", format!("{}/", member), 0, 0, 0, 100644, data.len()).unwrap();
+        deb.extend_from_slice(&data);
+        if data.len() % 2 == 1 {
+            deb.push(b'\n');
+        }
+    }
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, deb).unwrap();
+}
+
+/// Erzeugt ein signiertes lokales Repository mit einem Paket und trägt es mit passendem
+/// Schlüssel im Nutzermodus ein; liefert das Repository-Verzeichnis
+fn add_signed_repository(home: &Path, root: &Path) -> std::path::PathBuf {
+    let repo_dir = root.join("repo");
+    write_deb(
+        &repo_dir.join("pool/main/s/signed-hello/signed-hello_1.0_all.deb"),
+        "Package: signed-hello\nVersion: 1.0\nArchitecture: all\nDescription: greeting from a signed repository\n",
+    );
+
+    // Schlüsselpaar wie von `apt-ng-build generate-key`: roher geheimer Schlüssel und .pub
+    let signing_key = ed25519_dalek::SigningKey::from_bytes(&[42; 32]);
+    let key_path = root.join("repo.key");
+    fs::write(&key_path, signing_key.to_bytes()).unwrap();
+    let trusted_dir = home.join("config/apt-ng/trusted.gpg.d");
+    fs::create_dir_all(&trusted_dir).unwrap();
+    fs::write(trusted_dir.join("repo.pub"), signing_key.verifying_key().to_bytes()).unwrap();
+
+    let output = apt_ng_user(home, &["repo", "generate", &repo_dir.display().to_string(), "--key", &key_path.display().to_string()]);
+    assert!(output.status.success(), "repo generate failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(repo_dir.join("dists/stable/InRelease").exists());
+    assert!(repo_dir.join("dists/stable/Release.gpg").exists());
+
+    let repo_url = format!("file://{}", repo_dir.display());
+    let output = apt_ng_user(home, &["repo", "add", &repo_url]);
+    assert!(output.status.success(), "repo add failed: {}", String::from_utf8_lossy(&output.stderr));
+    let config_path = home.join("config/apt-ng/config.toml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(&config_path, format!("{}\n[keyrings]\napt_keyrings = false\n", config)).unwrap();
+    repo_dir
+}
+
+#[test]
+fn test_update_verifies_generated_signed_repository() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path().join("home");
+    add_signed_repository(&home, temp_dir.path());
+
+    // Mit dem passenden Schlüssel ist die Signaturprüfung aktiv und erfolgreich
+    let output = apt_ng_user(&home, &["-v", "update"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "update failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("Repository signature verified"), "{}", stdout);
+
+    let output = apt_ng_user(&home, &["search", "signed-hello"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("signed-hello"));
+}
+
+#[test]
+fn test_trusted_key_does_not_change_policy_of_unsigned_repositories() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path().join("home");
+    let repo_dir = temp_dir.path().join("repo");
+    write_deb(
+        &repo_dir.join("pool/main/plain-hello_1.0_all.deb"),
+        "Package: plain-hello\nVersion: 1.0\nArchitecture: all\nDescription: unsigned test package\n",
+    );
+    let output = apt_ng_user(&home, &["repo", "generate", &repo_dir.display().to_string()]);
+    assert!(output.status.success(), "repo generate failed: {}", String::from_utf8_lossy(&output.stderr));
+    let output = apt_ng_user(&home, &["repo", "add", &format!("file://{}", repo_dir.display())]);
+    assert!(output.status.success(), "repo add failed: {}", String::from_utf8_lossy(&output.stderr));
+    let config_path = home.join("config/apt-ng/config.toml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(&config_path, format!("{}\n[keyrings]\napt_keyrings = false\n", config)).unwrap();
+    let trusted_dir = home.join("config/apt-ng/trusted.gpg.d");
+    let add_trusted_key = || {
+        fs::create_dir_all(&trusted_dir).unwrap();
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        fs::write(trusted_dir.join("other.pub"), key.verifying_key().to_bytes()).unwrap();
+    };
+    // Abgelehnte Repositories werden übersprungen; ob es übernommen wurde, zeigt die Suche
+    // in einem jeweils neu aufgebauten Index
+    let update_accepts_repo = || {
+        let output = apt_ng_user(&home, &["--rebuild-index", "update"]);
+        assert!(output.status.success(), "update failed: {}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&apt_ng_user(&home, &["search", "plain"]).stdout).contains("plain-hello")
+    };
+
+    // Ohne signed-by/trusted gilt die feste Vorgabe: Signaturen sind nötig, mit oder ohne Schlüssel
+    assert!(!update_accepts_repo());
+    add_trusted_key();
+    assert!(!update_accepts_repo());
+
+    // Mit require_signatures = false bleibt das Repository auch nach einem neuen Schlüssel ungeprüft
+    fs::remove_dir_all(&trusted_dir).unwrap();
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(&config_path, format!("require_signatures = false\n{}", config)).unwrap();
+    assert!(update_accepts_repo());
+    add_trusted_key();
+    assert!(update_accepts_repo());
+}
+
+#[test]
+fn test_forged_release_date_is_not_stored() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path().join("home");
+    let repo_dir = add_signed_repository(&home, temp_dir.path());
+    let output = apt_ng_user(&home, &["update"]);
+    assert!(output.status.success(), "update failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // Gefälschte Release-Datei aus der Zukunft mit ungültiger Signatur
+    let dists = repo_dir.join("dists/stable");
+    let inrelease = fs::read_to_string(dists.join("InRelease")).unwrap();
+    let date_line = inrelease.lines().find(|line| line.starts_with("Date: ")).unwrap().to_string();
+    fs::write(dists.join("InRelease"), inrelease.replace(&date_line, "Date: Fri, 01 Jan 2100 00:00:00 UTC")).unwrap();
+    let release_gpg = fs::read(dists.join("Release.gpg")).unwrap();
+    fs::remove_file(dists.join("Release.gpg")).unwrap();
+
+    let output = apt_ng_user(&home, &["update"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("no valid signature"), "{}", String::from_utf8_lossy(&output.stdout));
+
+    // Das echte Release wird danach nicht als Rollback abgelehnt
+    fs::write(dists.join("InRelease"), inrelease).unwrap();
+    fs::write(dists.join("Release.gpg"), release_gpg).unwrap();
+    let output = apt_ng_user(&home, &["update"]);
+    assert!(output.status.success(), "update after forged Release failed: {}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn test_offline_install_uses_cached_packages() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path().join("home");
+    let repo_dir = temp_dir.path().join("repo");
+    for name in ["cached-hello", "remote-hello"] {
+        write_deb(
+            &repo_dir.join(format!("pool/main/{}_1.0_all.deb", name)),
+            &format!("Package: {}\nVersion: 1.0\nArchitecture: all\nDescription: offline test package\n", name),
+        );
+    }
+    let output = apt_ng_user(&home, &["repo", "generate", &repo_dir.display().to_string()]);
+    assert!(output.status.success(), "repo generate failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = apt_ng_user(&home, &["repo", "add", &format!("file://{}", repo_dir.display()), "--trusted", "yes"]);
+    assert!(output.status.success(), "repo add failed: {}", String::from_utf8_lossy(&output.stderr));
+    let config_path = home.join("config/apt-ng/config.toml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(&config_path, format!("{}\n[keyrings]\napt_keyrings = false\n", config)).unwrap();
+
+    let output = apt_ng_user(&home, &["update"]);
+    assert!(output.status.success(), "update failed: {}", String::from_utf8_lossy(&output.stderr));
+    let output = apt_ng_user(&home, &["install", "--download-only", "cached-hello"]);
+    assert!(output.status.success(), "download failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // Ohne Repository bleibt nur der Cache
+    fs::remove_dir_all(&repo_dir).unwrap();
+
+    let output = apt_ng_user(&home, &["--offline", "update"]);
+    assert_eq!(output.status.code(), Some(apt_ng::error::exit_code::NETWORK));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("offline mode"));
+
+    let output = apt_ng_user(&home, &["--offline", "self-update"]);
+    assert_eq!(output.status.code(), Some(apt_ng::error::exit_code::NETWORK));
+
+    let output = apt_ng_user(&home, &["--offline", "install", "remote-hello"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Not cached (offline mode): remote-hello (1.0)"));
+
+    let output = apt_ng_user(&home, &["--offline", "--no-sandbox", "install", "cached-hello"]);
+    assert!(output.status.success(), "offline install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let output = apt_ng_user(&home, &["files", "cached-hello"]);
+    assert!(output.status.success(), "cached-hello not installed: {}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn test_full_upgrade_removes_packages_after_installing() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path().join("home");
+    let repo_dir = temp_dir.path().join("repo");
+    let publish = |packages: &[(&str, &str, &str)]| {
+        let _ = fs::remove_dir_all(&repo_dir);
+        for (name, version, relations) in packages {
+            write_deb(
+                &repo_dir.join(format!("pool/main/{}_{}_all.deb", name, version)),
+                &format!("Package: {}\nVersion: {}\nArchitecture: all\n{}Description: upgrade test package\n", name, version, relations),
+            );
+        }
+        let output = apt_ng_user(&home, &["repo", "generate", &repo_dir.display().to_string()]);
+        assert!(output.status.success(), "repo generate failed: {}", String::from_utf8_lossy(&output.stderr));
+        let output = apt_ng_user(&home, &["update"]);
+        assert!(output.status.success(), "update failed: {}", String::from_utf8_lossy(&output.stderr));
+    };
+    let installed = |name: &str| apt_ng_user(&home, &["files", name]).status.success();
+
+    fs::create_dir_all(&repo_dir).unwrap();
+    let output = apt_ng_user(&home, &["repo", "add", &format!("file://{}", repo_dir.display()), "--trusted", "yes"]);
+    assert!(output.status.success(), "repo add failed: {}", String::from_utf8_lossy(&output.stderr));
+    let config_path = home.join("config/apt-ng/config.toml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(&config_path, format!("{}\n[keyrings]\napt_keyrings = false\n", config)).unwrap();
+
+    publish(&[("app", "1.0", "Depends: libfoo1\n"), ("libfoo1", "1.0", ""), ("libold", "1.0", "")]);
+    let output = apt_ng_user(&home, &["--no-sandbox", "install", "app", "libold"]);
+    assert!(output.status.success(), "install failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // libfoo2 kollidiert mit libfoo1 und ersetzt das upstream verschwundene libold
+    publish(&[("app", "2.0", "Depends: libfoo2\n"), ("libfoo2", "2.0", "Conflicts: libfoo1\nReplaces: libold\n")]);
+
+    let output = apt_ng_user(&home, &["full-upgrade", "--download-only"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "download failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("run `apt-ng full-upgrade` to install them"), "{}", stdout);
+    assert!(installed("libfoo1") && installed("libold"));
+
+    // Scheitert die Installation, bleibt auch alles installiert, was entfernt werden sollte
+    let app_deb = repo_dir.join("pool/main/app_2.0_all.deb");
+    let good_deb = fs::read(&app_deb).unwrap();
+    fs::remove_dir_all(home.join("cache/apt-ng")).unwrap();
+    fs::write(&app_deb, b"corrupt").unwrap();
+    let output = apt_ng_user(&home, &["--no-sandbox", "full-upgrade", "--yes"]);
+    assert!(!output.status.success());
+    assert!(installed("libfoo1") && installed("libold"));
+
+    fs::write(&app_deb, good_deb).unwrap();
+    let output = apt_ng_user(&home, &["--no-sandbox", "full-upgrade", "--yes"]);
+    assert!(output.status.success(), "full-upgrade failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(installed("app") && installed("libfoo2"));
+    assert!(!installed("libfoo1") && !installed("libold"));
+
+    // Installationen und Entfernungen landen in einem einzigen Verlaufseintrag
+    let output = apt_ng_user(&home, &["history", "list", "-n", "1"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("full-upgrade --yes"), "{}", stdout);
+    for changes in ["1 upgrade", "1 install", "2 remove"] {
+        assert!(stdout.contains(changes), "{} missing: {}", changes, stdout);
+    }
+}
+
+#[test]
+fn test_failed_install_rolls_back_the_whole_batch() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path().join("home");
+    let repo_dir = temp_dir.path().join("repo");
+    for name in ["first", "second"] {
+        write_deb_with_data(
+            &repo_dir.join(format!("pool/main/{}_1.0_all.deb", name)),
+            &format!("Package: {}\nVersion: 1.0\nArchitecture: all\nDescription: batch test package\n", name),
+            tar_gz(&[(&format!("./usr/share/{}/data", name), b"data")]),
+        );
+    }
+    // Das letzte Paket des Stapels hat ein unlesbares Datenarchiv
+    write_deb_with_data(
+        &repo_dir.join("pool/main/broken_1.0_all.deb"),
+        "Package: broken\nVersion: 1.0\nArchitecture: all\nDepends: first, second\nDescription: batch test package\n",
+        b"corrupt".to_vec(),
+    );
+    let output = apt_ng_user(&home, &["repo", "generate", &repo_dir.display().to_string()]);
+    assert!(output.status.success(), "repo generate failed: {}", String::from_utf8_lossy(&output.stderr));
+    let output = apt_ng_user(&home, &["repo", "add", &format!("file://{}", repo_dir.display()), "--trusted", "yes"]);
+    assert!(output.status.success(), "repo add failed: {}", String::from_utf8_lossy(&output.stderr));
+    let config_path = home.join("config/apt-ng/config.toml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(&config_path, format!("{}\n[keyrings]\napt_keyrings = false\n", config)).unwrap();
+    let output = apt_ng_user(&home, &["update"]);
+    assert!(output.status.success(), "update failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = apt_ng_user(&home, &["--no-sandbox", "install", "broken"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Rolling back 2"), "{}", String::from_utf8_lossy(&output.stdout));
+
+    // Die bereits installierten Pakete sind samt Dateien und Journal wieder verschwunden
+    for name in ["first", "second"] {
+        assert!(!apt_ng_user(&home, &["files", name]).status.success(), "{} still installed", name);
+        assert!(!home.join(format!("data/apt-ng/root/usr/share/{}/data", name)).exists());
+    }
+    assert!(!home.join("state/apt-ng/transaction.journal").exists());
+}
+
+#[test]
+fn test_lockfile_records_installed_dependencies() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path().join("home");
+    let repo_dir = temp_dir.path().join("repo");
+    write_deb(&repo_dir.join("pool/main/lib_1.0_all.deb"), "Package: lib\nVersion: 1.0\nArchitecture: all\nDescription: lock test library\n");
+    write_deb(&repo_dir.join("pool/main/app_1.0_all.deb"), "Package: app\nVersion: 1.0\nArchitecture: all\nDepends: lib\nDescription: lock test app\n");
+    let output = apt_ng_user(&home, &["repo", "generate", &repo_dir.display().to_string()]);
+    assert!(output.status.success(), "repo generate failed: {}", String::from_utf8_lossy(&output.stderr));
+    let output = apt_ng_user(&home, &["repo", "add", &format!("file://{}", repo_dir.display()), "--trusted", "yes"]);
+    assert!(output.status.success(), "repo add failed: {}", String::from_utf8_lossy(&output.stderr));
+    let config_path = home.join("config/apt-ng/config.toml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(&config_path, format!("{}\n[keyrings]\napt_keyrings = false\n", config)).unwrap();
+    let output = apt_ng_user(&home, &["update"]);
+    assert!(output.status.success(), "update failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // lib ist schon installiert und gehört trotzdem ins Lockfile von app
+    let output = apt_ng_user(&home, &["--no-sandbox", "install", "lib"]);
+    assert!(output.status.success(), "install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let lock = temp_dir.path().join("app.lock");
+    let output = apt_ng_user(&home, &["--dry-run", "install", "app", "--write-lock", &lock.display().to_string()]);
+    assert!(output.status.success(), "install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let names = |lock: &Path| {
+        let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string(lock).unwrap()).unwrap();
+        lockfile["packages"].as_array().unwrap().iter().map(|pkg| pkg["name"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(names(&lock), ["lib", "app"]);
+
+    // Auch wenn nichts mehr zu tun ist, wird das Lockfile geschrieben
+    let output = apt_ng_user(&home, &["--no-sandbox", "install", "app"]);
+    assert!(output.status.success(), "install failed: {}", String::from_utf8_lossy(&output.stderr));
+    fs::remove_file(&lock).unwrap();
+    let output = apt_ng_user(&home, &["install", "app", "--write-lock", &lock.display().to_string()]);
+    assert!(output.status.success(), "install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("already installed"));
+    assert_eq!(names(&lock), ["lib", "app"]);
+}
+
+#[test]
+fn test_upgrade_uses_published_delta_and_records_savings() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path().join("home");
+    let repo_dir = temp_dir.path().join("repo");
+
+    // Nicht komprimierbarer Inhalt, der sich zwischen den Versionen nur am Ende ändert
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut payload: Vec<u8> = (0..256 * 1024).map(|_| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state as u8
+    }).collect();
+    let old_deb = temp_dir.path().join("app_1.0_all.deb");
+    let new_deb = temp_dir.path().join("app_2.0_all.deb");
+    write_deb_with_data(&old_deb, "Package: app\nVersion: 1.0\nArchitecture: all\nDescription: delta test package\n",
+        tar_gz(&[("./usr/share/app/data", &payload)]));
+    payload.truncate(payload.len() - 64);
+    payload.extend_from_slice(&[b'x'; 64]);
+    write_deb_with_data(&new_deb, "Package: app\nVersion: 2.0\nArchitecture: all\nDescription: delta test package\n",
+        tar_gz(&[("./usr/share/app/data", &payload)]));
+
+    let publish = |deb: &Path| {
+        let _ = fs::remove_dir_all(repo_dir.join("pool"));
+        fs::create_dir_all(repo_dir.join("pool/main")).unwrap();
+        fs::copy(deb, repo_dir.join("pool/main").join(deb.file_name().unwrap())).unwrap();
+        let output = apt_ng_user(&home, &["repo", "generate", &repo_dir.display().to_string()]);
+        assert!(output.status.success(), "repo generate failed: {}", String::from_utf8_lossy(&output.stderr));
+        let output = apt_ng_user(&home, &["update"]);
+        assert!(output.status.success(), "update failed: {}", String::from_utf8_lossy(&output.stderr));
+    };
+
+    fs::create_dir_all(&repo_dir).unwrap();
+    let output = apt_ng_user(&home, &["repo", "add", &format!("file://{}", repo_dir.display()), "--trusted", "yes"]);
+    assert!(output.status.success(), "repo add failed: {}", String::from_utf8_lossy(&output.stderr));
+    let config_path = home.join("config/apt-ng/config.toml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(&config_path, format!("{}\n[keyrings]\napt_keyrings = false\n", config)).unwrap();
+
+    publish(&old_deb);
+    let output = apt_ng_user(&home, &["--no-sandbox", "install", "app"]);
+    assert!(output.status.success(), "install failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // Das Repository bietet 2.0 als Delta an; das volle Paket verschwindet nach dem Update,
+    // sodass nur der Delta-Weg zum Ziel führt
+    let (delta, metadata) = apt_ng::delta::DeltaCalculator::calculate_delta(&old_deb, &new_deb, "xdelta3").unwrap();
+    assert!(metadata.is_worthwhile(), "{:?}", metadata);
+    fs::create_dir_all(repo_dir.join("deltas")).unwrap();
+    let delta_path = repo_dir.join("deltas/app_1.0_2.0_all.delta");
+    fs::write(&delta_path, delta).unwrap();
+    fs::write(delta_path.with_extension("delta.json"), serde_json::to_string(&metadata).unwrap()).unwrap();
+    publish(&new_deb);
+    fs::remove_file(repo_dir.join("pool/main/app_2.0_all.deb")).unwrap();
+
+    let output = apt_ng_user(&home, &["--no-sandbox", "upgrade"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "upgrade failed: {}\n{}", String::from_utf8_lossy(&output.stderr), stdout);
+    assert!(stdout.contains("via delta updates (1 package(s))"), "{}", stdout);
+    assert_eq!(fs::read(home.join("data/apt-ng/root/usr/share/app/data")).unwrap(), payload);
+
+    let output = apt_ng_user(&home, &["stats"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("saved across 1 package(s)"), "{}", stdout);
+}
+
+#[test]
+fn test_download_without_resolve_deps_skips_the_solver() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path().join("home");
+    let repo_dir = temp_dir.path().join("repo");
+    // Die Abhängigkeit fehlt im Repository: nur der Solver würde daran scheitern
+    write_deb(
+        &repo_dir.join("pool/main/app_1.0_all.deb"),
+        "Package: app\nVersion: 1.0\nArchitecture: all\nDepends: missing-lib\nDescription: download test package\n",
+    );
+    let output = apt_ng_user(&home, &["repo", "generate", &repo_dir.display().to_string()]);
+    assert!(output.status.success(), "repo generate failed: {}", String::from_utf8_lossy(&output.stderr));
+    let output = apt_ng_user(&home, &["repo", "add", &format!("file://{}", repo_dir.display()), "--trusted", "yes"]);
+    assert!(output.status.success(), "repo add failed: {}", String::from_utf8_lossy(&output.stderr));
+    let config_path = home.join("config/apt-ng/config.toml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(&config_path, format!("{}\n[keyrings]\napt_keyrings = false\n", config)).unwrap();
+    let output = apt_ng_user(&home, &["update"]);
+    assert!(output.status.success(), "update failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let target = temp_dir.path().join("debs");
+    fs::create_dir_all(&target).unwrap();
+    let download = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_apt-ng"))
+            .arg("--user")
+            .args(args)
+            .current_dir(&target)
+            .env("HOME", &home)
+            .env("XDG_CONFIG_HOME", home.join("config"))
+            .env("XDG_STATE_HOME", home.join("state"))
+            .env("XDG_CACHE_HOME", home.join("cache"))
+            .env("XDG_DATA_HOME", home.join("data"))
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap()
+    };
+
+    let output = download(&["download", "app"]);
+    assert!(output.status.success(), "download failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(target.join("app_1.0_all.deb").exists());
+
+    let output = download(&["download", "app", "--resolve-deps"]);
+    assert!(!output.status.success());
+}